@@ -0,0 +1,1322 @@
+use crate::config::{get_cache_format, get_thumbnail_thread_count, CacheFormat};
+use crate::error::Result;
+use crate::image_processor::{
+    build_thread_pool, file_stamp, generate_thumbnail, load_source_image, preview_path_for,
+    thumbnail_path_for, ExifInfo, ImageInfo,
+};
+use crate::tasks::TaskHandle;
+use image::imageops::FilterType;
+use image::{GrayImage, RgbImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+use rustface::{Detector, ImageData, Model};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Pixel value at or above which a pixel counts as blown out
+const HIGHLIGHT_LEVEL: u8 = 250;
+/// Pixel value at or below which a pixel counts as crushed to black
+const SHADOW_LEVEL: u8 = 5;
+
+/// Frames softer than this fraction of the session's median sharpness start scoring as blurry
+const BLUR_RATIO: f64 = 0.5;
+/// Share of blown-out pixels that is still fine / that is certainly a reject
+const HIGHLIGHT_CLIPPING_RANGE: (f64, f64) = (0.01, 0.10);
+/// Share of black pixels that is still fine / that is certainly a reject
+/// Stage shots have large black backgrounds, so only nearly black frames count
+const SHADOW_CLIPPING_RANGE: (f64, f64) = (0.60, 0.95);
+/// Maximum dHash distance between two frames considered near duplicates
+pub const DUPLICATE_DISTANCE: u32 = 6;
+/// Score given to the softer frame of a near-duplicate pair
+const DUPLICATE_SCORE: f64 = 0.6;
+/// Score given to a frame whose subject turned away (faces in a near duplicate, none in it)
+const FACE_LOST_SCORE: f64 = 0.6;
+/// How much less eye contrast than in a near duplicate is still fine / is certainly a blink
+const EYES_CLOSED_RANGE: (f64, f64) = (0.3, 0.7);
+/// Band of a face box (top and bottom, as fractions of its height) the eyes sit in
+const EYE_BAND: (f64, f64) = (0.2, 0.5);
+
+/// Edge contrast (Sobel magnitude) below which nothing is marked by focus peaking, so
+/// out-of-focus frames show no peaking at all
+const PEAKING_MIN_CONTRAST: f32 = 60.0;
+/// Share of a frame's pixels that focus peaking marks at most
+const PEAKING_SHARE: f32 = 0.05;
+const PEAKING_COLOR: Rgba<u8> = Rgba([255, 32, 32, 255]);
+/// Exposure warning colors for blown-out and crushed pixels
+const HIGHLIGHT_WARNING_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const SHADOW_WARNING_COLOR: Rgba<u8> = Rgba([0, 96, 255, 255]);
+
+/// Default `suggest_rejections` threshold
+pub const DEFAULT_REJECT_THRESHOLD: f64 = 0.6;
+
+/// SeetaFace frontal face model (BSD 2-Clause, shipped with the rustface crate)
+const FACE_MODEL: &[u8] = include_bytes!("../models/seeta_fd_frontal_v1.0.bin");
+/// Longest edge images are scaled to for face detection
+const FACE_DETECTION_EDGE: u32 = 1024;
+/// Smallest face searched for, in pixels of the detection image
+const MIN_FACE_SIZE: u32 = 20;
+/// Detector confidence needed to report a face
+const FACE_SCORE_THRESHOLD: f64 = 2.0;
+
+static FACE_MODEL_CACHE: OnceLock<Option<Model>> = OnceLock::new();
+
+thread_local! {
+    // Detectors keep scratch buffers and aren't Sync, so each worker thread gets its own
+    static FACE_DETECTOR: RefCell<Option<Box<dyn Detector>>> = const { RefCell::new(None) };
+}
+
+/// Image metrics used by the culling assistant, computed from the cached thumbnail
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ImageAnalysis {
+    pub filename: String,
+    /// Variance of the Laplacian over the whole frame (higher is sharper)
+    pub sharpness: f64,
+    /// Variance of the Laplacian over the sharpest face, or the center of the frame without faces
+    pub subject_sharpness: f64,
+    /// Share of blown-out pixels (0.0 - 1.0)
+    pub highlight_clipping: f64,
+    /// Share of black pixels (0.0 - 1.0)
+    pub shadow_clipping: f64,
+    /// 64-bit difference hash for similarity checks
+    pub dhash: u64,
+    /// Detected faces
+    pub faces: Vec<FaceBox>,
+    /// Edge contrast in the eye band of the largest face relative to the whole face, which
+    /// drops when the eyes close; None without faces
+    pub eye_contrast: Option<f64>,
+}
+
+/// Perceptual hash of a frame, all that similarity queries need of its analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageHash {
+    pub filename: String,
+    pub dhash: u64,
+}
+
+impl From<&ImageAnalysis> for ImageHash {
+    fn from(analysis: &ImageAnalysis) -> Self {
+        Self {
+            filename: analysis.filename.clone(),
+            dhash: analysis.dhash,
+        }
+    }
+}
+
+/// Face bounding box, normalized to 0.0 - 1.0 of the image width/height
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FaceBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Detector confidence
+    pub score: f64,
+}
+
+/// Why a frame is proposed as a reject; each reason carries its own 0.0 - 1.0 score
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RejectReason {
+    Blurry {
+        score: f64,
+    },
+    SubjectSoft {
+        score: f64,
+    },
+    Overexposed {
+        score: f64,
+    },
+    Underexposed {
+        score: f64,
+    },
+    Duplicate {
+        score: f64,
+        of: String,
+    },
+    /// A near duplicate shows faces this frame doesn't (subject turned or looked away)
+    FaceLost {
+        score: f64,
+        of: String,
+    },
+    /// Much less eye contrast than in a near duplicate, as when the subject blinked
+    EyesClosed {
+        score: f64,
+        of: String,
+    },
+}
+
+impl RejectReason {
+    pub fn score(&self) -> f64 {
+        match self {
+            RejectReason::Blurry { score }
+            | RejectReason::SubjectSoft { score }
+            | RejectReason::Overexposed { score }
+            | RejectReason::Underexposed { score }
+            | RejectReason::Duplicate { score, .. }
+            | RejectReason::FaceLost { score, .. }
+            | RejectReason::EyesClosed { score, .. } => *score,
+        }
+    }
+}
+
+/// A proposed reject; never applied without the user's confirmation
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RejectionSuggestion {
+    pub filename: String,
+    pub score: f64,
+    pub reasons: Vec<RejectReason>,
+}
+
+/// Compute the metrics of one image from its thumbnail
+/// Faces are searched in `face_image_path`, which may be a larger rendition (e.g. the preview)
+pub fn analyze_image(
+    filename: &str,
+    thumbnail_path: &Path,
+    face_image_path: &Path,
+) -> Result<ImageAnalysis> {
+    let gray = image::open(thumbnail_path)?.to_luma8();
+    if face_image_path == thumbnail_path {
+        let faces = detect_faces(&gray);
+        return Ok(analyze_gray(filename, &gray, &gray, faces));
+    }
+    let face_gray = image::open(face_image_path)?.to_luma8();
+    let faces = detect_faces(&face_gray);
+    Ok(analyze_gray(filename, &gray, &face_gray, faces))
+}
+
+/// Find faces in a grayscale image
+/// Returns no faces if the embedded model can't be read
+pub fn detect_faces(gray: &GrayImage) -> Vec<FaceBox> {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    // Large images are scaled down: detection time grows with the pixel count
+    let scaled;
+    let gray = if width.max(height) > FACE_DETECTION_EDGE {
+        scaled = image::imageops::resize(
+            gray,
+            width * FACE_DETECTION_EDGE / width.max(height),
+            height * FACE_DETECTION_EDGE / width.max(height),
+            FilterType::Triangle,
+        );
+        &scaled
+    } else {
+        gray
+    };
+    let (width, height) = gray.dimensions();
+
+    FACE_DETECTOR.with(|cell| {
+        let mut detector = cell.borrow_mut();
+        if detector.is_none() {
+            *detector = create_face_detector();
+        }
+        let Some(detector) = detector.as_mut() else {
+            return Vec::new();
+        };
+
+        detector
+            .detect(&ImageData::new(gray.as_raw(), width, height))
+            .into_iter()
+            .map(|face| {
+                let bbox = face.bbox();
+                let x = bbox.x().max(0) as f64;
+                let y = bbox.y().max(0) as f64;
+                FaceBox {
+                    x: x / width as f64,
+                    y: y / height as f64,
+                    width: (bbox.width() as f64).min(width as f64 - x) / width as f64,
+                    height: (bbox.height() as f64).min(height as f64 - y) / height as f64,
+                    score: face.score(),
+                }
+            })
+            .collect()
+    })
+}
+
+fn create_face_detector() -> Option<Box<dyn Detector>> {
+    let model = FACE_MODEL_CACHE
+        .get_or_init(|| match rustface::read_model(FACE_MODEL) {
+            Ok(model) => Some(model),
+            Err(e) => {
+                tracing::error!("Failed to load face detection model: {}", e);
+                None
+            }
+        })
+        .clone()?;
+
+    let mut detector = rustface::create_detector_with_model(model);
+    detector.set_min_face_size(MIN_FACE_SIZE);
+    detector.set_score_thresh(FACE_SCORE_THRESHOLD);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+    Some(detector)
+}
+
+/// Analyze images in parallel from their cached thumbnails, generating missing thumbnails first
+/// Faces are searched in the cached preview where one exists, as thumbnails are too small
+/// for faces of distant subjects
+/// Returns `(file_stamp, analysis)` per analyzed image; failures are logged and skipped,
+/// and cancelling `task` returns what has been analyzed so far
+pub fn analyze_images(
+    images: &[ImageInfo],
+    cache_dir: &Path,
+    preview_dir: &Path,
+    thumbnail_size: u32,
+    task: &TaskHandle,
+) -> Vec<(String, ImageAnalysis)> {
+    let completed = AtomicUsize::new(0);
+    let pool = build_thread_pool(get_thumbnail_thread_count());
+
+    pool.install(|| {
+        images
+            .par_iter()
+            .filter_map(|image| {
+                if !task.checkpoint() {
+                    return None;
+                }
+
+                // Stamped before reading, so a file replaced meanwhile is analyzed again later
+                let stamp = file_stamp(image);
+                let result = if get_cache_format() == CacheFormat::Avif {
+                    analyze_source(image, thumbnail_size)
+                } else {
+                    analyze_cached(image, cache_dir, preview_dir, thumbnail_size)
+                };
+                task.set_progress(completed.fetch_add(1, Ordering::Relaxed) + 1);
+
+                match result {
+                    Ok(analysis) => Some((stamp, analysis)),
+                    Err(e) => {
+                        tracing::warn!("Failed to analyze {}: {}", image.path, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    })
+}
+
+/// Perceptual hash of an image from its cached thumbnail, generating a missing thumbnail first
+/// Hashed from the original file when the cache format can't be decoded here
+pub fn hash_image(image: &ImageInfo, cache_dir: &Path, thumbnail_size: u32) -> Result<u64> {
+    let gray = if get_cache_format() == CacheFormat::Avif {
+        load_source_image(Path::new(&image.path), thumbnail_size)?
+            .thumbnail(thumbnail_size, thumbnail_size)
+            .to_luma8()
+    } else {
+        let thumbnail_path = thumbnail_path_for(cache_dir, &image.filename);
+        if !thumbnail_path.exists() {
+            generate_thumbnail(Path::new(&image.path), &thumbnail_path, thumbnail_size)?;
+        }
+        image::open(&thumbnail_path)?.to_luma8()
+    };
+    Ok(dhash(&gray))
+}
+
+/// Analyze an image from its cached thumbnail and preview
+fn analyze_cached(
+    image: &ImageInfo,
+    cache_dir: &Path,
+    preview_dir: &Path,
+    thumbnail_size: u32,
+) -> Result<ImageAnalysis> {
+    let thumbnail_path = thumbnail_path_for(cache_dir, &image.filename);
+    let preview_path = preview_path_for(preview_dir, &image.filename);
+    let face_image_path = if preview_path.exists() {
+        &preview_path
+    } else {
+        &thumbnail_path
+    };
+    if !thumbnail_path.exists() {
+        generate_thumbnail(Path::new(&image.path), &thumbnail_path, thumbnail_size)?;
+    }
+    analyze_image(&image.filename, &thumbnail_path, face_image_path)
+}
+
+/// Analyze an image from the original file
+/// Used when the cache format can't be decoded here (AVIF is encode-only in this build)
+fn analyze_source(image: &ImageInfo, thumbnail_size: u32) -> Result<ImageAnalysis> {
+    let img = load_source_image(Path::new(&image.path), FACE_DETECTION_EDGE)?;
+    let face_gray = img
+        .thumbnail(FACE_DETECTION_EDGE, FACE_DETECTION_EDGE)
+        .to_luma8();
+    let faces = detect_faces(&face_gray);
+    let gray = img.thumbnail(thumbnail_size, thumbnail_size).to_luma8();
+    Ok(analyze_gray(&image.filename, &gray, &face_gray, faces))
+}
+
+/// Metrics of a thumbnail `gray`, with `faces` found in `face_gray` (the same image or a
+/// larger rendition of it)
+fn analyze_gray(
+    filename: &str,
+    gray: &GrayImage,
+    face_gray: &GrayImage,
+    faces: Vec<FaceBox>,
+) -> ImageAnalysis {
+    let (width, height) = gray.dimensions();
+    let (highlight_clipping, shadow_clipping) = clipping(gray);
+
+    ImageAnalysis {
+        filename: filename.to_string(),
+        sharpness: laplacian_variance(gray, 0, 0, width, height),
+        subject_sharpness: subject_sharpness(gray, &faces),
+        highlight_clipping,
+        shadow_clipping,
+        dhash: dhash(gray),
+        eye_contrast: eye_contrast(face_gray, &faces),
+        faces,
+    }
+}
+
+/// Laplacian variance of the eye band of the largest face over that of the whole face
+/// Open eyes (pupils, whites, lashes) are the busiest part of a face; closed lids are smooth
+fn eye_contrast(gray: &GrayImage, faces: &[FaceBox]) -> Option<f64> {
+    let face = faces
+        .iter()
+        .max_by(|a, b| (a.width * a.height).total_cmp(&(b.width * b.height)))?;
+    let (width, height) = gray.dimensions();
+    let x = (face.x * width as f64).round() as u32;
+    let y = (face.y * height as f64).round() as u32;
+    let face_width = (face.width * width as f64).round() as u32;
+    let face_height = (face.height * height as f64).round() as u32;
+
+    let whole = laplacian_variance(gray, x, y, face_width, face_height);
+    if whole <= 0.0 {
+        return None;
+    }
+    let band_top = y + (face_height as f64 * EYE_BAND.0) as u32;
+    let band_height = (face_height as f64 * (EYE_BAND.1 - EYE_BAND.0)) as u32;
+    Some(laplacian_variance(gray, x, band_top, face_width, band_height) / whole)
+}
+
+/// Sharpness of the subject: the sharpest face if there are any, otherwise the
+/// center of the frame, where the subject usually is
+fn subject_sharpness(gray: &GrayImage, faces: &[FaceBox]) -> f64 {
+    let (width, height) = gray.dimensions();
+    let to_pixels = |value: f64, size: u32| (value * size as f64).round() as u32;
+
+    let face_sharpness = faces
+        .iter()
+        .map(|face| {
+            laplacian_variance(
+                gray,
+                to_pixels(face.x, width),
+                to_pixels(face.y, height),
+                to_pixels(face.width, width),
+                to_pixels(face.height, height),
+            )
+        })
+        .fold(None, |best: Option<f64>, value| {
+            Some(best.map_or(value, |b| b.max(value)))
+        });
+
+    // Faces too small to measure at thumbnail size fall back to the center
+    match face_sharpness {
+        Some(value) if value > 0.0 => value,
+        _ => laplacian_variance(gray, width / 4, height / 4, width / 2, height / 2),
+    }
+}
+
+/// Variance of the 4-neighbour Laplacian inside a region (a standard focus measure)
+pub fn laplacian_variance(gray: &GrayImage, x: u32, y: u32, width: u32, height: u32) -> f64 {
+    let x_end = (x + width).min(gray.width());
+    let y_end = (y + height).min(gray.height());
+    if x_end < x + 3 || y_end < y + 3 {
+        return 0.0;
+    }
+
+    let pixel = |px: u32, py: u32| gray.get_pixel(px, py)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    let mut count = 0.0;
+    for py in (y + 1)..(y_end - 1) {
+        for px in (x + 1)..(x_end - 1) {
+            let laplacian = 4.0 * pixel(px, py)
+                - pixel(px - 1, py)
+                - pixel(px + 1, py)
+                - pixel(px, py - 1)
+                - pixel(px, py + 1);
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+            count += 1.0;
+        }
+    }
+
+    let mean = sum / count;
+    sum_sq / count - mean * mean
+}
+
+/// Focus peaking overlay of `gray`: its sharpest edges marked in color on a transparent image
+/// of the same size
+pub fn focus_peaking(gray: &GrayImage) -> RgbaImage {
+    let (width, height) = gray.dimensions();
+    let mut overlay = RgbaImage::new(width, height);
+    if width < 3 || height < 3 {
+        return overlay;
+    }
+    // Sensor noise would otherwise light up flat areas of high-ISO frames
+    let smooth = image::imageops::blur(gray, 0.8);
+    let pixel = |x: u32, y: u32| smooth.get_pixel(x, y)[0] as f32;
+    let magnitudes: Vec<f32> = (1..height - 1)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let pixel = &pixel;
+            (1..width - 1).map(move |x| {
+                let gx = pixel(x + 1, y - 1) + 2.0 * pixel(x + 1, y) + pixel(x + 1, y + 1)
+                    - pixel(x - 1, y - 1)
+                    - 2.0 * pixel(x - 1, y)
+                    - pixel(x - 1, y + 1);
+                let gy = pixel(x - 1, y + 1) + 2.0 * pixel(x, y + 1) + pixel(x + 1, y + 1)
+                    - pixel(x - 1, y - 1)
+                    - 2.0 * pixel(x, y - 1)
+                    - pixel(x + 1, y - 1);
+                (gx * gx + gy * gy).sqrt()
+            })
+        })
+        .collect();
+
+    // Only the strongest edges of the frame count, and only if they are crisp at all
+    let mut sorted = magnitudes.clone();
+    let rank = ((sorted.len() as f32 * (1.0 - PEAKING_SHARE)) as usize).min(sorted.len() - 1);
+    let (_, &mut cutoff, _) = sorted.select_nth_unstable_by(rank, f32::total_cmp);
+    let threshold = cutoff.max(PEAKING_MIN_CONTRAST);
+
+    let inner = width - 2;
+    for (i, &magnitude) in magnitudes.iter().enumerate() {
+        if magnitude >= threshold && magnitude > 0.0 {
+            let (x, y) = (i as u32 % inner + 1, i as u32 / inner + 1);
+            overlay.put_pixel(x, y, PEAKING_COLOR);
+        }
+    }
+    overlay
+}
+
+/// Exposure warning overlay of `img`: pixels with a blown-out channel and pixels crushed to
+/// black in all channels marked in color on a transparent image of the same size
+/// Judged per channel, as a saturated sky can clip in blue long before its brightness does
+pub fn clipping_mask(img: &RgbImage) -> RgbaImage {
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let channels = img.get_pixel(x, y).0;
+        if channels.iter().any(|&c| c >= HIGHLIGHT_LEVEL) {
+            HIGHLIGHT_WARNING_COLOR
+        } else if channels.iter().all(|&c| c <= SHADOW_LEVEL) {
+            SHADOW_WARNING_COLOR
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+/// Shares of blown-out and black pixels
+fn clipping(gray: &GrayImage) -> (f64, f64) {
+    let total = gray.pixels().len().max(1) as f64;
+    let highlights = gray.pixels().filter(|p| p[0] >= HIGHLIGHT_LEVEL).count() as f64;
+    let shadows = gray.pixels().filter(|p| p[0] <= SHADOW_LEVEL).count() as f64;
+    (highlights / total, shadows / total)
+}
+
+/// Difference hash: one bit per horizontally adjacent pixel pair of a 9x8 downscale
+pub fn dhash(gray: &GrayImage) -> u64 {
+    let small = image::imageops::resize(gray, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Number of differing bits between two hashes
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Map `value` linearly from `range` onto 0.0 - 1.0
+fn ramp(value: f64, (low, high): (f64, f64)) -> f64 {
+    ((value - low) / (high - low)).clamp(0.0, 1.0)
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
+
+/// How much softer than `median` a frame is, as a 0.0 - 1.0 score
+fn softness(value: f64, median: f64) -> f64 {
+    if median <= 0.0 {
+        return 0.0;
+    }
+    ramp(BLUR_RATIO - value / median, (0.0, BLUR_RATIO))
+}
+
+/// Score every frame and return those at or above `threshold`, worst first
+/// `analyses` must be in shooting order so that consecutive near-duplicates are found
+pub fn suggest_rejections(analyses: &[ImageAnalysis], threshold: f64) -> Vec<RejectionSuggestion> {
+    // Sharpness is judged relative to the session: lenses, subjects and
+    // thumbnail sizes make absolute values meaningless
+    let median_sharpness = median(analyses.iter().map(|a| a.sharpness).collect());
+    let median_subject = median(analyses.iter().map(|a| a.subject_sharpness).collect());
+
+    let mut suggestions: Vec<RejectionSuggestion> = analyses
+        .iter()
+        .enumerate()
+        .filter_map(|(index, analysis)| {
+            let mut reasons = vec![
+                RejectReason::Blurry {
+                    score: softness(analysis.sharpness, median_sharpness),
+                },
+                RejectReason::SubjectSoft {
+                    score: softness(analysis.subject_sharpness, median_subject),
+                },
+                RejectReason::Overexposed {
+                    score: ramp(analysis.highlight_clipping, HIGHLIGHT_CLIPPING_RANGE),
+                },
+                RejectReason::Underexposed {
+                    score: ramp(analysis.shadow_clipping, SHADOW_CLIPPING_RANGE),
+                },
+            ];
+
+            // Near duplicates show the same scene, so their faces can be compared too
+            let duplicates: Vec<&ImageAnalysis> = [index.checked_sub(1), Some(index + 1)]
+                .into_iter()
+                .flatten()
+                .filter_map(|i| analyses.get(i))
+                .filter(|other| hash_distance(other.dhash, analysis.dhash) <= DUPLICATE_DISTANCE)
+                .collect();
+
+            // The softer frame of a near-duplicate pair is the one to drop
+            if let Some(sharper) = duplicates
+                .iter()
+                .filter(|other| other.sharpness > analysis.sharpness)
+                .max_by(|a, b| a.sharpness.total_cmp(&b.sharpness))
+            {
+                reasons.push(RejectReason::Duplicate {
+                    score: DUPLICATE_SCORE,
+                    of: sharper.filename.clone(),
+                });
+            }
+
+            if analysis.faces.is_empty() {
+                if let Some(other) = duplicates.iter().find(|other| !other.faces.is_empty()) {
+                    reasons.push(RejectReason::FaceLost {
+                        score: FACE_LOST_SCORE,
+                        of: other.filename.clone(),
+                    });
+                }
+            } else if let Some(own) = analysis.eye_contrast {
+                let open = duplicates
+                    .iter()
+                    .filter_map(|other| Some((other, other.eye_contrast?)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((other, contrast)) = open.filter(|(_, contrast)| *contrast > own) {
+                    reasons.push(RejectReason::EyesClosed {
+                        score: ramp(1.0 - own / contrast, EYES_CLOSED_RANGE),
+                        of: other.filename.clone(),
+                    });
+                }
+            }
+
+            reasons.retain(|reason| reason.score() >= threshold && reason.score() > 0.0);
+            reasons.sort_by(|a, b| b.score().total_cmp(&a.score()));
+
+            let score = reasons.first()?.score();
+            Some(RejectionSuggestion {
+                filename: analysis.filename.clone(),
+                score,
+                reasons,
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions
+}
+
+/// Largest gap between two frames of the same burst (continuous shooting is >= ~3 fps)
+pub const DEFAULT_BURST_GAP_MS: u64 = 1000;
+/// Weight of sharpness (vs. exposure) when ranking frames of a burst
+const BURST_SHARPNESS_WEIGHT: f64 = 0.7;
+
+/// Frames shot in one continuous burst
+/// The ID is the first frame's filename, which stays stable as long as the burst does
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BurstGroup {
+    pub id: String,
+    pub filenames: Vec<String>,
+}
+
+/// Per-frame scores within a burst (0.0 - 1.0, higher is better)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FrameScore {
+    pub filename: String,
+    pub sharpness_score: f64,
+    pub exposure_score: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BurstRanking {
+    pub group_id: String,
+    /// Recommended keeper (the user may pick another)
+    pub recommended: String,
+    /// Best frame first
+    pub frames: Vec<FrameScore>,
+}
+
+/// Group frames into bursts from their capture times (milliseconds)
+/// Only groups of two or more frames are returned
+pub fn detect_bursts(frames: &[(String, i64)], max_gap_ms: u64) -> Vec<BurstGroup> {
+    let mut sorted: Vec<&(String, i64)> = frames.iter().collect();
+    sorted.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    let mut previous: Option<i64> = None;
+    for (filename, time) in sorted {
+        match previous {
+            Some(prev) if time.abs_diff(prev) <= max_gap_ms => {
+                groups.last_mut().unwrap().push(filename.clone());
+            }
+            _ => groups.push(vec![filename.clone()]),
+        }
+        previous = Some(*time);
+    }
+
+    groups
+        .into_iter()
+        .filter(|filenames| filenames.len() > 1)
+        .map(|filenames| BurstGroup {
+            id: filenames[0].clone(),
+            filenames,
+        })
+        .collect()
+}
+
+/// Largest gap between two frames of the same exposure bracket
+/// EXIF dates are to the second, and the long frame of a dim bracket can take a while
+pub const DEFAULT_BRACKET_GAP_MS: u64 = 3000;
+/// Exposure biases closer than this (in EV) count as the same step
+const BRACKET_BIAS_TOLERANCE: f32 = 0.05;
+
+/// Frames of one exposure bracket (e.g. for HDR), labeled as a unit
+/// The ID is the first frame's filename, like burst IDs
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BracketSet {
+    pub id: String,
+    pub filenames: Vec<String>,
+    /// Exposure bias in EV of each frame, in the order of `filenames`
+    pub exposure_bias: Vec<f32>,
+}
+
+/// Exposure bias in EV of an EXIF value such as "-0.6666666666666666 EV"
+pub fn exposure_bias(exif: &ExifInfo) -> Option<f32> {
+    let value = exif.exposure_compensation.as_deref()?.trim();
+    let value = value.strip_suffix("EV").unwrap_or(value).trim();
+    value.parse().ok().filter(|bias: &f32| bias.is_finite())
+}
+
+/// Group frames into exposure brackets from their capture times (milliseconds) and biases:
+/// runs of frames shot at most `max_gap_ms` apart, each at a different bias
+/// A bias seen again starts the next bracket, so brackets shot back to back (an HDR
+/// panorama) stay apart and a plain burst at one exposure isn't a bracket at all
+pub fn detect_brackets(frames: &[(String, i64, f32)], max_gap_ms: u64) -> Vec<BracketSet> {
+    let mut sorted: Vec<&(String, i64, f32)> = frames.iter().collect();
+    sorted.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut sets: Vec<Vec<&(String, i64, f32)>> = Vec::new();
+    for frame in sorted {
+        let (_, time, bias) = frame;
+        let continues = sets.last().is_some_and(|set| {
+            let (_, previous, _) = set[set.len() - 1];
+            time.abs_diff(*previous) <= max_gap_ms
+                && set
+                    .iter()
+                    .all(|(_, _, other)| (other - bias).abs() > BRACKET_BIAS_TOLERANCE)
+        });
+        if continues {
+            sets.last_mut().unwrap().push(frame);
+        } else {
+            sets.push(vec![frame]);
+        }
+    }
+
+    sets.into_iter()
+        .filter(|set| set.len() > 1)
+        .map(|set| BracketSet {
+            id: set[0].0.clone(),
+            filenames: set
+                .iter()
+                .map(|(filename, _, _)| filename.clone())
+                .collect(),
+            exposure_bias: set.iter().map(|(_, _, bias)| *bias).collect(),
+        })
+        .collect()
+}
+
+/// Rank the frames of a burst; sharpness is relative to the burst's sharpest frame
+/// Returns None for an empty burst
+pub fn rank_burst(group_id: &str, analyses: &[ImageAnalysis]) -> Option<BurstRanking> {
+    let max_sharpness = analyses.iter().map(|a| a.sharpness).fold(0.0, f64::max);
+    let max_subject = analyses
+        .iter()
+        .map(|a| a.subject_sharpness)
+        .fold(0.0, f64::max);
+    let relative = |value: f64, max: f64| if max > 0.0 { value / max } else { 0.0 };
+
+    let mut frames: Vec<FrameScore> = analyses
+        .iter()
+        .map(|analysis| {
+            // The subject being sharp matters more than the background
+            let sharpness_score = 0.6 * relative(analysis.subject_sharpness, max_subject)
+                + 0.4 * relative(analysis.sharpness, max_sharpness);
+            let exposure_score = 1.0
+                - ramp(analysis.highlight_clipping, HIGHLIGHT_CLIPPING_RANGE)
+                    .max(ramp(analysis.shadow_clipping, SHADOW_CLIPPING_RANGE));
+            FrameScore {
+                filename: analysis.filename.clone(),
+                sharpness_score,
+                exposure_score,
+                score: BURST_SHARPNESS_WEIGHT * sharpness_score
+                    + (1.0 - BURST_SHARPNESS_WEIGHT) * exposure_score,
+            }
+        })
+        .collect();
+    frames.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Some(BurstRanking {
+        group_id: group_id.to_string(),
+        recommended: frames.first()?.filename.clone(),
+        frames,
+    })
+}
+
+/// Default maximum dHash distance for `cluster_similar` and `find_similar`
+/// Looser than `DUPLICATE_DISTANCE`: the same scene, not necessarily the same moment
+pub const DEFAULT_SIMILARITY_DISTANCE: u32 = 10;
+
+/// Visually similar frames from anywhere in the session
+/// The ID is the first member's filename (in session order)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SimilarCluster {
+    pub id: String,
+    pub filenames: Vec<String>,
+}
+
+/// Group frames whose hashes are within `max_distance` of each other (single linkage,
+/// so A~B and B~C puts all three together); only clusters of two or more are returned
+pub fn cluster_similar(hashes: &[ImageHash], max_distance: u32) -> Vec<SimilarCluster> {
+    // Union-find over frame indices
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..hashes.len() {
+        for j in (i + 1)..hashes.len() {
+            if hash_distance(hashes[i].dhash, hashes[j].dhash) <= max_distance {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[b.max(a)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    // Roots are the smallest index of their cluster, so clusters come out in session order
+    let mut clusters: Vec<Vec<String>> = vec![Vec::new(); hashes.len()];
+    for (i, hash) in hashes.iter().enumerate() {
+        let root = find(&mut parent, i);
+        clusters[root].push(hash.filename.clone());
+    }
+
+    clusters
+        .into_iter()
+        .filter(|filenames| filenames.len() > 1)
+        .map(|filenames| SimilarCluster {
+            id: filenames[0].clone(),
+            filenames,
+        })
+        .collect()
+}
+
+/// A frame similar to another one, `distance` bits of dHash apart
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SimilarFrame {
+    pub filename: String,
+    pub distance: u32,
+}
+
+/// Frames within `max_distance` of `filename`, most similar first (ties in session order)
+/// None when `filename` has no hash
+pub fn find_similar(
+    hashes: &[ImageHash],
+    filename: &str,
+    max_distance: u32,
+) -> Option<Vec<SimilarFrame>> {
+    let target = hashes.iter().find(|hash| hash.filename == filename)?;
+    let mut similar: Vec<SimilarFrame> = hashes
+        .iter()
+        .filter(|hash| hash.filename != filename)
+        .map(|hash| SimilarFrame {
+            filename: hash.filename.clone(),
+            distance: hash_distance(hash.dhash, target.dhash),
+        })
+        .filter(|frame| frame.distance <= max_distance)
+        .collect();
+    // Stable, so equally similar frames stay in session order
+    similar.sort_by_key(|frame| frame.distance);
+    Some(similar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn checkerboard(size: u32, cell: u32) -> GrayImage {
+        GrayImage::from_fn(size, size, |x, y| {
+            if (x / cell + y / cell).is_multiple_of(2) {
+                Luma([40])
+            } else {
+                Luma([200])
+            }
+        })
+    }
+
+    fn analysis(filename: &str, sharpness: f64, dhash: u64) -> ImageAnalysis {
+        ImageAnalysis {
+            filename: filename.to_string(),
+            sharpness,
+            subject_sharpness: sharpness,
+            highlight_clipping: 0.0,
+            shadow_clipping: 0.0,
+            dhash,
+            faces: Vec::new(),
+            eye_contrast: None,
+        }
+    }
+
+    #[test]
+    fn test_laplacian_variance_detects_blur() {
+        let sharp = checkerboard(64, 4);
+        let blurred = image::imageops::blur(&sharp, 3.0);
+
+        let sharp_score = laplacian_variance(&sharp, 0, 0, 64, 64);
+        let blurred_score = laplacian_variance(&blurred, 0, 0, 64, 64);
+        assert!(sharp_score > blurred_score * 10.0);
+
+        // Flat images and tiny regions have no detail
+        let flat = GrayImage::from_pixel(16, 16, Luma([128]));
+        assert_eq!(laplacian_variance(&flat, 0, 0, 16, 16), 0.0);
+        assert_eq!(laplacian_variance(&sharp, 0, 0, 2, 2), 0.0);
+    }
+
+    #[test]
+    fn test_clipping() {
+        let mut gray = GrayImage::from_pixel(10, 10, Luma([128]));
+        for x in 0..10 {
+            gray.put_pixel(x, 0, Luma([255]));
+            gray.put_pixel(x, 1, Luma([0]));
+            gray.put_pixel(x, 2, Luma([0]));
+        }
+        let result = analyze_gray("a.jpg", &gray, &gray, Vec::new());
+        assert!((result.highlight_clipping - 0.1).abs() < 1e-9);
+        assert!((result.shadow_clipping - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dhash_similarity() {
+        let base = checkerboard(64, 8);
+        let brighter = GrayImage::from_fn(64, 64, |x, y| {
+            Luma([base.get_pixel(x, y)[0].saturating_add(20)])
+        });
+        let different = checkerboard(64, 3);
+
+        assert_eq!(hash_distance(dhash(&base), dhash(&brighter)), 0);
+        assert!(hash_distance(dhash(&base), dhash(&different)) > DUPLICATE_DISTANCE);
+    }
+
+    #[test]
+    fn test_suggest_blurry_frame() {
+        let analyses = vec![
+            analysis("a.jpg", 100.0, 0),
+            analysis("b.jpg", 110.0, u64::MAX),
+            analysis("c.jpg", 5.0, 0xF0F0),
+        ];
+        let suggestions = suggest_rejections(&analyses, DEFAULT_REJECT_THRESHOLD);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].filename, "c.jpg");
+        assert!(matches!(
+            suggestions[0].reasons[0],
+            RejectReason::Blurry { .. } | RejectReason::SubjectSoft { .. }
+        ));
+    }
+
+    #[test]
+    fn test_suggest_softer_duplicate() {
+        let analyses = vec![
+            analysis("a.jpg", 100.0, 0b1010),
+            analysis("b.jpg", 90.0, 0b1011),
+            analysis("c.jpg", 95.0, u64::MAX),
+        ];
+        let suggestions = suggest_rejections(&analyses, 0.5);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].filename, "b.jpg");
+        assert_eq!(
+            suggestions[0].reasons,
+            vec![RejectReason::Duplicate {
+                score: DUPLICATE_SCORE,
+                of: "a.jpg".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_suggest_face_heuristics() {
+        let face = FaceBox {
+            x: 0.4,
+            y: 0.2,
+            width: 0.2,
+            height: 0.3,
+            score: 5.0,
+        };
+        let with_face = |filename: &str, eye_contrast: f64| ImageAnalysis {
+            faces: vec![face],
+            eye_contrast: Some(eye_contrast),
+            ..analysis(filename, 100.0, 0)
+        };
+        let analyses = vec![
+            with_face("open.jpg", 2.0),
+            with_face("blink.jpg", 0.4),
+            analysis("away.jpg", 100.0, 0),
+        ];
+        let suggestions = suggest_rejections(&analyses, 0.5);
+
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].filename, "blink.jpg");
+        assert_eq!(
+            suggestions[0].reasons,
+            vec![RejectReason::EyesClosed {
+                score: 1.0,
+                of: "open.jpg".to_string()
+            }]
+        );
+        assert_eq!(suggestions[1].filename, "away.jpg");
+        assert_eq!(
+            suggestions[1].reasons,
+            vec![RejectReason::FaceLost {
+                score: FACE_LOST_SCORE,
+                of: "blink.jpg".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_eye_contrast() {
+        // A flat face with detail in the eye band (open) or only around the mouth (closed)
+        let face_image = |eyes_open: bool| {
+            GrayImage::from_fn(100, 100, |x, y| {
+                let in_band = if eyes_open {
+                    (25..45).contains(&y)
+                } else {
+                    (70..90).contains(&y)
+                };
+                if (10..90).contains(&x) && in_band && (x + y).is_multiple_of(2) {
+                    Luma([255])
+                } else {
+                    Luma([90])
+                }
+            })
+        };
+        let face = FaceBox {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+            score: 5.0,
+        };
+
+        let open = eye_contrast(&face_image(true), &[face]).unwrap();
+        let closed = eye_contrast(&face_image(false), &[face]).unwrap();
+        assert!(open > 1.0);
+        assert!(closed < open / 10.0);
+        assert_eq!(eye_contrast(&face_image(true), &[]), None);
+    }
+
+    #[test]
+    fn test_suggest_exposure_and_threshold() {
+        let mut blown = analysis("blown.jpg", 100.0, 0);
+        blown.highlight_clipping = 0.5;
+        let mut dark = analysis("dark.jpg", 100.0, u64::MAX);
+        dark.shadow_clipping = 0.7;
+        let analyses = vec![blown, dark];
+
+        let suggestions = suggest_rejections(&analyses, DEFAULT_REJECT_THRESHOLD);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].filename, "blown.jpg");
+        assert_eq!(suggestions[0].score, 1.0);
+
+        // A lower threshold also proposes the mostly black frame
+        let suggestions = suggest_rejections(&analyses, 0.2);
+        assert_eq!(suggestions.len(), 2);
+        assert!(matches!(
+            suggestions[1].reasons[0],
+            RejectReason::Underexposed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_detect_bursts() {
+        let frames = vec![
+            ("c.jpg".to_string(), 10_300),
+            ("a.jpg".to_string(), 10_000),
+            ("b.jpg".to_string(), 10_150),
+            ("single.jpg".to_string(), 20_000),
+            ("d.jpg".to_string(), 30_000),
+            ("e.jpg".to_string(), 30_900),
+        ];
+        let bursts = detect_bursts(&frames, DEFAULT_BURST_GAP_MS);
+
+        assert_eq!(
+            bursts,
+            vec![
+                BurstGroup {
+                    id: "a.jpg".to_string(),
+                    filenames: vec!["a.jpg".into(), "b.jpg".into(), "c.jpg".into()],
+                },
+                BurstGroup {
+                    id: "d.jpg".to_string(),
+                    filenames: vec!["d.jpg".into(), "e.jpg".into()],
+                },
+            ]
+        );
+        assert!(detect_bursts(&frames, 100).is_empty());
+    }
+
+    #[test]
+    fn test_exposure_bias() {
+        let exif = |value: &str| ExifInfo {
+            exposure_compensation: Some(value.to_string()),
+            ..ExifInfo::default()
+        };
+        assert_eq!(exposure_bias(&exif("-2 EV")), Some(-2.0));
+        assert_eq!(
+            exposure_bias(&exif("0.6666666666666666 EV")),
+            Some(0.6666667)
+        );
+        assert_eq!(exposure_bias(&exif("unknown EV")), None);
+        assert_eq!(exposure_bias(&ExifInfo::default()), None);
+    }
+
+    #[test]
+    fn test_detect_brackets() {
+        let frame = |filename: &str, time: i64, bias: f32| (filename.to_string(), time, bias);
+        let frames = [
+            // Two three-frame brackets back to back
+            frame("a.nef", 0, 0.0),
+            frame("b.nef", 0, -2.0),
+            frame("c.nef", 1000, 2.0),
+            frame("d.nef", 2000, 0.0),
+            frame("e.nef", 2000, -2.0),
+            frame("f.nef", 3000, 2.0),
+            // A burst at one exposure
+            frame("g.nef", 60_000, 0.0),
+            frame("h.nef", 60_200, 0.0),
+            // Too far apart
+            frame("i.nef", 120_000, -1.0),
+            frame("j.nef", 130_000, 1.0),
+        ];
+        let brackets = detect_brackets(&frames, DEFAULT_BRACKET_GAP_MS);
+
+        assert_eq!(
+            brackets,
+            vec![
+                BracketSet {
+                    id: "a.nef".to_string(),
+                    filenames: vec!["a.nef".into(), "b.nef".into(), "c.nef".into()],
+                    exposure_bias: vec![0.0, -2.0, 2.0],
+                },
+                BracketSet {
+                    id: "d.nef".to_string(),
+                    filenames: vec!["d.nef".into(), "e.nef".into(), "f.nef".into()],
+                    exposure_bias: vec![0.0, -2.0, 2.0],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_burst() {
+        let mut blown = analysis("blown.jpg", 200.0, 0);
+        blown.highlight_clipping = 0.5;
+        let analyses = vec![
+            analysis("soft.jpg", 50.0, 0),
+            analysis("sharp.jpg", 180.0, 0),
+            blown,
+        ];
+
+        let ranking = rank_burst("soft.jpg", &analyses).unwrap();
+        assert_eq!(ranking.recommended, "sharp.jpg");
+        assert_eq!(ranking.frames.len(), 3);
+        assert_eq!(ranking.frames[2].filename, "soft.jpg");
+        assert_eq!(ranking.frames[1].exposure_score, 0.0);
+
+        assert!(rank_burst("none", &[]).is_none());
+    }
+
+    #[test]
+    fn test_detect_faces_without_faces() {
+        assert!(detect_faces(&GrayImage::from_pixel(200, 150, Luma([128]))).is_empty());
+        assert!(detect_faces(&checkerboard(64, 4)).is_empty());
+        assert!(detect_faces(&GrayImage::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_face_model_loads() {
+        assert!(create_face_detector().is_some());
+    }
+
+    #[test]
+    fn test_subject_sharpness_uses_faces() {
+        // Sharp detail only in the top-left corner, flat elsewhere
+        let gray = GrayImage::from_fn(100, 100, |x, y| {
+            if x < 20 && y < 20 && (x + y).is_multiple_of(2) {
+                Luma([255])
+            } else {
+                Luma([0])
+            }
+        });
+        let face = FaceBox {
+            x: 0.0,
+            y: 0.0,
+            width: 0.2,
+            height: 0.2,
+            score: 5.0,
+        };
+
+        assert_eq!(subject_sharpness(&gray, &[]), 0.0);
+        assert!(subject_sharpness(&gray, &[face]) > 0.0);
+    }
+
+    #[test]
+    fn test_cluster_similar() {
+        let analyses = [
+            analysis("stage_a1.jpg", 100.0, 0),
+            analysis("other.jpg", 100.0, u64::MAX),
+            analysis("stage_b.jpg", 100.0, 0xFFFF_0000_0000_0000),
+            analysis("stage_a2.jpg", 100.0, 0b111),
+            // Chained through stage_a2
+            analysis("stage_a3.jpg", 100.0, 0b111_1111_1111),
+        ];
+
+        let hashes: Vec<ImageHash> = analyses.iter().map(ImageHash::from).collect();
+
+        let clusters = cluster_similar(&hashes, DEFAULT_SIMILARITY_DISTANCE);
+        assert_eq!(
+            clusters,
+            vec![SimilarCluster {
+                id: "stage_a1.jpg".to_string(),
+                filenames: vec![
+                    "stage_a1.jpg".into(),
+                    "stage_a2.jpg".into(),
+                    "stage_a3.jpg".into()
+                ],
+            }]
+        );
+
+        // A strict distance keeps only exact matches
+        assert!(cluster_similar(&hashes, 0).is_empty());
+        assert!(cluster_similar(&[], DEFAULT_SIMILARITY_DISTANCE).is_empty());
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let hash = |filename: &str, dhash: u64| ImageHash {
+            filename: filename.to_string(),
+            dhash,
+        };
+        let hashes = [
+            hash("pose_a.jpg", 0b1111),
+            hash("other.jpg", u64::MAX),
+            hash("pose_b.jpg", 0b1),
+            hash("pose.jpg", 0),
+            hash("pose_c.jpg", 0b1000),
+        ];
+
+        let similar = find_similar(&hashes, "pose.jpg", DEFAULT_SIMILARITY_DISTANCE).unwrap();
+        let ranked: Vec<(&str, u32)> = similar
+            .iter()
+            .map(|frame| (frame.filename.as_str(), frame.distance))
+            .collect();
+        assert_eq!(
+            ranked,
+            [("pose_b.jpg", 1), ("pose_c.jpg", 1), ("pose_a.jpg", 4)]
+        );
+
+        assert!(find_similar(&hashes, "pose.jpg", 0).unwrap().is_empty());
+        assert_eq!(find_similar(&hashes, "missing.jpg", 10), None);
+    }
+
+    #[test]
+    fn test_focus_peaking() {
+        // A hard edge on the left half, a soft one (out of focus) on the right half
+        let soft = |x: u32| 200.0 - 160.0 / (1.0 + (-(x as f32 - 150.0) / 6.0).exp());
+        let gray = GrayImage::from_fn(200, 60, |x, _| {
+            image::Luma([match x {
+                0..25 => 40,
+                25..100 => 200,
+                _ => soft(x).round() as u8,
+            }])
+        });
+
+        let overlay = focus_peaking(&gray);
+        assert_eq!(overlay.dimensions(), (200, 60));
+        let marked = |x0: u32, x1: u32| {
+            (x0..x1)
+                .flat_map(|x| (0..60).map(move |y| (x, y)))
+                .filter(|&(x, y)| overlay.get_pixel(x, y)[3] > 0)
+                .count()
+        };
+        assert!(marked(20, 30) > 0);
+        assert_eq!(marked(0, 20) + marked(30, 100), 0);
+        assert_eq!(marked(100, 200), 0);
+
+        // A frame without crisp edges shows nothing
+        let gray = GrayImage::from_fn(200, 60, |x, _| image::Luma([soft(x).round() as u8]));
+        let overlay = focus_peaking(&gray);
+        assert!(overlay.pixels().all(|p| p[3] == 0));
+    }
+
+    #[test]
+    fn test_clipping_mask() {
+        let img = RgbImage::from_fn(4, 1, |x, _| {
+            image::Rgb(match x {
+                0 => [255, 255, 255],
+                1 => [40, 90, 252],
+                2 => [3, 2, 40],
+                _ => [0, 4, 1],
+            })
+        });
+        let mask = clipping_mask(&img);
+        assert_eq!(*mask.get_pixel(0, 0), HIGHLIGHT_WARNING_COLOR);
+        // One clipped channel is enough
+        assert_eq!(*mask.get_pixel(1, 0), HIGHLIGHT_WARNING_COLOR);
+        assert_eq!(mask.get_pixel(2, 0)[3], 0);
+        assert_eq!(*mask.get_pixel(3, 0), SHADOW_WARNING_COLOR);
+    }
+}
@@ -0,0 +1,161 @@
+// Thumbnail throughput measured on the user's own machine and photos
+// The best thread count depends on cores, disk and RAW sizes more than on any formula
+
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::{build_thread_pool, generate_thumbnail, is_raw_format};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Fewest thumbnails generated per measurement, so short runs aren't dominated by noise
+const MIN_RUN_ITEMS: usize = 8;
+/// A lower thread count is preferred when it is within this share of the fastest
+const TOLERANCE: f64 = 0.05;
+
+/// Throughput at one thread count, in thumbnails per second
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ThreadBenchmark {
+    pub threads: usize,
+    pub jpeg_per_second: Option<f64>,
+    pub raw_per_second: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BenchmarkReport {
+    pub results: Vec<ThreadBenchmark>,
+    /// Suggested `thumbnail_threads`
+    pub recommended_threads: usize,
+    /// Suggested `raw_decode_threads`; None without RAW samples
+    pub recommended_raw_threads: Option<usize>,
+}
+
+/// Thread counts worth measuring: powers of two up to the core count, plus the core count
+pub fn thread_counts(cpu_count: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|&n| n < cpu_count)
+        .collect();
+    counts.push(cpu_count.max(1));
+    counts
+}
+
+/// Lowest thread count whose throughput is within the tolerance of the best
+pub fn pick_threads(throughputs: &[(usize, f64)]) -> Option<usize> {
+    let best = throughputs
+        .iter()
+        .map(|(_, throughput)| *throughput)
+        .fold(f64::NAN, f64::max);
+    throughputs
+        .iter()
+        .filter(|(_, throughput)| *throughput >= best * (1.0 - TOLERANCE))
+        .map(|(threads, _)| *threads)
+        .min()
+}
+
+/// Thumbnails per second generating `samples` (repeated as needed) on `threads` threads
+fn measure(samples: &[PathBuf], threads: usize, output_dir: &Path, size: u32) -> f64 {
+    let items = MIN_RUN_ITEMS.max(threads * 2).max(samples.len());
+    let pool = build_thread_pool(threads);
+    let started = Instant::now();
+    pool.install(|| {
+        (0..items).into_par_iter().for_each(|i| {
+            let output = output_dir.join(format!("{}.jpg", i));
+            let _ = generate_thumbnail(&samples[i % samples.len()], &output, size);
+        });
+    });
+    items as f64 / started.elapsed().as_secs_f64().max(f64::EPSILON)
+}
+
+/// Measure JPEG and RAW thumbnail throughput at several thread counts
+/// Samples that fail to decode are left out; it's an error when none work
+pub fn run(sample_paths: &[PathBuf], cpu_count: usize, size: u32) -> Result<BenchmarkReport> {
+    let output_dir = std::env::temp_dir().join(format!("glimpse-benchmark-{}", std::process::id()));
+    std::fs::create_dir_all(&output_dir).map_err(|e| GlimpseError::io_at(&output_dir, e))?;
+
+    // The warm-up also pulls the samples into the OS file cache, so the first
+    // thread count isn't penalized for disk reads
+    let (raw, jpeg): (Vec<PathBuf>, Vec<PathBuf>) = sample_paths
+        .iter()
+        .filter(|path| generate_thumbnail(path, &output_dir.join("warmup.jpg"), size).is_ok())
+        .cloned()
+        .partition(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(is_raw_format)
+        });
+    if raw.is_empty() && jpeg.is_empty() {
+        let _ = std::fs::remove_dir_all(&output_dir);
+        return Err(GlimpseError::InvalidPath(
+            "None of the benchmark samples could be decoded".to_string(),
+        ));
+    }
+
+    let results: Vec<ThreadBenchmark> = thread_counts(cpu_count)
+        .into_iter()
+        .map(|threads| ThreadBenchmark {
+            threads,
+            jpeg_per_second: (!jpeg.is_empty()).then(|| measure(&jpeg, threads, &output_dir, size)),
+            raw_per_second: (!raw.is_empty()).then(|| measure(&raw, threads, &output_dir, size)),
+        })
+        .collect();
+    let _ = std::fs::remove_dir_all(&output_dir);
+
+    let best = |throughput: fn(&ThreadBenchmark) -> Option<f64>| {
+        let throughputs: Vec<(usize, f64)> = results
+            .iter()
+            .filter_map(|r| throughput(r).map(|t| (r.threads, t)))
+            .collect();
+        pick_threads(&throughputs)
+    };
+    let recommended_raw_threads = best(|r| r.raw_per_second);
+    let recommended_threads = best(|r| r.jpeg_per_second)
+        .or(recommended_raw_threads)
+        .unwrap_or(1);
+
+    Ok(BenchmarkReport {
+        results,
+        recommended_threads,
+        recommended_raw_threads,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(1), vec![1]);
+        assert_eq!(thread_counts(4), vec![1, 2, 4]);
+        assert_eq!(thread_counts(12), vec![1, 2, 4, 8, 12]);
+    }
+
+    #[test]
+    fn test_pick_threads() {
+        // 8 threads barely beat 4, so 4 is enough
+        assert_eq!(
+            pick_threads(&[(1, 10.0), (2, 19.0), (4, 30.0), (8, 31.0)]),
+            Some(4)
+        );
+        // Oversubscription slows down
+        assert_eq!(pick_threads(&[(1, 10.0), (2, 18.0), (4, 12.0)]), Some(2));
+        assert_eq!(pick_threads(&[]), None);
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = tempdir().unwrap();
+        let sample = dir.path().join("sample.png");
+        image::RgbImage::new(64, 48).save(&sample).unwrap();
+        let broken = dir.path().join("broken.jpg");
+        std::fs::write(&broken, b"not an image").unwrap();
+
+        let report = run(&[sample, broken.clone()], 2, 32).unwrap();
+        assert_eq!(report.results.len(), 2);
+        assert!(report.results.iter().all(|r| r.jpeg_per_second.is_some()));
+        assert_eq!(report.recommended_raw_threads, None);
+        assert!(report.recommended_threads <= 2);
+
+        assert!(run(&[broken], 2, 32).is_err());
+    }
+}
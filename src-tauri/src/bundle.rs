@@ -0,0 +1,186 @@
+// Portable session bundles (.glimpse files) for handing a cull over to another machine
+// File keys are relative to the session root, so labels apply again as long as the
+// folder layout is the same, wherever the folders are mounted
+
+use crate::database::{Database, Label, SessionSettings, ViewState};
+use crate::error::{GlimpseError, Result};
+use std::path::Path;
+
+/// File extension of session bundles
+pub const BUNDLE_EXTENSION: &str = "glimpse";
+
+/// Layout version written to new bundles; bundles from newer versions are rejected
+const BUNDLE_VERSION: u32 = 1;
+
+/// Everything the user decided in a session, independent of this machine's caches
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionBundle {
+    pub version: u32,
+    /// Folders on the exporting machine; the importer may point it elsewhere
+    pub folders: Vec<String>,
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub view_state: ViewState,
+    #[serde(default)]
+    pub settings: SessionSettings,
+    #[serde(default)]
+    pub last_selected_index: i32,
+    pub exported_at: String,
+}
+
+impl SessionBundle {
+    /// Collect the labels and view state of a session
+    pub fn from_session(db: &Database, session_id: &str) -> Result<Self> {
+        let session = db
+            .get_session(session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        let mut folders = db.get_session_folders(session_id)?;
+        if folders.is_empty() {
+            folders.push(session.folder_path);
+        }
+
+        let settings = SessionSettings {
+            // Export destinations and sync folders are paths on this machine
+            export_destination: None,
+            sync_dir: None,
+            ..db.get_session_settings(session_id)?
+        };
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            folders,
+            labels: db.get_labels(session_id)?,
+            view_state: db.get_view_state(session_id)?,
+            settings,
+            last_selected_index: session.last_selected_index,
+            exported_at: chrono::Local::now().to_rfc3339(),
+        })
+    }
+
+    /// Apply the bundle to an existing session
+    /// Labels in the bundle replace the session's labels for the same files; others are kept
+    pub fn apply(&self, db: &Database, session_id: &str) -> Result<usize> {
+        for label in &self.labels {
+            db.set_label(session_id, &label.filename, label.label.as_deref())?;
+        }
+        db.set_view_state(session_id, &self.view_state)?;
+        let local = db.get_session_settings(session_id)?;
+        let settings = SessionSettings {
+            export_destination: local.export_destination,
+            sync_dir: local.sync_dir,
+            ..self.settings.clone()
+        };
+        db.set_session_settings(session_id, &settings)?;
+        db.update_last_selected(session_id, self.last_selected_index)?;
+        Ok(self.labels.len())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| GlimpseError::InvalidBundle(e.to_string()))?;
+        std::fs::write(path, content).map_err(|e| GlimpseError::io_at(path, e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| GlimpseError::io_at(path, e))?;
+        let bundle: Self = serde_json::from_str(&content)
+            .map_err(|e| GlimpseError::InvalidBundle(e.to_string()))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(GlimpseError::InvalidBundle(format!(
+                "Bundle version {} needs a newer Glimpse",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Session;
+    use tempfile::tempdir;
+
+    fn create_session(db: &Database, id: &str, folder: &str) {
+        db.upsert_session(&Session {
+            id: id.to_string(),
+            folder_path: folder.to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 2,
+        })
+        .unwrap();
+        db.set_session_folders(id, &[folder.to_string()]).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let db = Database::open_in_memory().unwrap();
+        create_session(&db, "source", "/Volumes/SSD/shoot");
+        db.set_label("source", "DSC_0001.NEF", Some("adopted"))
+            .unwrap();
+        db.set_label("source", "DSC_0002.NEF", Some("rejected"))
+            .unwrap();
+        db.set_session_settings(
+            "source",
+            &SessionSettings {
+                sort_order: None,
+                filter: None,
+                preview_size: Some(2048),
+                export_destination: Some("/Users/a/export".to_string()),
+                sync_dir: None,
+                white_balance: None,
+            },
+        )
+        .unwrap();
+        db.update_last_selected("source", 1).unwrap();
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(format!("shoot.{}", BUNDLE_EXTENSION));
+        let bundle = SessionBundle::from_session(&db, "source").unwrap();
+        assert_eq!(bundle.settings.export_destination, None);
+        bundle.write(&path).unwrap();
+
+        // Another machine with the folder mounted elsewhere
+        let other = Database::open_in_memory().unwrap();
+        create_session(&other, "target", "D:/shoot");
+        let imported = SessionBundle::read(&path).unwrap();
+        assert_eq!(imported, bundle);
+        assert_eq!(imported.apply(&other, "target").unwrap(), 2);
+
+        let mut labels = other.get_labels("target").unwrap();
+        labels.sort_by(|a, b| a.filename.cmp(&b.filename));
+        assert_eq!(labels[0].label.as_deref(), Some("adopted"));
+        assert_eq!(labels[1].label.as_deref(), Some("rejected"));
+        assert_eq!(
+            other.get_session_settings("target").unwrap().preview_size,
+            Some(2048)
+        );
+        assert_eq!(
+            other
+                .get_session("target")
+                .unwrap()
+                .unwrap()
+                .last_selected_index,
+            1
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_newer_bundles() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("future.glimpse");
+        std::fs::write(
+            &path,
+            r#"{"version": 99, "folders": [], "labels": [], "exported_at": ""}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            SessionBundle::read(&path),
+            Err(GlimpseError::InvalidBundle(_))
+        ));
+        std::fs::write(&path, "not json").unwrap();
+        assert!(SessionBundle::read(&path).is_err());
+    }
+}
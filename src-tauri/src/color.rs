@@ -0,0 +1,106 @@
+use image::{DynamicImage, RgbImage};
+use moxcms::{ColorProfile, Layout, TransformOptions};
+
+/// Color space an image was encoded in, as far as we can tell from the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceSpace {
+    /// No profile and no EXIF hint: already sRGB by convention
+    Srgb,
+    /// Camera JPEG shot in AdobeRGB mode (EXIF interop index R03, no embedded profile)
+    AdobeRgb,
+    /// Embedded ICC profile
+    Icc(Vec<u8>),
+}
+
+/// Convert an image to sRGB so it displays the same as in color-managed tools
+/// Images in an unknown or unsupported space are returned unchanged
+pub fn to_srgb(img: DynamicImage, source: &SourceSpace) -> DynamicImage {
+    let profile = match source {
+        SourceSpace::Srgb => return img,
+        SourceSpace::AdobeRgb => ColorProfile::new_adobe_rgb(),
+        SourceSpace::Icc(icc) => match ColorProfile::new_from_slice(icc) {
+            Ok(profile) => profile,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable ICC profile: {:?}", e);
+                return img;
+            }
+        },
+    };
+
+    let rgb = img.to_rgb8();
+    let transform = match profile.create_transform_8bit(
+        Layout::Rgb,
+        &ColorProfile::new_srgb(),
+        Layout::Rgb,
+        TransformOptions::default(),
+    ) {
+        Ok(transform) => transform,
+        Err(e) => {
+            // e.g. CMYK or grayscale profiles on an image decoded as RGB
+            tracing::warn!("Unsupported ICC profile: {:?}", e);
+            return img;
+        }
+    };
+
+    let mut converted = vec![0u8; rgb.as_raw().len()];
+    if let Err(e) = transform.transform(rgb.as_raw(), &mut converted) {
+        tracing::warn!("Failed to convert to sRGB: {:?}", e);
+        return img;
+    }
+
+    match RgbImage::from_raw(rgb.width(), rgb.height(), converted) {
+        Some(converted) => DynamicImage::ImageRgb8(converted),
+        None => img,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(r: u8, g: u8, b: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, image::Rgb([r, g, b])))
+    }
+
+    fn first_pixel(img: &DynamicImage) -> [u8; 3] {
+        img.to_rgb8().get_pixel(0, 0).0
+    }
+
+    #[test]
+    fn test_srgb_is_untouched() {
+        let img = pixel(100, 180, 100);
+        assert_eq!(
+            first_pixel(&to_srgb(img, &SourceSpace::Srgb)),
+            [100, 180, 100]
+        );
+
+        let icc = ColorProfile::new_srgb().encode().unwrap();
+        let converted = first_pixel(&to_srgb(pixel(100, 180, 100), &SourceSpace::Icc(icc)));
+        for (a, b) in converted.iter().zip([100u8, 180, 100]) {
+            assert!(a.abs_diff(b) <= 2, "{:?}", converted);
+        }
+    }
+
+    #[test]
+    fn test_adobe_rgb_gets_more_saturated() {
+        // AdobeRGB values shown as sRGB look washed out; converting restores saturation
+        let converted = first_pixel(&to_srgb(pixel(100, 180, 100), &SourceSpace::AdobeRgb));
+        assert!(
+            converted[1] as i32 - (converted[0] as i32) > 80,
+            "{:?}",
+            converted
+        );
+
+        let icc = ColorProfile::new_adobe_rgb().encode().unwrap();
+        let from_icc = first_pixel(&to_srgb(pixel(100, 180, 100), &SourceSpace::Icc(icc)));
+        for (a, b) in from_icc.iter().zip(converted) {
+            assert!(a.abs_diff(b) <= 2, "{:?} vs {:?}", from_icc, converted);
+        }
+    }
+
+    #[test]
+    fn test_invalid_profile_is_ignored() {
+        let converted = to_srgb(pixel(1, 2, 3), &SourceSpace::Icc(vec![0; 16]));
+        assert_eq!(first_pixel(&converted), [1, 2, 3]);
+    }
+}
@@ -1,18 +1,73 @@
-use crate::config::{self, AppConfig};
-use crate::database::{Database, Label, Session};
-use crate::error::Result;
+use crate::analysis::{
+    self, BracketSet, BurstGroup, BurstRanking, FaceBox, ImageAnalysis, ImageHash,
+    RejectionSuggestion, SimilarCluster, SimilarFrame,
+};
+use crate::benchmark::{self, BenchmarkReport};
+use crate::bundle::SessionBundle;
+use crate::config::{
+    self, AppConfig, ExportFilter, ExportMode, ExportPreset, ExportSettings, Keybindings,
+    PowerMode, SftpTarget, WhiteBalance,
+};
+use crate::content_cache::{self, DedupStats};
+use crate::database::{
+    compare_labels, Bookmark, CullingPass, Database, DuplicateGroup, ExportRecord, FailureKind,
+    FileIdentity, IntegrityReport, InterruptedOperation, Label, OperationFileState, OperationKind,
+    OptimizeStats, PassDifference, ProblemFile, Project, Session, SessionLabelStats,
+    SessionSettings, ThumbnailFailure, ViewState,
+};
+use crate::disk;
+use crate::error::{GlimpseError, Result};
+use crate::export::{self, ExportOptions, ExportResult};
+use crate::gear::{self, GearFilter, GearStats};
+use crate::geocode::{self, ImageLocation, Place};
+use crate::histogram::{self, Histogram, HistogramSource};
 use crate::image_processor::{
-    extract_exif, generate_session_id, generate_thumbnails_parallel, get_cache_dir,
-    get_preview_dir, normalize_path, scan_folder, scan_subfolders, ExifInfo, ImageInfo,
-    SubfolderInfo,
+    self, build_thread_pool, cache_base_dir_for, capture_time_millis, count_missing_cache,
+    ensure_preview, extract_exif, file_stamp, generate_folders_session_id,
+    generate_thumbnails_parallel, get_cache_base_dir, get_cache_dir, get_preview_dir,
+    normalize_path, process_image, scan_folder, scan_folders, scan_folders_streaming,
+    scan_subfolders, session_root, ExifInfo, GenerationQueue, ImageInfo, Overlay, RawAdjustments,
+    SubfolderInfo, ThumbnailResult,
 };
-use std::path::Path;
-use std::sync::Mutex;
-use tauri::{AppHandle, Emitter, State};
+use crate::keychain;
+use crate::labelsync;
+use crate::logging;
+use crate::memory;
+use crate::metadata::{self, MetadataEdits};
+use crate::netio;
+use crate::operations::{self, Resolution, ResolveOutcome};
+use crate::power::{self, PowerStatus};
+use crate::reconcile::{self, LabelTransfer};
+use crate::rename::{self, Rename, SequenceOrder};
+use crate::sftp::SftpClient;
+use crate::stats::{self, FolderStats, EXIF_DATE_FORMAT};
+use crate::system;
+use crate::tasks::{TaskInfo, TaskKind, TaskManager, TaskStatus};
+use crate::tether::{self, ArrivalTracker};
+use crate::trash;
+use crate::video::{self, VideoInfo};
+use crate::volume::{self, SourceChange, SourceMonitor};
+use crate::xmp::{self, XmpSidecar};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 pub struct AppState {
     pub db: Mutex<Database>,
     pub current_session_id: Mutex<Option<String>>,
+    /// Files whose previews are currently being prefetched
+    pub prefetching: Arc<Mutex<HashSet<String>>>,
+    /// Work queue of the running thumbnail generation
+    pub generation_queue: Mutex<Option<Arc<GenerationQueue>>>,
+    /// Bumped for each opened session so the previous source watcher stops
+    pub source_watch: AtomicU64,
+    /// Long-running background jobs
+    pub tasks: TaskManager,
+    /// Analysis tasks low-power mode handled: paused by it (true) or resumed by the user
+    pub power_paused: Mutex<HashMap<u64, bool>>,
 }
 
 impl AppState {
@@ -20,8 +75,27 @@ impl AppState {
         Ok(Self {
             db: Mutex::new(Database::new()?),
             current_session_id: Mutex::new(None),
+            prefetching: Arc::new(Mutex::new(HashSet::new())),
+            generation_queue: Mutex::new(None),
+            source_watch: AtomicU64::new(0),
+            tasks: TaskManager::new(),
+            power_paused: Mutex::new(HashMap::new()),
         })
     }
+
+    /// Get the ID of the currently open session
+    pub fn current_session_id(&self) -> Result<String> {
+        self.current_session_id
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(GlimpseError::NoActiveSession)
+    }
+}
+
+/// Notify the frontend that a task started, changed state or finished
+fn emit_task(app: &AppHandle, info: TaskInfo) {
+    let _ = app.emit("task-updated", info);
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -30,42 +104,311 @@ struct ProgressPayload {
     total: usize,
 }
 
+/// Images found so far while scanning a folder being opened
+/// `reset` marks the first batch of a scan; a retried scan starts over
+#[derive(Clone, serde::Serialize)]
+struct ScanProgressPayload {
+    images: Vec<ImageInfo>,
+    reset: bool,
+}
+
+/// Stage of opening a folder: scanning, saving, labels, cache
+#[derive(Clone, serde::Serialize)]
+struct OpenProgressPayload {
+    stage: &'static str,
+}
+
+/// Stand-in of an image whose thumbnail isn't generated yet
+#[derive(Clone, serde::Serialize)]
+struct InstantThumbnailPayload {
+    filename: String,
+    thumbnail_path: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct PreviewReadyPayload {
+    filename: String,
+    preview_path: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct DiskSpaceWarningPayload {
+    path: String,
+    required: u64,
+    available: u64,
+    shortfall: u64,
+}
+
 /// Open a folder and retrieve the list of images
 #[tauri::command]
 pub async fn open_folder(
     app: AppHandle,
     state: State<'_, AppState>,
     folder_path: String,
-) -> std::result::Result<OpenFolderResult, String> {
-    let path = Path::new(&folder_path);
+) -> Result<OpenFolderResult> {
+    open_folders(app, state, vec![folder_path]).await
+}
+
+/// Open several folders (e.g. one per memory card) as a single session
+/// Images are identified by their path relative to the folders' common ancestor
+#[tauri::command]
+pub async fn open_folders(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    folder_paths: Vec<String>,
+) -> Result<OpenFolderResult> {
+    if folder_paths.is_empty() {
+        return Err(GlimpseError::InvalidPath("no folder given".to_string()));
+    }
+    let folders: Vec<PathBuf> = folder_paths.iter().map(PathBuf::from).collect();
+    let root = if folders.len() == 1 {
+        folder_paths[0].clone()
+    } else {
+        normalize_path(&session_root(&folders))
+    };
+
+    // Scan the folders
+    // Images are streamed to the UI as they are found
+    let _ = app.emit("open-progress", OpenProgressPayload { stage: "scanning" });
+    let images = {
+        let folders = folders.clone();
+        let app = app.clone();
+        scan_resilient(PathBuf::from(&root), move || {
+            let mut reset = true;
+            scan_folders_streaming(&folders, |images| {
+                let _ = app.emit("scan-progress", ScanProgressPayload { images, reset });
+                reset = false;
+            })
+        })
+        .await?
+    };
+
+    // Session bookkeeping reads the sync folder and stats every cache file, which can take a
+    // while on slow shares; keep it off the async runtime
+    let (images, prepared) = {
+        let app = app.clone();
+        let folder_paths = folder_paths.clone();
+        let folders = folders.clone();
+        tokio::task::spawn_blocking(move || {
+            let prepared = prepare_session(&app, &folder_paths, &folders, root, &images)?;
+            Ok::<_, GlimpseError>((images, prepared))
+        })
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))??
+    };
+    let PreparedSession {
+        session_id,
+        subfolders,
+        labels,
+        last_selected,
+        view_state,
+        settings,
+        effective_config,
+        cache_dir,
+        preview_dir,
+    } = prepared;
+
+    // Generate thumbnails and previews in background
+    let images_clone = images.clone();
+    let app_for_progress = app.clone();
+    let app_for_complete = app.clone();
+    let cache_dir_clone = cache_dir.clone();
+    let preview_dir_clone = preview_dir.clone();
+    let session_id_for_limit = session_id.clone();
+
+    // Start with the images around the last selected position
+    let queue = Arc::new(GenerationQueue::new(
+        images.len(),
+        last_selected.max(0) as usize,
+    ));
+    *state.generation_queue.lock().unwrap() = Some(Arc::clone(&queue));
+
+    // The previous folder's jobs are no longer needed
+    for kind in [TaskKind::Thumbnails, TaskKind::Exif, TaskKind::Tether] {
+        for info in state.tasks.cancel_kind(kind) {
+            emit_task(&app, info);
+        }
+    }
+    let task = state.tasks.start(TaskKind::Thumbnails, images.len());
+    let task_id = task.id();
+    emit_task(&app, task.info());
+
+    start_instant_thumbnail_pass(&app, session_id.clone(), &images, &cache_dir);
+
+    // Blocking thread: workers wait on the task while it is paused
+    tokio::task::spawn_blocking(move || {
+        let results = generate_thumbnails_parallel(
+            &images_clone,
+            &cache_dir_clone,
+            &preview_dir_clone,
+            &effective_config,
+            &queue,
+            &task,
+            move |completed, total| {
+                let _ = app_for_progress
+                    .emit("thumbnail-progress", ProgressPayload { completed, total });
+            },
+        );
 
-    // Scan the folder
-    let images = scan_folder(path).map_err(|e| e.to_string())?;
+        record_generation_results(
+            &app_for_complete,
+            &session_id_for_limit,
+            &images_clone,
+            &results,
+        );
+
+        // Completion notification
+        let _ = app_for_complete.emit("thumbnails-complete", results);
+        emit_task(&app_for_complete, task.finish());
+
+        // Keep total cache within the configured limit
+        enforce_cache_limit(&session_id_for_limit);
+    });
+
+    start_exif_job(&app, session_id.clone(), images.clone())?;
+    watch_sources(&app, session_id.clone(), folders);
+
+    Ok(OpenFolderResult {
+        session_id,
+        images,
+        labels,
+        last_selected_index: last_selected,
+        cache_dir: normalize_path(&cache_dir),
+        cache_extension: config::get_cache_format().extension().to_string(),
+        subfolders,
+        settings,
+        view_state,
+        task_id,
+        folders: folder_paths,
+    })
+}
+
+/// Images an instant thumbnail pass handles before reporting them
+const INSTANT_THUMBNAIL_BATCH: usize = 64;
+
+/// Extract the EXIF thumbnails of images without a cached thumbnail as low-resolution
+/// stand-ins, reported through `instant-thumbnails` batches
+/// Nothing is decoded, so the grid fills in long before the thumbnail job gets to every image;
+/// the pass stops once another session is opened
+fn start_instant_thumbnail_pass(
+    app: &AppHandle,
+    session_id: String,
+    images: &[ImageInfo],
+    cache_dir: &Path,
+) {
+    let missing: Vec<ImageInfo> = images
+        .iter()
+        .filter(|image| {
+            !image.is_video
+                && !image_processor::thumbnail_path_for(cache_dir, &image.filename).exists()
+        })
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    let cache_dir = cache_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        // EXIF reads like the EXIF job, so it shares that job's part of the thread budget
+        // instead of competing with the thumbnail workers
+        let pool = build_thread_pool(config::get_exif_thread_count());
+        for batch in missing.chunks(INSTANT_THUMBNAIL_BATCH) {
+            let state = app.state::<AppState>();
+            if state.current_session_id().ok().as_ref() != Some(&session_id) {
+                return;
+            }
+            let thumbnails: Vec<InstantThumbnailPayload> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .filter_map(|image| {
+                        // The thumbnail job may have caught up meanwhile
+                        let thumbnail =
+                            image_processor::thumbnail_path_for(&cache_dir, &image.filename);
+                        if thumbnail.exists() {
+                            return None;
+                        }
+                        // Always extracted again, as the image may have changed since
+                        let path = image_processor::instant_thumbnail_path_for(
+                            &cache_dir,
+                            &image.filename,
+                        );
+                        image_processor::generate_instant_thumbnail(Path::new(&image.path), &path)
+                            .ok()?;
+                        Some(InstantThumbnailPayload {
+                            filename: image.filename.clone(),
+                            thumbnail_path: normalize_path(&path),
+                        })
+                    })
+                    .collect()
+            });
+            if !thumbnails.is_empty() {
+                let _ = app.emit("instant-thumbnails", thumbnails);
+            }
+        }
+    });
+}
+
+/// Session state `open_folders` returns, gathered on a blocking thread
+struct PreparedSession {
+    session_id: String,
+    subfolders: Vec<SubfolderInfo>,
+    labels: Vec<Label>,
+    last_selected: i32,
+    view_state: ViewState,
+    settings: SessionSettings,
+    effective_config: AppConfig,
+    cache_dir: PathBuf,
+    preview_dir: PathBuf,
+}
+
+/// Record the opened session and load what the UI needs to show it
+/// Reports each stage as an `open-progress` event
+fn prepare_session(
+    app: &AppHandle,
+    folder_paths: &[String],
+    folders: &[PathBuf],
+    root: String,
+    images: &[ImageInfo],
+) -> Result<PreparedSession> {
+    let state = app.state::<AppState>();
 
     // Only look for subfolders with images when the top level is empty — keeps the common
     // path allocation-free while giving the UI enough info to guide the user.
-    let subfolders = if images.is_empty() {
-        scan_subfolders(path).unwrap_or_default()
+    let subfolders = if images.is_empty() && folders.len() == 1 {
+        scan_subfolders(&folders[0]).unwrap_or_default()
     } else {
         Vec::new()
     };
 
+    tracing::info!(
+        "Opened {} ({} images)",
+        folder_paths.join(", "),
+        images.len()
+    );
+
     // Generate session ID
-    let session_id = generate_session_id(&folder_path);
+    let session_id = generate_folders_session_id(folder_paths);
 
     // Save to database
+    let _ = app.emit("open-progress", OpenProgressPayload { stage: "saving" });
     {
         let db = state.db.lock().unwrap();
 
         let session = Session {
             id: session_id.clone(),
-            folder_path: folder_path.clone(),
+            folder_path: root,
             last_opened: Some(chrono::Local::now().to_rfc3339()),
             last_selected_index: 0,
             total_files: images.len() as i32,
         };
 
-        db.upsert_session(&session).map_err(|e| e.to_string())?;
+        db.upsert_session(&session)?;
+        if folder_paths.len() > 1 {
+            db.set_session_folders(&session_id, folder_paths)?;
+        }
+        db.set_file_identities(&session_id, images)?;
     }
 
     // Save current session ID
@@ -74,204 +417,3545 @@ pub async fn open_folder(
         *current = Some(session_id.clone());
     }
 
-    // Get label information
+    let _ = app.emit("open-progress", OpenProgressPayload { stage: "labels" });
+    // Get label information, including changes other machines synced meanwhile
     let labels = {
         let db = state.db.lock().unwrap();
-        db.get_labels(&session_id).map_err(|e| e.to_string())?
+        if let Err(e) = merge_synced_labels(&db, &session_id) {
+            tracing::warn!("Failed to merge synced labels: {}", e);
+        }
+        db.get_labels(&session_id)?
     };
 
-    // Get last selected position
-    let last_selected = {
+    // Get last selected position and view state
+    let (last_selected, view_state) = {
         let db = state.db.lock().unwrap();
-        db.get_session(&session_id)
-            .map_err(|e| e.to_string())?
-            .map(|s| s.last_selected_index)
-            .unwrap_or(0)
+        (
+            db.get_session(&session_id)?
+                .map(|s| s.last_selected_index)
+                .unwrap_or(0),
+            db.get_view_state(&session_id)?,
+        )
     };
 
-    // Get cache directory and preview directory
-    let cache_dir = get_cache_dir(&session_id).map_err(|e| e.to_string())?;
-    let preview_dir = get_preview_dir(&session_id).map_err(|e| e.to_string())?;
+    // Get per-session settings and apply them over the global config
+    let (settings, effective_config) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.get_session_settings(&session_id)?,
+            effective_config(&db, &session_id)?,
+        )
+    };
 
-    // Generate thumbnails and previews in background
-    let images_clone = images.clone();
-    let app_for_progress = app.clone();
-    let app_for_complete = app.clone();
-    let cache_dir_clone = cache_dir.clone();
-    let preview_dir_clone = preview_dir.clone();
+    // Get cache directory and preview directory
+    let cache_dir = get_cache_dir(&session_id)?;
+    let preview_dir = get_preview_dir(&session_id)?;
 
-    tokio::spawn(async move {
-        let results = generate_thumbnails_parallel(
-            &images_clone,
-            &cache_dir_clone,
-            &preview_dir_clone,
-            move |completed, total| {
-                let _ = app_for_progress
-                    .emit("thumbnail-progress", ProgressPayload { completed, total });
+    let _ = app.emit("open-progress", OpenProgressPayload { stage: "cache" });
+    // Warn (but keep going) when the cache volume can't hold the new thumbnails;
+    // labeling still works without them
+    let (missing_thumbnails, missing_previews) =
+        count_missing_cache(images, &cache_dir, &preview_dir);
+    let required = disk::estimate_cache_bytes(
+        missing_thumbnails,
+        missing_previews,
+        effective_config.thumbnail_size,
+        effective_config.preview_size,
+    );
+    if let Err(GlimpseError::InsufficientSpace {
+        path,
+        required,
+        available,
+    }) = disk::ensure_space(&cache_dir, required)
+    {
+        tracing::warn!(
+            "Low disk space for cache at {}: {} bytes required, {} available",
+            path,
+            required,
+            available
+        );
+        let _ = app.emit(
+            "disk-space-warning",
+            DiskSpaceWarningPayload {
+                path,
+                required,
+                available,
+                shortfall: required - available,
             },
         );
+    }
 
-        // Completion notification
-        let _ = app_for_complete.emit("thumbnails-complete", results);
-    });
-
-    Ok(OpenFolderResult {
+    Ok(PreparedSession {
         session_id,
-        images,
-        labels,
-        last_selected_index: last_selected,
-        cache_dir: normalize_path(&cache_dir),
         subfolders,
+        labels,
+        last_selected,
+        view_state,
+        settings,
+        effective_config,
+        cache_dir,
+        preview_dir,
     })
 }
 
+/// Persist why thumbnails failed and remember files that failed to decode because
+/// they are damaged; earlier entries of files that work now are forgotten
+fn record_generation_results(
+    app: &AppHandle,
+    session_id: &str,
+    images: &[ImageInfo],
+    results: &[ThumbnailResult],
+) {
+    let modified: HashMap<&str, &str> = images
+        .iter()
+        .map(|image| (image.filename.as_str(), image.modified_at.as_str()))
+        .collect();
+    let state = app.state::<AppState>();
+    let db = state.db.lock().unwrap();
+
+    let failed_at = chrono::Local::now().to_rfc3339();
+    let mut readable = Vec::new();
+    for result in results {
+        if result.success {
+            readable.push(result.filename.clone());
+            continue;
+        }
+
+        let failure = ThumbnailFailure {
+            filename: result.filename.clone(),
+            kind: if result.corrupt {
+                FailureKind::Corrupt
+            } else if result.placeholder {
+                FailureKind::Placeholder
+            } else {
+                FailureKind::Error
+            },
+            error: result.error.clone().unwrap_or_default(),
+            failed_at: failed_at.clone(),
+        };
+        if let Err(e) = db.set_thumbnail_failure(session_id, &failure) {
+            tracing::warn!(
+                "Failed to record thumbnail failure of {}: {}",
+                failure.filename,
+                e
+            );
+        }
+
+        if result.corrupt {
+            let file = ProblemFile {
+                filename: result.filename.clone(),
+                original_modified: modified
+                    .get(result.filename.as_str())
+                    .unwrap_or(&"-")
+                    .to_string(),
+                error: result.error.clone().unwrap_or_default(),
+            };
+            if let Err(e) = db.set_problem_file(session_id, &file) {
+                tracing::warn!("Failed to record problem file {}: {}", file.filename, e);
+            }
+        }
+    }
+    if let Err(e) = db
+        .delete_problem_files(session_id, &readable)
+        .and_then(|_| db.delete_thumbnail_failures(session_id, &readable))
+    {
+        tracing::warn!("Failed to update problem files: {}", e);
+    }
+}
+
+/// Thumbnail failures of a session, by file
 #[derive(serde::Serialize)]
-pub struct OpenFolderResult {
+pub struct GenerationReport {
+    pub failures: Vec<ThumbnailFailure>,
+    pub corrupt: usize,
+    pub placeholders: usize,
+}
+
+/// Which images of a session got no thumbnail, and why
+#[tauri::command]
+pub fn get_generation_report(
+    state: State<'_, AppState>,
     session_id: String,
-    images: Vec<ImageInfo>,
-    labels: Vec<Label>,
-    last_selected_index: i32,
-    cache_dir: String,
-    subfolders: Vec<SubfolderInfo>,
+) -> Result<GenerationReport> {
+    let failures = state
+        .db
+        .lock()
+        .unwrap()
+        .get_thumbnail_failures(&session_id)?;
+    let count = |kind| failures.iter().filter(|f| f.kind == kind).count();
+    Ok(GenerationReport {
+        corrupt: count(FailureKind::Corrupt),
+        placeholders: count(FailureKind::Placeholder),
+        failures,
+    })
+}
+
+/// Files of a session that couldn't be decoded because their data is damaged
+#[tauri::command]
+pub fn get_problem_files(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ProblemFile>> {
+    state.db.lock().unwrap().get_problem_files(&session_id)
+}
+
+/// Session created or updated from a bundle
+#[derive(serde::Serialize)]
+pub struct ImportedSession {
+    pub session_id: String,
+    pub folders: Vec<String>,
+    pub labels: usize,
+}
+
+/// Save a session's labels, view state and settings as a portable `.glimpse` bundle
+#[tauri::command]
+pub fn export_session_bundle(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+) -> Result<()> {
+    let bundle = SessionBundle::from_session(&state.db.lock().unwrap(), &session_id)?;
+    bundle.write(Path::new(&path))?;
+    tracing::info!(
+        "Exported session {} ({} labels) to {}",
+        session_id,
+        bundle.labels.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Import a `.glimpse` bundle into the session of `folder_paths`
+/// (the folders it was exported from when omitted, e.g. on a shared NAS)
+/// The returned folders are opened to continue the cull
+#[tauri::command]
+pub fn import_session_bundle(
+    state: State<'_, AppState>,
+    path: String,
+    folder_paths: Option<Vec<String>>,
+) -> Result<ImportedSession> {
+    let bundle = SessionBundle::read(Path::new(&path))?;
+    let folder_paths = folder_paths.unwrap_or_else(|| bundle.folders.clone());
+    if folder_paths.is_empty() {
+        return Err(GlimpseError::InvalidPath("no folder given".to_string()));
+    }
+    if let Some(missing) = folder_paths.iter().find(|f| !Path::new(f).is_dir()) {
+        return Err(GlimpseError::InvalidPath(missing.clone()));
+    }
+
+    let session_id = generate_folders_session_id(&folder_paths);
+    let db = state.db.lock().unwrap();
+    if db.get_session(&session_id)?.is_none() {
+        let folders: Vec<PathBuf> = folder_paths.iter().map(PathBuf::from).collect();
+        let root = if folders.len() == 1 {
+            folder_paths[0].clone()
+        } else {
+            normalize_path(&session_root(&folders))
+        };
+        db.upsert_session(&Session {
+            id: session_id.clone(),
+            folder_path: root,
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 0,
+        })?;
+        if folder_paths.len() > 1 {
+            db.set_session_folders(&session_id, &folder_paths)?;
+        }
+    }
+    let labels = bundle.apply(&db, &session_id)?;
+    tracing::info!(
+        "Imported {} labels from {} into session {}",
+        labels,
+        path,
+        session_id
+    );
+
+    Ok(ImportedSession {
+        session_id,
+        folders: folder_paths,
+        labels,
+    })
+}
+
+/// Files read between progress updates (and database writes) of the EXIF job
+const EXIF_BATCH_SIZE: usize = 64;
+
+#[derive(Clone, serde::Serialize)]
+struct ExifCompletePayload {
+    session_id: String,
+    read: usize,
+}
+
+/// Read the EXIF data of a session's images in the background, so sorting and filtering
+/// by capture time work soon after opening; files already cached as unchanged are skipped
+/// Content fingerprints are taken along the way, for duplicates across sessions
+fn start_exif_job(app: &AppHandle, session_id: String, images: Vec<ImageInfo>) -> Result<()> {
+    let state = app.state::<AppState>();
+    let (cached, fingerprinted) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.get_exif_cache(&session_id)?,
+            db.get_file_fingerprints(&session_id)?,
+        )
+    };
+    let app = app.clone();
+    tokio::task::spawn_blocking(move || {
+        let missing = image_processor::stale_exif_files(images, &cached, &fingerprinted);
+        if missing.is_empty() {
+            return;
+        }
+
+        let task = app
+            .state::<AppState>()
+            .tasks
+            .start(TaskKind::Exif, missing.len());
+        emit_task(&app, task.info());
+        let pool = build_thread_pool(config::get_exif_thread_count());
+        let mut read = 0;
+        for batch in missing.chunks(EXIF_BATCH_SIZE) {
+            if !task.checkpoint() {
+                break;
+            }
+            // Files without readable EXIF are cached as empty so they aren't retried every time
+            let results: Vec<(Option<ExifInfo>, Option<String>)> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|(image, read_exif, _)| {
+                        let path = Path::new(&image.path);
+                        let exif = read_exif.then(|| extract_exif(path).unwrap_or_default());
+                        (exif, content_cache::fingerprint(path).ok())
+                    })
+                    .collect()
+            });
+
+            let state = app.state::<AppState>();
+            let db = state.db.lock().unwrap();
+            let (mut exifs, mut fingerprints) = (Vec::new(), Vec::new());
+            for ((image, _, stamp), (exif, fingerprint)) in batch.iter().zip(results) {
+                if let Some(exif) = exif {
+                    exifs.push((image.filename.clone(), image.modified_at.clone(), exif));
+                }
+                if let Some(fingerprint) = fingerprint {
+                    fingerprints.push((image.filename.clone(), stamp.clone(), fingerprint));
+                }
+            }
+            if let Err(e) = db.set_exif_cache(&session_id, &exifs) {
+                tracing::warn!("Failed to cache EXIF: {}", e);
+            }
+            if let Err(e) = db.set_file_fingerprints(&session_id, &fingerprints) {
+                tracing::warn!("Failed to store fingerprints: {}", e);
+            }
+            drop(db);
+
+            read += batch.len();
+            task.set_progress(read);
+            let _ = app.emit(
+                "exif-progress",
+                ProgressPayload {
+                    completed: read,
+                    total: missing.len(),
+                },
+            );
+        }
+
+        emit_task(&app, task.finish());
+        let _ = app.emit("exif-complete", ExifCompletePayload { session_id, read });
+    });
+    Ok(())
+}
+
+/// Scan on a blocking thread with retries, failing with a clear error instead of hanging
+/// when the network share holding `root` stops answering (the scan stops making progress)
+async fn scan_resilient<F>(root: PathBuf, scan: F) -> Result<Vec<ImageInfo>>
+where
+    F: FnMut() -> Result<Vec<ImageInfo>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || scan_guarded(root, scan))
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Blocking form of `scan_resilient`, for threads that are already off the runtime
+fn scan_guarded<F>(root: PathBuf, mut scan: F) -> Result<Vec<ImageInfo>>
+where
+    F: FnMut() -> Result<Vec<ImageInfo>> + Send + 'static,
+{
+    netio::ensure_reachable(&root).map_err(|e| e.or_source_unavailable(&root))?;
+    let timeout_root = root.clone();
+    netio::with_stall_timeout(&timeout_root, netio::SCAN_STALL_TIMEOUT, move || {
+        netio::with_retry(&root, &mut scan)
+    })
+}
+
+/// Emitted once files were moved to another folder, so both sessions refresh
+#[derive(Clone, serde::Serialize)]
+pub struct FilesMoved {
+    pub source_session_id: String,
+    pub destination_session_id: String,
+    /// Keys in the source session of the files that were moved
+    pub moved: Vec<String>,
+    pub failed: usize,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FileDeletedPayload {
+    session_id: String,
+    filename: String,
+    to_trash: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct SourceUnavailablePayload {
+    session_id: String,
+    missing: Vec<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct SourceAvailablePayload {
+    session_id: String,
+}
+
+/// Watch the folders of the opened session until another one is opened
+/// When they disappear (drive unplugged), running jobs are paused and `source-unavailable`
+/// is emitted; once they are back the jobs resume and `source-available` is emitted
+fn watch_sources(app: &AppHandle, session_id: String, folders: Vec<PathBuf>) {
+    let generation = app
+        .state::<AppState>()
+        .source_watch
+        .fetch_add(1, Ordering::Relaxed)
+        + 1;
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        let mut monitor = SourceMonitor::default();
+        let mut paused = Vec::new();
+
+        while state.source_watch.load(Ordering::Relaxed) == generation {
+            std::thread::sleep(volume::WATCH_INTERVAL);
+            match monitor.poll(volume::missing_folders(&folders)) {
+                Some(SourceChange::Lost(missing)) => {
+                    let missing: Vec<String> = missing.iter().map(|p| normalize_path(p)).collect();
+                    tracing::warn!("Source folders unavailable: {}", missing.join(", "));
+
+                    // Tethering already waits for its folders to come back
+                    for info in state.tasks.list() {
+                        if info.status != TaskStatus::Running || info.kind == TaskKind::Tether {
+                            continue;
+                        }
+                        if let Ok(info) = state.tasks.pause(info.id) {
+                            paused.push(info.id);
+                            emit_task(&app, info);
+                        }
+                    }
+                    let _ = app.emit(
+                        "source-unavailable",
+                        SourceUnavailablePayload {
+                            session_id: session_id.clone(),
+                            missing,
+                        },
+                    );
+                }
+                Some(SourceChange::Restored) => {
+                    tracing::info!("Source folders available again");
+                    for id in paused.drain(..) {
+                        if let Ok(info) = state.tasks.resume(id) {
+                            emit_task(&app, info);
+                        }
+                    }
+                    let _ = app.emit(
+                        "source-available",
+                        SourceAvailablePayload {
+                            session_id: session_id.clone(),
+                        },
+                    );
+                }
+                None => {}
+            }
+        }
+    });
+}
+
+/// Global config with the per-session overrides of `session_id` applied
+fn effective_config(db: &Database, session_id: &str) -> Result<AppConfig> {
+    let settings = db.get_session_settings(session_id)?;
+    let mut config = config::get_config();
+    if let Some(preview_size) = settings.preview_size {
+        config.preview_size = preview_size;
+    }
+    if let Some(white_balance) = settings.white_balance {
+        config.raw_white_balance = white_balance;
+    }
+    Ok(config)
+}
+
+/// Key of an image of the open session from its path
+fn session_key(root: &str, path: &Path) -> Option<String> {
+    path.strip_prefix(root)
+        .ok()
+        .map(normalize_path)
+        .filter(|key| !key.is_empty())
+}
+
+/// Re-render the preview of a RAW image of the open session with exposure (stops) and white
+/// balance (mode, Kelvin, tint) changed; the values are remembered for the image
+/// Without a mode the image follows the session's white balance
+/// Returns the path of the rendered preview, which is replaced on every call
+#[tauri::command]
+pub async fn get_adjusted_preview(
+    app: AppHandle,
+    path: String,
+    exposure: f32,
+    wb_temp: Option<f32>,
+    wb_tint: Option<f32>,
+    white_balance: Option<WhiteBalance>,
+) -> Result<String> {
+    let adjustments = RawAdjustments {
+        exposure,
+        wb_temp,
+        wb_tint,
+        white_balance,
+    };
+    adjustments.validate()?;
+
+    let state = app.state::<AppState>();
+    let session_id = state.current_session_id()?;
+    let (key, config) = {
+        let db = state.db.lock().unwrap();
+        let session = db
+            .get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        let key = session_key(&session.folder_path, Path::new(&path))
+            .ok_or_else(|| GlimpseError::InvalidPath(path.clone()))?;
+        db.set_adjustments(&session_id, &key, &adjustments)?;
+        (key, effective_config(&db, &session_id)?)
+    };
+    let output_path =
+        image_processor::adjusted_preview_path_for(&get_preview_dir(&session_id)?, &key);
+
+    tokio::task::spawn_blocking(move || {
+        image_processor::run_with_raw_stack(move || {
+            image_processor::generate_adjusted_preview(
+                Path::new(&path),
+                &output_path,
+                config.preview_size,
+                config.raw_white_balance,
+                &adjustments,
+            )
+            .map(|_| normalize_path(&output_path))
+        })
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Focus peaking overlay of an image of the open session: a PNG the size of its preview,
+/// transparent except for in-focus edges
+#[tauri::command]
+pub async fn get_focus_peaking(app: AppHandle, path: String) -> Result<String> {
+    overlay_for(app, path, Overlay::FocusPeaking).await
+}
+
+/// Exposure warning overlay of an image of the open session: a PNG the size of its preview,
+/// transparent except for blown-out highlights and crushed shadows
+#[tauri::command]
+pub async fn get_clipping_mask(app: AppHandle, path: String) -> Result<String> {
+    overlay_for(app, path, Overlay::Clipping).await
+}
+
+/// Path of an overlay of an image of the open session, generated if missing or stale
+async fn overlay_for(app: AppHandle, path: String, overlay: Overlay) -> Result<String> {
+    let state = app.state::<AppState>();
+    let session_id = state.current_session_id()?;
+    let (key, config) = {
+        let db = state.db.lock().unwrap();
+        let session = db
+            .get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        let key = session_key(&session.folder_path, Path::new(&path))
+            .ok_or_else(|| GlimpseError::InvalidPath(path.clone()))?;
+        (key, effective_config(&db, &session_id)?)
+    };
+    let preview_dir = get_preview_dir(&session_id)?;
+    let output_path = overlay.path_for(&preview_dir, &key);
+
+    tokio::task::spawn_blocking(move || {
+        image_processor::run_with_raw_stack(move || {
+            let image_path = Path::new(&path);
+            let preview = ensure_preview(
+                image_path,
+                &key,
+                &preview_dir,
+                config.preview_size,
+                config.raw_white_balance,
+            )?;
+            // Previews are regenerated when settings change, so an older overlay is stale
+            let source = preview.as_deref().unwrap_or(image_path);
+            let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+            let fresh = modified(&output_path)
+                .zip(modified(source))
+                .is_some_and(|(overlay, source)| overlay >= source);
+            if !fresh {
+                image_processor::generate_overlay(
+                    overlay,
+                    image_path,
+                    preview.as_deref(),
+                    &output_path,
+                    config.preview_size,
+                )?;
+            }
+            Ok(normalize_path(&output_path))
+        })
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Per-channel histogram of an image of the open session
+/// `source` selects the rendered preview (default) or the linear data of a RAW file, whose
+/// clipping shares show what the sensor actually clipped rather than the tone curve
+#[tauri::command]
+pub async fn get_histogram(
+    app: AppHandle,
+    path: String,
+    source: Option<HistogramSource>,
+) -> Result<Histogram> {
+    let source = source.unwrap_or_default();
+    let is_raw = Path::new(&path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(image_processor::is_raw_format);
+    if source == HistogramSource::Raw && !is_raw {
+        return Err(GlimpseError::RawProcessing(format!(
+            "RAW histograms need a RAW file: {}",
+            path
+        )));
+    }
+
+    let state = app.state::<AppState>();
+    let session_id = state.current_session_id()?;
+    let (key, config) = {
+        let db = state.db.lock().unwrap();
+        let session = db
+            .get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        let key = session_key(&session.folder_path, Path::new(&path))
+            .ok_or_else(|| GlimpseError::InvalidPath(path.clone()))?;
+        (key, effective_config(&db, &session_id)?)
+    };
+    let preview_dir = get_preview_dir(&session_id)?;
+
+    tokio::task::spawn_blocking(move || {
+        image_processor::run_with_raw_stack(move || {
+            let image_path = Path::new(&path);
+            if source == HistogramSource::Raw {
+                return histogram::from_raw(image_path);
+            }
+            let preview = ensure_preview(
+                image_path,
+                &key,
+                &preview_dir,
+                config.preview_size,
+                config.raw_white_balance,
+            )?;
+            let img = image_processor::load_displayed_image(
+                image_path,
+                preview.as_deref(),
+                config.preview_size,
+            )?;
+            Ok(histogram::from_image(&img.to_rgb8()))
+        })
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Adjustments stored for an image of the open session
+#[tauri::command]
+pub fn get_image_adjustments(
+    state: State<'_, AppState>,
+    filename: String,
+) -> Result<RawAdjustments> {
+    let session_id = state.current_session_id()?;
+    let db = state.db.lock().unwrap();
+    db.get_adjustments(&session_id, &filename)
+}
+
+#[derive(serde::Serialize)]
+pub struct PreviewResponse {
+    /// None for files that are displayed directly
+    preview_path: Option<String>,
+    /// The path is the camera's embedded preview; the developed one follows in a
+    /// `preview-ready` event
+    pending: bool,
+}
+
+/// Preview of an image of the open session for the detail view
+/// A RAW file without a developed preview gets its embedded JPEG right away, and is developed
+/// in the background (see `prefetch_previews`)
+#[tauri::command]
+pub async fn get_preview(app: AppHandle, filename: String) -> Result<PreviewResponse> {
+    let state = app.state::<AppState>();
+    let session_id = state.current_session_id()?;
+    let (folder_path, config) = {
+        let db = state.db.lock().unwrap();
+        let session = db
+            .get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        (session.folder_path, effective_config(&db, &session_id)?)
+    };
+    let preview_dir = get_preview_dir(&session_id)?;
+    let image_path = Path::new(&folder_path).join(&filename);
+
+    let preview_path = image_processor::preview_path_for(&preview_dir, &filename);
+    let embedded_path = image_processor::embedded_preview_path_for(&preview_dir, &filename);
+    let is_raw = Path::new(&filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(image_processor::is_raw_format);
+    if !is_raw || preview_path.exists() {
+        let _ = std::fs::remove_file(&embedded_path);
+        let path = ensure_preview(
+            &image_path,
+            &filename,
+            &preview_dir,
+            config.preview_size,
+            config.raw_white_balance,
+        )?;
+        return Ok(PreviewResponse {
+            preview_path: path.map(|p| normalize_path(&p)),
+            pending: false,
+        });
+    }
+
+    let embedded = {
+        let embedded_path = embedded_path.clone();
+        tokio::task::spawn_blocking(move || {
+            embedded_path.exists()
+                || image_processor::generate_embedded_preview(
+                    &image_path,
+                    &embedded_path,
+                    config.preview_size,
+                )
+                .is_ok()
+        })
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+    };
+    prefetch_previews(app.clone(), state, vec![filename])?;
+    Ok(PreviewResponse {
+        preview_path: embedded.then(|| normalize_path(&embedded_path)),
+        pending: true,
+    })
+}
+
+/// Generate previews for the given files ahead of navigation
+/// Runs in the background; a `preview-ready` event is emitted per generated preview
+#[tauri::command]
+pub fn prefetch_previews(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    filenames: Vec<String>,
+) -> Result<()> {
+    let session_id = state.current_session_id()?;
+    let (folder_path, config) = {
+        let db = state.db.lock().unwrap();
+        let session = db
+            .get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?;
+        (session.folder_path, effective_config(&db, &session_id)?)
+    };
+    let preview_dir = get_preview_dir(&session_id)?;
+
+    // Skip files another prefetch is already working on
+    let filenames: Vec<String> = {
+        let mut prefetching = state.prefetching.lock().unwrap();
+        filenames
+            .into_iter()
+            .filter(|filename| prefetching.insert(filename.clone()))
+            .collect()
+    };
+    if filenames.is_empty() {
+        return Ok(());
+    }
+
+    let prefetching = Arc::clone(&state.prefetching);
+    let task = state.tasks.start(TaskKind::Prefetch, filenames.len());
+    emit_task(&app, task.info());
+
+    image_processor::spawn_with_raw_stack(move || {
+        let mut filenames = filenames.into_iter().enumerate();
+        while task.checkpoint() {
+            let Some((index, filename)) = filenames.next() else {
+                break;
+            };
+            let image_path = Path::new(&folder_path).join(&filename);
+            match ensure_preview(
+                &image_path,
+                &filename,
+                &preview_dir,
+                config.preview_size,
+                config.raw_white_balance,
+            ) {
+                Ok(Some(path)) => {
+                    let _ = app.emit(
+                        "preview-ready",
+                        PreviewReadyPayload {
+                            filename: filename.clone(),
+                            preview_path: normalize_path(&path),
+                        },
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to prefetch preview for {}: {}", filename, e),
+            }
+            prefetching.lock().unwrap().remove(&filename);
+            task.set_progress(index + 1);
+        }
+
+        // Files left over by a cancel can be prefetched again later
+        let mut prefetching = prefetching.lock().unwrap();
+        for (_, filename) in filenames {
+            prefetching.remove(&filename);
+        }
+        drop(prefetching);
+        emit_task(&app, task.finish());
+    })?;
+
+    Ok(())
+}
+
+#[derive(Clone, serde::Serialize)]
+struct TetherArrivalPayload {
+    image: ImageInfo,
+    thumbnail: ThumbnailResult,
+    /// Newest frame of the batch, to be selected when auto-select is on
+    select: bool,
+}
+
+/// Start tether mode: watch the current session's folders for new files,
+/// generate their thumbnails and push them to the frontend as `tether-arrival` events
+/// Runs until stopped with `stop_tether` or `cancel_task`; returns the task ID
+#[tauri::command]
+pub async fn start_tether(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auto_select: Option<bool>,
+) -> Result<u64> {
+    let session_id = state.current_session_id()?;
+    let (folders, config) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.get_session_folders(&session_id)?,
+            effective_config(&db, &session_id)?,
+        )
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let cache_dir = get_cache_dir(&session_id)?;
+    let preview_dir = get_preview_dir(&session_id)?;
+    let auto_select = auto_select.unwrap_or(true);
+
+    let initial = folders.clone();
+    let mut tracker = ArrivalTracker::new(
+        &scan_resilient(session_root(&folders), move || scan_folders(&initial)).await?,
+    );
+
+    for info in state.tasks.cancel_kind(TaskKind::Tether) {
+        emit_task(&app, info);
+    }
+    let task = state.tasks.start(TaskKind::Tether, 0);
+    let task_id = task.id();
+    emit_task(&app, task.info());
+    tracing::info!("Tethering started for session {}", session_id);
+
+    image_processor::spawn_with_raw_stack(move || {
+        let mut received = 0;
+        while task.checkpoint() {
+            std::thread::sleep(tether::POLL_INTERVAL);
+
+            // The folder may briefly vanish (card swap, network hiccup); keep watching
+            let polled = folders.clone();
+            let images = match scan_guarded(session_root(&folders), move || scan_folders(&polled)) {
+                Ok(images) => images,
+                Err(e) => {
+                    tracing::warn!("Tether scan failed: {}", e);
+                    continue;
+                }
+            };
+
+            let arrived = tracker.poll(images);
+            let newest = arrived.len().saturating_sub(1);
+            for (index, image) in arrived.into_iter().enumerate() {
+                let thumbnail = process_image(
+                    &image,
+                    &cache_dir,
+                    &preview_dir,
+                    config.thumbnail_size,
+                    config.preview_size,
+                    config.raw_white_balance,
+                );
+                record_generation_results(
+                    &app,
+                    &session_id,
+                    std::slice::from_ref(&image),
+                    std::slice::from_ref(&thumbnail),
+                );
+                let _ = app.emit(
+                    "tether-arrival",
+                    TetherArrivalPayload {
+                        image,
+                        thumbnail,
+                        select: auto_select && index == newest,
+                    },
+                );
+                received += 1;
+                task.set_progress(received);
+            }
+        }
+
+        tracing::info!("Tethering stopped after {} new files", received);
+        emit_task(&app, task.finish());
+    })?;
+
+    Ok(task_id)
+}
+
+/// Stop tether mode
+#[tauri::command]
+pub fn stop_tether(app: AppHandle, state: State<'_, AppState>) {
+    for info in state.tasks.cancel_kind(TaskKind::Tether) {
+        emit_task(&app, info);
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct OpenFolderResult {
+    session_id: String,
+    images: Vec<ImageInfo>,
+    labels: Vec<Label>,
+    last_selected_index: i32,
+    cache_dir: String,
+    /// File extension of cached thumbnails (follows the cache format setting)
+    cache_extension: String,
+    subfolders: Vec<SubfolderInfo>,
+    settings: SessionSettings,
+    view_state: ViewState,
+    /// ID of the thumbnail generation task
+    task_id: u64,
+    /// Source folders of the session
+    folders: Vec<String>,
+}
+
+/// Set a label
+#[tauri::command]
+pub fn set_label(
+    state: State<'_, AppState>,
+    filename: String,
+    label: Option<String>,
+) -> Result<()> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    let sync_dir = db.get_session_settings(&session_id)?.sync_dir;
+    set_synced_label(
+        &db,
+        &session_id,
+        sync_dir.as_deref(),
+        &filename,
+        label.as_deref(),
+    )
+}
+
+/// Set a label and append it to the session's sync journal, if it has one
+fn set_synced_label(
+    db: &Database,
+    session_id: &str,
+    sync_dir: Option<&str>,
+    filename: &str,
+    label: Option<&str>,
+) -> Result<()> {
+    db.set_label(session_id, filename, label)?;
+
+    // Sync is best effort; the label is stored locally either way
+    if let Some(sync_dir) = sync_dir {
+        let entry = labelsync::JournalEntry::now(filename, label);
+        if let Err(e) = labelsync::append(Path::new(sync_dir), &entry) {
+            tracing::warn!("Failed to write label sync journal: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Labels of files that look renamed since they were labeled, for the user to confirm
+/// A label is proposed for an unlabeled file with the same size and modification time as
+/// the vanished file it was stored under
+#[tauri::command]
+pub async fn reconcile_session(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<LabelTransfer>> {
+    let folders = {
+        let db = state.db.lock().unwrap();
+        if db.get_session(&session_id)?.is_none() {
+            return Err(GlimpseError::SessionNotFound);
+        }
+        db.get_session_folders(&session_id)?
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+
+    let db = state.db.lock().unwrap();
+    Ok(reconcile::propose_transfers(
+        &images,
+        &db.get_labels(&session_id)?,
+        &db.get_file_identities(&session_id)?,
+    ))
+}
+
+/// Move labels over to the new names the user confirmed; returns the session's labels
+#[tauri::command]
+pub fn transfer_labels(
+    state: State<'_, AppState>,
+    session_id: String,
+    transfers: Vec<LabelTransfer>,
+) -> Result<Vec<Label>> {
+    let db = state.db.lock().unwrap();
+    let sync_dir = db.get_session_settings(&session_id)?.sync_dir;
+    for transfer in &transfers {
+        if !db.transfer_label(&session_id, &transfer.from, &transfer.to)? {
+            continue;
+        }
+        if let Some(sync_dir) = &sync_dir {
+            for entry in [
+                labelsync::JournalEntry::now(&transfer.from, None),
+                labelsync::JournalEntry::now(&transfer.to, Some(&transfer.label)),
+            ] {
+                if let Err(e) = labelsync::append(Path::new(sync_dir), &entry) {
+                    tracing::warn!("Failed to write label sync journal: {}", e);
+                }
+            }
+        }
+    }
+    db.get_labels(&session_id)
+}
+
+/// Rename a session's files on disk from a template (see `rename::render`), taking their
+/// labels and caches along; `undo_rename` takes the last batch back
+#[tauri::command]
+pub async fn rename_files(
+    state: State<'_, AppState>,
+    session_id: String,
+    template: String,
+) -> Result<Vec<Rename>> {
+    let images = session_exif(&state, &session_id).await?;
+    let renames = rename::plan(&images, &template)?;
+    apply_renames(&state, &session_id, &renames, true)?;
+    Ok(renames)
+}
+
+/// Give the files of the last `rename_files` batch their old names back
+/// Returns the renames made to do so
+#[tauri::command]
+pub fn undo_rename(state: State<'_, AppState>, session_id: String) -> Result<Vec<Rename>> {
+    let renames: Vec<Rename> = {
+        let db = state.db.lock().unwrap();
+        db.get_last_renames(&session_id)?
+            .iter()
+            .rev()
+            .map(Rename::inverse)
+            .collect()
+    };
+    apply_renames(&state, &session_id, &renames, false)?;
+    Ok(renames)
+}
+
+/// Rename files on disk, then their rows and cache files
+/// Nothing is renamed when a target exists; files already renamed are put back when a later
+/// rename or the database update fails
+fn apply_renames(
+    state: &AppState,
+    session_id: &str,
+    renames: &[Rename],
+    undoable: bool,
+) -> Result<()> {
+    let root = {
+        let db = state.db.lock().unwrap();
+        db.get_session(session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?
+            .folder_path
+    };
+    let root = Path::new(&root);
+    if let Some(rename) = renames.iter().find(|r| root.join(&r.to).exists()) {
+        return Err(GlimpseError::RenameConflict(rename.to.clone()));
+    }
+
+    let roll_back = |done: &[Rename]| {
+        for rename in done.iter().rev() {
+            if let Err(e) = std::fs::rename(root.join(&rename.to), root.join(&rename.from)) {
+                tracing::error!("Failed to restore {}: {}", rename.from, e);
+            }
+        }
+    };
+    for (index, rename) in renames.iter().enumerate() {
+        let from = root.join(&rename.from);
+        if let Err(e) = std::fs::rename(&from, root.join(&rename.to)) {
+            roll_back(&renames[..index]);
+            return Err(GlimpseError::io_at(&from, e));
+        }
+    }
+    let result = state
+        .db
+        .lock()
+        .unwrap()
+        .apply_renames(session_id, renames, undoable);
+    if let Err(e) = result {
+        roll_back(renames);
+        return Err(e);
+    }
+
+    // Missing cache files are simply generated again under the new name
+    let cache_dir = get_cache_dir(session_id)?;
+    let preview_dir = get_preview_dir(session_id)?;
+    for rename in renames {
+        let _ = std::fs::rename(
+            image_processor::thumbnail_path_for(&cache_dir, &rename.from),
+            image_processor::thumbnail_path_for(&cache_dir, &rename.to),
+        );
+        let _ = std::fs::rename(
+            image_processor::preview_path_for(&preview_dir, &rename.from),
+            image_processor::preview_path_for(&preview_dir, &rename.to),
+        );
+    }
+    Ok(())
+}
+
+/// Move files of the open session to another folder, with their labels and caches, which
+/// then belong to that folder's session; emits `files-moved`
+/// Files whose name is taken in the destination are left where they are
+#[tauri::command]
+pub async fn move_files(
+    app: AppHandle,
+    filenames: Vec<String>,
+    destination: String,
+) -> Result<FilesMoved> {
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let source_session_id = state.current_session_id()?;
+        let destination_session_id =
+            generate_folders_session_id(std::slice::from_ref(&destination));
+        if destination_session_id == source_session_id {
+            return Err(GlimpseError::InvalidPath(
+                "Files are already in this folder".into(),
+            ));
+        }
+        let root = {
+            let db = state.db.lock().unwrap();
+            if db.get_session(&destination_session_id)?.is_none() {
+                db.upsert_session(&Session {
+                    id: destination_session_id.clone(),
+                    folder_path: destination.clone(),
+                    last_opened: None,
+                    last_selected_index: 0,
+                    total_files: 0,
+                })?;
+            }
+            db.get_session(&source_session_id)?
+                .ok_or(GlimpseError::SessionNotFound)?
+                .folder_path
+        };
+        let destination_dir = PathBuf::from(&destination);
+        std::fs::create_dir_all(&destination_dir)
+            .map_err(|e| GlimpseError::io_at(&destination_dir, e))?;
+
+        let mut moves = Vec::new();
+        let mut failed = 0;
+        for filename in &filenames {
+            let src = Path::new(&root).join(filename);
+            let Some(name) = src.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                failed += 1;
+                continue;
+            };
+            let dst = destination_dir.join(&name);
+            if dst.exists() {
+                failed += 1;
+                continue;
+            }
+            match move_file(&src, &dst) {
+                Ok(()) => moves.push((filename.clone(), name)),
+                Err(e) => {
+                    tracing::warn!("Failed to move {}: {}", src.display(), e);
+                    failed += 1;
+                }
+            }
+        }
+
+        state.db.lock().unwrap().move_to_session(
+            &source_session_id,
+            &destination_session_id,
+            &moves,
+        )?;
+        // Cache files follow on a best-effort basis; missing ones are generated again
+        let dirs = [
+            (
+                get_cache_dir(&source_session_id)?,
+                get_cache_dir(&destination_session_id)?,
+            ),
+            (
+                get_preview_dir(&source_session_id)?,
+                get_preview_dir(&destination_session_id)?,
+            ),
+        ];
+        for (from, to) in &moves {
+            let _ = std::fs::rename(
+                image_processor::thumbnail_path_for(&dirs[0].0, from),
+                image_processor::thumbnail_path_for(&dirs[0].1, to),
+            );
+            let _ = std::fs::rename(
+                image_processor::preview_path_for(&dirs[1].0, from),
+                image_processor::preview_path_for(&dirs[1].1, to),
+            );
+        }
+
+        let result = FilesMoved {
+            source_session_id,
+            destination_session_id,
+            moved: moves.into_iter().map(|(from, _)| from).collect(),
+            failed,
+        };
+        let _ = app.emit("files-moved", result.clone());
+        Ok(result)
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Delete a file of the open session, to the trash or permanently, together with its
+/// thumbnail, preview and everything stored about it; emits `file-deleted`
+#[tauri::command]
+pub fn delete_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    filename: String,
+    to_trash: bool,
+) -> Result<()> {
+    let session_id = state.current_session_id()?;
+    let root = {
+        let db = state.db.lock().unwrap();
+        db.get_session(&session_id)?
+            .ok_or(GlimpseError::SessionNotFound)?
+            .folder_path
+    };
+    let path = Path::new(&root).join(&filename);
+
+    // Journaled so a crash between deleting the file and forgetting it can be finished later
+    let source = normalize_path(&path);
+    let operation_id = state.db.lock().unwrap().begin_operation(
+        &session_id,
+        OperationKind::Delete,
+        if to_trash { "trash" } else { "" },
+        None,
+        &[(source.clone(), filename.clone())],
+    )?;
+    let deleted = if to_trash {
+        trash::move_to_trash(&path)
+    } else {
+        std::fs::remove_file(&path).map_err(|e| GlimpseError::io_at(&path, e))
+    };
+    if let Err(e) = deleted {
+        state.db.lock().unwrap().finish_operation(operation_id)?;
+        return Err(e);
+    }
+    state.db.lock().unwrap().set_operation_file_state(
+        operation_id,
+        &source,
+        OperationFileState::Done,
+    )?;
+
+    image_processor::remove_image_cache(&session_id, &filename)?;
+    {
+        let db = state.db.lock().unwrap();
+        db.forget_file(&session_id, &filename)?;
+        db.finish_operation(operation_id)?;
+    }
+
+    let _ = app.emit(
+        "file-deleted",
+        FileDeletedPayload {
+            session_id,
+            filename,
+            to_trash,
+        },
+    );
+    Ok(())
+}
+
+/// Move a file, copying it when the destination is on another volume
+fn move_file(src: &Path, dst: &Path) -> Result<()> {
+    if std::fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = std::fs::copy(src, dst) {
+        let _ = std::fs::remove_file(dst);
+        return Err(GlimpseError::io_at(src, e));
+    }
+    std::fs::remove_file(src).map_err(|e| GlimpseError::io_at(src, e))
+}
+
+/// Apply label changes other machines wrote to the session's sync folder
+fn merge_synced_labels(db: &Database, session_id: &str) -> Result<usize> {
+    let Some(sync_dir) = db.get_session_settings(session_id)?.sync_dir else {
+        return Ok(0);
+    };
+    let journal = labelsync::read_journals(Path::new(&sync_dir))?;
+    let changes = labelsync::remote_changes(
+        &journal,
+        &db.get_label_times(session_id)?,
+        labelsync::machine_id(),
+    );
+    for (filename, label) in &changes {
+        db.set_label(session_id, filename, label.as_deref())?;
+    }
+    if !changes.is_empty() {
+        tracing::info!("Merged {} synced label changes", changes.len());
+    }
+    Ok(changes.len())
+}
+
+/// Get the pinned candidates of the current session for side-by-side comparison
+#[tauri::command]
+pub fn get_compare_set(state: State<'_, AppState>) -> Result<Vec<String>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.get_compare_set(&session_id)
+}
+
+/// Pin files to the compare set of the current session; returns the updated set
+#[tauri::command]
+pub fn add_to_compare_set(
+    state: State<'_, AppState>,
+    filenames: Vec<String>,
+) -> Result<Vec<String>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.add_to_compare_set(&session_id, &filenames)?;
+    db.get_compare_set(&session_id)
+}
+
+/// Unpin files from the compare set of the current session; returns the updated set
+#[tauri::command]
+pub fn remove_from_compare_set(
+    state: State<'_, AppState>,
+    filenames: Vec<String>,
+) -> Result<Vec<String>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.remove_from_compare_set(&session_id, &filenames)?;
+    db.get_compare_set(&session_id)
+}
+
+/// Unpin everything in the compare set of the current session
+#[tauri::command]
+pub fn clear_compare_set(state: State<'_, AppState>) -> Result<()> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.clear_compare_set(&session_id)
+}
+
+/// Bookmarked folder, with whether it can be opened right now (e.g. the card is inserted)
+#[derive(serde::Serialize)]
+pub struct BookmarkEntry {
+    #[serde(flatten)]
+    bookmark: Bookmark,
+    available: bool,
+}
+
+fn bookmark_entries(db: &Database) -> Result<Vec<BookmarkEntry>> {
+    Ok(db
+        .get_bookmarks()?
+        .into_iter()
+        .map(|bookmark| BookmarkEntry {
+            available: Path::new(&bookmark.path).is_dir(),
+            bookmark,
+        })
+        .collect())
+}
+
+/// List the bookmarked folders
+#[tauri::command]
+pub fn list_bookmarks(state: State<'_, AppState>) -> Result<Vec<BookmarkEntry>> {
+    let db = state.db.lock().unwrap();
+    bookmark_entries(&db)
+}
+
+/// Bookmark a folder, named after it unless `name` is given; returns the updated list
+#[tauri::command]
+pub fn add_bookmark(
+    state: State<'_, AppState>,
+    path: String,
+    name: Option<String>,
+) -> Result<Vec<BookmarkEntry>> {
+    let folder = Path::new(&path);
+    if !folder.is_dir() {
+        return Err(GlimpseError::InvalidPath(path));
+    }
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| folder.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| path.clone());
+
+    let db = state.db.lock().unwrap();
+    db.add_bookmark(&normalize_path(folder), name.trim())?;
+    bookmark_entries(&db)
+}
+
+/// Rename a bookmark; returns the updated list
+#[tauri::command]
+pub fn rename_bookmark(
+    state: State<'_, AppState>,
+    path: String,
+    name: String,
+) -> Result<Vec<BookmarkEntry>> {
+    if name.trim().is_empty() {
+        return Err(GlimpseError::InvalidConfig(
+            "Bookmark name can't be empty".to_string(),
+        ));
+    }
+    let db = state.db.lock().unwrap();
+    if !db.rename_bookmark(&path, name.trim())? {
+        return Err(GlimpseError::InvalidPath(path));
+    }
+    bookmark_entries(&db)
+}
+
+/// Remove a bookmark; returns the updated list
+#[tauri::command]
+pub fn remove_bookmark(state: State<'_, AppState>, path: String) -> Result<Vec<BookmarkEntry>> {
+    let db = state.db.lock().unwrap();
+    db.remove_bookmark(&path)?;
+    bookmark_entries(&db)
+}
+
+/// Order bookmarks like `paths` (e.g. after dragging one); returns the updated list
+#[tauri::command]
+pub fn reorder_bookmarks(
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<BookmarkEntry>> {
+    let db = state.db.lock().unwrap();
+    db.reorder_bookmarks(&paths)?;
+    bookmark_entries(&db)
+}
+
+/// List the culling passes of the current session
+#[tauri::command]
+pub fn list_passes(state: State<'_, AppState>) -> Result<Vec<CullingPass>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.get_passes(&session_id)
+}
+
+/// Add a culling pass to the current session, optionally starting from the active pass's labels
+#[tauri::command]
+pub fn create_pass(
+    state: State<'_, AppState>,
+    name: String,
+    copy_labels: Option<bool>,
+) -> Result<()> {
+    let session_id = state.current_session_id()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(GlimpseError::InvalidConfig(
+            "Pass name must not be empty".to_string(),
+        ));
+    }
+
+    let db = state.db.lock().unwrap();
+    db.create_pass(&session_id, name, copy_labels.unwrap_or(false))
+}
+
+/// Make another pass active; returns its labels
+#[tauri::command]
+pub fn switch_pass(state: State<'_, AppState>, name: String) -> Result<Vec<Label>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.switch_pass(&session_id, &name)?;
+    db.get_labels(&session_id)
+}
+
+/// Files labeled differently in two passes of the current session
+#[tauri::command]
+pub fn compare_passes(
+    state: State<'_, AppState>,
+    a: String,
+    b: String,
+) -> Result<Vec<PassDifference>> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    Ok(compare_labels(
+        &db.get_pass_labels(&session_id, &a)?,
+        &db.get_pass_labels(&session_id, &b)?,
+    ))
+}
+
+/// Get settings of the current session
+#[tauri::command]
+pub fn get_session_settings(state: State<'_, AppState>) -> Result<SessionSettings> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.get_session_settings(&session_id)
+}
+
+/// Save settings of the current session
+/// Changing the session's preview size deletes its previews made at the old size
+#[tauri::command]
+pub fn set_session_settings(state: State<'_, AppState>, settings: SessionSettings) -> Result<()> {
+    if let Some(preview_size) = settings.preview_size {
+        config::validate_preview_size(preview_size)?;
+    }
+    if let Some(sync_dir) = &settings.sync_dir {
+        if !Path::new(sync_dir).is_dir() {
+            return Err(GlimpseError::InvalidPath(sync_dir.clone()));
+        }
+    }
+
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    let previous = db.get_session_settings(&session_id)?;
+    db.set_session_settings(&session_id, &settings)?;
+
+    // Previews made at the old size are dropped and rendered again on demand
+    let default_size = config::get_config().preview_size;
+    if previous.preview_size.unwrap_or(default_size)
+        != settings.preview_size.unwrap_or(default_size)
+    {
+        let preview_dir = get_preview_dir(&session_id)?;
+        std::thread::spawn(move || {
+            let removed = image_processor::remove_sized_files_in(&preview_dir);
+            tracing::info!("Removed {} previews after a preview size change", removed);
+        });
+    }
+    Ok(())
+}
+
+/// Change the white balance RAW previews of the open session are developed with
+/// None follows the global setting. Cached RAW previews are dropped and the ones that were
+/// cached are developed again in the background (see `prefetch_previews`)
+#[tauri::command]
+pub async fn set_session_white_balance(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    mode: Option<WhiteBalance>,
+) -> Result<usize> {
+    let session_id = state.current_session_id()?;
+    {
+        let db = state.db.lock().unwrap();
+        let settings = db.get_session_settings(&session_id)?;
+        if settings.white_balance == mode {
+            return Ok(0);
+        }
+        db.set_session_settings(
+            &session_id,
+            &SessionSettings {
+                white_balance: mode,
+                ..settings
+            },
+        )?;
+    }
+    refresh_raw_previews(app, state).await
+}
+
+/// Drop the RAW previews of the open session, developed with settings that since changed,
+/// and render them again; returns how many there were
+async fn refresh_raw_previews(app: AppHandle, state: State<'_, AppState>) -> Result<usize> {
+    let session_id = state.current_session_id()?;
+    let folders = state.db.lock().unwrap().get_session_folders(&session_id)?;
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let preview_dir = get_preview_dir(&session_id)?;
+    let images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+    let mut stale = Vec::new();
+    for image in images {
+        let is_raw = Path::new(&image.filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(image_processor::is_raw_format);
+        if !is_raw {
+            continue;
+        }
+        let _ = std::fs::remove_file(image_processor::adjusted_preview_path_for(
+            &preview_dir,
+            &image.filename,
+        ));
+        if std::fs::remove_file(image_processor::preview_path_for(
+            &preview_dir,
+            &image.filename,
+        ))
+        .is_ok()
+        {
+            stale.push(image.filename);
+        }
+    }
+
+    let count = stale.len();
+    prefetch_previews(app, state, stale)?;
+    Ok(count)
+}
+
+/// Move the thumbnail generation focus to `center_index` (e.g. the middle of the visible rows)
+#[tauri::command]
+pub fn set_generation_priority(state: State<'_, AppState>, center_index: usize) {
+    if let Some(queue) = state.generation_queue.lock().unwrap().as_ref() {
+        queue.set_center(center_index);
+    }
+}
+
+/// Save selection position
+#[tauri::command]
+pub fn save_selection(state: State<'_, AppState>, index: i32) -> Result<()> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.update_last_selected(&session_id, index)
+}
+
+/// Save filter, sort order and grid zoom of the current session
+#[tauri::command]
+pub fn save_view_state(state: State<'_, AppState>, view_state: ViewState) -> Result<()> {
+    let session_id = state.current_session_id()?;
+
+    let db = state.db.lock().unwrap();
+    db.set_view_state(&session_id, &view_state)
+}
+
+/// Export adopted files
+/// Runs as a task so the frontend can pause or cancel it while it copies
+/// With `export_new_only`, files an earlier export already wrote to the destination are
+/// skipped unless they changed since (e.g. delivering late picks); `gear` narrows the export
+/// to frames of one camera body or lens (e.g. only the second shooter's)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_adopted(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    source_folder: String,
+    destination_folder: String,
+    mode: String,
+    options: Option<ExportOptions>,
+    export_new_only: Option<bool>,
+    gear: Option<GearFilter>,
+) -> Result<ExportResult> {
+    let options = options.unwrap_or_default();
+    let gear = gear.unwrap_or_default();
+    let new_only = export_new_only.unwrap_or(false);
+    let session_id = state.current_session_id()?;
+
+    // Get rejected labels
+    let rejected_files: std::collections::HashSet<String> = {
+        let db = state.db.lock().unwrap();
+        db.get_labels(&session_id)?
+            .into_iter()
+            .filter(|l| l.label.as_deref() == Some("rejected"))
+            .map(|l| l.filename)
+            .collect()
+    };
+
+    // Scan files in folder (all source folders for merged sessions)
+    let folders = {
+        let db = state.db.lock().unwrap();
+        db.get_session_folders(&session_id)?
+    };
+    let images = if folders.len() > 1 {
+        let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+        scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?
+    } else {
+        let source = PathBuf::from(&source_folder);
+        scan_resilient(source.clone(), move || scan_folder(&source)).await?
+    };
+    let total = images.len();
+    let to_export: Vec<ImageInfo> = images
+        .into_iter()
+        .filter(|image| !rejected_files.contains(&image.filename))
+        .collect();
+    let to_export = filter_by_gear(&state, &session_id, to_export, &gear).await?;
+    let selected = to_export.len();
+
+    let batch = ExportBatch {
+        session_id: session_id.clone(),
+        images: to_export,
+        destination: PathBuf::from(&destination_folder),
+    };
+    let (outcomes, cancelled) = export_images(
+        &app,
+        &state,
+        vec![batch],
+        mode == "move",
+        &options,
+        None,
+        new_only,
+    )
+    .await?;
+
+    let result = outcomes[0].into_result(total, total - selected, cancelled);
+    let settings = ExportSettings {
+        destination: destination_folder,
+        mode: if mode == "move" {
+            ExportMode::Move
+        } else {
+            ExportMode::Copy
+        },
+        filter: ExportFilter::NotRejected,
+        rename_template: None,
+        seq_order: SequenceOrder::default(),
+        gear,
+        new_only,
+        options,
+    };
+    let db = state.db.lock().unwrap();
+    db.add_export_record(&session_id, None, &settings, &result)?;
+    Ok(result)
+}
+
+/// Files of one session exported into one folder
+struct ExportBatch {
+    session_id: String,
+    images: Vec<ImageInfo>,
+    destination: PathBuf,
+}
+
+/// What became of the files of an export batch
+#[derive(Debug, Clone, Copy, Default)]
+struct BatchOutcome {
+    copied: usize,
+    failed: usize,
+    /// Left out by `new_only`: exported to the same folder before and unchanged since
+    unchanged: usize,
+}
+
+impl BatchOutcome {
+    fn into_result(self, total: usize, skipped: usize, cancelled: bool) -> ExportResult {
+        ExportResult {
+            total,
+            copied: self.copied,
+            skipped,
+            unchanged: self.unchanged,
+            failed: self.failed,
+            cancelled,
+        }
+    }
+}
+
+/// File an export writes
+struct PlannedFile {
+    batch: usize,
+    operation_id: Option<i64>,
+    src: PathBuf,
+    dst: PathBuf,
+    identity: FileIdentity,
+    /// XMP sidecar content written next to `dst`
+    sidecar: Option<String>,
+    /// Metadata corrections written into `dst`, a JPEG
+    edits: Option<MetadataEdits>,
+}
+
+/// Copy (or move) batches of images into their folders (subfolders with `keep_folders`),
+/// named with `rename` (template and numbering order) if given
+/// With `new_only`, files exported to the same folder before are left out unless they changed
+/// or their copy is gone. Runs as one export task; returns what became of each batch's files
+/// and whether the export was cancelled
+async fn export_images(
+    app: &AppHandle,
+    state: &AppState,
+    batches: Vec<ExportBatch>,
+    is_move: bool,
+    options: &ExportOptions,
+    rename: Option<(&str, SequenceOrder)>,
+    new_only: bool,
+) -> Result<(Vec<BatchOutcome>, bool)> {
+    if let Some(small_jpeg) = &options.small_jpeg {
+        small_jpeg.validate()?;
+    }
+    if is_move && options.re_encodes() {
+        return Err(GlimpseError::InvalidConfig(
+            "Exports that rotate, resize, watermark or convert images copy; the originals can't be moved"
+                .to_string(),
+        ));
+    }
+    if let Some(watermark) = &options.watermark {
+        // A missing logo or font fails the export rather than every file
+        watermark.validate()?;
+        watermark.render(64)?;
+    }
+    // Renamed files are numbered in capture order across all batches; names are given
+    // before `new_only` leaves files out, so a file keeps its name between runs
+    let mut names = match rename {
+        Some((template, order)) => {
+            let mut files = Vec::new();
+            for batch in &batches {
+                let mut exif: HashMap<String, ExifInfo> = session_exif(state, &batch.session_id)
+                    .await?
+                    .into_iter()
+                    .map(|(image, exif)| (image.filename, exif))
+                    .collect();
+                files.extend(batch.images.iter().map(|image| {
+                    let exif = exif.remove(&image.filename).unwrap_or_default();
+                    (image.clone(), exif)
+                }));
+            }
+            Some(rename::export_names(&files, template, order)?.into_iter())
+        }
+        None => None,
+    };
+
+    let credits = config::get_export_credits();
+    let mut outcomes = vec![BatchOutcome::default(); batches.len()];
+    let mut used_names: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    let mut exported: HashMap<&Path, HashMap<String, FileIdentity>> = HashMap::new();
+    let mut planned: Vec<PlannedFile> = Vec::new();
+    for (batch_index, batch) in batches.iter().enumerate() {
+        let exported = match exported.entry(&batch.destination) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let files = if new_only {
+                    let db = state.db.lock().unwrap();
+                    db.get_exported_files(&normalize_path(&batch.destination))?
+                } else {
+                    HashMap::new()
+                };
+                entry.insert(files)
+            }
+        };
+        let corrections = batch_corrections(state, &batch.session_id).await?;
+        let labels: HashMap<String, Option<String>> = match options.xmp_sidecar {
+            Some(_) => {
+                let db = state.db.lock().unwrap();
+                db.get_labels(&batch.session_id)?
+                    .into_iter()
+                    .map(|l| (l.filename, l.label))
+                    .collect()
+            }
+            None => HashMap::new(),
+        };
+
+        for image in &batch.images {
+            let name = names.as_mut().and_then(Iterator::next);
+            let key = export::output_name(name.as_deref().unwrap_or(&image.filename), options);
+            let (folder, key) = if options.keep_folders {
+                let file_name = Path::new(&key).file_name().unwrap_or_default();
+                (
+                    batch
+                        .destination
+                        .join(export::relative_folder(&image.filename)),
+                    file_name.to_string_lossy().to_string(),
+                )
+            } else {
+                (batch.destination.clone(), key)
+            };
+            let used_names = used_names.entry(folder.clone()).or_default();
+            let dst = folder.join(export_file_name(&key, used_names));
+            let src = PathBuf::from(&image.path);
+            let identity = FileIdentity {
+                size: image.size,
+                modified_at: image.modified_at.clone(),
+            };
+            if exported.get(&normalize_path(&src)) == Some(&identity) && dst.exists() {
+                outcomes[batch_index].unchanged += 1;
+                continue;
+            }
+            // Checked on the written file: the small JPEG mode turns RAW files into JPEGs
+            let is_raw = dst
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(image_processor::is_raw_format);
+            let edits = corrections.get(&image.filename);
+            // RAW files only take corrections through a sidecar, so it's written for them anyway
+            let sidecar = options
+                .xmp_sidecar
+                .clone()
+                .or_else(|| edits.map(|_| XmpSidecar::default()))
+                .filter(|_| is_raw)
+                .map(|sidecar| {
+                    let label = labels.get(&image.filename).cloned().flatten();
+                    sidecar.render(label.as_deref(), edits)
+                });
+            // JPEGs also get the artist and copyright from the settings
+            let edits = Some(edits.cloned().unwrap_or_default().or(&credits))
+                .filter(|edits| export::is_jpeg(&dst) && !edits.is_empty());
+            planned.push(PlannedFile {
+                batch: batch_index,
+                operation_id: None,
+                src,
+                dst,
+                identity,
+                sidecar,
+                edits,
+            });
+        }
+    }
+
+    // Fail early if a destination can't hold its files (or doesn't answer)
+    let mut export_files: HashMap<&Path, Vec<(PathBuf, u64)>> = batches
+        .iter()
+        .map(|batch| (batch.destination.as_path(), Vec::new()))
+        .collect();
+    for file in &planned {
+        export_files
+            .get_mut(batches[file.batch].destination.as_path())
+            .unwrap()
+            .push((file.src.clone(), file.identity.size));
+    }
+    for (destination, files) in export_files {
+        netio::with_timeout(destination, netio::PROBE_TIMEOUT, {
+            let destination = destination.to_path_buf();
+            move || {
+                // Source folders are checked once; a session rarely spans more than a few
+                let mut same_volume: HashMap<PathBuf, bool> = HashMap::new();
+                let bytes = disk::export_bytes(files.iter().map(|(src, size)| {
+                    let folder = src.parent().unwrap_or(src).to_path_buf();
+                    let freed = is_move
+                        && *same_volume
+                            .entry(folder)
+                            .or_insert_with(|| disk::same_volume(src, &destination));
+                    (*size, freed)
+                }));
+                disk::ensure_space(&destination, bytes)
+            }
+        })?;
+
+        // Create destination folder
+        netio::with_retry(destination, || {
+            std::fs::create_dir_all(destination).map_err(|e| GlimpseError::io_at(destination, e))
+        })?;
+    }
+    for folder in used_names.keys() {
+        netio::with_retry(folder, || {
+            std::fs::create_dir_all(folder).map_err(|e| GlimpseError::io_at(folder, e))
+        })?;
+    }
+
+    // Moves are journaled first so a crash midway can be resumed or rolled back,
+    // one operation per batch
+    let mut operation_ids = Vec::new();
+    if is_move {
+        for (batch_index, batch) in batches.iter().enumerate() {
+            let journaled: Vec<(String, String)> = planned
+                .iter()
+                .filter(|file| file.batch == batch_index)
+                .map(|file| (normalize_path(&file.src), normalize_path(&file.dst)))
+                .collect();
+            if journaled.is_empty() {
+                continue;
+            }
+            let params = serde_json::to_string(options).ok();
+            let db = state.db.lock().unwrap();
+            let id = db.begin_operation(
+                &batch.session_id,
+                OperationKind::ExportMove,
+                &batch.destination.to_string_lossy(),
+                params.as_deref(),
+                &journaled,
+            )?;
+            operation_ids.push(id);
+            for file in planned.iter_mut().filter(|file| file.batch == batch_index) {
+                file.operation_id = Some(id);
+            }
+        }
+    }
+    let destinations: Vec<String> = batches
+        .iter()
+        .map(|batch| normalize_path(&batch.destination))
+        .collect();
+
+    let task = state.tasks.start(TaskKind::Export, planned.len());
+    emit_task(app, task.info());
+
+    // Copy on a blocking thread; the task may sit paused between files
+    let journal_app = app.clone();
+    let options = options.clone();
+    let (outcomes, task) = tokio::task::spawn_blocking(move || {
+        let state = journal_app.state::<AppState>();
+        let journal = |operation_id: Option<i64>, src: &Path, file_state: OperationFileState| {
+            if let Some(id) = operation_id {
+                let db = state.db.lock().unwrap();
+                if let Err(e) = db.set_operation_file_state(id, &normalize_path(src), file_state) {
+                    tracing::warn!("Failed to update operation journal: {}", e);
+                }
+            }
+        };
+
+        for (index, file) in planned.iter().enumerate() {
+            if !task.checkpoint() {
+                break;
+            }
+            let (src, dst) = (&file.src, &file.dst);
+
+            // Shares that drop out briefly are retried per file
+            let result = netio::with_retry(src, || export::export_file(src, dst, &options))
+                .and_then(|_| match &file.edits {
+                    Some(edits) => metadata::write_jpeg(dst, edits),
+                    None => Ok(()),
+                })
+                .and_then(|_| {
+                    if let Some(sidecar) = &file.sidecar {
+                        if let Err(e) = xmp::write_sidecar(dst, sidecar) {
+                            tracing::warn!("Failed to write XMP sidecar: {}", e);
+                        }
+                    }
+                    // Move mode: write first, then delete original
+                    if is_move {
+                        journal(file.operation_id, src, OperationFileState::Copied);
+                        netio::with_retry(src, || {
+                            std::fs::remove_file(src).map_err(|e| GlimpseError::io_at(src, e))
+                        })?;
+                        journal(file.operation_id, src, OperationFileState::Done);
+                    }
+                    Ok(())
+                });
+
+            let outcome = &mut outcomes[file.batch];
+            match result {
+                Ok(_) => {
+                    outcome.copied += 1;
+                    let db = state.db.lock().unwrap();
+                    if let Err(e) = db.record_exported_file(
+                        &destinations[file.batch],
+                        &normalize_path(src),
+                        &file.identity,
+                    ) {
+                        tracing::warn!("Failed to record exported file: {}", e);
+                    }
+                }
+                Err(_) => outcome.failed += 1,
+            }
+            task.set_progress(index + 1);
+        }
+
+        (outcomes, task)
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+
+    let cancelled = task.is_cancelled();
+    emit_task(app, task.finish());
+    for id in operation_ids {
+        state.db.lock().unwrap().finish_operation(id)?;
+    }
+
+    Ok((outcomes, cancelled))
+}
+
+/// Metadata corrections a session's exported files get, by file name; files whose capture
+/// time was shifted get the shifted date
+async fn batch_corrections(
+    state: &AppState,
+    session_id: &str,
+) -> Result<HashMap<String, MetadataEdits>> {
+    let (mut edits, offsets) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.get_metadata_edits(session_id)?,
+            db.get_time_offsets(session_id)?,
+        )
+    };
+    if !offsets.is_empty() {
+        for (image, exif) in session_exif(state, session_id).await? {
+            if offsets.contains_key(&image.filename) && exif.date_taken.is_some() {
+                edits.entry(image.filename).or_default().date_taken = exif.date_taken;
+            }
+        }
+    }
+    Ok(edits)
+}
+
+/// Label progress of a project: each session and the sum over all of them
+#[derive(serde::Serialize)]
+pub struct ProjectStats {
+    sessions: Vec<SessionLabelStats>,
+    total_files: i64,
+    adopted: i64,
+    rejected: i64,
+    unlabeled: i64,
+}
+
+fn project_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(GlimpseError::InvalidConfig(
+            "Project name can't be empty".to_string(),
+        ));
+    }
+    Ok(name)
+}
+
+fn ensure_sessions_exist(db: &Database, session_ids: &[String]) -> Result<()> {
+    for session_id in session_ids {
+        if db.get_session(session_id)?.is_none() {
+            return Err(GlimpseError::SessionNotFound);
+        }
+    }
+    Ok(())
+}
+
+fn get_project(db: &Database, project_id: i64) -> Result<Project> {
+    db.get_project(project_id)?
+        .ok_or(GlimpseError::ProjectNotFound(project_id))
+}
+
+/// List all projects
+#[tauri::command]
+pub fn list_projects(state: State<'_, AppState>) -> Result<Vec<Project>> {
+    let db = state.db.lock().unwrap();
+    db.get_projects()
+}
+
+/// Group sessions into a new project
+#[tauri::command]
+pub fn create_project(
+    state: State<'_, AppState>,
+    name: String,
+    session_ids: Vec<String>,
+) -> Result<Project> {
+    let name = project_name(&name)?;
+    let db = state.db.lock().unwrap();
+    ensure_sessions_exist(&db, &session_ids)?;
+    let project_id = db.create_project(name)?;
+    db.set_project_sessions(project_id, &session_ids)?;
+    get_project(&db, project_id)
+}
+
+/// Rename a project
+#[tauri::command]
+pub fn rename_project(
+    state: State<'_, AppState>,
+    project_id: i64,
+    name: String,
+) -> Result<Project> {
+    let name = project_name(&name)?;
+    let db = state.db.lock().unwrap();
+    if !db.rename_project(project_id, name)? {
+        return Err(GlimpseError::ProjectNotFound(project_id));
+    }
+    get_project(&db, project_id)
+}
+
+/// Replace the sessions of a project (e.g. after adding the third card)
+#[tauri::command]
+pub fn set_project_sessions(
+    state: State<'_, AppState>,
+    project_id: i64,
+    session_ids: Vec<String>,
+) -> Result<Project> {
+    let db = state.db.lock().unwrap();
+    get_project(&db, project_id)?;
+    ensure_sessions_exist(&db, &session_ids)?;
+    db.set_project_sessions(project_id, &session_ids)?;
+    get_project(&db, project_id)
+}
+
+/// Delete a project; its sessions and their labels stay
+#[tauri::command]
+pub fn delete_project(state: State<'_, AppState>, project_id: i64) -> Result<()> {
+    let db = state.db.lock().unwrap();
+    db.delete_project(project_id)
+}
+
+/// Adopted, rejected and unlabeled counts over all sessions of a project
+#[tauri::command]
+pub fn get_project_stats(state: State<'_, AppState>, project_id: i64) -> Result<ProjectStats> {
+    let db = state.db.lock().unwrap();
+    get_project(&db, project_id)?;
+    let sessions = db.get_project_label_stats(project_id)?;
+    Ok(ProjectStats {
+        total_files: sessions.iter().map(|s| s.total_files).sum(),
+        adopted: sessions.iter().map(|s| s.adopted).sum(),
+        rejected: sessions.iter().map(|s| s.rejected).sum(),
+        unlabeled: sessions.iter().map(|s| s.unlabeled).sum(),
+        sessions,
+    })
+}
+
+/// Export the files not rejected in any session of a project into one folder
+/// Like `export_adopted`, as a single task; names that clash across sessions get numbered
+#[tauri::command]
+pub async fn export_project(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    project_id: i64,
+    destination_folder: String,
+    mode: String,
+    options: Option<ExportOptions>,
+    export_new_only: Option<bool>,
+) -> Result<ExportResult> {
+    let options = options.unwrap_or_default();
+    let session_ids = {
+        let db = state.db.lock().unwrap();
+        get_project(&db, project_id)?.session_ids
+    };
+
+    let mut total = 0;
+    let mut batches = Vec::new();
+    for session_id in session_ids {
+        let (count, to_export) =
+            export_selection(&state, &session_id, ExportFilter::NotRejected).await?;
+        total += count;
+        batches.push(ExportBatch {
+            session_id,
+            images: to_export,
+            destination: PathBuf::from(&destination_folder),
+        });
+    }
+    let selected: usize = batches.iter().map(|batch| batch.images.len()).sum();
+
+    let (outcomes, cancelled) = export_images(
+        &app,
+        &state,
+        batches,
+        mode == "move",
+        &options,
+        None,
+        export_new_only.unwrap_or(false),
+    )
+    .await?;
+
+    let outcome = outcomes
+        .iter()
+        .fold(BatchOutcome::default(), |sum, outcome| BatchOutcome {
+            copied: sum.copied + outcome.copied,
+            failed: sum.failed + outcome.failed,
+            unchanged: sum.unchanged + outcome.unchanged,
+        });
+    Ok(outcome.into_result(total, total - selected, cancelled))
+}
+
+/// Scan a session's folders for the files an export with `filter` writes
+/// Returns the number of files in the session along with them
+async fn export_selection(
+    state: &AppState,
+    session_id: &str,
+    filter: ExportFilter,
+) -> Result<(usize, Vec<ImageInfo>)> {
+    let files = labeled_files(state, session_id).await?;
+    let total = files.len();
+    let selected = files
+        .into_iter()
+        .filter(|(_, label)| filter.includes(label.as_deref()))
+        .map(|(image, _)| image)
+        .collect();
+    Ok((total, selected))
+}
+
+/// Leave out the images not taken with the gear of `filter`, going by the cached EXIF data
+async fn filter_by_gear(
+    state: &AppState,
+    session_id: &str,
+    images: Vec<ImageInfo>,
+    filter: &GearFilter,
+) -> Result<Vec<ImageInfo>> {
+    if filter.is_empty() {
+        return Ok(images);
+    }
+    let exif: HashMap<String, ExifInfo> = session_exif(state, session_id)
+        .await?
+        .into_iter()
+        .map(|(image, exif)| (image.filename, exif))
+        .collect();
+    Ok(images
+        .into_iter()
+        .filter(|image| {
+            exif.get(&image.filename)
+                .is_some_and(|exif| filter.matches(exif))
+        })
+        .collect())
+}
+
+/// Scan a session's folders, pairing each file with its label
+async fn labeled_files(
+    state: &AppState,
+    session_id: &str,
+) -> Result<Vec<(ImageInfo, Option<String>)>> {
+    let (mut labels, folders) = {
+        let db = state.db.lock().unwrap();
+        let labels: HashMap<String, Option<String>> = db
+            .get_labels(session_id)?
+            .into_iter()
+            .map(|l| (l.filename, l.label))
+            .collect();
+        (labels, db.get_session_folders(session_id)?)
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+
+    Ok(images
+        .into_iter()
+        .map(|image| {
+            let label = labels.remove(&image.filename).flatten();
+            (image, label)
+        })
+        .collect())
+}
+
+/// List the saved export presets
+#[tauri::command]
+pub fn list_export_presets() -> Vec<ExportPreset> {
+    config::get_config().export_presets
+}
+
+/// Save an export preset, replacing the one with the same name; returns all presets
+#[tauri::command]
+pub fn save_export_preset(app: AppHandle, preset: ExportPreset) -> Result<Vec<ExportPreset>> {
+    let mut config = config::get_config();
+    match config
+        .export_presets
+        .iter_mut()
+        .find(|existing| existing.name == preset.name)
+    {
+        Some(existing) => *existing = preset,
+        None => config.export_presets.push(preset),
+    }
+    let presets = config.export_presets.clone();
+    apply_config(&app, config)?;
+    Ok(presets)
+}
+
+/// Delete an export preset; returns the remaining presets
+#[tauri::command]
+pub fn delete_export_preset(app: AppHandle, name: String) -> Result<Vec<ExportPreset>> {
+    let mut config = config::get_config();
+    config.export_presets.retain(|preset| preset.name != name);
+    let presets = config.export_presets.clone();
+    apply_config(&app, config)?;
+    Ok(presets)
+}
+
+/// Export the open session with a saved preset
+#[tauri::command]
+pub async fn run_export_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<ExportResult> {
+    let preset = config::get_config()
+        .export_presets
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| GlimpseError::InvalidConfig(format!("No export preset named {}", name)))?;
+    let session_id = state.current_session_id()?;
+    export_session(
+        &app,
+        &state,
+        &session_id,
+        Some(&preset.name),
+        &preset.settings,
+    )
+    .await
+}
+
+/// Export a session with `settings` and add the run to its export history
+async fn export_session(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: &str,
+    preset: Option<&str>,
+    settings: &ExportSettings,
+) -> Result<ExportResult> {
+    let (total, to_export) = export_selection(state, session_id, settings.filter).await?;
+    let to_export = filter_by_gear(state, session_id, to_export, &settings.gear).await?;
+    let selected = to_export.len();
+    let batch = ExportBatch {
+        session_id: session_id.to_string(),
+        images: to_export,
+        destination: PathBuf::from(&settings.destination),
+    };
+    let (outcomes, cancelled) = export_images(
+        app,
+        state,
+        vec![batch],
+        settings.is_move(),
+        &settings.options,
+        settings
+            .rename_template
+            .as_deref()
+            .map(|template| (template, settings.seq_order)),
+        settings.new_only,
+    )
+    .await?;
+
+    let result = outcomes[0].into_result(total, total - selected, cancelled);
+    let db = state.db.lock().unwrap();
+    db.add_export_record(session_id, preset, settings, &result)?;
+    Ok(result)
+}
+
+/// Results of both sides of a split export
+#[derive(serde::Serialize)]
+pub struct SplitExportResult {
+    adopted: ExportResult,
+    rejected: ExportResult,
+}
+
+/// Export adopted files to one folder and rejected files to a "rejects" folder in one task
+/// (the usual end-of-cull disposition); unlabeled files stay where they are
+/// Each side is recorded in the export history on its own
+#[tauri::command]
+pub async fn export_split(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    adopted_destination: String,
+    rejected_destination: String,
+    mode: String,
+    options: Option<ExportOptions>,
+) -> Result<SplitExportResult> {
+    let options = options.unwrap_or_default();
+    let session_id = state.current_session_id()?;
+    if Path::new(&adopted_destination) == Path::new(&rejected_destination) {
+        return Err(GlimpseError::InvalidPath(format!(
+            "Adopted and rejected files need different folders: {}",
+            adopted_destination
+        )));
+    }
+
+    let files = labeled_files(&state, &session_id).await?;
+    let total = files.len();
+    let (mut adopted, mut rejected) = (Vec::new(), Vec::new());
+    for (image, label) in files {
+        if ExportFilter::Adopted.includes(label.as_deref()) {
+            adopted.push(image);
+        } else if ExportFilter::Rejected.includes(label.as_deref()) {
+            rejected.push(image);
+        }
+    }
+
+    let sides = [
+        (ExportFilter::Adopted, adopted_destination, adopted),
+        (ExportFilter::Rejected, rejected_destination, rejected),
+    ];
+    let settings: Vec<ExportSettings> = sides
+        .iter()
+        .map(|(filter, destination, _)| ExportSettings {
+            destination: destination.clone(),
+            mode: if mode == "move" {
+                ExportMode::Move
+            } else {
+                ExportMode::Copy
+            },
+            filter: *filter,
+            rename_template: None,
+            seq_order: SequenceOrder::default(),
+            gear: GearFilter::default(),
+            new_only: false,
+            options: options.clone(),
+        })
+        .collect();
+    let selected: Vec<usize> = sides.iter().map(|(_, _, images)| images.len()).collect();
+    let batches = sides
+        .into_iter()
+        .map(|(_, destination, images)| ExportBatch {
+            session_id: session_id.clone(),
+            images,
+            destination: PathBuf::from(destination),
+        })
+        .collect();
+
+    let (outcomes, cancelled) =
+        export_images(&app, &state, batches, mode == "move", &options, None, false).await?;
+
+    let db = state.db.lock().unwrap();
+    let mut results = Vec::new();
+    for ((settings, selected), outcome) in settings.iter().zip(selected).zip(outcomes) {
+        let result = outcome.into_result(total, total - selected, cancelled);
+        db.add_export_record(&session_id, None, settings, &result)?;
+        results.push(result);
+    }
+    let rejected = results.pop().unwrap_or_default();
+    let adopted = results.pop().unwrap_or_default();
+    Ok(SplitExportResult { adopted, rejected })
+}
+
+/// List the configured SFTP targets
+#[tauri::command]
+pub fn list_sftp_targets() -> Vec<SftpTarget> {
+    config::get_config().sftp_targets
+}
+
+/// Save an SFTP target, replacing the one with the same name; returns all targets
+/// A given password is stored in the OS keychain; an empty one removes the stored password
+#[tauri::command]
+pub fn save_sftp_target(
+    app: AppHandle,
+    target: SftpTarget,
+    password: Option<String>,
+) -> Result<Vec<SftpTarget>> {
+    let mut config = config::get_config();
+    let account = target.keychain_account();
+    match config
+        .sftp_targets
+        .iter_mut()
+        .find(|existing| existing.name == target.name)
+    {
+        Some(existing) => *existing = target,
+        None => config.sftp_targets.push(target),
+    }
+    let targets = config.sftp_targets.clone();
+    apply_config(&app, config)?;
+
+    match password.as_deref() {
+        Some("") => keychain::delete_password(&account)?,
+        Some(password) => keychain::set_password(&account, password)?,
+        None => {}
+    }
+    Ok(targets)
+}
+
+/// Delete an SFTP target and its stored password; returns the remaining targets
+#[tauri::command]
+pub fn delete_sftp_target(app: AppHandle, name: String) -> Result<Vec<SftpTarget>> {
+    let mut config = config::get_config();
+    let removed: Vec<SftpTarget> = config
+        .sftp_targets
+        .iter()
+        .filter(|target| target.name == name)
+        .cloned()
+        .collect();
+    config.sftp_targets.retain(|target| target.name != name);
+    let targets = config.sftp_targets.clone();
+    apply_config(&app, config)?;
+
+    for target in removed {
+        let account = target.keychain_account();
+        // Another target may log in to the same account
+        if !targets
+            .iter()
+            .any(|other| other.keychain_account() == account)
+        {
+            keychain::delete_password(&account)?;
+        }
+    }
+    Ok(targets)
+}
+
+/// Upload the open session's files (by default all but rejected ones) to an SFTP target
+/// Files already on the server at full size count as unchanged, and shorter ones left by an
+/// interrupted upload are resumed; each upload is checked by its size on the server
+#[tauri::command]
+pub async fn export_sftp(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    target: String,
+    filter: Option<ExportFilter>,
+) -> Result<ExportResult> {
+    let target = config::get_config()
+        .sftp_targets
+        .into_iter()
+        .find(|existing| existing.name == target)
+        .ok_or_else(|| GlimpseError::InvalidConfig(format!("No SFTP target named {}", target)))?;
+    let session_id = state.current_session_id()?;
+    upload_session(
+        &app,
+        &state,
+        &session_id,
+        target,
+        filter.unwrap_or_default(),
+    )
+    .await
+}
+
+/// Upload the files of a session passing `filter` to an SFTP target and record the run
+/// in its export history
+async fn upload_session(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: &str,
+    target: SftpTarget,
+    filter: ExportFilter,
+) -> Result<ExportResult> {
+    let settings = ExportSettings {
+        destination: target.url(),
+        mode: ExportMode::Copy,
+        filter,
+        rename_template: None,
+        seq_order: SequenceOrder::default(),
+        gear: GearFilter::default(),
+        new_only: true,
+        options: ExportOptions::default(),
+    };
+    let (total, images) = export_selection(state, session_id, filter).await?;
+    let selected = images.len();
+
+    let password = keychain::get_password(&target.keychain_account())?;
+    let client = SftpClient::new(target, password)?;
+    let (client, remote_sizes) = tokio::task::spawn_blocking(move || {
+        let sizes = client.remote_sizes()?;
+        Ok::<_, GlimpseError>((client, sizes))
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))??;
+
+    let task = state.tasks.start(TaskKind::Export, selected);
+    emit_task(app, task.info());
+
+    // Upload on a blocking thread; the task may sit paused between files
+    let (outcome, task) = tokio::task::spawn_blocking(move || {
+        let mut outcome = BatchOutcome::default();
+        let mut used_names = HashSet::new();
+        for (index, image) in images.iter().enumerate() {
+            if !task.checkpoint() {
+                break;
+            }
+            let name = export_file_name(&image.filename, &mut used_names);
+            match remote_sizes.get(&name) {
+                Some(&size) if size == image.size => outcome.unchanged += 1,
+                uploaded => {
+                    let resume = uploaded.is_some_and(|&size| size < image.size);
+                    match client.upload(Path::new(&image.path), &name, resume) {
+                        Ok(()) => outcome.copied += 1,
+                        Err(e) => {
+                            tracing::warn!("Failed to upload {}: {}", image.path, e);
+                            outcome.failed += 1;
+                        }
+                    }
+                }
+            }
+            task.set_progress(index + 1);
+        }
+        (outcome, task)
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+
+    let cancelled = task.is_cancelled();
+    emit_task(app, task.finish());
+    let result = outcome.into_result(total, total - selected, cancelled);
+    let db = state.db.lock().unwrap();
+    db.add_export_record(session_id, None, &settings, &result)?;
+    Ok(result)
+}
+
+/// Past exports of a session, newest first
+#[tauri::command]
+pub fn get_export_history(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ExportRecord>> {
+    let db = state.db.lock().unwrap();
+    db.get_export_history(&session_id)
+}
+
+/// Run a past export again with the same settings (e.g. after adopting late picks)
+/// Files already in the destination are overwritten unless `export_new_only` (which defaults
+/// to the past run's setting) skips the unchanged ones; the run gets its own history entry
+#[tauri::command]
+pub async fn rerun_export(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    history_id: i64,
+    export_new_only: Option<bool>,
+) -> Result<ExportResult> {
+    let record = {
+        let db = state.db.lock().unwrap();
+        db.get_export_record(history_id)?
+            .ok_or(GlimpseError::ExportNotFound(history_id))?
+    };
+    // Uploads go back to the SFTP target they were sent to
+    let target = config::get_config()
+        .sftp_targets
+        .into_iter()
+        .find(|target| target.url() == record.settings.destination);
+    if let Some(target) = target {
+        return upload_session(
+            &app,
+            &state,
+            &record.session_id,
+            target,
+            record.settings.filter,
+        )
+        .await;
+    }
+    if record.settings.destination.starts_with("sftp://") {
+        return Err(GlimpseError::InvalidConfig(format!(
+            "No SFTP target uploads to {}",
+            record.settings.destination
+        )));
+    }
+
+    let settings = ExportSettings {
+        new_only: export_new_only.unwrap_or(record.settings.new_only),
+        ..record.settings
+    };
+    export_session(
+        &app,
+        &state,
+        &record.session_id,
+        record.preset.as_deref(),
+        &settings,
+    )
+    .await
+}
+
+/// Destructive operations an earlier run didn't finish (e.g. an export-move cut short by a crash)
+#[tauri::command]
+pub fn get_interrupted_operations(state: State<'_, AppState>) -> Result<Vec<InterruptedOperation>> {
+    let db = state.db.lock().unwrap();
+    db.get_interrupted_operations()
+}
+
+/// Resume, roll back or dismiss an interrupted operation
+/// It stays journaled if some files couldn't be recovered, so it can be retried
+#[tauri::command]
+pub async fn resolve_operation(
+    app: AppHandle,
+    operation_id: i64,
+    resolution: Resolution,
+) -> Result<ResolveOutcome> {
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().unwrap();
+        let interrupted = db
+            .get_interrupted_operations()?
+            .iter()
+            .any(|operation| operation.id == operation_id);
+        let operation = db
+            .get_operation(operation_id)?
+            .filter(|_| interrupted)
+            .ok_or(GlimpseError::OperationNotFound(operation_id))?;
+        operations::resolve(&db, &operation, resolution)
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Destination file name of an exported image, from its session key or new name
+/// Merged sessions can hold the same name from several folders; later ones
+/// get their folder path prefixed instead of overwriting the first, and a number
+/// when that is taken too (the same card folder layout in two sessions of a project)
+fn export_file_name(filename: &str, used_names: &mut HashSet<String>) -> String {
+    let name = Path::new(filename)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| filename.to_string());
+    if used_names.insert(name.clone()) {
+        return name;
+    }
+
+    let flattened = filename.replace(['/', ':'], "_");
+    if used_names.insert(flattened.clone()) {
+        return flattened;
+    }
+
+    let flattened_path = Path::new(&flattened);
+    let stem = flattened_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = flattened_path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| format!("{}_{}{}", stem, n, extension))
+        .find(|candidate| used_names.insert(candidate.clone()))
+        .unwrap()
+}
+
+/// Analyze all images of a session in shooting order, reusing stored results of unchanged files
+/// New analyses run as an `analysis` task and are stored for the next call
+async fn analyze_session(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: &str,
+    only: Option<&[String]>,
+) -> Result<Vec<ImageAnalysis>> {
+    let (folders, mut stored, stored_fingerprints, config) = {
+        let db = state.db.lock().unwrap();
+        if db.get_session(session_id)?.is_none() {
+            return Err(GlimpseError::SessionNotFound);
+        }
+        (
+            db.get_session_folders(session_id)?,
+            db.get_analyses(session_id)?,
+            db.get_file_fingerprints(session_id)?,
+            effective_config(&db, session_id)?,
+        )
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let mut images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+    if let Some(only) = only {
+        images.retain(|image| only.contains(&image.filename));
+    }
+    let cache_dir = get_cache_dir(session_id)?;
+    let preview_dir = get_preview_dir(session_id)?;
+
+    // Files changed since their last analysis are analyzed again
+    let missing: Vec<ImageInfo> = images
+        .iter()
+        .filter(|image| {
+            stored
+                .get(&image.filename)
+                .is_none_or(|(stamp, _)| stamp != &file_stamp(image))
+        })
+        .cloned()
+        .collect();
+
+    if !missing.is_empty() {
+        let task = state.tasks.start(TaskKind::Analysis, missing.len());
+        emit_task(app, task.info());
+        if power::is_low_power() {
+            apply_power_state(app);
+        }
+
+        let (computed, fingerprints, task) = tokio::task::spawn_blocking(move || {
+            let computed = analysis::analyze_images(
+                &missing,
+                &cache_dir,
+                &preview_dir,
+                config.thumbnail_size,
+                &task,
+            );
+            let fingerprints = fingerprint_images(&missing, &stored_fingerprints);
+            let fingerprints: HashMap<String, String> = missing
+                .into_iter()
+                .zip(fingerprints)
+                .filter_map(|(image, (_, fingerprint))| Some((image.filename, fingerprint?)))
+                .collect();
+            (computed, fingerprints, task)
+        })
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+        emit_task(app, task.finish());
+
+        // Hashes are kept by content as well, for other sessions with the same files
+        let mut fingerprint_rows = Vec::new();
+        let mut hashes = Vec::new();
+        for (stamp, analysis) in &computed {
+            if let Some(fingerprint) = fingerprints.get(&analysis.filename) {
+                fingerprint_rows.push((
+                    analysis.filename.clone(),
+                    stamp.clone(),
+                    fingerprint.clone(),
+                ));
+                hashes.push((fingerprint.clone(), analysis.dhash));
+            }
+        }
+
+        let db = state.db.lock().unwrap();
+        db.set_file_fingerprints(session_id, &fingerprint_rows)?;
+        db.set_perceptual_hashes(&hashes)?;
+        for (stamp, analysis) in computed {
+            db.set_analysis(session_id, &stamp, &analysis)?;
+            stored.insert(analysis.filename.clone(), (stamp, analysis));
+        }
+    }
+
+    Ok(images
+        .iter()
+        .filter_map(|image| stored.remove(&image.filename).map(|(_, a)| a))
+        .collect())
+}
+
+/// File stamps and content fingerprints of `images`, reusing fingerprints stored for
+/// unchanged files; None for files that can't be read
+fn fingerprint_images(
+    images: &[ImageInfo],
+    stored: &HashMap<String, (String, String)>,
+) -> Vec<(String, Option<String>)> {
+    let pool = build_thread_pool(config::get_thumbnail_thread_count());
+    pool.install(|| {
+        images
+            .par_iter()
+            .map(|image| {
+                let stamp = file_stamp(image);
+                let fingerprint = match stored.get(&image.filename) {
+                    Some((stored, fingerprint)) if stored == &stamp => Some(fingerprint.clone()),
+                    _ => content_cache::fingerprint(Path::new(&image.path)).ok(),
+                };
+                (stamp, fingerprint)
+            })
+            .collect()
+    })
+}
+
+/// Perceptual hashes of all images of a session in shooting order
+/// Taken from stored analyses of unchanged files, then from hashes any session stored for the
+/// same content; only the rest are computed (as an `analysis` task) and stored by content
+/// fingerprint, so similarity queries don't decode images again on every call
+async fn session_hashes(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: &str,
+) -> Result<Vec<ImageHash>> {
+    let (folders, analyses, stored_fingerprints, thumbnail_size) = {
+        let db = state.db.lock().unwrap();
+        if db.get_session(session_id)?.is_none() {
+            return Err(GlimpseError::SessionNotFound);
+        }
+        (
+            db.get_session_folders(session_id)?,
+            db.get_analyses(session_id)?,
+            db.get_file_fingerprints(session_id)?,
+            effective_config(&db, session_id)?.thumbnail_size,
+        )
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+    let cache_dir = get_cache_dir(session_id)?;
+
+    let mut hashes: HashMap<String, u64> = images
+        .iter()
+        .filter_map(|image| {
+            let (stamp, analysis) = analyses.get(&image.filename)?;
+            (stamp == &file_stamp(image)).then(|| (image.filename.clone(), analysis.dhash))
+        })
+        .collect();
+    let pending: Vec<ImageInfo> = images
+        .iter()
+        .filter(|image| !hashes.contains_key(&image.filename))
+        .cloned()
+        .collect();
+
+    if !pending.is_empty() {
+        let (pending, stamped) = tokio::task::spawn_blocking(move || {
+            let stamped = fingerprint_images(&pending, &stored_fingerprints);
+            (pending, stamped)
+        })
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+
+        let known = {
+            let db = state.db.lock().unwrap();
+            let rows: Vec<(String, String, String)> = pending
+                .iter()
+                .zip(&stamped)
+                .filter_map(|(image, (stamp, fingerprint))| {
+                    Some((image.filename.clone(), stamp.clone(), fingerprint.clone()?))
+                })
+                .collect();
+            db.set_file_fingerprints(session_id, &rows)?;
+            let fingerprints: Vec<String> = rows.into_iter().map(|(_, _, f)| f).collect();
+            db.get_perceptual_hashes(&fingerprints)?
+        };
+        let mut missing = Vec::new();
+        for (image, (_, fingerprint)) in pending.into_iter().zip(stamped) {
+            match fingerprint.as_ref().and_then(|f| known.get(f)) {
+                Some(&dhash) => {
+                    hashes.insert(image.filename, dhash);
+                }
+                None => missing.push((image, fingerprint)),
+            }
+        }
+
+        if !missing.is_empty() {
+            let task = state.tasks.start(TaskKind::Analysis, missing.len());
+            emit_task(app, task.info());
+            if power::is_low_power() {
+                apply_power_state(app);
+            }
+
+            let (computed, task) = tokio::task::spawn_blocking(move || {
+                let completed = AtomicUsize::new(0);
+                let pool = build_thread_pool(config::get_thumbnail_thread_count());
+                let computed: Vec<(String, Option<String>, u64)> = pool.install(|| {
+                    missing
+                        .par_iter()
+                        .filter_map(|(image, fingerprint)| {
+                            if !task.checkpoint() {
+                                return None;
+                            }
+                            let result = analysis::hash_image(image, &cache_dir, thumbnail_size);
+                            task.set_progress(completed.fetch_add(1, Ordering::Relaxed) + 1);
+                            match result {
+                                Ok(dhash) => {
+                                    Some((image.filename.clone(), fingerprint.clone(), dhash))
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Failed to hash {}: {}", image.path, e);
+                                    None
+                                }
+                            }
+                        })
+                        .collect()
+                });
+                (computed, task)
+            })
+            .await
+            .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+            emit_task(app, task.finish());
+
+            let db = state.db.lock().unwrap();
+            db.set_perceptual_hashes(
+                &computed
+                    .iter()
+                    .filter_map(|(_, fingerprint, dhash)| Some((fingerprint.clone()?, *dhash)))
+                    .collect::<Vec<_>>(),
+            )?;
+            hashes.extend(
+                computed
+                    .into_iter()
+                    .map(|(filename, _, dhash)| (filename, dhash)),
+            );
+        }
+    }
+
+    Ok(images
+        .into_iter()
+        .filter_map(|image| {
+            let dhash = *hashes.get(&image.filename)?;
+            Some(ImageHash {
+                filename: image.filename,
+                dhash,
+            })
+        })
+        .collect())
+}
+
+/// Propose rejects for a session from sharpness, exposure clipping and near-duplicates
+/// Nothing is labeled; the frontend lets the user confirm the suggestions in bulk
+#[tauri::command]
+pub async fn suggest_rejections(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    threshold: Option<f64>,
+) -> Result<Vec<RejectionSuggestion>> {
+    let threshold = threshold
+        .unwrap_or(analysis::DEFAULT_REJECT_THRESHOLD)
+        .clamp(0.0, 1.0);
+    let analyses = analyze_session(&app, &state, &session_id, None).await?;
+    Ok(analysis::suggest_rejections(&analyses, threshold))
+}
+
+#[derive(serde::Serialize)]
+pub struct ImageFaces {
+    filename: String,
+    face_count: usize,
+    faces: Vec<FaceBox>,
+}
+
+/// Faces found in each image of a session (for "faces present" filters and face crops)
+/// Images are analyzed first where needed
+#[tauri::command]
+pub async fn get_faces(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ImageFaces>> {
+    let analyses = analyze_session(&app, &state, &session_id, None).await?;
+    Ok(analyses
+        .into_iter()
+        .map(|analysis| ImageFaces {
+            filename: analysis.filename,
+            face_count: analysis.faces.len(),
+            faces: analysis.faces,
+        })
+        .collect())
+}
+
+/// Files stored in more than one folder, across every session, e.g. a card dumped twice
+/// Files are known by the fingerprints taken when their session was opened; copies no
+/// longer on disk are left out
+#[tauri::command]
+pub async fn find_global_duplicates(app: AppHandle) -> Result<Vec<DuplicateGroup>> {
+    let groups = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().unwrap();
+        db.find_global_duplicates()?
+    };
+    tokio::task::spawn_blocking(move || {
+        groups
+            .into_iter()
+            .filter_map(|mut group| {
+                group.files.retain(|file| Path::new(&file.path).exists());
+                group.spans_folders().then_some(group)
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))
+}
+
+/// Group visually similar frames across the whole session, not just adjacent ones
+#[tauri::command]
+pub async fn cluster_similar(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    max_distance: Option<u32>,
+) -> Result<Vec<SimilarCluster>> {
+    let hashes = session_hashes(&app, &state, &session_id).await?;
+    Ok(analysis::cluster_similar(
+        &hashes,
+        max_distance.unwrap_or(analysis::DEFAULT_SIMILARITY_DISTANCE),
+    ))
+}
+
+/// Frames of the current session most similar to `filename`, closest first, for gathering
+/// the variants of a pose
+#[tauri::command]
+pub async fn find_similar(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    filename: String,
+    max_distance: Option<u32>,
+) -> Result<Vec<SimilarFrame>> {
+    let session_id = state.current_session_id()?;
+    let hashes = session_hashes(&app, &state, &session_id).await?;
+    analysis::find_similar(
+        &hashes,
+        &filename,
+        max_distance.unwrap_or(analysis::DEFAULT_SIMILARITY_DISTANCE),
+    )
+    .ok_or(GlimpseError::InvalidPath(filename))
+}
+
+/// Burst groups of the current session
+/// Frames are timed by their EXIF capture time; the file time is only used for frames
+/// without an EXIF date, as cards store it at two-second steps and copies reset it
+async fn session_bursts(state: &AppState, max_gap_ms: u64) -> Result<Vec<BurstGroup>> {
+    let session_id = state.current_session_id()?;
+    let frames: Vec<(String, i64)> = session_exif(state, &session_id)
+        .await?
+        .into_iter()
+        .filter_map(|(image, exif)| {
+            let time = image_processor::exif_capture_millis(&exif)
+                .or_else(|| capture_time_millis(Path::new(&image.path)))?;
+            Some((image.filename, time))
+        })
+        .collect();
+    Ok(analysis::detect_bursts(&frames, max_gap_ms))
+}
+
+/// Detect bursts (frames shot less than `max_gap_ms` apart) in the current session
+#[tauri::command]
+pub async fn detect_bursts(
+    state: State<'_, AppState>,
+    max_gap_ms: Option<u64>,
+) -> Result<Vec<BurstGroup>> {
+    session_bursts(&state, max_gap_ms.unwrap_or(analysis::DEFAULT_BURST_GAP_MS)).await
+}
+
+/// Score the frames of a burst and recommend the keeper
+#[tauri::command]
+pub async fn rank_burst(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    group_id: String,
+    max_gap_ms: Option<u64>,
+) -> Result<BurstRanking> {
+    let max_gap_ms = max_gap_ms.unwrap_or(analysis::DEFAULT_BURST_GAP_MS);
+    let group = session_bursts(&state, max_gap_ms)
+        .await?
+        .into_iter()
+        .find(|group| group.id == group_id)
+        .ok_or_else(|| GlimpseError::BurstNotFound(group_id.clone()))?;
+
+    let session_id = state.current_session_id()?;
+    let analyses = analyze_session(&app, &state, &session_id, Some(&group.filenames)).await?;
+
+    analysis::rank_burst(&group.id, &analyses).ok_or_else(|| {
+        GlimpseError::Analysis(format!("no frame of burst {} could be analyzed", group_id))
+    })
+}
+
+/// Exposure brackets of the current session
+async fn session_brackets(state: &AppState, max_gap_ms: u64) -> Result<Vec<BracketSet>> {
+    let session_id = state.current_session_id()?;
+    let frames: Vec<(String, i64, f32)> = session_exif(state, &session_id)
+        .await?
+        .into_iter()
+        .filter_map(|(image, exif)| {
+            let bias = analysis::exposure_bias(&exif)?;
+            let taken = exif.date_taken.as_deref()?;
+            let time = chrono::NaiveDateTime::parse_from_str(taken, EXIF_DATE_FORMAT).ok()?;
+            Some((image.filename, time.and_utc().timestamp_millis(), bias))
+        })
+        .collect();
+    Ok(analysis::detect_brackets(&frames, max_gap_ms))
+}
+
+/// Detect exposure brackets (frames at different exposure biases shot at most `max_gap_ms`
+/// apart) in the current session
+#[tauri::command]
+pub async fn detect_brackets(
+    state: State<'_, AppState>,
+    max_gap_ms: Option<u64>,
+) -> Result<Vec<BracketSet>> {
+    session_brackets(
+        &state,
+        max_gap_ms.unwrap_or(analysis::DEFAULT_BRACKET_GAP_MS),
+    )
+    .await
+}
+
+/// Label every frame of a bracket set at once; a bracket is kept or dropped as a whole
+/// Returns the labeled filenames
+#[tauri::command]
+pub async fn label_bracket_set(
+    state: State<'_, AppState>,
+    set_id: String,
+    label: Option<String>,
+    max_gap_ms: Option<u64>,
+) -> Result<Vec<String>> {
+    let max_gap_ms = max_gap_ms.unwrap_or(analysis::DEFAULT_BRACKET_GAP_MS);
+    let set = session_brackets(&state, max_gap_ms)
+        .await?
+        .into_iter()
+        .find(|set| set.id == set_id)
+        .ok_or_else(|| GlimpseError::BracketNotFound(set_id.clone()))?;
+
+    let session_id = state.current_session_id()?;
+    let db = state.db.lock().unwrap();
+    let sync_dir = db.get_session_settings(&session_id)?.sync_dir;
+    for filename in &set.filenames {
+        set_synced_label(
+            &db,
+            &session_id,
+            sync_dir.as_deref(),
+            filename,
+            label.as_deref(),
+        )?;
+    }
+    Ok(set.filenames)
+}
+
+/// List running and paused background tasks
+#[tauri::command]
+pub fn list_tasks(state: State<'_, AppState>) -> Vec<TaskInfo> {
+    state.tasks.list()
+}
+
+/// Pause a background task
+#[tauri::command]
+pub fn pause_task(app: AppHandle, state: State<'_, AppState>, task_id: u64) -> Result<TaskInfo> {
+    let info = state.tasks.pause(task_id)?;
+    emit_task(&app, info.clone());
+    Ok(info)
+}
+
+/// Resume a paused background task
+#[tauri::command]
+pub fn resume_task(app: AppHandle, state: State<'_, AppState>, task_id: u64) -> Result<TaskInfo> {
+    let info = state.tasks.resume(task_id)?;
+    // Resumed by hand, so low-power mode leaves it running
+    if let Some(paused) = state.power_paused.lock().unwrap().get_mut(&task_id) {
+        *paused = false;
+    }
+    emit_task(&app, info.clone());
+    Ok(info)
+}
+
+/// Cancel a background task
+/// Work already done (generated thumbnails, exported files) is kept
+#[tauri::command]
+pub fn cancel_task(app: AppHandle, state: State<'_, AppState>, task_id: u64) -> Result<TaskInfo> {
+    let info = state.tasks.cancel(task_id)?;
+    emit_task(&app, info.clone());
+    Ok(info)
+}
+
+/// EXIF data of every image in a session, in shooting order, with capture date corrections
+/// applied (see `shift_capture_times` and `edit_metadata`)
+/// Results are cached per file and only re-read when the file has changed
+async fn session_exif(state: &AppState, session_id: &str) -> Result<Vec<(ImageInfo, ExifInfo)>> {
+    let (folders, mut cached, offsets, edits) = {
+        let db = state.db.lock().unwrap();
+        if db.get_session(session_id)?.is_none() {
+            return Err(GlimpseError::SessionNotFound);
+        }
+        (
+            db.get_session_folders(session_id)?,
+            db.get_exif_cache(session_id)?,
+            db.get_time_offsets(session_id)?,
+            db.get_metadata_edits(session_id)?,
+        )
+    };
+    let folders: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    let images = scan_resilient(session_root(&folders), move || scan_folders(&folders)).await?;
+
+    let missing: Vec<ImageInfo> = images
+        .iter()
+        .filter(|image| {
+            cached
+                .get(&image.filename)
+                .map(|(modified, _)| modified != &image.modified_at)
+                .unwrap_or(true)
+        })
+        .cloned()
+        .collect();
+
+    if !missing.is_empty() {
+        // Files without readable EXIF are cached as empty so they aren't retried every time
+        let read: Vec<(String, String, ExifInfo)> = tokio::task::spawn_blocking(move || {
+            build_thread_pool(config::get_thumbnail_thread_count()).install(|| {
+                missing
+                    .into_par_iter()
+                    .map(|image| {
+                        let exif = extract_exif(Path::new(&image.path)).unwrap_or_default();
+                        (image.filename, image.modified_at, exif)
+                    })
+                    .collect()
+            })
+        })
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+
+        state.db.lock().unwrap().set_exif_cache(session_id, &read)?;
+        for (filename, modified, exif) in read {
+            cached.insert(filename, (modified, exif));
+        }
+    }
+
+    Ok(images
+        .into_iter()
+        .filter_map(|image| {
+            let (_, mut exif) = cached.remove(&image.filename)?;
+            metadata::correct_exif(
+                &mut exif,
+                edits.get(&image.filename),
+                offsets.get(&image.filename).copied(),
+            );
+            Some((image, exif))
+        })
+        .collect())
 }
 
-/// Set a label
+/// Shift the capture time of a session's `filenames` by `seconds`, e.g. -3600 for a second
+/// body whose clock ran an hour ahead; corrections add up and are kept in the database, so
+/// the files themselves are untouched
 #[tauri::command]
-pub fn set_label(
+pub fn shift_capture_times(
     state: State<'_, AppState>,
-    filename: String,
-    label: Option<String>,
-) -> std::result::Result<(), String> {
-    let session_id = {
-        let current = state.current_session_id.lock().unwrap();
-        current.clone().ok_or("No session active")?
-    };
-
+    session_id: String,
+    filenames: Vec<String>,
+    seconds: i64,
+) -> Result<()> {
     let db = state.db.lock().unwrap();
-    db.set_label(&session_id, &filename, label.as_deref())
-        .map_err(|e| e.to_string())
+    if db.get_session(&session_id)?.is_none() {
+        return Err(GlimpseError::SessionNotFound);
+    }
+    db.add_time_offset(&session_id, &filenames, seconds)
 }
 
-/// Save selection position
+/// Drop the capture time corrections of a session's `filenames`
 #[tauri::command]
-pub fn save_selection(state: State<'_, AppState>, index: i32) -> std::result::Result<(), String> {
-    let session_id = {
-        let current = state.current_session_id.lock().unwrap();
-        current.clone().ok_or("No session active")?
-    };
+pub fn reset_capture_times(
+    state: State<'_, AppState>,
+    session_id: String,
+    filenames: Vec<String>,
+) -> Result<()> {
+    state
+        .db
+        .lock()
+        .unwrap()
+        .clear_time_offsets(&session_id, &filenames)
+}
 
+/// Correct the artist, copyright or capture date of a session's `filenames`; fields left out
+/// are kept and empty ones drop their correction. Corrections are written on export (EXIF
+/// of JPEGs, XMP sidecar of RAW files), the files themselves are untouched
+#[tauri::command]
+pub fn edit_metadata(
+    state: State<'_, AppState>,
+    session_id: String,
+    filenames: Vec<String>,
+    edits: MetadataEdits,
+) -> Result<()> {
+    edits.validate()?;
     let db = state.db.lock().unwrap();
-    db.update_last_selected(&session_id, index)
-        .map_err(|e| e.to_string())
+    if db.get_session(&session_id)?.is_none() {
+        return Err(GlimpseError::SessionNotFound);
+    }
+    db.edit_metadata(&session_id, &filenames, &edits)
 }
 
-/// Export adopted files
+/// Metadata corrections of a session by file name
 #[tauri::command]
-pub async fn export_adopted(
+pub fn get_metadata_edits(
     state: State<'_, AppState>,
-    source_folder: String,
-    destination_folder: String,
-    mode: String,
-) -> std::result::Result<ExportResult, String> {
-    let session_id = {
-        let current = state.current_session_id.lock().unwrap();
-        current.clone().ok_or("No session active")?
-    };
-
-    // Get rejected labels
-    let rejected_files: std::collections::HashSet<String> = {
-        let db = state.db.lock().unwrap();
-        db.get_labels(&session_id)
-            .map_err(|e| e.to_string())?
-            .into_iter()
-            .filter(|l| l.label.as_deref() == Some("rejected"))
-            .map(|l| l.filename)
-            .collect()
-    };
-
-    // Scan files in folder
-    let images = scan_folder(Path::new(&source_folder)).map_err(|e| e.to_string())?;
-
-    // Create destination folder
-    std::fs::create_dir_all(&destination_folder).map_err(|e| e.to_string())?;
-
-    let is_move = mode == "move";
-    let mut copied = 0;
-    let mut failed = 0;
+    session_id: String,
+) -> Result<HashMap<String, MetadataEdits>> {
+    state.db.lock().unwrap().get_metadata_edits(&session_id)
+}
 
-    for image in &images {
-        // Export only if not rejected
-        if !rejected_files.contains(&image.filename) {
-            let src = Path::new(&image.path);
-            let dst = Path::new(&destination_folder).join(&image.filename);
+/// Capture time corrections of a session, in seconds by file name
+#[tauri::command]
+pub fn get_capture_time_offsets(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<HashMap<String, i64>> {
+    state.db.lock().unwrap().get_time_offsets(&session_id)
+}
 
-            let result = if is_move {
-                // Move mode: copy first, then delete original
-                std::fs::copy(src, &dst).and_then(|_| std::fs::remove_file(src))
-            } else {
-                // Copy mode
-                std::fs::copy(src, &dst).map(|_| ())
-            };
+/// Per-body, per-lens, per-focal-length and per-ISO usage counts of a session
+#[tauri::command]
+pub async fn get_gear_stats(state: State<'_, AppState>, session_id: String) -> Result<GearStats> {
+    let exifs: Vec<ExifInfo> = session_exif(&state, &session_id)
+        .await?
+        .into_iter()
+        .map(|(_, exif)| exif)
+        .collect();
+    Ok(gear::gear_stats(&exifs))
+}
 
-            match result {
-                Ok(_) => copied += 1,
-                Err(_) => failed += 1,
+/// GPS positions of a session's geotagged images, from the EXIF cache
+#[tauri::command]
+pub async fn get_session_locations(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ImageLocation>> {
+    Ok(session_exif(&state, &session_id)
+        .await?
+        .into_iter()
+        .filter_map(|(image, exif)| {
+            let (latitude, longitude) = (exif.latitude?, exif.longitude?);
+            // Cameras without a fix yet sometimes record 0, 0
+            if latitude == 0.0 && longitude == 0.0 {
+                return None;
             }
-        }
-    }
+            Some(ImageLocation {
+                filename: image.filename,
+                latitude,
+                longitude,
+            })
+        })
+        .collect())
+}
 
-    Ok(ExportResult {
-        total: images.len(),
-        copied,
-        skipped: rejected_files.len(),
-        failed,
-    })
+/// Nearest city to a GPS position (e.g. "Kyoto, Japan" for the EXIF panel), looked up offline
+#[tauri::command]
+pub async fn reverse_geocode(latitude: f64, longitude: f64) -> Result<Option<Place>> {
+    // Reading a GeoNames file the first time takes a moment
+    tokio::task::spawn_blocking(move || geocode::reverse_geocode(latitude, longitude))
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
 }
 
-#[derive(serde::Serialize)]
-pub struct ExportResult {
-    total: usize,
-    copied: usize,
-    skipped: usize,
-    failed: usize,
+/// Total size, per-format counts and capture-date range of a session's files
+#[tauri::command]
+pub async fn get_folder_stats(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<FolderStats> {
+    let images = session_exif(&state, &session_id).await?;
+    Ok(stats::folder_stats(&images))
 }
 
 /// Get EXIF information
 #[tauri::command]
-pub fn get_exif(image_path: String) -> std::result::Result<ExifInfo, String> {
-    extract_exif(std::path::Path::new(&image_path)).map_err(|e| e.to_string())
+pub fn get_exif(image_path: String) -> Result<ExifInfo> {
+    let path = Path::new(&image_path);
+    extract_exif(path).map_err(|e| e.or_source_unavailable(path))
+}
+
+/// Get video duration/codec information
+#[tauri::command]
+pub fn get_video_info(video_path: String) -> Result<VideoInfo> {
+    video::probe_video(Path::new(&video_path))
 }
 
 /// Clear thumbnail cache
 #[tauri::command]
-pub fn clear_cache(state: State<'_, AppState>) -> std::result::Result<(), String> {
-    let session_id = {
-        let current = state.current_session_id.lock().unwrap();
-        current.clone().ok_or("No session active")?
-    };
+pub fn clear_cache(state: State<'_, AppState>) -> Result<()> {
+    let session_id = state.current_session_id()?;
 
-    let cache_dir = get_cache_dir(&session_id).map_err(|e| e.to_string())?;
+    let cache_dir = get_cache_dir(&session_id)?;
 
     // Delete files in cache directory
     if cache_dir.exists() {
-        std::fs::remove_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        std::fs::remove_dir_all(&cache_dir)?;
         // Recreate directory
-        std::fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&cache_dir)?;
     }
 
     Ok(())
 }
 
+/// Delete the thumbnails and previews of some files, e.g. after they were replaced externally
+/// They are regenerated the next time the folder is opened; returns the number of cache files removed
+#[tauri::command]
+pub fn clear_cache_for_files(
+    state: State<'_, AppState>,
+    session_id: String,
+    filenames: Vec<String>,
+) -> Result<usize> {
+    let cache_dir = get_cache_dir(&session_id)?;
+    let preview_dir = get_preview_dir(&session_id)?;
+
+    let mut removed = 0;
+    for filename in &filenames {
+        for path in [
+            image_processor::thumbnail_path_for(&cache_dir, filename),
+            image_processor::preview_path_for(&preview_dir, filename),
+        ] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(GlimpseError::io_at(&path, e)),
+            }
+        }
+    }
+
+    state
+        .db
+        .lock()
+        .unwrap()
+        .delete_thumbnail_cache(&session_id, &filenames)?;
+    Ok(removed)
+}
+
+/// Delete the thumbnails and previews of one session; labels are kept
+/// Returns the number of bytes freed
+#[tauri::command]
+pub fn clear_session_cache(state: State<'_, AppState>, session_id: String) -> Result<u64> {
+    if state.current_session_id.lock().unwrap().as_deref() == Some(session_id.as_str()) {
+        return Err(GlimpseError::InvalidPath(
+            "Cannot clear the cache of the open folder".into(),
+        ));
+    }
+
+    let cache_base_dir = get_cache_base_dir()?;
+    let session_dir = cache_base_dir.join(&session_id);
+    if session_id.is_empty()
+        || session_id == content_cache::SHARED_CACHE_FOLDER
+        || session_dir.parent() != Some(cache_base_dir.as_path())
+    {
+        return Err(GlimpseError::InvalidPath(format!(
+            "Invalid session ID: {}",
+            session_id
+        )));
+    }
+    if !session_dir.exists() {
+        return Ok(0);
+    }
+
+    let size = get_dir_size(&session_dir);
+    std::fs::remove_dir_all(&session_dir).map_err(|e| GlimpseError::io_at(&session_dir, e))?;
+    content_cache::prune_unshared(&cache_base_dir);
+    Ok(size)
+}
+
 /// Get system information
 #[derive(serde::Serialize)]
 pub struct SystemInfo {
     pub cpu_count: usize,
     pub current_threads: usize,
     pub recommended_threads: usize,
+    pub current_raw_threads: usize,
+    pub ffmpeg_available: bool,
+    /// Installed and currently available RAM in bytes, when the OS reports them
+    pub total_memory_bytes: Option<u64>,
+    pub available_memory_bytes: Option<u64>,
+    pub os_version: String,
+    pub gpu_name: Option<String>,
 }
 
+/// Runs off the main thread; the first GPU lookup can take a moment
 #[tauri::command]
-pub fn get_system_info() -> SystemInfo {
+pub async fn get_system_info() -> SystemInfo {
     let cpu_count = config::get_cpu_count();
     let recommended = ((cpu_count as f64 * 0.8).round() as usize).max(2);
 
@@ -279,16 +3963,195 @@ pub fn get_system_info() -> SystemInfo {
         cpu_count,
         current_threads: config::get_thumbnail_thread_count(),
         recommended_threads: recommended,
+        current_raw_threads: config::get_raw_decode_thread_count(),
+        ffmpeg_available: video::is_ffmpeg_available(),
+        total_memory_bytes: memory::total_memory(),
+        available_memory_bytes: memory::available_memory(),
+        os_version: system::os_version(),
+        gpu_name: system::gpu_name(),
+    }
+}
+
+/// Re-check the power source and pause or resume analysis to match
+/// Emits `power-mode-changed` when low-power mode turns on or off
+fn apply_power_state(app: &AppHandle) -> PowerStatus {
+    let was_low_power = power::is_low_power();
+    let status = power::refresh(config::get_power_mode());
+    let state = app.state::<AppState>();
+    let mut handled = state.power_paused.lock().unwrap();
+
+    if status.low_power {
+        // Analysis started since the last check is paused too; tasks the user resumed stay running
+        for info in state.tasks.list() {
+            if info.kind != TaskKind::Analysis || handled.contains_key(&info.id) {
+                continue;
+            }
+            if info.status == TaskStatus::Running {
+                if let Ok(info) = state.tasks.pause(info.id) {
+                    handled.insert(info.id, true);
+                    emit_task(app, info);
+                }
+            }
+        }
+    } else {
+        for (id, paused) in handled.drain() {
+            if paused {
+                if let Ok(info) = state.tasks.resume(id) {
+                    emit_task(app, info);
+                }
+            }
+        }
+    }
+
+    if status.low_power != was_low_power {
+        tracing::info!(
+            "Low-power mode {}",
+            if status.low_power { "on" } else { "off" }
+        );
+        let _ = app.emit("power-mode-changed", status);
+    }
+    status
+}
+
+/// Keep low-power mode in line with the power source for the lifetime of the app
+pub fn watch_power(app: &AppHandle) {
+    apply_power_state(app);
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(power::POLL_INTERVAL);
+        apply_power_state(&app);
+    });
+}
+
+/// Get the power mode and whether low-power mode is in effect
+#[tauri::command]
+pub fn get_power_status() -> PowerStatus {
+    PowerStatus {
+        mode: config::get_power_mode(),
+        on_battery: power::on_battery(),
+        low_power: power::is_low_power(),
     }
 }
 
+/// Choose when low-power mode is used; takes effect immediately
+#[tauri::command]
+pub fn set_power_mode(app: AppHandle, mode: PowerMode) -> Result<PowerStatus> {
+    let mut config = config::get_config();
+    config.power_mode = mode;
+    apply_config(&app, config)?;
+    Ok(apply_power_state(&app))
+}
+
+/// Measure thumbnail throughput on `sample_paths` at several thread counts
+/// Returns the fastest settings; applying them is left to the caller
+#[tauri::command]
+pub async fn run_benchmark(sample_paths: Vec<String>) -> Result<BenchmarkReport> {
+    let samples: Vec<PathBuf> = sample_paths.iter().map(PathBuf::from).collect();
+    let size = config::get_config().thumbnail_size;
+    tokio::task::spawn_blocking(move || benchmark::run(&samples, config::get_cpu_count(), size))
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Save config and notify all windows with a `config-changed` event
+fn apply_config(app: &AppHandle, config: AppConfig) -> Result<()> {
+    config::update_config(config.clone())?;
+    let _ = app.emit("config-changed", config);
+    Ok(())
+}
+
 /// Set thread count
 #[tauri::command]
-pub fn set_thread_count(thread_count: Option<usize>) -> std::result::Result<(), String> {
-    let config = AppConfig {
-        thumbnail_threads: thread_count,
-    };
-    config::update_config(config)
+pub fn set_thread_count(app: AppHandle, thread_count: Option<usize>) -> Result<()> {
+    let mut config = config::get_config();
+    config.thumbnail_threads = thread_count;
+    apply_config(&app, config)
+}
+
+/// Get application config
+#[tauri::command]
+pub fn get_config() -> AppConfig {
+    config::get_config()
+}
+
+/// Replace application config (validated before saving)
+/// The cache location is kept as-is; it can only be changed via `migrate_cache_dir`
+/// Changing the cache format deletes the old cache files; they are regenerated under the
+/// new format's extension the next time a folder is opened. Changing the thumbnail or
+/// preview size likewise deletes the thumbnails or previews made at the old size
+#[tauri::command]
+pub async fn set_config(app: AppHandle, mut config: AppConfig) -> Result<AppConfig> {
+    let previous = config::get_config();
+    config.cache_dir = previous.cache_dir.clone();
+    apply_config(&app, config.clone())?;
+
+    if previous.lens_correction != config.lens_correction
+        || previous.lensfun_dir != config.lensfun_dir
+        || previous.preview_denoise_iso != config.preview_denoise_iso
+    {
+        let state = app.state::<AppState>();
+        if state.current_session_id().is_ok() {
+            refresh_raw_previews(app.clone(), state).await?;
+        }
+    }
+
+    if previous.cache_format != config.cache_format {
+        let cache_base = cache_base_dir_for(previous.cache_dir.as_deref())?;
+        let old_format = previous.cache_format;
+        std::thread::spawn(move || {
+            let removed = image_processor::remove_cache_files(&cache_base, old_format);
+            tracing::info!(
+                "Removed {} cache files after a cache format change",
+                removed
+            );
+        });
+    }
+
+    let resized: Vec<&str> = [
+        (
+            previous.thumbnail_size != config.thumbnail_size,
+            "thumbnails",
+        ),
+        (previous.preview_size != config.preview_size, "previews"),
+    ]
+    .into_iter()
+    .filter_map(|(changed, folder)| changed.then_some(folder))
+    .collect();
+    if !resized.is_empty() {
+        let cache_base = cache_base_dir_for(previous.cache_dir.as_deref())?;
+        std::thread::spawn(move || {
+            let removed: usize = resized
+                .iter()
+                .map(|folder| image_processor::remove_sized_cache_files(&cache_base, folder))
+                .sum();
+            tracing::info!("Removed {} cache files after a cache size change", removed);
+        });
+    }
+    Ok(config)
+}
+
+/// Get keybindings (defaults merged with customizations)
+#[tauri::command]
+pub fn get_keybindings() -> Keybindings {
+    config::get_config().effective_keybindings()
+}
+
+/// Save keybindings
+/// Bindings equal to the default are not stored, so future default changes apply to them
+#[tauri::command]
+pub fn set_keybindings(app: AppHandle, keybindings: Keybindings) -> Result<Keybindings> {
+    config::validate_keybindings(&keybindings)?;
+
+    let defaults = config::default_keybindings();
+    let mut config = config::get_config();
+    config.keybindings = keybindings
+        .into_iter()
+        .filter(|(action, combo)| defaults.get(action) != Some(combo))
+        .collect();
+    let effective = config.effective_keybindings();
+    apply_config(&app, config)?;
+
+    Ok(effective)
 }
 
 /// Storage information
@@ -298,10 +4161,28 @@ pub struct StorageInfo {
     pub cache_size_display: String,
     pub label_count: i64,
     pub session_count: i64,
+    /// Per-session breakdown, most recently opened first
+    pub sessions: Vec<SessionStorage>,
+}
+
+/// Storage used by one session
+#[derive(serde::Serialize)]
+pub struct SessionStorage {
+    pub session_id: String,
+    pub folder_path: String,
+    pub thumbnail_bytes: u64,
+    pub preview_bytes: u64,
+    pub label_count: i64,
+    pub last_opened: Option<String>,
 }
 
 /// Calculate directory size recursively
+/// Files hard-linked by the shared cache are counted once
 fn get_dir_size(path: &Path) -> u64 {
+    dir_size_counting_links_once(path, &mut HashSet::new())
+}
+
+fn dir_size_counting_links_once(path: &Path, seen: &mut HashSet<(u64, u64)>) -> u64 {
     if !path.exists() {
         return 0;
     }
@@ -311,15 +4192,156 @@ fn get_dir_size(path: &Path) -> u64 {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_file() {
-                size += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if content_cache::file_id(&metadata).is_none_or(|id| seen.insert(id)) {
+                    size += metadata.len();
+                }
             } else if path.is_dir() {
-                size += get_dir_size(&path);
+                size += dir_size_counting_links_once(&path, seen);
             }
         }
     }
     size
 }
 
+#[derive(Clone, serde::Serialize)]
+struct CacheExpiredPayload {
+    sessions: usize,
+    reclaimed_bytes: u64,
+    max_age_days: u32,
+}
+
+/// Delete the caches of sessions not opened within `cache_expiry_days`, in the background
+/// Emits `cache-expired` with what was reclaimed; labels and settings are kept
+pub fn expire_old_caches(app: &AppHandle) {
+    let Some(max_age_days) = config::get_config().cache_expiry_days else {
+        return;
+    };
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let Ok(cache_base_dir) = get_cache_base_dir() else {
+            return;
+        };
+        let state = app.state::<AppState>();
+        let last_opened = match state.db.lock().unwrap().get_session_last_opened() {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                tracing::warn!("Cache expiry skipped: {}", e);
+                return;
+            }
+        };
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(max_age_days as i64);
+
+        let mut sessions = 0;
+        let mut reclaimed_bytes = 0;
+        for path in image_processor::expired_session_dirs(&cache_base_dir, &last_opened, cutoff) {
+            // A folder opened since startup is in use again
+            let current = state.current_session_id.lock().unwrap().clone();
+            if current.is_some_and(|id| path.file_name().is_some_and(|name| name == id.as_str())) {
+                continue;
+            }
+            let size = get_dir_size(&path);
+            if std::fs::remove_dir_all(&path).is_ok() {
+                sessions += 1;
+                reclaimed_bytes += size;
+            }
+        }
+        if sessions == 0 {
+            return;
+        }
+        reclaimed_bytes += content_cache::prune_unshared(&cache_base_dir);
+
+        tracing::info!(
+            "Expired caches of {} sessions not opened in {} days, reclaimed {}",
+            sessions,
+            max_age_days,
+            format_bytes(reclaimed_bytes)
+        );
+        let _ = app.emit(
+            "cache-expired",
+            CacheExpiredPayload {
+                sessions,
+                reclaimed_bytes,
+                max_age_days,
+            },
+        );
+    });
+}
+
+/// Delete the oldest session caches until the cache fits within `cache_limit_mb`
+/// The cache of `current_session_id` is never deleted
+fn enforce_cache_limit(current_session_id: &str) {
+    let Some(limit_mb) = config::get_config().cache_limit_mb else {
+        return;
+    };
+    let Ok(cache_base_dir) = get_cache_base_dir() else {
+        return;
+    };
+    let limit_bytes = limit_mb * 1024 * 1024;
+
+    let mut total = get_dir_size(&cache_base_dir);
+    if total <= limit_bytes {
+        return;
+    }
+
+    // Collect other session caches, oldest first
+    let mut sessions: Vec<_> = match std::fs::read_dir(&cache_base_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|e| e.path().is_dir() && e.file_name() != current_session_id)
+            .map(|e| {
+                let modified = e
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+                (modified, e.path())
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    sessions.sort_by_key(|(modified, _)| *modified);
+
+    for (_, path) in sessions {
+        if total <= limit_bytes {
+            break;
+        }
+        let size = get_dir_size(&path);
+        if std::fs::remove_dir_all(&path).is_ok() {
+            total = total.saturating_sub(size);
+        }
+    }
+}
+
+/// Move a file or directory, falling back to copy + delete across volumes
+fn move_path(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if std::fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+
+    copy_path(src, dst)?;
+    if src.is_dir() {
+        std::fs::remove_dir_all(src)
+    } else {
+        std::fs::remove_file(src)
+    }
+}
+
+/// Copy a file or directory recursively
+fn copy_path(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        std::fs::create_dir_all(dst)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(src, dst).map(|_| ())
+    }
+}
+
 /// Format bytes to human-readable string
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -339,54 +4361,265 @@ fn format_bytes(bytes: u64) -> String {
 
 /// Get storage information
 #[tauri::command]
-pub fn get_storage_info(state: State<'_, AppState>) -> std::result::Result<StorageInfo, String> {
+pub fn get_storage_info(state: State<'_, AppState>) -> Result<StorageInfo> {
     let db = state.db.lock().unwrap();
 
     // Get cache directory path
-    let data_dir = dirs::data_dir().ok_or_else(|| "Cannot find data directory".to_string())?;
-    let cache_base_dir = data_dir.join("Glimpse").join("cache");
+    let cache_base_dir = get_cache_base_dir()?;
 
     // Calculate cache size
     let cache_size_bytes = get_dir_size(&cache_base_dir);
 
     // Get counts from database
-    let label_count = db.get_label_count().map_err(|e| e.to_string())?;
-    let session_count = db.get_session_count().map_err(|e| e.to_string())?;
+    let label_count = db.get_label_count()?;
+    let session_count = db.get_session_count()?;
+
+    let sessions = db
+        .get_session_summaries()?
+        .into_iter()
+        .map(|session| {
+            let session_dir = cache_base_dir.join(&session.id);
+            SessionStorage {
+                thumbnail_bytes: get_dir_size(&session_dir.join("thumbnails")),
+                preview_bytes: get_dir_size(&session_dir.join("previews")),
+                session_id: session.id,
+                folder_path: session.folder_path,
+                label_count: session.label_count,
+                last_opened: session.last_opened,
+            }
+        })
+        .collect();
 
     Ok(StorageInfo {
         cache_size_bytes,
         cache_size_display: format_bytes(cache_size_bytes),
         label_count,
         session_count,
+        sessions,
     })
 }
 
 /// Clear all thumbnail cache
 #[tauri::command]
-pub fn clear_all_cache(state: State<'_, AppState>) -> std::result::Result<u64, String> {
+pub fn clear_all_cache(state: State<'_, AppState>) -> Result<u64> {
     let db = state.db.lock().unwrap();
 
     // Get cache directory path
-    let data_dir = dirs::data_dir().ok_or_else(|| "Cannot find data directory".to_string())?;
-    let cache_base_dir = data_dir.join("Glimpse").join("cache");
+    let cache_base_dir = get_cache_base_dir()?;
 
     // Calculate size before deletion
     let size = get_dir_size(&cache_base_dir);
 
     // Delete cache directory
     if cache_base_dir.exists() {
-        std::fs::remove_dir_all(&cache_base_dir).map_err(|e| e.to_string())?;
+        std::fs::remove_dir_all(&cache_base_dir)?;
     }
 
     // Clear thumbnail_cache table
-    db.clear_all_sessions().map_err(|e| e.to_string())?;
+    db.clear_all_sessions()?;
 
     Ok(size)
 }
 
+/// Link identical cache files across sessions to reclaim space
+#[tauri::command]
+pub async fn dedup_cache() -> Result<DedupStats> {
+    let cache_base_dir = get_cache_base_dir()?;
+    let stats = tokio::task::spawn_blocking(move || content_cache::dedup(&cache_base_dir))
+        .await
+        .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?;
+    tracing::info!(
+        "Cache dedup linked {} files, reclaimed {} bytes",
+        stats.files_linked,
+        stats.bytes_reclaimed
+    );
+    Ok(stats)
+}
+
 /// Clear all label data
 #[tauri::command]
-pub fn clear_all_labels(state: State<'_, AppState>) -> std::result::Result<i64, String> {
+pub fn clear_all_labels(state: State<'_, AppState>) -> Result<i64> {
     let db = state.db.lock().unwrap();
-    db.clear_all_labels().map_err(|e| e.to_string())
+    db.clear_all_labels()
+}
+
+/// Compact the database after labels or sessions were cleared
+#[tauri::command]
+pub async fn optimize_database(app: AppHandle) -> Result<OptimizeStats> {
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().unwrap();
+        db.optimize()
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Check the database for damage and rows pointing at nothing; `repair` deletes those rows
+#[tauri::command]
+pub async fn check_database(app: AppHandle, repair: Option<bool>) -> Result<IntegrityReport> {
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().unwrap();
+        db.check_integrity(repair.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| GlimpseError::Io(std::io::Error::other(e)))?
+}
+
+/// Allow the webview to load thumbnails from a custom cache location
+/// (the default location is already covered by the static asset scope)
+pub fn allow_cache_dir_access(app: &AppHandle) {
+    if config::get_config().cache_dir.is_none() {
+        return;
+    }
+    if let Ok(cache_base_dir) = get_cache_base_dir() {
+        let _ = app
+            .asset_protocol_scope()
+            .allow_directory(&cache_base_dir, true);
+    }
+}
+
+/// Move the cache to another location and remember it in the config
+/// Pass None to move it back to the app data directory
+/// Returns the number of bytes moved
+#[tauri::command]
+pub fn migrate_cache_dir(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    cache_dir: Option<String>,
+) -> Result<u64> {
+    let mut new_config = config::get_config();
+    new_config.cache_dir = cache_dir;
+    new_config.validate()?;
+
+    let old_base = get_cache_base_dir()?;
+    let new_base = cache_base_dir_for(new_config.cache_dir.as_deref())?;
+
+    if old_base == new_base {
+        return Ok(0);
+    }
+    if new_base.starts_with(&old_base) || old_base.starts_with(&new_base) {
+        return Err(GlimpseError::InvalidPath(
+            "Cache directory cannot be moved into itself".to_string(),
+        ));
+    }
+
+    let size = get_dir_size(&old_base);
+
+    // Move session caches one by one
+    std::fs::create_dir_all(&new_base).map_err(|e| GlimpseError::io_at(&new_base, e))?;
+    if old_base.exists() {
+        for entry in std::fs::read_dir(&old_base)? {
+            let entry = entry?;
+            let src = entry.path();
+            move_path(&src, &new_base.join(entry.file_name()))
+                .map_err(|e| GlimpseError::io_at(&src, e))?;
+        }
+        let _ = std::fs::remove_dir(&old_base);
+    }
+
+    // Update stored cache paths
+    {
+        let db = state.db.lock().unwrap();
+        db.update_cache_paths(&normalize_path(&old_base), &normalize_path(&new_base))?;
+    }
+
+    apply_config(&app, new_config)?;
+    allow_cache_dir_access(&app);
+
+    Ok(size)
+}
+
+/// Open a file or folder with the OS default handler (Finder/Explorer/file manager)
+fn open_with_system(path: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = std::process::Command::new("explorer");
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command.arg(path).spawn().map(|_| ())
+}
+
+/// Show a file in the OS file manager with the file selected
+#[cfg(target_os = "macos")]
+fn reveal_with_system(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+}
+
+/// Show a file in the OS file manager with the file selected
+#[cfg(target_os = "windows")]
+fn reveal_with_system(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer only understands backslashes and needs the path quoted after the comma
+    let windows_path = path.to_string_lossy().replace('/', "\\");
+    std::process::Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", windows_path))
+        .spawn()
+        .map(|_| ())
+}
+
+/// Show a file in the OS file manager with the file selected
+/// Most Linux file managers implement the FileManager1 D-Bus interface;
+/// fall back to opening the parent folder when it's unavailable
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_with_system(path: &Path) -> std::io::Result<()> {
+    // Escaped like the trash records, also keeping commas from splitting the D-Bus array
+    let uri = format!("file://{}", trash::percent_encode(path));
+    let revealed = std::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    if revealed {
+        Ok(())
+    } else {
+        open_with_system(path.parent().unwrap_or(path))
+    }
+}
+
+/// Reveal an image in Finder/Explorer/the file manager
+#[tauri::command]
+pub fn reveal_in_explorer(path: String) -> Result<()> {
+    let path = Path::new(&path);
+    if !path.exists() {
+        return Err(GlimpseError::io_at(
+            path,
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        ));
+    }
+    reveal_with_system(path).map_err(|e| GlimpseError::io_at(path, e))
+}
+
+/// Get the most recent log lines (oldest first)
+#[tauri::command]
+pub fn get_recent_logs(n: usize) -> Result<Vec<String>> {
+    let log_dir = logging::get_log_dir()
+        .ok_or_else(|| GlimpseError::InvalidPath("Cannot find data directory".into()))?;
+    logging::read_recent_logs(&log_dir, n).map_err(|e| GlimpseError::io_at(&log_dir, e))
+}
+
+/// Open the log folder in the OS file manager
+#[tauri::command]
+pub fn open_log_folder() -> Result<()> {
+    let log_dir = logging::get_log_dir()
+        .ok_or_else(|| GlimpseError::InvalidPath("Cannot find data directory".into()))?;
+    std::fs::create_dir_all(&log_dir).map_err(|e| GlimpseError::io_at(&log_dir, e))?;
+    open_with_system(&log_dir).map_err(|e| GlimpseError::io_at(&log_dir, e))
 }
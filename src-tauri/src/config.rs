@@ -1,18 +1,698 @@
+use crate::error::{GlimpseError, Result};
+use crate::export::ExportOptions;
+use crate::gear::GearFilter;
+use crate::metadata::MetadataEdits;
+use crate::power;
+use crate::rename::{self, SequenceOrder};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
 static CONFIG: OnceLock<std::sync::RwLock<AppConfig>> = OnceLock::new();
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Default thumbnail edge length in pixels
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 300;
+/// Default preview edge length in pixels
+pub const DEFAULT_PREVIEW_SIZE: u32 = 2000;
+
+/// Default size above which standard images get a preview
+pub const DEFAULT_LARGE_IMAGE_PREVIEW_MEGAPIXELS: u32 = 40;
+
+/// Allowed range for thumbnail size
+const THUMBNAIL_SIZE_RANGE: std::ops::RangeInclusive<u32> = 100..=1000;
+/// Allowed range for preview size
+const PREVIEW_SIZE_RANGE: std::ops::RangeInclusive<u32> = 1000..=8000;
+/// Allowed range for the large image preview threshold
+const LARGE_IMAGE_PREVIEW_MEGAPIXELS_RANGE: std::ops::RangeInclusive<u32> = 1..=1000;
+/// Smallest accepted cache limit (anything lower would evict the current session)
+const MIN_CACHE_LIMIT_MB: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     /// Number of threads for thumbnail generation
     /// If None, auto-calculate (80% of CPU logical cores)
     pub thumbnail_threads: Option<usize>,
+    /// Number of RAW files decoded at once, on top of the thumbnail threads' limit
+    /// RAW decodes take hundreds of megabytes each; JPEG/PNG thumbnails keep using every thread
+    /// If None, half of the thumbnail threads
+    pub raw_decode_threads: Option<usize>,
+    /// Maximum total cache size in megabytes
+    /// If None, the cache grows without limit
+    pub cache_limit_mb: Option<u64>,
+    /// Delete the caches of sessions not opened for this many days (checked on startup)
+    /// If None, caches are kept until the size limit removes them
+    pub cache_expiry_days: Option<u32>,
+    /// Folder that holds the thumbnail/preview cache
+    /// If None, the cache lives in the app data directory
+    pub cache_dir: Option<String>,
+    /// Thumbnail edge length in pixels
+    pub thumbnail_size: u32,
+    /// Preview edge length in pixels (RAW detail view)
+    pub preview_size: u32,
+    /// Image format of cached thumbnails and previews
+    pub cache_format: CacheFormat,
+    /// White balance RAW previews are developed with; sessions can override it
+    pub raw_white_balance: WhiteBalance,
+    /// Demosaic of RAW thumbnails and previews
+    pub raw_demosaic: RawDemosaic,
+    /// Decoder RAW files are read with
+    pub raw_backend: RawBackend,
+    /// dcraw or LibRaw's dcraw_emu, tried on RAW files the built-in decoder can't read
+    pub external_raw_decoder: Option<String>,
+    /// JPEG/PNG images above this many megapixels get a downscaled preview like RAW files,
+    /// so the detail view doesn't decode them at full size; None disables this
+    pub large_image_preview_megapixels: Option<u32>,
+    /// What to do with cloud "online-only" files (OneDrive/iCloud) when generating thumbnails
+    pub cloud_placeholders: PlaceholderPolicy,
+    /// Whether folder scans include symlinked files and folders
+    pub symlinks: SymlinkPolicy,
+    /// Include dotfiles, NAS metadata folders and files marked hidden in folder scans
+    pub include_hidden_files: bool,
+    /// Glob patterns of files left out of folder scans (e.g. `*.tmp`, `*_rejects/*`)
+    /// Patterns containing `/` are matched against the whole path, others against the file name
+    pub scan_exclude: Vec<String>,
+    /// RAW extensions (without the dot) scanned in addition to the built-in ones,
+    /// for cameras released after this version
+    pub extra_raw_extensions: Vec<String>,
+    /// Still image extensions scanned in addition to JPEG/PNG; the decoder must read the format
+    pub extra_image_extensions: Vec<String>,
+    /// When to save battery by using fewer thumbnail threads and pausing analysis
+    pub power_mode: PowerMode,
+    /// Defaults for the export dialog
+    pub export: ExportDefaults,
+    /// Named exports run with one click
+    pub export_presets: Vec<ExportPreset>,
+    /// SFTP servers exports can be uploaded to
+    pub sftp_targets: Vec<SftpTarget>,
+    /// GeoNames cities file (e.g. cities1000.txt) places are looked up in; None uses the
+    /// built-in list of major cities
+    pub geonames_file: Option<String>,
+    /// RAW previews of frames shot at this ISO or above get a light chroma noise reduction;
+    /// None turns it off
+    pub preview_denoise_iso: Option<u32>,
+    /// Correct lens distortion and vignetting in RAW previews, from lensfun profiles
+    pub lens_correction: bool,
+    /// lensfun database folder (version_1) profiles are read from; None looks in the places
+    /// lensfun installs to
+    pub lensfun_dir: Option<String>,
+    /// Artist and copyright written into the EXIF of every exported JPEG; a file's own
+    /// metadata corrections take precedence
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    /// Customized keyboard shortcuts (action -> key combo)
+    /// Actions not listed here use the default binding
+    pub keybindings: Keybindings,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            thumbnail_threads: None,
+            raw_decode_threads: None,
+            cache_limit_mb: None,
+            cache_expiry_days: None,
+            cache_dir: None,
+            thumbnail_size: DEFAULT_THUMBNAIL_SIZE,
+            preview_size: DEFAULT_PREVIEW_SIZE,
+            cache_format: CacheFormat::default(),
+            raw_white_balance: WhiteBalance::default(),
+            raw_demosaic: RawDemosaic::default(),
+            raw_backend: RawBackend::default(),
+            external_raw_decoder: None,
+            large_image_preview_megapixels: Some(DEFAULT_LARGE_IMAGE_PREVIEW_MEGAPIXELS),
+            cloud_placeholders: PlaceholderPolicy::default(),
+            symlinks: SymlinkPolicy::default(),
+            include_hidden_files: false,
+            scan_exclude: Vec::new(),
+            extra_raw_extensions: Vec::new(),
+            extra_image_extensions: Vec::new(),
+            power_mode: PowerMode::default(),
+            export: ExportDefaults::default(),
+            export_presets: Vec::new(),
+            sftp_targets: Vec::new(),
+            geonames_file: None,
+            preview_denoise_iso: None,
+            lens_correction: false,
+            lensfun_dir: None,
+            artist: None,
+            copyright: None,
+            keybindings: Keybindings::new(),
+        }
+    }
+}
+
+/// Image format of cache files
+/// AVIF is roughly half the size of JPEG at the same visual quality, but slower to encode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheFormat {
+    #[default]
+    Jpeg,
+    Avif,
+}
+
+impl CacheFormat {
+    /// File extension of cache files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CacheFormat::Jpeg => "jpg",
+            CacheFormat::Avif => "avif",
+        }
+    }
+
+    /// Format of an existing cache file, judged by its extension
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("avif") => CacheFormat::Avif,
+            _ => CacheFormat::Jpeg,
+        }
+    }
+}
+
+/// Decoder of RAW files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawBackend {
+    #[default]
+    Rawloader,
+    /// Supports more recent cameras; only in builds with the `rawler` feature
+    Rawler,
+}
+
+/// How RAW files are demosaiced when developing thumbnails and previews
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawDemosaic {
+    /// Full-resolution demosaic, then a filtered downscale
+    #[default]
+    HighQuality,
+    /// Demosaic straight to the output size by binning photosites; several times faster,
+    /// softer and more prone to color moire
+    Fast,
+}
+
+/// White balance of RAW previews
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalance {
+    /// As the camera recorded it
+    #[default]
+    AsShot,
+    /// Measured from the image (gray world)
+    Auto,
+    /// Fixed daylight (5500 K), for comparing frames shot under the same light
+    Daylight,
+}
+
+impl WhiteBalance {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WhiteBalance::AsShot => "as_shot",
+            WhiteBalance::Auto => "auto",
+            WhiteBalance::Daylight => "daylight",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "as_shot" => Some(WhiteBalance::AsShot),
+            "auto" => Some(WhiteBalance::Auto),
+            "daylight" => Some(WhiteBalance::Daylight),
+            _ => None,
+        }
+    }
+}
+
+/// Handling of cloud placeholder files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderPolicy {
+    /// Leave them alone; they get no thumbnail until synced
+    #[default]
+    Skip,
+    /// Read them anyway, making the sync client download them
+    Hydrate,
+}
+
+/// Handling of symbolic links (and Windows junctions) found while scanning
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Treat links like their targets; broken links are skipped
+    #[default]
+    Follow,
+    /// Leave links out of scans
+    Ignore,
+}
+
+/// When low-power mode is used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerMode {
+    /// Low power while running on battery
+    #[default]
+    Auto,
+    /// Always low power
+    Saver,
+    /// Never low power
+    Performance,
+}
+
+/// Keyboard shortcut mapping (action -> key combo such as "Mod+O" or "1")
+pub type Keybindings = BTreeMap<String, String>;
+
+/// Rebindable actions and their default key combos
+/// "Mod" stands for Ctrl on Windows and Cmd on macOS
+const DEFAULT_KEYBINDINGS: &[(&str, &str)] = &[
+    ("open_folder", "Mod+O"),
+    ("export", "Mod+E"),
+    ("select_all", "Mod+A"),
+    ("toggle_label", "1"),
+    ("toggle_label_compare", "2"),
+    ("enter_detail", "Enter"),
+    ("enter_compare", "C"),
+    ("enter_gallery", "G"),
+    ("open_help", "?"),
+];
+
+/// Get the default keybindings
+pub fn default_keybindings() -> Keybindings {
+    DEFAULT_KEYBINDINGS
+        .iter()
+        .map(|(action, key)| (action.to_string(), key.to_string()))
+        .collect()
+}
+
+/// Normalize a key combo so equivalent spellings compare equal
+/// ("ctrl+o", "Cmd+O" and "Mod+O" all become "Mod+O")
+fn normalize_key_combo(combo: &str) -> Option<String> {
+    let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+    // A trailing empty part means the key itself is "+"
+    let key = match parts.pop()? {
+        "" if combo.ends_with('+') => "+",
+        "" => return None,
+        key => key,
+    };
+
+    let mut modifiers = Vec::new();
+    for modifier in parts.iter().filter(|m| !m.is_empty()) {
+        let name = match modifier.to_lowercase().as_str() {
+            "mod" | "ctrl" | "control" | "cmd" | "command" | "meta" => "Mod",
+            "shift" => "Shift",
+            "alt" | "option" => "Alt",
+            _ => return None,
+        };
+        if !modifiers.contains(&name) {
+            modifiers.push(name);
+        }
+    }
+    modifiers.sort_unstable();
+
+    let key = if key.chars().count() == 1 {
+        key.to_uppercase()
+    } else {
+        key.to_string()
+    };
+
+    modifiers.push(&key);
+    Some(modifiers.join("+"))
+}
+
+/// Validate keybindings: actions must be known, combos parseable, and no two
+/// actions may share the same combo
+pub fn validate_keybindings(keybindings: &Keybindings) -> Result<()> {
+    let mut merged = default_keybindings();
+    for (action, combo) in keybindings {
+        if !merged.contains_key(action) {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Unknown action: {}",
+                action
+            )));
+        }
+        merged.insert(action.clone(), combo.clone());
+    }
+
+    let mut used: BTreeMap<String, &str> = BTreeMap::new();
+    for (action, combo) in &merged {
+        let normalized = normalize_key_combo(combo)
+            .ok_or_else(|| GlimpseError::InvalidConfig(format!("Invalid key combo: {}", combo)))?;
+        if let Some(other) = used.insert(normalized, action) {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Key combo {} is assigned to both {} and {}",
+                combo, other, action
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Export mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportMode {
+    #[default]
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportDefaults {
+    /// Default export mode
+    pub mode: ExportMode,
+    /// Last used export destination folder
+    pub destination: Option<String>,
+}
+
+/// Which files of a session an export writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFilter {
+    /// Everything except rejected files (unlabeled ones included)
+    #[default]
+    NotRejected,
+    Adopted,
+    Rejected,
+    All,
+}
+
+impl ExportFilter {
+    /// Whether a file with `label` is exported
+    pub fn includes(self, label: Option<&str>) -> bool {
+        match self {
+            ExportFilter::NotRejected => label != Some("rejected"),
+            ExportFilter::Adopted => label == Some("adopted"),
+            ExportFilter::Rejected => label == Some("rejected"),
+            ExportFilter::All => true,
+        }
+    }
+}
+
+/// Where and how an export writes a session's files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportSettings {
+    pub destination: String,
+    #[serde(default)]
+    pub mode: ExportMode,
+    #[serde(default)]
+    pub filter: ExportFilter,
+    /// Template exported files are named with (see `rename::render`); None keeps the names
+    #[serde(default)]
+    pub rename_template: Option<String>,
+    /// Order `{seq}` numbers the files in
+    #[serde(default)]
+    pub seq_order: SequenceOrder,
+    /// Only export frames taken with this camera or lens
+    #[serde(default)]
+    pub gear: GearFilter,
+    /// Skip files an earlier export wrote to the destination unless they changed since
+    #[serde(default)]
+    pub new_only: bool,
+    /// Rotation, resizing and metadata of written files
+    #[serde(default)]
+    pub options: ExportOptions,
+}
+
+impl ExportSettings {
+    pub fn is_move(&self) -> bool {
+        self.mode == ExportMode::Move
+    }
+}
+
+/// Export settings saved under a name (e.g. "Client proofs", "Archive copy")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: ExportSettings,
+}
+
+/// An SFTP server (e.g. a studio's delivery server) exports can be uploaded to
+/// A password, for servers that don't take the user's SSH key, is kept in the OS keychain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SftpTarget {
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_sftp_port")]
+    pub port: u16,
+    pub user: String,
+    /// Folder on the server the files are uploaded to
+    pub remote_dir: String,
+    /// Private key to log in with; the SSH agent and default keys are used otherwise
+    #[serde(default)]
+    pub identity_file: Option<String>,
+}
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
+impl SftpTarget {
+    /// Keychain account the target's password is stored under
+    pub fn keychain_account(&self) -> String {
+        format!("sftp://{}@{}:{}", self.user, self.host, self.port)
+    }
+
+    /// Where the target uploads to, as export history records it
+    pub fn url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.keychain_account(),
+            self.remote_dir.trim_start_matches('/')
+        )
+    }
 }
 
 impl AppConfig {
+    /// Validate config values
+    pub fn validate(&self) -> Result<()> {
+        if let Some(threads) = self.thumbnail_threads {
+            let max = get_cpu_count();
+            if threads == 0 || threads > max {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Thread count must be between 1 and {}",
+                    max
+                )));
+            }
+        }
+
+        if let Some(threads) = self.raw_decode_threads {
+            let max = get_cpu_count();
+            if threads == 0 || threads > max {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "RAW decode thread count must be between 1 and {}",
+                    max
+                )));
+            }
+        }
+
+        if let Some(limit) = self.cache_limit_mb {
+            if limit < MIN_CACHE_LIMIT_MB {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Cache limit must be at least {} MB",
+                    MIN_CACHE_LIMIT_MB
+                )));
+            }
+        }
+
+        if self.cache_expiry_days == Some(0) {
+            return Err(GlimpseError::InvalidConfig(
+                "Cache expiry must be at least 1 day".to_string(),
+            ));
+        }
+
+        if let Some(dir) = &self.cache_dir {
+            if !std::path::Path::new(dir).is_absolute() {
+                return Err(GlimpseError::InvalidConfig(
+                    "Cache directory must be an absolute path".to_string(),
+                ));
+            }
+        }
+
+        if self.raw_backend == RawBackend::Rawler && !cfg!(feature = "rawler") {
+            return Err(GlimpseError::InvalidConfig(
+                "This build has no rawler decoder".to_string(),
+            ));
+        }
+
+        if let Some(file) = &self.geonames_file {
+            let path = std::path::Path::new(file);
+            if !path.is_absolute() || !path.is_file() {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "GeoNames file not found: {}",
+                    file
+                )));
+            }
+        }
+
+        if self.preview_denoise_iso == Some(0) {
+            return Err(GlimpseError::InvalidConfig(
+                "Noise reduction ISO must be above 0".to_string(),
+            ));
+        }
+
+        if let Some(dir) = &self.lensfun_dir {
+            let path = std::path::Path::new(dir);
+            if !path.is_absolute() || !path.is_dir() {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "lensfun database folder not found: {}",
+                    dir
+                )));
+            }
+        }
+
+        MetadataEdits {
+            artist: self.artist.clone(),
+            copyright: self.copyright.clone(),
+            date_taken: None,
+        }
+        .validate()?;
+
+        if let Some(decoder) = &self.external_raw_decoder {
+            let path = std::path::Path::new(decoder);
+            if !path.is_absolute() || !path.is_file() {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "External RAW decoder not found: {}",
+                    decoder
+                )));
+            }
+        }
+
+        if !THUMBNAIL_SIZE_RANGE.contains(&self.thumbnail_size) {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Thumbnail size must be between {} and {}",
+                THUMBNAIL_SIZE_RANGE.start(),
+                THUMBNAIL_SIZE_RANGE.end()
+            )));
+        }
+
+        if let Some(megapixels) = self.large_image_preview_megapixels {
+            if !LARGE_IMAGE_PREVIEW_MEGAPIXELS_RANGE.contains(&megapixels) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Large image preview threshold must be between {} and {} megapixels",
+                    LARGE_IMAGE_PREVIEW_MEGAPIXELS_RANGE.start(),
+                    LARGE_IMAGE_PREVIEW_MEGAPIXELS_RANGE.end()
+                )));
+            }
+        }
+
+        for pattern in &self.scan_exclude {
+            glob::Pattern::new(pattern).map_err(|e| {
+                GlimpseError::InvalidConfig(format!(
+                    "Invalid exclude pattern \"{}\": {}",
+                    pattern, e
+                ))
+            })?;
+        }
+
+        for extension in self
+            .extra_raw_extensions
+            .iter()
+            .chain(&self.extra_image_extensions)
+        {
+            if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Invalid file extension \"{}\" (use letters and digits without the dot)",
+                    extension
+                )));
+            }
+        }
+        for extension in &self.extra_image_extensions {
+            if !image::ImageFormat::from_extension(extension).is_some_and(|f| f.reading_enabled()) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Images with the extension \"{}\" can't be decoded",
+                    extension
+                )));
+            }
+        }
+
+        let mut preset_names = std::collections::HashSet::new();
+        for preset in &self.export_presets {
+            if preset.name.trim().is_empty() || !preset_names.insert(preset.name.as_str()) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Export preset names must be unique and not empty: \"{}\"",
+                    preset.name
+                )));
+            }
+            let settings = &preset.settings;
+            if !std::path::Path::new(&settings.destination).is_absolute() {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Destination of export preset \"{}\" must be an absolute path",
+                    preset.name
+                )));
+            }
+            if settings.options.max_size == Some(0) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "Export preset \"{}\" can't resize to 0 pixels",
+                    preset.name
+                )));
+            }
+            if let Some(template) = &settings.rename_template {
+                rename::validate_template(template)?;
+            }
+            if let Some(small_jpeg) = &settings.options.small_jpeg {
+                small_jpeg.validate()?;
+                if settings.is_move() {
+                    return Err(GlimpseError::InvalidConfig(format!(
+                        "Export preset \"{}\" can't move files as small JPEGs",
+                        preset.name
+                    )));
+                }
+            }
+            if let Some(watermark) = &settings.options.watermark {
+                watermark.validate()?;
+            }
+            if let Some(sidecar) = &settings.options.xmp_sidecar {
+                if !(1..=5).contains(&sidecar.rating) {
+                    return Err(GlimpseError::InvalidConfig(format!(
+                        "XMP rating of export preset \"{}\" must be 1 to 5 stars",
+                        preset.name
+                    )));
+                }
+            }
+        }
+
+        let mut target_names = std::collections::HashSet::new();
+        for target in &self.sftp_targets {
+            if target.name.trim().is_empty() || !target_names.insert(target.name.as_str()) {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "SFTP target names must be unique and not empty: \"{}\"",
+                    target.name
+                )));
+            }
+            // Both end up on the ssh command line
+            let invalid = |value: &str| {
+                value.is_empty() || value.starts_with('-') || value.contains(char::is_whitespace)
+            };
+            if invalid(&target.host) || invalid(&target.user) || target.port == 0 {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "SFTP target \"{}\" needs a host, user and port",
+                    target.name
+                )));
+            }
+            if target.remote_dir.trim().is_empty() {
+                return Err(GlimpseError::InvalidConfig(format!(
+                    "SFTP target \"{}\" needs a remote folder",
+                    target.name
+                )));
+            }
+        }
+
+        validate_preview_size(self.preview_size)?;
+        validate_keybindings(&self.keybindings)
+    }
+
+    /// Get keybindings with defaults filled in for actions that were not customized
+    pub fn effective_keybindings(&self) -> Keybindings {
+        let mut keybindings = default_keybindings();
+        keybindings.extend(self.keybindings.clone());
+        keybindings
+    }
+
     /// Get config file path
     fn config_path() -> Option<PathBuf> {
         dirs::config_dir().map(|p| p.join("Glimpse").join("config.json"))
@@ -27,19 +707,33 @@ impl AppConfig {
     }
 
     /// Save config
-    pub fn save(&self) -> Result<(), String> {
-        let path = Self::config_path().ok_or("Cannot find config directory")?;
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()
+            .ok_or_else(|| GlimpseError::InvalidPath("Cannot find config directory".into()))?;
 
         // Create directory
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            fs::create_dir_all(parent).map_err(|e| GlimpseError::io_at(parent, e))?;
         }
 
-        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(&path, content).map_err(|e| e.to_string())
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| GlimpseError::InvalidConfig(e.to_string()))?;
+        fs::write(&path, content).map_err(|e| GlimpseError::io_at(&path, e))
     }
 }
 
+/// Validate a preview size (shared with per-session overrides)
+pub fn validate_preview_size(size: u32) -> Result<()> {
+    if !PREVIEW_SIZE_RANGE.contains(&size) {
+        return Err(GlimpseError::InvalidConfig(format!(
+            "Preview size must be between {} and {}",
+            PREVIEW_SIZE_RANGE.start(),
+            PREVIEW_SIZE_RANGE.end()
+        )));
+    }
+    Ok(())
+}
+
 /// Get global config
 pub fn get_config() -> AppConfig {
     CONFIG
@@ -49,8 +743,129 @@ pub fn get_config() -> AppConfig {
         .clone()
 }
 
+/// Read one value of the global config without cloning all of it
+fn read_config<T>(read: impl FnOnce(&AppConfig) -> T) -> T {
+    read(
+        &CONFIG
+            .get_or_init(|| std::sync::RwLock::new(AppConfig::load()))
+            .read()
+            .unwrap(),
+    )
+}
+
+/// Get the cache format
+pub fn get_cache_format() -> CacheFormat {
+    read_config(|config| config.cache_format)
+}
+
+pub fn get_raw_backend() -> RawBackend {
+    read_config(|config| config.raw_backend)
+}
+
+pub fn get_raw_demosaic() -> RawDemosaic {
+    read_config(|config| config.raw_demosaic)
+}
+
+/// Handling of cloud placeholder files
+pub fn get_placeholder_policy() -> PlaceholderPolicy {
+    read_config(|config| config.cloud_placeholders)
+}
+
+pub fn get_power_mode() -> PowerMode {
+    read_config(|config| config.power_mode)
+}
+
+/// Handling of symlinks in folder scans
+pub fn get_symlink_policy() -> SymlinkPolicy {
+    read_config(|config| config.symlinks)
+}
+
+/// Whether folder scans include hidden and system files
+pub fn get_include_hidden_files() -> bool {
+    read_config(|config| config.include_hidden_files)
+}
+
+/// Compiled exclude patterns for folder scans
+pub fn get_scan_exclude() -> Vec<glob::Pattern> {
+    read_config(|config| {
+        config
+            .scan_exclude
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect()
+    })
+}
+
+/// External RAW decoder to fall back to, if one is configured
+pub fn get_external_raw_decoder() -> Option<PathBuf> {
+    read_config(|config| config.external_raw_decoder.as_ref().map(PathBuf::from))
+}
+
+pub fn get_geonames_file() -> Option<PathBuf> {
+    read_config(|config| config.geonames_file.as_ref().map(PathBuf::from))
+}
+
+/// ISO from which RAW previews get chroma noise reduction, if it's turned on
+pub fn get_preview_denoise_iso() -> Option<u32> {
+    read_config(|config| config.preview_denoise_iso)
+}
+
+/// Whether RAW previews get lens corrections
+pub fn get_lens_correction() -> bool {
+    read_config(|config| config.lens_correction)
+}
+
+pub fn get_lensfun_dir() -> Option<PathBuf> {
+    read_config(|config| config.lensfun_dir.as_ref().map(PathBuf::from))
+}
+
+/// Artist and copyright exported JPEGs are stamped with; blank ones are left out
+pub fn get_export_credits() -> MetadataEdits {
+    read_config(|config| {
+        let value = |field: &Option<String>| {
+            field
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        MetadataEdits {
+            artist: value(&config.artist),
+            copyright: value(&config.copyright),
+            date_taken: None,
+        }
+    })
+}
+
+/// Whether the extension was added to the RAW extensions in the config
+pub fn is_extra_raw_extension(extension: &str) -> bool {
+    read_config(|config| {
+        config
+            .extra_raw_extensions
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Whether the extension was added to the still image extensions in the config
+pub fn is_extra_image_extension(extension: &str) -> bool {
+    read_config(|config| {
+        config
+            .extra_image_extensions
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Pixel count above which standard images get a preview
+pub fn get_large_image_preview_pixels() -> Option<u64> {
+    read_config(|config| config.large_image_preview_megapixels)
+        .map(|megapixels| megapixels as u64 * 1_000_000)
+}
+
 /// Update global config
-pub fn update_config(config: AppConfig) -> Result<(), String> {
+pub fn update_config(config: AppConfig) -> Result<()> {
+    config.validate()?;
     config.save()?;
 
     if let Some(lock) = CONFIG.get() {
@@ -69,14 +884,45 @@ pub fn get_cpu_count() -> usize {
 }
 
 /// Get number of threads for thumbnail generation
+/// Halved in low-power mode
 pub fn get_thumbnail_thread_count() -> usize {
     let config = get_config();
     let cpu_count = get_cpu_count();
 
-    config.thumbnail_threads.unwrap_or_else(|| {
+    let threads = config.thumbnail_threads.unwrap_or_else(|| {
         // Default: 80% of CPU logical cores (minimum 2)
         calculate_default_threads(cpu_count)
-    })
+    });
+    if power::is_low_power() {
+        power::low_power_threads(threads)
+    } else {
+        threads
+    }
+}
+
+/// Get number of RAW files decoded at once, never more than the thumbnail threads
+pub fn get_raw_decode_thread_count() -> usize {
+    let thumbnail_threads = get_thumbnail_thread_count();
+    read_config(|c| c.raw_decode_threads)
+        .unwrap_or_else(|| calculate_default_raw_threads(thumbnail_threads))
+        .min(thumbnail_threads)
+}
+
+/// Get number of threads of the EXIF job that runs next to thumbnail generation
+pub fn get_exif_thread_count() -> usize {
+    split_exif_threads(get_thumbnail_thread_count()).1
+}
+
+/// Split the thumbnail threads into (thumbnail, EXIF) threads for an opened folder
+/// EXIF reads mostly wait on I/O, so they get a quarter; each gets at least one
+pub fn split_exif_threads(threads: usize) -> (usize, usize) {
+    let exif = (threads / 4).max(1);
+    (threads.saturating_sub(exif).max(1), exif)
+}
+
+/// Default RAW decode count: half of the thumbnail threads (minimum 1)
+pub fn calculate_default_raw_threads(thumbnail_threads: usize) -> usize {
+    (thumbnail_threads / 2).max(1)
 }
 
 /// Calculate default thread count (80% of CPU logical cores, minimum 2)
@@ -93,6 +939,285 @@ mod tests {
     fn test_app_config_default() {
         let config = AppConfig::default();
         assert!(config.thumbnail_threads.is_none());
+        assert!(config.cache_limit_mb.is_none());
+        assert_eq!(config.thumbnail_size, DEFAULT_THUMBNAIL_SIZE);
+        assert_eq!(config.preview_size, DEFAULT_PREVIEW_SIZE);
+        assert_eq!(config.export.mode, ExportMode::Copy);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_app_config_loads_legacy_file() {
+        // Config files written before the extra fields existed only contain thread count
+        let parsed: AppConfig = serde_json::from_str(r#"{"thumbnail_threads": 4}"#).unwrap();
+        assert_eq!(parsed.thumbnail_threads, Some(4));
+        assert_eq!(parsed.thumbnail_size, DEFAULT_THUMBNAIL_SIZE);
+        assert_eq!(parsed.preview_size, DEFAULT_PREVIEW_SIZE);
+        assert!(parsed.export.destination.is_none());
+    }
+
+    #[test]
+    fn test_cache_format() {
+        let parsed: AppConfig = serde_json::from_str(r#"{"cache_format": "avif"}"#).unwrap();
+        assert_eq!(parsed.cache_format, CacheFormat::Avif);
+        assert_eq!(AppConfig::default().cache_format, CacheFormat::Jpeg);
+
+        assert_eq!(
+            CacheFormat::from_path(std::path::Path::new("/cache/a_preview.avif")),
+            CacheFormat::Avif
+        );
+        assert_eq!(
+            CacheFormat::from_path(std::path::Path::new("/cache/a.jpg")),
+            CacheFormat::Jpeg
+        );
+    }
+
+    fn preset(name: &str) -> ExportPreset {
+        ExportPreset {
+            name: name.to_string(),
+            settings: ExportSettings {
+                destination: "/Volumes/Delivery".to_string(),
+                mode: ExportMode::Copy,
+                filter: ExportFilter::Adopted,
+                rename_template: Some("{date}_{seq}".to_string()),
+                seq_order: SequenceOrder::Name,
+                gear: GearFilter::default(),
+                new_only: true,
+                options: ExportOptions {
+                    max_size: Some(2048),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    fn sftp_target(name: &str) -> SftpTarget {
+        SftpTarget {
+            name: name.to_string(),
+            host: "delivery.example.com".to_string(),
+            port: 2222,
+            user: "studio".to_string(),
+            remote_dir: "/srv/proofs".to_string(),
+            identity_file: None,
+        }
+    }
+
+    #[test]
+    fn test_sftp_target_url() {
+        assert_eq!(
+            sftp_target("proofs").url(),
+            "sftp://studio@delivery.example.com:2222/srv/proofs"
+        );
+    }
+
+    #[test]
+    fn test_export_filter() {
+        assert!(ExportFilter::NotRejected.includes(None));
+        assert!(!ExportFilter::NotRejected.includes(Some("rejected")));
+        assert!(!ExportFilter::Adopted.includes(None));
+        assert!(ExportFilter::Adopted.includes(Some("adopted")));
+        assert!(ExportFilter::Rejected.includes(Some("rejected")));
+        assert!(!ExportFilter::Rejected.includes(None));
+        assert!(ExportFilter::All.includes(Some("rejected")));
+    }
+
+    #[test]
+    fn test_app_config_validate() {
+        let invalid = [
+            AppConfig {
+                thumbnail_threads: Some(0),
+                ..Default::default()
+            },
+            AppConfig {
+                thumbnail_threads: Some(get_cpu_count() + 1),
+                ..Default::default()
+            },
+            AppConfig {
+                raw_decode_threads: Some(0),
+                ..Default::default()
+            },
+            AppConfig {
+                cache_limit_mb: Some(10),
+                ..Default::default()
+            },
+            AppConfig {
+                cache_expiry_days: Some(0),
+                ..Default::default()
+            },
+            AppConfig {
+                cache_dir: Some("relative/cache".to_string()),
+                ..Default::default()
+            },
+            AppConfig {
+                scan_exclude: vec!["[unclosed".to_string()],
+                ..Default::default()
+            },
+            AppConfig {
+                extra_raw_extensions: vec![".nrw".to_string()],
+                ..Default::default()
+            },
+            AppConfig {
+                extra_image_extensions: vec!["psd2".to_string()],
+                ..Default::default()
+            },
+            AppConfig {
+                external_raw_decoder: Some("dcraw_emu".to_string()),
+                ..Default::default()
+            },
+            AppConfig {
+                thumbnail_size: 50,
+                ..Default::default()
+            },
+            AppConfig {
+                preview_size: 10000,
+                ..Default::default()
+            },
+            AppConfig {
+                large_image_preview_megapixels: Some(0),
+                ..Default::default()
+            },
+            AppConfig {
+                export_presets: vec![preset("Proofs"), preset("Proofs")],
+                ..Default::default()
+            },
+            AppConfig {
+                export_presets: vec![{
+                    let mut preset = preset("Proofs");
+                    preset.settings.destination = "relative/proofs".to_string();
+                    preset
+                }],
+                ..Default::default()
+            },
+            AppConfig {
+                sftp_targets: vec![sftp_target("Studio"), sftp_target("Studio")],
+                ..Default::default()
+            },
+            AppConfig {
+                sftp_targets: vec![SftpTarget {
+                    host: "-oProxyCommand=evil".to_string(),
+                    ..sftp_target("Studio")
+                }],
+                ..Default::default()
+            },
+            AppConfig {
+                sftp_targets: vec![SftpTarget {
+                    remote_dir: String::new(),
+                    ..sftp_target("Studio")
+                }],
+                ..Default::default()
+            },
+            AppConfig {
+                export_presets: vec![{
+                    let mut preset = preset("Proofs");
+                    preset.settings.rename_template = Some("{date}_{unknown}".to_string());
+                    preset
+                }],
+                ..Default::default()
+            },
+        ];
+        for config in &invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+        let rawler = AppConfig {
+            raw_backend: RawBackend::Rawler,
+            ..Default::default()
+        };
+        assert_eq!(rawler.validate().is_ok(), cfg!(feature = "rawler"));
+
+        let valid = AppConfig {
+            thumbnail_threads: Some(1),
+            raw_decode_threads: Some(1),
+            cache_limit_mb: Some(2048),
+            cache_expiry_days: Some(90),
+            cache_dir: Some(
+                std::env::temp_dir()
+                    .join("glimpse-cache")
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            thumbnail_size: 400,
+            preview_size: 3000,
+            cache_format: CacheFormat::Avif,
+            raw_white_balance: WhiteBalance::Daylight,
+            raw_demosaic: RawDemosaic::Fast,
+            raw_backend: RawBackend::Rawloader,
+            external_raw_decoder: None,
+            large_image_preview_megapixels: None,
+            cloud_placeholders: PlaceholderPolicy::Hydrate,
+            symlinks: SymlinkPolicy::Ignore,
+            include_hidden_files: true,
+            scan_exclude: vec!["*_rejects/*".to_string(), "*.tmp".to_string()],
+            extra_raw_extensions: vec!["nrw".to_string()],
+            extra_image_extensions: vec!["tif".to_string(), "webp".to_string()],
+            power_mode: PowerMode::Saver,
+            export: ExportDefaults {
+                mode: ExportMode::Move,
+                destination: Some("/tmp/export".to_string()),
+            },
+            export_presets: vec![preset("Client proofs"), preset("Archive copy")],
+            sftp_targets: vec![sftp_target("Studio")],
+            geonames_file: None,
+            preview_denoise_iso: Some(3200),
+            lens_correction: true,
+            lensfun_dir: Some(std::env::temp_dir().to_string_lossy().to_string()),
+            artist: Some("Jane Doe".to_string()),
+            copyright: Some("(c) Studio North".to_string()),
+            keybindings: Keybindings::from([("toggle_label".to_string(), "X".to_string())]),
+        };
+        assert!(valid.validate().is_ok());
+    }
+
+    #[test]
+    fn test_normalize_key_combo() {
+        assert_eq!(normalize_key_combo("ctrl+o"), Some("Mod+O".to_string()));
+        assert_eq!(normalize_key_combo("Cmd+O"), Some("Mod+O".to_string()));
+        assert_eq!(
+            normalize_key_combo("Shift+Mod+x"),
+            Some("Mod+Shift+X".to_string())
+        );
+        assert_eq!(normalize_key_combo("Enter"), Some("Enter".to_string()));
+        assert_eq!(normalize_key_combo("Shift++"), Some("Shift++".to_string()));
+        assert_eq!(normalize_key_combo(""), None);
+        assert_eq!(normalize_key_combo("Hyper+O"), None);
+    }
+
+    #[test]
+    fn test_validate_keybindings() {
+        assert!(validate_keybindings(&default_keybindings()).is_ok());
+
+        // Rebinding to a free key is fine
+        let custom = Keybindings::from([("enter_compare".to_string(), "V".to_string())]);
+        assert!(validate_keybindings(&custom).is_ok());
+
+        // Conflicts with a default binding ("toggle_label" is "1")
+        let conflict = Keybindings::from([("enter_compare".to_string(), "1".to_string())]);
+        assert!(validate_keybindings(&conflict).is_err());
+
+        // Conflicts regardless of modifier spelling
+        let conflict = Keybindings::from([("export".to_string(), "ctrl+o".to_string())]);
+        assert!(validate_keybindings(&conflict).is_err());
+
+        // Swapping two bindings is fine
+        let swapped = Keybindings::from([
+            ("toggle_label".to_string(), "2".to_string()),
+            ("toggle_label_compare".to_string(), "1".to_string()),
+        ]);
+        assert!(validate_keybindings(&swapped).is_ok());
+
+        let unknown = Keybindings::from([("launch_rocket".to_string(), "R".to_string())]);
+        assert!(validate_keybindings(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_effective_keybindings() {
+        let config = AppConfig {
+            keybindings: Keybindings::from([("enter_gallery".to_string(), "Y".to_string())]),
+            ..Default::default()
+        };
+        let keybindings = config.effective_keybindings();
+        assert_eq!(keybindings.len(), DEFAULT_KEYBINDINGS.len());
+        assert_eq!(keybindings["enter_gallery"], "Y");
+        assert_eq!(keybindings["open_folder"], "Mod+O");
     }
 
     #[test]
@@ -106,10 +1231,16 @@ mod tests {
         // Some value case
         let config = AppConfig {
             thumbnail_threads: Some(4),
+            cache_limit_mb: Some(1024),
+            export: ExportDefaults {
+                mode: ExportMode::Move,
+                destination: Some("/Volumes/Backup".to_string()),
+            },
+            ..Default::default()
         };
         let json = serde_json::to_string(&config).unwrap();
         let parsed: AppConfig = serde_json::from_str(&json).unwrap();
-        assert_eq!(config.thumbnail_threads, parsed.thumbnail_threads);
+        assert_eq!(config, parsed);
     }
 
     #[test]
@@ -120,6 +1251,7 @@ mod tests {
         // Save config
         let config = AppConfig {
             thumbnail_threads: Some(6),
+            ..Default::default()
         };
         let content = serde_json::to_string_pretty(&config).unwrap();
         std::fs::write(&config_path, &content).unwrap();
@@ -161,6 +1293,21 @@ mod tests {
         assert_eq!(calculate_default_threads(16), 13);
     }
 
+    #[test]
+    fn test_calculate_default_raw_threads() {
+        assert_eq!(calculate_default_raw_threads(2), 1);
+        assert_eq!(calculate_default_raw_threads(6), 3);
+        assert_eq!(calculate_default_raw_threads(13), 6);
+    }
+
+    #[test]
+    fn test_split_exif_threads() {
+        assert_eq!(split_exif_threads(1), (1, 1));
+        assert_eq!(split_exif_threads(2), (1, 1));
+        assert_eq!(split_exif_threads(8), (6, 2));
+        assert_eq!(split_exif_threads(13), (10, 3));
+    }
+
     #[test]
     fn test_calculate_default_threads_minimum() {
         // Minimum 2 threads guaranteed
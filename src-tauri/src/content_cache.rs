@@ -0,0 +1,333 @@
+// Cache entries shared between sessions, keyed by a fingerprint of the source file
+// Session caches stay where the frontend expects them; identical files are hard-linked
+// to one copy in the shared folder instead of being generated again
+
+use crate::error::{GlimpseError, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Folder inside the cache base holding the shared entries
+pub const SHARED_CACHE_FOLDER: &str = "shared";
+
+/// Bytes read from the start and the end of a file for its fingerprint
+/// Enough to tell photos apart without reading whole RAW files
+const FINGERPRINT_CHUNK: u64 = 64 * 1024;
+
+/// Result of a dedup pass
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DedupStats {
+    /// Cache files replaced by a link to an identical one
+    pub files_linked: usize,
+    pub bytes_reclaimed: u64,
+}
+
+/// Fingerprint of a source file: its length plus the first and last 64 KiB
+/// Stays the same when the file is moved, renamed or opened from another folder
+pub fn fingerprint(path: &Path) -> Result<String> {
+    let mut file = File::open(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let len = file.metadata()?.len();
+
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+    let mut chunk = Vec::with_capacity(FINGERPRINT_CHUNK as usize);
+    (&mut file)
+        .take(FINGERPRINT_CHUNK)
+        .read_to_end(&mut chunk)?;
+    hasher.update(&chunk);
+    if len > FINGERPRINT_CHUNK * 2 {
+        chunk.clear();
+        file.seek(SeekFrom::End(-(FINGERPRINT_CHUNK as i64)))?;
+        file.read_to_end(&mut chunk)?;
+        hasher.update(&chunk);
+    }
+    Ok(hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Shared entry for a session cache file
+/// Session cache files live in `<cache base>/<session>/<thumbnails|previews>/`
+fn shared_path_for(output_path: &Path, fingerprint: &str, variant: &str) -> Option<PathBuf> {
+    let cache_base = output_path.parent()?.parent()?.parent()?;
+    let extension = output_path.extension()?.to_str()?;
+    Some(
+        cache_base
+            .join(SHARED_CACHE_FOLDER)
+            .join(&fingerprint[..2])
+            .join(format!("{}_{}.{}", fingerprint, variant, extension)),
+    )
+}
+
+/// Fill `output_path` from the shared cache when the same file was cached before
+/// (by any session), otherwise run `generate` and add the result to the shared cache
+/// `variant` tells apart entries of one file, e.g. thumbnails and previews of different sizes
+pub fn reuse_or_generate<F>(
+    image_path: &Path,
+    output_path: &Path,
+    variant: &str,
+    generate: F,
+) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let shared_path = match fingerprint(image_path) {
+        Ok(fingerprint) => shared_path_for(output_path, &fingerprint, variant),
+        Err(e) => {
+            tracing::debug!("Cannot fingerprint {}: {}", image_path.display(), e);
+            None
+        }
+    };
+    let Some(shared_path) = shared_path else {
+        return generate();
+    };
+
+    if shared_path.exists() && link_or_copy(&shared_path, output_path).is_ok() {
+        return Ok(());
+    }
+
+    generate()?;
+    let shared = shared_path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| link_or_copy(output_path, &shared_path));
+    if let Err(e) = shared {
+        tracing::debug!("Cannot share {}: {}", output_path.display(), e);
+    }
+    Ok(())
+}
+
+/// Hard-link `src` to `dst`, copying when the file system has no hard links
+/// An existing `dst` is left as it is
+fn link_or_copy(src: &Path, dst: &Path) -> std::io::Result<()> {
+    match std::fs::hard_link(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        Err(_) => {
+            let temp_path = temp_path_for(dst);
+            std::fs::copy(src, &temp_path)?;
+            std::fs::rename(&temp_path, dst).inspect_err(|_| {
+                let _ = std::fs::remove_file(&temp_path);
+            })
+        }
+    }
+}
+
+/// Replace `dst` with a hard link to `src` in one rename, so readers never miss the file
+fn replace_with_link(src: &Path, dst: &Path) -> std::io::Result<()> {
+    let temp_path = temp_path_for(dst);
+    std::fs::hard_link(src, &temp_path)?;
+    std::fs::rename(&temp_path, dst).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.link", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Identity of the data behind a path; hard links to one file share it
+#[cfg(unix)]
+pub fn file_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Number of paths linked to the data of a file
+#[cfg(unix)]
+fn link_count(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Delete shared entries no session links to any more
+/// Returns the bytes freed; nothing is pruned where link counts are unavailable
+pub fn prune_unshared(cache_base: &Path) -> u64 {
+    walkdir::WalkDir::new(cache_base.join(SHARED_CACHE_FOLDER))
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            (link_count(&metadata)? == 1 && std::fs::remove_file(entry.path()).is_ok())
+                .then_some(metadata.len())
+        })
+        .sum()
+}
+
+/// Link identical cache files under `cache_base` to a single copy
+/// Reclaims what was generated twice before entries were shared, or while sharing
+/// wasn't possible; identical source files give byte-identical cache files
+pub fn dedup(cache_base: &Path) -> DedupStats {
+    // Group by size first so only candidates get hashed
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in walkdir::WalkDir::new(cache_base)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_cache_file(entry.path()))
+    {
+        if let Ok(metadata) = entry.metadata() {
+            by_size
+                .entry(metadata.len())
+                .or_default()
+                .push(entry.into_path());
+        }
+    }
+
+    let mut stats = DedupStats::default();
+    for (size, paths) in by_size.into_iter().filter(|(_, paths)| paths.len() > 1) {
+        let mut by_hash: HashMap<[u8; 32], Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            if let Ok(data) = std::fs::read(&path) {
+                by_hash
+                    .entry(Sha256::digest(&data).into())
+                    .or_default()
+                    .push(path);
+            }
+        }
+
+        for paths in by_hash.into_values() {
+            let Some((keep, duplicates)) = paths.split_first() else {
+                continue;
+            };
+            let keep_id = std::fs::metadata(keep).ok().and_then(|m| file_id(&m));
+            // Data of files linked to each other is only reclaimed once
+            let mut replaced_ids = HashSet::new();
+            for duplicate in duplicates {
+                let id = std::fs::metadata(duplicate).ok().and_then(|m| file_id(&m));
+                if keep_id.is_some() && id == keep_id {
+                    continue;
+                }
+                match replace_with_link(keep, duplicate) {
+                    Ok(()) => {
+                        stats.files_linked += 1;
+                        if id.is_none() || replaced_ids.insert(id) {
+                            stats.bytes_reclaimed += size;
+                        }
+                    }
+                    Err(e) => tracing::debug!("Cannot link {}: {}", duplicate.display(), e),
+                }
+            }
+        }
+    }
+    stats
+}
+
+/// Finished thumbnails and previews; skips files still being written
+fn is_cache_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default();
+    !name.contains(".tmp.") && (extension == "jpg" || extension == "avif")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fingerprint_ignores_location() {
+        let dir = tempdir().unwrap();
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.path().join("a.jpg"), &data).unwrap();
+        fs::create_dir(dir.path().join("renamed")).unwrap();
+        fs::write(dir.path().join("renamed").join("b.jpg"), &data).unwrap();
+
+        let mut changed = data.clone();
+        *changed.last_mut().unwrap() ^= 1;
+        fs::write(dir.path().join("c.jpg"), &changed).unwrap();
+
+        let a = fingerprint(&dir.path().join("a.jpg")).unwrap();
+        assert_eq!(
+            a,
+            fingerprint(&dir.path().join("renamed").join("b.jpg")).unwrap()
+        );
+        assert_ne!(a, fingerprint(&dir.path().join("c.jpg")).unwrap());
+    }
+
+    #[test]
+    fn test_reuse_across_sessions() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("photo.jpg");
+        fs::write(&source, b"source").unwrap();
+        let first = dir.path().join("cache").join("s1").join("thumbnails");
+        let second = dir.path().join("cache").join("s2").join("thumbnails");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        let mut generated = 0;
+        for dir in [&first, &second] {
+            let output = dir.join("photo.jpg");
+            reuse_or_generate(&source, &output, "t320", || {
+                generated += 1;
+                fs::write(&output, b"thumbnail")?;
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(fs::read(&output).unwrap(), b"thumbnail");
+        }
+        assert_eq!(generated, 1);
+
+        // A different variant is generated separately
+        let preview = first.join("photo_preview.jpg");
+        reuse_or_generate(&source, &preview, "p2000", || {
+            generated += 1;
+            fs::write(&preview, b"preview")?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(generated, 2);
+    }
+
+    #[test]
+    fn test_dedup_links_identical_files() {
+        let dir = tempdir().unwrap();
+        for session in ["s1", "s2", "s3"] {
+            let thumbnails = dir.path().join(session).join("thumbnails");
+            fs::create_dir_all(&thumbnails).unwrap();
+            fs::write(thumbnails.join("a.jpg"), b"same thumbnail").unwrap();
+        }
+        fs::write(
+            dir.path().join("s1").join("thumbnails").join("b.jpg"),
+            b"different!!!!!",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("s2").join("thumbnails").join("a.1.tmp.jpg"),
+            b"same thumbnail",
+        )
+        .unwrap();
+
+        let stats = dedup(dir.path());
+        assert_eq!(
+            stats,
+            DedupStats {
+                files_linked: 2,
+                bytes_reclaimed: 2 * 14,
+            }
+        );
+        assert_eq!(
+            fs::read(dir.path().join("s3").join("thumbnails").join("a.jpg")).unwrap(),
+            b"same thumbnail"
+        );
+
+        // Already linked files are not counted again
+        if cfg!(unix) {
+            assert_eq!(dedup(dir.path()), DedupStats::default());
+        }
+    }
+}
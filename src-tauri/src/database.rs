@@ -1,6 +1,33 @@
+use crate::analysis::ImageAnalysis;
+use crate::config::{ExportSettings, WhiteBalance};
 use crate::error::{GlimpseError, Result};
+use crate::export::ExportResult;
+use crate::image_processor::{normalize_path, ExifInfo, ImageInfo, RawAdjustments};
+use crate::metadata::MetadataEdits;
+use crate::rename::Rename;
 use rusqlite::{params, Connection};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Random ID of this run of the app, which journaled operations are tagged with
+/// Process IDs get reused, so one left by a crashed run could pass for a running one
+fn run_id() -> &'static str {
+    static RUN_ID: OnceLock<String> = OnceLock::new();
+    RUN_ID.get_or_init(|| {
+        use std::hash::{BuildHasher, Hasher};
+        // RandomState is seeded from the OS's random source once per process
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        hasher.write_u32(std::process::id());
+        format!("{:016x}", hasher.finish())
+    })
+}
 
 pub struct Database {
     conn: Connection,
@@ -16,11 +43,24 @@ impl Database {
         }
 
         let conn = Connection::open(&db_path)?;
+        // WAL commits don't rewrite the database file and survive an app crash as soon as
+        // they return; only a power loss can undo the last few, except for labels (see
+        // `set_label`)
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
         let db = Self { conn };
         db.init_schema()?;
         Ok(db)
     }
 
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let db = Self {
+            conn: Connection::open_in_memory()?,
+        };
+        db.init_schema()?;
+        Ok(db)
+    }
+
     fn get_db_path() -> Result<PathBuf> {
         let data_dir = dirs::data_dir()
             .ok_or_else(|| GlimpseError::InvalidPath("Cannot find data directory".into()))?;
@@ -58,6 +98,214 @@ impl Database {
                 FOREIGN KEY (session_id) REFERENCES sessions(id)
             );
 
+            CREATE TABLE IF NOT EXISTS session_settings (
+                session_id TEXT PRIMARY KEY,
+                sort_order TEXT,
+                filter TEXT,
+                grid_zoom INTEGER,
+                preview_size INTEGER,
+                export_destination TEXT,
+                sync_dir TEXT,
+                white_balance TEXT,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS session_folders (
+                session_id TEXT,
+                position INTEGER,
+                folder_path TEXT NOT NULL,
+                PRIMARY KEY (session_id, position),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS image_analysis (
+                session_id TEXT,
+                filename TEXT,
+                file_stamp TEXT,
+                sharpness REAL,
+                subject_sharpness REAL,
+                highlight_clipping REAL,
+                shadow_clipping REAL,
+                dhash INTEGER,
+                faces TEXT,
+                eye_contrast REAL,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS exif_cache (
+                session_id TEXT,
+                filename TEXT,
+                original_modified TEXT,
+                exif TEXT,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS time_offsets (
+                session_id TEXT,
+                filename TEXT,
+                seconds INTEGER NOT NULL,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS metadata_edits (
+                session_id TEXT,
+                filename TEXT,
+                edits TEXT NOT NULL,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS file_fingerprints (
+                session_id TEXT,
+                filename TEXT,
+                file_stamp TEXT,
+                fingerprint TEXT NOT NULL,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS perceptual_hashes (
+                fingerprint TEXT PRIMARY KEY,
+                dhash INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS problem_files (
+                session_id TEXT,
+                filename TEXT,
+                original_modified TEXT,
+                error TEXT,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS thumbnail_failures (
+                session_id TEXT,
+                filename TEXT,
+                kind TEXT,
+                error TEXT,
+                failed_at TEXT,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS operations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT,
+                kind TEXT,
+                destination TEXT,
+                params TEXT,
+                run_id TEXT,
+                started_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS operation_files (
+                operation_id INTEGER,
+                source TEXT,
+                destination TEXT,
+                state TEXT,
+                PRIMARY KEY (operation_id, source),
+                FOREIGN KEY (operation_id) REFERENCES operations(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS compare_set (
+                session_id TEXT,
+                filename TEXT,
+                pinned_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS culling_passes (
+                session_id TEXT,
+                name TEXT,
+                created_at TEXT,
+                active INTEGER DEFAULT 0,
+                PRIMARY KEY (session_id, name),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS pass_labels (
+                session_id TEXT,
+                pass_name TEXT,
+                filename TEXT,
+                label TEXT,
+                PRIMARY KEY (session_id, pass_name, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS file_identities (
+                session_id TEXT,
+                filename TEXT,
+                size INTEGER,
+                modified_at TEXT,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS rename_history (
+                session_id TEXT,
+                position INTEGER,
+                from_name TEXT,
+                to_name TEXT,
+                PRIMARY KEY (session_id, position),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS image_adjustments (
+                session_id TEXT,
+                filename TEXT,
+                exposure REAL,
+                wb_temp REAL,
+                wb_tint REAL,
+                white_balance TEXT,
+                PRIMARY KEY (session_id, filename),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS bookmarks (
+                path TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS export_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT,
+                preset TEXT,
+                settings TEXT,
+                result TEXT,
+                exported_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS exported_files (
+                destination TEXT,
+                source TEXT,
+                size INTEGER,
+                modified_at TEXT,
+                exported_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (destination, source)
+            );
+
+            CREATE TABLE IF NOT EXISTS projects (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS project_sessions (
+                project_id INTEGER,
+                session_id TEXT,
+                position INTEGER,
+                PRIMARY KEY (project_id, session_id),
+                FOREIGN KEY (project_id) REFERENCES projects(id),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
             CREATE INDEX IF NOT EXISTS idx_labels_session ON labels(session_id);
             CREATE INDEX IF NOT EXISTS idx_thumbnail_cache_session ON thumbnail_cache(session_id);
             "#,
@@ -116,6 +364,136 @@ impl Database {
         Ok(())
     }
 
+    /// View state the session was left in (defaults if the session is unknown)
+    pub fn get_view_state(&self, session_id: &str) -> Result<ViewState> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filter, sort_order, grid_zoom FROM session_settings WHERE session_id = ?1",
+        )?;
+
+        let view_state = stmt
+            .query_row(params![session_id], |row| {
+                Ok(ViewState {
+                    filter: row.get(0)?,
+                    sort_order: row.get(1)?,
+                    grid_zoom: row.get(2)?,
+                })
+            })
+            .optional()?;
+
+        Ok(view_state.unwrap_or_default())
+    }
+
+    pub fn set_view_state(&self, session_id: &str, view_state: &ViewState) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO session_settings (session_id, filter, sort_order, grid_zoom, updated_at)
+            VALUES (?1, ?2, ?3, ?4, datetime('now'))
+            ON CONFLICT(session_id) DO UPDATE SET
+                filter = excluded.filter,
+                sort_order = excluded.sort_order,
+                grid_zoom = excluded.grid_zoom,
+                updated_at = excluded.updated_at
+            "#,
+            params![
+                session_id,
+                view_state.filter,
+                view_state.sort_order,
+                view_state.grid_zoom
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Session settings operations
+    pub fn get_session_settings(&self, session_id: &str) -> Result<SessionSettings> {
+        let mut stmt = self.conn.prepare(
+            "SELECT sort_order, filter, preview_size, export_destination, sync_dir, white_balance
+             FROM session_settings WHERE session_id = ?1",
+        )?;
+
+        let settings = stmt
+            .query_row(params![session_id], |row| {
+                Ok(SessionSettings {
+                    sort_order: row.get(0)?,
+                    filter: row.get(1)?,
+                    preview_size: row.get(2)?,
+                    export_destination: row.get(3)?,
+                    sync_dir: row.get(4)?,
+                    white_balance: row
+                        .get::<_, Option<String>>(5)?
+                        .as_deref()
+                        .and_then(WhiteBalance::parse),
+                })
+            })
+            .optional()?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn set_session_settings(&self, session_id: &str, settings: &SessionSettings) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO session_settings
+                (session_id, sort_order, filter, preview_size, export_destination, sync_dir,
+                 white_balance, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))
+            ON CONFLICT(session_id) DO UPDATE SET
+                sort_order = excluded.sort_order,
+                filter = excluded.filter,
+                preview_size = excluded.preview_size,
+                export_destination = excluded.export_destination,
+                sync_dir = excluded.sync_dir,
+                white_balance = excluded.white_balance,
+                updated_at = excluded.updated_at
+            "#,
+            params![
+                session_id,
+                settings.sort_order,
+                settings.filter,
+                settings.preview_size,
+                settings.export_destination,
+                settings.sync_dir,
+                settings.white_balance.map(WhiteBalance::as_str)
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Session folder operations
+    /// Source folders of a session; sessions opened from a single folder
+    /// have no rows and fall back to the session's folder path
+    pub fn get_session_folders(&self, session_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT folder_path FROM session_folders WHERE session_id = ?1 ORDER BY position",
+        )?;
+
+        let folders = stmt
+            .query_map(params![session_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        if folders.is_empty() {
+            return Ok(self
+                .get_session(session_id)?
+                .map(|s| vec![s.folder_path])
+                .unwrap_or_default());
+        }
+        Ok(folders)
+    }
+
+    pub fn set_session_folders(&self, session_id: &str, folders: &[String]) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM session_folders WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        for (position, folder) in folders.iter().enumerate() {
+            self.conn.execute(
+                "INSERT INTO session_folders (session_id, position, folder_path) VALUES (?1, ?2, ?3)",
+                params![session_id, position as i64, folder],
+            )?;
+        }
+        Ok(())
+    }
+
     // Label operations
     pub fn get_labels(&self, session_id: &str) -> Result<Vec<Label>> {
         let mut stmt = self
@@ -134,282 +512,3316 @@ impl Database {
         Ok(labels)
     }
 
+    /// When each label of a session was last changed ("YYYY-MM-DD HH:MM:SS" UTC)
+    pub fn get_label_times(&self, session_id: &str) -> Result<HashMap<String, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT filename, updated_at FROM labels WHERE session_id = ?1")?;
+
+        let times = stmt
+            .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(times)
+    }
+
+    /// Committed with a full sync before returning, so a label survives a crash and a power
+    /// loss as soon as it is set; a commit under WAL is a single append, cheap enough per key
     pub fn set_label(&self, session_id: &str, filename: &str, label: Option<&str>) -> Result<()> {
-        if let Some(label_value) = label {
-            self.conn.execute(
+        self.conn.execute_batch("PRAGMA synchronous = FULL")?;
+        let result = write_label(&self.conn, session_id, filename, label, &label_time());
+        self.conn.execute_batch("PRAGMA synchronous = NORMAL")?;
+        result
+    }
+
+    // Operation journal
+    // Destructive operations are written down before they touch any file and removed once
+    // they end, so whatever is left from an earlier run was interrupted by a crash
+
+    /// Journal an operation and the files it will process; returns its id
+    pub fn begin_operation(
+        &self,
+        session_id: &str,
+        kind: OperationKind,
+        destination: &str,
+        params: Option<&str>,
+        files: &[(String, String)],
+    ) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO operations (session_id, kind, destination, params, run_id)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            params![session_id, kind.as_str(), destination, params, run_id()],
+        )?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
                 r#"
-                INSERT INTO labels (session_id, filename, label, updated_at)
-                VALUES (?1, ?2, ?3, datetime('now'))
-                ON CONFLICT(session_id, filename) DO UPDATE SET
-                    label = excluded.label,
-                    updated_at = excluded.updated_at
+                INSERT OR REPLACE INTO operation_files (operation_id, source, destination, state)
+                VALUES (?1, ?2, ?3, ?4)
                 "#,
-                params![session_id, filename, label_value],
-            )?;
-        } else {
-            self.conn.execute(
-                "DELETE FROM labels WHERE session_id = ?1 AND filename = ?2",
-                params![session_id, filename],
             )?;
+            for (source, destination) in files {
+                stmt.execute(params![
+                    id,
+                    source,
+                    destination,
+                    OperationFileState::Pending.as_str()
+                ])?;
+            }
         }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn set_operation_file_state(
+        &self,
+        operation_id: i64,
+        source: &str,
+        state: OperationFileState,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE operation_files SET state = ?3 WHERE operation_id = ?1 AND source = ?2",
+            params![operation_id, source, state.as_str()],
+        )?;
         Ok(())
     }
 
-    // Thumbnail cache operations
-    pub fn get_thumbnail_cache(&self, session_id: &str, filename: &str) -> Result<Option<String>> {
+    /// Remove an operation from the journal once it ended or was resolved
+    pub fn finish_operation(&self, operation_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM operation_files WHERE operation_id = ?1",
+            params![operation_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM operations WHERE id = ?1",
+            params![operation_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_operation(&self, operation_id: i64) -> Result<Option<Operation>> {
         let mut stmt = self.conn.prepare(
-            "SELECT cache_path FROM thumbnail_cache WHERE session_id = ?1 AND filename = ?2",
+            "SELECT id, session_id, kind, destination, params, started_at
+             FROM operations WHERE id = ?1",
         )?;
 
-        let cache_path = stmt
-            .query_row(params![session_id, filename], |row| row.get(0))
+        let operation = stmt
+            .query_row(params![operation_id], |row| {
+                Ok(Operation {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    kind: OperationKind::parse(&row.get::<_, String>(2)?),
+                    destination: row.get(3)?,
+                    params: row.get(4)?,
+                    started_at: row.get(5)?,
+                })
+            })
             .optional()?;
 
-        Ok(cache_path)
+        Ok(operation)
     }
 
-    pub fn set_thumbnail_cache(
-        &self,
-        session_id: &str,
-        filename: &str,
-        cache_path: &str,
-        original_modified: &str,
-    ) -> Result<()> {
-        self.conn.execute(
-            r#"
-            INSERT INTO thumbnail_cache (session_id, filename, cache_path, original_modified)
-            VALUES (?1, ?2, ?3, ?4)
-            ON CONFLICT(session_id, filename) DO UPDATE SET
-                cache_path = excluded.cache_path,
-                original_modified = excluded.original_modified
-            "#,
-            params![session_id, filename, cache_path, original_modified],
+    pub fn get_operation_files(&self, operation_id: i64) -> Result<Vec<OperationFile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT source, destination, state FROM operation_files WHERE operation_id = ?1",
         )?;
-        Ok(())
-    }
 
-    // Storage info operations
-    pub fn get_label_count(&self) -> Result<i64> {
-        let count: i64 = self
-            .conn
-            .query_row("SELECT COUNT(*) FROM labels", [], |row| row.get(0))?;
-        Ok(count)
-    }
+        let files = stmt
+            .query_map(params![operation_id], |row| {
+                Ok(OperationFile {
+                    source: row.get(0)?,
+                    destination: row.get(1)?,
+                    state: OperationFileState::parse(&row.get::<_, String>(2)?),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
 
-    pub fn get_session_count(&self) -> Result<i64> {
-        let count: i64 = self
-            .conn
-            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))?;
-        Ok(count)
+        Ok(files)
     }
 
-    pub fn get_all_session_ids(&self) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare("SELECT id FROM sessions")?;
-        let ids = stmt
-            .query_map([], |row| row.get(0))?
+    /// Operations left unfinished by an earlier run of the app, oldest first
+    pub fn get_interrupted_operations(&self) -> Result<Vec<InterruptedOperation>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT o.id, o.session_id, o.kind, o.destination, o.started_at,
+                COUNT(CASE WHEN f.state = 'pending' THEN 1 END),
+                COUNT(CASE WHEN f.state = 'copied' THEN 1 END),
+                COUNT(CASE WHEN f.state = 'done' THEN 1 END)
+            FROM operations o LEFT JOIN operation_files f ON f.operation_id = o.id
+            WHERE o.run_id IS NOT ?1
+            GROUP BY o.id ORDER BY o.id
+            "#,
+        )?;
+
+        let operations = stmt
+            .query_map(params![run_id()], |row| {
+                Ok(InterruptedOperation {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    kind: OperationKind::parse(&row.get::<_, String>(2)?),
+                    destination: row.get(3)?,
+                    started_at: row.get(4)?,
+                    pending: row.get(5)?,
+                    copied: row.get(6)?,
+                    done: row.get(7)?,
+                })
+            })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
-        Ok(ids)
+
+        Ok(operations)
     }
 
-    pub fn clear_all_labels(&self) -> Result<i64> {
-        let count = self.get_label_count()?;
-        self.conn.execute("DELETE FROM labels", [])?;
-        Ok(count)
+    // Compare set operations
+
+    /// Pinned candidates of a session in the order they were pinned
+    pub fn get_compare_set(&self, session_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename FROM compare_set WHERE session_id = ?1 ORDER BY pinned_at, rowid",
+        )?;
+
+        let filenames = stmt
+            .query_map(params![session_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(filenames)
     }
 
-    pub fn clear_all_sessions(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM thumbnail_cache", [])?;
-        self.conn.execute("DELETE FROM labels", [])?;
-        self.conn.execute("DELETE FROM sessions", [])?;
+    /// Pin files; already pinned files keep their position
+    pub fn add_to_compare_set(&self, session_id: &str, filenames: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO compare_set (session_id, filename) VALUES (?1, ?2)",
+            )?;
+            for filename in filenames {
+                stmt.execute(params![session_id, filename])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
-}
 
-// rusqlite Optional trait workaround
-trait Optional<T> {
-    fn optional(self) -> std::result::Result<Option<T>, rusqlite::Error>;
-}
+    pub fn remove_from_compare_set(&self, session_id: &str, filenames: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt =
+                tx.prepare("DELETE FROM compare_set WHERE session_id = ?1 AND filename = ?2")?;
+            for filename in filenames {
+                stmt.execute(params![session_id, filename])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn clear_compare_set(&self, session_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM compare_set WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    // Bookmark operations
+
+    /// Pinned folders in the user's order
+    pub fn get_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, name FROM bookmarks ORDER BY position, rowid")?;
+
+        let bookmarks = stmt
+            .query_map([], |row| {
+                Ok(Bookmark {
+                    path: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(bookmarks)
+    }
+
+    /// Pin a folder at the end; pinning it again only renames it
+    pub fn add_bookmark(&self, path: &str, name: &str) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO bookmarks (path, name, position)
+            VALUES (?1, ?2, (SELECT COALESCE(MAX(position), -1) + 1 FROM bookmarks))
+            ON CONFLICT(path) DO UPDATE SET name = excluded.name
+            "#,
+            params![path, name],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false when the folder wasn't bookmarked
+    pub fn rename_bookmark(&self, path: &str, name: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE bookmarks SET name = ?2 WHERE path = ?1",
+            params![path, name],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn remove_bookmark(&self, path: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM bookmarks WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    /// Put bookmarks in the order of `paths`; ones not listed keep their place after them
+    pub fn reorder_bookmarks(&self, paths: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            tx.execute(
+                "UPDATE bookmarks SET position = position + ?1",
+                params![paths.len() as i64],
+            )?;
+            let mut stmt = tx.prepare("UPDATE bookmarks SET position = ?2 WHERE path = ?1")?;
+            for (position, path) in paths.iter().enumerate() {
+                stmt.execute(params![path, position as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Export history operations
+
+    /// Record an export run; returns its history id
+    pub fn add_export_record(
+        &self,
+        session_id: &str,
+        preset: Option<&str>,
+        settings: &ExportSettings,
+        result: &ExportResult,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO export_history (session_id, preset, settings, result) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                session_id,
+                preset,
+                serde_json::to_string(settings).ok(),
+                serde_json::to_string(result).ok(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Export runs of a session, newest first
+    pub fn get_export_history(&self, session_id: &str) -> Result<Vec<ExportRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, session_id, preset, settings, result, exported_at
+            FROM export_history
+            WHERE session_id = ?1
+            ORDER BY id DESC
+            "#,
+        )?;
+        let records = stmt
+            .query_map(params![session_id], export_record_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(records.into_iter().flatten().collect())
+    }
+
+    pub fn get_export_record(&self, id: i64) -> Result<Option<ExportRecord>> {
+        let record = self
+            .conn
+            .query_row(
+                "SELECT id, session_id, preset, settings, result, exported_at FROM export_history WHERE id = ?1",
+                params![id],
+                export_record_from_row,
+            )
+            .optional()?;
+        Ok(record.flatten())
+    }
+
+    /// Source files written to `destination` by earlier exports, as they were then
+    pub fn get_exported_files(&self, destination: &str) -> Result<HashMap<String, FileIdentity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT source, size, modified_at FROM exported_files WHERE destination = ?1",
+        )?;
+        let files = stmt
+            .query_map(params![destination], |row| {
+                Ok((
+                    row.get(0)?,
+                    FileIdentity {
+                        size: row.get::<_, i64>(1)? as u64,
+                        modified_at: row.get(2)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(files)
+    }
+
+    pub fn record_exported_file(
+        &self,
+        destination: &str,
+        source: &str,
+        identity: &FileIdentity,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO exported_files (destination, source, size, modified_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(destination, source) DO UPDATE SET
+                size = excluded.size,
+                modified_at = excluded.modified_at,
+                exported_at = CURRENT_TIMESTAMP
+            "#,
+            params![
+                destination,
+                source,
+                identity.size as i64,
+                identity.modified_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Project operations
+
+    pub fn create_project(&self, name: &str) -> Result<i64> {
+        self.conn
+            .execute("INSERT INTO projects (name) VALUES (?1)", params![name])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All projects with their sessions, oldest first
+    pub fn get_projects(&self) -> Result<Vec<Project>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, created_at FROM projects ORDER BY id")?;
+        let projects = stmt
+            .query_map([], |row| {
+                Ok(Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: row.get(2)?,
+                    session_ids: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        projects
+            .into_iter()
+            .map(|project| {
+                Ok(Project {
+                    session_ids: self.get_project_sessions(project.id)?,
+                    ..project
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_project(&self, project_id: i64) -> Result<Option<Project>> {
+        let project = self
+            .conn
+            .query_row(
+                "SELECT id, name, created_at FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| {
+                    Ok(Project {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        created_at: row.get(2)?,
+                        session_ids: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+
+        match project {
+            Some(project) => Ok(Some(Project {
+                session_ids: self.get_project_sessions(project_id)?,
+                ..project
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Sessions of a project in the order they were added
+    pub fn get_project_sessions(&self, project_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT session_id FROM project_sessions WHERE project_id = ?1 ORDER BY position",
+        )?;
+        let sessions = stmt
+            .query_map(params![project_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    /// Replace the sessions of a project
+    pub fn set_project_sessions(&self, project_id: i64, session_ids: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM project_sessions WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO project_sessions (project_id, session_id, position) VALUES (?1, ?2, ?3)",
+            )?;
+            for (position, session_id) in session_ids.iter().enumerate() {
+                stmt.execute(params![project_id, session_id, position as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns false when the project doesn't exist
+    pub fn rename_project(&self, project_id: i64, name: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE projects SET name = ?2 WHERE id = ?1",
+            params![project_id, name],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Delete a project; its sessions stay
+    pub fn delete_project(&self, project_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM project_sessions WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
+        Ok(())
+    }
+
+    /// Label counts of each session of a project, in project order
+    pub fn get_project_label_stats(&self, project_id: i64) -> Result<Vec<SessionLabelStats>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT s.id, s.folder_path, COALESCE(s.total_files, 0),
+                   (SELECT COUNT(*) FROM labels l WHERE l.session_id = s.id AND l.label = 'adopted'),
+                   (SELECT COUNT(*) FROM labels l WHERE l.session_id = s.id AND l.label = 'rejected')
+            FROM project_sessions p
+            JOIN sessions s ON s.id = p.session_id
+            WHERE p.project_id = ?1
+            ORDER BY p.position
+            "#,
+        )?;
+        let stats = stmt
+            .query_map(params![project_id], |row| {
+                let total_files: i64 = row.get(2)?;
+                let adopted: i64 = row.get(3)?;
+                let rejected: i64 = row.get(4)?;
+                Ok(SessionLabelStats {
+                    session_id: row.get(0)?,
+                    folder_path: row.get(1)?,
+                    total_files,
+                    adopted,
+                    rejected,
+                    unlabeled: (total_files - adopted - rejected).max(0),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(stats)
+    }
+
+    // Culling pass operations
+    // The labels table always holds the active pass; the others wait in pass_labels,
+    // so everything working on labels (export, sync, bundles) follows the active pass
+
+    /// Sessions from before passes get the default pass holding their labels
+    fn ensure_default_pass(&self, session_id: &str) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO culling_passes (session_id, name, created_at, active)
+            SELECT ?1, ?2, datetime('now'), 1
+            WHERE NOT EXISTS (SELECT 1 FROM culling_passes WHERE session_id = ?1)
+            "#,
+            params![session_id, DEFAULT_PASS],
+        )?;
+        Ok(())
+    }
+
+    /// Passes of a session in creation order
+    pub fn get_passes(&self, session_id: &str) -> Result<Vec<CullingPass>> {
+        self.ensure_default_pass(session_id)?;
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT name, created_at, active,
+                CASE WHEN active THEN
+                    (SELECT COUNT(*) FROM labels WHERE session_id = p.session_id)
+                ELSE
+                    (SELECT COUNT(*) FROM pass_labels
+                     WHERE session_id = p.session_id AND pass_name = p.name)
+                END
+            FROM culling_passes p WHERE session_id = ?1 ORDER BY created_at, rowid
+            "#,
+        )?;
+
+        let passes = stmt
+            .query_map(params![session_id], |row| {
+                Ok(CullingPass {
+                    name: row.get(0)?,
+                    created_at: row.get(1)?,
+                    active: row.get(2)?,
+                    label_count: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(passes)
+    }
+
+    pub fn get_active_pass(&self, session_id: &str) -> Result<String> {
+        self.ensure_default_pass(session_id)?;
+        let name = self.conn.query_row(
+            "SELECT name FROM culling_passes WHERE session_id = ?1 AND active",
+            params![session_id],
+            |row| row.get(0),
+        )?;
+        Ok(name)
+    }
+
+    /// Add a pass, starting empty or with a copy of the active pass's labels
+    pub fn create_pass(&self, session_id: &str, name: &str, copy_active: bool) -> Result<()> {
+        self.ensure_default_pass(session_id)?;
+        let inserted = self.conn.execute(
+            r#"
+            INSERT OR IGNORE INTO culling_passes (session_id, name, created_at, active)
+            VALUES (?1, ?2, datetime('now'), 0)
+            "#,
+            params![session_id, name],
+        )?;
+        if inserted == 0 {
+            return Err(GlimpseError::PassExists(name.to_string()));
+        }
+        if copy_active {
+            self.conn.execute(
+                r#"
+                INSERT INTO pass_labels (session_id, pass_name, filename, label)
+                SELECT session_id, ?2, filename, label FROM labels WHERE session_id = ?1
+                "#,
+                params![session_id, name],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Make another pass the active one, parking the current pass's labels
+    pub fn switch_pass(&self, session_id: &str, name: &str) -> Result<()> {
+        let active = self.get_active_pass(session_id)?;
+        if active == name {
+            return Ok(());
+        }
+        if !self.get_passes(session_id)?.iter().any(|p| p.name == name) {
+            return Err(GlimpseError::PassNotFound(name.to_string()));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT OR REPLACE INTO pass_labels (session_id, pass_name, filename, label)
+            SELECT session_id, ?2, filename, label FROM labels WHERE session_id = ?1
+            "#,
+            params![session_id, active],
+        )?;
+        tx.execute(
+            "DELETE FROM labels WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        tx.execute(
+            r#"
+            INSERT INTO labels (session_id, filename, label, updated_at)
+            SELECT session_id, filename, label, datetime('now') FROM pass_labels
+            WHERE session_id = ?1 AND pass_name = ?2
+            "#,
+            params![session_id, name],
+        )?;
+        tx.execute(
+            "DELETE FROM pass_labels WHERE session_id = ?1 AND pass_name = ?2",
+            params![session_id, name],
+        )?;
+        tx.execute(
+            "UPDATE culling_passes SET active = (name = ?2) WHERE session_id = ?1",
+            params![session_id, name],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Labels of any pass of a session
+    pub fn get_pass_labels(&self, session_id: &str, name: &str) -> Result<Vec<Label>> {
+        if self.get_active_pass(session_id)? == name {
+            return self.get_labels(session_id);
+        }
+        if !self.get_passes(session_id)?.iter().any(|p| p.name == name) {
+            return Err(GlimpseError::PassNotFound(name.to_string()));
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, label FROM pass_labels WHERE session_id = ?1 AND pass_name = ?2",
+        )?;
+
+        let labels = stmt
+            .query_map(params![session_id, name], |row| {
+                Ok(Label {
+                    filename: row.get(0)?,
+                    label: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(labels)
+    }
+
+    /// Move the label of a renamed file over to its new name, in every pass
+    /// Returns false (and changes nothing) when the new name was labeled meanwhile
+    pub fn transfer_label(&self, session_id: &str, from: &str, to: &str) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        let moved = tx.execute(
+            r#"
+            UPDATE labels SET filename = ?3
+            WHERE session_id = ?1 AND filename = ?2
+                AND NOT EXISTS (SELECT 1 FROM labels WHERE session_id = ?1 AND filename = ?3)
+            "#,
+            params![session_id, from, to],
+        )?;
+        if moved == 0 {
+            return Ok(false);
+        }
+        tx.execute(
+            "UPDATE OR IGNORE pass_labels SET filename = ?3 WHERE session_id = ?1 AND filename = ?2",
+            params![session_id, from, to],
+        )?;
+        tx.execute(
+            "DELETE FROM file_identities WHERE session_id = ?1 AND filename = ?2",
+            params![session_id, from],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Move everything stored under the old names of renamed files to their new names
+    /// With `undoable` the renames are kept for `get_last_renames`, replacing the previous
+    /// batch; otherwise the kept batch is dropped
+    pub fn apply_renames(
+        &self,
+        session_id: &str,
+        renames: &[Rename],
+        undoable: bool,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for rename in renames {
+            for table in FILE_TABLES {
+                tx.execute(
+                    &format!(
+                        "UPDATE OR REPLACE {} SET filename = ?3 WHERE session_id = ?1 AND filename = ?2",
+                        table
+                    ),
+                    params![session_id, rename.from, rename.to],
+                )?;
+            }
+            // The recorded cache path names the old file
+            tx.execute(
+                "DELETE FROM thumbnail_cache WHERE session_id = ?1 AND filename = ?2",
+                params![session_id, rename.from],
+            )?;
+        }
+
+        tx.execute(
+            "DELETE FROM rename_history WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        if undoable {
+            let mut stmt = tx.prepare(
+                "INSERT INTO rename_history (session_id, position, from_name, to_name) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (position, rename) in renames.iter().enumerate() {
+                stmt.execute(params![session_id, position as i64, rename.from, rename.to])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Hand the rows of files moved to another folder over to that folder's session
+    /// `moves` pairs each file's key in `from_session` with its key in `to_session`.
+    /// Only the active labels move along; other passes and the compare set stay behind
+    pub fn move_to_session(
+        &self,
+        from_session: &str,
+        to_session: &str,
+        moves: &[(String, String)],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (from, to) in moves {
+            for table in FILE_TABLES.iter().filter(|table| **table != "pass_labels") {
+                tx.execute(
+                    &format!(
+                        "UPDATE OR REPLACE {} SET session_id = ?3, filename = ?4 WHERE session_id = ?1 AND filename = ?2",
+                        table
+                    ),
+                    params![from_session, from, to_session, to],
+                )?;
+            }
+            for table in ["pass_labels", "compare_set", "thumbnail_cache"] {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE session_id = ?1 AND filename = ?2",
+                        table
+                    ),
+                    params![from_session, from],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete everything stored about a file that was deleted, in every pass
+    pub fn forget_file(&self, session_id: &str, filename: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for table in FILE_TABLES.iter().chain(&["thumbnail_cache"]) {
+            tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE session_id = ?1 AND filename = ?2",
+                    table
+                ),
+                params![session_id, filename],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The last undoable batch of renames, in the order they were made
+    pub fn get_last_renames(&self, session_id: &str) -> Result<Vec<Rename>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT from_name, to_name FROM rename_history WHERE session_id = ?1 ORDER BY position",
+        )?;
+        let renames = stmt
+            .query_map(params![session_id], |row| {
+                Ok(Rename {
+                    from: row.get(0)?,
+                    to: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(renames)
+    }
+
+    // File identity operations
+    // Sizes and modification times of the files seen in a session; rows of files that
+    // disappeared are kept, so a renamed file can be recognized by them
+    pub fn get_file_identities(&self, session_id: &str) -> Result<HashMap<String, FileIdentity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, size, modified_at FROM file_identities WHERE session_id = ?1",
+        )?;
+        let identities = stmt
+            .query_map(params![session_id], |row| {
+                Ok((
+                    row.get(0)?,
+                    FileIdentity {
+                        size: row.get::<_, i64>(1)? as u64,
+                        modified_at: row.get(2)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(identities)
+    }
+
+    pub fn set_file_identities(&self, session_id: &str, images: &[ImageInfo]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO file_identities (session_id, filename, size, modified_at)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(session_id, filename) DO UPDATE SET
+                    size = excluded.size,
+                    modified_at = excluded.modified_at
+                "#,
+            )?;
+            for image in images {
+                stmt.execute(params![
+                    session_id,
+                    image.filename,
+                    image.size as i64,
+                    image.modified_at
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Preview adjustment operations
+    pub fn get_adjustments(&self, session_id: &str, filename: &str) -> Result<RawAdjustments> {
+        let adjustments = self
+            .conn
+            .query_row(
+                "SELECT exposure, wb_temp, wb_tint, white_balance FROM image_adjustments WHERE session_id = ?1 AND filename = ?2",
+                params![session_id, filename],
+                |row| {
+                    Ok(RawAdjustments {
+                        exposure: row.get::<_, f64>(0)? as f32,
+                        wb_temp: row.get::<_, Option<f64>>(1)?.map(|v| v as f32),
+                        wb_tint: row.get::<_, Option<f64>>(2)?.map(|v| v as f32),
+                        white_balance: row
+                            .get::<_, Option<String>>(3)?
+                            .as_deref()
+                            .and_then(WhiteBalance::parse),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(adjustments.unwrap_or_default())
+    }
+
+    /// Store the adjustments of an image; the default removes them
+    pub fn set_adjustments(
+        &self,
+        session_id: &str,
+        filename: &str,
+        adjustments: &RawAdjustments,
+    ) -> Result<()> {
+        if adjustments.is_default() {
+            self.conn.execute(
+                "DELETE FROM image_adjustments WHERE session_id = ?1 AND filename = ?2",
+                params![session_id, filename],
+            )?;
+            return Ok(());
+        }
+        self.conn.execute(
+            r#"
+            INSERT INTO image_adjustments
+                (session_id, filename, exposure, wb_temp, wb_tint, white_balance)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                exposure = excluded.exposure,
+                wb_temp = excluded.wb_temp,
+                wb_tint = excluded.wb_tint,
+                white_balance = excluded.white_balance
+            "#,
+            params![
+                session_id,
+                filename,
+                adjustments.exposure as f64,
+                adjustments.wb_temp.map(|v| v as f64),
+                adjustments.wb_tint.map(|v| v as f64),
+                adjustments.white_balance.map(WhiteBalance::as_str)
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Thumbnail cache operations
+    pub fn get_thumbnail_cache(&self, session_id: &str, filename: &str) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT cache_path FROM thumbnail_cache WHERE session_id = ?1 AND filename = ?2",
+        )?;
+
+        let cache_path = stmt
+            .query_row(params![session_id, filename], |row| row.get(0))
+            .optional()?;
+
+        Ok(cache_path)
+    }
+
+    pub fn set_thumbnail_cache(
+        &self,
+        session_id: &str,
+        filename: &str,
+        cache_path: &str,
+        original_modified: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO thumbnail_cache (session_id, filename, cache_path, original_modified)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                cache_path = excluded.cache_path,
+                original_modified = excluded.original_modified
+            "#,
+            params![session_id, filename, cache_path, original_modified],
+        )?;
+        Ok(())
+    }
+
+    /// Forget the cached thumbnails of some files of a session
+    pub fn delete_thumbnail_cache(&self, session_id: &str, filenames: &[String]) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("DELETE FROM thumbnail_cache WHERE session_id = ?1 AND filename = ?2")?;
+        let mut deleted = 0;
+        for filename in filenames {
+            deleted += stmt.execute(params![session_id, filename])?;
+        }
+        Ok(deleted)
+    }
+
+    // Image analysis operations
+    /// Stored analyses of a session with the file stamp (size and modification time) they
+    /// were computed for
+    pub fn get_analyses(
+        &self,
+        session_id: &str,
+    ) -> Result<HashMap<String, (String, ImageAnalysis)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, file_stamp, sharpness, subject_sharpness,
+                    highlight_clipping, shadow_clipping, dhash, faces, eye_contrast
+             FROM image_analysis WHERE session_id = ?1",
+        )?;
+
+        let analyses = stmt
+            .query_map(params![session_id], |row| {
+                let analysis = ImageAnalysis {
+                    filename: row.get(0)?,
+                    sharpness: row.get(2)?,
+                    subject_sharpness: row.get(3)?,
+                    highlight_clipping: row.get(4)?,
+                    shadow_clipping: row.get(5)?,
+                    // SQLite integers are signed; the hash is stored bit for bit
+                    dhash: row.get::<_, i64>(6)? as u64,
+                    faces: row
+                        .get::<_, Option<String>>(7)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                    eye_contrast: row.get(8)?,
+                };
+                Ok((analysis.filename.clone(), (row.get(1)?, analysis)))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(analyses)
+    }
+
+    pub fn set_analysis(
+        &self,
+        session_id: &str,
+        file_stamp: &str,
+        analysis: &ImageAnalysis,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO image_analysis
+                (session_id, filename, file_stamp, sharpness, subject_sharpness,
+                 highlight_clipping, shadow_clipping, dhash, faces, eye_contrast)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                file_stamp = excluded.file_stamp,
+                sharpness = excluded.sharpness,
+                subject_sharpness = excluded.subject_sharpness,
+                highlight_clipping = excluded.highlight_clipping,
+                shadow_clipping = excluded.shadow_clipping,
+                dhash = excluded.dhash,
+                faces = excluded.faces,
+                eye_contrast = excluded.eye_contrast
+            "#,
+            params![
+                session_id,
+                analysis.filename,
+                file_stamp,
+                analysis.sharpness,
+                analysis.subject_sharpness,
+                analysis.highlight_clipping,
+                analysis.shadow_clipping,
+                analysis.dhash as i64,
+                serde_json::to_string(&analysis.faces).ok(),
+                analysis.eye_contrast
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Cached EXIF data of a session, keyed by filename, with the file's modified time
+    pub fn get_exif_cache(&self, session_id: &str) -> Result<HashMap<String, (String, ExifInfo)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, original_modified, exif FROM exif_cache WHERE session_id = ?1",
+        )?;
+        // Entries written by an older ExifInfo layout lack some of its fields (which would
+        // read as empty); they are simply read again
+        let fields: Vec<String> = match serde_json::to_value(ExifInfo::default()) {
+            Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+        let is_current = |value: &serde_json::Value| {
+            value
+                .as_object()
+                .is_some_and(|map| fields.iter().all(|field| map.contains_key(field)))
+        };
+
+        let cached = stmt
+            .query_map(params![session_id], |row| {
+                let exif: Option<String> = row.get(2)?;
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, exif))
+            })?
+            .filter_map(|row| match row {
+                Ok((filename, modified, exif)) => exif
+                    .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+                    .filter(is_current)
+                    .and_then(|value| serde_json::from_value(value).ok())
+                    .map(|exif| Ok((filename, (modified, exif)))),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(cached)
+    }
+
+    /// Store `(filename, original_modified, exif)` of a session's files
+    pub fn set_exif_cache(
+        &self,
+        session_id: &str,
+        entries: &[(String, String, ExifInfo)],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO exif_cache (session_id, filename, original_modified, exif)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(session_id, filename) DO UPDATE SET
+                    original_modified = excluded.original_modified,
+                    exif = excluded.exif
+                "#,
+            )?;
+            for (filename, original_modified, exif) in entries {
+                stmt.execute(params![
+                    session_id,
+                    filename,
+                    original_modified,
+                    serde_json::to_string(exif).ok()
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Content fingerprints and perceptual hashes
+    /// Content fingerprints of a session's files with the modification time they were taken at
+    pub fn get_file_fingerprints(
+        &self,
+        session_id: &str,
+    ) -> Result<HashMap<String, (String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, file_stamp, fingerprint FROM file_fingerprints WHERE session_id = ?1",
+        )?;
+        let fingerprints = stmt
+            .query_map(params![session_id], |row| {
+                Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(fingerprints)
+    }
+
+    /// Store `(filename, file_stamp, fingerprint)` of a session's files
+    pub fn set_file_fingerprints(
+        &self,
+        session_id: &str,
+        fingerprints: &[(String, String, String)],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO file_fingerprints (session_id, filename, file_stamp, fingerprint)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(session_id, filename) DO UPDATE SET
+                    file_stamp = excluded.file_stamp,
+                    fingerprint = excluded.fingerprint
+                "#,
+            )?;
+            for (filename, stamp, fingerprint) in fingerprints {
+                stmt.execute(params![session_id, filename, stamp, fingerprint])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Files with the same content in more than one folder, across all sessions
+    /// A file seen by several sessions (a folder and its parent) counts once
+    pub fn find_global_duplicates(&self) -> Result<Vec<DuplicateGroup>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT f.fingerprint, f.session_id, s.folder_path, f.filename, i.size
+            FROM file_fingerprints f
+            JOIN sessions s ON s.id = f.session_id
+            LEFT JOIN file_identities i
+                ON i.session_id = f.session_id AND i.filename = f.filename
+            WHERE f.fingerprint IN (
+                SELECT fingerprint FROM file_fingerprints
+                GROUP BY fingerprint HAVING COUNT(*) > 1
+            )
+            ORDER BY f.fingerprint, s.folder_path, f.filename
+            "#,
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        for (fingerprint, session_id, folder_path, filename, size) in rows {
+            let path = normalize_path(&Path::new(&folder_path).join(&filename));
+            let group = match groups.last_mut() {
+                Some(group) if group.fingerprint == fingerprint => group,
+                _ => {
+                    groups.push(DuplicateGroup {
+                        fingerprint,
+                        size: 0,
+                        files: Vec::new(),
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+            group.size = group.size.max(size.unwrap_or(0) as u64);
+            if !group.files.iter().any(|file| file.path == path) {
+                group.files.push(DuplicateFile { session_id, path });
+            }
+        }
+        groups.retain(DuplicateGroup::spans_folders);
+        Ok(groups)
+    }
+
+    /// Perceptual hashes stored for any of `fingerprints`, from whichever session computed them
+    pub fn get_perceptual_hashes(&self, fingerprints: &[String]) -> Result<HashMap<String, u64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT dhash FROM perceptual_hashes WHERE fingerprint = ?1")?;
+        let mut hashes = HashMap::new();
+        for fingerprint in fingerprints {
+            let dhash: Option<i64> = stmt
+                .query_row(params![fingerprint], |row| row.get(0))
+                .optional()?;
+            if let Some(dhash) = dhash {
+                hashes.insert(fingerprint.clone(), dhash as u64);
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Store perceptual hashes by the content fingerprint of the file they were computed from
+    pub fn set_perceptual_hashes(&self, hashes: &[(String, u64)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO perceptual_hashes (fingerprint, dhash) VALUES (?1, ?2)",
+            )?;
+            for (fingerprint, dhash) in hashes {
+                // SQLite integers are signed; the hash is stored bit for bit
+                stmt.execute(params![fingerprint, *dhash as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Capture time corrections
+    /// Seconds added to the capture time of a session's corrected files
+    pub fn get_time_offsets(&self, session_id: &str) -> Result<HashMap<String, i64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT filename, seconds FROM time_offsets WHERE session_id = ?1")?;
+        let offsets = stmt
+            .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(offsets)
+    }
+
+    /// Add `seconds` to the correction of each of `filenames`; corrections adding up to zero
+    /// are removed
+    pub fn add_time_offset(
+        &self,
+        session_id: &str,
+        filenames: &[String],
+        seconds: i64,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO time_offsets (session_id, filename, seconds)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT(session_id, filename) DO UPDATE SET
+                    seconds = seconds + excluded.seconds
+                "#,
+            )?;
+            for filename in filenames {
+                stmt.execute(params![session_id, filename, seconds])?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM time_offsets WHERE session_id = ?1 AND seconds = 0",
+            params![session_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn clear_time_offsets(&self, session_id: &str, filenames: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for filename in filenames {
+            tx.execute(
+                "DELETE FROM time_offsets WHERE session_id = ?1 AND filename = ?2",
+                params![session_id, filename],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Metadata corrections
+    pub fn get_metadata_edits(&self, session_id: &str) -> Result<HashMap<String, MetadataEdits>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT filename, edits FROM metadata_edits WHERE session_id = ?1")?;
+        let edits = stmt
+            .query_map(params![session_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .filter_map(|row| match row {
+                Ok((filename, json)) => serde_json::from_str(&json)
+                    .ok()
+                    .map(|edits| Ok((filename, edits))),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(edits)
+    }
+
+    /// Merge `edits` into the corrections of each of `filenames` (see `MetadataEdits::merge`)
+    pub fn edit_metadata(
+        &self,
+        session_id: &str,
+        filenames: &[String],
+        edits: &MetadataEdits,
+    ) -> Result<()> {
+        let mut stored = self.get_metadata_edits(session_id)?;
+        let tx = self.conn.unchecked_transaction()?;
+        for filename in filenames {
+            let mut merged = stored.remove(filename).unwrap_or_default();
+            merged.merge(edits);
+            if merged.is_empty() {
+                tx.execute(
+                    "DELETE FROM metadata_edits WHERE session_id = ?1 AND filename = ?2",
+                    params![session_id, filename],
+                )?;
+            } else {
+                tx.execute(
+                    r#"
+                    INSERT INTO metadata_edits (session_id, filename, edits)
+                    VALUES (?1, ?2, ?3)
+                    ON CONFLICT(session_id, filename) DO UPDATE SET edits = excluded.edits
+                    "#,
+                    params![
+                        session_id,
+                        filename,
+                        serde_json::to_string(&merged).unwrap_or_default()
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rewrite stored cache paths after the cache directory has moved
+    // Problem file operations
+    /// Files of a session that failed to decode because their data is damaged
+    pub fn get_problem_files(&self, session_id: &str) -> Result<Vec<ProblemFile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, original_modified, error FROM problem_files
+             WHERE session_id = ?1 ORDER BY filename",
+        )?;
+
+        let files = stmt
+            .query_map(params![session_id], |row| {
+                Ok(ProblemFile {
+                    filename: row.get(0)?,
+                    original_modified: row.get(1)?,
+                    error: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(files)
+    }
+
+    pub fn set_problem_file(&self, session_id: &str, file: &ProblemFile) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO problem_files (session_id, filename, original_modified, error)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                original_modified = excluded.original_modified,
+                error = excluded.error
+            "#,
+            params![
+                session_id,
+                file.filename,
+                file.original_modified,
+                file.error
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Drop files from a session's problem list (e.g. once they decode again)
+    pub fn delete_problem_files(&self, session_id: &str, filenames: &[String]) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("DELETE FROM problem_files WHERE session_id = ?1 AND filename = ?2")?;
+        let mut deleted = 0;
+        for filename in filenames {
+            deleted += stmt.execute(params![session_id, filename])?;
+        }
+        Ok(deleted)
+    }
+
+    // Thumbnail failure operations
+    /// Files whose thumbnail could not be generated in the latest run that reached them
+    pub fn get_thumbnail_failures(&self, session_id: &str) -> Result<Vec<ThumbnailFailure>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filename, kind, error, failed_at FROM thumbnail_failures
+             WHERE session_id = ?1 ORDER BY filename",
+        )?;
+
+        let failures = stmt
+            .query_map(params![session_id], |row| {
+                Ok(ThumbnailFailure {
+                    filename: row.get(0)?,
+                    kind: FailureKind::parse(&row.get::<_, String>(1)?),
+                    error: row.get(2)?,
+                    failed_at: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(failures)
+    }
+
+    pub fn set_thumbnail_failure(
+        &self,
+        session_id: &str,
+        failure: &ThumbnailFailure,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO thumbnail_failures (session_id, filename, kind, error, failed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                kind = excluded.kind,
+                error = excluded.error,
+                failed_at = excluded.failed_at
+            "#,
+            params![
+                session_id,
+                failure.filename,
+                failure.kind.as_str(),
+                failure.error,
+                failure.failed_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Forget earlier failures of files whose thumbnail has been generated since
+    pub fn delete_thumbnail_failures(
+        &self,
+        session_id: &str,
+        filenames: &[String],
+    ) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("DELETE FROM thumbnail_failures WHERE session_id = ?1 AND filename = ?2")?;
+        let mut deleted = 0;
+        for filename in filenames {
+            deleted += stmt.execute(params![session_id, filename])?;
+        }
+        Ok(deleted)
+    }
+
+    pub fn update_cache_paths(&self, old_prefix: &str, new_prefix: &str) -> Result<usize> {
+        let updated = self.conn.execute(
+            r#"
+            UPDATE thumbnail_cache
+            SET cache_path = ?2 || substr(cache_path, length(?1) + 1)
+            WHERE substr(cache_path, 1, length(?1)) = ?1
+            "#,
+            params![old_prefix, new_prefix],
+        )?;
+        Ok(updated)
+    }
+
+    // Storage info operations
+    pub fn get_label_count(&self) -> Result<i64> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM labels", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    pub fn get_session_count(&self) -> Result<i64> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Every session with its label count, most recently opened first
+    pub fn get_session_summaries(&self) -> Result<Vec<SessionSummary>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT s.id, s.folder_path, s.last_opened,
+                   (SELECT COUNT(*) FROM labels l WHERE l.session_id = s.id)
+            FROM sessions s
+            ORDER BY s.last_opened DESC
+            "#,
+        )?;
+        let sessions = stmt
+            .query_map([], |row| {
+                Ok(SessionSummary {
+                    id: row.get(0)?,
+                    folder_path: row.get(1)?,
+                    last_opened: row.get(2)?,
+                    label_count: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    /// When each session was last opened, as stored (RFC 3339 or SQLite datetime)
+    pub fn get_session_last_opened(&self) -> Result<Vec<(String, Option<String>)>> {
+        let mut stmt = self.conn.prepare("SELECT id, last_opened FROM sessions")?;
+        let sessions = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    pub fn get_all_session_ids(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM sessions")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Bytes used by the database's pages
+    fn size_bytes(&self) -> Result<u64> {
+        let size: i64 = self.conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size as u64)
+    }
+
+    /// Shrink the database file after deletions (VACUUM) and refresh query planner
+    /// statistics (ANALYZE)
+    pub fn optimize(&self) -> Result<OptimizeStats> {
+        let before_bytes = self.size_bytes()?;
+        self.conn.execute_batch("VACUUM; ANALYZE;")?;
+        // Also shrink the write-ahead log the vacuum went through
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let after_bytes = self.size_bytes()?;
+        Ok(OptimizeStats {
+            before_bytes,
+            after_bytes,
+            bytes_reclaimed: before_bytes.saturating_sub(after_bytes),
+        })
+    }
+
+    /// Check the database file and look for rows that point at nothing: data of sessions
+    /// that no longer exist and thumbnail cache entries whose file is gone
+    /// With `repair` those rows are deleted; damage to the file itself is only reported
+    pub fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let integrity_errors = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|message| message != "ok")
+            .collect();
+
+        let mut orphaned_rows = Vec::new();
+        for table in SESSION_TABLES {
+            let count: i64 = self.conn.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {} WHERE session_id NOT IN (SELECT id FROM sessions)",
+                    table
+                ),
+                [],
+                |row| row.get(0),
+            )?;
+            if count > 0 {
+                orphaned_rows.push(OrphanedRows {
+                    table: table.to_string(),
+                    count: count as usize,
+                });
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT session_id, filename, cache_path FROM thumbnail_cache")?;
+        let missing_cache: Vec<(String, String)> = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|(_, _, cache_path)| !Path::new(cache_path).exists())
+            .map(|(session_id, filename, _)| (session_id, filename))
+            .collect();
+
+        let repaired = repair && (!orphaned_rows.is_empty() || !missing_cache.is_empty());
+        if repaired {
+            let tx = self.conn.unchecked_transaction()?;
+            for orphaned in &orphaned_rows {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE session_id NOT IN (SELECT id FROM sessions)",
+                        orphaned.table
+                    ),
+                    [],
+                )?;
+            }
+            {
+                let mut delete = tx.prepare(
+                    "DELETE FROM thumbnail_cache WHERE session_id = ?1 AND filename = ?2",
+                )?;
+                for (session_id, filename) in &missing_cache {
+                    delete.execute(params![session_id, filename])?;
+                }
+            }
+            tx.commit()?;
+        }
+
+        Ok(IntegrityReport {
+            integrity_errors,
+            orphaned_rows,
+            missing_cache_files: missing_cache.len(),
+            repaired,
+        })
+    }
+
+    pub fn clear_all_labels(&self) -> Result<i64> {
+        let count = self.get_label_count()?;
+        self.conn.execute("DELETE FROM labels", [])?;
+        self.conn.execute("DELETE FROM pass_labels", [])?;
+        Ok(count)
+    }
+
+    pub fn clear_all_sessions(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM thumbnail_cache", [])?;
+        self.conn.execute("DELETE FROM session_settings", [])?;
+        self.conn.execute("DELETE FROM session_folders", [])?;
+        self.conn.execute("DELETE FROM image_analysis", [])?;
+        self.conn.execute("DELETE FROM exif_cache", [])?;
+        self.conn.execute("DELETE FROM time_offsets", [])?;
+        self.conn.execute("DELETE FROM metadata_edits", [])?;
+        self.conn.execute("DELETE FROM file_fingerprints", [])?;
+        self.conn.execute("DELETE FROM problem_files", [])?;
+        self.conn.execute("DELETE FROM thumbnail_failures", [])?;
+        self.conn.execute("DELETE FROM compare_set", [])?;
+        self.conn.execute("DELETE FROM file_identities", [])?;
+        self.conn.execute("DELETE FROM rename_history", [])?;
+        self.conn.execute("DELETE FROM image_adjustments", [])?;
+        self.conn.execute("DELETE FROM pass_labels", [])?;
+        self.conn.execute("DELETE FROM culling_passes", [])?;
+        self.conn.execute("DELETE FROM project_sessions", [])?;
+        self.conn.execute("DELETE FROM export_history", [])?;
+        self.conn.execute("DELETE FROM labels", [])?;
+        self.conn.execute("DELETE FROM sessions", [])?;
+        Ok(())
+    }
+}
+
+/// Export history row; None when its settings were written by an incompatible version
+fn export_record_from_row(row: &rusqlite::Row) -> rusqlite::Result<Option<ExportRecord>> {
+    let settings: Option<String> = row.get(3)?;
+    let result: Option<String> = row.get(4)?;
+    let Some(settings) = settings.and_then(|json| serde_json::from_str(&json).ok()) else {
+        return Ok(None);
+    };
+    Ok(Some(ExportRecord {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        preset: row.get(2)?,
+        settings,
+        result: result
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        exported_at: row.get(5)?,
+    }))
+}
+
+// rusqlite Optional trait workaround
+trait Optional<T> {
+    fn optional(self) -> std::result::Result<Option<T>, rusqlite::Error>;
+}
+
+impl<T> Optional<T> for std::result::Result<T, rusqlite::Error> {
+    fn optional(self) -> std::result::Result<Option<T>, rusqlite::Error> {
+        match self {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub folder_path: String,
+    pub last_opened: Option<String>,
+    pub last_selected_index: i32,
+    pub total_files: i32,
+}
+
+/// Result of `Database::optimize`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OptimizeStats {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub bytes_reclaimed: u64,
+}
+
+/// Tables whose rows belong to a session
+const SESSION_TABLES: &[&str] = &[
+    "labels",
+    "thumbnail_cache",
+    "session_settings",
+    "session_folders",
+    "image_analysis",
+    "exif_cache",
+    "time_offsets",
+    "metadata_edits",
+    "file_fingerprints",
+    "problem_files",
+    "thumbnail_failures",
+    "compare_set",
+    "culling_passes",
+    "pass_labels",
+    "file_identities",
+    "rename_history",
+    "image_adjustments",
+];
+
+/// Tables keyed by file name, whose rows move along when a file is renamed
+const FILE_TABLES: &[&str] = &[
+    "labels",
+    "pass_labels",
+    "image_analysis",
+    "exif_cache",
+    "time_offsets",
+    "metadata_edits",
+    "file_fingerprints",
+    "problem_files",
+    "thumbnail_failures",
+    "compare_set",
+    "file_identities",
+    "image_adjustments",
+];
+
+/// Copies of the same file (by content fingerprint) stored in different folders
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DuplicateGroup {
+    pub fingerprint: String,
+    /// Size of one copy in bytes, as the last scan saw it
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+impl DuplicateGroup {
+    /// Whether the copies are in more than one folder
+    pub fn spans_folders(&self) -> bool {
+        let mut folders = self.files.iter().map(|file| Path::new(&file.path).parent());
+        folders
+            .next()
+            .is_some_and(|first| folders.any(|folder| folder != first))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DuplicateFile {
+    /// A session the file belongs to
+    pub session_id: String,
+    pub path: String,
+}
+
+/// Size and modification time of a file as the last scan saw it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileIdentity {
+    pub size: u64,
+    pub modified_at: String,
+}
+
+/// Rows of one table left behind by a session that no longer exists
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OrphanedRows {
+    pub table: String,
+    pub count: usize,
+}
+
+/// Result of `Database::check_integrity`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct IntegrityReport {
+    /// Messages from `PRAGMA integrity_check`; empty when the file is sound
+    pub integrity_errors: Vec<String>,
+    pub orphaned_rows: Vec<OrphanedRows>,
+    /// Thumbnail cache entries whose cache file is missing
+    pub missing_cache_files: usize,
+    /// Whether orphaned rows and missing cache entries were deleted
+    pub repaired: bool,
+}
+
+/// Current time as SQLite's datetime('now') writes it
+fn label_time() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn write_label(
+    conn: &Connection,
+    session_id: &str,
+    filename: &str,
+    label: Option<&str>,
+    changed_at: &str,
+) -> Result<()> {
+    if let Some(label_value) = label {
+        conn.execute(
+            r#"
+            INSERT INTO labels (session_id, filename, label, updated_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(session_id, filename) DO UPDATE SET
+                label = excluded.label,
+                updated_at = excluded.updated_at
+            "#,
+            params![session_id, filename, label_value, changed_at],
+        )?;
+    } else {
+        conn.execute(
+            "DELETE FROM labels WHERE session_id = ?1 AND filename = ?2",
+            params![session_id, filename],
+        )?;
+    }
+    Ok(())
+}
+
+/// Name of the pass holding a session's labels before any other pass is created
+pub const DEFAULT_PASS: &str = "First pass";
+
+/// Folder pinned for one-click opening (card ingest folder, client delivery root)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Bookmark {
+    pub path: String,
+    pub name: String,
+}
+
+/// One export run of a session
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ExportRecord {
+    pub id: i64,
+    pub session_id: String,
+    /// Name of the preset it was run with
+    pub preset: Option<String>,
+    pub settings: ExportSettings,
+    pub result: ExportResult,
+    pub exported_at: String,
+}
+
+/// Sessions grouped for one shoot (e.g. the three cards of a wedding)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub session_ids: Vec<String>,
+}
+
+/// How far culling of one session got
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SessionLabelStats {
+    pub session_id: String,
+    pub folder_path: String,
+    pub total_files: i64,
+    pub adopted: i64,
+    pub rejected: i64,
+    pub unlabeled: i64,
+}
+
+/// Named set of labels of a session (e.g. a first pass and a tighter client review)
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CullingPass {
+    pub name: String,
+    pub created_at: String,
+    pub active: bool,
+    pub label_count: i64,
+}
+
+/// File labeled differently in two passes
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PassDifference {
+    pub filename: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+/// Files whose label differs between two label sets, sorted by filename
+pub fn compare_labels(a: &[Label], b: &[Label]) -> Vec<PassDifference> {
+    let label_map = |labels: &[Label]| -> HashMap<String, Option<String>> {
+        labels
+            .iter()
+            .filter(|l| l.label.is_some())
+            .map(|l| (l.filename.clone(), l.label.clone()))
+            .collect()
+    };
+    let (a, b) = (label_map(a), label_map(b));
+
+    let mut filenames: Vec<&String> = a.keys().chain(b.keys()).collect();
+    filenames.sort();
+    filenames.dedup();
+    filenames
+        .into_iter()
+        .filter_map(|filename| {
+            let (a, b) = (
+                a.get(filename).cloned().flatten(),
+                b.get(filename).cloned().flatten(),
+            );
+            (a != b).then(|| PassDifference {
+                filename: filename.clone(),
+                a,
+                b,
+            })
+        })
+        .collect()
+}
+
+/// File whose data couldn't be decoded
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ProblemFile {
+    pub filename: String,
+    pub original_modified: String,
+    pub error: String,
+}
+
+/// Destructive operation recorded in the operation journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    /// Export in move mode: each file is written to the destination, then the original deleted
+    ExportMove,
+    /// Deletion of a session's file, to the trash (destination "trash") or permanently;
+    /// the file's destination is its name in the session
+    Delete,
+}
+
+impl OperationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            OperationKind::ExportMove => "export_move",
+            OperationKind::Delete => "delete",
+        }
+    }
+
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "delete" => OperationKind::Delete,
+            _ => OperationKind::ExportMove,
+        }
+    }
+}
+
+/// How far a journaled operation got with one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationFileState {
+    /// Not started, or interrupted while writing the destination
+    Pending,
+    /// Written to the destination; the original may still exist
+    Copied,
+    /// Finished; the original is gone
+    Done,
+}
+
+impl OperationFileState {
+    fn as_str(self) -> &'static str {
+        match self {
+            OperationFileState::Pending => "pending",
+            OperationFileState::Copied => "copied",
+            OperationFileState::Done => "done",
+        }
+    }
+
+    fn parse(state: &str) -> Self {
+        match state {
+            "copied" => OperationFileState::Copied,
+            "done" => OperationFileState::Done,
+            _ => OperationFileState::Pending,
+        }
+    }
+}
+
+/// Journaled operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub id: i64,
+    pub session_id: String,
+    pub kind: OperationKind,
+    pub destination: String,
+    /// Operation-specific settings as JSON (e.g. export options)
+    pub params: Option<String>,
+    pub started_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationFile {
+    pub source: String,
+    pub destination: String,
+    pub state: OperationFileState,
+}
+
+/// Operation an earlier run didn't finish, with how many files reached each state
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct InterruptedOperation {
+    pub id: i64,
+    pub session_id: String,
+    pub kind: OperationKind,
+    pub destination: String,
+    pub started_at: String,
+    pub pending: i64,
+    pub copied: i64,
+    pub done: i64,
+}
+
+/// Why a thumbnail could not be generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureKind {
+    /// The file data is truncated or corrupt
+    Corrupt,
+    /// Online-only cloud file that was not downloaded
+    Placeholder,
+    /// Anything else (unsupported camera, unreadable file, ...)
+    Error,
+}
+
+impl FailureKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            FailureKind::Corrupt => "corrupt",
+            FailureKind::Placeholder => "placeholder",
+            FailureKind::Error => "error",
+        }
+    }
+
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "corrupt" => FailureKind::Corrupt,
+            "placeholder" => FailureKind::Placeholder,
+            _ => FailureKind::Error,
+        }
+    }
+}
+
+/// File whose thumbnail could not be generated
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ThumbnailFailure {
+    pub filename: String,
+    pub kind: FailureKind,
+    pub error: String,
+    pub failed_at: String,
+}
+
+/// Session as listed in the storage settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    pub folder_path: String,
+    pub last_opened: Option<String>,
+    pub label_count: i64,
+}
+
+/// Per-session overrides of the global config
+/// None means "use the global setting"
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    pub sort_order: Option<String>,
+    pub filter: Option<String>,
+    pub preview_size: Option<u32>,
+    pub export_destination: Option<String>,
+    /// Shared folder (Dropbox, NAS) the session's label changes are synced through
+    pub sync_dir: Option<String>,
+    pub white_balance: Option<WhiteBalance>,
+}
+
+/// Where the user left off in a session's grid, restored on reopen
+/// Kept with the session settings, whose sort order and filter it shares
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    pub filter: Option<String>,
+    pub sort_order: Option<String>,
+    /// Grid thumbnail size in pixels
+    pub grid_zoom: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Label {
+    pub filename: String,
+    pub label: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::FaceBox;
+    use rusqlite::Connection;
+
+    fn create_test_db() -> Database {
+        Database::open_in_memory().unwrap()
+    }
+
+    #[test]
+    fn test_init_schema() {
+        let db = create_test_db();
+        // Verify schema was created successfully
+        let count: i32 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='sessions'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_upsert_and_get_session() {
+        let db = create_test_db();
+
+        let session = Session {
+            id: "test_session_id".to_string(),
+            folder_path: "/test/path".to_string(),
+            last_opened: Some("2024-12-15T14:00:00".to_string()),
+            last_selected_index: 10,
+            total_files: 100,
+        };
+
+        db.upsert_session(&session).unwrap();
+
+        let retrieved = db.get_session("test_session_id").unwrap().unwrap();
+        assert_eq!(retrieved.id, "test_session_id");
+        assert_eq!(retrieved.folder_path, "/test/path");
+        assert_eq!(retrieved.last_selected_index, 10);
+        assert_eq!(retrieved.total_files, 100);
+    }
+
+    #[test]
+    fn test_update_last_selected() {
+        let db = create_test_db();
+
+        let session = Session {
+            id: "test_session_id".to_string(),
+            folder_path: "/test/path".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 50,
+        };
+
+        db.upsert_session(&session).unwrap();
+        db.update_last_selected("test_session_id", 25).unwrap();
+
+        let retrieved = db.get_session("test_session_id").unwrap().unwrap();
+        assert_eq!(retrieved.last_selected_index, 25);
+    }
+
+    #[test]
+    fn test_set_and_get_label() {
+        let db = create_test_db();
+
+        // Create session
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+
+        // Set labels
+        db.set_label("test_session", "image1.jpg", Some("rejected"))
+            .unwrap();
+        db.set_label("test_session", "image2.jpg", Some("rejected"))
+            .unwrap();
+
+        // Get labels
+        let labels = db.get_labels("test_session").unwrap();
+        assert_eq!(labels.len(), 2);
+
+        let image1_label = labels.iter().find(|l| l.filename == "image1.jpg").unwrap();
+        assert_eq!(image1_label.label, Some("rejected".to_string()));
+
+        // Only label writes sync fully; other writes go back to NORMAL (1)
+        let synchronous: i64 = db
+            .conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1);
+    }
+
+    #[test]
+    fn test_remove_label() {
+        let db = create_test_db();
+
+        // Create session
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+
+        // Set label
+        db.set_label("test_session", "image1.jpg", Some("rejected"))
+            .unwrap();
+
+        // Remove label
+        db.set_label("test_session", "image1.jpg", None).unwrap();
+
+        // Get labels
+        let labels = db.get_labels("test_session").unwrap();
+        assert_eq!(labels.len(), 0);
+    }
+
+    #[test]
+    fn test_interrupted_operations() {
+        let db = create_test_db();
+        let files = vec![
+            ("/src/a.NEF".to_string(), "/dst/a.NEF".to_string()),
+            ("/src/b.NEF".to_string(), "/dst/b.NEF".to_string()),
+        ];
+        let id = db
+            .begin_operation("session", OperationKind::ExportMove, "/dst", None, &files)
+            .unwrap();
+        db.set_operation_file_state(id, "/src/a.NEF", OperationFileState::Done)
+            .unwrap();
+
+        // Still running in this run of the app
+        assert!(db.get_interrupted_operations().unwrap().is_empty());
+
+        // Left behind by a previous run, even one that had the same process ID
+        db.conn
+            .execute("UPDATE operations SET run_id = 'c0ffee'", [])
+            .unwrap();
+        let interrupted = db.get_interrupted_operations().unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!((interrupted[0].pending, interrupted[0].done), (1, 1));
+
+        db.finish_operation(id).unwrap();
+        assert!(db.get_interrupted_operations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_export_history() {
+        let db = create_test_db();
+        db.upsert_session(&Session {
+            id: "shoot".to_string(),
+            folder_path: "/shoot".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 3,
+        })
+        .unwrap();
+        let settings = ExportSettings {
+            destination: "/Volumes/Delivery/proofs".to_string(),
+            mode: crate::config::ExportMode::Copy,
+            filter: crate::config::ExportFilter::Adopted,
+            rename_template: Some("proof_{seq}".to_string()),
+            seq_order: Default::default(),
+            gear: Default::default(),
+            new_only: false,
+            options: Default::default(),
+        };
+        let result = ExportResult {
+            total: 3,
+            copied: 2,
+            skipped: 1,
+            ..Default::default()
+        };
+
+        let first = db
+            .add_export_record("shoot", Some("Client proofs"), &settings, &result)
+            .unwrap();
+        let second = db
+            .add_export_record("shoot", None, &settings, &ExportResult::default())
+            .unwrap();
+
+        let history = db.get_export_history("shoot").unwrap();
+        assert_eq!(
+            history.iter().map(|r| r.id).collect::<Vec<_>>(),
+            [second, first]
+        );
+        let record = db.get_export_record(first).unwrap().unwrap();
+        assert_eq!(record.preset.as_deref(), Some("Client proofs"));
+        assert_eq!(record.settings, settings);
+        assert_eq!(record.result, result);
+        assert!(db.get_export_record(second + 1).unwrap().is_none());
+        assert!(db.get_export_history("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_exported_files() {
+        let db = create_test_db();
+        let identity = FileIdentity {
+            size: 100,
+            modified_at: "2024/12/15 10:30".to_string(),
+        };
+        db.record_exported_file("/delivery", "/shoot/a.jpg", &identity)
+            .unwrap();
+        db.record_exported_file("/archive", "/shoot/b.jpg", &identity)
+            .unwrap();
+        let edited = FileIdentity {
+            size: 120,
+            ..identity.clone()
+        };
+        db.record_exported_file("/delivery", "/shoot/a.jpg", &edited)
+            .unwrap();
+
+        let exported = db.get_exported_files("/delivery").unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported["/shoot/a.jpg"], edited);
+        assert!(db.get_exported_files("/other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_projects() {
+        let db = create_test_db();
+        for (id, total_files) in [("card1", 3), ("card2", 2)] {
+            db.upsert_session(&Session {
+                id: id.to_string(),
+                folder_path: format!("/Volumes/{}", id),
+                last_opened: None,
+                last_selected_index: 0,
+                total_files,
+            })
+            .unwrap();
+        }
+        db.set_label("card1", "a.NEF", Some("adopted")).unwrap();
+        db.set_label("card1", "b.NEF", Some("rejected")).unwrap();
+        db.set_label("card2", "a.NEF", Some("adopted")).unwrap();
+
+        let wedding = db.create_project("Wedding").unwrap();
+        db.set_project_sessions(wedding, &["card2".to_string(), "card1".to_string()])
+            .unwrap();
+        let project = db.get_project(wedding).unwrap().unwrap();
+        assert_eq!(project.name, "Wedding");
+        assert_eq!(project.session_ids, ["card2", "card1"]);
+
+        let stats = db.get_project_label_stats(wedding).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].adopted, stats[0].rejected, stats[0].unlabeled),
+            (1, 0, 1)
+        );
+        assert_eq!(
+            (stats[1].adopted, stats[1].rejected, stats[1].unlabeled),
+            (1, 1, 1)
+        );
+
+        assert!(db.rename_project(wedding, "Smith wedding").unwrap());
+        assert!(!db.rename_project(wedding + 1, "Missing").unwrap());
+        assert_eq!(db.get_projects().unwrap()[0].name, "Smith wedding");
+
+        // Sessions outlive their project
+        db.delete_project(wedding).unwrap();
+        assert!(db.get_project(wedding).unwrap().is_none());
+        assert!(db.get_session("card1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let db = create_test_db();
+        let paths = |db: &Database| {
+            db.get_bookmarks()
+                .unwrap()
+                .into_iter()
+                .map(|b| b.path)
+                .collect::<Vec<_>>()
+        };
+
+        db.add_bookmark("/Volumes/CARD/DCIM", "Card").unwrap();
+        db.add_bookmark("/Users/a/Delivery", "Delivery").unwrap();
+        db.add_bookmark("/Users/a/Ingest", "Ingest").unwrap();
+        // Pinning again renames in place
+        db.add_bookmark("/Volumes/CARD/DCIM", "Card reader")
+            .unwrap();
+        assert_eq!(
+            paths(&db),
+            ["/Volumes/CARD/DCIM", "/Users/a/Delivery", "/Users/a/Ingest"]
+        );
+        assert_eq!(db.get_bookmarks().unwrap()[0].name, "Card reader");
+
+        db.reorder_bookmarks(&["/Users/a/Ingest".to_string()])
+            .unwrap();
+        assert_eq!(
+            paths(&db),
+            ["/Users/a/Ingest", "/Volumes/CARD/DCIM", "/Users/a/Delivery"]
+        );
+
+        assert!(db.rename_bookmark("/Users/a/Ingest", "Inbox").unwrap());
+        assert!(!db.rename_bookmark("/missing", "x").unwrap());
+        db.remove_bookmark("/Volumes/CARD/DCIM").unwrap();
+        assert_eq!(paths(&db), ["/Users/a/Ingest", "/Users/a/Delivery"]);
+    }
+
+    #[test]
+    fn test_compare_set() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        db.add_to_compare_set("test_session", &names(&["b.jpg", "a.jpg"]))
+            .unwrap();
+        // Pinning again keeps the original order
+        db.add_to_compare_set("test_session", &names(&["c.jpg", "b.jpg"]))
+            .unwrap();
+        assert_eq!(
+            db.get_compare_set("test_session").unwrap(),
+            names(&["b.jpg", "a.jpg", "c.jpg"])
+        );
+
+        db.remove_from_compare_set("test_session", &names(&["a.jpg"]))
+            .unwrap();
+        assert_eq!(
+            db.get_compare_set("test_session").unwrap(),
+            names(&["b.jpg", "c.jpg"])
+        );
+        db.clear_compare_set("test_session").unwrap();
+        assert!(db.get_compare_set("test_session").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_culling_passes() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        db.set_label("test_session", "image1.jpg", Some("rejected"))
+            .unwrap();
+
+        // Labels from before passes belong to the default pass
+        assert_eq!(db.get_active_pass("test_session").unwrap(), DEFAULT_PASS);
+        db.create_pass("test_session", "Client review", true)
+            .unwrap();
+        assert!(matches!(
+            db.create_pass("test_session", "Client review", false),
+            Err(GlimpseError::PassExists(_))
+        ));
+
+        db.switch_pass("test_session", "Client review").unwrap();
+        db.set_label("test_session", "image2.jpg", Some("rejected"))
+            .unwrap();
+        let passes = db.get_passes("test_session").unwrap();
+        assert_eq!(passes.len(), 2);
+        assert!(!passes[0].active && passes[0].label_count == 1);
+        assert!(passes[1].active && passes[1].label_count == 2);
+
+        // Switching back restores the first pass untouched
+        db.switch_pass("test_session", DEFAULT_PASS).unwrap();
+        assert_eq!(db.get_labels("test_session").unwrap().len(), 1);
+        assert!(matches!(
+            db.switch_pass("test_session", "Missing"),
+            Err(GlimpseError::PassNotFound(_))
+        ));
+
+        let differences = compare_labels(
+            &db.get_pass_labels("test_session", DEFAULT_PASS).unwrap(),
+            &db.get_pass_labels("test_session", "Client review").unwrap(),
+        );
+        assert_eq!(
+            differences,
+            vec![PassDifference {
+                filename: "image2.jpg".to_string(),
+                a: None,
+                b: Some("rejected".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_optimize_reclaims_deleted_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database {
+            conn: Connection::open(dir.path().join("glimpse.db")).unwrap(),
+        };
+        db.init_schema().unwrap();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        for i in 0..2000 {
+            db.set_label(
+                "test_session",
+                &format!("IMG_{:04}.JPG", i),
+                Some("rejected"),
+            )
+            .unwrap();
+        }
+        db.clear_all_labels().unwrap();
+
+        let stats = db.optimize().unwrap();
+        assert!(stats.bytes_reclaimed > 0);
+        assert_eq!(
+            stats.after_bytes,
+            stats.before_bytes - stats.bytes_reclaimed
+        );
+    }
+
+    #[test]
+    fn test_check_integrity_repairs_orphans() {
+        let db = create_test_db();
+        let dir = tempfile::tempdir().unwrap();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        let cached = dir.path().join("image1.jpg");
+        std::fs::write(&cached, b"jpeg").unwrap();
+        db.set_thumbnail_cache(
+            "test_session",
+            "image1.jpg",
+            cached.to_str().unwrap(),
+            "2024-01-01",
+        )
+        .unwrap();
+        db.set_thumbnail_cache(
+            "test_session",
+            "image2.jpg",
+            dir.path().join("image2.jpg").to_str().unwrap(),
+            "2024-01-01",
+        )
+        .unwrap();
+        db.set_label("test_session", "image1.jpg", Some("adopted"))
+            .unwrap();
+        // Left behind by a session deleted without enforced foreign keys
+        db.conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        db.set_label("deleted_session", "image1.jpg", Some("adopted"))
+            .unwrap();
+        db.set_label("deleted_session", "image2.jpg", Some("rejected"))
+            .unwrap();
+
+        let report = db.check_integrity(false).unwrap();
+        assert!(report.integrity_errors.is_empty());
+        assert_eq!(
+            report.orphaned_rows,
+            vec![OrphanedRows {
+                table: "labels".to_string(),
+                count: 2,
+            }]
+        );
+        assert_eq!(report.missing_cache_files, 1);
+        assert!(!report.repaired);
+
+        assert!(db.check_integrity(true).unwrap().repaired);
+        let report = db.check_integrity(false).unwrap();
+        assert!(report.orphaned_rows.is_empty());
+        assert_eq!(report.missing_cache_files, 0);
+        assert_eq!(db.get_labels("test_session").unwrap().len(), 1);
+        assert!(db
+            .get_thumbnail_cache("test_session", "image1.jpg")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_transfer_label() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        let image = ImageInfo {
+            filename: "DSC_0001.NEF".to_string(),
+            path: "/test/DSC_0001.NEF".to_string(),
+            size: 100,
+            modified_at: "2024/12/15 10:00".to_string(),
+            is_video: false,
+        };
+        db.set_file_identities("test_session", std::slice::from_ref(&image))
+            .unwrap();
+        db.set_label("test_session", "DSC_0001.NEF", Some("adopted"))
+            .unwrap();
+        db.set_label("test_session", "taken.NEF", Some("rejected"))
+            .unwrap();
+
+        assert!(db
+            .transfer_label("test_session", "DSC_0001.NEF", "renamed.NEF")
+            .unwrap());
+        // The target was labeled meanwhile
+        assert!(!db
+            .transfer_label("test_session", "renamed.NEF", "taken.NEF")
+            .unwrap());
+
+        let mut labels = db.get_labels("test_session").unwrap();
+        labels.sort_by(|a, b| a.filename.cmp(&b.filename));
+        assert_eq!(labels[0].filename, "renamed.NEF");
+        assert_eq!(labels[0].label.as_deref(), Some("adopted"));
+        assert_eq!(labels[1].label.as_deref(), Some("rejected"));
+        assert!(db.get_file_identities("test_session").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_renames() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        db.set_label("test_session", "DSC_0001.NEF", Some("adopted"))
+            .unwrap();
+        db.add_to_compare_set("test_session", &["DSC_0001.NEF".to_string()])
+            .unwrap();
+        let renames = vec![Rename {
+            from: "DSC_0001.NEF".to_string(),
+            to: "shoot_0001.NEF".to_string(),
+        }];
+
+        db.apply_renames("test_session", &renames, true).unwrap();
+        let labels = db.get_labels("test_session").unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].filename, "shoot_0001.NEF");
+        assert_eq!(
+            db.get_compare_set("test_session").unwrap(),
+            vec!["shoot_0001.NEF"]
+        );
+        assert_eq!(db.get_last_renames("test_session").unwrap(), renames);
+
+        // Undoing doesn't leave a batch to undo
+        db.apply_renames("test_session", &[renames[0].inverse()], false)
+            .unwrap();
+        assert_eq!(
+            db.get_labels("test_session").unwrap()[0].filename,
+            "DSC_0001.NEF"
+        );
+        assert!(db.get_last_renames("test_session").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_move_to_session() {
+        let db = create_test_db();
+        for id in ["source", "destination"] {
+            db.upsert_session(&Session {
+                id: id.to_string(),
+                folder_path: format!("/{}", id),
+                last_opened: None,
+                last_selected_index: 0,
+                total_files: 1,
+            })
+            .unwrap();
+        }
+        db.set_label("source", "card1/DSC_0001.NEF", Some("adopted"))
+            .unwrap();
+        db.add_to_compare_set("source", &["card1/DSC_0001.NEF".to_string()])
+            .unwrap();
+
+        db.move_to_session(
+            "source",
+            "destination",
+            &[("card1/DSC_0001.NEF".to_string(), "DSC_0001.NEF".to_string())],
+        )
+        .unwrap();
+        assert!(db.get_labels("source").unwrap().is_empty());
+        assert!(db.get_compare_set("source").unwrap().is_empty());
+        let labels = db.get_labels("destination").unwrap();
+        assert_eq!(labels[0].filename, "DSC_0001.NEF");
+        assert_eq!(labels[0].label.as_deref(), Some("adopted"));
+    }
+
+    #[test]
+    fn test_forget_file() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        db.set_label("test_session", "image1.jpg", Some("rejected"))
+            .unwrap();
+        db.set_label("test_session", "image2.jpg", Some("adopted"))
+            .unwrap();
+        db.set_thumbnail_cache(
+            "test_session",
+            "image1.jpg",
+            "/cache/image1.jpg",
+            "2024-01-01",
+        )
+        .unwrap();
+
+        db.forget_file("test_session", "image1.jpg").unwrap();
+        let labels = db.get_labels("test_session").unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].filename, "image2.jpg");
+        assert!(db
+            .get_thumbnail_cache("test_session", "image1.jpg")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_adjustments() {
+        let db = create_test_db();
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+        assert!(db
+            .get_adjustments("test_session", "DSC_0001.NEF")
+            .unwrap()
+            .is_default());
+
+        let adjustments = RawAdjustments {
+            exposure: 0.5,
+            wb_temp: Some(6500.0),
+            wb_tint: None,
+            white_balance: Some(WhiteBalance::Daylight),
+        };
+        db.set_adjustments("test_session", "DSC_0001.NEF", &adjustments)
+            .unwrap();
+        assert_eq!(
+            db.get_adjustments("test_session", "DSC_0001.NEF").unwrap(),
+            adjustments
+        );
+        db.set_adjustments("test_session", "DSC_0001.NEF", &RawAdjustments::default())
+            .unwrap();
+        assert!(db
+            .get_adjustments("test_session", "DSC_0001.NEF")
+            .unwrap()
+            .is_default());
+    }
+
+    #[test]
+    fn test_get_nonexistent_session() {
+        let db = create_test_db();
+        let result = db.get_session("nonexistent").unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_thumbnail_cache() {
+        let db = create_test_db();
+
+        // Create session
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+
+        // Set thumbnail cache
+        db.set_thumbnail_cache(
+            "test_session",
+            "image1.jpg",
+            "/cache/image1.thumb.jpg",
+            "2024-12-15T14:00:00",
+        )
+        .unwrap();
+
+        // Get cache
+        let cache_path = db
+            .get_thumbnail_cache("test_session", "image1.jpg")
+            .unwrap();
+        assert_eq!(cache_path, Some("/cache/image1.thumb.jpg".to_string()));
 
-impl<T> Optional<T> for std::result::Result<T, rusqlite::Error> {
-    fn optional(self) -> std::result::Result<Option<T>, rusqlite::Error> {
-        match self {
-            Ok(value) => Ok(Some(value)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
-        }
+        // Delete only the listed files
+        db.set_thumbnail_cache(
+            "test_session",
+            "image2.jpg",
+            "/cache/image2.thumb.jpg",
+            "2024-12-15T14:00:00",
+        )
+        .unwrap();
+        let deleted = db
+            .delete_thumbnail_cache(
+                "test_session",
+                &["image1.jpg".to_string(), "missing.jpg".to_string()],
+            )
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert!(db
+            .get_thumbnail_cache("test_session", "image1.jpg")
+            .unwrap()
+            .is_none());
+        assert!(db
+            .get_thumbnail_cache("test_session", "image2.jpg")
+            .unwrap()
+            .is_some());
     }
-}
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct Session {
-    pub id: String,
-    pub folder_path: String,
-    pub last_opened: Option<String>,
-    pub last_selected_index: i32,
-    pub total_files: i32,
-}
+    #[test]
+    fn test_update_cache_paths() {
+        let db = create_test_db();
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct Label {
-    pub filename: String,
-    pub label: Option<String>,
-}
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rusqlite::Connection;
+        db.set_thumbnail_cache(
+            "test_session",
+            "image1.jpg",
+            "/old/cache/test_session/thumbnails/image1.jpg",
+            "2024-12-15T14:00:00",
+        )
+        .unwrap();
+        db.set_thumbnail_cache(
+            "test_session",
+            "image2.jpg",
+            "/elsewhere/image2.jpg",
+            "2024-12-15T14:00:00",
+        )
+        .unwrap();
 
-    fn create_test_db() -> Database {
-        let conn = Connection::open_in_memory().unwrap();
-        let db = Database { conn };
-        db.init_schema().unwrap();
-        db
+        let updated = db.update_cache_paths("/old/cache", "/new/cache").unwrap();
+        assert_eq!(updated, 1);
+
+        assert_eq!(
+            db.get_thumbnail_cache("test_session", "image1.jpg")
+                .unwrap(),
+            Some("/new/cache/test_session/thumbnails/image1.jpg".to_string())
+        );
+        assert_eq!(
+            db.get_thumbnail_cache("test_session", "image2.jpg")
+                .unwrap(),
+            Some("/elsewhere/image2.jpg".to_string())
+        );
     }
 
     #[test]
-    fn test_init_schema() {
+    fn test_session_settings() {
         let db = create_test_db();
-        // Verify schema was created successfully
-        let count: i32 = db
-            .conn
-            .query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='sessions'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap();
-        assert_eq!(count, 1);
+
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
+        };
+        db.upsert_session(&session).unwrap();
+
+        // Defaults when nothing is stored
+        let settings = db.get_session_settings("test_session").unwrap();
+        assert_eq!(settings, SessionSettings::default());
+
+        let settings = SessionSettings {
+            sort_order: Some("date".to_string()),
+            filter: Some("adopted".to_string()),
+            preview_size: Some(3000),
+            export_destination: Some("/export/stage".to_string()),
+            sync_dir: Some("/Dropbox/shoot".to_string()),
+            white_balance: Some(WhiteBalance::Auto),
+        };
+        db.set_session_settings("test_session", &settings).unwrap();
+        assert_eq!(db.get_session_settings("test_session").unwrap(), settings);
+
+        // Overwrite clears unset overrides
+        let settings = SessionSettings {
+            filter: Some("rejected".to_string()),
+            ..Default::default()
+        };
+        db.set_session_settings("test_session", &settings).unwrap();
+        assert_eq!(db.get_session_settings("test_session").unwrap(), settings);
     }
 
     #[test]
-    fn test_upsert_and_get_session() {
+    fn test_session_folders() {
         let db = create_test_db();
 
         let session = Session {
-            id: "test_session_id".to_string(),
-            folder_path: "/test/path".to_string(),
-            last_opened: Some("2024-12-15T14:00:00".to_string()),
-            last_selected_index: 10,
-            total_files: 100,
+            id: "test_session".to_string(),
+            folder_path: "/Volumes".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 10,
         };
-
         db.upsert_session(&session).unwrap();
 
-        let retrieved = db.get_session("test_session_id").unwrap().unwrap();
-        assert_eq!(retrieved.id, "test_session_id");
-        assert_eq!(retrieved.folder_path, "/test/path");
-        assert_eq!(retrieved.last_selected_index, 10);
-        assert_eq!(retrieved.total_files, 100);
+        // Falls back to the session folder
+        assert_eq!(
+            db.get_session_folders("test_session").unwrap(),
+            vec!["/Volumes"]
+        );
+
+        let folders = vec![
+            "/Volumes/CARD1/DCIM".to_string(),
+            "/Volumes/CARD2/DCIM".to_string(),
+        ];
+        db.set_session_folders("test_session", &folders).unwrap();
+        assert_eq!(db.get_session_folders("test_session").unwrap(), folders);
+
+        // Replacing keeps only the new set
+        db.set_session_folders("test_session", &folders[1..])
+            .unwrap();
+        assert_eq!(
+            db.get_session_folders("test_session").unwrap(),
+            vec!["/Volumes/CARD2/DCIM"]
+        );
+
+        assert!(db.get_session_folders("missing").unwrap().is_empty());
     }
 
     #[test]
-    fn test_update_last_selected() {
+    fn test_view_state() {
         let db = create_test_db();
 
         let session = Session {
-            id: "test_session_id".to_string(),
-            folder_path: "/test/path".to_string(),
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
             last_opened: None,
             last_selected_index: 0,
-            total_files: 50,
+            total_files: 10,
         };
-
         db.upsert_session(&session).unwrap();
-        db.update_last_selected("test_session_id", 25).unwrap();
+        assert_eq!(
+            db.get_view_state("test_session").unwrap(),
+            ViewState::default()
+        );
 
-        let retrieved = db.get_session("test_session_id").unwrap().unwrap();
-        assert_eq!(retrieved.last_selected_index, 25);
+        let view_state = ViewState {
+            filter: Some("adopted".to_string()),
+            sort_order: Some("date".to_string()),
+            grid_zoom: Some(240),
+        };
+        db.set_view_state("test_session", &view_state).unwrap();
+
+        // Reopening the session keeps the view state
+        db.upsert_session(&session).unwrap();
+        assert_eq!(db.get_view_state("test_session").unwrap(), view_state);
     }
 
     #[test]
-    fn test_set_and_get_label() {
+    fn test_set_and_get_analysis() {
         let db = create_test_db();
 
-        // Create session
         let session = Session {
             id: "test_session".to_string(),
             folder_path: "/test".to_string(),
             last_opened: None,
             last_selected_index: 0,
-            total_files: 10,
+            total_files: 1,
         };
         db.upsert_session(&session).unwrap();
 
-        // Set labels
-        db.set_label("test_session", "image1.jpg", Some("rejected"))
-            .unwrap();
-        db.set_label("test_session", "image2.jpg", Some("rejected"))
+        let analysis = ImageAnalysis {
+            filename: "DSC_0001.NEF".to_string(),
+            sharpness: 120.5,
+            subject_sharpness: 200.0,
+            highlight_clipping: 0.02,
+            shadow_clipping: 0.4,
+            dhash: u64::MAX - 1,
+            faces: vec![FaceBox {
+                x: 0.25,
+                y: 0.1,
+                width: 0.2,
+                height: 0.3,
+                score: 4.5,
+            }],
+            eye_contrast: Some(1.5),
+        };
+        db.set_analysis("test_session", "24117248:1734273120123456789", &analysis)
             .unwrap();
 
-        // Get labels
-        let labels = db.get_labels("test_session").unwrap();
-        assert_eq!(labels.len(), 2);
-
-        let image1_label = labels.iter().find(|l| l.filename == "image1.jpg").unwrap();
-        assert_eq!(image1_label.label, Some("rejected".to_string()));
+        let analyses = db.get_analyses("test_session").unwrap();
+        assert_eq!(
+            analyses.get("DSC_0001.NEF"),
+            Some(&("24117248:1734273120123456789".to_string(), analysis))
+        );
+        assert!(db.get_analyses("other_session").unwrap().is_empty());
     }
 
     #[test]
-    fn test_remove_label() {
+    fn test_exif_cache() {
         let db = create_test_db();
 
-        // Create session
         let session = Session {
             id: "test_session".to_string(),
             folder_path: "/test".to_string(),
             last_opened: None,
             last_selected_index: 0,
-            total_files: 10,
+            total_files: 1,
         };
         db.upsert_session(&session).unwrap();
 
-        // Set label
-        db.set_label("test_session", "image1.jpg", Some("rejected"))
+        let exif = ExifInfo {
+            camera_model: Some("NIKON Z 6".to_string()),
+            iso: Some("ISO 800".to_string()),
+            ..Default::default()
+        };
+        db.set_exif_cache(
+            "test_session",
+            &[(
+                "DSC_0001.NEF".to_string(),
+                "2024/12/15 14:32".to_string(),
+                exif.clone(),
+            )],
+        )
+        .unwrap();
+
+        let cached = db.get_exif_cache("test_session").unwrap();
+        assert_eq!(
+            cached.get("DSC_0001.NEF"),
+            Some(&("2024/12/15 14:32".to_string(), exif.clone()))
+        );
+        assert!(db.get_exif_cache("other_session").unwrap().is_empty());
+
+        // Written before GPS positions were read
+        let mut old = serde_json::to_value(&exif).unwrap();
+        old.as_object_mut().unwrap().remove("latitude");
+        db.conn
+            .execute(
+                "UPDATE exif_cache SET exif = ?1 WHERE filename = 'DSC_0001.NEF'",
+                params![old.to_string()],
+            )
             .unwrap();
+        assert!(db.get_exif_cache("test_session").unwrap().is_empty());
+    }
 
-        // Remove label
-        db.set_label("test_session", "image1.jpg", None).unwrap();
+    #[test]
+    fn test_time_offsets() {
+        let db = create_test_db();
+        db.upsert_session(&Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 2,
+        })
+        .unwrap();
+        let files = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
 
-        // Get labels
-        let labels = db.get_labels("test_session").unwrap();
-        assert_eq!(labels.len(), 0);
+        db.add_time_offset("test_session", &files(&["A.NEF", "B.NEF"]), -3600)
+            .unwrap();
+        db.add_time_offset("test_session", &files(&["A.NEF"]), 90)
+            .unwrap();
+        let offsets = db.get_time_offsets("test_session").unwrap();
+        assert_eq!(offsets.get("A.NEF"), Some(&-3510));
+        assert_eq!(offsets.get("B.NEF"), Some(&-3600));
+        assert!(db.get_time_offsets("other_session").unwrap().is_empty());
+
+        // Undoing a correction by hand leaves nothing behind
+        db.add_time_offset("test_session", &files(&["B.NEF"]), 3600)
+            .unwrap();
+        db.clear_time_offsets("test_session", &files(&["A.NEF"]))
+            .unwrap();
+        assert!(db.get_time_offsets("test_session").unwrap().is_empty());
     }
 
     #[test]
-    fn test_get_nonexistent_session() {
+    fn test_perceptual_hashes() {
         let db = create_test_db();
-        let result = db.get_session("nonexistent").unwrap();
-        assert!(result.is_none());
+        db.upsert_session(&Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 1,
+        })
+        .unwrap();
+
+        let fingerprint = (
+            "A.NEF".to_string(),
+            "24117248:1704067200000000000".to_string(),
+            "abc123".to_string(),
+        );
+        db.set_file_fingerprints("test_session", std::slice::from_ref(&fingerprint))
+            .unwrap();
+        assert_eq!(
+            db.get_file_fingerprints("test_session")
+                .unwrap()
+                .get("A.NEF"),
+            Some(&(fingerprint.1.clone(), fingerprint.2.clone()))
+        );
+
+        // Shared by every session, and read back bit for bit
+        db.set_perceptual_hashes(&[("abc123".to_string(), u64::MAX - 1)])
+            .unwrap();
+        let hashes = db
+            .get_perceptual_hashes(&["abc123".to_string(), "unknown".to_string()])
+            .unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes.get("abc123"), Some(&(u64::MAX - 1)));
     }
 
     #[test]
-    fn test_thumbnail_cache() {
+    fn test_find_global_duplicates() {
         let db = create_test_db();
+        for (id, folder_path) in [
+            ("card_dump", "/photos/dump"),
+            ("sorted", "/photos/sorted"),
+            ("dump_parent", "/photos"),
+        ] {
+            db.upsert_session(&Session {
+                id: id.to_string(),
+                folder_path: folder_path.to_string(),
+                last_opened: None,
+                last_selected_index: 0,
+                total_files: 2,
+            })
+            .unwrap();
+        }
+        let row = |filename: &str, fingerprint: &str| {
+            (
+                filename.to_string(),
+                "2024-01-01T00:00:00Z".to_string(),
+                fingerprint.to_string(),
+            )
+        };
+        db.set_file_fingerprints(
+            "card_dump",
+            &[
+                row("DSC_0001.NEF", "same"),
+                row("DSC_0002.NEF", "only_here"),
+            ],
+        )
+        .unwrap();
+        db.set_file_fingerprints("sorted", &[row("best/DSC_0001.NEF", "same")])
+            .unwrap();
+        // The card dump again through its parent folder: the same files, not copies
+        db.set_file_fingerprints(
+            "dump_parent",
+            &[
+                row("dump/DSC_0001.NEF", "same"),
+                row("dump/DSC_0002.NEF", "only_here"),
+            ],
+        )
+        .unwrap();
 
-        // Create session
-        let session = Session {
+        let groups = db.find_global_duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].fingerprint, "same");
+        let paths: Vec<&str> = groups[0].files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/photos/dump/DSC_0001.NEF",
+                "/photos/sorted/best/DSC_0001.NEF"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metadata_edits() {
+        let db = create_test_db();
+        db.upsert_session(&Session {
             id: "test_session".to_string(),
             folder_path: "/test".to_string(),
             last_opened: None,
             last_selected_index: 0,
-            total_files: 10,
-        };
-        db.upsert_session(&session).unwrap();
+            total_files: 2,
+        })
+        .unwrap();
+        let files = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
 
-        // Set thumbnail cache
-        db.set_thumbnail_cache(
+        db.edit_metadata(
             "test_session",
-            "image1.jpg",
-            "/cache/image1.thumb.jpg",
-            "2024-12-15T14:00:00",
+            &files(&["A.JPG", "B.NEF"]),
+            &MetadataEdits {
+                artist: Some("Jane Doe".to_string()),
+                copyright: Some("Studio North".to_string()),
+                ..MetadataEdits::default()
+            },
+        )
+        .unwrap();
+        db.edit_metadata(
+            "test_session",
+            &files(&["B.NEF"]),
+            &MetadataEdits {
+                artist: Some(String::new()),
+                ..MetadataEdits::default()
+            },
         )
         .unwrap();
+        let edits = db.get_metadata_edits("test_session").unwrap();
+        assert_eq!(edits["A.JPG"].artist.as_deref(), Some("Jane Doe"));
+        assert_eq!(edits["B.NEF"].artist, None);
+        assert_eq!(edits["B.NEF"].copyright.as_deref(), Some("Studio North"));
 
-        // Get cache
-        let cache_path = db
-            .get_thumbnail_cache("test_session", "image1.jpg")
+        // Dropping the last correction removes the file's row
+        db.edit_metadata(
+            "test_session",
+            &files(&["B.NEF"]),
+            &MetadataEdits {
+                copyright: Some(String::new()),
+                ..MetadataEdits::default()
+            },
+        )
+        .unwrap();
+        assert!(!db
+            .get_metadata_edits("test_session")
+            .unwrap()
+            .contains_key("B.NEF"));
+    }
+
+    #[test]
+    fn test_problem_files() {
+        let db = create_test_db();
+
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 2,
+        };
+        db.upsert_session(&session).unwrap();
+
+        let damaged = ProblemFile {
+            filename: "DSC_0002.NEF".to_string(),
+            original_modified: "2024/12/15 14:32".to_string(),
+            error: "RAW processing error: unexpected end of file".to_string(),
+        };
+        db.set_problem_file("test_session", &damaged).unwrap();
+        // Failing again only updates the entry
+        db.set_problem_file("test_session", &damaged).unwrap();
+        assert_eq!(db.get_problem_files("test_session").unwrap(), vec![damaged]);
+        assert!(db.get_problem_files("other_session").unwrap().is_empty());
+
+        let deleted = db
+            .delete_problem_files("test_session", &["DSC_0002.NEF".to_string()])
             .unwrap();
-        assert_eq!(cache_path, Some("/cache/image1.thumb.jpg".to_string()));
+        assert_eq!(deleted, 1);
+        assert!(db.get_problem_files("test_session").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_thumbnail_failures() {
+        let db = create_test_db();
+
+        let session = Session {
+            id: "test_session".to_string(),
+            folder_path: "/test".to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 2,
+        };
+        db.upsert_session(&session).unwrap();
+
+        let failure = ThumbnailFailure {
+            filename: "DSC_0001.NEF".to_string(),
+            kind: FailureKind::Error,
+            error: "RAW processing error: Couldn't find camera".to_string(),
+            failed_at: "2024-12-15T14:32:00+09:00".to_string(),
+        };
+        db.set_thumbnail_failure("test_session", &failure).unwrap();
+        let placeholder = ThumbnailFailure {
+            filename: "DSC_0002.JPG".to_string(),
+            kind: FailureKind::Placeholder,
+            ..failure.clone()
+        };
+        db.set_thumbnail_failure("test_session", &placeholder)
+            .unwrap();
+
+        assert_eq!(
+            db.get_thumbnail_failures("test_session").unwrap(),
+            vec![failure, placeholder.clone()]
+        );
+
+        db.delete_thumbnail_failures("test_session", &["DSC_0001.NEF".to_string()])
+            .unwrap();
+        assert_eq!(
+            db.get_thumbnail_failures("test_session").unwrap(),
+            vec![placeholder]
+        );
+    }
+
+    #[test]
+    fn test_session_summaries() {
+        let db = create_test_db();
+
+        for (id, last_opened) in [
+            ("older", "2024-01-01T10:00:00+09:00"),
+            ("newer", "2024-06-01T10:00:00+09:00"),
+        ] {
+            db.upsert_session(&Session {
+                id: id.to_string(),
+                folder_path: format!("/photos/{}", id),
+                last_opened: Some(last_opened.to_string()),
+                last_selected_index: 0,
+                total_files: 2,
+            })
+            .unwrap();
+        }
+        db.set_label("older", "a.jpg", Some("adopted")).unwrap();
+        db.set_label("older", "b.jpg", Some("rejected")).unwrap();
+
+        let summaries = db.get_session_summaries().unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].id, "newer");
+        assert_eq!(summaries[0].label_count, 0);
+        assert_eq!(summaries[1].folder_path, "/photos/older");
+        assert_eq!(summaries[1].label_count, 2);
     }
 }
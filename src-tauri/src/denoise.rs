@@ -0,0 +1,90 @@
+// Light chroma noise reduction of high-ISO RAW previews
+// Color blotches are most of what makes an unprocessed night frame look worse than it will
+// after editing; luminance is left alone, so sharpness can still be judged
+
+use crate::image_processor::ExifInfo;
+use image::imageops;
+use image::{ImageBuffer, Luma, Rgb, RgbImage};
+
+/// Blur radius (Gaussian sigma) of the color planes per 1000 pixels of the long edge
+const CHROMA_SIGMA_PER_1000PX: f32 = 1.5;
+
+/// ISO speed of an EXIF value such as "ISO 3200"
+pub fn iso(exif: &ExifInfo) -> Option<u32> {
+    let value = exif.iso.as_deref()?.trim();
+    let value = value.strip_prefix("ISO").unwrap_or(value).trim();
+    // Some cameras list several values ("3200, 0, 0")
+    value.split(',').next()?.trim().parse().ok()
+}
+
+/// `img` with its color (CbCr) planes smoothed and its brightness (Y) untouched
+pub fn reduce_chroma_noise(img: &RgbImage) -> RgbImage {
+    let (width, height) = img.dimensions();
+    let sigma = (width.max(height) as f32 / 1000.0 * CHROMA_SIGMA_PER_1000PX).max(1.0);
+
+    let plane = |f: fn([f32; 3]) -> f32| -> ImageBuffer<Luma<f32>, Vec<f32>> {
+        ImageBuffer::from_fn(width, height, |x, y| {
+            Luma([f(img.get_pixel(x, y).0.map(f32::from))])
+        })
+    };
+    let cb = imageops::blur(
+        &plane(|[r, g, b]| -0.168_736 * r - 0.331_264 * g + 0.5 * b),
+        sigma,
+    );
+    let cr = imageops::blur(
+        &plane(|[r, g, b]| 0.5 * r - 0.418_688 * g - 0.081_312 * b),
+        sigma,
+    );
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let [r, g, b] = img.get_pixel(x, y).0.map(f32::from);
+        let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+        let (cb, cr) = (cb.get_pixel(x, y).0[0], cr.get_pixel(x, y).0[0]);
+        let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        Rgb([
+            channel(luma + 1.402 * cr),
+            channel(luma - 0.344_136 * cb - 0.714_136 * cr),
+            channel(luma + 1.772 * cb),
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso() {
+        let exif = |iso: &str| ExifInfo {
+            iso: Some(iso.to_string()),
+            ..ExifInfo::default()
+        };
+        assert_eq!(iso(&exif("ISO 3200")), Some(3200));
+        assert_eq!(iso(&exif("ISO 6400, 0, 0")), Some(6400));
+        assert_eq!(iso(&exif("Auto")), None);
+        assert_eq!(iso(&ExifInfo::default()), None);
+    }
+
+    #[test]
+    fn test_reduce_chroma_noise() {
+        // Mid gray with alternating red and cyan speckles of equal brightness
+        let img = RgbImage::from_fn(64, 64, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgb([160, 115, 120])
+            } else {
+                Rgb([100, 141, 136])
+            }
+        });
+        let denoised = reduce_chroma_noise(&img);
+
+        let luma =
+            |p: Rgb<u8>| 0.299 * p.0[0] as f32 + 0.587 * p.0[1] as f32 + 0.114 * p.0[2] as f32;
+        for (x, y) in [(10, 10), (11, 10), (30, 41)] {
+            let (before, after) = (*img.get_pixel(x, y), *denoised.get_pixel(x, y));
+            assert!((luma(before) - luma(after)).abs() < 1.5);
+            // Speckles fade to the gray around them
+            let spread = |p: Rgb<u8>| p.0.iter().max().unwrap() - p.0.iter().min().unwrap();
+            assert!(spread(after) < 10, "{:?}", after);
+        }
+    }
+}
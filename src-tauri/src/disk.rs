@@ -0,0 +1,158 @@
+use crate::error::{GlimpseError, Result};
+use std::path::Path;
+
+/// Rough size of one cached thumbnail at 300px (JPEG)
+const THUMBNAIL_BYTES_AT_300: u64 = 40 * 1024;
+/// Rough size of one cached preview at 2000px (JPEG quality 90)
+const PREVIEW_BYTES_AT_2000: u64 = 1200 * 1024;
+/// Extra headroom kept free so the OS and database don't run dry
+const SAFETY_MARGIN_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Get available space on the volume holding `path`
+/// If `path` doesn't exist yet, the nearest existing ancestor is used
+pub fn available_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| GlimpseError::InvalidPath(path.to_string_lossy().to_string()))?;
+    fs4::available_space(existing).map_err(|e| GlimpseError::io_at(existing, e))
+}
+
+/// Whether `src` and `folder` are on the same volume
+/// If `folder` doesn't exist yet, the nearest existing ancestor is used
+#[cfg(unix)]
+pub fn same_volume(src: &Path, folder: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let folder = folder.ancestors().find(|p| p.exists()).unwrap_or(folder);
+    match (std::fs::metadata(src), std::fs::metadata(folder)) {
+        (Ok(src), Ok(folder)) => src.dev() == folder.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+pub fn same_volume(src: &Path, folder: &Path) -> bool {
+    let root = |path: &Path| {
+        crate::volume::volume_root(path).map(|root| root.to_string_lossy().to_lowercase())
+    };
+    root(src).is_some_and(|src| root(folder) == Some(src))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn same_volume(_src: &Path, _folder: &Path) -> bool {
+    false
+}
+
+/// Fail with `InsufficientSpace` if `required` bytes (plus a safety margin)
+/// don't fit on the volume holding `path`
+pub fn ensure_space(path: &Path, required: u64) -> Result<()> {
+    let available = available_space(path)?;
+    check_space(path, required, available)
+}
+
+fn check_space(path: &Path, required: u64, available: u64) -> Result<()> {
+    let required = required.saturating_add(SAFETY_MARGIN_BYTES);
+    if available < required {
+        return Err(GlimpseError::InsufficientSpace {
+            path: path.to_string_lossy().replace('\\', "/"),
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Bytes an export of `(size, freed)` files needs on its destination
+/// A freed file is moved within one volume: its original is deleted right after the copy,
+/// so all of those together only need room for the largest one
+pub fn export_bytes(files: impl IntoIterator<Item = (u64, bool)>) -> u64 {
+    let (mut copied, mut largest_freed) = (0u64, 0u64);
+    for (size, freed) in files {
+        if freed {
+            largest_freed = largest_freed.max(size);
+        } else {
+            copied = copied.saturating_add(size);
+        }
+    }
+    copied.saturating_add(largest_freed)
+}
+
+/// Estimate bytes needed to cache `thumbnails` thumbnails and `previews` previews
+/// JPEG size grows roughly with pixel count, i.e. with the square of the edge length
+pub fn estimate_cache_bytes(
+    thumbnails: usize,
+    previews: usize,
+    thumbnail_size: u32,
+    preview_size: u32,
+) -> u64 {
+    let scale = |base: u64, base_edge: u64, edge: u32| {
+        base * (edge as u64 * edge as u64) / (base_edge * base_edge)
+    };
+    let per_thumbnail = scale(THUMBNAIL_BYTES_AT_300, 300, thumbnail_size);
+    let per_preview = scale(PREVIEW_BYTES_AT_2000, 2000, preview_size);
+    per_thumbnail * thumbnails as u64 + per_preview * previews as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_available_space_nonexistent_child() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("not").join("created");
+        assert!(available_space(&missing).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_check_space() {
+        let path = Path::new("/export");
+        assert!(check_space(path, 1000, SAFETY_MARGIN_BYTES + 1000).is_ok());
+
+        let err = check_space(path, 1000, 500).unwrap_err();
+        assert_eq!(err.code(), "insufficient_space");
+        match err {
+            GlimpseError::InsufficientSpace {
+                required,
+                available,
+                ..
+            } => {
+                assert_eq!(required, SAFETY_MARGIN_BYTES + 1000);
+                assert_eq!(available, 500);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_same_volume() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("DSC_0001.NEF");
+        std::fs::write(&src, b"raw data").unwrap();
+        assert!(same_volume(&src, &dir.path().join("export").join("2024")));
+        assert!(!same_volume(&dir.path().join("missing"), dir.path()));
+    }
+
+    #[test]
+    fn test_export_bytes() {
+        assert_eq!(export_bytes([]), 0);
+        assert_eq!(export_bytes([(100, false), (300, false)]), 400);
+        assert_eq!(export_bytes([(100, true), (300, true)]), 300);
+        assert_eq!(export_bytes([(100, false), (300, true), (200, true)]), 400);
+    }
+
+    #[test]
+    fn test_estimate_cache_bytes() {
+        assert_eq!(estimate_cache_bytes(0, 0, 300, 2000), 0);
+        assert_eq!(
+            estimate_cache_bytes(10, 2, 300, 2000),
+            10 * THUMBNAIL_BYTES_AT_300 + 2 * PREVIEW_BYTES_AT_2000
+        );
+        // Doubling the edge length roughly quadruples the size
+        assert_eq!(
+            estimate_cache_bytes(1, 0, 600, 2000),
+            4 * THUMBNAIL_BYTES_AT_300
+        );
+    }
+}
@@ -0,0 +1,192 @@
+// JPEG previews cameras embed in RAW files
+// Used when the RAW data itself can't be decoded (unsupported body, slightly corrupt file),
+// and the tiny EXIF thumbnails of JPEGs and RAWs as stand-ins until thumbnails are ready
+
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::extract_exif;
+use exif::{In, Reader, Tag};
+use image::metadata::Orientation;
+use image::DynamicImage;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// The largest embedded preview of a RAW file, rotated like the developed image would be
+pub fn load_embedded_preview(path: &Path) -> Result<DynamicImage> {
+    let data = std::fs::read(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let jpeg = largest_jpeg(&data).ok_or_else(|| {
+        GlimpseError::RawProcessing(format!("No embedded preview in {}", path.display()))
+    })?;
+    let mut img = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)?;
+
+    let orientation = extract_exif(path)
+        .ok()
+        .and_then(|exif| exif.orientation)
+        .and_then(|value| Orientation::from_exif(value as u8));
+    if let Some(orientation) = orientation {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+/// The thumbnail stored in a file's EXIF (IFD1), rotated like the image
+/// Usually about 160x120; an error for files without one
+pub fn load_exif_thumbnail(path: &Path) -> Result<DynamicImage> {
+    let missing = || GlimpseError::ExifError(format!("No EXIF thumbnail in {}", path.display()));
+    let file = File::open(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .map_err(|e| GlimpseError::ExifError(e.to_string()))?;
+
+    let uint = |tag, ifd| exif.get_field(tag, ifd)?.value.get_uint(0);
+    let offset = uint(Tag::JPEGInterchangeFormat, In::THUMBNAIL).ok_or_else(missing)? as usize;
+    let len = uint(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL).ok_or_else(missing)? as usize;
+    let jpeg = exif
+        .buf()
+        .get(offset..offset.saturating_add(len))
+        .ok_or_else(missing)?;
+    let mut img = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)?;
+
+    let orientation =
+        uint(Tag::Orientation, In::PRIMARY).and_then(|value| Orientation::from_exif(value as u8));
+    if let Some(orientation) = orientation {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+/// The largest complete JPEG stream in `data`
+/// Cameras embed several sizes; the largest is usually close to full resolution
+pub fn largest_jpeg(data: &[u8]) -> Option<&[u8]> {
+    let mut largest: Option<&[u8]> = None;
+    let mut pos = 0;
+    while let Some(offset) = data[pos..].windows(3).position(|w| w == [0xFF, 0xD8, 0xFF]) {
+        let start = pos + offset;
+        match jpeg_len(&data[start..]) {
+            Some(len) => {
+                let jpeg = &data[start..start + len];
+                if largest.is_none_or(|l| l.len() < len) {
+                    largest = Some(jpeg);
+                }
+                pos = start + len;
+            }
+            None => pos = start + 2,
+        }
+    }
+    largest
+}
+
+/// Length of the JPEG stream starting at `data[0]`, through its EOI marker
+/// Segments are skipped by their lengths, so thumbnails nested in EXIF aren't mistaken for
+/// the end; None when the stream is cut off or malformed
+fn jpeg_len(data: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            0xD9 => return Some(pos),
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+
+        let len = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        if len < 2 {
+            return None;
+        }
+        pos += len;
+        if marker == 0xDA {
+            // Entropy-coded data runs to the next marker other than stuffing and restarts
+            loop {
+                let byte = *data.get(pos)?;
+                let next = *data.get(pos + 1)?;
+                if byte == 0xFF && next != 0x00 && !(0xD0..=0xD7).contains(&next) {
+                    break;
+                }
+                pos += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 7) as u8, (y * 13) as u8, ((x + y) * 3) as u8])
+        });
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 90)
+            .encode_image(&img)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_largest_jpeg() {
+        let small = jpeg(16, 16);
+        let large = jpeg(256, 192);
+        let mut raw = b"II*\0\x08\0\0\0sensor data \xFF\xD8\xFF broken".to_vec();
+        raw.extend(&small);
+        raw.extend(b"\0\xFF\xD8\xFF\xD9 more sensor data");
+        raw.extend(&large);
+        raw.extend(&[0u8; 64]);
+
+        assert_eq!(jpeg_len(&large), Some(large.len()));
+        assert_eq!(largest_jpeg(&raw), Some(large.as_slice()));
+        // Cut off before its end
+        assert_eq!(jpeg_len(&large[..large.len() - 10]), None);
+        assert_eq!(largest_jpeg(b"II*\0\x08\0\0\0"), None);
+    }
+
+    #[test]
+    fn test_load_embedded_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSC_0001.NEF");
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend(jpeg(256, 192));
+        std::fs::write(&path, &raw).unwrap();
+
+        let img = load_embedded_preview(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (256, 192));
+
+        std::fs::write(&path, b"II*\0\x08\0\0\0").unwrap();
+        assert!(load_embedded_preview(&path).is_err());
+    }
+
+    #[test]
+    fn test_load_exif_thumbnail() {
+        let thumbnail = jpeg(16, 12);
+        // IFD0 with Orientation 6 (rotate 90 degrees), IFD1 pointing at the JPEG right after it
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend([1, 0]);
+        raw.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        raw.extend(26u32.to_le_bytes());
+        raw.extend([2, 0]);
+        raw.extend([0x01, 0x02, 4, 0, 1, 0, 0, 0]);
+        raw.extend(56u32.to_le_bytes());
+        raw.extend([0x02, 0x02, 4, 0, 1, 0, 0, 0]);
+        raw.extend((thumbnail.len() as u32).to_le_bytes());
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(&thumbnail);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSC_0001.NEF");
+        std::fs::write(&path, &raw).unwrap();
+        let img = load_exif_thumbnail(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (12, 16));
+
+        raw.truncate(56 + thumbnail.len() / 2);
+        std::fs::write(&path, &raw).unwrap();
+        assert!(load_exif_thumbnail(&path).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +6,13 @@ pub enum GlimpseError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("IO error at {path}: {source}")]
+    FileIo {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
 
@@ -17,20 +25,240 @@ pub enum GlimpseError {
     #[error("EXIF error: {0}")]
     ExifError(String),
 
+    #[error("Video processing error: {0}")]
+    Video(String),
+
     #[error("Session not found")]
     SessionNotFound,
 
+    #[error("No session active")]
+    NoActiveSession,
+
+    #[error("Task not found: {0}")]
+    TaskNotFound(u64),
+
+    #[error("Burst not found: {0}")]
+    BurstNotFound(String),
+
+    #[error("Bracket set not found: {0}")]
+    BracketNotFound(String),
+
+    #[error("Interrupted operation not found: {0}")]
+    OperationNotFound(i64),
+
+    #[error("Export history entry not found: {0}")]
+    ExportNotFound(i64),
+
+    #[error("Project not found: {0}")]
+    ProjectNotFound(i64),
+
+    #[error("Culling pass not found: {0}")]
+    PassNotFound(String),
+
+    #[error("Culling pass already exists: {0}")]
+    PassExists(String),
+
+    #[error("Rename target already exists: {0}")]
+    RenameConflict(String),
+
+    #[error("Analysis error: {0}")]
+    Analysis(String),
+
+    #[error("SFTP error: {0}")]
+    Sftp(String),
+
+    #[error("Keychain error: {0}")]
+    Keychain(String),
+
+    #[error("Font error: {0}")]
+    Font(String),
+
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid session bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Source drive is not connected: {0}")]
+    SourceUnavailable(String),
+
+    #[error("Network share unreachable: {0}")]
+    ShareUnreachable(String),
+
+    #[error(
+        "Not enough disk space at {path}: {} more bytes needed",
+        .required.saturating_sub(*.available)
+    )]
+    InsufficientSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
+}
+
+impl GlimpseError {
+    /// Whether decoding failed because the file data is truncated or corrupt
+    pub fn is_corrupt_data(&self) -> bool {
+        use std::io::ErrorKind;
+        match self {
+            GlimpseError::Image(image::ImageError::Decoding(_)) => true,
+            // Cameras rawloader doesn't know yet aren't damaged files
+            GlimpseError::RawProcessing(message) => !message.contains("Couldn't find camera"),
+            GlimpseError::Image(image::ImageError::IoError(e))
+            | GlimpseError::Io(e)
+            | GlimpseError::FileIo { source: e, .. } => {
+                matches!(e.kind(), ErrorKind::UnexpectedEof | ErrorKind::InvalidData)
+            }
+            _ => false,
+        }
+    }
+
+    /// Report errors on a disconnected external drive as such instead of a bare IO error
+    pub fn or_source_unavailable(self, path: &Path) -> Self {
+        match self {
+            GlimpseError::Io(_) | GlimpseError::FileIo { .. }
+                if crate::volume::is_volume_missing(path) =>
+            {
+                GlimpseError::SourceUnavailable(path.to_string_lossy().replace('\\', "/"))
+            }
+            e => e,
+        }
+    }
+
+    /// Wrap an IO error together with the path it occurred on
+    pub fn io_at(path: &Path, source: std::io::Error) -> Self {
+        GlimpseError::FileIo {
+            path: path.to_string_lossy().replace('\\', "/"),
+            source,
+        }
+    }
+
+    /// Stable error code for the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            GlimpseError::Io(e) | GlimpseError::FileIo { source: e, .. } => io_error_code(e),
+            GlimpseError::Database(_) => "database",
+            GlimpseError::Image(image::ImageError::Unsupported(_)) => "unsupported_format",
+            GlimpseError::Image(_) => "image",
+            GlimpseError::RawProcessing(_) => "raw_processing",
+            GlimpseError::ExifError(_) => "exif",
+            GlimpseError::Video(_) => "video",
+            GlimpseError::SessionNotFound => "session_not_found",
+            GlimpseError::NoActiveSession => "no_active_session",
+            GlimpseError::TaskNotFound(_) => "task_not_found",
+            GlimpseError::BurstNotFound(_) => "burst_not_found",
+            GlimpseError::BracketNotFound(_) => "bracket_not_found",
+            GlimpseError::OperationNotFound(_) => "operation_not_found",
+            GlimpseError::ExportNotFound(_) => "export_not_found",
+            GlimpseError::ProjectNotFound(_) => "project_not_found",
+            GlimpseError::PassNotFound(_) => "pass_not_found",
+            GlimpseError::PassExists(_) => "pass_exists",
+            GlimpseError::RenameConflict(_) => "rename_conflict",
+            GlimpseError::Analysis(_) => "analysis",
+            GlimpseError::Sftp(_) => "sftp",
+            GlimpseError::Keychain(_) => "keychain",
+            GlimpseError::Font(_) => "font",
+            GlimpseError::InvalidPath(_) => "invalid_path",
+            GlimpseError::InvalidConfig(_) => "invalid_config",
+            GlimpseError::InvalidBundle(_) => "invalid_bundle",
+            GlimpseError::InsufficientSpace { .. } => "insufficient_space",
+            GlimpseError::ShareUnreachable(_) => "share_unreachable",
+            GlimpseError::SourceUnavailable(_) => "source_unavailable",
+        }
+    }
+
+    /// Path the error relates to, if known
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            GlimpseError::FileIo { path, .. }
+            | GlimpseError::InsufficientSpace { path, .. }
+            | GlimpseError::ShareUnreachable(path)
+            | GlimpseError::SourceUnavailable(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Map an IO error to an error code
+fn io_error_code(e: &std::io::Error) -> &'static str {
+    // Windows sharing/lock violations (file opened by another program)
+    #[cfg(windows)]
+    if matches!(e.raw_os_error(), Some(32) | Some(33)) {
+        return "file_locked";
+    }
+
+    match e.kind() {
+        std::io::ErrorKind::NotFound => "not_found",
+        std::io::ErrorKind::PermissionDenied => "permission_denied",
+        std::io::ErrorKind::AlreadyExists => "already_exists",
+        std::io::ErrorKind::WouldBlock => "file_locked",
+        _ => "io",
+    }
 }
 
+/// Errors are sent to the frontend as `{ code, message, path }`
 impl serde::Serialize for GlimpseError {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("GlimpseError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("path", &self.path())?;
+        state.end()
     }
 }
 
 pub type Result<T> = std::result::Result<T, GlimpseError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_error_payload() {
+        let err = GlimpseError::io_at(
+            Path::new("/photos/DSC_0001.NEF"),
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+        let json = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(json["code"], "permission_denied");
+        assert_eq!(json["path"], "/photos/DSC_0001.NEF");
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .contains("/photos/DSC_0001.NEF"));
+    }
+
+    #[test]
+    fn test_serialize_error_without_path() {
+        let json = serde_json::to_value(GlimpseError::NoActiveSession).unwrap();
+
+        assert_eq!(json["code"], "no_active_session");
+        assert_eq!(json["message"], "No session active");
+        assert!(json["path"].is_null());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            GlimpseError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)).code(),
+            "not_found"
+        );
+        assert_eq!(
+            GlimpseError::RawProcessing("unsupported camera".into()).code(),
+            "raw_processing"
+        );
+        assert_eq!(
+            GlimpseError::InvalidConfig("bad".into()).code(),
+            "invalid_config"
+        );
+    }
+}
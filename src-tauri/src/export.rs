@@ -0,0 +1,668 @@
+use crate::error::{GlimpseError, Result};
+use crate::image_processor;
+use crate::tiff::{Tiff, LONG};
+use crate::watermark::Watermark;
+use crate::xmp::XmpSidecar;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageError, ImageReader};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Component, Path, PathBuf};
+
+/// JPEG quality used when an exported image has to be re-encoded
+const EXPORT_JPEG_QUALITY: u8 = 95;
+
+/// Defaults of the small JPEG mode: big enough for a full-screen view, small enough to mail
+const SMALL_JPEG_LONG_EDGE: u32 = 2048;
+const SMALL_JPEG_QUALITY: u8 = 82;
+
+/// IFD0 pointers to the EXIF and GPS sub-IFDs
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+/// IFD0 tags identifying the camera (DNG CameraSerialNumber)
+const PRIVATE_IFD0_TAGS: &[u16] = &[0xc62f];
+/// EXIF IFD tags identifying the photographer or their gear:
+/// CameraOwnerName, BodySerialNumber, LensSerialNumber, and MakerNote (which holds serials too)
+const PRIVATE_EXIF_TAGS: &[u16] = &[0xa430, 0xa431, 0xa435, 0x927c];
+
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+const JPEG_APP1: u8 = 0xe1;
+const JPEG_SOS: u8 = 0xda;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// XMP packets (and their extensions) repeat GPS and owner data, so they go entirely
+const XMP_HEADERS: &[&[u8]] = &[
+    b"http://ns.adobe.com/xap/1.0/\0",
+    b"http://ns.adobe.com/xmp/extension/\0",
+];
+
+/// How exported files are written
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Rotate JPEGs upright per their EXIF orientation (and reset the tag), which re-encodes
+    /// them; when off, JPEGs keep their pixels and orientation tag as shot
+    pub auto_rotate: bool,
+    /// Remove GPS coordinates, serial numbers and owner name from exported JPEGs
+    pub strip_private_metadata: bool,
+    /// Downscale JPEGs whose long edge is larger than this (e.g. client proofs);
+    /// other formats are copied at full size. None keeps every file's size
+    pub max_size: Option<u32>,
+    /// Recreate the subfolders files are in (relative to the session root) under the
+    /// destination instead of putting every file in one folder
+    pub keep_folders: bool,
+    /// Write an XMP sidecar with rating, color label and keywords next to each exported RAW
+    /// file, so the editor opens keepers rated
+    pub xmp_sidecar: Option<XmpSidecar>,
+    /// Write every still image, RAW files included, as a small sRGB JPEG without metadata
+    /// other than the session's corrections (web galleries, email); the other options above
+    /// don't apply to them then
+    pub small_jpeg: Option<SmallJpeg>,
+    /// Stamp a logo or text on exported JPEGs (and small JPEGs); they are turned upright
+    /// first so the mark sits where it should, whatever `auto_rotate` says
+    pub watermark: Option<Watermark>,
+}
+
+impl ExportOptions {
+    /// Whether exported JPEGs (or all stills) may be re-encoded rather than copied
+    /// A move must not replace an original with a lossy copy, so it rejects these options
+    pub fn re_encodes(&self) -> bool {
+        self.auto_rotate
+            || self.max_size.is_some()
+            || self.small_jpeg.is_some()
+            || self.watermark.is_some()
+    }
+}
+
+/// Size and quality of the JPEGs the small JPEG mode writes
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SmallJpeg {
+    /// Longest side in pixels; smaller images keep their size
+    pub long_edge: u32,
+    /// JPEG quality (1-100)
+    pub quality: u8,
+}
+
+impl Default for SmallJpeg {
+    fn default() -> Self {
+        Self {
+            long_edge: SMALL_JPEG_LONG_EDGE,
+            quality: SMALL_JPEG_QUALITY,
+        }
+    }
+}
+
+impl SmallJpeg {
+    pub fn validate(&self) -> Result<()> {
+        if self.long_edge == 0 || !(1..=100).contains(&self.quality) {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Small JPEGs need a size above 0 and a quality of 1 to 100: {:?}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of an export run
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExportResult {
+    /// Files in the exported session(s)
+    pub total: usize,
+    pub copied: usize,
+    /// Files the export's filter left out
+    pub skipped: usize,
+    /// Files left out because an earlier export wrote them and they haven't changed since
+    #[serde(default)]
+    pub unchanged: usize,
+    pub failed: usize,
+    /// Export was cancelled before all files were processed
+    pub cancelled: bool,
+}
+
+/// Subfolder of the destination a file with the session key `key` goes to with
+/// `keep_folders`; only plain folder names are kept, so it never leads outside it
+pub fn relative_folder(key: &str) -> PathBuf {
+    Path::new(key)
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Name the export of `name` is written under; the small JPEG mode turns stills into .jpg
+/// files and copies videos as they are
+pub fn output_name(name: &str, options: &ExportOptions) -> String {
+    if options.small_jpeg.is_some() && !is_video(Path::new(name)) {
+        return Path::new(name)
+            .with_extension("jpg")
+            .to_string_lossy()
+            .to_string();
+    }
+    name.to_string()
+}
+
+fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| image_processor::is_video_format(&e.to_lowercase()))
+}
+
+pub fn is_jpeg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_lowercase().as_str(), "jpg" | "jpeg"))
+        .unwrap_or(false)
+}
+
+/// Write one exported file to `dst`
+/// Files that need no conversion are copied as they are (cloned on copy-on-write volumes)
+pub fn export_file(src: &Path, dst: &Path, options: &ExportOptions) -> Result<()> {
+    if let Some(small_jpeg) = &options.small_jpeg {
+        if !is_video(src) {
+            return write_small_jpeg(src, dst, small_jpeg, options.watermark.as_ref());
+        }
+    }
+    if is_jpeg(src) {
+        if options.re_encodes() && write_converted_jpeg(src, dst, options)? {
+            return Ok(());
+        }
+        if options.strip_private_metadata {
+            // Metadata lives in its own segments, so this needs no re-encoding
+            let data = std::fs::read(src).map_err(|e| GlimpseError::io_at(src, e))?;
+            let stripped = strip_jpeg_metadata(&data).ok_or_else(|| {
+                GlimpseError::ExifError(format!("Cannot strip metadata of {}", src.display()))
+            })?;
+            std::fs::write(dst, stripped).map_err(|e| GlimpseError::io_at(dst, e))?;
+            return Ok(());
+        }
+    }
+    crate::reflink::clone_or_copy(src, dst)?;
+    Ok(())
+}
+
+/// Copy of a JPEG with private EXIF fields blanked and XMP packets dropped
+/// Returns None if the file isn't a well-formed JPEG
+fn strip_jpeg_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&JPEG_SOI) {
+        return None;
+    }
+
+    let mut out = JPEG_SOI.to_vec();
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        if marker == 0xff {
+            // Fill byte
+            pos += 1;
+            continue;
+        }
+        if marker == JPEG_SOS {
+            // Entropy-coded image data follows; copy the rest verbatim
+            out.extend_from_slice(&data[pos..]);
+            return Some(out);
+        }
+
+        // The length counts its own two bytes, so anything shorter is corrupt
+        let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        if len < 2 {
+            return None;
+        }
+        let segment = data.get(pos..pos + 2 + len)?;
+        let payload = &segment[4..];
+
+        if marker == JPEG_APP1 && payload.starts_with(EXIF_HEADER) {
+            let mut segment = segment.to_vec();
+            strip_private_exif(&mut segment[4 + EXIF_HEADER.len()..]);
+            out.extend_from_slice(&segment);
+        } else if marker == JPEG_APP1 && XMP_HEADERS.iter().any(|h| payload.starts_with(h)) {
+            // Dropped
+        } else {
+            out.extend_from_slice(segment);
+        }
+        pos += 2 + len;
+    }
+}
+
+/// Blank GPS data, serial numbers and owner name in an EXIF chunk (TIFF structure)
+/// Values are zeroed in place so every offset in the chunk stays valid
+fn strip_private_exif(exif: &mut [u8]) {
+    let ranges = private_exif_ranges(exif).unwrap_or_default();
+    for range in ranges {
+        let end = range.end.min(exif.len());
+        if range.start < end {
+            exif[range.start..end].fill(0);
+        }
+    }
+}
+
+fn private_exif_ranges(exif: &[u8]) -> Option<Vec<std::ops::Range<usize>>> {
+    let tiff = Tiff::from_header(exif)?;
+    let value_range = |pos: usize, size: usize| pos..pos.saturating_add(size);
+    let mut ranges = Vec::new();
+
+    for entry in tiff.entries(tiff.u32(4)? as usize, 0)? {
+        if PRIVATE_IFD0_TAGS.contains(&entry.tag) {
+            ranges.push(value_range(entry.value_pos, entry.size()));
+        }
+        if entry.typ != LONG {
+            continue;
+        }
+        let Some(sub_ifd) = tiff.u32(entry.value_pos).map(|p| p as usize) else {
+            continue;
+        };
+
+        if entry.tag == TAG_EXIF_IFD {
+            for sub in tiff.entries(sub_ifd, 0).unwrap_or_default() {
+                if PRIVATE_EXIF_TAGS.contains(&sub.tag) {
+                    ranges.push(value_range(sub.value_pos, sub.size()));
+                }
+            }
+        } else if entry.tag == TAG_GPS_IFD {
+            // Blank every GPS value, then the directory itself so it reads as empty
+            let gps = tiff.entries(sub_ifd, 0).unwrap_or_default();
+            for sub in &gps {
+                ranges.push(value_range(sub.value_pos, sub.size()));
+            }
+            ranges.push(value_range(sub_ifd, 2 + gps.len() * 12));
+        }
+    }
+    Some(ranges)
+}
+
+/// Re-encode a JPEG upright (with `auto_rotate`), downscaled to `max_size` and/or
+/// watermarked, keeping its EXIF (orientation reset when rotated) and ICC profile
+/// Returns false without writing anything if the image is already upright and small enough
+/// and gets no watermark
+fn write_converted_jpeg(src: &Path, dst: &Path, options: &ExportOptions) -> Result<bool> {
+    let mut decoder = ImageReader::open(src)
+        .map_err(|e| GlimpseError::io_at(src, e))?
+        .with_guessed_format()
+        .map_err(|e| GlimpseError::io_at(src, e))?
+        .into_decoder()?;
+
+    let (width, height) = decoder.dimensions();
+    let resize = options.max_size.filter(|&max| width.max(height) > max);
+    let mut exif = decoder.exif_metadata()?;
+    let orientation = match exif.as_mut() {
+        Some(exif) if options.auto_rotate || options.watermark.is_some() => {
+            match Orientation::remove_from_exif_chunk(exif) {
+                None | Some(Orientation::NoTransforms) => None,
+                orientation => orientation,
+            }
+        }
+        _ => None,
+    };
+    if orientation.is_none() && resize.is_none() && options.watermark.is_none() {
+        return Ok(false);
+    }
+    if options.strip_private_metadata {
+        if let Some(exif) = exif.as_mut() {
+            strip_private_exif(exif);
+        }
+    }
+    let icc = decoder.icc_profile()?;
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    if let Some(orientation) = orientation {
+        img.apply_orientation(orientation);
+    }
+    if let Some(max) = resize {
+        img = img.resize(max, max, FilterType::Lanczos3);
+    }
+    if let Some(watermark) = &options.watermark {
+        img = stamp(img, watermark)?;
+    }
+
+    let file = File::create(dst).map_err(|e| GlimpseError::io_at(dst, e))?;
+    let mut writer = BufWriter::new(file);
+    let mut encoder = JpegEncoder::new_with_quality(&mut writer, EXPORT_JPEG_QUALITY);
+    if let Some(exif) = exif {
+        encoder
+            .set_exif_metadata(exif)
+            .map_err(ImageError::Unsupported)?;
+    }
+    if let Some(icc) = icc {
+        encoder
+            .set_icc_profile(icc)
+            .map_err(ImageError::Unsupported)?;
+    }
+    img.write_with_encoder(encoder)?;
+    Ok(true)
+}
+
+/// Decode any still image (RAW through the configured decoder), convert it to sRGB and write
+/// it upright as a JPEG no larger than `settings.long_edge`
+/// Nothing of the original's metadata is carried over; viewers take untagged JPEGs as sRGB
+fn write_small_jpeg(
+    src: &Path,
+    dst: &Path,
+    settings: &SmallJpeg,
+    watermark: Option<&Watermark>,
+) -> Result<()> {
+    let mut img = image_processor::load_source_image(src, settings.long_edge)?;
+    // RAW files are developed upright; other stills only record their orientation
+    let is_raw = src
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| image_processor::is_raw_format(&e.to_lowercase()));
+    if !is_raw {
+        let orientation = image_processor::extract_exif(src)
+            .ok()
+            .and_then(|exif| exif.orientation)
+            .and_then(|value| Orientation::from_exif(value as u8));
+        if let Some(orientation) = orientation {
+            img.apply_orientation(orientation);
+        }
+    }
+    if img.width().max(img.height()) > settings.long_edge {
+        img = img.resize(settings.long_edge, settings.long_edge, FilterType::Lanczos3);
+    }
+
+    if let Some(watermark) = watermark {
+        img = stamp(img, watermark)?;
+    }
+
+    let file = File::create(dst).map_err(|e| GlimpseError::io_at(dst, e))?;
+    let mut writer = BufWriter::new(file);
+    let encoder = JpegEncoder::new_with_quality(&mut writer, settings.quality);
+    // JPEG has no alpha channel
+    DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?;
+    Ok(())
+}
+
+/// `img` with `watermark` stamped on it, as 8-bit RGB
+fn stamp(img: DynamicImage, watermark: &Watermark) -> Result<DynamicImage> {
+    let mut rgb = img.into_rgb8();
+    watermark.apply(&mut rgb)?;
+    Ok(DynamicImage::ImageRgb8(rgb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Little-endian EXIF chunk holding only an orientation tag
+    fn exif_with_orientation(orientation: u16) -> Vec<u8> {
+        let mut exif = b"II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        exif.extend_from_slice(&1u16.to_le_bytes());
+        exif.extend_from_slice(&0x0112u16.to_le_bytes());
+        exif.extend_from_slice(&3u16.to_le_bytes());
+        exif.extend_from_slice(&1u32.to_le_bytes());
+        exif.extend_from_slice(&(orientation as u32).to_le_bytes());
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif
+    }
+
+    fn write_jpeg(path: &Path, width: u32, height: u32, orientation: u16) {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        let mut file = File::create(path).unwrap();
+        let mut encoder = JpegEncoder::new(&mut file);
+        encoder
+            .set_exif_metadata(exif_with_orientation(orientation))
+            .unwrap();
+        img.write_with_encoder(encoder).unwrap();
+    }
+
+    fn read_back(path: &Path) -> (u32, u32, Orientation) {
+        let mut decoder = ImageReader::open(path)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        let orientation = decoder.orientation().unwrap();
+        let (width, height) = decoder.dimensions();
+        (width, height, orientation)
+    }
+
+    #[test]
+    fn test_rotated_jpeg_is_exported_upright() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("portrait.jpg");
+        let dst = dir.path().join("exported.jpg");
+        write_jpeg(&src, 32, 16, 6);
+
+        let options = ExportOptions {
+            auto_rotate: true,
+            ..Default::default()
+        };
+        export_file(&src, &dst, &options).unwrap();
+        assert_eq!(read_back(&dst), (16, 32, Orientation::NoTransforms));
+    }
+
+    #[test]
+    fn test_resized_export() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("portrait.jpg");
+        let dst = dir.path().join("exported.jpg");
+        write_jpeg(&src, 64, 32, 6);
+
+        let options = ExportOptions {
+            auto_rotate: true,
+            max_size: Some(16),
+            ..Default::default()
+        };
+        export_file(&src, &dst, &options).unwrap();
+        assert_eq!(read_back(&dst), (8, 16, Orientation::NoTransforms));
+
+        // Without rotation the orientation tag is kept for the viewer
+        let options = ExportOptions {
+            auto_rotate: false,
+            max_size: Some(16),
+            ..Default::default()
+        };
+        export_file(&src, &dst, &options).unwrap();
+        assert_eq!(read_back(&dst), (16, 8, Orientation::Rotate90));
+    }
+
+    #[test]
+    fn test_small_jpeg_export() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("portrait.png");
+        let dst = dir.path().join("portrait.jpg");
+        image::RgbaImage::new(300, 100).save(&src).unwrap();
+
+        let options = ExportOptions {
+            small_jpeg: Some(SmallJpeg {
+                long_edge: 60,
+                quality: 80,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            output_name("card1/portrait.png", &options),
+            "card1/portrait.jpg"
+        );
+        assert_eq!(output_name("clip.MOV", &options), "clip.MOV");
+        assert_eq!(
+            output_name("portrait.png", &ExportOptions::default()),
+            "portrait.png"
+        );
+
+        export_file(&src, &dst, &options).unwrap();
+        let (width, height, _) = read_back(&dst);
+        assert_eq!((width, height), (60, 20));
+        let exported = std::fs::read(&dst).unwrap();
+        assert!(!exported.windows(4).any(|w| w == b"Exif"));
+
+        // Smaller images aren't enlarged
+        let wide = ExportOptions {
+            small_jpeg: Some(SmallJpeg::default()),
+            ..Default::default()
+        };
+        export_file(&src, &dst, &wide).unwrap();
+        assert_eq!(read_back(&dst).0, 300);
+        assert!(SmallJpeg {
+            quality: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_watermarked_export() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("portrait.jpg");
+        let dst = dir.path().join("exported.jpg");
+        let logo = dir.path().join("logo.png");
+        write_jpeg(&src, 64, 32, 6);
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 255, 255, 255]))
+            .save(&logo)
+            .unwrap();
+
+        // Turned upright for the mark even without auto-rotation
+        let options = ExportOptions {
+            auto_rotate: false,
+            watermark: Some(Watermark {
+                mark: crate::watermark::WatermarkMark::Image {
+                    path: logo.to_string_lossy().to_string(),
+                },
+                position: crate::watermark::WatermarkPosition::Center,
+                opacity: 1.0,
+                scale: 0.5,
+            }),
+            ..Default::default()
+        };
+        export_file(&src, &dst, &options).unwrap();
+        assert_eq!(read_back(&dst), (32, 64, Orientation::NoTransforms));
+        let exported = image::open(&dst).unwrap().to_rgb8();
+        assert!(exported.get_pixel(16, 32).0[0] > 200);
+        assert!(exported.get_pixel(2, 2).0[0] < 50);
+    }
+
+    #[test]
+    fn test_relative_folder() {
+        assert_eq!(relative_folder("DSC_0001.NEF"), PathBuf::new());
+        assert_eq!(
+            relative_folder("card1/DCIM/DSC_0001.NEF"),
+            PathBuf::from("card1").join("DCIM")
+        );
+        // Keys of folders sharing no root are absolute paths
+        assert_eq!(
+            relative_folder("/Volumes/A/../shoot/a.jpg"),
+            PathBuf::from("Volumes").join("A").join("shoot")
+        );
+    }
+
+    #[test]
+    fn test_straight_copy_keeps_bytes() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("portrait.jpg");
+        let dst = dir.path().join("exported.jpg");
+        write_jpeg(&src, 32, 16, 6);
+
+        // By default rotated JPEGs are copied as they are
+        export_file(&src, &dst, &ExportOptions::default()).unwrap();
+        assert_eq!(std::fs::read(&src).unwrap(), std::fs::read(&dst).unwrap());
+
+        // Upright JPEGs are never re-encoded
+        let upright = dir.path().join("upright.jpg");
+        let upright_dst = dir.path().join("upright_exported.jpg");
+        write_jpeg(&upright, 32, 16, 1);
+        let options = ExportOptions {
+            auto_rotate: true,
+            ..Default::default()
+        };
+        export_file(&upright, &upright_dst, &options).unwrap();
+        assert_eq!(
+            std::fs::read(&upright).unwrap(),
+            std::fs::read(&upright_dst).unwrap()
+        );
+    }
+
+    /// Little-endian EXIF chunk with a make, a body serial number and a GPS latitude
+    fn exif_with_private_data() -> Vec<u8> {
+        fn entry(buf: &mut Vec<u8>, tag: u16, typ: u16, count: u32, value: u32) {
+            buf.extend_from_slice(&tag.to_le_bytes());
+            buf.extend_from_slice(&typ.to_le_bytes());
+            buf.extend_from_slice(&count.to_le_bytes());
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut exif = b"II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        // IFD0 at 8: Make, EXIF pointer, GPS pointer; data from 50
+        exif.extend_from_slice(&3u16.to_le_bytes());
+        entry(&mut exif, 0x010f, 2, 6, 50);
+        entry(&mut exif, TAG_EXIF_IFD, LONG, 1, 56);
+        entry(&mut exif, TAG_GPS_IFD, LONG, 1, 84);
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif.extend_from_slice(b"Canon\0");
+        // EXIF IFD at 56: BodySerialNumber; data from 74
+        exif.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut exif, 0xa431, 2, 10, 74);
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif.extend_from_slice(b"987654321\0");
+        // GPS IFD at 84: GPSLatitudeRef, GPSLatitude; data from 114
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut exif, 0x0001, 2, 2, u32::from_le_bytes(*b"N\0\0\0"));
+        entry(&mut exif, 0x0002, 5, 3, 114);
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        for (num, den) in [(35u32, 1u32), (41, 1), (1234, 100)] {
+            exif.extend_from_slice(&num.to_le_bytes());
+            exif.extend_from_slice(&den.to_le_bytes());
+        }
+        exif
+    }
+
+    #[test]
+    fn test_strip_private_metadata() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("location.jpg");
+        let dst = dir.path().join("exported.jpg");
+
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(16, 8));
+        let mut file = File::create(&src).unwrap();
+        let mut encoder = JpegEncoder::new(&mut file);
+        encoder.set_exif_metadata(exif_with_private_data()).unwrap();
+        img.write_with_encoder(encoder).unwrap();
+        drop(file);
+
+        let options = ExportOptions {
+            strip_private_metadata: true,
+            ..Default::default()
+        };
+        export_file(&src, &dst, &options).unwrap();
+
+        let exported = std::fs::read(&dst).unwrap();
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(&exported))
+            .unwrap();
+        let make = exif.get_field(exif::Tag::Make, exif::In::PRIMARY).unwrap();
+        assert_eq!(make.display_value().to_string(), "\"Canon\"");
+        assert!(exif
+            .get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY)
+            .is_none());
+        assert!(!exported.windows(9).any(|w| w == b"987654321"));
+
+        // Image data is untouched
+        assert_eq!(read_back(&dst).0, 16);
+        let original = std::fs::read(&src).unwrap();
+        assert_eq!(original.len(), exported.len());
+    }
+
+    #[test]
+    fn test_strip_rejects_non_jpeg_data() {
+        assert!(strip_jpeg_metadata(b"not a jpeg").is_none());
+        assert!(strip_jpeg_metadata(&[0xff, 0xd8, 0xff, 0xe1, 0x00]).is_none());
+        // Segment lengths below 2 don't even cover the length field
+        assert!(strip_jpeg_metadata(&[0xff, 0xd8, 0xff, 0xe1, 0x00, 0x00]).is_none());
+        assert!(strip_jpeg_metadata(&[0xff, 0xd8, 0xff, 0xe1, 0x00, 0x01, 0xff]).is_none());
+    }
+}
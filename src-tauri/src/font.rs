@@ -0,0 +1,733 @@
+// Minimal TrueType reader and rasterizer for watermark text
+// Reads the glyph outlines of .ttf/.ttc fonts and fills them with antialiasing; hinting,
+// kerning and CFF outlines (most .otf files) aren't supported
+
+use crate::error::{GlimpseError, Result};
+use image::GrayImage;
+use std::path::{Path, PathBuf};
+
+/// Fonts tried when the watermark doesn't name one
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+];
+
+/// Nesting composite glyphs may have; deeper ones are treated as broken
+const MAX_COMPONENT_DEPTH: usize = 8;
+
+/// A point of a glyph outline in font units; off-curve points are quadratic controls
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+    on_curve: bool,
+}
+
+/// Big-endian reads from font data
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn i16_at(data: &[u8], pos: usize) -> Option<i16> {
+    u16_at(data, pos).map(|v| v as i16)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// A TrueType font (the first face of a collection)
+pub struct Font {
+    data: Vec<u8>,
+    glyf: usize,
+    loca: usize,
+    long_loca: bool,
+    hmtx: usize,
+    num_hmetrics: u16,
+    num_glyphs: u16,
+    /// Position of the cmap subtable and its format (4 or 12)
+    cmap: (usize, u16),
+    units_per_em: f32,
+    ascent: f32,
+    descent: f32,
+}
+
+impl Font {
+    /// Load the font at `path`, or the first system font found when None
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => SYSTEM_FONTS
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.is_file())
+                .ok_or_else(|| {
+                    GlimpseError::Font("No system font found; choose a font file".into())
+                })?,
+        };
+        let data = std::fs::read(&path).map_err(|e| GlimpseError::io_at(&path, e))?;
+        Self::from_bytes(data).ok_or_else(|| {
+            GlimpseError::Font(format!(
+                "{} is not a TrueType font with glyph outlines",
+                path.display()
+            ))
+        })
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let offset = if data.get(0..4)? == b"ttcf" {
+            u32_at(&data, 12)? as usize
+        } else {
+            0
+        };
+        let num_tables = u16_at(&data, offset + 4)? as usize;
+        let table = |tag: &[u8]| {
+            (0..num_tables).find_map(|i| {
+                let record = offset + 12 + i * 16;
+                (data.get(record..record + 4)? == tag)
+                    .then(|| u32_at(&data, record + 8).map(|pos| pos as usize))
+                    .flatten()
+            })
+        };
+        let (head, maxp, hhea, hmtx) = (
+            table(b"head")?,
+            table(b"maxp")?,
+            table(b"hhea")?,
+            table(b"hmtx")?,
+        );
+        let (loca, glyf, cmap) = (table(b"loca")?, table(b"glyf")?, table(b"cmap")?);
+
+        let units_per_em = u16_at(&data, head + 18)?;
+        if units_per_em == 0 {
+            return None;
+        }
+        let cmap = Self::find_cmap(&data, cmap)?;
+        Some(Self {
+            long_loca: i16_at(&data, head + 50)? == 1,
+            num_glyphs: u16_at(&data, maxp + 4)?,
+            ascent: f32::from(i16_at(&data, hhea + 4)?),
+            descent: f32::from(i16_at(&data, hhea + 6)?),
+            num_hmetrics: u16_at(&data, hhea + 34)?,
+            units_per_em: f32::from(units_per_em),
+            glyf,
+            loca,
+            hmtx,
+            cmap,
+            data,
+        })
+    }
+
+    /// Unicode subtable of the cmap at `cmap`: a full-range one (format 12) when there is
+    /// one, the BMP one (format 4) otherwise
+    fn find_cmap(data: &[u8], cmap: usize) -> Option<(usize, u16)> {
+        let count = u16_at(data, cmap + 2)? as usize;
+        let mut best: Option<(usize, u16)> = None;
+        for i in 0..count {
+            let record = cmap + 4 + i * 8;
+            let platform = u16_at(data, record)?;
+            let encoding = u16_at(data, record + 2)?;
+            let subtable = cmap + u32_at(data, record + 4)? as usize;
+            let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+            match u16_at(data, subtable)? {
+                12 if unicode => return Some((subtable, 12)),
+                4 if unicode && best.is_none() => best = Some((subtable, 4)),
+                _ => {}
+            }
+        }
+        best
+    }
+
+    /// Glyph of `c`; 0 (the font's missing-glyph box) when it has none
+    fn glyph_index(&self, c: char) -> u16 {
+        let c = c as u32;
+        let data = &self.data;
+        let (table, format) = self.cmap;
+        let found = if format == 12 {
+            (|| {
+                let groups = u32_at(data, table + 12)? as usize;
+                (0..groups).find_map(|i| {
+                    let group = table + 16 + i * 12;
+                    let start = u32_at(data, group)?;
+                    let end = u32_at(data, group + 4)?;
+                    (start..=end)
+                        .contains(&c)
+                        .then(|| u32_at(data, group + 8).map(|glyph| glyph + c - start))
+                        .flatten()
+                })
+            })()
+        } else {
+            (|| {
+                if c > 0xffff {
+                    return None;
+                }
+                let seg_x2 = u16_at(data, table + 6)? as usize;
+                let ends = table + 14;
+                let starts = ends + seg_x2 + 2;
+                let deltas = starts + seg_x2;
+                let range_offsets = deltas + seg_x2;
+                (0..seg_x2 / 2).find_map(|i| {
+                    let end = u32::from(u16_at(data, ends + i * 2)?);
+                    let start = u32::from(u16_at(data, starts + i * 2)?);
+                    if c < start || c > end {
+                        return None;
+                    }
+                    let delta = u32::from(u16_at(data, deltas + i * 2)?);
+                    let range_offset = u16_at(data, range_offsets + i * 2)? as usize;
+                    if range_offset == 0 {
+                        return Some((c + delta) & 0xffff);
+                    }
+                    let pos = range_offsets + i * 2 + range_offset + (c - start) as usize * 2;
+                    match u16_at(data, pos)? {
+                        0 => Some(0),
+                        glyph => Some((u32::from(glyph) + delta) & 0xffff),
+                    }
+                })
+            })()
+        };
+        found
+            .filter(|&glyph| glyph < u32::from(self.num_glyphs))
+            .unwrap_or(0) as u16
+    }
+
+    /// Horizontal advance of `glyph` in font units
+    fn advance(&self, glyph: u16) -> f32 {
+        let index = glyph.min(self.num_hmetrics.saturating_sub(1)) as usize;
+        f32::from(u16_at(&self.data, self.hmtx + index * 4).unwrap_or(0))
+    }
+
+    /// Width of `text` in ems
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.chars()
+            .map(|c| self.advance(self.glyph_index(c)))
+            .sum::<f32>()
+            / self.units_per_em
+    }
+
+    /// Range of `glyph`'s data in the glyf table; None for glyphs without an outline
+    fn glyph_range(&self, glyph: u16) -> Option<std::ops::Range<usize>> {
+        let index = glyph as usize;
+        let (start, end) = if self.long_loca {
+            (
+                u32_at(&self.data, self.loca + index * 4)? as usize,
+                u32_at(&self.data, self.loca + index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                u16_at(&self.data, self.loca + index * 2)? as usize * 2,
+                u16_at(&self.data, self.loca + index * 2 + 2)? as usize * 2,
+            )
+        };
+        (start < end).then(|| self.glyf + start..self.glyf + end)
+    }
+
+    /// Contours of `glyph` in font units, components of composite glyphs placed
+    fn outline(&self, glyph: u16, depth: usize) -> Option<Vec<Vec<Point>>> {
+        let Some(range) = self.glyph_range(glyph) else {
+            return Some(Vec::new());
+        };
+        let data = self.data.get(range)?;
+        let contours = i16_at(data, 0)?;
+        if contours >= 0 {
+            simple_outline(data, contours as usize)
+        } else if depth < MAX_COMPONENT_DEPTH {
+            self.composite_outline(data, depth)
+        } else {
+            None
+        }
+    }
+
+    fn composite_outline(&self, data: &[u8], depth: usize) -> Option<Vec<Vec<Point>>> {
+        const ARGS_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_XY: u16 = 0x0002;
+        const HAVE_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const HAVE_XY_SCALE: u16 = 0x0040;
+        const HAVE_2X2: u16 = 0x0080;
+        let f2dot14 = |pos: usize| i16_at(data, pos).map(|v| f32::from(v) / 16384.0);
+
+        let mut contours = Vec::new();
+        let mut pos = 10;
+        loop {
+            let flags = u16_at(data, pos)?;
+            let component = u16_at(data, pos + 2)?;
+            pos += 4;
+            let (dx, dy) = if flags & ARGS_ARE_WORDS != 0 {
+                pos += 4;
+                (i16_at(data, pos - 4)?, i16_at(data, pos - 2)?)
+            } else {
+                pos += 2;
+                (
+                    i16::from(*data.get(pos - 2)? as i8),
+                    i16::from(*data.get(pos - 1)? as i8),
+                )
+            };
+            // Components placed by matching points are rare; they're left unmoved
+            let (dx, dy) = if flags & ARGS_ARE_XY != 0 {
+                (f32::from(dx), f32::from(dy))
+            } else {
+                (0.0, 0.0)
+            };
+            let (a, b, c, d) = if flags & HAVE_SCALE != 0 {
+                pos += 2;
+                let scale = f2dot14(pos - 2)?;
+                (scale, 0.0, 0.0, scale)
+            } else if flags & HAVE_XY_SCALE != 0 {
+                pos += 4;
+                (f2dot14(pos - 4)?, 0.0, 0.0, f2dot14(pos - 2)?)
+            } else if flags & HAVE_2X2 != 0 {
+                pos += 8;
+                (
+                    f2dot14(pos - 8)?,
+                    f2dot14(pos - 6)?,
+                    f2dot14(pos - 4)?,
+                    f2dot14(pos - 2)?,
+                )
+            } else {
+                (1.0, 0.0, 0.0, 1.0)
+            };
+
+            for contour in self.outline(component, depth + 1)? {
+                contours.push(
+                    contour
+                        .into_iter()
+                        .map(|p| Point {
+                            x: a * p.x + c * p.y + dx,
+                            y: b * p.x + d * p.y + dy,
+                            on_curve: p.on_curve,
+                        })
+                        .collect(),
+                );
+            }
+            if flags & MORE_COMPONENTS == 0 {
+                return Some(contours);
+            }
+        }
+    }
+
+    /// Coverage mask of `text` on one line, `px_per_em` pixels to the em
+    pub fn render(&self, text: &str, px_per_em: f32) -> GrayImage {
+        let scale = px_per_em / self.units_per_em;
+        // One pixel of padding keeps antialiased edges inside the mask
+        let width = (self.text_width(text) * px_per_em).ceil() as usize + 2;
+        let height = ((self.ascent - self.descent) * scale).ceil() as usize + 2;
+        let mut raster = Raster::new(width, height);
+
+        let mut pen = 1.0;
+        for c in text.chars() {
+            let glyph = self.glyph_index(c);
+            let contours = self.outline(glyph, 0).unwrap_or_default();
+            // Font units have y up; the mask has it down from the ascender
+            let place = |p: Point| (pen + p.x * scale, 1.0 + (self.ascent - p.y) * scale);
+            for contour in &contours {
+                for (from, control, to) in segments(contour) {
+                    let (from, to) = (place(from), place(to));
+                    match control {
+                        Some(control) => raster.draw_quad(from, place(control), to),
+                        None => raster.draw_line(from, to),
+                    }
+                }
+            }
+            pen += self.advance(glyph) * scale;
+        }
+        raster.into_mask()
+    }
+}
+
+/// Points of a simple glyph: contour ends, instructions, then flags and coordinates
+fn simple_outline(data: &[u8], contours: usize) -> Option<Vec<Vec<Point>>> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const REPEAT: u8 = 0x08;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let ends: Vec<usize> = (0..contours)
+        .map(|i| u16_at(data, 10 + i * 2).map(usize::from))
+        .collect::<Option<_>>()?;
+    let count = ends.last().map_or(0, |&end| end + 1);
+    let instructions = u16_at(data, 10 + contours * 2)? as usize;
+    let mut pos = 12 + contours * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(count);
+    while flags.len() < count {
+        let flag = *data.get(pos)?;
+        pos += 1;
+        flags.push(flag);
+        if flag & REPEAT != 0 {
+            let repeat = *data.get(pos)?;
+            pos += 1;
+            flags.extend(std::iter::repeat_n(flag, repeat as usize));
+        }
+    }
+    flags.truncate(count);
+
+    let mut read_coordinates = |short: u8, same_or_positive: u8| -> Option<Vec<f32>> {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = i32::from(*data.get(pos)?);
+                pos += 1;
+                value += if flag & same_or_positive != 0 {
+                    delta
+                } else {
+                    -delta
+                };
+            } else if flag & same_or_positive == 0 {
+                value += i32::from(i16_at(data, pos)?);
+                pos += 2;
+            }
+            values.push(value as f32);
+        }
+        Some(values)
+    };
+    let xs = read_coordinates(X_SHORT, X_SAME_OR_POSITIVE)?;
+    let ys = read_coordinates(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+    let mut outline = Vec::with_capacity(contours);
+    let mut start = 0;
+    for end in ends {
+        if end < start || end >= count {
+            return None;
+        }
+        outline.push(
+            (start..=end)
+                .map(|i| Point {
+                    x: xs[i],
+                    y: ys[i],
+                    on_curve: flags[i] & ON_CURVE != 0,
+                })
+                .collect(),
+        );
+        start = end + 1;
+    }
+    Some(outline)
+}
+
+/// Lines and quadratic curves (from, control, to) of a closed contour
+/// Two off-curve points in a row have an implied on-curve point halfway between them
+fn segments(contour: &[Point]) -> Vec<(Point, Option<Point>, Point)> {
+    let mid = |a: Point, b: Point| Point {
+        x: (a.x + b.x) / 2.0,
+        y: (a.y + b.y) / 2.0,
+        on_curve: true,
+    };
+    let (Some(&first), Some(&last)) = (contour.first(), contour.last()) else {
+        return Vec::new();
+    };
+    let start = if first.on_curve {
+        first
+    } else if last.on_curve {
+        last
+    } else {
+        mid(last, first)
+    };
+
+    let mut segments = Vec::with_capacity(contour.len());
+    let mut current = start;
+    let mut control: Option<Point> = None;
+    for &point in contour.iter().chain(std::iter::once(&start)) {
+        match (point.on_curve, control) {
+            (true, control_point) => {
+                segments.push((current, control_point, point));
+                current = point;
+                control = None;
+            }
+            (false, Some(previous)) => {
+                let implied = mid(previous, point);
+                segments.push((current, Some(previous), implied));
+                current = implied;
+                control = Some(point);
+            }
+            (false, None) => control = Some(point),
+        }
+    }
+    // Drop the zero-length segment the start point makes with itself
+    segments.retain(|(from, control, to)| control.is_some() || from != to);
+    segments
+}
+
+/// Signed-area accumulation rasterizer: each edge adds the area it covers to the right of
+/// it per pixel, and a running sum over each row gives the coverage with nonzero winding
+struct Raster {
+    width: usize,
+    height: usize,
+    area: Vec<f32>,
+}
+
+impl Raster {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            // Edges at the right border spill one cell into the next row's start
+            area: vec![0.0; width * height + 4],
+        }
+    }
+
+    fn draw_quad(&mut self, from: (f32, f32), control: (f32, f32), to: (f32, f32)) {
+        let deviation = ((from.0 - 2.0 * control.0 + to.0).powi(2)
+            + (from.1 - 2.0 * control.1 + to.1).powi(2))
+        .sqrt();
+        let steps = (1.0 + (deviation * 3.0).sqrt()).floor().min(32.0) as usize;
+        let mut previous = from;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let u = 1.0 - t;
+            let point = (
+                u * u * from.0 + 2.0 * u * t * control.0 + t * t * to.0,
+                u * u * from.1 + 2.0 * u * t * control.1 + t * t * to.1,
+            );
+            self.draw_line(previous, point);
+            previous = point;
+        }
+    }
+
+    fn draw_line(&mut self, p0: (f32, f32), p1: (f32, f32)) {
+        if (p0.1 - p1.1).abs() <= f32::EPSILON {
+            return;
+        }
+        let (dir, p0, p1) = if p0.1 < p1.1 {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+        let clamp_x = |x: f32| x.clamp(0.0, (self.width - 1) as f32);
+        let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+        let mut x = p0.0;
+        if p0.1 < 0.0 {
+            x -= p0.1 * dxdy;
+        }
+        let first_row = p0.1.max(0.0) as usize;
+        let last_row = (p1.1.ceil().max(0.0) as usize).min(self.height);
+        for y in first_row..last_row {
+            let row = y * self.width;
+            let dy = ((y + 1) as f32).min(p1.1) - (y as f32).max(p0.1);
+            let x_next = x + dxdy * dy;
+            let d = dy * dir;
+            let (x0, x1) = if x < x_next {
+                (clamp_x(x), clamp_x(x_next))
+            } else {
+                (clamp_x(x_next), clamp_x(x))
+            };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+            if x1i <= x0i + 1 {
+                // Within one pixel: split by where the edge crosses it on average
+                let xmf = 0.5 * (x0 + x1) - x0_floor;
+                self.area[row + x0i] += d - d * xmf;
+                self.area[row + x0i + 1] += d * xmf;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                self.area[row + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.area[row + x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.area[row + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.area[row + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.area[row + x1i - 1] += d * (1.0 - a2 - am);
+                }
+                self.area[row + x1i] += d * am;
+            }
+            x = x_next;
+        }
+    }
+
+    fn into_mask(self) -> GrayImage {
+        let mut sum = 0.0f32;
+        let pixels = self.area[..self.width * self.height]
+            .iter()
+            .map(|area| {
+                sum += area;
+                (sum.abs().min(1.0) * 255.0).round() as u8
+            })
+            .collect();
+        GrayImage::from_raw(self.width as u32, self.height as u32, pixels)
+            .expect("buffer matches the mask size")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn table(tag: &[u8; 4], data: Vec<u8>) -> ([u8; 4], Vec<u8>) {
+        (*tag, data)
+    }
+
+    /// TrueType font with a filled square for 'A' and 'B' (glyphs 1 and 2, B as a
+    /// composite of A) and a space; 1000 units to the em, ascent 800, descent -200
+    pub(crate) fn square_font() -> Vec<u8> {
+        let be16 = |v: i16| v.to_be_bytes();
+        let mut head = vec![0u8; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        head[50..52].copy_from_slice(&be16(0));
+        let mut maxp = vec![0u8; 6];
+        maxp[4..6].copy_from_slice(&4u16.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[4..6].copy_from_slice(&be16(800));
+        hhea[6..8].copy_from_slice(&be16(-200));
+        hhea[34..36].copy_from_slice(&4u16.to_be_bytes());
+        let mut hmtx = Vec::new();
+        for advance in [500u16, 1000, 1000, 250] {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+            hmtx.extend_from_slice(&0u16.to_be_bytes());
+        }
+
+        // Glyph 1: square from (100, 0) to (900, 800) with long coordinates
+        let mut square = Vec::new();
+        square.extend_from_slice(&be16(1));
+        for v in [100i16, 0, 900, 800] {
+            square.extend_from_slice(&be16(v));
+        }
+        square.extend_from_slice(&3u16.to_be_bytes());
+        square.extend_from_slice(&0u16.to_be_bytes());
+        square.extend_from_slice(&[0x01; 4]);
+        for dx in [100i16, 0, 800, 0] {
+            square.extend_from_slice(&be16(dx));
+        }
+        for dy in [0i16, 800, 0, -800] {
+            square.extend_from_slice(&be16(dy));
+        }
+        // Glyph 2: glyph 1 unmoved
+        let mut composite = Vec::new();
+        composite.extend_from_slice(&be16(-1));
+        for v in [100i16, 0, 900, 800] {
+            composite.extend_from_slice(&be16(v));
+        }
+        composite.extend_from_slice(&0x0002u16.to_be_bytes());
+        composite.extend_from_slice(&1u16.to_be_bytes());
+        composite.extend_from_slice(&[0, 0]);
+        let mut glyf = square.clone();
+        glyf.resize(glyf.len().next_multiple_of(2), 0);
+        let composite_start = glyf.len();
+        glyf.extend_from_slice(&composite);
+        glyf.resize(glyf.len().next_multiple_of(2), 0);
+        let mut loca = Vec::new();
+        for offset in [0, 0, composite_start, glyf.len(), glyf.len()] {
+            loca.extend_from_slice(&((offset / 2) as u16).to_be_bytes());
+        }
+
+        // Format 4: space -> 3, 'A'-'B' -> 1-2, and the closing 0xFFFF segment
+        let mut cmap = Vec::new();
+        cmap.extend_from_slice(&0u16.to_be_bytes());
+        cmap.extend_from_slice(&1u16.to_be_bytes());
+        cmap.extend_from_slice(&3u16.to_be_bytes());
+        cmap.extend_from_slice(&1u16.to_be_bytes());
+        cmap.extend_from_slice(&12u32.to_be_bytes());
+        let segments: [(u16, u16, i16); 3] =
+            [(32, 32, 3 - 32), (65, 66, 1 - 65), (0xffff, 0xffff, 1)];
+        let mut subtable = Vec::new();
+        subtable.extend_from_slice(&4u16.to_be_bytes());
+        subtable.extend_from_slice(&0u16.to_be_bytes());
+        subtable.extend_from_slice(&0u16.to_be_bytes());
+        subtable.extend_from_slice(&6u16.to_be_bytes());
+        subtable.extend_from_slice(&[0; 6]);
+        for (_, end, _) in segments {
+            subtable.extend_from_slice(&end.to_be_bytes());
+        }
+        subtable.extend_from_slice(&0u16.to_be_bytes());
+        for (start, _, _) in segments {
+            subtable.extend_from_slice(&start.to_be_bytes());
+        }
+        for (_, _, delta) in segments {
+            subtable.extend_from_slice(&be16(delta));
+        }
+        subtable.extend_from_slice(&[0; 6]);
+        let length = subtable.len() as u16;
+        subtable[2..4].copy_from_slice(&length.to_be_bytes());
+        cmap.extend_from_slice(&subtable);
+
+        let tables = [
+            table(b"cmap", cmap),
+            table(b"glyf", glyf),
+            table(b"head", head),
+            table(b"hhea", hhea),
+            table(b"hmtx", hmtx),
+            table(b"loca", loca),
+            table(b"maxp", maxp),
+        ];
+        let mut font = Vec::new();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        font.extend_from_slice(&[0; 6]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, data) in &tables {
+            font.extend_from_slice(tag);
+            font.extend_from_slice(&0u32.to_be_bytes());
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in &tables {
+            font.extend_from_slice(data);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+        font
+    }
+
+    #[test]
+    fn test_glyph_lookup() {
+        let font = Font::from_bytes(square_font()).unwrap();
+        assert_eq!(font.glyph_index('A'), 1);
+        assert_eq!(font.glyph_index('B'), 2);
+        assert_eq!(font.glyph_index(' '), 3);
+        assert_eq!(font.glyph_index('©'), 0);
+        assert_eq!(font.text_width("A B"), 2.25);
+        assert_eq!(font.outline(2, 0), font.outline(1, 0));
+        assert!(Font::from_bytes(b"not a font".to_vec()).is_none());
+    }
+
+    #[test]
+    fn test_render() {
+        let font = Font::from_bytes(square_font()).unwrap();
+        // 100 px to the em: the square covers x 10-90 and y 0-80 of each 100 px cell
+        let mask = font.render("AB", 100.0);
+        assert_eq!(mask.dimensions(), (202, 102));
+        let at = |x: u32, y: u32| mask.get_pixel(x, y).0[0];
+        assert_eq!(at(50, 40), 255);
+        assert_eq!(at(150, 40), 255);
+        assert_eq!(at(5, 40), 0);
+        assert_eq!(at(100, 40), 0);
+        assert_eq!(at(50, 90), 0);
+        // Edges at half a pixel are half covered
+        let half = font.render("A", 105.0);
+        let edge = half.get_pixel(11, 40).0[0];
+        assert!((100..=155).contains(&edge), "{}", edge);
+    }
+
+    #[test]
+    fn test_segments() {
+        let point = |x: f32, y: f32, on_curve: bool| Point { x, y, on_curve };
+        // Circle-like contour of only off-curve points
+        let contour = [
+            point(0.0, 1.0, false),
+            point(1.0, 0.0, false),
+            point(0.0, -1.0, false),
+            point(-1.0, 0.0, false),
+        ];
+        let segments = segments(&contour);
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|(_, control, _)| control.is_some()));
+        assert_eq!(segments[0].0, segments[3].2);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::image_processor::ExifInfo;
+use std::collections::HashMap;
+
+/// How many frames used one body / lens / focal length / ISO
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct UsageCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Equipment usage across a session, each list most used first
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct GearStats {
+    pub image_count: usize,
+    pub bodies: Vec<UsageCount>,
+    /// Serial numbers of the bodies (several when shooters share a model)
+    pub body_serials: Vec<UsageCount>,
+    pub lenses: Vec<UsageCount>,
+    pub focal_lengths: Vec<UsageCount>,
+    pub isos: Vec<UsageCount>,
+}
+
+/// Frames to keep by the gear they were taken with (e.g. only the second shooter's body)
+/// Fields left empty match any frame; the others must all match, ignoring case
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GearFilter {
+    /// Body as listed in `GearStats::bodies`, or its bare EXIF model
+    pub camera: Option<String>,
+    pub serial: Option<String>,
+    pub lens: Option<String>,
+}
+
+impl GearFilter {
+    fn wanted(value: &Option<String>) -> Option<&str> {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+    }
+
+    /// Whether the filter lets every frame through
+    pub fn is_empty(&self) -> bool {
+        [&self.camera, &self.serial, &self.lens]
+            .into_iter()
+            .all(|value| Self::wanted(value).is_none())
+    }
+
+    pub fn matches(&self, exif: &ExifInfo) -> bool {
+        let same = |wanted: &str, actual: Option<&str>| {
+            actual.is_some_and(|actual| actual.trim().eq_ignore_ascii_case(wanted))
+        };
+        Self::wanted(&self.camera).is_none_or(|camera| {
+            same(camera, body_name(exif).as_deref()) || same(camera, exif.camera_model.as_deref())
+        }) && Self::wanted(&self.serial)
+            .is_none_or(|serial| same(serial, exif.body_serial.as_deref()))
+            && Self::wanted(&self.lens).is_none_or(|lens| same(lens, exif.lens_model.as_deref()))
+    }
+}
+
+/// Camera body name, without repeating the maker when the model already includes it
+/// ("Canon" + "Canon EOS R5" -> "Canon EOS R5", "NIKON CORPORATION" + "NIKON Z 6" -> "NIKON Z 6")
+fn body_name(exif: &ExifInfo) -> Option<String> {
+    let model = exif
+        .camera_model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty());
+    let make = exif
+        .camera_make
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty());
+
+    match (make, model) {
+        (Some(make), Some(model)) => {
+            let brand = make.split_whitespace().next().unwrap_or(make);
+            if model.to_lowercase().starts_with(&brand.to_lowercase()) {
+                Some(model.to_string())
+            } else {
+                Some(format!("{} {}", make, model))
+            }
+        }
+        (None, Some(model)) => Some(model.to_string()),
+        (Some(make), None) => Some(make.to_string()),
+        (None, None) => None,
+    }
+}
+
+fn count(values: impl Iterator<Item = Option<String>>) -> Vec<UsageCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for value in values.flatten() {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    let mut counts: Vec<UsageCount> = counts
+        .into_iter()
+        .map(|(value, count)| UsageCount { value, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts
+}
+
+/// Aggregate EXIF data into usage counts; frames missing a field are left out of that list
+pub fn gear_stats(exifs: &[ExifInfo]) -> GearStats {
+    let field = |get: fn(&ExifInfo) -> &Option<String>| {
+        count(exifs.iter().map(|exif| {
+            get(exif)
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        }))
+    };
+
+    GearStats {
+        image_count: exifs.len(),
+        bodies: count(exifs.iter().map(body_name)),
+        body_serials: field(|exif| &exif.body_serial),
+        lenses: field(|exif| &exif.lens_model),
+        focal_lengths: field(|exif| &exif.focal_length),
+        isos: field(|exif| &exif.iso),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exif(make: &str, model: &str, lens: Option<&str>, iso: &str) -> ExifInfo {
+        ExifInfo {
+            camera_make: Some(make.to_string()),
+            camera_model: Some(model.to_string()),
+            lens_model: lens.map(str::to_string),
+            iso: Some(iso.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gear_stats() {
+        let exifs = vec![
+            exif(
+                "Canon",
+                "Canon EOS R5",
+                Some("RF24-70mm F2.8 L IS USM"),
+                "ISO 100",
+            ),
+            exif(
+                "Canon",
+                "Canon EOS R5",
+                Some("RF70-200mm F2.8 L IS USM"),
+                "ISO 400",
+            ),
+            exif(
+                "Canon",
+                "Canon EOS R5",
+                Some("RF24-70mm F2.8 L IS USM"),
+                "ISO 100",
+            ),
+            exif("NIKON CORPORATION", "NIKON Z 6", None, "ISO 100"),
+            ExifInfo::default(),
+        ];
+
+        let stats = gear_stats(&exifs);
+        assert_eq!(stats.image_count, 5);
+        assert_eq!(
+            stats.bodies,
+            vec![
+                UsageCount {
+                    value: "Canon EOS R5".into(),
+                    count: 3
+                },
+                UsageCount {
+                    value: "NIKON Z 6".into(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(stats.lenses[0].value, "RF24-70mm F2.8 L IS USM");
+        assert_eq!(stats.lenses[0].count, 2);
+        assert_eq!(stats.lenses.len(), 2);
+        assert_eq!(stats.isos[0].value, "ISO 100");
+        assert_eq!(stats.isos[0].count, 3);
+        assert!(stats.focal_lengths.is_empty());
+    }
+
+    #[test]
+    fn test_gear_filter() {
+        let mut second = exif(
+            "Canon",
+            "Canon EOS R5",
+            Some("RF70-200mm F2.8 L IS USM"),
+            "ISO 400",
+        );
+        second.body_serial = Some("032021000123".to_string());
+        let main = exif(
+            "Canon",
+            "Canon EOS R5",
+            Some("RF24-70mm F2.8 L IS USM"),
+            "ISO 100",
+        );
+
+        let by_serial = GearFilter {
+            serial: Some("032021000123".to_string()),
+            ..Default::default()
+        };
+        assert!(by_serial.matches(&second));
+        assert!(!by_serial.matches(&main));
+
+        let by_body_and_lens = GearFilter {
+            camera: Some("canon eos r5".to_string()),
+            lens: Some("RF24-70mm F2.8 L IS USM".to_string()),
+            serial: Some(" ".to_string()),
+        };
+        assert!(by_body_and_lens.matches(&main));
+        assert!(!by_body_and_lens.matches(&second));
+        assert!(!by_body_and_lens.matches(&ExifInfo::default()));
+
+        // Bare model of a body listed with its maker
+        let fuji = GearFilter {
+            camera: Some("X-T5".to_string()),
+            ..Default::default()
+        };
+        assert!(fuji.matches(&exif("FUJIFILM", "X-T5", None, "ISO 125")));
+        assert!(GearFilter::default().is_empty());
+        assert!(!fuji.is_empty());
+    }
+
+    #[test]
+    fn test_body_name_keeps_make_when_model_lacks_it() {
+        let info = exif("FUJIFILM", "X-T5", None, "ISO 125");
+        assert_eq!(body_name(&info).as_deref(), Some("FUJIFILM X-T5"));
+    }
+}
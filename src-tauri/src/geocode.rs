@@ -0,0 +1,552 @@
+// Offline reverse geocoding: GPS positions to the nearest city
+// A list of major cities ships with the app; a GeoNames cities file (cities500.txt and the
+// like, from download.geonames.org) can be set in the config for towns and regions
+
+use crate::config;
+use crate::error::{GlimpseError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Built-in cities: name, country code, latitude, longitude
+const BUILTIN_CITIES: &str = include_str!("../data/cities.tsv");
+
+/// GeoNames admin1 names read from next to the cities file, when present
+const GEONAMES_ADMIN1_FILE: &str = "admin1CodesASCII.txt";
+
+/// Positions farther than this from every known city get no place
+const MAX_DISTANCE_KM: f64 = 100.0;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// Length of one degree of latitude
+const KM_PER_DEGREE: f64 = EARTH_RADIUS_KM * std::f64::consts::PI / 180.0;
+
+/// Loaded city index and the GeoNames file it came from (None for the built-in list)
+static INDEX: Mutex<Option<(Option<PathBuf>, Arc<CityIndex>)>> = Mutex::new(None);
+
+/// The place a position is in or near
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Place {
+    pub city: String,
+    /// State, province or prefecture; only known with a GeoNames file and its admin1 names
+    pub region: Option<String>,
+    pub country: String,
+    /// ISO 3166-1 alpha-2 code
+    pub country_code: String,
+    /// Distance to the city's center
+    pub distance_km: f64,
+    /// Display name, e.g. "Kyoto, Japan"
+    pub label: String,
+}
+
+/// GPS position of an image, for plotting a session on a map
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ImageLocation {
+    pub filename: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+struct City {
+    name: String,
+    region: Option<String>,
+    country_code: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// Cities sorted by latitude, so a lookup only visits those within reach
+pub struct CityIndex {
+    cities: Vec<City>,
+}
+
+impl CityIndex {
+    fn new(mut cities: Vec<City>) -> Self {
+        cities.sort_by(|a, b| a.latitude.total_cmp(&b.latitude));
+        Self { cities }
+    }
+
+    /// The list of major cities bundled with the app
+    pub fn builtin() -> Self {
+        let cities = BUILTIN_CITIES
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let name = fields.next()?;
+                let country_code = fields.next()?;
+                let latitude = fields.next()?.parse().ok()?;
+                let longitude = fields.next()?.parse().ok()?;
+                Some(City {
+                    name: name.to_string(),
+                    region: None,
+                    country_code: country_code.to_string(),
+                    latitude,
+                    longitude,
+                })
+            })
+            .collect();
+        Self::new(cities)
+    }
+
+    /// Cities of a GeoNames dump (tab-separated: name in column 2, latitude and longitude in
+    /// 5-6, country code in 9, admin1 code in 11)
+    pub fn load_geonames(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| GlimpseError::io_at(path, e))?;
+        let admin1 = path
+            .parent()
+            .map(|folder| folder.join(GEONAMES_ADMIN1_FILE))
+            .and_then(|admin1| std::fs::read_to_string(admin1).ok())
+            .map(|text| parse_admin1(&text))
+            .unwrap_or_default();
+
+        let cities: Vec<City> = text
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                if fields.len() < 11 {
+                    return None;
+                }
+                let country_code = fields[8];
+                Some(City {
+                    name: fields[1].to_string(),
+                    region: admin1
+                        .get(&format!("{}.{}", country_code, fields[10]))
+                        .cloned(),
+                    country_code: country_code.to_string(),
+                    latitude: fields[4].parse().ok()?,
+                    longitude: fields[5].parse().ok()?,
+                })
+            })
+            .collect();
+        if cities.is_empty() {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "{} is not a GeoNames cities file",
+                path.display()
+            )));
+        }
+        Ok(Self::new(cities))
+    }
+
+    /// The city nearest to a position, if one is within `MAX_DISTANCE_KM`
+    pub fn nearest(&self, latitude: f64, longitude: f64) -> Option<Place> {
+        let start = self.cities.partition_point(|city| city.latitude < latitude);
+        let mut best: Option<(&City, f64)> = None;
+        let reach = |best: &Option<(&City, f64)>| best.map_or(MAX_DISTANCE_KM, |(_, d)| d);
+
+        // Walk north and south from the position's latitude; no city beyond the latitude
+        // band of the best distance so far can be closer
+        for city in self.cities[start..].iter() {
+            if (city.latitude - latitude) * KM_PER_DEGREE > reach(&best) {
+                break;
+            }
+            let distance = distance_km(latitude, longitude, city.latitude, city.longitude);
+            if distance <= reach(&best) {
+                best = Some((city, distance));
+            }
+        }
+        for city in self.cities[..start].iter().rev() {
+            if (latitude - city.latitude) * KM_PER_DEGREE > reach(&best) {
+                break;
+            }
+            let distance = distance_km(latitude, longitude, city.latitude, city.longitude);
+            if distance <= reach(&best) {
+                best = Some((city, distance));
+            }
+        }
+
+        let (city, distance_km) = best?;
+        let country = country_name(&city.country_code)
+            .unwrap_or(&city.country_code)
+            .to_string();
+        let label = match &city.region {
+            Some(region) if region != &city.name => {
+                format!("{}, {}, {}", city.name, region, country)
+            }
+            _ => format!("{}, {}", city.name, country),
+        };
+        Some(Place {
+            city: city.name.clone(),
+            region: city.region.clone(),
+            country,
+            country_code: city.country_code.clone(),
+            distance_km,
+            label,
+        })
+    }
+}
+
+/// Names by "country.admin1" code from GeoNames' admin1CodesASCII.txt
+fn parse_admin1(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?.to_string(), fields.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// Great-circle distance (haversine)
+fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Place of a GPS position, from the configured GeoNames file or the built-in cities
+/// None when no known city is near (at sea, in the wilderness)
+pub fn reverse_geocode(latitude: f64, longitude: f64) -> Result<Option<Place>> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(GlimpseError::InvalidConfig(format!(
+            "Not a GPS position: {}, {}",
+            latitude, longitude
+        )));
+    }
+
+    let source = config::get_geonames_file();
+    let index = {
+        let mut cached = INDEX.lock().unwrap();
+        match cached.as_ref() {
+            Some((path, index)) if *path == source => Arc::clone(index),
+            _ => {
+                let index = Arc::new(match &source {
+                    Some(path) => CityIndex::load_geonames(path)?,
+                    None => CityIndex::builtin(),
+                });
+                *cached = Some((source, Arc::clone(&index)));
+                index
+            }
+        }
+    };
+    Ok(index.nearest(latitude, longitude))
+}
+
+/// English short name of an ISO 3166-1 alpha-2 country code
+pub fn country_name(code: &str) -> Option<&'static str> {
+    COUNTRIES
+        .binary_search_by(|(c, _)| c.cmp(&code))
+        .ok()
+        .map(|i| COUNTRIES[i].1)
+}
+
+/// ISO 3166-1 alpha-2 codes (plus Kosovo's XK, as GeoNames uses it), sorted by code
+const COUNTRIES: &[(&str, &str)] = &[
+    ("AD", "Andorra"),
+    ("AE", "United Arab Emirates"),
+    ("AF", "Afghanistan"),
+    ("AG", "Antigua and Barbuda"),
+    ("AI", "Anguilla"),
+    ("AL", "Albania"),
+    ("AM", "Armenia"),
+    ("AO", "Angola"),
+    ("AQ", "Antarctica"),
+    ("AR", "Argentina"),
+    ("AS", "American Samoa"),
+    ("AT", "Austria"),
+    ("AU", "Australia"),
+    ("AW", "Aruba"),
+    ("AX", "Åland Islands"),
+    ("AZ", "Azerbaijan"),
+    ("BA", "Bosnia and Herzegovina"),
+    ("BB", "Barbados"),
+    ("BD", "Bangladesh"),
+    ("BE", "Belgium"),
+    ("BF", "Burkina Faso"),
+    ("BG", "Bulgaria"),
+    ("BH", "Bahrain"),
+    ("BI", "Burundi"),
+    ("BJ", "Benin"),
+    ("BL", "Saint Barthélemy"),
+    ("BM", "Bermuda"),
+    ("BN", "Brunei"),
+    ("BO", "Bolivia"),
+    ("BQ", "Caribbean Netherlands"),
+    ("BR", "Brazil"),
+    ("BS", "Bahamas"),
+    ("BT", "Bhutan"),
+    ("BV", "Bouvet Island"),
+    ("BW", "Botswana"),
+    ("BY", "Belarus"),
+    ("BZ", "Belize"),
+    ("CA", "Canada"),
+    ("CC", "Cocos (Keeling) Islands"),
+    ("CD", "DR Congo"),
+    ("CF", "Central African Republic"),
+    ("CG", "Republic of the Congo"),
+    ("CH", "Switzerland"),
+    ("CI", "Côte d'Ivoire"),
+    ("CK", "Cook Islands"),
+    ("CL", "Chile"),
+    ("CM", "Cameroon"),
+    ("CN", "China"),
+    ("CO", "Colombia"),
+    ("CR", "Costa Rica"),
+    ("CU", "Cuba"),
+    ("CV", "Cape Verde"),
+    ("CW", "Curaçao"),
+    ("CX", "Christmas Island"),
+    ("CY", "Cyprus"),
+    ("CZ", "Czechia"),
+    ("DE", "Germany"),
+    ("DJ", "Djibouti"),
+    ("DK", "Denmark"),
+    ("DM", "Dominica"),
+    ("DO", "Dominican Republic"),
+    ("DZ", "Algeria"),
+    ("EC", "Ecuador"),
+    ("EE", "Estonia"),
+    ("EG", "Egypt"),
+    ("EH", "Western Sahara"),
+    ("ER", "Eritrea"),
+    ("ES", "Spain"),
+    ("ET", "Ethiopia"),
+    ("FI", "Finland"),
+    ("FJ", "Fiji"),
+    ("FK", "Falkland Islands"),
+    ("FM", "Micronesia"),
+    ("FO", "Faroe Islands"),
+    ("FR", "France"),
+    ("GA", "Gabon"),
+    ("GB", "United Kingdom"),
+    ("GD", "Grenada"),
+    ("GE", "Georgia"),
+    ("GF", "French Guiana"),
+    ("GG", "Guernsey"),
+    ("GH", "Ghana"),
+    ("GI", "Gibraltar"),
+    ("GL", "Greenland"),
+    ("GM", "Gambia"),
+    ("GN", "Guinea"),
+    ("GP", "Guadeloupe"),
+    ("GQ", "Equatorial Guinea"),
+    ("GR", "Greece"),
+    ("GS", "South Georgia and the South Sandwich Islands"),
+    ("GT", "Guatemala"),
+    ("GU", "Guam"),
+    ("GW", "Guinea-Bissau"),
+    ("GY", "Guyana"),
+    ("HK", "Hong Kong"),
+    ("HM", "Heard Island and McDonald Islands"),
+    ("HN", "Honduras"),
+    ("HR", "Croatia"),
+    ("HT", "Haiti"),
+    ("HU", "Hungary"),
+    ("ID", "Indonesia"),
+    ("IE", "Ireland"),
+    ("IL", "Israel"),
+    ("IM", "Isle of Man"),
+    ("IN", "India"),
+    ("IO", "British Indian Ocean Territory"),
+    ("IQ", "Iraq"),
+    ("IR", "Iran"),
+    ("IS", "Iceland"),
+    ("IT", "Italy"),
+    ("JE", "Jersey"),
+    ("JM", "Jamaica"),
+    ("JO", "Jordan"),
+    ("JP", "Japan"),
+    ("KE", "Kenya"),
+    ("KG", "Kyrgyzstan"),
+    ("KH", "Cambodia"),
+    ("KI", "Kiribati"),
+    ("KM", "Comoros"),
+    ("KN", "Saint Kitts and Nevis"),
+    ("KP", "North Korea"),
+    ("KR", "South Korea"),
+    ("KW", "Kuwait"),
+    ("KY", "Cayman Islands"),
+    ("KZ", "Kazakhstan"),
+    ("LA", "Laos"),
+    ("LB", "Lebanon"),
+    ("LC", "Saint Lucia"),
+    ("LI", "Liechtenstein"),
+    ("LK", "Sri Lanka"),
+    ("LR", "Liberia"),
+    ("LS", "Lesotho"),
+    ("LT", "Lithuania"),
+    ("LU", "Luxembourg"),
+    ("LV", "Latvia"),
+    ("LY", "Libya"),
+    ("MA", "Morocco"),
+    ("MC", "Monaco"),
+    ("MD", "Moldova"),
+    ("ME", "Montenegro"),
+    ("MF", "Saint Martin"),
+    ("MG", "Madagascar"),
+    ("MH", "Marshall Islands"),
+    ("MK", "North Macedonia"),
+    ("ML", "Mali"),
+    ("MM", "Myanmar"),
+    ("MN", "Mongolia"),
+    ("MO", "Macau"),
+    ("MP", "Northern Mariana Islands"),
+    ("MQ", "Martinique"),
+    ("MR", "Mauritania"),
+    ("MS", "Montserrat"),
+    ("MT", "Malta"),
+    ("MU", "Mauritius"),
+    ("MV", "Maldives"),
+    ("MW", "Malawi"),
+    ("MX", "Mexico"),
+    ("MY", "Malaysia"),
+    ("MZ", "Mozambique"),
+    ("NA", "Namibia"),
+    ("NC", "New Caledonia"),
+    ("NE", "Niger"),
+    ("NF", "Norfolk Island"),
+    ("NG", "Nigeria"),
+    ("NI", "Nicaragua"),
+    ("NL", "Netherlands"),
+    ("NO", "Norway"),
+    ("NP", "Nepal"),
+    ("NR", "Nauru"),
+    ("NU", "Niue"),
+    ("NZ", "New Zealand"),
+    ("OM", "Oman"),
+    ("PA", "Panama"),
+    ("PE", "Peru"),
+    ("PF", "French Polynesia"),
+    ("PG", "Papua New Guinea"),
+    ("PH", "Philippines"),
+    ("PK", "Pakistan"),
+    ("PL", "Poland"),
+    ("PM", "Saint Pierre and Miquelon"),
+    ("PN", "Pitcairn Islands"),
+    ("PR", "Puerto Rico"),
+    ("PS", "Palestine"),
+    ("PT", "Portugal"),
+    ("PW", "Palau"),
+    ("PY", "Paraguay"),
+    ("QA", "Qatar"),
+    ("RE", "Réunion"),
+    ("RO", "Romania"),
+    ("RS", "Serbia"),
+    ("RU", "Russia"),
+    ("RW", "Rwanda"),
+    ("SA", "Saudi Arabia"),
+    ("SB", "Solomon Islands"),
+    ("SC", "Seychelles"),
+    ("SD", "Sudan"),
+    ("SE", "Sweden"),
+    ("SG", "Singapore"),
+    ("SH", "Saint Helena, Ascension and Tristan da Cunha"),
+    ("SI", "Slovenia"),
+    ("SJ", "Svalbard and Jan Mayen"),
+    ("SK", "Slovakia"),
+    ("SL", "Sierra Leone"),
+    ("SM", "San Marino"),
+    ("SN", "Senegal"),
+    ("SO", "Somalia"),
+    ("SR", "Suriname"),
+    ("SS", "South Sudan"),
+    ("ST", "São Tomé and Príncipe"),
+    ("SV", "El Salvador"),
+    ("SX", "Sint Maarten"),
+    ("SY", "Syria"),
+    ("SZ", "Eswatini"),
+    ("TC", "Turks and Caicos Islands"),
+    ("TD", "Chad"),
+    ("TF", "French Southern Territories"),
+    ("TG", "Togo"),
+    ("TH", "Thailand"),
+    ("TJ", "Tajikistan"),
+    ("TK", "Tokelau"),
+    ("TL", "Timor-Leste"),
+    ("TM", "Turkmenistan"),
+    ("TN", "Tunisia"),
+    ("TO", "Tonga"),
+    ("TR", "Turkey"),
+    ("TT", "Trinidad and Tobago"),
+    ("TV", "Tuvalu"),
+    ("TW", "Taiwan"),
+    ("TZ", "Tanzania"),
+    ("UA", "Ukraine"),
+    ("UG", "Uganda"),
+    ("UM", "United States Minor Outlying Islands"),
+    ("US", "United States"),
+    ("UY", "Uruguay"),
+    ("UZ", "Uzbekistan"),
+    ("VA", "Vatican City"),
+    ("VC", "Saint Vincent and the Grenadines"),
+    ("VE", "Venezuela"),
+    ("VG", "British Virgin Islands"),
+    ("VI", "United States Virgin Islands"),
+    ("VN", "Vietnam"),
+    ("VU", "Vanuatu"),
+    ("WF", "Wallis and Futuna"),
+    ("WS", "Samoa"),
+    ("XK", "Kosovo"),
+    ("YE", "Yemen"),
+    ("YT", "Mayotte"),
+    ("ZA", "South Africa"),
+    ("ZM", "Zambia"),
+    ("ZW", "Zimbabwe"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_builtin_cities() {
+        let index = CityIndex::builtin();
+        assert!(index.cities.len() > 500);
+        // Every country code has a name
+        for city in &index.cities {
+            assert!(
+                country_name(&city.country_code).is_some(),
+                "{}",
+                city.country_code
+            );
+        }
+
+        // Kiyomizu-dera
+        let place = index.nearest(34.995, 135.785).unwrap();
+        assert_eq!(place.label, "Kyoto, Japan");
+        assert!(place.distance_km < 3.0);
+        assert_eq!(index.nearest(-33.857, 151.215).unwrap().city, "Sydney");
+        // Middle of the Pacific
+        assert!(index.nearest(0.0, -140.0).is_none());
+    }
+
+    #[test]
+    fn test_geonames_file() {
+        let dir = tempdir().unwrap();
+        let cities = dir.path().join("cities500.txt");
+        std::fs::write(
+            &cities,
+            "1857910\tKyoto\tKyoto\tKioto\t35.02107\t135.75385\tP\tPPLA\tJP\t\t22\t\t\t\t1459640\n\
+             1850147\tUji\tUji\t\t34.88333\t135.8\tP\tPPL\tJP\t\t22\t\t\t\t184678\n\
+             broken line\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(GEONAMES_ADMIN1_FILE),
+            "JP.22\tKyoto\tKyoto\t1857907\n",
+        )
+        .unwrap();
+
+        let index = CityIndex::load_geonames(&cities).unwrap();
+        assert_eq!(index.cities.len(), 2);
+        let place = index.nearest(34.889, 135.807).unwrap();
+        assert_eq!(place.label, "Uji, Kyoto, Japan");
+        assert_eq!(place.region.as_deref(), Some("Kyoto"));
+        // The region isn't repeated when the city shares its name
+        assert_eq!(index.nearest(35.02, 135.75).unwrap().label, "Kyoto, Japan");
+
+        std::fs::write(&cities, "not geonames\n").unwrap();
+        assert!(CityIndex::load_geonames(&cities).is_err());
+    }
+
+    #[test]
+    fn test_distance() {
+        // Tokyo to Osaka is about 400 km
+        let distance = distance_km(35.69, 139.69, 34.69, 135.50);
+        assert!((390.0..410.0).contains(&distance), "{}", distance);
+        assert!(distance_km(10.0, 179.9, 10.0, -179.9) < 25.0);
+        assert!(COUNTRIES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+}
@@ -0,0 +1,202 @@
+// Per-channel histograms for exposure evaluation
+// Either of the rendered preview, or of a RAW file's linear photosite values before white
+// balance and the tone curve, which show how close the sensor itself came to clipping
+
+use crate::error::{GlimpseError, Result};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Number of bins per channel
+pub const BINS: usize = 256;
+/// Fraction of the white level at or above which a photosite counts as clipped
+/// Sensors often saturate a little below the white level they report
+const RAW_CLIPPED_FRACTION: f32 = 0.98;
+
+/// What a histogram is computed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistogramSource {
+    /// The preview as displayed (sRGB, after the tone curve)
+    #[default]
+    Rendered,
+    /// Linear RAW photosite values between black and white level
+    Raw,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub source: HistogramSource,
+    /// Pixel (or photosite) counts per bin, darkest first
+    pub red: Vec<u64>,
+    pub green: Vec<u64>,
+    pub blue: Vec<u64>,
+    /// Share of clipped values per channel (red, green, blue; 0.0 - 1.0)
+    pub highlight_clipping: [f64; 3],
+    /// Share of values at black per channel
+    pub shadow_clipping: [f64; 3],
+}
+
+/// Counts kept while filling a histogram
+struct Accumulator {
+    bins: [Vec<u64>; 3],
+    highlights: [u64; 3],
+    shadows: [u64; 3],
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            bins: std::array::from_fn(|_| vec![0; BINS]),
+            highlights: [0; 3],
+            shadows: [0; 3],
+        }
+    }
+
+    fn finish(self, source: HistogramSource) -> Histogram {
+        let shares = |counts: [u64; 3]| {
+            std::array::from_fn(|c| {
+                let total: u64 = self.bins[c].iter().sum();
+                counts[c] as f64 / total.max(1) as f64
+            })
+        };
+        let (highlight_clipping, shadow_clipping) = (shares(self.highlights), shares(self.shadows));
+        let [red, green, blue] = self.bins;
+        Histogram {
+            source,
+            red,
+            green,
+            blue,
+            highlight_clipping,
+            shadow_clipping,
+        }
+    }
+}
+
+/// Histogram of a rendered image; only 255 and 0 count as clipped
+pub fn from_image(img: &RgbImage) -> Histogram {
+    let mut acc = Accumulator::new();
+    for pixel in img.pixels() {
+        for (c, &value) in pixel.0.iter().enumerate() {
+            acc.bins[c][value as usize] += 1;
+            acc.highlights[c] += u64::from(value == u8::MAX);
+            acc.shadows[c] += u64::from(value == 0);
+        }
+    }
+    acc.finish(HistogramSource::Rendered)
+}
+
+/// Histogram of a RAW file's linear data
+/// Only mosaiced sensors (Bayer, X-Trans) are supported, and only through rawloader
+pub fn from_raw(path: &Path) -> Result<Histogram> {
+    let raw =
+        rawloader::decode_file(path).map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+    let rawloader::RawImageData::Integer(data) = &raw.data else {
+        return Err(GlimpseError::RawProcessing(format!(
+            "No integer RAW data in {}",
+            path.display()
+        )));
+    };
+    if raw.cpp != 1 || !raw.cfa.is_valid() {
+        return Err(GlimpseError::RawProcessing(format!(
+            "No mosaiced RAW data in {}",
+            path.display()
+        )));
+    }
+
+    // Masked borders outside the crop only ever hold black
+    let [top, right, bottom, left] = raw.crops;
+    let area = (
+        top..raw.height.saturating_sub(bottom),
+        left..raw.width.saturating_sub(right),
+    );
+    Ok(from_photosites(
+        data,
+        raw.width,
+        area,
+        |row, col| raw.cfa.color_at(row, col),
+        raw.blacklevels,
+        raw.whitelevels,
+    ))
+}
+
+/// Histogram of mosaiced photosites in `area` (rows, columns), scaled from black to white level
+/// CFA colors are 0 red, 1 green, 2 blue and 3 the second green
+fn from_photosites(
+    data: &[u16],
+    width: usize,
+    (rows, cols): (std::ops::Range<usize>, std::ops::Range<usize>),
+    color_at: impl Fn(usize, usize) -> usize,
+    black: [u16; 4],
+    white: [u16; 4],
+) -> Histogram {
+    let mut acc = Accumulator::new();
+    for row in rows {
+        let Some(line) = data.get(row * width..(row + 1) * width) else {
+            break;
+        };
+        for col in cols.clone() {
+            let color = color_at(row, col);
+            let channel = match color {
+                0 => 0,
+                1 | 3 => 1,
+                2 => 2,
+                _ => continue,
+            };
+            let (black, white) = (black[color] as f32, white[color] as f32);
+            let level = ((line[col] as f32 - black) / (white - black).max(1.0)).clamp(0.0, 1.0);
+
+            let bin = ((level * BINS as f32) as usize).min(BINS - 1);
+            acc.bins[channel][bin] += 1;
+            acc.highlights[channel] += u64::from(level >= RAW_CLIPPED_FRACTION);
+            acc.shadows[channel] += u64::from(level <= 0.0);
+        }
+    }
+    acc.finish(HistogramSource::Raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_image() {
+        let img = RgbImage::from_fn(4, 1, |x, _| match x {
+            0 => image::Rgb([255, 255, 255]),
+            1 => image::Rgb([255, 128, 0]),
+            _ => image::Rgb([10, 20, 30]),
+        });
+        let histogram = from_image(&img);
+        assert_eq!(histogram.source, HistogramSource::Rendered);
+        assert_eq!(histogram.red[255], 2);
+        assert_eq!(histogram.red[10], 2);
+        assert_eq!(histogram.green[128], 1);
+        assert_eq!(histogram.blue[0], 1);
+        assert_eq!(histogram.highlight_clipping, [0.5, 0.25, 0.25]);
+        assert_eq!(histogram.shadow_clipping, [0.0, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn test_from_photosites() {
+        // RGGB; one red clipped and one just below, greens mid-level, blue black
+        // The first row is masked
+        let black = [100; 4];
+        let white = [4100; 4];
+        #[rustfmt::skip]
+        let data = [
+            0, 0, 0, 0,
+            4100, 2100, 3900, 2100,
+            2100, 100, 2100, 50,
+        ];
+        let rggb = |row: usize, col: usize| [[0, 1], [3, 2]][(row + 1) % 2][col % 2];
+        let histogram = from_photosites(&data, 4, (1..3, 0..4), rggb, black, white);
+
+        assert_eq!(histogram.source, HistogramSource::Raw);
+        assert_eq!(histogram.red[BINS - 1], 1);
+        assert_eq!(histogram.red[243], 1);
+        assert_eq!(histogram.green[BINS / 2], 4);
+        assert_eq!(histogram.blue[0], 2);
+        assert_eq!(histogram.highlight_clipping, [0.5, 0.0, 0.0]);
+        assert_eq!(histogram.shadow_clipping, [0.0, 0.0, 1.0]);
+    }
+}
@@ -1,21 +1,96 @@
-use crate::config::get_thumbnail_thread_count;
+use crate::color::{self, SourceSpace};
+use crate::config::{
+    get_cache_format, get_config, get_external_raw_decoder, get_include_hidden_files,
+    get_large_image_preview_pixels, get_lens_correction, get_placeholder_policy,
+    get_preview_denoise_iso, get_raw_backend, get_raw_decode_thread_count, get_raw_demosaic,
+    get_scan_exclude, get_symlink_policy, get_thumbnail_thread_count, is_extra_image_extension,
+    is_extra_raw_extension, split_exif_threads, AppConfig, CacheFormat, PlaceholderPolicy,
+    RawDemosaic, SymlinkPolicy, WhiteBalance,
+};
+use crate::content_cache::{self, SHARED_CACHE_FOLDER};
+use crate::denoise;
+use crate::embedded;
 use crate::error::{GlimpseError, Result};
+use crate::lens;
+use crate::makernote::{self, FocusArea};
+use crate::memory;
+use crate::netio;
+use crate::placeholder::is_cloud_placeholder;
+use crate::raw_decoder::{self, DevelopOptions, RawDecoder};
+use crate::sniff::{self, FileFormat};
+use crate::tasks::TaskHandle;
+use crate::video;
+use chrono::{DateTime, Utc};
 use exif::{In, Reader, Tag};
-use image::{DynamicImage, ImageFormat};
-use rayon::prelude::*;
-use rayon::ThreadPoolBuilder;
-use std::fs::File;
-use std::io::BufReader;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, Metadata};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
-
-const THUMBNAIL_SIZE: u32 = 300;
-const PREVIEW_SIZE: u32 = 2000;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::time::Duration;
 
 /// Normalize path (convert backslashes to forward slashes)
 /// Convert Windows paths to a format usable with the asset:// protocol
+/// Long-path prefixes (`\\?\`) are dropped; std::fs adds them again where needed
 pub fn normalize_path(path: &Path) -> String {
-    path.to_string_lossy().replace('\\', "/")
+    let path = path.to_string_lossy().replace('\\', "/");
+    if let Some(unc) = path.strip_prefix("//?/UNC/") {
+        return format!("//{}", unc);
+    }
+    match path.strip_prefix("//?/") {
+        Some(local) => local.to_string(),
+        None => path,
+    }
+}
+
+/// Classic Windows path length limit
+const MAX_PATH: usize = 260;
+
+/// Path to pass to external tools (ffmpeg), which don't lift the Windows path length
+/// limit themselves the way std::fs does; long paths get the `\\?\` prefix
+pub fn external_path(path: &Path) -> PathBuf {
+    if cfg!(windows) && path.as_os_str().len() >= MAX_PATH {
+        if let Some(verbatim) = verbatim_path(&path.to_string_lossy()) {
+            return PathBuf::from(verbatim);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// `C:\dir\..\file` -> `\\?\C:\file`, `\\server\share` -> `\\?\UNC\server\share`
+/// Windows takes verbatim paths literally, so separators and `.`/`..` are resolved here
+/// None for relative paths and paths that are already verbatim
+fn verbatim_path(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    if path.starts_with(r"\\?\") {
+        return None;
+    }
+    let (prefix, rest) = if let Some(unc) = path.strip_prefix(r"\\") {
+        (r"\\?\UNC\".to_string(), unc)
+    } else {
+        let drive = path.get(..3).filter(|drive| {
+            let drive = drive.as_bytes();
+            drive[0].is_ascii_alphabetic() && drive[1] == b':' && drive[2] == b'\\'
+        })?;
+        (format!(r"\\?\{}", drive), &path[3..])
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    Some(prefix + &components.join("\\"))
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -24,6 +99,39 @@ pub struct ImageInfo {
     pub path: String,
     pub size: u64,
     pub modified_at: String,
+    pub is_video: bool,
+}
+
+/// Images for tests: `/shoot/<filename>`, 100 bytes, modified 2024/12/15 10:00
+#[cfg(test)]
+impl ImageInfo {
+    pub fn fixture(filename: &str) -> Self {
+        ImageInfo {
+            filename: filename.to_string(),
+            path: format!("/shoot/{}", filename),
+            size: 100,
+            modified_at: "2024/12/15 10:00".to_string(),
+            is_video: false,
+        }
+    }
+
+    pub fn with_size(self, size: u64) -> Self {
+        ImageInfo { size, ..self }
+    }
+
+    pub fn with_modified_at(self, modified_at: &str) -> Self {
+        ImageInfo {
+            modified_at: modified_at.to_string(),
+            ..self
+        }
+    }
+
+    pub fn video(self) -> Self {
+        ImageInfo {
+            is_video: true,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -33,13 +141,30 @@ pub struct ThumbnailResult {
     pub preview_path: Option<String>,
     pub success: bool,
     pub error: Option<String>,
+    /// Cloud online-only file that was skipped instead of downloaded
+    pub placeholder: bool,
+    /// Decoding failed because the file is truncated or corrupt
+    pub corrupt: bool,
+    /// The RAW data couldn't be decoded; the thumbnail shows the camera's embedded preview
+    pub embedded_preview: bool,
+}
+
+/// What a thumbnail or preview was rendered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rendition {
+    /// The image data itself
+    Decoded,
+    /// The JPEG embedded in a RAW file whose sensor data couldn't be decoded
+    EmbeddedPreview,
 }
 
 /// EXIF information
-#[derive(Debug, Clone, serde::Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct ExifInfo {
     pub camera_make: Option<String>,
     pub camera_model: Option<String>,
+    /// BodySerialNumber, telling apart two bodies of the same model
+    pub body_serial: Option<String>,
     pub lens_model: Option<String>,
     pub focal_length: Option<String>,
     pub aperture: Option<String>,
@@ -47,14 +172,23 @@ pub struct ExifInfo {
     pub iso: Option<String>,
     pub exposure_compensation: Option<String>,
     pub date_taken: Option<String>,
+    /// UTC offset the camera recorded for `date_taken` (OffsetTimeOriginal), e.g. "+09:00"
+    pub offset_time: Option<String>,
+    /// Fraction of a second of `date_taken` as recorded (SubSecTimeOriginal), e.g. "05"
+    pub sub_sec_time: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
     pub orientation: Option<u16>,
+    /// GPS position in degrees, negative south of the equator and west of Greenwich
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Where the camera focused, from maker notes (Canon/Nikon/Sony)
+    pub focus_areas: Vec<FocusArea>,
 }
 
 /// Extract EXIF information from an image
 pub fn extract_exif(image_path: &Path) -> Result<ExifInfo> {
-    let file = File::open(image_path)?;
+    let file = File::open(image_path).map_err(|e| GlimpseError::io_at(image_path, e))?;
     let mut bufreader = BufReader::new(file);
 
     let exif = Reader::new()
@@ -132,6 +266,36 @@ pub fn extract_exif(image_path: &Path) -> Result<ExifInfo> {
         );
     }
 
+    if let Some(field) = exif.get_field(Tag::BodySerialNumber, In::PRIMARY) {
+        info.body_serial = Some(
+            field
+                .display_value()
+                .to_string()
+                .trim_matches('"')
+                .to_string(),
+        );
+    }
+
+    if let Some(field) = exif.get_field(Tag::SubSecTimeOriginal, In::PRIMARY) {
+        let digits: String = field
+            .display_value()
+            .to_string()
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        info.sub_sec_time = Some(digits).filter(|digits| !digits.is_empty());
+    }
+
+    if let Some(field) = exif.get_field(Tag::OffsetTimeOriginal, In::PRIMARY) {
+        info.offset_time = Some(
+            field
+                .display_value()
+                .to_string()
+                .trim_matches('"')
+                .to_string(),
+        );
+    }
+
     // Image dimensions
     if let Some(field) = exif.get_field(Tag::PixelXDimension, In::PRIMARY) {
         if let exif::Value::Long(ref v) = field.value {
@@ -157,9 +321,49 @@ pub fn extract_exif(image_path: &Path) -> Result<ExifInfo> {
         }
     }
 
+    // GPS position
+    info.latitude = gps_degrees(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S");
+    info.longitude = gps_degrees(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W");
+
+    // Autofocus areas
+    if let Some(field) = exif.get_field(Tag::MakerNote, In::PRIMARY) {
+        if let exif::Value::Undefined(_, offset) = field.value {
+            info.focus_areas = makernote::focus_areas(
+                info.camera_make.as_deref().unwrap_or_default(),
+                exif.buf(),
+                exif.little_endian(),
+                offset as usize,
+            );
+        }
+    }
+
     Ok(info)
 }
 
+/// Degrees of a GPS coordinate stored as degrees, minutes and seconds; negative when its
+/// reference is `negative` ("S" or "W")
+fn gps_degrees(exif: &exif::Exif, value: Tag, reference: Tag, negative: &str) -> Option<f64> {
+    let field = exif.get_field(value, In::PRIMARY)?;
+    let exif::Value::Rational(ref parts) = field.value else {
+        return None;
+    };
+    if parts.is_empty() {
+        return None;
+    }
+    let degrees: f64 = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, unit)| part.to_f64() / unit)
+        .sum();
+    if !degrees.is_finite() {
+        return None;
+    }
+    let is_negative = exif
+        .get_field(reference, In::PRIMARY)
+        .is_some_and(|field| field.display_value().to_string().trim_matches('"') == negative);
+    Some(if is_negative { -degrees } else { degrees })
+}
+
 /// Supported RAW file extensions
 const RAW_EXTENSIONS: &[&str] = &[
     "nef", "NEF", // Nikon
@@ -176,34 +380,243 @@ const RAW_EXTENSIONS: &[&str] = &[
 /// Supported standard image extensions
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "JPG", "jpeg", "JPEG", "png", "PNG"];
 
-/// Check if extension is a RAW format
+/// Supported video extensions (poster frames are extracted with ffmpeg)
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "MP4", "mov", "MOV", "m4v", "M4V", // MPEG-4 / QuickTime
+    "mts", "MTS", "m2ts", "M2TS", // AVCHD
+    "avi", "AVI",
+];
+
+/// Check if extension is a RAW format (built-in or added in the config)
 fn is_raw_extension(ext: &str) -> bool {
-    RAW_EXTENSIONS.contains(&ext)
+    RAW_EXTENSIONS.contains(&ext) || is_extra_raw_extension(ext)
 }
 
+/// Check if extension is a video format
+fn is_video_extension(ext: &str) -> bool {
+    VIDEO_EXTENSIONS.contains(&ext)
+}
+
+/// Check if extension is a supported still image, RAW or video format
 fn is_supported_image_extension(ext: &str) -> bool {
-    RAW_EXTENSIONS.contains(&ext) || IMAGE_EXTENSIONS.contains(&ext)
+    is_builtin_extension(ext) || is_extra_raw_extension(ext) || is_extra_image_extension(ext)
+}
+
+fn is_builtin_extension(ext: &str) -> bool {
+    RAW_EXTENSIONS.contains(&ext) || IMAGE_EXTENSIONS.contains(&ext) || is_video_extension(ext)
+}
+
+/// Whether a file with a user-added extension holds data the decoders here can read
+/// Judged from the first bytes, so e.g. a video format added as RAW stays out of sessions
+fn is_readable_extra_format(path: &Path, raw: bool) -> bool {
+    match (raw, sniff::sniff_file(path)) {
+        (true, Some(FileFormat::Raw | FileFormat::Tiff)) => true,
+        (false, Some(FileFormat::Image(format))) => format.reading_enabled(),
+        (false, Some(_)) => true,
+        _ => false,
+    }
+}
+
+/// Decoder a file is routed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decoder {
+    Video,
+    Raw,
+    Standard,
+}
+
+/// Pick the decoder from the file content, falling back to the extension
+/// so files with a wrong or missing extension still decode
+fn decoder_for(path: &Path) -> Decoder {
+    let extension = lowercase_extension(&path.to_string_lossy());
+    if is_video_extension(&extension) {
+        return Decoder::Video;
+    }
+    match sniff::sniff_file(path) {
+        Some(FileFormat::Raw) => Decoder::Raw,
+        // TIFF-based RAWs carry their own extensions; plain TIFFs keep .tif
+        Some(FileFormat::Tiff) if !matches!(extension.as_str(), "tif" | "tiff") => Decoder::Raw,
+        Some(_) => Decoder::Standard,
+        None if is_raw_extension(&extension) => Decoder::Raw,
+        None => Decoder::Standard,
+    }
+}
+
+/// Lowercased extension of a file name
+fn lowercase_extension(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// RAW files and videos can't be displayed directly, so they get a preview image
+/// Very large JPEG/PNG files get one too, so the detail view doesn't decode them at full size
+fn needs_preview(image_path: &Path, filename: &str) -> bool {
+    needs_preview_above(image_path, filename, get_large_image_preview_pixels())
+}
+
+fn needs_preview_above(image_path: &Path, filename: &str, max_pixels: Option<u64>) -> bool {
+    let extension = lowercase_extension(filename);
+    // Formats added in the config, and files without an extension,
+    // may not display in the webview
+    if extension.is_empty()
+        || is_raw_extension(&extension)
+        || is_video_extension(&extension)
+        || (!is_builtin_extension(&extension) && is_extra_image_extension(&extension))
+    {
+        return true;
+    }
+    // Only the header is read to get the dimensions, unless that would download the file
+    max_pixels.is_some_and(|max_pixels| {
+        if is_cloud_placeholder(image_path) {
+            return false;
+        }
+        image::image_dimensions(image_path)
+            .is_ok_and(|(width, height)| width as u64 * height as u64 > max_pixels)
+    })
+}
+
+/// Folders that NAS systems and operating systems fill with their own files
+/// Synology keeps generated thumbnails (JPEGs) in `@eaDir`
+const SYSTEM_FOLDERS: &[&str] = &[
+    "@eaDir",
+    "#recycle",
+    "#snapshot",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+];
+
+/// Which directory entries folder scans pick up
+#[derive(Debug, Clone)]
+struct ScanOptions {
+    symlinks: SymlinkPolicy,
+    include_hidden: bool,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl ScanOptions {
+    fn from_config() -> Self {
+        Self {
+            symlinks: get_symlink_policy(),
+            include_hidden: get_include_hidden_files(),
+            exclude: get_scan_exclude(),
+        }
+    }
+
+    /// Whether an exclude pattern matches the entry at `path`
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        let full_path = normalize_path(path);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.exclude.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(&full_path, options)
+            } else {
+                pattern.matches_with(&name, options)
+            }
+        })
+    }
+
+    /// Metadata of an entry the scan includes
+    /// Links are resolved to their target when followed; None for ignored and broken links
+    /// and excluded entries and, unless included, hidden and system files
+    fn resolve(&self, entry: &std::fs::DirEntry) -> Result<Option<Metadata>> {
+        if !self.include_hidden && is_hidden_name(&entry.file_name().to_string_lossy()) {
+            return Ok(None);
+        }
+        if self.is_excluded(&entry.path()) {
+            return Ok(None);
+        }
+        let metadata = if entry.file_type()?.is_symlink() {
+            match self.symlinks {
+                SymlinkPolicy::Follow => std::fs::metadata(entry.path()).ok(),
+                SymlinkPolicy::Ignore => None,
+            }
+        } else {
+            Some(entry.metadata()?)
+        };
+        Ok(metadata.filter(|metadata| self.include_hidden || !has_hidden_attribute(metadata)))
+    }
+}
+
+/// Dotfiles (including macOS `._` resource forks) and NAS/OS system folders
+fn is_hidden_name(name: &str) -> bool {
+    name.starts_with('.') || SYSTEM_FOLDERS.contains(&name)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_metadata: &Metadata) -> bool {
+    false
 }
 
 /// Scan image files in a folder
 pub fn scan_folder(folder_path: &Path) -> Result<Vec<ImageInfo>> {
+    scan_folder_with(folder_path, &ScanOptions::from_config())
+}
+
+fn scan_folder_with(folder_path: &Path, options: &ScanOptions) -> Result<Vec<ImageInfo>> {
+    scan_folder_visit(folder_path, options, &mut |_| {})
+}
+
+/// Scan a folder, handing each image to `visit` as it is found (in directory order)
+fn scan_folder_visit(
+    folder_path: &Path,
+    options: &ScanOptions,
+    visit: &mut dyn FnMut(&mut ImageInfo),
+) -> Result<Vec<ImageInfo>> {
     let mut images = Vec::new();
 
-    for entry in std::fs::read_dir(folder_path)? {
+    let entries =
+        std::fs::read_dir(folder_path).map_err(|e| GlimpseError::io_at(folder_path, e))?;
+    for entry in entries {
+        // Keeps a watched scan alive, and stops it once it was given up on
+        netio::heartbeat(folder_path)?;
         let entry = entry?;
         let path = entry.path();
 
-        if !path.is_file() {
-            continue;
-        }
-
         let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
-        if !is_supported_image_extension(extension) {
+        if !extension.is_empty() && !is_supported_image_extension(extension) {
             continue;
         }
 
-        let metadata = entry.metadata()?;
+        let Some(metadata) = options.resolve(&entry)? else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        // Files without an extension (e.g. from recovery tools) are kept when they hold an image
+        if extension.is_empty() {
+            if sniff::sniff_file(&path).is_none() {
+                continue;
+            }
+        } else if !is_builtin_extension(extension)
+            && !is_readable_extra_format(&path, is_extra_raw_extension(extension))
+        {
+            tracing::debug!(
+                "Skipping {}: not a format that can be decoded",
+                path.display()
+            );
+            continue;
+        }
         let modified = metadata
             .modified()
             .ok()
@@ -213,12 +626,15 @@ pub fn scan_folder(folder_path: &Path) -> Result<Vec<ImageInfo>> {
             })
             .unwrap_or_else(|| "-".to_string());
 
-        images.push(ImageInfo {
+        let mut image = ImageInfo {
             filename: path.file_name().unwrap().to_string_lossy().to_string(),
             path: normalize_path(&path),
             size: metadata.len(),
             modified_at: modified,
-        });
+            is_video: is_video_extension(extension),
+        };
+        visit(&mut image);
+        images.push(image);
     }
 
     // Sort by filename
@@ -227,6 +643,168 @@ pub fn scan_folder(folder_path: &Path) -> Result<Vec<ImageInfo>> {
     Ok(images)
 }
 
+/// Folder that file keys of a session are relative to
+/// For a single folder this is the folder itself (keys are plain file names);
+/// for several folders it is their deepest common ancestor, which may be empty
+/// when they share none (e.g. different drives on Windows)
+pub fn session_root(folders: &[PathBuf]) -> PathBuf {
+    let Some((first, rest)) = folders.split_first() else {
+        return PathBuf::new();
+    };
+
+    let mut root = first.clone();
+    for folder in rest {
+        while !folder.starts_with(&root) {
+            if !root.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    root
+}
+
+/// Scan several folders as one set
+/// Each image's `filename` is its path relative to `session_root`, so names that
+/// repeat across folders (e.g. two cards both holding DSC_0001.NEF) stay distinct.
+/// A merged set is ordered by modification time, then by key, to interleave cameras.
+/// Folders that are links to another folder of the set are only scanned once.
+pub fn scan_folders(folders: &[PathBuf]) -> Result<Vec<ImageInfo>> {
+    scan_folders_streaming(folders, |_| {})
+}
+
+/// Images handed to the callback of `scan_folders_streaming` at once
+const SCAN_BATCH_SIZE: usize = 500;
+/// Longest a found image waits before its batch is handed over
+const SCAN_BATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// `scan_folders`, handing images to `on_batch` in batches as directory entries are read,
+/// so a huge folder can be shown before the scan finishes
+/// Batches are in directory order; the returned list is ordered like `scan_folders`
+pub fn scan_folders_streaming(
+    folders: &[PathBuf],
+    mut on_batch: impl FnMut(Vec<ImageInfo>),
+) -> Result<Vec<ImageInfo>> {
+    let mut batch = Vec::new();
+    let mut last_batch = std::time::Instant::now();
+    let mut collect = |image: &ImageInfo| {
+        batch.push(image.clone());
+        if batch.len() >= SCAN_BATCH_SIZE || last_batch.elapsed() >= SCAN_BATCH_INTERVAL {
+            on_batch(std::mem::take(&mut batch));
+            last_batch = std::time::Instant::now();
+        }
+    };
+    let images = scan_folders_visit(folders, &mut collect)?;
+    if !batch.is_empty() {
+        on_batch(batch);
+    }
+    Ok(images)
+}
+
+fn scan_folders_visit(
+    folders: &[PathBuf],
+    visit: &mut dyn FnMut(&ImageInfo),
+) -> Result<Vec<ImageInfo>> {
+    let options = ScanOptions::from_config();
+    let mut folders = folders.to_vec();
+    folders.sort();
+    folders.dedup();
+    let mut seen = BTreeSet::new();
+    folders.retain(|folder| seen.insert(std::fs::canonicalize(folder).unwrap_or(folder.clone())));
+    if folders.len() == 1 {
+        return scan_folder_visit(&folders[0], &options, &mut |image| visit(image));
+    }
+
+    let root = session_root(&folders);
+    let mut images = Vec::new();
+    for folder in &folders {
+        images.extend(scan_folder_visit(folder, &options, &mut |image| {
+            let path = folder.join(&image.filename);
+            image.filename = normalize_path(path.strip_prefix(&root).unwrap_or(&path));
+            visit(image);
+        })?);
+    }
+
+    images.sort_by(|a, b| {
+        a.modified_at
+            .cmp(&b.modified_at)
+            .then_with(|| a.filename.cmp(&b.filename))
+    });
+    Ok(images)
+}
+
+/// Session ID for a set of folders
+/// Independent of the order the folders were chosen in; a single folder keeps
+/// the ID it has always had so existing labels are found again
+pub fn generate_folders_session_id(folder_paths: &[String]) -> String {
+    let mut sorted = folder_paths.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    generate_session_id(&sorted.join("\n"))
+}
+
+/// Capture time of a file in milliseconds since the epoch, from its modification time
+/// Cameras stamp files with the shooting time, but FAT and exFAT cards keep it at
+/// two-second steps and copying or syncing may reset it
+pub fn capture_time_millis(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let datetime: chrono::DateTime<chrono::Utc> = modified.into();
+    Some(datetime.timestamp_millis())
+}
+
+/// Size and modification time (to the nanosecond) of an image, which change whenever the
+/// file is replaced; `modified_at` only has minutes
+pub fn file_stamp(image: &ImageInfo) -> String {
+    let stamp = std::fs::metadata(&image.path).ok().and_then(|metadata| {
+        let modified = metadata.modified().ok()?;
+        let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(format!("{}:{}", metadata.len(), since_epoch.as_nanos()))
+    });
+    stamp.unwrap_or_else(|| format!("{}:{}", image.size, image.modified_at))
+}
+
+/// Images whose EXIF or content fingerprint isn't cached for the file as it is now, as
+/// (image, whether its EXIF needs reading, its file stamp)
+/// EXIF is cached by modification time, fingerprints by file stamp, which takes a stat per file
+pub fn stale_exif_files(
+    images: Vec<ImageInfo>,
+    cached: &HashMap<String, (String, ExifInfo)>,
+    fingerprinted: &HashMap<String, (String, String)>,
+) -> Vec<(ImageInfo, bool, String)> {
+    images
+        .into_iter()
+        .filter_map(|image| {
+            let read_exif = cached
+                .get(&image.filename)
+                .is_none_or(|(modified, _)| modified != &image.modified_at);
+            let stamp = file_stamp(&image);
+            let fingerprint = fingerprinted
+                .get(&image.filename)
+                .is_none_or(|(stored, _)| stored != &stamp);
+            (read_exif || fingerprint).then_some((image, read_exif, stamp))
+        })
+        .collect()
+}
+
+/// Capture time in milliseconds since the epoch from DateTimeOriginal and
+/// SubSecTimeOriginal, taken as local time like the file times cameras write
+pub fn exif_capture_millis(exif: &ExifInfo) -> Option<i64> {
+    let taken = chrono::NaiveDateTime::parse_from_str(
+        exif.date_taken.as_deref()?,
+        crate::stats::EXIF_DATE_FORMAT,
+    )
+    .ok()?;
+    let seconds = taken
+        .and_local_timezone(chrono::Local)
+        .earliest()?
+        .timestamp_millis();
+    // "5" is half a second, "05" a twentieth
+    let millis = exif.sub_sec_time.as_deref().map_or(0, |digits| {
+        let digits: String = digits.trim().chars().chain("000".chars()).take(3).collect();
+        digits.parse::<i64>().unwrap_or(0)
+    });
+    Some(seconds + millis)
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SubfolderInfo {
     pub name: String,
@@ -243,10 +821,16 @@ const SUBFOLDER_SCAN_CAP: usize = 5000;
 /// Intentionally non-recursive and metadata-free so it stays cheap even when the target
 /// holds many subfolders. Subfolders with zero images are omitted.
 pub fn scan_subfolders(folder_path: &Path) -> Result<Vec<SubfolderInfo>> {
+    let options = ScanOptions::from_config();
+    let is_dir = |entry: &std::fs::DirEntry| {
+        options
+            .resolve(entry)
+            .is_ok_and(|m| m.is_some_and(|m| m.is_dir()))
+    };
     let mut subfolders = Vec::new();
 
     for entry in std::fs::read_dir(folder_path)?.flatten() {
-        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+        if !is_dir(&entry) {
             continue;
         }
         let path = entry.path();
@@ -257,15 +841,16 @@ pub fn scan_subfolders(folder_path: &Path) -> Result<Vec<SubfolderInfo>> {
 
         let mut image_count = 0usize;
         for child in children.flatten().take(SUBFOLDER_SCAN_CAP) {
-            if !child.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
-                continue;
-            }
             let child_path = child.path();
             let ext = child_path
                 .extension()
                 .and_then(|e| e.to_str())
                 .unwrap_or("");
-            if is_supported_image_extension(ext) {
+            if is_supported_image_extension(ext)
+                && options
+                    .resolve(&child)
+                    .is_ok_and(|m| m.is_some_and(|m| m.is_file()))
+            {
                 image_count += 1;
             }
         }
@@ -303,232 +888,1103 @@ pub fn generate_session_id(folder_path: &str) -> String {
     hex::encode(&result[..16])
 }
 
-/// Get cache directory path for thumbnails
-pub fn get_cache_dir(session_id: &str) -> Result<PathBuf> {
+/// Name of the folder created inside a user-chosen cache location
+/// Keeps cache clearing from touching anything else in that location
+pub const CUSTOM_CACHE_FOLDER: &str = "GlimpseCache";
+
+/// Get the root cache directory (parent of all session caches)
+pub fn get_cache_base_dir() -> Result<PathBuf> {
+    cache_base_dir_for(get_config().cache_dir.as_deref())
+}
+
+/// Resolve the root cache directory for a configured cache location
+pub fn cache_base_dir_for(custom_dir: Option<&str>) -> Result<PathBuf> {
+    if let Some(dir) = custom_dir {
+        return Ok(PathBuf::from(dir).join(CUSTOM_CACHE_FOLDER));
+    }
     let data_dir = dirs::data_dir()
         .ok_or_else(|| GlimpseError::InvalidPath("Cannot find data directory".into()))?;
-    let cache_dir = data_dir
-        .join("Glimpse")
-        .join("cache")
-        .join(session_id)
-        .join("thumbnails");
+    Ok(data_dir.join("Glimpse").join("cache"))
+}
+
+/// Get cache directory path for thumbnails
+pub fn get_cache_dir(session_id: &str) -> Result<PathBuf> {
+    let cache_dir = get_cache_base_dir()?.join(session_id).join("thumbnails");
     std::fs::create_dir_all(&cache_dir)?;
     Ok(cache_dir)
 }
 
 /// Get cache directory path for previews (larger images for detail view)
 pub fn get_preview_dir(session_id: &str) -> Result<PathBuf> {
-    let data_dir = dirs::data_dir()
-        .ok_or_else(|| GlimpseError::InvalidPath("Cannot find data directory".into()))?;
-    let preview_dir = data_dir
-        .join("Glimpse")
-        .join("cache")
-        .join(session_id)
-        .join("previews");
+    let preview_dir = get_cache_base_dir()?.join(session_id).join("previews");
     std::fs::create_dir_all(&preview_dir)?;
     Ok(preview_dir)
 }
 
-/// Generate thumbnail
-pub fn generate_thumbnail(image_path: &Path, output_path: &Path) -> Result<()> {
-    let extension = image_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase())
-        .unwrap_or_default();
+/// Parse a session's `last_opened` (RFC 3339, or SQLite's UTC `datetime('now')`)
+fn parse_last_opened(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").map(|t| t.and_utc())
+        })
+        .ok()
+}
 
-    let img = if is_raw_extension(&extension) {
-        load_raw_image(image_path)?
-    } else {
-        image::open(image_path)?
+/// Session cache folders under `cache_base` whose session wasn't opened since `cutoff`
+/// Folders of unknown sessions are judged by their modification time
+pub fn expired_session_dirs(
+    cache_base: &Path,
+    last_opened: &[(String, Option<String>)],
+    cutoff: DateTime<Utc>,
+) -> Vec<PathBuf> {
+    let last_opened: HashMap<&str, DateTime<Utc>> = last_opened
+        .iter()
+        .filter_map(|(id, opened)| Some((id.as_str(), parse_last_opened(opened.as_deref()?)?)))
+        .collect();
+
+    let Ok(entries) = std::fs::read_dir(cache_base) else {
+        return Vec::new();
     };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir() && entry.file_name() != SHARED_CACHE_FOLDER)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let opened = last_opened
+                .get(name.to_string_lossy().as_ref())
+                .copied()
+                .or_else(|| {
+                    let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                    Some(modified.into())
+                });
+            opened.is_some_and(|opened| opened < cutoff)
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
 
-    // Resize to thumbnail size
-    let thumbnail = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+/// File name stem for cache files of an image
+/// Keys of merged sessions contain folders, which are percent-encoded into the name so
+/// that distinct keys never share a cache file (mirrored by `toImageItem`)
+fn cache_stem(filename: &str) -> String {
+    let path = Path::new(filename);
+    let file_stem = path.file_stem().unwrap().to_string_lossy();
+    let stem = match path.parent().map(normalize_path) {
+        Some(parent) if !parent.is_empty() => format!("{}/{}", parent, file_stem),
+        _ => file_stem.to_string(),
+    };
+    stem.replace('%', "%25")
+        .replace('/', "%2F")
+        .replace(':', "%3A")
+}
 
-    // Save as JPEG format
-    thumbnail.save_with_format(output_path, ImageFormat::Jpeg)?;
+/// Cached thumbnail path for an image file (in the configured cache format)
+pub fn thumbnail_path_for(cache_dir: &Path, filename: &str) -> PathBuf {
+    let extension = get_cache_format().extension();
+    cache_dir.join(format!("{}.{}", cache_stem(filename), extension))
+}
 
-    Ok(())
+/// Low-resolution stand-in shown until the thumbnail of an image file is generated
+/// Always JPEG; made from the EXIF thumbnail, which is JPEG already
+pub fn instant_thumbnail_path_for(cache_dir: &Path, filename: &str) -> PathBuf {
+    cache_dir.join(format!("{}_instant.jpg", cache_stem(filename)))
 }
 
-/// Generate preview image (larger size for detail view)
-/// Only generates for RAW files since standard images can be displayed directly
-pub fn generate_preview(image_path: &Path, output_path: &Path) -> Result<()> {
-    let extension = image_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase())
-        .unwrap_or_default();
+/// Cached preview path for an image file (in the configured cache format)
+pub fn preview_path_for(preview_dir: &Path, filename: &str) -> PathBuf {
+    let extension = get_cache_format().extension();
+    preview_dir.join(format!("{}_preview.{}", cache_stem(filename), extension))
+}
 
-    // Only generate previews for RAW files
-    if !is_raw_extension(&extension) {
-        return Err(crate::error::GlimpseError::InvalidPath(
-            "Preview generation only needed for RAW files".into(),
-        ));
-    }
+/// Cached preview with the user's adjustments applied, replaced on every change
+pub fn adjusted_preview_path_for(preview_dir: &Path, filename: &str) -> PathBuf {
+    let extension = get_cache_format().extension();
+    preview_dir.join(format!("{}_adjusted.{}", cache_stem(filename), extension))
+}
 
-    let img = load_raw_image(image_path)?;
+/// Focus peaking overlay for the detail view
+/// Always PNG, since the overlay is transparent where nothing is in focus
+pub fn peaking_path_for(preview_dir: &Path, filename: &str) -> PathBuf {
+    preview_dir.join(format!("{}_peaking.png", cache_stem(filename)))
+}
 
-    // Resize to preview size (larger than thumbnail)
-    let preview = img.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+/// Exposure warning overlay for the detail view (PNG like the focus peaking overlay)
+pub fn clipping_mask_path_for(preview_dir: &Path, filename: &str) -> PathBuf {
+    preview_dir.join(format!("{}_clipping.png", cache_stem(filename)))
+}
 
-    // Save as high-quality JPEG
-    let mut output_file = std::fs::File::create(output_path)?;
-    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output_file, 90);
-    preview.write_with_encoder(encoder)?;
+/// Embedded camera preview shown until the RAW preview is developed
+/// Always JPEG, which is much faster to encode than AVIF
+pub fn embedded_preview_path_for(preview_dir: &Path, filename: &str) -> PathBuf {
+    preview_dir.join(format!("{}_embedded.jpg", cache_stem(filename)))
+}
 
+/// Delete the cached thumbnail and previews of one image of a session
+pub fn remove_image_cache(session_id: &str, filename: &str) -> Result<()> {
+    let session_dir = get_cache_base_dir()?.join(session_id);
+    let (cache_dir, preview_dir) = (session_dir.join("thumbnails"), session_dir.join("previews"));
+    for cache_file in [
+        thumbnail_path_for(&cache_dir, filename),
+        preview_path_for(&preview_dir, filename),
+        adjusted_preview_path_for(&preview_dir, filename),
+        embedded_preview_path_for(&preview_dir, filename),
+    ] {
+        match std::fs::remove_file(&cache_file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to remove {}: {}", cache_file.display(), e),
+        }
+    }
     Ok(())
 }
 
-/// Check if an extension is a RAW format (public version)
-pub fn is_raw_format(extension: &str) -> bool {
-    is_raw_extension(extension)
+/// Delete cache files written in `format` from every session under `cache_base`
+/// Used after the cache format changes; the new format is regenerated on demand
+pub fn remove_cache_files(cache_base: &Path, format: CacheFormat) -> usize {
+    let extension = format.extension();
+    walkdir::WalkDir::new(cache_base)
+        .min_depth(3)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            let in_cache_dir = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .is_some_and(|name| name == "thumbnails" || name == "previews");
+            let in_shared_dir = path
+                .parent()
+                .and_then(|p| p.parent())
+                .and_then(|p| p.file_name())
+                .is_some_and(|name| name == SHARED_CACHE_FOLDER);
+            (in_cache_dir || in_shared_dir)
+                && entry.file_type().is_file()
+                && entry.path().extension().is_some_and(|e| e == extension)
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
 }
 
-/// Load RAW image
-fn load_raw_image(path: &Path) -> Result<DynamicImage> {
-    let raw_image =
-        rawloader::decode_file(path).map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
-
-    // Process RAW data and convert to RGB image
-    let mut pipeline = imagepipe::Pipeline::new_from_source(imagepipe::ImageSource::Raw(raw_image))
-        .map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
-
-    let srgb_image = pipeline
-        .output_8bit(None)
-        .map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
-
-    let width = srgb_image.width;
-    let height = srgb_image.height;
-    let pixels = srgb_image.data;
-
-    let img = image::RgbImage::from_raw(width as u32, height as u32, pixels).ok_or_else(|| {
-        GlimpseError::RawProcessing("Failed to create image from raw data".into())
-    })?;
+/// Delete thumbnails (`folder` "thumbnails") or previews ("previews") made at a size that
+/// no longer applies, from every session under `cache_base`
+/// Shared entries are kept, since their names carry the size
+pub fn remove_sized_cache_files(cache_base: &Path, folder: &str) -> usize {
+    let Ok(entries) = std::fs::read_dir(cache_base) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir() && entry.file_name() != SHARED_CACHE_FOLDER)
+        .map(|entry| remove_sized_files_in(&entry.path().join(folder)))
+        .sum()
+}
 
-    Ok(DynamicImage::ImageRgb8(img))
+/// Delete the size-dependent files in one session's thumbnails or previews folder
+/// Instant thumbnails and embedded previews don't depend on the size and are kept
+pub fn remove_sized_files_in(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.ends_with("_instant.jpg") && !name.ends_with("_embedded.jpg")
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
 }
 
-/// Generate multiple thumbnails and previews in parallel
-/// Limit thread count to control CPU usage
-/// For RAW files, also generates a larger preview image for detail view
-pub fn generate_thumbnails_parallel<F>(
+/// Count thumbnails and previews that still need to be generated
+pub fn count_missing_cache(
     images: &[ImageInfo],
     cache_dir: &Path,
     preview_dir: &Path,
-    progress_callback: F,
-) -> Vec<ThumbnailResult>
+) -> (usize, usize) {
+    let thumbnails = images
+        .iter()
+        .filter(|image| !thumbnail_path_for(cache_dir, &image.filename).exists())
+        .count();
+    let previews = images
+        .iter()
+        .filter(|image| {
+            !preview_path_for(preview_dir, &image.filename).exists()
+                && needs_preview(Path::new(&image.path), &image.filename)
+        })
+        .count();
+    (thumbnails, previews)
+}
+
+/// Write through a temporary file next to `output_path` and rename it into place,
+/// so concurrent generators and the webview never see a half-written image
+fn write_atomically<F>(output_path: &Path, write: F) -> Result<()>
 where
-    F: Fn(usize, usize) + Sync + Send + 'static,
+    F: FnOnce(&Path) -> Result<()>,
 {
-    let total = images.len();
-    let (tx, rx) = mpsc::channel();
-
-    // Thread for progress reporting
-    std::thread::spawn(move || {
-        let mut completed = 0;
-        while rx.recv().is_ok() {
-            completed += 1;
-            progress_callback(completed, total);
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let temp_path = output_path.with_extension(format!(
+        "{}.tmp.jpg",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    match write(&temp_path) {
+        Ok(()) => std::fs::rename(&temp_path, output_path)
+            .map_err(|e| GlimpseError::io_at(output_path, e)),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
         }
-    });
+    }
+}
 
-    // Create custom thread pool with limited thread count
-    // RAW image processing (imagepipe) consumes large amounts of stack space,
-    // default 2MB may not be sufficient. Increased to 8MB.
-    let num_threads = get_thumbnail_thread_count();
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .stack_size(8 * 1024 * 1024) // 8MB stack per thread for RAW processing
-        .build()
-        .expect("Failed to create thread pool");
-
-    let cache_dir = cache_dir.to_path_buf();
-    let preview_dir = preview_dir.to_path_buf();
-    let results = pool.install(|| {
-        images
-            .par_iter()
-            .map(|image| {
-                let file_stem = Path::new(&image.filename)
-                    .file_stem()
-                    .unwrap()
-                    .to_string_lossy();
-                let thumbnail_filename = format!("{}.jpg", file_stem);
-                let thumbnail_path = cache_dir.join(&thumbnail_filename);
-
-                // Check if this is a RAW file
-                let extension = Path::new(&image.filename)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .map(|s| s.to_lowercase())
-                    .unwrap_or_default();
-                let is_raw = is_raw_extension(&extension);
-
-                // Preview path for RAW files
-                let preview_filename = format!("{}_preview.jpg", file_stem);
-                let preview_path_buf = preview_dir.join(&preview_filename);
-
-                // Generate thumbnail
-                let thumbnail_result = if thumbnail_path.exists() {
-                    Ok(())
-                } else {
-                    generate_thumbnail(Path::new(&image.path), &thumbnail_path)
-                };
-
-                // Generate preview for RAW files
-                let preview_path = if is_raw {
-                    if preview_path_buf.exists() {
-                        Some(normalize_path(&preview_path_buf))
-                    } else {
-                        match generate_preview(Path::new(&image.path), &preview_path_buf) {
-                            Ok(_) => Some(normalize_path(&preview_path_buf)),
-                            Err(e) => {
-                                eprintln!(
-                                    "Failed to generate preview for {}: {}",
-                                    image.filename, e
-                                );
-                                None
-                            }
-                        }
-                    }
-                } else {
-                    None
-                };
-
-                let result = match thumbnail_result {
-                    Ok(_) => ThumbnailResult {
-                        filename: image.filename.clone(),
-                        thumbnail_path: normalize_path(&thumbnail_path),
-                        preview_path,
-                        success: true,
-                        error: None,
-                    },
-                    Err(e) => ThumbnailResult {
-                        filename: image.filename.clone(),
-                        thumbnail_path: String::new(),
-                        preview_path: None,
-                        success: false,
-                        error: Some(e.to_string()),
-                    },
-                };
-
-                // Progress notification
-                let _ = tx.send(());
-
-                result
-            })
-            .collect()
-    });
+/// JPEG quality of cached thumbnails
+const THUMBNAIL_JPEG_QUALITY: u8 = 75;
+/// JPEG quality of cached previews
+const PREVIEW_JPEG_QUALITY: u8 = 90;
+/// AVIF encoder speed (1 slowest - 10 fastest) and quality (1 - 100)
+/// Quality 65 looks about like JPEG 90 at roughly half the size
+const AVIF_SPEED: u8 = 8;
+const AVIF_QUALITY: u8 = 65;
+
+/// Encode a cache image in `format`
+fn save_cache_image(
+    img: &DynamicImage,
+    path: &Path,
+    format: CacheFormat,
+    jpeg_quality: u8,
+) -> Result<()> {
+    let file = File::create(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        CacheFormat::Jpeg => {
+            img.write_with_encoder(JpegEncoder::new_with_quality(&mut writer, jpeg_quality))?
+        }
+        CacheFormat::Avif => {
+            let encoder =
+                AvifEncoder::new_with_speed_quality(&mut writer, AVIF_SPEED, AVIF_QUALITY);
+            match img {
+                DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => {
+                    img.write_with_encoder(encoder)?
+                }
+                _ => DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?,
+            }
+        }
+    }
+    writer.flush().map_err(|e| GlimpseError::io_at(path, e))
+}
 
-    results
+/// Write a video poster frame as a cache image
+/// ffmpeg writes JPEG; other cache formats are re-encoded from it
+fn write_poster_frame(
+    video_path: &Path,
+    output_path: &Path,
+    size: u32,
+    jpeg_quality: u8,
+) -> Result<()> {
+    let format = CacheFormat::from_path(output_path);
+    write_atomically(output_path, |path| {
+        video::extract_poster_frame(video_path, path, size)?;
+        if format != CacheFormat::Jpeg {
+            let frame = image::open(path)?;
+            save_cache_image(&frame, path, format, jpeg_quality)?;
+        }
+        Ok(())
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+/// Decode a RAW or standard image to sRGB
+/// Videos are decoded from a poster frame of `video_size`
+pub fn load_source_image(image_path: &Path, video_size: u32) -> Result<DynamicImage> {
+    let decoder = decoder_for(image_path);
+
+    if decoder == Decoder::Video {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let poster_path = std::env::temp_dir().join(format!(
+            "glimpse-poster-{}-{}.jpg",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = video::extract_poster_frame(image_path, &poster_path, video_size)
+            .and_then(|_| Ok(image::open(&poster_path)?));
+        let _ = std::fs::remove_file(&poster_path);
+        return result;
+    }
+
+    // RAW output from imagepipe is already sRGB
+    if decoder == Decoder::Raw {
+        return load_raw_image(image_path, None);
+    }
+    let (img, source_space) = load_standard_image(image_path)?;
+    Ok(color::to_srgb(img, &source_space))
+}
+
+/// Re-rendering of a RAW preview with exposure and white balance changed
+/// The original file is never touched; the default leaves the camera's rendering as it is
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct RawAdjustments {
+    /// Exposure change in stops
+    pub exposure: f32,
+    /// White balance color temperature in Kelvin; None keeps the white balance as shot
+    pub wb_temp: Option<f32>,
+    /// Green-magenta shift of the white balance (1.0 neutral); used with `wb_temp`
+    pub wb_tint: Option<f32>,
+    /// White balance mode of this image; None follows the session. `wb_temp` refines it
+    #[serde(default)]
+    pub white_balance: Option<WhiteBalance>,
+}
+
+impl RawAdjustments {
+    pub const EXPOSURE_RANGE: std::ops::RangeInclusive<f32> = -5.0..=5.0;
+    pub const TEMP_RANGE: std::ops::RangeInclusive<f32> = 2000.0..=12000.0;
+    pub const TINT_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let in_range = |value: Option<f32>, range: &std::ops::RangeInclusive<f32>| {
+            value.is_none_or(|value| range.contains(&value))
+        };
+        if !in_range(Some(self.exposure), &Self::EXPOSURE_RANGE)
+            || !in_range(self.wb_temp, &Self::TEMP_RANGE)
+            || !in_range(self.wb_tint, &Self::TINT_RANGE)
+        {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Adjustment out of range: {:?}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Render a RAW file's preview with `adjustments` applied
+/// Demosaicing happens at preview size, which keeps re-rendering while dragging a slider fast
+pub fn generate_adjusted_preview(
+    image_path: &Path,
+    output_path: &Path,
+    size: u32,
+    white_balance: WhiteBalance,
+    adjustments: &RawAdjustments,
+) -> Result<()> {
+    if decoder_for(image_path) != Decoder::Raw {
+        return Err(GlimpseError::RawProcessing(format!(
+            "Adjustments need a RAW file: {}",
+            image_path.display()
+        )));
+    }
+    let white_balance = adjustments.white_balance.unwrap_or(white_balance);
+    let img = load_raw_image_with(image_path, white_balance, adjustments, Some(size))?;
+    let preview = finish_raw_preview(img.thumbnail(size, size), image_path);
+
+    let format = CacheFormat::from_path(output_path);
+    write_atomically(output_path, |path| {
+        save_cache_image(&preview, path, format, PREVIEW_JPEG_QUALITY)
+    })
+}
+
+/// Generate thumbnail
+/// The cache format follows the extension of `output_path`
+pub fn generate_thumbnail(image_path: &Path, output_path: &Path, size: u32) -> Result<Rendition> {
+    let decoder = decoder_for(image_path);
+
+    if decoder == Decoder::Video {
+        write_poster_frame(image_path, output_path, size, THUMBNAIL_JPEG_QUALITY)?;
+        return Ok(Rendition::Decoded);
+    }
+
+    // RAW output from imagepipe is already sRGB
+    let (img, source_space, rendition) = if decoder == Decoder::Raw {
+        let decoded = load_raw_image(image_path, Some(size));
+        let (img, rendition) = or_fallback(image_path, decoded, Some(size))?;
+        (img, SourceSpace::Srgb, rendition)
+    } else {
+        let (img, source_space) = load_standard_image(image_path)?;
+        (img, source_space, Rendition::Decoded)
+    };
+
+    // Resize to thumbnail size, then convert the (much smaller) result to sRGB
+    let thumbnail = color::to_srgb(img.thumbnail(size, size), &source_space);
+
+    let format = CacheFormat::from_path(output_path);
+    write_atomically(output_path, |path| {
+        save_cache_image(&thumbnail, path, format, THUMBNAIL_JPEG_QUALITY)
+    })?;
+    Ok(rendition)
+}
+
+/// Fall back when the RAW data of `image_path` didn't decode: first to the external decoder
+/// if one is configured, then to the embedded preview
+fn or_fallback(
+    image_path: &Path,
+    decoded: Result<DynamicImage>,
+    max_size: Option<u32>,
+) -> Result<(DynamicImage, Rendition)> {
+    let e = match decoded {
+        Ok(img) => return Ok((img, Rendition::Decoded)),
+        Err(e) => e,
+    };
+
+    if let Some(binary) = get_external_raw_decoder() {
+        let options = DevelopOptions {
+            max_size,
+            ..Default::default()
+        };
+        match (raw_decoder::External { binary }).develop(image_path, &options) {
+            Ok(img) => return Ok((img, Rendition::Decoded)),
+            Err(external) => tracing::warn!("External decoder failed: {}", external),
+        }
+    }
+
+    match embedded::load_embedded_preview(image_path) {
+        Ok(img) => {
+            tracing::info!(
+                "Using the embedded preview of {}: {}",
+                image_path.display(),
+                e
+            );
+            Ok((img, Rendition::EmbeddedPreview))
+        }
+        // The decode error says more about what's wrong with the file
+        Err(_) => Err(e),
+    }
+}
+
+/// Write the embedded preview of a RAW file, scaled to fit `size`
+pub fn generate_embedded_preview(image_path: &Path, output_path: &Path, size: u32) -> Result<()> {
+    if decoder_for(image_path) != Decoder::Raw {
+        return Err(GlimpseError::RawProcessing(format!(
+            "Not a RAW file: {}",
+            image_path.display()
+        )));
+    }
+    let img = embedded::load_embedded_preview(image_path)?;
+    let preview = img.thumbnail(size, size);
+    write_atomically(output_path, |path| {
+        save_cache_image(&preview, path, CacheFormat::Jpeg, PREVIEW_JPEG_QUALITY)
+    })
+}
+
+/// Write the instant thumbnail of an image from its EXIF thumbnail
+/// Nothing is decoded but the thumbnail itself, so this takes a millisecond or two per file
+/// Cloud online-only files are left to the thumbnail job, which knows whether to download them
+pub fn generate_instant_thumbnail(image_path: &Path, output_path: &Path) -> Result<()> {
+    if is_cloud_placeholder(image_path) {
+        return Err(GlimpseError::ExifError(format!(
+            "Not downloaded for an instant thumbnail: {}",
+            image_path.display()
+        )));
+    }
+    let img = embedded::load_exif_thumbnail(image_path)?;
+    write_atomically(output_path, |path| {
+        save_cache_image(&img, path, CacheFormat::Jpeg, THUMBNAIL_JPEG_QUALITY)
+    })
+}
+
+/// Generate preview image (larger size for detail view)
+/// Standard images only need one when they are too large to display directly
+/// `white_balance` only applies to RAW files
+pub fn generate_preview(
+    image_path: &Path,
+    output_path: &Path,
+    size: u32,
+    white_balance: WhiteBalance,
+) -> Result<Rendition> {
+    let decoder = decoder_for(image_path);
+
+    // Videos get a poster frame
+    if decoder == Decoder::Video {
+        write_poster_frame(image_path, output_path, size, PREVIEW_JPEG_QUALITY)?;
+        return Ok(Rendition::Decoded);
+    }
+
+    let (img, source_space, rendition) = if decoder == Decoder::Raw {
+        let decoded = load_raw_image_with(
+            image_path,
+            white_balance,
+            &RawAdjustments::default(),
+            demosaic_size(size),
+        );
+        let (img, rendition) = or_fallback(image_path, decoded, demosaic_size(size))?;
+        (img, SourceSpace::Srgb, rendition)
+    } else {
+        let (img, source_space) = load_standard_image(image_path)?;
+        (img, source_space, Rendition::Decoded)
+    };
+
+    // Resize to preview size (larger than thumbnail)
+    let mut preview = color::to_srgb(img.thumbnail(size, size), &source_space);
+    // Embedded previews come from the camera, which corrects the lenses it knows itself
+    if decoder == Decoder::Raw && rendition == Rendition::Decoded {
+        preview = finish_raw_preview(preview, image_path);
+    }
+
+    let format = CacheFormat::from_path(output_path);
+    write_atomically(output_path, |path| {
+        save_cache_image(&preview, path, format, PREVIEW_JPEG_QUALITY)
+    })?;
+    Ok(rendition)
+}
+
+/// Overlays computed from the image shown in the detail view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    FocusPeaking,
+    Clipping,
+}
+
+impl Overlay {
+    /// Cached overlay path for an image file
+    pub fn path_for(self, preview_dir: &Path, filename: &str) -> PathBuf {
+        match self {
+            Overlay::FocusPeaking => peaking_path_for(preview_dir, filename),
+            Overlay::Clipping => clipping_mask_path_for(preview_dir, filename),
+        }
+    }
+}
+
+/// The image shown in the detail view, decoded
+/// `preview` is the cached preview where the image has one; it is only read when its cache
+/// format can be decoded here (AVIF is encode-only in this build)
+pub fn load_displayed_image(
+    image_path: &Path,
+    preview: Option<&Path>,
+    size: u32,
+) -> Result<DynamicImage> {
+    Ok(match preview {
+        Some(preview) if CacheFormat::from_path(preview) != CacheFormat::Avif => {
+            image::open(preview)?
+        }
+        Some(_) => load_source_image(image_path, size)?.thumbnail(size, size),
+        None => load_source_image(image_path, size)?,
+    })
+}
+
+/// Write an overlay of an image, sized like the image shown in the detail view
+pub fn generate_overlay(
+    overlay: Overlay,
+    image_path: &Path,
+    preview: Option<&Path>,
+    output_path: &Path,
+    size: u32,
+) -> Result<()> {
+    let img = load_displayed_image(image_path, preview, size)?;
+    let overlay = match overlay {
+        Overlay::FocusPeaking => crate::analysis::focus_peaking(&img.to_luma8()),
+        Overlay::Clipping => crate::analysis::clipping_mask(&img.to_rgb8()),
+    };
+
+    write_atomically(output_path, |path| {
+        Ok(overlay.save_with_format(path, image::ImageFormat::Png)?)
+    })
+}
+
+/// Corrections of a developed RAW preview turned on in the settings: chroma noise reduction
+/// of high-ISO frames, then lens correction
+fn finish_raw_preview(img: DynamicImage, image_path: &Path) -> DynamicImage {
+    let denoise_iso = get_preview_denoise_iso();
+    let lens_correction = get_lens_correction();
+    if denoise_iso.is_none() && !lens_correction {
+        return img;
+    }
+    let Ok(exif) = extract_exif(image_path) else {
+        return img;
+    };
+
+    let mut img = img;
+    if let Some(threshold) = denoise_iso {
+        if denoise::iso(&exif).is_some_and(|iso| iso >= threshold) {
+            img = denoise::reduce_chroma_noise(&img.to_rgb8()).into();
+        }
+    }
+    if lens_correction {
+        img = lens::correct_preview(img, &exif);
+    }
+    img
+}
+
+/// Make sure the preview of an image exists, generating it if needed
+/// Returns None for files that are displayed directly (JPEG/PNG of moderate size)
+pub fn ensure_preview(
+    image_path: &Path,
+    filename: &str,
+    preview_dir: &Path,
+    size: u32,
+    white_balance: WhiteBalance,
+) -> Result<Option<PathBuf>> {
+    if !needs_preview(image_path, filename) {
+        return Ok(None);
+    }
+
+    let preview_path = preview_path_for(preview_dir, filename);
+    if !preview_path.exists() {
+        // Previews developed with another white balance are cached apart
+        let mut variant = match white_balance {
+            WhiteBalance::AsShot => format!("p{}", size),
+            mode => format!("p{}-{}", size, mode.as_str()),
+        };
+        variant.push_str(demosaic_suffix(image_path));
+        if decoder_for(image_path) == Decoder::Raw {
+            if let Some(iso) = get_preview_denoise_iso() {
+                variant.push_str(&format!("-nr{}", iso));
+            }
+            if get_lens_correction() {
+                variant.push_str("-lens");
+            }
+        }
+        content_cache::reuse_or_generate(image_path, &preview_path, &variant, || {
+            generate_preview(image_path, &preview_path, size, white_balance).map(|_| ())
+        })?;
+    }
+    Ok(Some(preview_path))
+}
+
+/// Check if an extension is a RAW format (public version)
+pub fn is_raw_format(extension: &str) -> bool {
+    is_raw_extension(extension)
+}
+
+/// Check if an extension is a video format (public version)
+pub fn is_video_format(extension: &str) -> bool {
+    is_video_extension(extension)
+}
+
+/// Load a JPEG/PNG along with the color space it was encoded in
+fn load_standard_image(path: &Path) -> Result<(DynamicImage, SourceSpace)> {
+    let mut decoder = ImageReader::open(path)
+        .map_err(|e| GlimpseError::io_at(path, e))?
+        .with_guessed_format()
+        .map_err(|e| GlimpseError::io_at(path, e))?
+        .into_decoder()?;
+
+    let source_space = match decoder.icc_profile().ok().flatten() {
+        Some(icc) => SourceSpace::Icc(icc),
+        None if is_exif_adobe_rgb(path) => SourceSpace::AdobeRgb,
+        None => SourceSpace::Srgb,
+    };
+
+    Ok((DynamicImage::from_decoder(decoder)?, source_space))
+}
+
+/// Whether EXIF marks the file as AdobeRGB (DCF interoperability index "R03")
+/// Cameras set this instead of embedding a profile when shooting in AdobeRGB mode
+fn is_exif_adobe_rgb(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let Ok(exif) = Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return false;
+    };
+    exif.get_field(Tag::InteroperabilityIndex, In::PRIMARY)
+        .is_some_and(|field| match &field.value {
+            exif::Value::Ascii(values) => values.iter().any(|v| v.starts_with(b"R03")),
+            _ => false,
+        })
+}
+
+/// Size to develop a RAW file at for `size` pixel output; None demosaics at full resolution
+fn demosaic_size(size: u32) -> Option<u32> {
+    match get_raw_demosaic() {
+        RawDemosaic::HighQuality => None,
+        RawDemosaic::Fast => Some(size),
+    }
+}
+
+/// Cache variant suffix of RAW files developed with the fast demosaic
+fn demosaic_suffix(image_path: &Path) -> &'static str {
+    if decoder_for(image_path) == Decoder::Raw && get_raw_demosaic() == RawDemosaic::Fast {
+        "-fast"
+    } else {
+        ""
+    }
+}
+
+/// Load RAW image, for output of `size` pixels when given (see `demosaic_size`)
+fn load_raw_image(path: &Path, size: Option<u32>) -> Result<DynamicImage> {
+    load_raw_image_with(
+        path,
+        WhiteBalance::AsShot,
+        &RawAdjustments::default(),
+        size.and_then(demosaic_size),
+    )
+}
+
+/// Load RAW image with a white balance mode and adjustments, optionally developed at no
+/// more than `max_size` pixels, with the configured backend
+fn load_raw_image_with(
+    path: &Path,
+    white_balance: WhiteBalance,
+    adjustments: &RawAdjustments,
+    max_size: Option<u32>,
+) -> Result<DynamicImage> {
+    let options = DevelopOptions {
+        white_balance,
+        adjustments: *adjustments,
+        max_size,
+    };
+    raw_decoder::backend(get_raw_backend()).develop(path, &options)
+}
+
+/// Pending work of a thumbnail generation run, handed out nearest-to-center first
+/// so the part of the grid the user is looking at fills in before the rest
+pub struct GenerationQueue {
+    pending: Mutex<BTreeSet<usize>>,
+    center: AtomicUsize,
+}
+
+impl GenerationQueue {
+    pub fn new(len: usize, center: usize) -> Self {
+        Self {
+            pending: Mutex::new((0..len).collect()),
+            center: AtomicUsize::new(center),
+        }
+    }
+
+    /// Move the generation focus (e.g. when the grid scrolls)
+    pub fn set_center(&self, center: usize) {
+        self.center.store(center, Ordering::Relaxed);
+    }
+
+    /// Number of images not yet picked up
+    pub fn remaining(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Take the pending index closest to the center
+    /// On ties the later index wins, since users mostly scroll forward
+    fn next(&self) -> Option<usize> {
+        self.next_where(|_| true)
+    }
+
+    /// Take the pending index closest to the center among those `accept` allows
+    fn next_where(&self, accept: impl Fn(usize) -> bool) -> Option<usize> {
+        let mut pending = self.pending.lock().unwrap();
+        let center = self.center.load(Ordering::Relaxed);
+
+        let after = pending.range(center..).copied().find(|&i| accept(i));
+        let before = pending.range(..center).rev().copied().find(|&i| accept(i));
+        let index = match (before, after) {
+            (Some(b), Some(a)) => {
+                if center - b < a - center {
+                    b
+                } else {
+                    a
+                }
+            }
+            (b, a) => a.or(b)?,
+        };
+
+        pending.remove(&index);
+        Some(index)
+    }
+}
+
+/// Rough peak memory of one RAW decode (a 45MP sensor demosaiced to float RGB)
+const RAW_DECODE_BYTES: u64 = 600 * 1024 * 1024;
+/// RAM left to the OS, the webview and the rest of the app
+const MEMORY_RESERVE_BYTES: u64 = 1024 * 1024 * 1024;
+/// How often a waiting worker rechecks available memory
+const MEMORY_RECHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Limits how many RAW files decode at once so a run doesn't exhaust RAM
+/// Decodes are capped at `max`, and another starts only while available memory covers it;
+/// one always may, so generation keeps moving on any machine
+pub struct RawDecodeGate {
+    active: Mutex<usize>,
+    released: Condvar,
+    max: usize,
+}
+
+/// Held while a RAW file decodes
+pub struct RawDecodePermit<'a>(&'a RawDecodeGate);
+
+impl Drop for RawDecodePermit<'_> {
+    fn drop(&mut self) {
+        *self.0.active.lock().unwrap() -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+impl RawDecodeGate {
+    pub fn new(max: usize) -> Self {
+        Self {
+            active: Mutex::new(0),
+            released: Condvar::new(),
+            max: max.max(1),
+        }
+    }
+
+    /// Start a RAW decode if one fits right now
+    pub fn try_acquire(&self) -> Option<RawDecodePermit<'_>> {
+        let mut active = self.active.lock().unwrap();
+        if !can_start_raw_decode(*active, self.max, memory::available_memory()) {
+            return None;
+        }
+        *active += 1;
+        Some(RawDecodePermit(self))
+    }
+
+    /// Wait until another RAW decode fits
+    pub fn acquire(&self) -> RawDecodePermit<'_> {
+        let mut active = self.active.lock().unwrap();
+        while !can_start_raw_decode(*active, self.max, memory::available_memory()) {
+            active = self
+                .released
+                .wait_timeout(active, MEMORY_RECHECK_INTERVAL)
+                .unwrap()
+                .0;
+        }
+        *active += 1;
+        RawDecodePermit(self)
+    }
+}
+
+/// Whether one more RAW decode may start next to `active` running ones
+/// Running decodes may not have allocated their buffers yet, so their share is reserved too
+fn can_start_raw_decode(active: usize, max: usize, available: Option<u64>) -> bool {
+    if active == 0 {
+        return true;
+    }
+    if active >= max {
+        return false;
+    }
+    available.is_none_or(|available| {
+        available >= MEMORY_RESERVE_BYTES + RAW_DECODE_BYTES * (active as u64 + 1)
+    })
+}
+
+/// Generate the thumbnail (and preview for RAW files and videos) of one image
+pub fn process_image(
+    image: &ImageInfo,
+    cache_dir: &Path,
+    preview_dir: &Path,
+    thumbnail_size: u32,
+    preview_size: u32,
+    white_balance: WhiteBalance,
+) -> ThumbnailResult {
+    let thumbnail_path = thumbnail_path_for(cache_dir, &image.filename);
+    let image_path = Path::new(&image.path);
+
+    // Online-only cloud files are skipped unless configured to download them
+    if !thumbnail_path.exists()
+        && get_placeholder_policy() == PlaceholderPolicy::Skip
+        && is_cloud_placeholder(image_path)
+    {
+        return ThumbnailResult {
+            filename: image.filename.clone(),
+            thumbnail_path: String::new(),
+            preview_path: None,
+            success: false,
+            error: Some("File is online-only and has not been downloaded".to_string()),
+            placeholder: true,
+            corrupt: false,
+            embedded_preview: false,
+        };
+    }
+
+    // Generate thumbnail
+    // Thumbnails of the same file cached by another session are reused
+    let rendition = std::cell::Cell::new(Rendition::Decoded);
+    let thumbnail_result = if thumbnail_path.exists() {
+        Ok(())
+    } else {
+        content_cache::reuse_or_generate(
+            image_path,
+            &thumbnail_path,
+            &format!("t{}{}", thumbnail_size, demosaic_suffix(image_path)),
+            || {
+                rendition.set(generate_thumbnail(
+                    image_path,
+                    &thumbnail_path,
+                    thumbnail_size,
+                )?);
+                Ok(())
+            },
+        )
+    };
+
+    // Generate preview for RAW files and videos
+    let preview_path = match ensure_preview(
+        image_path,
+        &image.filename,
+        preview_dir,
+        preview_size,
+        white_balance,
+    ) {
+        Ok(path) => path.map(|p| normalize_path(&p)),
+        Err(e) => {
+            tracing::warn!("Failed to generate preview for {}: {}", image.path, e);
+            None
+        }
+    };
+
+    match thumbnail_result {
+        Ok(_) => ThumbnailResult {
+            filename: image.filename.clone(),
+            thumbnail_path: normalize_path(&thumbnail_path),
+            preview_path,
+            success: true,
+            error: None,
+            placeholder: false,
+            corrupt: false,
+            embedded_preview: rendition.get() == Rendition::EmbeddedPreview,
+        },
+        Err(e) => {
+            tracing::warn!("Failed to generate thumbnail for {}: {}", image.path, e);
+            ThumbnailResult {
+                filename: image.filename.clone(),
+                thumbnail_path: String::new(),
+                preview_path: None,
+                success: false,
+                error: Some(e.to_string()),
+                placeholder: false,
+                corrupt: e.is_corrupt_data(),
+                embedded_preview: false,
+            }
+        }
+    }
+}
+
+/// Create a thread pool for image processing with a limited thread count
+/// RAW image processing (imagepipe) consumes large amounts of stack space,
+/// default 2MB may not be sufficient. Increased to 8MB.
+pub fn build_thread_pool(num_threads: usize) -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .stack_size(RAW_STACK_SIZE) // 8MB stack per thread for RAW processing
+        .build()
+        .expect("Failed to create thread pool")
+}
+
+/// Stack size of threads that decode RAW files (see `build_thread_pool`)
+const RAW_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Run `f` on a thread with a stack large enough for RAW decoding and wait for it
+/// A panic of `f` is returned as a RAW processing error
+pub fn run_with_raw_stack<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    spawn_with_raw_stack(f)?
+        .join()
+        .map_err(|_| GlimpseError::RawProcessing("RAW processing panicked".into()))?
+}
+
+/// Start `f` on a thread with a stack large enough for RAW decoding, for background work
+pub fn spawn_with_raw_stack<T, F>(f: F) -> Result<std::thread::JoinHandle<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    Ok(std::thread::Builder::new()
+        .stack_size(RAW_STACK_SIZE)
+        .spawn(f)?)
+}
+
+/// Generate multiple thumbnails and previews in parallel
+/// Limit thread count to control CPU usage
+/// For RAW files, also generates a larger preview image for detail view
+/// Images are processed in the order handed out by `queue`; results keep the order of `images`
+/// Pausing `task` holds the workers, cancelling it stops them and returns the results so far
+pub fn generate_thumbnails_parallel<F>(
+    images: &[ImageInfo],
+    cache_dir: &Path,
+    preview_dir: &Path,
+    config: &AppConfig,
+    queue: &GenerationQueue,
+    task: &TaskHandle,
+    progress_callback: F,
+) -> Vec<ThumbnailResult>
+where
+    F: Fn(usize, usize) + Sync + Send + 'static,
+{
+    let total = images.len();
+    let (tx, rx) = mpsc::channel();
+
+    // Thread for progress reporting
+    std::thread::spawn(move || {
+        let mut completed = 0;
+        while rx.recv().is_ok() {
+            completed += 1;
+            progress_callback(completed, total);
+        }
+    });
+
+    // The EXIF job of the opened folder runs alongside on the rest of the thread budget
+    let (num_threads, _) = split_exif_threads(get_thumbnail_thread_count());
+    let thumbnail_size = config.thumbnail_size;
+    let preview_size = config.preview_size;
+    let pool = build_thread_pool(num_threads);
+    // RAW decodes get fewer slots than the pool so JPEG/PNG work keeps the other threads busy
+    let raw_gate = RawDecodeGate::new(get_raw_decode_thread_count());
+    let is_raw: Vec<bool> = images
+        .iter()
+        .map(|image| is_raw_extension(&lowercase_extension(&image.filename)))
+        .collect();
+
+    // Each worker keeps pulling the most urgent image until the queue is drained
+    let results = Mutex::new(Vec::with_capacity(total));
+    let completed = AtomicUsize::new(0);
+    pool.scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|_| {
+                while task.checkpoint() {
+                    // Without a free RAW slot, take the nearest non-RAW image instead of idling
+                    let (index, _permit) = match raw_gate.try_acquire() {
+                        Some(permit) => {
+                            let Some(index) = queue.next() else {
+                                break;
+                            };
+                            (index, is_raw[index].then_some(permit))
+                        }
+                        None => match queue.next_where(|i| !is_raw[i]) {
+                            Some(index) => (index, None),
+                            None if queue.remaining() == 0 => break,
+                            None => {
+                                let permit = raw_gate.acquire();
+                                let Some(index) = queue.next() else {
+                                    break;
+                                };
+                                (index, is_raw[index].then_some(permit))
+                            }
+                        },
+                    };
+                    let result = process_image(
+                        &images[index],
+                        cache_dir,
+                        preview_dir,
+                        thumbnail_size,
+                        preview_size,
+                        config.raw_white_balance,
+                    );
+
+                    // Progress notification
+                    task.set_progress(completed.fetch_add(1, Ordering::Relaxed) + 1);
+                    let _ = tx.send(());
+
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{TaskKind, TaskManager};
+    use std::fs;
+    use tempfile::tempdir;
 
     #[test]
     fn test_generate_session_id() {
@@ -546,6 +2002,28 @@ mod tests {
         assert_eq!(session_id1.len(), 32);
     }
 
+    #[test]
+    fn test_run_with_raw_stack() {
+        assert_eq!(run_with_raw_stack(|| Ok(42)).unwrap(), 42);
+        let panicked = run_with_raw_stack(|| -> Result<()> { panic!("decoder bug") });
+        assert!(matches!(panicked, Err(GlimpseError::RawProcessing(_))));
+    }
+
+    #[test]
+    fn test_exif_capture_millis() {
+        let exif = |sub_sec: Option<&str>| ExifInfo {
+            date_taken: Some("2024-05-01 18:30:12".to_string()),
+            sub_sec_time: sub_sec.map(str::to_string),
+            ..Default::default()
+        };
+        let base = exif_capture_millis(&exif(None)).unwrap();
+        assert_eq!(exif_capture_millis(&exif(Some("5"))), Some(base + 500));
+        assert_eq!(exif_capture_millis(&exif(Some("05"))), Some(base + 50));
+        assert_eq!(exif_capture_millis(&exif(Some("1234"))), Some(base + 123));
+        assert_eq!(exif_capture_millis(&exif(Some("5  "))), Some(base + 500));
+        assert_eq!(exif_capture_millis(&ExifInfo::default()), None);
+    }
+
     #[test]
     fn test_scan_folder_empty() {
         let dir = tempdir().unwrap();
@@ -607,6 +2085,646 @@ mod tests {
         assert_eq!(result[0].filename, "image.jpg");
     }
 
+    #[test]
+    fn test_scan_folder_skips_hidden_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("DSC_0001.JPG"), b"fake jpg").unwrap();
+        fs::write(dir.path().join("._DSC_0001.JPG"), b"resource fork").unwrap();
+        fs::write(dir.path().join(".hidden.jpg"), b"fake jpg").unwrap();
+        fs::create_dir(dir.path().join("@eaDir")).unwrap();
+        fs::write(
+            dir.path().join("@eaDir").join("SYNOPHOTO_THUMB_M.jpg"),
+            b"thumb",
+        )
+        .unwrap();
+
+        let options = |include_hidden| ScanOptions {
+            symlinks: SymlinkPolicy::Follow,
+            include_hidden,
+            exclude: Vec::new(),
+        };
+        let images = scan_folder_with(dir.path(), &options(false)).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].filename, "DSC_0001.JPG");
+
+        let images = scan_folder_with(dir.path(), &options(true)).unwrap();
+        assert_eq!(images.len(), 3);
+    }
+
+    #[test]
+    fn test_is_readable_extra_format() {
+        let dir = tempdir().unwrap();
+        let tiff_raw = dir.path().join("frame.nrw");
+        fs::write(&tiff_raw, b"II*\0\x08\0\0\0 rest of the file").unwrap();
+        let video = dir.path().join("clip.nrw");
+        fs::write(&video, b"\0\0\0\x18ftypmp42").unwrap();
+        let png = dir.path().join("image.webp");
+        DynamicImage::new_rgb8(2, 2)
+            .save_with_format(&png, image::ImageFormat::Png)
+            .unwrap();
+
+        assert!(is_readable_extra_format(&tiff_raw, true));
+        assert!(!is_readable_extra_format(&video, true));
+        // Still images are sniffed by content, whatever the extension says
+        assert!(is_readable_extra_format(&png, false));
+        assert!(!is_readable_extra_format(&video, false));
+        assert!(!is_readable_extra_format(
+            &dir.path().join("missing.nrw"),
+            true
+        ));
+    }
+
+    #[test]
+    fn test_content_detection_for_missing_extensions() {
+        let dir = tempdir().unwrap();
+        let recovered = dir.path().join("IMG_0001");
+        DynamicImage::new_rgb8(4, 4)
+            .save_with_format(&recovered, image::ImageFormat::Jpeg)
+            .unwrap();
+        fs::write(dir.path().join("README"), b"not an image").unwrap();
+        // A PNG misnamed as a RAW file
+        let misnamed = dir.path().join("DSC_0002.NEF");
+        DynamicImage::new_rgb8(4, 4)
+            .save_with_format(&misnamed, image::ImageFormat::Png)
+            .unwrap();
+
+        let images = scan_folder(dir.path()).unwrap();
+        let names: Vec<_> = images.iter().map(|i| i.filename.as_str()).collect();
+        assert_eq!(names, vec!["DSC_0002.NEF", "IMG_0001"]);
+
+        assert_eq!(decoder_for(&recovered), Decoder::Standard);
+        assert_eq!(decoder_for(&misnamed), Decoder::Standard);
+        let thumbnail = dir.path().join("thumb.jpg");
+        generate_thumbnail(&misnamed, &thumbnail, 2).unwrap();
+        generate_thumbnail(&recovered, &thumbnail, 2).unwrap();
+        assert!(needs_preview_above(&recovered, "IMG_0001", None));
+    }
+
+    #[test]
+    fn test_scan_folder_exclude_patterns() {
+        let dir = tempdir().unwrap();
+        let rejects = dir.path().join("client_rejects");
+        fs::create_dir(&rejects).unwrap();
+        fs::write(dir.path().join("keep.jpg"), b"fake jpg").unwrap();
+        fs::write(dir.path().join("upload.TMP.jpg"), b"fake jpg").unwrap();
+        fs::write(rejects.join("rejected.jpg"), b"fake jpg").unwrap();
+
+        let options = ScanOptions {
+            symlinks: SymlinkPolicy::Follow,
+            include_hidden: false,
+            exclude: ["*.tmp.jpg", "*_rejects/*"]
+                .iter()
+                .map(|p| glob::Pattern::new(p).unwrap())
+                .collect(),
+        };
+        let images = scan_folder_with(dir.path(), &options).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].filename, "keep.jpg");
+
+        // Opening the excluded folder itself doesn't bring its files back
+        assert!(scan_folder_with(&rejects, &options).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_folder_symlink_policy() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        fs::write(dir.path().join("a.jpg"), b"fake jpg").unwrap();
+        fs::write(outside.path().join("linked.jpg"), b"linked jpg data").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("linked.jpg"), dir.path().join("b.jpg"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone.jpg"), dir.path().join("c.jpg")).unwrap();
+
+        let follow = ScanOptions {
+            symlinks: SymlinkPolicy::Follow,
+            include_hidden: false,
+            exclude: Vec::new(),
+        };
+        let followed = scan_folder_with(dir.path(), &follow).unwrap();
+        let names: Vec<_> = followed.iter().map(|i| i.filename.as_str()).collect();
+        assert_eq!(names, vec!["a.jpg", "b.jpg"]);
+        // Size of the target, not of the link
+        assert_eq!(followed[1].size, 15);
+
+        let ignore = ScanOptions {
+            symlinks: SymlinkPolicy::Ignore,
+            include_hidden: false,
+            exclude: Vec::new(),
+        };
+        let ignored = scan_folder_with(dir.path(), &ignore).unwrap();
+        assert_eq!(ignored.len(), 1);
+        assert_eq!(ignored[0].filename, "a.jpg");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_folders_skips_linked_duplicates() {
+        let dir = tempdir().unwrap();
+        let card = dir.path().join("card");
+        fs::create_dir(&card).unwrap();
+        fs::write(card.join("a.jpg"), b"fake jpg").unwrap();
+        std::os::unix::fs::symlink(&card, dir.path().join("alias")).unwrap();
+
+        let images = scan_folders(&[card.clone(), dir.path().join("alias")]).unwrap();
+        assert_eq!(images.len(), 1);
+    }
+
+    #[test]
+    fn test_scan_folder_includes_videos() {
+        let dir = tempdir().unwrap();
+
+        fs::write(dir.path().join("clip.MP4"), b"fake mp4").unwrap();
+        fs::write(dir.path().join("photo.jpg"), b"fake jpg").unwrap();
+        fs::write(dir.path().join("take.mov"), b"fake mov").unwrap();
+
+        let result = scan_folder(dir.path()).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert!(result[0].is_video);
+        assert!(!result[1].is_video);
+        assert!(result[2].is_video);
+        assert!(needs_preview(&dir.path().join("take.mov"), "take.mov"));
+        assert!(needs_preview(
+            &dir.path().join("DSC_0001.NEF"),
+            "DSC_0001.NEF"
+        ));
+        assert!(!needs_preview(&dir.path().join("photo.jpg"), "photo.jpg"));
+    }
+
+    #[test]
+    fn test_long_path_prefixes() {
+        assert_eq!(
+            normalize_path(Path::new(r"\\?\C:\Photos\a.jpg")),
+            "C:/Photos/a.jpg"
+        );
+        assert_eq!(
+            normalize_path(Path::new(r"\\?\UNC\nas\share\a.jpg")),
+            "//nas/share/a.jpg"
+        );
+        assert_eq!(
+            verbatim_path("C:/Photos/./2024/../a.jpg").as_deref(),
+            Some(r"\\?\C:\Photos\a.jpg")
+        );
+        assert_eq!(
+            verbatim_path(r"\\nas\share\a.jpg").as_deref(),
+            Some(r"\\?\UNC\nas\share\a.jpg")
+        );
+        assert_eq!(verbatim_path(r"\\?\C:\a.jpg"), None);
+        assert_eq!(verbatim_path("relative/a.jpg"), None);
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("image_preview.jpg");
+
+        write_atomically(&output, |path| {
+            assert_ne!(path, output.as_path());
+            fs::write(path, b"data")?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"data");
+
+        // Failed writes leave nothing behind
+        let failed = dir.path().join("failed_preview.jpg");
+        let result = write_atomically(&failed, |path| {
+            fs::write(path, b"partial")?;
+            Err(GlimpseError::RawProcessing("decode failed".into()))
+        });
+        assert!(result.is_err());
+        assert!(!failed.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_generate_thumbnail_in_cache_format() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source.png");
+        image::RgbImage::from_pixel(64, 32, image::Rgb([200, 120, 40]))
+            .save(&source)
+            .unwrap();
+
+        let jpeg = dir.path().join("source.jpg");
+        generate_thumbnail(&source, &jpeg, 16).unwrap();
+        assert!(fs::read(&jpeg).unwrap().starts_with(&[0xff, 0xd8]));
+
+        let avif = dir.path().join("source.avif");
+        generate_thumbnail(&source, &avif, 16).unwrap();
+        assert_eq!(&fs::read(&avif).unwrap()[4..12], b"ftypavif");
+    }
+
+    #[test]
+    fn test_remove_cache_files() {
+        let dir = tempdir().unwrap();
+        let thumbnails = dir.path().join("session").join("thumbnails");
+        let previews = dir.path().join("session").join("previews");
+        fs::create_dir_all(&thumbnails).unwrap();
+        fs::create_dir_all(&previews).unwrap();
+        fs::write(thumbnails.join("a.jpg"), b"old").unwrap();
+        fs::write(previews.join("a_preview.jpg"), b"old").unwrap();
+        fs::write(thumbnails.join("b.avif"), b"new").unwrap();
+        let shared = dir.path().join(SHARED_CACHE_FOLDER).join("ab");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("abcd_t320.jpg"), b"old").unwrap();
+        // Files outside the cache folders are left alone
+        fs::write(dir.path().join("session").join("notes.jpg"), b"keep").unwrap();
+
+        assert_eq!(remove_cache_files(dir.path(), CacheFormat::Jpeg), 3);
+        assert!(thumbnails.join("b.avif").exists());
+        assert!(dir.path().join("session").join("notes.jpg").exists());
+        assert!(!previews.join("a_preview.jpg").exists());
+    }
+
+    #[test]
+    fn test_stale_exif_files() {
+        let dir = tempdir().unwrap();
+        let images: Vec<ImageInfo> = ["a.jpg", "b.jpg", "c.jpg"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                fs::write(&path, name.as_bytes()).unwrap();
+                ImageInfo {
+                    filename: name.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    size: 5,
+                    modified_at: "2024/12/15 14:32".to_string(),
+                    is_video: false,
+                }
+            })
+            .collect();
+        let cached_exif = || ("2024/12/15 14:32".to_string(), ExifInfo::default());
+        let fingerprint = |image: &ImageInfo| (file_stamp(image), "f".to_string());
+
+        // a is cached and unchanged; b's EXIF is older than the file; c has no fingerprint
+        let cached = HashMap::from([
+            ("a.jpg".to_string(), cached_exif()),
+            (
+                "b.jpg".to_string(),
+                ("2024/12/15 14:00".to_string(), ExifInfo::default()),
+            ),
+            ("c.jpg".to_string(), cached_exif()),
+        ]);
+        let fingerprinted = HashMap::from([
+            ("a.jpg".to_string(), fingerprint(&images[0])),
+            ("b.jpg".to_string(), fingerprint(&images[1])),
+        ]);
+        let stale: Vec<(String, bool)> = stale_exif_files(images.clone(), &cached, &fingerprinted)
+            .into_iter()
+            .map(|(image, read_exif, _)| (image.filename, read_exif))
+            .collect();
+        assert_eq!(
+            stale,
+            [("b.jpg".to_string(), true), ("c.jpg".to_string(), false)]
+        );
+
+        // A replaced file gets a new stamp even within the same minute
+        fs::write(&images[0].path, b"replaced").unwrap();
+        let stale = stale_exif_files(images[..1].to_vec(), &cached, &fingerprinted);
+        assert_eq!(stale.len(), 1);
+        assert!(!stale[0].1);
+    }
+
+    #[test]
+    fn test_remove_sized_cache_files() {
+        let dir = tempdir().unwrap();
+        let thumbnails = dir.path().join("session").join("thumbnails");
+        let previews = dir.path().join("session").join("previews");
+        fs::create_dir_all(&thumbnails).unwrap();
+        fs::create_dir_all(&previews).unwrap();
+        fs::write(thumbnails.join("a.jpg"), b"old size").unwrap();
+        fs::write(thumbnails.join("a_instant.jpg"), b"exif").unwrap();
+        fs::write(previews.join("a_preview.jpg"), b"keep").unwrap();
+        let shared = dir.path().join(SHARED_CACHE_FOLDER).join("thumbnails");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("abcd_t320.jpg"), b"sized by name").unwrap();
+
+        assert_eq!(remove_sized_cache_files(dir.path(), "thumbnails"), 1);
+        assert!(!thumbnails.join("a.jpg").exists());
+        assert!(thumbnails.join("a_instant.jpg").exists());
+        assert!(previews.join("a_preview.jpg").exists());
+        assert!(shared.join("abcd_t320.jpg").exists());
+
+        fs::write(previews.join("a_embedded.jpg"), b"camera").unwrap();
+        assert_eq!(remove_sized_files_in(&previews), 1);
+        assert!(previews.join("a_embedded.jpg").exists());
+        assert_eq!(remove_sized_files_in(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_ensure_preview_skips_standard_images() {
+        let dir = tempdir().unwrap();
+        let image = dir.path().join("photo.jpg");
+        fs::write(&image, b"fake jpg").unwrap();
+
+        let result =
+            ensure_preview(&image, "photo.jpg", dir.path(), 2000, WhiteBalance::AsShot).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_large_standard_images_get_preview() {
+        let dir = tempdir().unwrap();
+        let image = dir.path().join("pano.png");
+        image::RgbImage::from_pixel(400, 100, image::Rgb([10, 20, 30]))
+            .save(&image)
+            .unwrap();
+
+        assert!(needs_preview_above(&image, "pano.png", Some(30_000)));
+        assert!(!needs_preview_above(&image, "pano.png", Some(40_000)));
+        assert!(!needs_preview_above(&image, "pano.png", None));
+
+        let preview = dir.path().join("pano_preview.jpg");
+        generate_preview(&image, &preview, 200, WhiteBalance::AsShot).unwrap();
+        assert_eq!(image::image_dimensions(&preview).unwrap(), (200, 50));
+    }
+
+    #[test]
+    fn test_undecodable_raw_uses_embedded_preview() {
+        let dir = tempdir().unwrap();
+        let jpeg = dir.path().join("embedded.jpg");
+        image::RgbImage::from_pixel(400, 300, image::Rgb([200, 100, 50]))
+            .save(&jpeg)
+            .unwrap();
+        // Not real sensor data, but the camera's JPEG is in there
+        let raw = dir.path().join("DSC_0001.NEF");
+        let mut data = b"II*\0\x08\0\0\0".to_vec();
+        data.extend(fs::read(&jpeg).unwrap());
+        fs::write(&raw, data).unwrap();
+
+        let thumbnail = dir.path().join("thumb.jpg");
+        assert_eq!(
+            generate_thumbnail(&raw, &thumbnail, 100).unwrap(),
+            Rendition::EmbeddedPreview
+        );
+        assert_eq!(image::image_dimensions(&thumbnail).unwrap(), (100, 75));
+
+        let preview = embedded_preview_path_for(dir.path(), "DSC_0001.NEF");
+        generate_embedded_preview(&raw, &preview, 200).unwrap();
+        assert_eq!(image::image_dimensions(&preview).unwrap(), (200, 150));
+        assert!(generate_embedded_preview(&jpeg, &preview, 200).is_err());
+    }
+
+    #[test]
+    fn test_generation_queue_order() {
+        let queue = GenerationQueue::new(6, 2);
+
+        // Nearest first, later index wins ties
+        assert_eq!(queue.next(), Some(2));
+        assert_eq!(queue.next(), Some(3));
+        assert_eq!(queue.next(), Some(1));
+
+        // Moving the center redirects the remaining work
+        queue.set_center(5);
+        assert_eq!(queue.next(), Some(5));
+        assert_eq!(queue.next(), Some(4));
+        assert_eq!(queue.next(), Some(0));
+        assert_eq!(queue.next(), None);
+        assert_eq!(queue.remaining(), 0);
+    }
+
+    #[test]
+    fn test_can_start_raw_decode() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        // The first decode always runs, even when memory is short
+        assert!(can_start_raw_decode(0, 8, Some(0)));
+        assert!(can_start_raw_decode(1, 8, Some(8 * GIB)));
+        assert!(!can_start_raw_decode(1, 8, Some(2 * GIB)));
+        assert!(!can_start_raw_decode(8, 8, Some(64 * GIB)));
+        // Unknown memory leaves only the cap
+        assert!(can_start_raw_decode(3, 8, None));
+    }
+
+    #[test]
+    fn test_generation_queue_next_where() {
+        let queue = GenerationQueue::new(6, 2);
+        // Images 2 and 3 are RAW and wait for a free decode slot
+        let not_raw = |i: usize| i != 2 && i != 3;
+        assert_eq!(queue.next_where(not_raw), Some(1));
+        assert_eq!(queue.next_where(not_raw), Some(4));
+        assert_eq!(queue.next(), Some(2));
+        assert_eq!(queue.remaining(), 3);
+    }
+
+    #[test]
+    fn test_raw_decode_gate_cap() {
+        let gate = RawDecodeGate::new(1);
+        let permit = gate.try_acquire();
+        assert!(permit.is_some());
+        assert!(gate.try_acquire().is_none());
+        drop(permit);
+        assert!(gate.try_acquire().is_some());
+    }
+
+    #[test]
+    fn test_generation_queue_center_out_of_range() {
+        let queue = GenerationQueue::new(3, 100);
+        assert_eq!(queue.next(), Some(2));
+        assert_eq!(queue.next(), Some(1));
+        assert_eq!(queue.next(), Some(0));
+        assert_eq!(queue.next(), None);
+    }
+
+    #[test]
+    fn test_generate_thumbnails_parallel_keeps_order() {
+        let dir = tempdir().unwrap();
+        let cache_dir = dir.path().join("thumbnails");
+        let preview_dir = dir.path().join("previews");
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::create_dir_all(&preview_dir).unwrap();
+
+        for i in 0..5 {
+            image::RgbImage::new(8, 8)
+                .save(dir.path().join(format!("img{}.png", i)))
+                .unwrap();
+        }
+        let images = scan_folder(dir.path()).unwrap();
+        let queue = GenerationQueue::new(images.len(), 3);
+        let tasks = TaskManager::new();
+        let task = tasks.start(TaskKind::Thumbnails, images.len());
+
+        let results = generate_thumbnails_parallel(
+            &images,
+            &cache_dir,
+            &preview_dir,
+            &AppConfig::default(),
+            &queue,
+            &task,
+            |_, _| {},
+        );
+
+        assert_eq!(results.len(), 5);
+        for (image, result) in images.iter().zip(&results) {
+            assert_eq!(image.filename, result.filename);
+            assert!(result.success);
+        }
+    }
+
+    #[test]
+    fn test_scan_folders_streaming() {
+        let dir = tempdir().unwrap();
+        let card1 = dir.path().join("CARD1");
+        let card2 = dir.path().join("CARD2");
+        fs::create_dir_all(&card1).unwrap();
+        fs::create_dir_all(&card2).unwrap();
+        for i in 0..SCAN_BATCH_SIZE + 5 {
+            fs::write(card1.join(format!("DSC_{:04}.JPG", i)), b"a").unwrap();
+        }
+        fs::write(card2.join("DSC_0001.JPG"), b"b").unwrap();
+
+        let mut batches = Vec::new();
+        let images = scan_folders_streaming(&[card1, card2], |batch| batches.push(batch)).unwrap();
+        assert!(batches.len() >= 2);
+        assert!(batches.iter().all(|batch| batch.len() <= SCAN_BATCH_SIZE));
+
+        // Streamed images carry their final keys
+        let mut streamed: Vec<String> = batches.concat().into_iter().map(|i| i.filename).collect();
+        let mut keys: Vec<String> = images.into_iter().map(|i| i.filename).collect();
+        streamed.sort();
+        keys.sort();
+        assert_eq!(streamed, keys);
+        assert!(keys.contains(&"CARD2/DSC_0001.JPG".to_string()));
+    }
+
+    #[test]
+    fn test_session_root() {
+        let card1 = PathBuf::from("/Volumes/CARD1/DCIM/100NIKON");
+        let card2 = PathBuf::from("/Volumes/CARD2/DCIM/100NIKON");
+
+        assert_eq!(session_root(std::slice::from_ref(&card1)), card1);
+        assert_eq!(
+            session_root(&[card1.clone(), card2]),
+            PathBuf::from("/Volumes")
+        );
+        assert_eq!(
+            session_root(&[card1.clone(), PathBuf::from("/Volumes/CARD1")]),
+            PathBuf::from("/Volumes/CARD1")
+        );
+        assert_eq!(
+            session_root(&[card1, PathBuf::from("relative")]),
+            PathBuf::new()
+        );
+    }
+
+    #[test]
+    fn test_scan_folders_keeps_duplicate_names_apart() {
+        let dir = tempdir().unwrap();
+        let card1 = dir.path().join("CARD1");
+        let card2 = dir.path().join("CARD2");
+        fs::create_dir_all(&card1).unwrap();
+        fs::create_dir_all(&card2).unwrap();
+        fs::write(card1.join("DSC_0001.JPG"), b"a").unwrap();
+        fs::write(card2.join("DSC_0001.JPG"), b"b").unwrap();
+        fs::write(card2.join("DSC_0002.JPG"), b"c").unwrap();
+
+        let images = scan_folders(&[card1.clone(), card2.clone()]).unwrap();
+        let mut keys: Vec<&str> = images.iter().map(|i| i.filename.as_str()).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "CARD1/DSC_0001.JPG",
+                "CARD2/DSC_0001.JPG",
+                "CARD2/DSC_0002.JPG"
+            ]
+        );
+
+        // Keys resolve back to the files through the session root
+        let root = session_root(&[card1.clone(), card2]);
+        for image in &images {
+            assert_eq!(normalize_path(&root.join(&image.filename)), image.path);
+        }
+
+        // A single folder keeps plain file names
+        let images = scan_folders(&[card1]).unwrap();
+        assert_eq!(images[0].filename, "DSC_0001.JPG");
+    }
+
+    #[test]
+    fn test_generate_folders_session_id() {
+        let a = "/Volumes/CARD1".to_string();
+        let b = "/Volumes/CARD2".to_string();
+
+        assert_eq!(
+            generate_folders_session_id(&[a.clone(), b.clone()]),
+            generate_folders_session_id(&[b.clone(), a.clone()])
+        );
+        assert_eq!(
+            generate_folders_session_id(std::slice::from_ref(&a)),
+            generate_session_id(&a)
+        );
+        assert_ne!(
+            generate_folders_session_id(&[a.clone(), b]),
+            generate_session_id(&a)
+        );
+    }
+
+    #[test]
+    fn test_cache_paths_of_merged_keys() {
+        let dir = Path::new("/cache");
+        assert_eq!(
+            thumbnail_path_for(dir, "DSC_0001.NEF"),
+            dir.join("DSC_0001.jpg")
+        );
+        assert_ne!(
+            thumbnail_path_for(dir, "CARD1/DSC_0001.NEF"),
+            thumbnail_path_for(dir, "CARD2/DSC_0001.NEF")
+        );
+        assert_eq!(
+            preview_path_for(dir, "CARD1/DCIM/DSC_0001.NEF"),
+            dir.join("CARD1%2FDCIM%2FDSC_0001_preview.jpg")
+        );
+        assert_ne!(
+            thumbnail_path_for(dir, "A_B/C.NEF"),
+            thumbnail_path_for(dir, "A/B_C.NEF")
+        );
+        assert_ne!(
+            thumbnail_path_for(dir, "A/B.NEF"),
+            thumbnail_path_for(dir, "A%2FB.NEF")
+        );
+    }
+
+    #[test]
+    fn test_raw_adjustments_validate() {
+        assert!(RawAdjustments::default().is_default());
+        let brighter = RawAdjustments {
+            exposure: 1.5,
+            wb_temp: Some(5500.0),
+            wb_tint: Some(1.0),
+            white_balance: None,
+        };
+        assert!(brighter.validate().is_ok());
+        assert!(!brighter.is_default());
+        for invalid in [
+            RawAdjustments {
+                exposure: 8.0,
+                ..RawAdjustments::default()
+            },
+            RawAdjustments {
+                wb_temp: Some(500.0),
+                ..RawAdjustments::default()
+            },
+            RawAdjustments {
+                wb_tint: Some(0.0),
+                ..RawAdjustments::default()
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+        // JPEGs have no RAW data to develop again
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = dir.path().join("a.jpg");
+        assert!(generate_adjusted_preview(
+            &jpeg,
+            &dir.path().join("out.jpg"),
+            100,
+            WhiteBalance::AsShot,
+            &brighter
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_get_cache_dir() {
         let session_id = "test_session_123";
@@ -620,6 +2738,83 @@ mod tests {
         assert!(cache_dir.to_string_lossy().contains("thumbnails"));
     }
 
+    #[test]
+    fn test_expired_session_dirs() {
+        let dir = tempdir().unwrap();
+        for name in [
+            "old",
+            "recent",
+            "sqlite_old",
+            "unknown",
+            SHARED_CACHE_FOLDER,
+        ] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        let last_opened = vec![
+            (
+                "old".to_string(),
+                Some("2024-01-01T10:00:00+09:00".to_string()),
+            ),
+            (
+                "recent".to_string(),
+                Some("2024-06-01T10:00:00+09:00".to_string()),
+            ),
+            (
+                "sqlite_old".to_string(),
+                Some("2024-02-01 08:00:00".to_string()),
+            ),
+        ];
+        let cutoff = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let mut expired = expired_session_dirs(dir.path(), &last_opened, cutoff);
+        expired.sort();
+        // "unknown" was just created, so its modification time is recent
+        assert_eq!(
+            expired,
+            vec![dir.path().join("old"), dir.path().join("sqlite_old")]
+        );
+    }
+
+    #[test]
+    fn test_cache_base_dir_for_custom_location() {
+        let base = cache_base_dir_for(Some("/Volumes/Scratch")).unwrap();
+        assert_eq!(
+            base,
+            PathBuf::from("/Volumes/Scratch").join(CUSTOM_CACHE_FOLDER)
+        );
+
+        let default = cache_base_dir_for(None).unwrap();
+        assert!(default.ends_with(Path::new("Glimpse").join("cache")));
+    }
+
+    #[test]
+    fn test_count_missing_cache() {
+        let dir = tempdir().unwrap();
+        let cache_dir = dir.path().join("thumbnails");
+        let preview_dir = dir.path().join("previews");
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::create_dir_all(&preview_dir).unwrap();
+
+        fs::write(dir.path().join("a.jpg"), b"fake jpg").unwrap();
+        fs::write(dir.path().join("b.NEF"), b"fake nef").unwrap();
+        fs::write(dir.path().join("c.NEF"), b"fake nef").unwrap();
+        let images = scan_folder(dir.path()).unwrap();
+
+        assert_eq!(
+            count_missing_cache(&images, &cache_dir, &preview_dir),
+            (3, 2)
+        );
+
+        fs::write(thumbnail_path_for(&cache_dir, "a.jpg"), b"thumb").unwrap();
+        fs::write(preview_path_for(&preview_dir, "b.NEF"), b"preview").unwrap();
+        assert_eq!(
+            count_missing_cache(&images, &cache_dir, &preview_dir),
+            (2, 1)
+        );
+    }
+
     #[test]
     fn test_image_info_has_correct_fields() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,223 @@
+// Passwords kept in the OS keychain (macOS Keychain, Secret Service on Linux, Windows
+// Credential Manager) instead of the config file
+
+use crate::error::Result;
+
+/// Service the passwords are stored under
+const SERVICE: &str = "Glimpse";
+
+/// Store the password of `account`, replacing an older one
+pub fn set_password(account: &str, password: &str) -> Result<()> {
+    platform::set_password(account, password)
+}
+
+/// Password of `account`; None when none was stored
+pub fn get_password(account: &str) -> Result<Option<String>> {
+    platform::get_password(account)
+}
+
+/// Forget the password of `account`; nothing happens if none was stored
+pub fn delete_password(account: &str) -> Result<()> {
+    platform::delete_password(account)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::SERVICE;
+    use crate::error::{GlimpseError, Result};
+    use security_framework::passwords;
+
+    /// errSecItemNotFound
+    const NOT_FOUND: i32 = -25300;
+
+    fn failure(e: security_framework::base::Error) -> GlimpseError {
+        GlimpseError::Keychain(e.to_string())
+    }
+
+    // The Security framework is called directly: `security add-generic-password -w` would
+    // put the password on a command line other users can read
+    pub fn set_password(account: &str, password: &str) -> Result<()> {
+        passwords::set_generic_password(SERVICE, account, password.as_bytes()).map_err(failure)
+    }
+
+    pub fn get_password(account: &str) -> Result<Option<String>> {
+        match passwords::get_generic_password(SERVICE, account) {
+            Ok(password) => Ok(Some(String::from_utf8_lossy(&password).to_string())),
+            Err(e) if e.code() == NOT_FOUND => Ok(None),
+            Err(e) => Err(failure(e)),
+        }
+    }
+
+    pub fn delete_password(account: &str) -> Result<()> {
+        match passwords::delete_generic_password(SERVICE, account) {
+            Err(e) if e.code() != NOT_FOUND => Err(failure(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::SERVICE;
+    use crate::error::{GlimpseError, Result};
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    /// secret-tool (libsecret) talks to whichever Secret Service runs (GNOME Keyring, KWallet)
+    fn secret_tool(args: &[&str], input: Option<&str>) -> Result<Output> {
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| GlimpseError::Keychain(format!("secret-tool is not available: {}", e)))?;
+        if let Some(input) = input {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            stdin
+                .write_all(input.as_bytes())
+                .map_err(|e| GlimpseError::Keychain(e.to_string()))?;
+        }
+        child
+            .wait_with_output()
+            .map_err(|e| GlimpseError::Keychain(e.to_string()))
+    }
+
+    pub fn set_password(account: &str, password: &str) -> Result<()> {
+        let label = format!("{} ({})", SERVICE, account);
+        let output = secret_tool(
+            &[
+                "store", "--label", &label, "service", SERVICE, "account", account,
+            ],
+            Some(password),
+        )?;
+        if !output.status.success() {
+            return Err(GlimpseError::Keychain(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn get_password(account: &str) -> Result<Option<String>> {
+        let output = secret_tool(&["lookup", "service", SERVICE, "account", account], None)?;
+        // lookup fails without output when nothing is stored
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if !stderr.is_empty() {
+                return Err(GlimpseError::Keychain(stderr));
+            }
+            if output.stdout.is_empty() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+
+    pub fn delete_password(account: &str) -> Result<()> {
+        secret_tool(&["clear", "service", SERVICE, "account", account], None)?;
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::SERVICE;
+    use crate::error::{GlimpseError, Result};
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE,
+        CRED_TYPE_GENERIC,
+    };
+
+    /// ERROR_NOT_FOUND
+    const NOT_FOUND: i32 = 1168;
+
+    fn target_name(account: &str) -> Vec<u16> {
+        format!("{}:{}", SERVICE, account)
+            .encode_utf16()
+            .chain(Some(0))
+            .collect()
+    }
+
+    fn last_error() -> GlimpseError {
+        GlimpseError::Keychain(std::io::Error::last_os_error().to_string())
+    }
+
+    pub fn set_password(account: &str, password: &str) -> Result<()> {
+        let mut target = target_name(account);
+        let mut user: Vec<u16> = account.encode_utf16().chain(Some(0)).collect();
+        let mut blob = password.as_bytes().to_vec();
+        // SAFETY: every pointer refers to a live buffer for the duration of the call;
+        // fields left zeroed are optional
+        let ok = unsafe {
+            let mut credential: CREDENTIALW = std::mem::zeroed();
+            credential.Type = CRED_TYPE_GENERIC;
+            credential.TargetName = target.as_mut_ptr();
+            credential.UserName = user.as_mut_ptr();
+            credential.CredentialBlobSize = blob.len() as u32;
+            credential.CredentialBlob = blob.as_mut_ptr();
+            credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+            CredWriteW(&credential, 0)
+        };
+        if ok == 0 {
+            return Err(last_error());
+        }
+        Ok(())
+    }
+
+    pub fn get_password(account: &str) -> Result<Option<String>> {
+        let target = target_name(account);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+        // SAFETY: target is NUL-terminated; the credential CredReadW allocates is read
+        // before CredFree releases it
+        unsafe {
+            if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                let error = std::io::Error::last_os_error();
+                if error.raw_os_error() == Some(NOT_FOUND) {
+                    return Ok(None);
+                }
+                return Err(GlimpseError::Keychain(error.to_string()));
+            }
+            let blob = std::slice::from_raw_parts(
+                (*credential).CredentialBlob,
+                (*credential).CredentialBlobSize as usize,
+            );
+            let password = String::from_utf8_lossy(blob).to_string();
+            CredFree(credential as *const _);
+            Ok(Some(password))
+        }
+    }
+
+    pub fn delete_password(account: &str) -> Result<()> {
+        let target = target_name(account);
+        // SAFETY: target is NUL-terminated
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            let error = std::io::Error::last_os_error();
+            if error.raw_os_error() != Some(NOT_FOUND) {
+                return Err(GlimpseError::Keychain(error.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+mod platform {
+    use crate::error::{GlimpseError, Result};
+
+    fn unsupported() -> GlimpseError {
+        GlimpseError::Keychain("No keychain on this platform".to_string())
+    }
+
+    pub fn set_password(_account: &str, _password: &str) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn get_password(_account: &str) -> Result<Option<String>> {
+        Err(unsupported())
+    }
+
+    pub fn delete_password(_account: &str) -> Result<()> {
+        Err(unsupported())
+    }
+}
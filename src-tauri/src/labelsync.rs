@@ -0,0 +1,241 @@
+// Label sync through a shared folder (Dropbox, NAS) without a server
+// Every machine appends its label changes to its own journal file in the folder, so sync
+// clients never have to merge concurrent writes; on open the journals of all machines are
+// replayed and the newest change of each file wins
+
+use crate::error::{GlimpseError, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const JOURNAL_PREFIX: &str = "glimpse-labels-";
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+/// One label change as written to a journal
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct JournalEntry {
+    pub filename: String,
+    /// None when the label was removed
+    pub label: Option<String>,
+    /// RFC3339 time of the change
+    pub changed_at: String,
+    pub machine: String,
+}
+
+impl JournalEntry {
+    /// A change made on this machine just now
+    pub fn now(filename: &str, label: Option<&str>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            label: label.map(str::to_string),
+            changed_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            machine: machine_id().to_string(),
+        }
+    }
+
+    fn changed_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.changed_at)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Stable identifier of this machine, kept in the app data directory
+pub fn machine_id() -> &'static str {
+    static MACHINE_ID: OnceLock<String> = OnceLock::new();
+    MACHINE_ID.get_or_init(|| {
+        let path = dirs::data_dir().map(|dir| dir.join("Glimpse").join("machine-id"));
+        if let Some(id) = path.as_ref().and_then(|p| std::fs::read_to_string(p).ok()) {
+            return id.trim().to_string();
+        }
+
+        use sha2::{Digest, Sha256};
+        let seed = format!(
+            "{:?}{}{:?}",
+            Utc::now().timestamp_nanos_opt(),
+            std::process::id(),
+            dirs::home_dir()
+        );
+        let id = hex::encode(&Sha256::digest(seed.as_bytes())[..6]);
+        if let Some(path) = path {
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(&path, &id);
+        }
+        id
+    })
+}
+
+fn journal_path(sync_dir: &Path, machine: &str) -> PathBuf {
+    sync_dir.join(format!(
+        "{}{}.{}",
+        JOURNAL_PREFIX, machine, JOURNAL_EXTENSION
+    ))
+}
+
+/// Append a change to this machine's journal in `sync_dir`
+pub fn append(sync_dir: &Path, entry: &JournalEntry) -> Result<()> {
+    let path = journal_path(sync_dir, &entry.machine);
+    let line =
+        serde_json::to_string(entry).map_err(|e| GlimpseError::InvalidConfig(e.to_string()))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| GlimpseError::io_at(&path, e))?;
+    writeln!(file, "{}", line).map_err(|e| GlimpseError::io_at(&path, e))
+}
+
+/// All changes in the journals of every machine in `sync_dir`
+/// Lines a sync client left half-written are skipped
+pub fn read_journals(sync_dir: &Path) -> Result<Vec<JournalEntry>> {
+    let entries = std::fs::read_dir(sync_dir).map_err(|e| GlimpseError::io_at(sync_dir, e))?;
+    let mut changes = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(JOURNAL_PREFIX) || !name.ends_with(JOURNAL_EXTENSION) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        changes.extend(
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok()),
+        );
+    }
+    Ok(changes)
+}
+
+/// Label changes from other machines to apply locally
+/// `local` holds when each labeled file was last changed here ("YYYY-MM-DD HH:MM:SS" UTC,
+/// as SQLite stores it). A file takes the newest journal change of any machine, unless that
+/// change came from this machine or the local label is newer still
+pub fn remote_changes(
+    journal: &[JournalEntry],
+    local: &HashMap<String, String>,
+    machine: &str,
+) -> Vec<(String, Option<String>)> {
+    let mut latest: HashMap<&str, (DateTime<Utc>, &JournalEntry)> = HashMap::new();
+    for entry in journal {
+        let Some(changed_at) = entry.changed_at() else {
+            continue;
+        };
+        match latest.get(entry.filename.as_str()) {
+            Some((time, _)) if *time >= changed_at => {}
+            _ => {
+                latest.insert(&entry.filename, (changed_at, entry));
+            }
+        }
+    }
+
+    let mut changes: Vec<(String, Option<String>)> = latest
+        .into_values()
+        .filter(|(changed_at, entry)| {
+            if entry.machine == machine {
+                return false;
+            }
+            let local_time = local.get(&entry.filename).and_then(|time| {
+                NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+                    .ok()
+                    .map(|time| time.and_utc())
+            });
+            local_time.is_none_or(|local_time| local_time < *changed_at)
+        })
+        .map(|(_, entry)| (entry.filename.clone(), entry.label.clone()))
+        .collect();
+    changes.sort();
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(filename: &str, label: Option<&str>, changed_at: &str, machine: &str) -> JournalEntry {
+        JournalEntry {
+            filename: filename.to_string(),
+            label: label.map(str::to_string),
+            changed_at: changed_at.to_string(),
+            machine: machine.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let dir = tempdir().unwrap();
+        let first = entry(
+            "a.NEF",
+            Some("adopted"),
+            "2024-12-15T05:00:00.000Z",
+            "studio",
+        );
+        let second = entry("b.NEF", None, "2024-12-15T05:01:00.000Z", "laptop");
+        append(dir.path(), &first).unwrap();
+        append(dir.path(), &second).unwrap();
+        // Half-synced line and unrelated files are ignored
+        std::fs::write(dir.path().join("glimpse-labels-broken.jsonl"), "{\"filena").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+
+        let mut journal = read_journals(dir.path()).unwrap();
+        journal.sort_by(|a, b| a.filename.cmp(&b.filename));
+        assert_eq!(journal, vec![first, second]);
+    }
+
+    #[test]
+    fn test_remote_changes() {
+        let journal = vec![
+            entry(
+                "a.NEF",
+                Some("adopted"),
+                "2024-12-15T05:00:00.000Z",
+                "laptop",
+            ),
+            entry(
+                "a.NEF",
+                Some("rejected"),
+                "2024-12-15T06:00:00.000Z",
+                "studio",
+            ),
+            // Removed here after the other machine labeled it
+            entry(
+                "b.NEF",
+                Some("adopted"),
+                "2024-12-15T05:00:00.000Z",
+                "studio",
+            ),
+            entry("b.NEF", None, "2024-12-15T05:30:00.000Z", "laptop"),
+            entry(
+                "c.NEF",
+                Some("adopted"),
+                "2024-12-15T05:00:00.000Z",
+                "studio",
+            ),
+            entry(
+                "d.NEF",
+                Some("adopted"),
+                "2024-12-15T05:00:00.000Z",
+                "studio",
+            ),
+        ];
+        let local = HashMap::from([
+            ("a.NEF".to_string(), "2024-12-15 05:00:00".to_string()),
+            // Relabeled here after the journal entry
+            ("c.NEF".to_string(), "2024-12-15 07:00:00".to_string()),
+        ]);
+
+        assert_eq!(
+            remote_changes(&journal, &local, "laptop"),
+            vec![
+                ("a.NEF".to_string(), Some("rejected".to_string())),
+                ("d.NEF".to_string(), Some("adopted".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,643 @@
+// Lens distortion and vignetting correction of RAW previews, from lensfun profiles
+// Wide-angle frames barrel and darken towards the corners until the editor corrects them;
+// previews corrected the same way show the framing the photo will end up with
+
+use crate::config;
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::ExifInfo;
+use image::{DynamicImage, Rgb, RgbImage};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where lensfun installs its database, tried in order when no folder is configured
+const LENSFUN_DIRS: &[&str] = &[
+    "/usr/share/lensfun/version_1",
+    "/usr/local/share/lensfun/version_1",
+    "/opt/homebrew/share/lensfun/version_1",
+    "/opt/local/share/lensfun/version_1",
+];
+
+/// Loaded database and the folder it was read from
+static DATABASE: Mutex<Option<(Option<PathBuf>, Arc<LensDatabase>)>> = Mutex::new(None);
+
+/// How a lens bends straight lines; `r` is the distance from the center, 1.0 at half the
+/// shorter side. Each model maps a corrected radius to where it lies in the photo as shot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distortion {
+    Poly3 { k1: f32 },
+    Poly5 { k1: f32, k2: f32 },
+    PtLens { a: f32, b: f32, c: f32 },
+}
+
+impl Distortion {
+    fn distorted_radius(&self, r: f32) -> f32 {
+        match *self {
+            Distortion::Poly3 { k1 } => r * (1.0 - k1 + k1 * r * r),
+            Distortion::Poly5 { k1, k2 } => r * (1.0 + k1 * r * r + k2 * r.powi(4)),
+            Distortion::PtLens { a, b, c } => {
+                r * (a * r.powi(3) + b * r * r + c * r + 1.0 - a - b - c)
+            }
+        }
+    }
+
+    /// `self` and `other` blended by `t` (0.0 is `self`); models that differ aren't blended
+    fn lerp(&self, other: &Distortion, t: f32) -> Distortion {
+        let mix = |x: f32, y: f32| x + (y - x) * t;
+        match (*self, *other) {
+            (Distortion::Poly3 { k1 }, Distortion::Poly3 { k1: k1b }) => {
+                Distortion::Poly3 { k1: mix(k1, k1b) }
+            }
+            (Distortion::Poly5 { k1, k2 }, Distortion::Poly5 { k1: k1b, k2: k2b }) => {
+                Distortion::Poly5 {
+                    k1: mix(k1, k1b),
+                    k2: mix(k2, k2b),
+                }
+            }
+            (
+                Distortion::PtLens { a, b, c },
+                Distortion::PtLens {
+                    a: ab,
+                    b: bb,
+                    c: cb,
+                },
+            ) => Distortion::PtLens {
+                a: mix(a, ab),
+                b: mix(b, bb),
+                c: mix(c, cb),
+            },
+            _ if t < 0.5 => *self,
+            _ => *other,
+        }
+    }
+}
+
+/// Light falloff of the "pa" model: 1 + k1·r² + k2·r⁴ + k3·r⁶, `r` 1.0 at the corners
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vignetting {
+    focal: f32,
+    aperture: f32,
+    k: [f32; 3],
+}
+
+/// Calibration of one lens
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensProfile {
+    pub model: String,
+    /// By focal length
+    distortion: Vec<(f32, Distortion)>,
+    vignetting: Vec<Vignetting>,
+}
+
+/// What is corrected on a photo taken at a given focal length and aperture
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Correction {
+    pub distortion: Option<Distortion>,
+    pub vignetting: Option<[f32; 3]>,
+}
+
+impl LensProfile {
+    /// Correction at `focal` mm and f/`aperture`: distortion interpolated between the nearest
+    /// calibrated focal lengths, vignetting from the nearest calibration
+    pub fn correction(&self, focal: f32, aperture: Option<f32>) -> Correction {
+        let below = self
+            .distortion
+            .iter()
+            .filter(|(f, _)| *f <= focal)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let above = self
+            .distortion
+            .iter()
+            .filter(|(f, _)| *f >= focal)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let distortion = match (below, above) {
+            (Some((f0, d0)), Some((f1, d1))) if f1 > f0 => {
+                Some(d0.lerp(d1, (focal - f0) / (f1 - f0)))
+            }
+            (Some((_, d)), _) | (None, Some((_, d))) => Some(*d),
+            (None, None) => None,
+        };
+
+        // Apertures and focal lengths are compared in stops and ratios
+        let distance = |v: &Vignetting| {
+            let focal = (v.focal / focal).ln().abs();
+            let aperture = aperture.map_or(0.0, |a| (v.aperture / a).log2().abs());
+            focal * 4.0 + aperture
+        };
+        let vignetting = self
+            .vignetting
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .map(|v| v.k);
+
+        Correction {
+            distortion,
+            vignetting,
+        }
+    }
+}
+
+/// Lens profiles of a lensfun database
+#[derive(Debug, Default)]
+pub struct LensDatabase {
+    lenses: Vec<LensProfile>,
+}
+
+impl LensDatabase {
+    /// Read every XML file of a lensfun database folder
+    pub fn load(dir: &Path) -> Result<Self> {
+        let entries = std::fs::read_dir(dir).map_err(|e| GlimpseError::io_at(dir, e))?;
+        let mut lenses = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("xml") {
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(xml) => lenses.extend(Self::parse(&xml).lenses),
+                Err(e) => tracing::warn!("Cannot read lens profiles {}: {}", path.display(), e),
+            }
+        }
+        Ok(Self { lenses })
+    }
+
+    /// Lenses of one lensfun XML file; cameras and malformed entries are skipped
+    pub fn parse(xml: &str) -> Self {
+        let mut lenses = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = find_element(rest, "lens") {
+            let block = &rest[start..];
+            let Some(end) = block.find("</lens>") else {
+                break;
+            };
+            if let Some(lens) = parse_lens(&block[..end]) {
+                lenses.push(lens);
+            }
+            rest = &block[end..];
+        }
+        Self { lenses }
+    }
+
+    /// Profile of the lens EXIF names `lens_model`: the same name, or else the longest
+    /// profile name whose words all appear in it
+    pub fn find(&self, lens_model: &str) -> Option<&LensProfile> {
+        let wanted = words(lens_model);
+        if wanted.is_empty() {
+            return None;
+        }
+        self.lenses
+            .iter()
+            .find(|lens| words(&lens.model) == wanted)
+            .or_else(|| {
+                self.lenses
+                    .iter()
+                    .filter(|lens| {
+                        let model = words(&lens.model);
+                        !model.is_empty() && model.iter().all(|word| wanted.contains(word))
+                    })
+                    .max_by_key(|lens| words(&lens.model).len())
+            })
+    }
+}
+
+/// Lower-case words of a lens name; "Nikkor Z 24-70mm f/4 S" and "NIKKOR Z 24-70mm F4 S"
+/// both give nikkor, z, 24, 70mm, f4, s
+fn words(name: &str) -> Vec<String> {
+    name.to_lowercase()
+        .replace("f/", "f")
+        .split(|c: char| !c.is_alphanumeric() && c != '.')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Position of the next `<name>` or `<name ...>` opening tag
+fn find_element(xml: &str, name: &str) -> Option<usize> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    while let Some(pos) = xml[from..].find(&open) {
+        let pos = from + pos;
+        match xml[pos + open.len()..].chars().next() {
+            Some('>' | ' ' | '\t' | '\n' | '\r' | '/') => return Some(pos),
+            _ => from = pos + open.len(),
+        }
+    }
+    None
+}
+
+fn parse_lens(block: &str) -> Option<LensProfile> {
+    // The untranslated name comes without a lang attribute
+    let model = block.match_indices("<model").find_map(|(pos, _)| {
+        let tag_end = pos + block[pos..].find('>')?;
+        let tag = &block[pos..tag_end];
+        if tag.contains("lang=") {
+            return None;
+        }
+        let text_end = tag_end + block[tag_end..].find("</model>")?;
+        Some(unescape(block[tag_end + 1..text_end].trim()))
+    })?;
+
+    let mut distortion = Vec::new();
+    let mut vignetting = Vec::new();
+    let mut rest = block;
+    while let Some(pos) = rest.find('<') {
+        let tag_end = rest[pos..].find('>').map(|end| pos + end)?;
+        let tag = &rest[pos + 1..tag_end];
+        let name = tag.split_whitespace().next().unwrap_or("");
+        let attr = |key: &str| attribute(tag, key);
+        let number = |key: &str| attr(key).and_then(|v| v.parse::<f32>().ok());
+        match name {
+            "distortion" => {
+                let model = match attr("model").as_deref() {
+                    Some("poly3") => number("k1").map(|k1| Distortion::Poly3 { k1 }),
+                    Some("poly5") => Some(Distortion::Poly5 {
+                        k1: number("k1").unwrap_or(0.0),
+                        k2: number("k2").unwrap_or(0.0),
+                    }),
+                    Some("ptlens") => Some(Distortion::PtLens {
+                        a: number("a").unwrap_or(0.0),
+                        b: number("b").unwrap_or(0.0),
+                        c: number("c").unwrap_or(0.0),
+                    }),
+                    _ => None,
+                };
+                if let (Some(focal), Some(model)) = (number("focal"), model) {
+                    distortion.push((focal, model));
+                }
+            }
+            "vignetting" if attr("model").as_deref() == Some("pa") => {
+                if let (Some(focal), Some(aperture)) = (number("focal"), number("aperture")) {
+                    vignetting.push(Vignetting {
+                        focal,
+                        aperture,
+                        k: [
+                            number("k1").unwrap_or(0.0),
+                            number("k2").unwrap_or(0.0),
+                            number("k3").unwrap_or(0.0),
+                        ],
+                    });
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[tag_end + 1..];
+    }
+
+    Some(LensProfile {
+        model,
+        distortion,
+        vignetting,
+    })
+}
+
+/// Value of `key="..."` in a tag
+fn attribute(tag: &str, key: &str) -> Option<String> {
+    let pattern = format!("{}=", key);
+    let mut from = 0;
+    while let Some(pos) = tag[from..].find(&pattern) {
+        let pos = from + pos;
+        // Not the tail of a longer name ("k1" in "bk1")
+        let starts_word = pos == 0 || tag[..pos].ends_with(char::is_whitespace);
+        let value = &tag[pos + pattern.len()..];
+        let quote = value.chars().next()?;
+        if starts_word && (quote == '"' || quote == '\'') {
+            let end = value[1..].find(quote)?;
+            return Some(unescape(&value[1..1 + end]));
+        }
+        from = pos + pattern.len();
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Leading number of an EXIF value such as "24", "f/2.8" or "24 mm"
+fn leading_number(value: &str) -> Option<f32> {
+    let value = value.trim().trim_start_matches("f/");
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// The lensfun database of the configured folder (or the first standard one found)
+fn database() -> Result<Option<Arc<LensDatabase>>> {
+    let configured = config::get_lensfun_dir();
+    let mut cached = DATABASE.lock().unwrap();
+    if let Some((dir, database)) = cached.as_ref() {
+        if *dir == configured {
+            return Ok(Some(Arc::clone(database)));
+        }
+    }
+    let dir = configured.clone().or_else(|| {
+        LENSFUN_DIRS
+            .iter()
+            .map(PathBuf::from)
+            .find(|dir| dir.is_dir())
+    });
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+    let database = Arc::new(LensDatabase::load(&dir)?);
+    *cached = Some((configured, Arc::clone(&database)));
+    Ok(Some(database))
+}
+
+/// Correct the preview `img` of a photo taken as `exif` says when its lens has a profile;
+/// anything missing leaves the preview as it is
+pub fn correct_preview(img: DynamicImage, exif: &ExifInfo) -> DynamicImage {
+    let (Some(lens_model), Some(focal)) = (
+        exif.lens_model.as_deref(),
+        exif.focal_length.as_deref().and_then(leading_number),
+    ) else {
+        return img;
+    };
+    let database = match database() {
+        Ok(Some(database)) => database,
+        Ok(None) => return img,
+        Err(e) => {
+            tracing::warn!("Cannot load lens profiles: {}", e);
+            return img;
+        }
+    };
+    match database.find(lens_model) {
+        Some(profile) => {
+            let aperture = exif.aperture.as_deref().and_then(leading_number);
+            apply(&img.into_rgb8(), &profile.correction(focal, aperture)).into()
+        }
+        None => {
+            tracing::debug!("No lens profile for {}", lens_model);
+            img
+        }
+    }
+}
+
+/// `img` with `correction` applied; after undistorting, the image is scaled so its edges
+/// stay filled, as editors crop it
+pub fn apply(img: &RgbImage, correction: &Correction) -> RgbImage {
+    let (width, height) = img.dimensions();
+    if width < 2 || height < 2 || *correction == Correction::default() {
+        return img.clone();
+    }
+    let center = ((width - 1) as f32 / 2.0, (height - 1) as f32 / 2.0);
+    let unit = width.min(height) as f32 / 2.0;
+    let diagonal = (center.0 * center.0 + center.1 * center.1).sqrt().max(1.0);
+
+    // Where a corrected pixel (relative to the center, with the scale applied) is in `img`
+    let source = |dx: f32, dy: f32| -> (f32, f32) {
+        match correction.distortion {
+            Some(distortion) => {
+                let r = (dx * dx + dy * dy).sqrt() / unit;
+                if r < 1e-6 {
+                    return (dx, dy);
+                }
+                let ratio = distortion.distorted_radius(r) / r;
+                (dx * ratio, dy * ratio)
+            }
+            None => (dx, dy),
+        }
+    };
+    let scale = match correction.distortion {
+        Some(_) => fill_scale(center, &source),
+        None => 1.0,
+    };
+
+    let to_linear: Vec<f32> = (0..=255)
+        .map(|v| {
+            let v = v as f32 / 255.0;
+            if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        })
+        .collect();
+    let to_srgb = |v: f32| {
+        let v = v.clamp(0.0, 1.0);
+        let v = if v <= 0.003_130_8 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        };
+        (v * 255.0).round() as u8
+    };
+
+    let mut out = RgbImage::new(width, height);
+    out.par_chunks_mut(width as usize * 3)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width as usize {
+                let (dx, dy) = ((x as f32 - center.0) / scale, (y as f32 - center.1) / scale);
+                let (sx, sy) = source(dx, dy);
+                let mut pixel = sample(img, center.0 + sx, center.1 + sy);
+                if let Some([k1, k2, k3]) = correction.vignetting {
+                    let r2 = (sx * sx + sy * sy) / (diagonal * diagonal);
+                    let falloff = 1.0 + k1 * r2 + k2 * r2 * r2 + k3 * r2 * r2 * r2;
+                    if falloff > 0.01 {
+                        pixel = Rgb(pixel.0.map(|v| to_srgb(to_linear[v as usize] / falloff)));
+                    }
+                }
+                row[x * 3..x * 3 + 3].copy_from_slice(&pixel.0);
+            }
+        });
+    out
+}
+
+/// Smallest enlargement (at least 0.5) for which every edge pixel of the corrected image
+/// comes from inside the original
+fn fill_scale(center: (f32, f32), source: &impl Fn(f32, f32) -> (f32, f32)) -> f32 {
+    const PROBES: usize = 32;
+    let (half_w, half_h) = center;
+    let edges: Vec<(f32, f32)> = (0..=PROBES)
+        .flat_map(|i| {
+            let t = i as f32 / PROBES as f32 * 2.0 - 1.0;
+            [
+                (t * half_w, -half_h),
+                (t * half_w, half_h),
+                (-half_w, t * half_h),
+                (half_w, t * half_h),
+            ]
+        })
+        .collect();
+    let fits = |scale: f32| {
+        edges.iter().all(|&(x, y)| {
+            let (sx, sy) = source(x / scale, y / scale);
+            // A model folding back on itself far out doesn't count as fitting
+            sx * x >= 0.0 && sy * y >= 0.0 && sx.abs() <= half_w + 0.5 && sy.abs() <= half_h + 0.5
+        })
+    };
+
+    let (mut low, mut high) = (0.5f32, 4.0f32);
+    if fits(low) {
+        return low;
+    }
+    if !fits(high) {
+        return high;
+    }
+    for _ in 0..24 {
+        let mid = (low + high) / 2.0;
+        if fits(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    high
+}
+
+/// Bilinear sample of `img` at (`x`, `y`), clamped to its edges
+fn sample(img: &RgbImage, x: f32, y: f32) -> Rgb<u8> {
+    let (width, height) = img.dimensions();
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let (a, b) = (img.get_pixel(x0, y0).0, img.get_pixel(x1, y0).0);
+    let (c, d) = (img.get_pixel(x0, y1).0, img.get_pixel(x1, y1).0);
+    Rgb(std::array::from_fn(|i| {
+        let top = a[i] as f32 * (1.0 - fx) + b[i] as f32 * fx;
+        let bottom = c[i] as f32 * (1.0 - fx) + d[i] as f32 * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"<lensdatabase version="1">
+    <camera>
+        <maker>Nikon</maker>
+        <model>Nikon Z 6</model>
+    </camera>
+    <lens>
+        <maker>Nikon</maker>
+        <model>Nikkor Z 14-30mm f/4 S</model>
+        <model lang="ja">ニッコール Z 14-30mm f/4 S</model>
+        <mount>Nikon Z</mount>
+        <calibration>
+            <distortion model="ptlens" focal="14" a="0.02" b="-0.06" c="0"/>
+            <distortion model="ptlens" focal="30" a="0" b="-0.02" c="0"/>
+            <vignetting model="pa" focal="14" aperture="4" distance="10" k1="-0.6" k2="0.2" k3="-0.1"/>
+            <vignetting model="pa" focal="30" aperture="8" distance="10" k1="-0.1" k2="0" k3="0"/>
+        </calibration>
+    </lens>
+    <lens>
+        <maker>Sigma</maker>
+        <model>Sigma 35mm f/1.4 DG HSM &amp; Art</model>
+        <calibration>
+            <distortion model="poly3" focal="35" k1="-0.01"/>
+        </calibration>
+    </lens>
+</lensdatabase>"#;
+
+    #[test]
+    fn test_parse_and_find() {
+        let database = LensDatabase::parse(PROFILES);
+        assert_eq!(database.lenses.len(), 2);
+
+        let nikkor = database.find("NIKKOR Z 14-30mm f/4 S").unwrap();
+        assert_eq!(nikkor.model, "Nikkor Z 14-30mm f/4 S");
+        assert_eq!(nikkor.distortion.len(), 2);
+        assert_eq!(nikkor.vignetting.len(), 2);
+        assert_eq!(
+            database
+                .find("Sigma 35mm F1.4 DG HSM & Art 012")
+                .unwrap()
+                .model,
+            "Sigma 35mm f/1.4 DG HSM & Art"
+        );
+        assert!(database.find("NIKKOR Z 50mm f/1.8 S").is_none());
+        assert!(database.find("").is_none());
+    }
+
+    #[test]
+    fn test_correction() {
+        let database = LensDatabase::parse(PROFILES);
+        let nikkor = database.find("Nikkor Z 14-30mm f/4 S").unwrap();
+
+        let wide = nikkor.correction(14.0, Some(4.0));
+        assert_eq!(
+            wide.distortion,
+            Some(Distortion::PtLens {
+                a: 0.02,
+                b: -0.06,
+                c: 0.0
+            })
+        );
+        assert_eq!(wide.vignetting, Some([-0.6, 0.2, -0.1]));
+
+        let Some(Distortion::PtLens { a, b, .. }) = nikkor.correction(22.0, None).distortion else {
+            panic!("Expected an interpolated ptlens model");
+        };
+        assert!((a - 0.01).abs() < 1e-6 && (b + 0.04).abs() < 1e-6);
+        assert_eq!(
+            nikkor.correction(28.0, Some(8.0)).vignetting,
+            Some([-0.1, 0.0, 0.0])
+        );
+        // Outside the calibrated range the nearest calibration is used
+        assert_eq!(
+            nikkor.correction(200.0, None).distortion,
+            nikkor.correction(30.0, None).distortion
+        );
+    }
+
+    #[test]
+    fn test_apply_vignetting() {
+        // Corners darkened to half the light of the center
+        let img = RgbImage::from_fn(101, 101, |x, y| {
+            let (dx, dy) = (x as f32 - 50.0, y as f32 - 50.0);
+            let r2 = (dx * dx + dy * dy) / (50.0 * 50.0 * 2.0);
+            let linear = 0.5 * (1.0 - 0.5 * r2);
+            let v = (1.055 * linear.powf(1.0 / 2.4) - 0.055) * 255.0;
+            Rgb([v.round() as u8; 3])
+        });
+        let corrected = apply(
+            &img,
+            &Correction {
+                distortion: None,
+                vignetting: Some([-0.5, 0.0, 0.0]),
+            },
+        );
+        let center = corrected.get_pixel(50, 50).0[0];
+        for (x, y) in [(0, 0), (100, 100), (0, 50), (75, 25)] {
+            assert!(corrected.get_pixel(x, y).0[0].abs_diff(center) <= 2);
+        }
+        assert!(img.get_pixel(0, 0).0[0] < center - 40);
+    }
+
+    #[test]
+    fn test_apply_distortion() {
+        // Horizontal position in red, vertical in green
+        let img = RgbImage::from_fn(120, 80, |x, y| Rgb([(x * 2) as u8, (y * 3) as u8, 0]));
+        let barrel = Correction {
+            distortion: Some(Distortion::Poly3 { k1: -0.1 }),
+            vignetting: None,
+        };
+        let corrected = apply(&img, &barrel);
+        assert_eq!(corrected.dimensions(), (120, 80));
+        // The center stays put, the long sides are pulled in and are scaled to just fill the
+        // frame
+        let center = corrected.get_pixel(60, 40).0;
+        assert!(center[0].abs_diff(120) <= 2 && center[1].abs_diff(120) <= 2);
+        assert!(corrected.get_pixel(0, 40).0[0] > 8);
+        assert!(corrected.get_pixel(60, 0).0[1] <= 6);
+
+        assert_eq!(apply(&img, &Correction::default()), img);
+    }
+
+    #[test]
+    fn test_leading_number() {
+        assert_eq!(leading_number("24"), Some(24.0));
+        assert_eq!(leading_number("f/2.8"), Some(2.8));
+        assert_eq!(leading_number("24.5 mm"), Some(24.5));
+        assert_eq!(leading_number("unknown"), None);
+    }
+}
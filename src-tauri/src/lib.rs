@@ -1,26 +1,96 @@
+pub mod analysis;
+pub mod benchmark;
+pub mod bundle;
+pub mod color;
 pub mod commands;
 pub mod config;
+pub mod content_cache;
 pub mod database;
+pub mod denoise;
+pub mod disk;
+pub mod embedded;
 pub mod error;
+pub mod export;
+pub mod font;
+pub mod gear;
+pub mod geocode;
+pub mod histogram;
 pub mod image_processor;
+pub mod keychain;
+pub mod labelsync;
+pub mod lens;
+pub mod logging;
+pub mod makernote;
+pub mod memory;
+pub mod metadata;
+pub mod netio;
+pub mod operations;
+pub mod placeholder;
+pub mod power;
+pub mod raw_decoder;
+pub mod reconcile;
+pub mod reflink;
+pub mod rename;
+pub mod sftp;
+pub mod sniff;
+pub mod stats;
+pub mod system;
+pub mod tasks;
+pub mod tether;
+pub mod tiff;
+pub mod trash;
+pub mod video;
+pub mod volume;
+pub mod watermark;
+pub mod xmp;
 
 pub use commands::AppState;
 use commands::{
-    clear_all_cache, clear_all_labels, clear_cache, export_adopted, get_exif, get_storage_info,
-    get_system_info, open_folder, save_selection, set_label, set_thread_count,
+    add_bookmark, add_to_compare_set, cancel_task, check_database, clear_all_cache,
+    clear_all_labels, clear_cache, clear_cache_for_files, clear_compare_set, clear_session_cache,
+    cluster_similar, compare_passes, create_pass, create_project, dedup_cache,
+    delete_export_preset, delete_file, delete_project, delete_sftp_target, detect_brackets,
+    detect_bursts, edit_metadata, export_adopted, export_project, export_session_bundle,
+    export_sftp, export_split, find_global_duplicates, find_similar, get_adjusted_preview,
+    get_capture_time_offsets, get_clipping_mask, get_compare_set, get_config, get_exif,
+    get_export_history, get_faces, get_focus_peaking, get_folder_stats, get_gear_stats,
+    get_generation_report, get_histogram, get_image_adjustments, get_interrupted_operations,
+    get_keybindings, get_metadata_edits, get_power_status, get_preview, get_problem_files,
+    get_project_stats, get_recent_logs, get_session_locations, get_session_settings,
+    get_storage_info, get_system_info, get_video_info, import_session_bundle, label_bracket_set,
+    list_bookmarks, list_export_presets, list_passes, list_projects, list_sftp_targets, list_tasks,
+    migrate_cache_dir, move_files, open_folder, open_folders, open_log_folder, optimize_database,
+    pause_task, prefetch_previews, rank_burst, reconcile_session, remove_bookmark,
+    remove_from_compare_set, rename_bookmark, rename_files, rename_project, reorder_bookmarks,
+    rerun_export, reset_capture_times, resolve_operation, resume_task, reveal_in_explorer,
+    reverse_geocode, run_benchmark, run_export_preset, save_export_preset, save_selection,
+    save_sftp_target, save_view_state, set_config, set_generation_priority, set_keybindings,
+    set_label, set_power_mode, set_project_sessions, set_session_settings,
+    set_session_white_balance, set_thread_count, shift_capture_times, start_tether, stop_tether,
+    suggest_rejections, switch_pass, transfer_labels, undo_rename,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .manage(AppState::new().expect("Failed to initialize app state"))
+        .setup(|app| {
+            commands::allow_cache_dir_access(app.handle());
+            commands::expire_old_caches(app.handle());
+            commands::watch_power(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             open_folder,
+            open_folders,
             set_label,
             save_selection,
+            save_view_state,
             export_adopted,
             get_exif,
             clear_cache,
@@ -29,6 +99,101 @@ pub fn run() {
             get_storage_info,
             clear_all_cache,
             clear_all_labels,
+            get_config,
+            set_config,
+            migrate_cache_dir,
+            get_session_settings,
+            set_session_settings,
+            get_keybindings,
+            set_keybindings,
+            get_recent_logs,
+            open_log_folder,
+            reveal_in_explorer,
+            get_video_info,
+            prefetch_previews,
+            set_generation_priority,
+            list_tasks,
+            pause_task,
+            resume_task,
+            cancel_task,
+            start_tether,
+            stop_tether,
+            suggest_rejections,
+            detect_bursts,
+            rank_burst,
+            get_faces,
+            cluster_similar,
+            get_gear_stats,
+            dedup_cache,
+            clear_session_cache,
+            clear_cache_for_files,
+            get_problem_files,
+            get_generation_report,
+            export_session_bundle,
+            import_session_bundle,
+            list_passes,
+            create_pass,
+            switch_pass,
+            compare_passes,
+            get_compare_set,
+            add_to_compare_set,
+            remove_from_compare_set,
+            clear_compare_set,
+            get_folder_stats,
+            get_power_status,
+            set_power_mode,
+            run_benchmark,
+            get_interrupted_operations,
+            resolve_operation,
+            optimize_database,
+            check_database,
+            reconcile_session,
+            transfer_labels,
+            rename_files,
+            undo_rename,
+            move_files,
+            delete_file,
+            get_adjusted_preview,
+            get_image_adjustments,
+            set_session_white_balance,
+            get_preview,
+            list_bookmarks,
+            add_bookmark,
+            rename_bookmark,
+            remove_bookmark,
+            reorder_bookmarks,
+            list_projects,
+            create_project,
+            rename_project,
+            set_project_sessions,
+            delete_project,
+            get_project_stats,
+            export_project,
+            list_export_presets,
+            save_export_preset,
+            delete_export_preset,
+            run_export_preset,
+            get_export_history,
+            rerun_export,
+            export_split,
+            list_sftp_targets,
+            save_sftp_target,
+            delete_sftp_target,
+            export_sftp,
+            reverse_geocode,
+            get_session_locations,
+            shift_capture_times,
+            reset_capture_times,
+            get_capture_time_offsets,
+            edit_metadata,
+            get_metadata_edits,
+            get_focus_peaking,
+            get_clipping_mask,
+            get_histogram,
+            find_global_duplicates,
+            find_similar,
+            detect_brackets,
+            label_bracket_set,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::prelude::*;
+
+/// Log file name prefix (files are named `glimpse.YYYY-MM-DD.log`)
+const LOG_FILE_PREFIX: &str = "glimpse";
+const LOG_FILE_SUFFIX: &str = "log";
+/// Number of daily log files to keep
+const MAX_LOG_FILES: usize = 7;
+
+/// Keeps the background log writer alive for the lifetime of the app
+static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Get log directory path
+pub fn get_log_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("Glimpse").join("logs"))
+}
+
+/// Initialize logging to a daily rotating file (and stderr in debug builds)
+/// Logging is best-effort: if the log directory can't be created, only stderr is used
+pub fn init() {
+    let file_layer = get_log_dir()
+        .and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix(LOG_FILE_SUFFIX)
+                .max_log_files(MAX_LOG_FILES)
+                .build(dir)
+                .ok()
+        })
+        .map(|appender| {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = LOG_GUARD.set(guard);
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+        });
+
+    let stderr_layer = cfg!(debug_assertions)
+        .then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+
+    let _ = tracing_subscriber::registry()
+        .with(tracing_subscriber::filter::LevelFilter::INFO)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init();
+}
+
+/// Read the last `count` log lines (oldest first) from the log files in `log_dir`
+pub fn read_recent_logs(log_dir: &Path, count: usize) -> std::io::Result<Vec<String>> {
+    if count == 0 || !log_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Date-stamped file names sort chronologically
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(LOG_FILE_PREFIX) && n.ends_with(LOG_FILE_SUFFIX))
+                    .unwrap_or(false)
+        })
+        .collect();
+    files.sort();
+
+    // Walk files newest first until enough lines are collected
+    let mut lines = Vec::new();
+    for file in files.iter().rev() {
+        let content = fs::read_to_string(file)?;
+        let remaining = count - lines.len();
+        lines.extend(content.lines().rev().take(remaining).map(String::from));
+        if lines.len() >= count {
+            break;
+        }
+    }
+
+    lines.reverse();
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_recent_logs_missing_dir() {
+        let dir = tempdir().unwrap();
+        let lines = read_recent_logs(&dir.path().join("missing"), 10).unwrap();
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_read_recent_logs_across_files() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("glimpse.2024-12-14.log"),
+            "old 1\nold 2\nold 3\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("glimpse.2024-12-15.log"), "new 1\nnew 2\n").unwrap();
+        fs::write(dir.path().join("other.txt"), "ignored\n").unwrap();
+
+        // Only the newest file is needed
+        let lines = read_recent_logs(dir.path(), 2).unwrap();
+        assert_eq!(lines, vec!["new 1", "new 2"]);
+
+        // Spills over into the previous day, keeping chronological order
+        let lines = read_recent_logs(dir.path(), 4).unwrap();
+        assert_eq!(lines, vec!["old 2", "old 3", "new 1", "new 2"]);
+
+        // Asking for more than exists returns everything
+        let lines = read_recent_logs(dir.path(), 100).unwrap();
+        assert_eq!(lines.len(), 5);
+    }
+}
@@ -0,0 +1,276 @@
+// Camera-specific autofocus data from EXIF maker notes
+// Maker notes are undocumented TIFF-style IFDs; offsets below follow ExifTool's tables
+
+use crate::tiff::{Tiff, SHORT};
+
+/// Side of the box drawn for makers that only record a focus point (Sony)
+const FOCUS_POINT_SIZE: f64 = 0.04;
+
+/// Canon AFInfo2
+const CANON_AF_INFO2: u16 = 0x0026;
+/// Nikon AFInfo2
+const NIKON_AF_INFO2: u16 = 0x00b7;
+/// Sony FocusLocation (image width, height, focus x, focus y)
+const SONY_FOCUS_LOCATION: u16 = 0x2027;
+
+/// Area the camera focused on, normalized to 0.0 - 1.0 of the image width/height
+/// Coordinates are in the stored (unrotated) image orientation
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FocusArea {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl FocusArea {
+    /// Build from a pixel rectangle centered on (`cx`, `cy`) in an image of `image_w` x `image_h`
+    fn from_center(cx: f64, cy: f64, w: f64, h: f64, image_w: f64, image_h: f64) -> Option<Self> {
+        if image_w <= 0.0 || image_h <= 0.0 {
+            return None;
+        }
+        let left = ((cx - w / 2.0) / image_w).clamp(0.0, 1.0);
+        let top = ((cy - h / 2.0) / image_h).clamp(0.0, 1.0);
+        let right = ((cx + w / 2.0) / image_w).clamp(0.0, 1.0);
+        let bottom = ((cy + h / 2.0) / image_h).clamp(0.0, 1.0);
+        Some(Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+/// Focus areas recorded in the maker note, if the camera is supported
+/// `exif_buf` is the whole TIFF structure and `maker_note_pos` the maker note's offset in it
+pub fn focus_areas(
+    make: &str,
+    exif_buf: &[u8],
+    little_endian: bool,
+    maker_note_pos: usize,
+) -> Vec<FocusArea> {
+    let tiff = Tiff::new(exif_buf, little_endian);
+    let make = make.to_lowercase();
+
+    let areas = if make.contains("canon") {
+        canon_focus_areas(tiff, maker_note_pos)
+    } else if make.contains("nikon") {
+        nikon_focus_areas(exif_buf.get(maker_note_pos..).unwrap_or_default())
+    } else if make.contains("sony") {
+        sony_focus_areas(tiff, maker_note_pos)
+    } else {
+        None
+    };
+    areas.unwrap_or_default()
+}
+
+/// Canon: the maker note is a bare IFD with offsets relative to the EXIF TIFF header
+/// AFInfo2 holds every AF point; only those flagged in focus are returned
+fn canon_focus_areas(tiff: Tiff, maker_note_pos: usize) -> Option<Vec<FocusArea>> {
+    let entry = tiff.find(maker_note_pos, 0, CANON_AF_INFO2)?;
+    if entry.typ != SHORT {
+        return None;
+    }
+    let v = tiff.shorts(entry.value_pos, entry.count)?;
+
+    // [size, area mode, point count, valid points, canon w, canon h, af image w, af image h, ...]
+    let points = *v.get(2)? as usize;
+    let (mut image_w, mut image_h) = (*v.get(6)? as f64, *v.get(7)? as f64);
+    if image_w == 0.0 || image_h == 0.0 {
+        image_w = *v.get(4)? as f64;
+        image_h = *v.get(5)? as f64;
+    }
+
+    let widths = v.get(8..8 + points)?;
+    let heights = v.get(8 + points..8 + points * 2)?;
+    let xs = v.get(8 + points * 2..8 + points * 3)?;
+    let ys = v.get(8 + points * 3..8 + points * 4)?;
+    let in_focus = v.get(8 + points * 4..8 + points * 4 + points.div_ceil(16))?;
+
+    Some(
+        (0..points)
+            .filter(|&i| in_focus[i / 16] & (1 << (i % 16)) != 0)
+            .filter_map(|i| {
+                // Positions are relative to the image center, with y increasing upwards
+                let cx = image_w / 2.0 + xs[i] as i16 as f64;
+                let cy = image_h / 2.0 - ys[i] as i16 as f64;
+                FocusArea::from_center(
+                    cx,
+                    cy,
+                    widths[i] as f64,
+                    heights[i] as f64,
+                    image_w,
+                    image_h,
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Nikon: "Nikon\0" + version, then a TIFF structure of its own at offset 10
+/// Only AFInfo2 version 0100 with contrast-detect (live view) AF records an area rectangle
+fn nikon_focus_areas(note: &[u8]) -> Option<Vec<FocusArea>> {
+    if !note.starts_with(b"Nikon\0") {
+        return None;
+    }
+    let inner = note.get(10..)?;
+    let tiff = Tiff::new(inner, inner.starts_with(b"II"));
+    let ifd_pos = tiff.u32(4)? as usize;
+    let entry = tiff.find(ifd_pos, 0, NIKON_AF_INFO2)?;
+    let info = tiff.bytes(&entry)?;
+    if !info.starts_with(b"0100") || *info.get(4)? == 0 {
+        return None;
+    }
+
+    let info = Tiff::new(info, tiff.little_endian());
+    let [image_w, image_h, cx, cy, w, h] = [16, 18, 20, 22, 24, 26].map(|pos| info.u16(pos));
+    let area = FocusArea::from_center(
+        cx? as f64,
+        cy? as f64,
+        w? as f64,
+        h? as f64,
+        image_w? as f64,
+        image_h? as f64,
+    )?;
+    Some(vec![area])
+}
+
+/// Sony: an IFD (optionally after a "SONY DSC " header) with offsets relative to the
+/// EXIF TIFF header; FocusLocation is a single point
+fn sony_focus_areas(tiff: Tiff, maker_note_pos: usize) -> Option<Vec<FocusArea>> {
+    let header = tiff.buf().get(maker_note_pos..maker_note_pos + 12)?;
+    let ifd_pos = if header.starts_with(b"SONY DSC ") || header.starts_with(b"SONY CAM ") {
+        maker_note_pos + 12
+    } else {
+        maker_note_pos
+    };
+
+    let entry = tiff.find(ifd_pos, 0, SONY_FOCUS_LOCATION)?;
+    if entry.typ != SHORT || entry.count < 4 {
+        return None;
+    }
+    let v = tiff.shorts(entry.value_pos, 4)?;
+    if v[2] == 0 && v[3] == 0 {
+        return None;
+    }
+
+    let (image_w, image_h) = (v[0] as f64, v[1] as f64);
+    let size = FOCUS_POINT_SIZE * image_w.max(image_h);
+    let area = FocusArea::from_center(v[2] as f64, v[3] as f64, size, size, image_w, image_h)?;
+    Some(vec![area])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian IFD with one entry whose out-of-line value is placed right after it
+    fn ifd_le(tag: u16, typ: u16, count: u32, value: &[u8], value_offset: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&tag.to_le_bytes());
+        buf.extend_from_slice(&typ.to_le_bytes());
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&value_offset.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(value);
+        buf
+    }
+
+    fn shorts_le(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_canon_af_info2() {
+        // Two points on a 6000x4000 image; only the second (right of and above center) in focus
+        let values = [
+            0, 0, 2, 2, 6000, 4000, 6000, 4000, // header
+            200, 200, // widths
+            100, 100, // heights
+            0, 1000, // x offsets
+            0, 500,  // y offsets (up)
+            0b10, // in focus
+        ];
+        let maker_note_pos = 8;
+        let value_offset = maker_note_pos + 18;
+        let mut buf = vec![0u8; maker_note_pos as usize];
+        buf.extend(ifd_le(
+            CANON_AF_INFO2,
+            SHORT,
+            values.len() as u32,
+            &shorts_le(&values),
+            value_offset,
+        ));
+
+        let areas = focus_areas("Canon", &buf, true, maker_note_pos as usize);
+        assert_eq!(areas.len(), 1);
+        let area = areas[0];
+        assert!((area.x - 3900.0 / 6000.0).abs() < 1e-9);
+        assert!((area.y - 1450.0 / 4000.0).abs() < 1e-9);
+        assert!((area.width - 200.0 / 6000.0).abs() < 1e-9);
+        assert!((area.height - 100.0 / 4000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nikon_contrast_detect_area() {
+        let mut af_info = b"0100".to_vec();
+        af_info.extend_from_slice(&[1, 0, 0, 0]);
+        af_info.extend_from_slice(&[0; 8]);
+        for v in [640u16, 480, 320, 240, 64, 48] {
+            af_info.extend_from_slice(&v.to_be_bytes());
+        }
+
+        // Big-endian inner TIFF: header, IFD at 8, value after the IFD
+        let mut inner = b"MM\0\x2a".to_vec();
+        inner.extend_from_slice(&8u32.to_be_bytes());
+        inner.extend_from_slice(&1u16.to_be_bytes());
+        inner.extend_from_slice(&NIKON_AF_INFO2.to_be_bytes());
+        inner.extend_from_slice(&7u16.to_be_bytes());
+        inner.extend_from_slice(&(af_info.len() as u32).to_be_bytes());
+        inner.extend_from_slice(&26u32.to_be_bytes());
+        inner.extend_from_slice(&0u32.to_be_bytes());
+        inner.extend_from_slice(&af_info);
+
+        let mut note = b"Nikon\0\x02\x10\0\0".to_vec();
+        note.extend(inner);
+
+        let areas = focus_areas("NIKON CORPORATION", &note, false, 0);
+        assert_eq!(areas.len(), 1);
+        let area = areas[0];
+        assert!((area.x - 0.45).abs() < 1e-9);
+        assert!((area.y - 0.45).abs() < 1e-9);
+        assert!((area.width - 0.1).abs() < 1e-9);
+        assert!((area.height - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sony_focus_location() {
+        let maker_note_pos = 4;
+        let mut buf = vec![0u8; maker_note_pos];
+        buf.extend_from_slice(b"SONY DSC \0\0\0");
+        let value_offset = (maker_note_pos + 12 + 18) as u32;
+        buf.extend(ifd_le(
+            SONY_FOCUS_LOCATION,
+            SHORT,
+            4,
+            &shorts_le(&[1000, 500, 500, 250]),
+            value_offset,
+        ));
+
+        let areas = focus_areas("SONY", &buf, true, maker_note_pos);
+        assert_eq!(areas.len(), 1);
+        assert!((areas[0].x - 0.48).abs() < 1e-9);
+        assert!((areas[0].width - 0.04).abs() < 1e-9);
+        assert!((areas[0].height - 0.08).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unknown_or_truncated_maker_notes() {
+        assert!(focus_areas("FUJIFILM", &[0; 64], true, 0).is_empty());
+        assert!(focus_areas("Canon", &[1, 0], true, 0).is_empty());
+        assert!(focus_areas("Canon", &[], true, 100).is_empty());
+        assert!(focus_areas("NIKON", b"Nikon\0", false, 0).is_empty());
+    }
+}
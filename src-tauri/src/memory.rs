@@ -0,0 +1,125 @@
+// Installed and currently available physical memory, read without extra dependencies
+// Used to keep parallel RAW decodes from pushing small machines into swap
+
+/// Bytes of RAM available for new allocations (free plus reclaimable cache)
+/// None when the platform doesn't report it
+pub fn available_memory() -> Option<u64> {
+    platform::available_memory()
+}
+
+/// Bytes of installed RAM
+pub fn total_memory() -> Option<u64> {
+    platform::total_memory()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    fn meminfo(key: &str) -> Option<u64> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        super::parse_meminfo(&meminfo, key)
+    }
+
+    pub fn available_memory() -> Option<u64> {
+        meminfo("MemAvailable")
+    }
+
+    pub fn total_memory() -> Option<u64> {
+        meminfo("MemTotal")
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    fn sysctl<T: Default>(name: &std::ffi::CStr) -> Option<T> {
+        let mut value = T::default();
+        let mut len = std::mem::size_of::<T>();
+        // SAFETY: value and len describe a valid buffer of T
+        let result = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr(),
+                &mut value as *mut T as *mut libc::c_void,
+                &mut len,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        (result == 0).then_some(value)
+    }
+
+    fn sysctl_u32(name: &std::ffi::CStr) -> Option<u64> {
+        sysctl::<u32>(name).map(u64::from)
+    }
+
+    pub fn total_memory() -> Option<u64> {
+        sysctl::<u64>(c"hw.memsize")
+    }
+
+    pub fn available_memory() -> Option<u64> {
+        // SAFETY: sysconf has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let pages = sysctl_u32(c"vm.page_free_count")?
+            + sysctl_u32(c"vm.page_speculative_count").unwrap_or(0)
+            + sysctl_u32(c"vm.page_pageable_external_count").unwrap_or(0);
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    fn memory_status() -> Option<MEMORYSTATUSEX> {
+        // SAFETY: MEMORYSTATUSEX is plain data and dwLength is set as the API requires
+        unsafe {
+            let mut status: MEMORYSTATUSEX = std::mem::zeroed();
+            status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+            (GlobalMemoryStatusEx(&mut status) != 0).then_some(status)
+        }
+    }
+
+    pub fn available_memory() -> Option<u64> {
+        memory_status().map(|status| status.ullAvailPhys)
+    }
+
+    pub fn total_memory() -> Option<u64> {
+        memory_status().map(|status| status.ullTotalPhys)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub fn available_memory() -> Option<u64> {
+        None
+    }
+
+    pub fn total_memory() -> Option<u64> {
+        None
+    }
+}
+
+/// A field of /proc/meminfo (e.g. "MemAvailable"), in bytes
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_meminfo(meminfo: &str, key: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| {
+        line.strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal:        8046788 kB\nMemFree:          312456 kB\nMemAvailable:    2097152 kB\n";
+        assert_eq!(
+            parse_meminfo(meminfo, "MemAvailable"),
+            Some(2 * 1024 * 1024 * 1024)
+        );
+        assert_eq!(parse_meminfo(meminfo, "MemTotal"), Some(8046788 * 1024));
+        assert_eq!(parse_meminfo(meminfo, "SwapTotal"), None);
+    }
+}
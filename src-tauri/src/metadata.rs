@@ -0,0 +1,508 @@
+// Metadata corrections (artist, copyright, capture date) made in Glimpse and written on export
+// Originals are never rewritten: exported JPEGs get the corrections in their EXIF, exported
+// RAW files in their XMP sidecar
+
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::ExifInfo;
+use crate::stats::{self, EXIF_DATE_FORMAT};
+use crate::tiff::{Tiff, LONG};
+use chrono::NaiveDateTime;
+use std::path::Path;
+
+const TAG_ARTIST: u16 = 0x013b;
+const TAG_COPYRIGHT: u16 = 0x8298;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const ASCII: u16 = 2;
+
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+const JPEG_APP0: u8 = 0xe0;
+const JPEG_APP1: u8 = 0xe1;
+const JPEG_SOS: u8 = 0xda;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// Largest EXIF chunk a JPEG APP1 segment holds
+const MAX_EXIF_LEN: usize = u16::MAX as usize - 2 - EXIF_HEADER.len();
+
+/// Corrected fields of a file; None keeps what the camera wrote
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MetadataEdits {
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    /// Capture date ("YYYY-MM-DD HH:MM:SS"); replaces the camera's, time corrections included
+    pub date_taken: Option<String>,
+}
+
+impl MetadataEdits {
+    pub fn is_empty(&self) -> bool {
+        self.artist.is_none() && self.copyright.is_none() && self.date_taken.is_none()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for value in [&self.artist, &self.copyright].into_iter().flatten() {
+            if value.contains('\0') {
+                return Err(GlimpseError::InvalidConfig(
+                    "Artist and copyright can't contain NUL characters".to_string(),
+                ));
+            }
+        }
+        if let Some(date) = self.date_taken.as_deref().filter(|d| !d.is_empty()) {
+            NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).map_err(|_| {
+                GlimpseError::InvalidConfig(format!(
+                    "Capture date must read YYYY-MM-DD HH:MM:SS: {}",
+                    date
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// These corrections, with the fields they leave unset taken from `defaults`
+    pub fn or(self, defaults: &MetadataEdits) -> MetadataEdits {
+        MetadataEdits {
+            artist: self.artist.or_else(|| defaults.artist.clone()),
+            copyright: self.copyright.or_else(|| defaults.copyright.clone()),
+            date_taken: self.date_taken.or_else(|| defaults.date_taken.clone()),
+        }
+    }
+
+    /// Take over the fields set in `other`; an empty string drops the correction
+    pub fn merge(&mut self, other: &MetadataEdits) {
+        let take = |field: &mut Option<String>, value: &Option<String>| {
+            if let Some(value) = value {
+                *field = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            }
+        };
+        take(&mut self.artist, &other.artist);
+        take(&mut self.copyright, &other.copyright);
+        take(&mut self.date_taken, &other.date_taken);
+    }
+}
+
+/// Apply a file's corrections to what its EXIF says: a corrected date wins over a time
+/// correction of `offset` seconds
+pub fn correct_exif(exif: &mut ExifInfo, edits: Option<&MetadataEdits>, offset: Option<i64>) {
+    if let Some(date) = edits.and_then(|edits| edits.date_taken.clone()) {
+        exif.date_taken = Some(date);
+    } else if let Some(seconds) = offset {
+        stats::shift_date_taken(exif, seconds);
+    }
+}
+
+/// Write `edits` into the EXIF of the JPEG at `path`, adding an EXIF segment if it has none
+pub fn write_jpeg(path: &Path, edits: &MetadataEdits) -> Result<()> {
+    let data = std::fs::read(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let edited = edit_jpeg(&data, edits).ok_or_else(|| {
+        GlimpseError::ExifError(format!("Cannot write metadata to {}", path.display()))
+    })?;
+    std::fs::write(path, edited).map_err(|e| GlimpseError::io_at(path, e))
+}
+
+/// Copy of a JPEG with `edits` in its EXIF segment
+/// Returns None if the file isn't a well-formed JPEG or the EXIF would outgrow its segment
+fn edit_jpeg(data: &[u8], edits: &MetadataEdits) -> Option<Vec<u8>> {
+    if !data.starts_with(&JPEG_SOI) {
+        return None;
+    }
+
+    let mut segments: Vec<(u8, &[u8])> = Vec::new();
+    let mut pos = 2;
+    let image_data = loop {
+        if *data.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        if marker == 0xff {
+            // Fill byte
+            pos += 1;
+            continue;
+        }
+        if marker == JPEG_SOS {
+            break &data[pos..];
+        }
+        let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        segments.push((marker, data.get(pos + 4..pos + 2 + len)?));
+        pos += 2 + len;
+    };
+
+    let existing = segments
+        .iter()
+        .position(|(marker, payload)| *marker == JPEG_APP1 && payload.starts_with(EXIF_HEADER));
+    let exif = edit_exif(existing.map(|i| &segments[i].1[EXIF_HEADER.len()..]), edits)?;
+    let mut payload = EXIF_HEADER.to_vec();
+    payload.extend_from_slice(&exif);
+
+    let mut out = JPEG_SOI.to_vec();
+    let write_segment = |out: &mut Vec<u8>, marker: u8, payload: &[u8]| {
+        out.extend_from_slice(&[0xff, marker]);
+        out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(payload);
+    };
+    // A new EXIF segment goes after the JFIF header, where readers look for it
+    let insert_at = existing.unwrap_or_else(|| {
+        segments
+            .iter()
+            .take_while(|(marker, _)| *marker == JPEG_APP0)
+            .count()
+    });
+    for (i, (marker, segment)) in segments.iter().enumerate() {
+        if i == insert_at {
+            write_segment(&mut out, JPEG_APP1, &payload);
+        }
+        if Some(i) != existing {
+            write_segment(&mut out, *marker, segment);
+        }
+    }
+    if insert_at == segments.len() {
+        write_segment(&mut out, JPEG_APP1, &payload);
+    }
+    out.extend_from_slice(image_data);
+    Some(out)
+}
+
+/// EXIF chunk (TIFF structure) with `edits` written into it, or a new one holding only them
+/// The directories that change are rewritten at the end of the chunk; everything they point
+/// to stays where it is, so no other offset needs fixing
+fn edit_exif(exif: Option<&[u8]>, edits: &MetadataEdits) -> Option<Vec<u8>> {
+    let mut out = match exif.filter(|exif| Tiff::from_header(exif).is_some()) {
+        Some(exif) => exif.to_vec(),
+        // Little-endian header and an empty IFD0
+        None => [b"II*\0".as_slice(), &8u32.to_le_bytes(), &[0; 6]].concat(),
+    };
+    let little_endian = Tiff::from_header(&out)?.little_endian();
+    let ifd0_pos = Tiff::from_header(&out)?.u32(4)? as usize;
+    let (mut ifd0, next_ifd) = read_ifd(&out, ifd0_pos)?;
+
+    if let Some(date) = &edits.date_taken {
+        let date = NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).ok()?;
+        let exif_ifd = ifd0
+            .iter()
+            .find(|entry| entry.tag == TAG_EXIF_IFD)
+            .and_then(|entry| Tiff::from_header(&out)?.u32(entry.pos))
+            .and_then(|pos| read_ifd(&out, pos as usize));
+        let (mut entries, next) = exif_ifd.unwrap_or_default();
+        let value = date.format("%Y:%m:%d %H:%M:%S").to_string();
+        set_ascii(
+            &mut out,
+            &mut entries,
+            TAG_DATE_TIME_ORIGINAL,
+            &value,
+            little_endian,
+        );
+        let pos = write_ifd(&mut out, entries, next, little_endian);
+        set_entry(
+            &mut ifd0,
+            RawEntry::new(TAG_EXIF_IFD, LONG, 1, pos, little_endian),
+        );
+    }
+    for (tag, value) in [
+        (TAG_ARTIST, &edits.artist),
+        (TAG_COPYRIGHT, &edits.copyright),
+    ] {
+        if let Some(value) = value {
+            set_ascii(&mut out, &mut ifd0, tag, value, little_endian);
+        }
+    }
+
+    let pos = write_ifd(&mut out, ifd0, next_ifd, little_endian);
+    let pos = if little_endian {
+        pos.to_le_bytes()
+    } else {
+        pos.to_be_bytes()
+    };
+    out[4..8].copy_from_slice(&pos);
+    (out.len() <= MAX_EXIF_LEN).then_some(out)
+}
+
+/// IFD entry as stored, its value (or value offset) field kept as raw bytes
+#[derive(Clone)]
+struct RawEntry {
+    tag: u16,
+    typ: u16,
+    count: u32,
+    value: [u8; 4],
+    /// Where `value` sits in the chunk, for reading sub-IFD pointers
+    pos: usize,
+}
+
+impl RawEntry {
+    fn new(tag: u16, typ: u16, count: u32, value: u32, little_endian: bool) -> Self {
+        let value = if little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        };
+        Self {
+            tag,
+            typ,
+            count,
+            value,
+            pos: 0,
+        }
+    }
+}
+
+/// Entries of the IFD at `pos` and its pointer to the next IFD
+fn read_ifd(exif: &[u8], pos: usize) -> Option<(Vec<RawEntry>, u32)> {
+    let tiff = Tiff::from_header(exif)?;
+    let count = tiff.u16(pos)? as usize;
+    let entries = (0..count)
+        .map(|i| {
+            let entry = pos + 2 + i * 12;
+            Some(RawEntry {
+                tag: tiff.u16(entry)?,
+                typ: tiff.u16(entry + 2)?,
+                count: tiff.u32(entry + 4)?,
+                value: exif.get(entry + 8..entry + 12)?.try_into().ok()?,
+                pos: entry + 8,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((entries, tiff.u32(pos + 2 + count * 12)?))
+}
+
+fn set_entry(entries: &mut Vec<RawEntry>, entry: RawEntry) {
+    entries.retain(|e| e.tag != entry.tag);
+    entries.push(entry);
+}
+
+/// Set `tag` to the string `value`, appending it to `out` if it doesn't fit in the entry
+fn set_ascii(
+    out: &mut Vec<u8>,
+    entries: &mut Vec<RawEntry>,
+    tag: u16,
+    value: &str,
+    little_endian: bool,
+) {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    let count = bytes.len() as u32;
+    let entry = if bytes.len() <= 4 {
+        bytes.resize(4, 0);
+        RawEntry {
+            tag,
+            typ: ASCII,
+            count,
+            value: bytes.try_into().unwrap(),
+            pos: 0,
+        }
+    } else {
+        let pos = append_aligned(out, &bytes);
+        RawEntry::new(tag, ASCII, count, pos, little_endian)
+    };
+    set_entry(entries, entry);
+}
+
+/// Append an IFD of `entries` (sorted by tag, as readers expect) and return its position
+fn write_ifd(out: &mut Vec<u8>, mut entries: Vec<RawEntry>, next: u32, little_endian: bool) -> u32 {
+    entries.sort_by_key(|entry| entry.tag);
+    let u16_bytes = |v: u16| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+    let u32_bytes = |v: u32| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+
+    let mut ifd = u16_bytes(entries.len() as u16).to_vec();
+    for entry in &entries {
+        ifd.extend_from_slice(&u16_bytes(entry.tag));
+        ifd.extend_from_slice(&u16_bytes(entry.typ));
+        ifd.extend_from_slice(&u32_bytes(entry.count));
+        ifd.extend_from_slice(&entry.value);
+    }
+    ifd.extend_from_slice(&u32_bytes(next));
+    append_aligned(out, &ifd)
+}
+
+/// Append `bytes` at an even position (TIFF offsets are word-aligned) and return it
+fn append_aligned(out: &mut Vec<u8>, bytes: &[u8]) -> u32 {
+    if out.len() % 2 == 1 {
+        out.push(0);
+    }
+    let pos = out.len() as u32;
+    out.extend_from_slice(bytes);
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{In, Reader, Tag};
+    use tempfile::tempdir;
+
+    fn edits() -> MetadataEdits {
+        MetadataEdits {
+            artist: Some("Jane Doe".to_string()),
+            copyright: Some("(c) 2024 Studio North".to_string()),
+            date_taken: Some("2024-12-15 19:05:30".to_string()),
+        }
+    }
+
+    fn read_field(jpeg: &[u8], tag: Tag) -> Option<String> {
+        let exif = Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(jpeg))
+            .ok()?;
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        Some(
+            field
+                .display_value()
+                .to_string()
+                .trim_matches('"')
+                .to_string(),
+        )
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::new(8, 8)
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_edit_jpeg_without_exif() {
+        let edited = edit_jpeg(&jpeg(), &edits()).unwrap();
+        assert_eq!(
+            read_field(&edited, Tag::Artist).as_deref(),
+            Some("Jane Doe")
+        );
+        assert_eq!(
+            read_field(&edited, Tag::Copyright).as_deref(),
+            Some("(c) 2024 Studio North")
+        );
+        assert_eq!(
+            read_field(&edited, Tag::DateTimeOriginal).as_deref(),
+            Some("2024-12-15 19:05:30")
+        );
+        assert!(image::load_from_memory(&edited).is_ok());
+    }
+
+    #[test]
+    fn test_edit_jpeg_keeps_other_fields() {
+        // Big-endian chunk with a make (out of line) and an EXIF IFD holding an ISO value
+        let mut exif = b"MM\0*".to_vec();
+        exif.extend_from_slice(&8u32.to_be_bytes());
+        exif.extend_from_slice(&2u16.to_be_bytes());
+        exif.extend_from_slice(&0x010fu16.to_be_bytes());
+        exif.extend_from_slice(&ASCII.to_be_bytes());
+        exif.extend_from_slice(&6u32.to_be_bytes());
+        exif.extend_from_slice(&38u32.to_be_bytes());
+        exif.extend_from_slice(&TAG_EXIF_IFD.to_be_bytes());
+        exif.extend_from_slice(&LONG.to_be_bytes());
+        exif.extend_from_slice(&1u32.to_be_bytes());
+        exif.extend_from_slice(&44u32.to_be_bytes());
+        exif.extend_from_slice(&0u32.to_be_bytes());
+        exif.extend_from_slice(b"Nikon\0");
+        exif.extend_from_slice(&1u16.to_be_bytes());
+        exif.extend_from_slice(&0x8827u16.to_be_bytes());
+        exif.extend_from_slice(&3u16.to_be_bytes());
+        exif.extend_from_slice(&1u32.to_be_bytes());
+        exif.extend_from_slice(&[0x03, 0x20, 0, 0]);
+        exif.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut data = JPEG_SOI.to_vec();
+        data.extend_from_slice(&[0xff, JPEG_APP1]);
+        data.extend_from_slice(&((EXIF_HEADER.len() + exif.len() + 2) as u16).to_be_bytes());
+        data.extend_from_slice(EXIF_HEADER);
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&jpeg()[2..]);
+        assert_eq!(read_field(&data, Tag::Make).as_deref(), Some("Nikon"));
+
+        let edited = edit_jpeg(&data, &edits()).unwrap();
+        assert_eq!(read_field(&edited, Tag::Make).as_deref(), Some("Nikon"));
+        assert_eq!(
+            read_field(&edited, Tag::PhotographicSensitivity).as_deref(),
+            Some("800")
+        );
+        assert_eq!(
+            read_field(&edited, Tag::Artist).as_deref(),
+            Some("Jane Doe")
+        );
+        assert_eq!(
+            read_field(&edited, Tag::DateTimeOriginal).as_deref(),
+            Some("2024-12-15 19:05:30")
+        );
+        // One EXIF segment, replaced rather than added
+        assert_eq!(edited.windows(6).filter(|w| w == &EXIF_HEADER).count(), 1);
+
+        // Edited again, the first edit's values are replaced
+        let renamed = MetadataEdits {
+            artist: Some("Ann".to_string()),
+            ..MetadataEdits::default()
+        };
+        let edited = edit_jpeg(&edited, &renamed).unwrap();
+        assert_eq!(read_field(&edited, Tag::Artist).as_deref(), Some("Ann"));
+        assert_eq!(
+            read_field(&edited, Tag::Copyright).as_deref(),
+            Some("(c) 2024 Studio North")
+        );
+    }
+
+    #[test]
+    fn test_write_jpeg() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("DSC_0001.JPG");
+        std::fs::write(&path, jpeg()).unwrap();
+        write_jpeg(&path, &edits()).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(read_field(&data, Tag::Artist).as_deref(), Some("Jane Doe"));
+
+        std::fs::write(&path, b"not a jpeg").unwrap();
+        assert!(write_jpeg(&path, &edits()).is_err());
+    }
+
+    #[test]
+    fn test_merge_and_validate() {
+        let mut stored = edits();
+        stored.merge(&MetadataEdits {
+            artist: Some(" ".to_string()),
+            copyright: Some("Studio North".to_string()),
+            date_taken: None,
+        });
+        assert_eq!(stored.artist, None);
+        assert_eq!(stored.copyright.as_deref(), Some("Studio North"));
+        assert_eq!(stored.date_taken.as_deref(), Some("2024-12-15 19:05:30"));
+        assert!(stored.validate().is_ok());
+
+        let stamped = stored.or(&MetadataEdits {
+            artist: Some("Studio North".to_string()),
+            copyright: Some("(c) Studio North".to_string()),
+            date_taken: None,
+        });
+        assert_eq!(stamped.artist.as_deref(), Some("Studio North"));
+        assert_eq!(stamped.copyright.as_deref(), Some("Studio North"));
+
+        let bad_date = MetadataEdits {
+            date_taken: Some("2024:12:15 19:05:30".to_string()),
+            ..MetadataEdits::default()
+        };
+        assert!(bad_date.validate().is_err());
+    }
+
+    #[test]
+    fn test_correct_exif() {
+        let taken = |date: &str| ExifInfo {
+            date_taken: Some(date.to_string()),
+            ..ExifInfo::default()
+        };
+        let mut exif = taken("2024-12-15 20:05:30");
+        correct_exif(&mut exif, None, Some(-3600));
+        assert_eq!(exif.date_taken.as_deref(), Some("2024-12-15 19:05:30"));
+
+        let mut exif = taken("2024-12-15 20:05:30");
+        correct_exif(&mut exif, Some(&edits()), Some(-60));
+        assert_eq!(exif.date_taken.as_deref(), Some("2024-12-15 19:05:30"));
+    }
+}
@@ -0,0 +1,255 @@
+// IO that survives flaky network shares
+// A share that stops answering would otherwise block a command (and the UI waiting on it)
+// for as long as the OS keeps retrying, which on SMB can be minutes
+
+use crate::error::{GlimpseError, Result};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a share may take to answer a single metadata lookup
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a folder scan may go without reading a directory entry before the share is
+/// given up on; a large folder may take much longer as a whole
+pub const SCAN_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Attempts for operations failing with transient network errors
+const RETRY_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled for each further one
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Run `op` on a helper thread and stop waiting for it after `timeout`
+/// A timed-out call keeps its thread blocked until the OS gives up; its result is dropped
+pub fn with_timeout<T, F>(path: &Path, timeout: Duration, op: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(op());
+    });
+    rx.recv_timeout(timeout)
+        .unwrap_or_else(|_| Err(unreachable_error(path)))
+}
+
+/// Progress of an operation run by `with_stall_timeout`
+struct Heartbeat {
+    last: Mutex<Instant>,
+    abandoned: AtomicBool,
+}
+
+thread_local! {
+    static HEARTBEAT: RefCell<Option<Arc<Heartbeat>>> = const { RefCell::new(None) };
+}
+
+/// Run `op` on a helper thread and stop waiting for it once it goes `timeout` without
+/// calling `heartbeat`, so slow but progressing work isn't given up on
+/// The abandoned operation fails at its next `heartbeat` instead of running on unseen
+pub fn with_stall_timeout<T, F>(path: &Path, timeout: Duration, op: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let heartbeat = Arc::new(Heartbeat {
+        last: Mutex::new(Instant::now()),
+        abandoned: AtomicBool::new(false),
+    });
+    let (tx, rx) = mpsc::channel();
+    let watched = heartbeat.clone();
+    std::thread::spawn(move || {
+        HEARTBEAT.with(|current| *current.borrow_mut() = Some(watched));
+        let _ = tx.send(op());
+    });
+    loop {
+        let idle = heartbeat.last.lock().unwrap().elapsed();
+        if idle >= timeout {
+            heartbeat.abandoned.store(true, Ordering::Relaxed);
+            return Err(unreachable_error(path));
+        }
+        match rx.recv_timeout(timeout - idle) {
+            Ok(result) => return result,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(unreachable_error(path)),
+        }
+    }
+}
+
+/// Report progress of the `with_stall_timeout` operation running on this thread, if any
+/// Fails once that operation was given up on
+pub fn heartbeat(path: &Path) -> Result<()> {
+    HEARTBEAT.with(|current| match current.borrow().as_ref() {
+        Some(heartbeat) if heartbeat.abandoned.load(Ordering::Relaxed) => {
+            Err(unreachable_error(path))
+        }
+        Some(heartbeat) => {
+            *heartbeat.last.lock().unwrap() = Instant::now();
+            Ok(())
+        }
+        None => Ok(()),
+    })
+}
+
+/// Run `op`, retrying with exponential backoff while it fails with a network error
+/// Errors that persist are reported as an unreachable share at `path`
+pub fn with_retry<T, F>(path: &Path, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut delay = RETRY_BASE_DELAY;
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if is_network_error(&e) => {
+                if attempt == RETRY_ATTEMPTS {
+                    tracing::warn!(
+                        "Giving up on {} after {} attempts: {}",
+                        path.display(),
+                        attempt,
+                        e
+                    );
+                    return Err(unreachable_error(path));
+                }
+                tracing::debug!("Retrying {} in {:?}: {}", path.display(), delay, e);
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Fail fast with a clear error when the share holding `path` doesn't answer
+pub fn ensure_reachable(path: &Path) -> Result<()> {
+    let probe_path = path.to_path_buf();
+    with_timeout(path, PROBE_TIMEOUT, move || {
+        std::fs::metadata(&probe_path)
+            .map(|_| ())
+            .map_err(|e| GlimpseError::io_at(&probe_path, e))
+    })
+}
+
+fn unreachable_error(path: &Path) -> GlimpseError {
+    GlimpseError::ShareUnreachable(path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Errors a network share produces while it (temporarily) can't be reached
+fn is_network_error(error: &GlimpseError) -> bool {
+    let (GlimpseError::Io(e) | GlimpseError::FileIo { source: e, .. }) = error else {
+        return false;
+    };
+
+    // ERROR_BAD_NETPATH, ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_BAD_NET_NAME,
+    // ERROR_SEM_TIMEOUT, ERROR_NETWORK_UNREACHABLE
+    #[cfg(windows)]
+    if matches!(
+        e.raw_os_error(),
+        Some(53) | Some(59) | Some(64) | Some(67) | Some(121) | Some(1231)
+    ) {
+        return true;
+    }
+
+    use std::io::ErrorKind;
+    matches!(
+        e.kind(),
+        ErrorKind::TimedOut
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::NetworkDown
+            | ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::ErrorKind;
+
+    fn io_error(kind: ErrorKind) -> GlimpseError {
+        GlimpseError::Io(std::io::Error::from(kind))
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let path = Path::new("//nas/photos");
+        assert_eq!(
+            with_timeout(path, Duration::from_secs(5), || Ok(42)).unwrap(),
+            42
+        );
+
+        let result = with_timeout(path, Duration::from_millis(20), || {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        assert!(matches!(result, Err(GlimpseError::ShareUnreachable(p)) if p == "//nas/photos"));
+    }
+
+    #[test]
+    fn test_with_stall_timeout() {
+        let path = Path::new("//nas/photos");
+        // Slower than the timeout as a whole, but never idle that long
+        let result = with_stall_timeout(path, Duration::from_millis(100), move || {
+            for _ in 0..10 {
+                std::thread::sleep(Duration::from_millis(30));
+                heartbeat(path)?;
+            }
+            Ok(42)
+        });
+        assert_eq!(result.unwrap(), 42);
+
+        // A stalled operation is given up on and stops at its next heartbeat
+        let (tx, rx) = mpsc::channel();
+        let result: Result<()> = with_stall_timeout(path, Duration::from_millis(20), move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let _ = tx.send(heartbeat(path).is_err());
+            Ok(())
+        });
+        assert!(matches!(result, Err(GlimpseError::ShareUnreachable(_))));
+        assert!(rx.recv().unwrap());
+
+        // Outside of a watched operation heartbeats do nothing
+        assert!(heartbeat(path).is_ok());
+    }
+
+    #[test]
+    fn test_with_retry_recovers_from_network_errors() {
+        let attempts = Cell::new(0);
+        let result = with_retry(Path::new("//nas/photos"), || {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 2 {
+                Err(io_error(ErrorKind::TimedOut))
+            } else {
+                Ok("scanned")
+            }
+        });
+        assert_eq!(result.unwrap(), "scanned");
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[test]
+    fn test_with_retry_gives_up() {
+        // Other errors aren't retried
+        let attempts = Cell::new(0);
+        let result: Result<()> = with_retry(Path::new("//nas/photos"), || {
+            attempts.set(attempts.get() + 1);
+            Err(io_error(ErrorKind::NotFound))
+        });
+        assert!(matches!(result, Err(GlimpseError::Io(_))));
+        assert_eq!(attempts.get(), 1);
+
+        let attempts = Cell::new(0);
+        let result: Result<()> = with_retry(Path::new("//nas/photos"), || {
+            attempts.set(attempts.get() + 1);
+            Err(io_error(ErrorKind::ConnectionReset))
+        });
+        assert!(matches!(result, Err(GlimpseError::ShareUnreachable(_))));
+        assert_eq!(attempts.get(), RETRY_ATTEMPTS);
+    }
+}
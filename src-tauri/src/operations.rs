@@ -0,0 +1,269 @@
+// Recovery of destructive operations a crash interrupted, from the operation journal
+// Each file's journal state says how far it got, so the operation can be finished or undone
+
+use crate::database::{Database, Operation, OperationFile, OperationFileState, OperationKind};
+use crate::error::{GlimpseError, Result};
+use crate::export::{self, ExportOptions};
+use crate::{image_processor, trash};
+use std::path::Path;
+
+/// What to do with an interrupted operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    /// Finish the remaining files
+    Resume,
+    /// Put every file back where it was before the operation
+    Rollback,
+    /// Forget the operation and leave the files as they are
+    Dismiss,
+}
+
+/// Files processed while resolving an operation
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ResolveOutcome {
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// Resolve an interrupted operation; the journal entry is removed when no file failed
+pub fn resolve(
+    db: &Database,
+    operation: &Operation,
+    resolution: Resolution,
+) -> Result<ResolveOutcome> {
+    let mut outcome = ResolveOutcome::default();
+    if resolution != Resolution::Dismiss {
+        let options: ExportOptions = operation
+            .params
+            .as_deref()
+            .and_then(|params| serde_json::from_str(params).ok())
+            .unwrap_or_default();
+
+        for file in db.get_operation_files(operation.id)? {
+            let result = match (operation.kind, resolution) {
+                (OperationKind::ExportMove, Resolution::Resume) => {
+                    resume_move(db, operation.id, &file, &options)
+                }
+                (OperationKind::ExportMove, _) => rollback_move(&file),
+                (OperationKind::Delete, Resolution::Resume) => resume_delete(db, operation, &file),
+                (OperationKind::Delete, _) => rollback_delete(&file),
+            };
+            match result {
+                Ok(()) => outcome.completed += 1,
+                Err(e) => {
+                    tracing::warn!("Could not recover {}: {}", file.source, e);
+                    outcome.failed += 1;
+                }
+            }
+        }
+    }
+
+    if outcome.failed == 0 {
+        db.finish_operation(operation.id)?;
+    }
+    Ok(outcome)
+}
+
+/// Write the file to the destination if that never finished, then delete the original
+fn resume_move(
+    db: &Database,
+    operation_id: i64,
+    file: &OperationFile,
+    options: &ExportOptions,
+) -> Result<()> {
+    let source = Path::new(&file.source);
+    let destination = Path::new(&file.destination);
+    if file.state == OperationFileState::Done || !source.exists() {
+        return Ok(());
+    }
+    // A pending file's destination may be half-written, so it is written again
+    if file.state == OperationFileState::Pending {
+        export::export_file(source, destination, options)?;
+        db.set_operation_file_state(operation_id, &file.source, OperationFileState::Copied)?;
+    }
+    std::fs::remove_file(source).map_err(|e| GlimpseError::io_at(source, e))?;
+    db.set_operation_file_state(operation_id, &file.source, OperationFileState::Done)
+}
+
+/// Remove the exported copy, or bring it back if the original is already gone
+/// A restored JPEG is the exported version, which may have had private metadata stripped
+fn rollback_move(file: &OperationFile) -> Result<()> {
+    let source = Path::new(&file.source);
+    let destination = Path::new(&file.destination);
+    if !destination.exists() {
+        return Ok(());
+    }
+    if source.exists() {
+        return std::fs::remove_file(destination).map_err(|e| GlimpseError::io_at(destination, e));
+    }
+    if std::fs::rename(destination, source).is_err() {
+        std::fs::copy(destination, source)
+            .and_then(|_| std::fs::remove_file(destination))
+            .map_err(|e| GlimpseError::io_at(destination, e))?;
+    }
+    Ok(())
+}
+
+/// Delete the file if that never happened, then forget it and its cache
+fn resume_delete(db: &Database, operation: &Operation, file: &OperationFile) -> Result<()> {
+    let source = Path::new(&file.source);
+    if file.state != OperationFileState::Done && source.exists() {
+        if operation.destination == "trash" {
+            trash::move_to_trash(source)?;
+        } else {
+            std::fs::remove_file(source).map_err(|e| GlimpseError::io_at(source, e))?;
+        }
+    }
+    image_processor::remove_image_cache(&operation.session_id, &file.destination)?;
+    db.forget_file(&operation.session_id, &file.destination)
+}
+
+/// Nothing to undo while the file is still there; a deleted one can only come back
+/// from the trash
+fn rollback_delete(file: &OperationFile) -> Result<()> {
+    let source = Path::new(&file.source);
+    if source.exists() {
+        return Ok(());
+    }
+    Err(GlimpseError::io_at(
+        source,
+        std::io::Error::other("Deleted files can only be restored from the trash"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Session;
+    use tempfile::tempdir;
+
+    /// An export-move of three files that crashed after finishing one and copying another
+    fn interrupted_move(db: &Database, dir: &Path) -> Operation {
+        db.upsert_session(&Session {
+            id: "session".to_string(),
+            folder_path: dir.join("src").to_string_lossy().to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 3,
+        })
+        .unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("dst")).unwrap();
+        let files: Vec<(String, String)> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let path = |folder: &str| dir.join(folder).join(format!("{}.NEF", name));
+                (
+                    path("src").to_string_lossy().to_string(),
+                    path("dst").to_string_lossy().to_string(),
+                )
+            })
+            .collect();
+        let id = db
+            .begin_operation("session", OperationKind::ExportMove, "dst", None, &files)
+            .unwrap();
+
+        // a: moved, b: copied but original not yet deleted, c: not started
+        std::fs::write(&files[0].1, b"a").unwrap();
+        db.set_operation_file_state(id, &files[0].0, OperationFileState::Done)
+            .unwrap();
+        std::fs::write(&files[1].0, b"b").unwrap();
+        std::fs::write(&files[1].1, b"b").unwrap();
+        db.set_operation_file_state(id, &files[1].0, OperationFileState::Copied)
+            .unwrap();
+        std::fs::write(&files[2].0, b"c").unwrap();
+        db.get_operation(id).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_resume_move() {
+        let db = Database::open_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        let operation = interrupted_move(&db, dir.path());
+
+        let outcome = resolve(&db, &operation, Resolution::Resume).unwrap();
+        assert_eq!(
+            outcome,
+            ResolveOutcome {
+                completed: 3,
+                failed: 0
+            }
+        );
+        for name in ["a", "b", "c"] {
+            assert!(!dir
+                .path()
+                .join("src")
+                .join(format!("{}.NEF", name))
+                .exists());
+            assert!(dir
+                .path()
+                .join("dst")
+                .join(format!("{}.NEF", name))
+                .exists());
+        }
+        assert!(db.get_operation(operation.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rollback_move() {
+        let db = Database::open_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        let operation = interrupted_move(&db, dir.path());
+
+        resolve(&db, &operation, Resolution::Rollback).unwrap();
+        for name in ["a", "b", "c"] {
+            assert!(dir
+                .path()
+                .join("src")
+                .join(format!("{}.NEF", name))
+                .exists());
+            assert!(!dir
+                .path()
+                .join("dst")
+                .join(format!("{}.NEF", name))
+                .exists());
+        }
+        assert!(db.get_operation(operation.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resume_delete() {
+        let db = Database::open_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        db.upsert_session(&Session {
+            id: "session".to_string(),
+            folder_path: dir.path().to_string_lossy().to_string(),
+            last_opened: None,
+            last_selected_index: 0,
+            total_files: 2,
+        })
+        .unwrap();
+        // a: crashed before the file was deleted, b: deleted but still labeled
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        std::fs::write(path("a.NEF"), b"a").unwrap();
+        let files = [
+            (path("a.NEF"), "a.NEF".to_string()),
+            (path("b.NEF"), "b.NEF".to_string()),
+        ];
+        let id = db
+            .begin_operation("session", OperationKind::Delete, "", None, &files)
+            .unwrap();
+        db.set_operation_file_state(id, &files[1].0, OperationFileState::Done)
+            .unwrap();
+        for (_, name) in &files {
+            db.set_label("session", name, Some("reject")).unwrap();
+        }
+        let operation = db.get_operation(id).unwrap().unwrap();
+        assert_eq!(operation.kind, OperationKind::Delete);
+
+        // Rolling back can't bring b back
+        let outcome = resolve(&db, &operation, Resolution::Rollback).unwrap();
+        assert_eq!(outcome.failed, 1);
+
+        resolve(&db, &operation, Resolution::Resume).unwrap();
+        assert!(!dir.path().join("a.NEF").exists());
+        assert!(db.get_labels("session").unwrap().is_empty());
+        assert!(db.get_operation(id).unwrap().is_none());
+    }
+}
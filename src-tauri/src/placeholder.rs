@@ -0,0 +1,54 @@
+// Cloud "online-only" placeholder files (OneDrive Files On-Demand, iCloud Drive)
+// Their data isn't on disk; reading one makes the sync client download the whole file
+
+use std::path::Path;
+
+/// Whether `path` is a placeholder whose content would have to be downloaded first
+/// Only reads metadata, which doesn't trigger a download
+pub fn is_cloud_placeholder(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| is_placeholder_metadata(&metadata))
+}
+
+#[cfg(windows)]
+fn is_placeholder_metadata(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+    metadata.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE
+            | FILE_ATTRIBUTE_RECALL_ON_OPEN
+            | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(target_os = "macos")]
+fn is_placeholder_metadata(metadata: &std::fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+
+    // Evicted iCloud Drive (and other File Provider) files are "dataless"
+    const SF_DATALESS: u32 = 0x4000_0000;
+    metadata.st_flags() & SF_DATALESS != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn is_placeholder_metadata(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_local_files_are_not_placeholders() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, b"local data").unwrap();
+
+        assert!(!is_cloud_placeholder(&path));
+        assert!(!is_cloud_placeholder(&dir.path().join("missing.jpg")));
+    }
+}
@@ -0,0 +1,170 @@
+// Battery detection and the low-power mode used while culling on location
+// In low-power mode thumbnail generation uses fewer threads and analysis jobs are paused
+
+use crate::config::PowerMode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the power source is checked in auto mode
+pub const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+static LOW_POWER: AtomicBool = AtomicBool::new(false);
+
+/// Power state reported to the frontend
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct PowerStatus {
+    pub mode: PowerMode,
+    /// None when the machine doesn't report a power source (most desktops)
+    pub on_battery: Option<bool>,
+    pub low_power: bool,
+}
+
+/// Whether low-power mode is in effect, as of the last `refresh`
+pub fn is_low_power() -> bool {
+    LOW_POWER.load(Ordering::Relaxed)
+}
+
+/// Re-evaluate low-power mode for `mode` and the current power source
+pub fn refresh(mode: PowerMode) -> PowerStatus {
+    let on_battery = on_battery();
+    let low_power = match mode {
+        PowerMode::Auto => on_battery.unwrap_or(false),
+        PowerMode::Saver => true,
+        PowerMode::Performance => false,
+    };
+    LOW_POWER.store(low_power, Ordering::Relaxed);
+    PowerStatus {
+        mode,
+        on_battery,
+        low_power,
+    }
+}
+
+/// Thumbnail threads to use in low-power mode
+pub fn low_power_threads(threads: usize) -> usize {
+    (threads / 2).max(1)
+}
+
+/// Whether the machine is running on battery
+pub fn on_battery() -> Option<bool> {
+    platform::on_battery()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn on_battery() -> Option<bool> {
+        super::on_battery_from(std::path::Path::new("/sys/class/power_supply"))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    pub fn on_battery() -> Option<bool> {
+        let output = std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        if output.contains("'Battery Power'") {
+            Some(true)
+        } else if output.contains("'AC Power'") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    pub fn on_battery() -> Option<bool> {
+        // SAFETY: SYSTEM_POWER_STATUS is plain data filled in by the call
+        let status = unsafe {
+            let mut status: SYSTEM_POWER_STATUS = std::mem::zeroed();
+            if GetSystemPowerStatus(&mut status) == 0 {
+                return None;
+            }
+            status
+        };
+        match status.ACLineStatus {
+            0 => Some(true),
+            1 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub fn on_battery() -> Option<bool> {
+        None
+    }
+}
+
+/// Power source from a sysfs `power_supply` directory
+/// With a mains adapter listed, on battery means it's offline; otherwise a discharging battery
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn on_battery_from(dir: &std::path::Path) -> Option<bool> {
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut mains_online = None;
+    let mut discharging = None;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let supply = entry.path();
+        match read(supply.join("type")).as_str() {
+            "Mains" => {
+                let online = read(supply.join("online")) == "1";
+                mains_online = Some(mains_online.unwrap_or(false) || online);
+            }
+            "Battery" => {
+                let status = read(supply.join("status"));
+                discharging = Some(discharging.unwrap_or(false) || status == "Discharging");
+            }
+            _ => {}
+        }
+    }
+    mains_online.map(|online| !online).or(discharging)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn supply(dir: &std::path::Path, name: &str, files: &[(&str, &str)]) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        for (file, content) in files {
+            std::fs::write(path.join(file), format!("{}\n", content)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_on_battery_from_sysfs() {
+        let dir = tempdir().unwrap();
+        assert_eq!(on_battery_from(dir.path()), None);
+
+        supply(
+            dir.path(),
+            "BAT0",
+            &[("type", "Battery"), ("status", "Discharging")],
+        );
+        assert_eq!(on_battery_from(dir.path()), Some(true));
+
+        supply(dir.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(on_battery_from(dir.path()), Some(false));
+        supply(dir.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(on_battery_from(dir.path()), Some(true));
+    }
+
+    #[test]
+    fn test_low_power_threads() {
+        assert_eq!(low_power_threads(1), 1);
+        assert_eq!(low_power_threads(6), 3);
+    }
+}
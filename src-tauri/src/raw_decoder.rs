@@ -0,0 +1,465 @@
+// RAW decoding backends
+// rawloader (with imagepipe) is the default; rawler covers bodies rawloader doesn't know yet
+// (CR3, recent Sony and Nikon models) and is compiled in with the `rawler` feature.
+// An external dcraw/dcraw_emu can be configured as a last resort
+
+use crate::config::{RawBackend, WhiteBalance};
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::{external_path, RawAdjustments};
+use image::DynamicImage;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How a RAW file is developed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DevelopOptions {
+    pub white_balance: WhiteBalance,
+    pub adjustments: RawAdjustments,
+    /// Develop at no more than this many pixels; None demosaics at full resolution
+    pub max_size: Option<u32>,
+}
+
+/// Decodes and develops RAW files to sRGB
+pub trait RawDecoder: Send + Sync {
+    fn develop(&self, path: &Path, options: &DevelopOptions) -> Result<DynamicImage>;
+}
+
+/// The decoder of `backend`
+pub fn backend(backend: RawBackend) -> &'static dyn RawDecoder {
+    match backend {
+        RawBackend::Rawloader => &Rawloader,
+        #[cfg(feature = "rawler")]
+        RawBackend::Rawler => &Rawler,
+        // Rejected by config validation in builds without rawler
+        #[cfg(not(feature = "rawler"))]
+        RawBackend::Rawler => &Rawloader,
+    }
+}
+
+/// rawloader decoding with the imagepipe pipeline
+pub struct Rawloader;
+
+impl RawDecoder for Rawloader {
+    fn develop(&self, path: &Path, options: &DevelopOptions) -> Result<DynamicImage> {
+        let mut raw_image =
+            rawloader::decode_file(path).map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+        if options.white_balance == WhiteBalance::Auto {
+            if let Some(coeffs) = gray_world_wb(&raw_image) {
+                raw_image.wb_coeffs = coeffs;
+            }
+        }
+
+        // Process RAW data and convert to RGB image
+        let mut pipeline =
+            imagepipe::Pipeline::new_from_source(imagepipe::ImageSource::Raw(raw_image))
+                .map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+        if let Some(size) = options.max_size {
+            pipeline.globals.settings.maxwidth = size as usize;
+            pipeline.globals.settings.maxheight = size as usize;
+        }
+        pipeline.ops.basecurve.exposure += options.adjustments.exposure;
+        if options.white_balance == WhiteBalance::Daylight {
+            pipeline.ops.tolab.set_temp(DAYLIGHT_TEMP, 1.0);
+        }
+        if options.adjustments.wb_temp.is_some() || options.adjustments.wb_tint.is_some() {
+            let (shot_temp, shot_tint) = pipeline.ops.tolab.get_temp();
+            pipeline.ops.tolab.set_temp(
+                options.adjustments.wb_temp.unwrap_or(shot_temp),
+                options.adjustments.wb_tint.unwrap_or(shot_tint),
+            );
+        }
+
+        let srgb_image = pipeline
+            .output_8bit(None)
+            .map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+
+        let width = srgb_image.width;
+        let height = srgb_image.height;
+        let pixels = srgb_image.data;
+
+        let img =
+            image::RgbImage::from_raw(width as u32, height as u32, pixels).ok_or_else(|| {
+                GlimpseError::RawProcessing("Failed to create image from raw data".into())
+            })?;
+
+        Ok(DynamicImage::ImageRgb8(img))
+    }
+}
+
+/// dcraw or LibRaw's dcraw_emu run as a separate process, writing a PPM to stdout
+/// Develops with the camera's white balance; adjustments aren't applied
+pub struct External {
+    pub binary: PathBuf,
+}
+
+impl External {
+    fn args(&self, path: &Path, options: &DevelopOptions) -> Vec<std::ffi::OsString> {
+        let is_dcraw_emu = self
+            .binary
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().contains("dcraw_emu"));
+        let mut args: Vec<std::ffi::OsString> = vec!["-w".into()];
+        // Half-size output skips demosaicing, plenty for thumbnails
+        if options.max_size.is_some() {
+            args.push("-h".into());
+        }
+        if is_dcraw_emu {
+            args.extend(["-Z".into(), "-".into()]);
+        } else {
+            args.push("-c".into());
+        }
+        args.push(external_path(path).into_os_string());
+        args
+    }
+}
+
+impl RawDecoder for External {
+    fn develop(&self, path: &Path, options: &DevelopOptions) -> Result<DynamicImage> {
+        let output = Command::new(&self.binary)
+            .args(self.args(path, options))
+            .output()
+            .map_err(|e| GlimpseError::io_at(&self.binary, e))?;
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(GlimpseError::RawProcessing(format!(
+                "{} failed for {}: {}",
+                self.binary.display(),
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(image::load_from_memory_with_format(
+            &output.stdout,
+            image::ImageFormat::Pnm,
+        )?)
+    }
+}
+
+/// rawler decoding with its own development
+/// A color temperature goes into the white balance rawler develops with; gray world, tint
+/// and exposure are applied to the developed image
+#[cfg(feature = "rawler")]
+pub struct Rawler;
+
+#[cfg(feature = "rawler")]
+impl RawDecoder for Rawler {
+    fn develop(&self, path: &Path, options: &DevelopOptions) -> Result<DynamicImage> {
+        let mut raw =
+            rawler::decode_file(path).map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+        let adjustments = &options.adjustments;
+        let temp = match options.white_balance {
+            WhiteBalance::Daylight => Some(adjustments.wb_temp.unwrap_or(DAYLIGHT_TEMP)),
+            _ => adjustments.wb_temp,
+        };
+        if let Some(temp) = temp {
+            let coeffs = rawler_xyz_to_cam(&raw)
+                .and_then(|matrix| temp_wb_coeffs(matrix, temp, adjustments.wb_tint.unwrap_or(1.0)))
+                .ok_or_else(|| {
+                    GlimpseError::RawProcessing(format!("No color matrix for {}", path.display()))
+                })?;
+            let [r, b] = [coeffs[0], coeffs[2]].map(|c| c * raw.wb_coeffs[1]);
+            raw.wb_coeffs = [r, raw.wb_coeffs[1], b, raw.wb_coeffs[3]];
+        }
+
+        let developed = rawler::imgop::develop::RawDevelop::default()
+            .develop_intermediate(&raw)
+            .map_err(|e| GlimpseError::RawProcessing(e.to_string()))?;
+        // Always developed at full size; callers scale the result down
+        let img = developed.to_dynamic_image().ok_or_else(|| {
+            GlimpseError::RawProcessing("Failed to create image from raw data".into())
+        })?;
+
+        let auto = temp.is_none() && options.white_balance == WhiteBalance::Auto;
+        let tint = match temp {
+            Some(_) => 1.0,
+            None => adjustments.wb_tint.unwrap_or(1.0),
+        };
+        if !auto && tint == 1.0 && adjustments.exposure == 0.0 {
+            return Ok(img);
+        }
+        let mut rgb = img.to_rgb8();
+        let mut gains = if auto {
+            gray_world_gains(&rgb)
+        } else {
+            [1.0; 3]
+        };
+        gains[1] *= tint;
+        apply_gains(&mut rgb, gains.map(|g| g * 2f32.powf(adjustments.exposure)));
+        Ok(DynamicImage::ImageRgb8(rgb))
+    }
+}
+
+/// The camera's XYZ to camera matrix for D65 light
+#[cfg(feature = "rawler")]
+fn rawler_xyz_to_cam(raw: &rawler::RawImage) -> Option<[[f32; 3]; 3]> {
+    let matrix = raw.color_matrix.get(&rawler::imgop::xyz::Illuminant::D65)?;
+    (matrix.len() >= 9)
+        .then(|| std::array::from_fn(|row| std::array::from_fn(|col| matrix[row * 3 + col])))
+}
+
+/// XYZ (Y = 1) of a black body at `temp` Kelvin, with Y divided by `tint`
+/// Kim et al.'s cubic approximation of the Planckian locus, valid from 1667 to 25000 K
+#[cfg(any(feature = "rawler", test))]
+fn planckian_xyz(temp: f32, tint: f32) -> [f32; 3] {
+    let t = temp.clamp(1667.0, 25000.0) as f64;
+    let x = if t < 4000.0 {
+        -0.266_123_9e9 / t.powi(3) - 0.234_358_9e6 / t.powi(2) + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t.powi(3) + 2.107_037_9e6 / t.powi(2) + 0.222_634_7e3 / t + 0.240_390
+    };
+    let y = if t < 2222.0 {
+        -1.106_381_4 * x.powi(3) - 1.348_110_20 * x.powi(2) + 2.185_558_32 * x - 0.202_196_83
+    } else if t < 4000.0 {
+        -0.954_947_6 * x.powi(3) - 1.374_185_93 * x.powi(2) + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x.powi(3) - 5.873_386_70 * x.powi(2) + 3.751_129_97 * x - 0.370_014_83
+    };
+    [(x / y) as f32, 1.0 / tint, ((1.0 - x - y) / y) as f32]
+}
+
+/// White balance coefficients (red, green, blue; green 1.0) that render light of `temp`
+/// and `tint` neutral on a camera with the given XYZ to camera matrix
+#[cfg(any(feature = "rawler", test))]
+fn temp_wb_coeffs(xyz_to_cam: [[f32; 3]; 3], temp: f32, tint: f32) -> Option<[f32; 3]> {
+    let white = planckian_xyz(temp, tint);
+    let cam: [f32; 3] = std::array::from_fn(|c| (0..3).map(|i| xyz_to_cam[c][i] * white[i]).sum());
+    if cam.iter().any(|&v| v <= 0.0 || !v.is_finite()) {
+        return None;
+    }
+    Some([cam[1] / cam[0], 1.0, cam[1] / cam[2]])
+}
+
+/// Linear light of an sRGB value
+#[cfg(any(feature = "rawler", test))]
+fn srgb_to_linear(v: u8) -> f32 {
+    let v = v as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Gray-world gains of a developed image, in linear light: each channel scaled so its
+/// average matches green. Clipped pixels are left out
+#[cfg(any(feature = "rawler", test))]
+fn gray_world_gains(img: &image::RgbImage) -> [f32; 3] {
+    let to_linear: [f32; 256] = std::array::from_fn(|v| srgb_to_linear(v as u8));
+    let clipped = (255.0 * CLIPPED_FRACTION) as u8;
+    let mut sums = [0f64; 3];
+    for pixel in img.pixels().filter(|p| p.0.iter().all(|&v| v < clipped)) {
+        for c in 0..3 {
+            sums[c] += to_linear[pixel.0[c] as usize] as f64;
+        }
+    }
+    if sums.iter().any(|&sum| sum <= 0.0) {
+        return [1.0; 3];
+    }
+    std::array::from_fn(|c| (sums[1] / sums[c]) as f32)
+}
+
+/// Scale each channel of an sRGB image by `gains` in linear light
+#[cfg(any(feature = "rawler", test))]
+fn apply_gains(img: &mut image::RgbImage, gains: [f32; 3]) {
+    let to_srgb = |v: f32| {
+        let v = v.clamp(0.0, 1.0);
+        let v = if v <= 0.003_130_8 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        };
+        (v * 255.0).round() as u8
+    };
+    let luts: [[u8; 256]; 3] = std::array::from_fn(|c| {
+        std::array::from_fn(|v| to_srgb(srgb_to_linear(v as u8) * gains[c]))
+    });
+    for pixel in img.pixels_mut() {
+        for c in 0..3 {
+            pixel.0[c] = luts[c][pixel.0[c] as usize];
+        }
+    }
+}
+
+/// Color temperature of the daylight white balance
+const DAYLIGHT_TEMP: f32 = 5500.0;
+/// Fraction of the white level above which a photosite counts as clipped
+const CLIPPED_FRACTION: f32 = 0.95;
+
+/// Gray-world white balance of a Bayer RAW image: each channel scaled so its average
+/// matches green. None for images it can't measure (linear DNGs, float data)
+fn gray_world_wb(raw: &rawloader::RawImage) -> Option<[f32; 4]> {
+    let rawloader::RawImageData::Integer(data) = &raw.data else {
+        return None;
+    };
+    if raw.cpp != 1 || !raw.cfa.is_valid() {
+        return None;
+    }
+    gray_world_coeffs(
+        data,
+        raw.width,
+        |row, col| raw.cfa.color_at(row, col),
+        raw.blacklevels,
+        raw.whitelevels,
+    )
+}
+
+/// Gray-world coefficients from mosaiced photosites; clipped ones are left out
+fn gray_world_coeffs(
+    data: &[u16],
+    width: usize,
+    color_at: impl Fn(usize, usize) -> usize,
+    black: [u16; 4],
+    white: [u16; 4],
+) -> Option<[f32; 4]> {
+    if width == 0 {
+        return None;
+    }
+    let mut sums = [0f64; 4];
+    let mut counts = [0u64; 4];
+    for (index, &value) in data.iter().enumerate() {
+        let color = color_at(index / width, index % width);
+        if color > 3 {
+            continue;
+        }
+        let (black, white) = (black[color] as f32, white[color] as f32);
+        let value = value as f32;
+        if value >= black + (white - black) * CLIPPED_FRACTION {
+            continue;
+        }
+        sums[color] += (value - black).max(0.0) as f64;
+        counts[color] += 1;
+    }
+
+    let means: Vec<f64> = (0..4).map(|c| sums[c] / counts[c].max(1) as f64).collect();
+    let green = means[1];
+    if green <= 0.0 || means[0] <= 0.0 || means[2] <= 0.0 {
+        return None;
+    }
+    let coeff = |c: usize| {
+        if counts[c] == 0 || means[c] <= 0.0 {
+            f32::NAN
+        } else {
+            (green / means[c]) as f32
+        }
+    };
+    Some([coeff(0), 1.0, coeff(2), coeff(3)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_args() {
+        let options = DevelopOptions {
+            max_size: Some(400),
+            ..Default::default()
+        };
+        let dcraw = External {
+            binary: PathBuf::from("/usr/bin/dcraw"),
+        };
+        assert_eq!(
+            dcraw.args(Path::new("/shoot/a.NEF"), &options),
+            ["-w", "-h", "-c", "/shoot/a.NEF"]
+        );
+        let dcraw_emu = External {
+            binary: PathBuf::from("/opt/libraw/bin/dcraw_emu"),
+        };
+        assert_eq!(
+            dcraw_emu.args(Path::new("/shoot/a.NEF"), &DevelopOptions::default()),
+            ["-w", "-Z", "-", "/shoot/a.NEF"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_develop() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("dcraw");
+        std::fs::write(
+            &binary,
+            "#!/bin/sh\nprintf 'P6\\n2 1\\n255\\n'; printf '\\377\\000\\000\\000\\377\\000'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let external = External { binary };
+        let img = external
+            .develop(Path::new("a.NEF"), &DevelopOptions::default())
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(img.dimensions(), (2, 1));
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0]);
+
+        let failing = External {
+            binary: PathBuf::from("/bin/false"),
+        };
+        assert!(failing
+            .develop(Path::new("a.NEF"), &DevelopOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_gray_world_coeffs() {
+        // RGGB mosaic of a scene with a blue cast
+        let rggb = |row: usize, col: usize| [[0, 1], [1, 2]][row % 2][col % 2];
+        let mut data = vec![];
+        for _ in 0..2 {
+            data.extend([164, 264, 164, 264]);
+            data.extend([264, 564, 264, 564]);
+        }
+        let coeffs = gray_world_coeffs(&data, 4, rggb, [64; 4], [4095; 4]).unwrap();
+        assert_eq!(coeffs[1], 1.0);
+        assert!((coeffs[0] - 2.0).abs() < 1e-4);
+        assert!((coeffs[2] - 0.4).abs() < 1e-4);
+
+        // Clipped photosites don't count
+        data[5] = 4095;
+        let clipped = gray_world_coeffs(&data, 4, rggb, [64; 4], [4095; 4]).unwrap();
+        assert_eq!(clipped[..3], coeffs[..3]);
+        // Nothing to measure in a black frame
+        assert_eq!(
+            gray_world_coeffs(&[64; 16], 4, rggb, [64; 4], [4095; 4]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_temp_wb_coeffs() {
+        // 5000 K lies at x 0.3451, y 0.3516 on the Planckian locus
+        let [x, y, z] = planckian_xyz(5000.0, 1.0);
+        assert!((x / (x + y + z) - 0.3451).abs() < 1e-3);
+        assert!((y / (x + y + z) - 0.3516).abs() < 1e-3);
+
+        // Warmer light needs more blue and less red
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        let warm = temp_wb_coeffs(identity, 3000.0, 1.0).unwrap();
+        let cool = temp_wb_coeffs(identity, 8000.0, 1.0).unwrap();
+        assert_eq!(warm[1], 1.0);
+        assert!(warm[0] < cool[0] && warm[2] > cool[2]);
+        // A magenta tint lowers green relative to red and blue
+        let tinted = temp_wb_coeffs(identity, 3000.0, 2.0).unwrap();
+        assert!(tinted[0] < warm[0] && tinted[2] < warm[2]);
+        assert_eq!(temp_wb_coeffs([[0.0; 3]; 3], 5000.0, 1.0), None);
+    }
+
+    #[test]
+    fn test_gray_world_gains() {
+        // A gray scene with a blue cast, and one clipped highlight
+        let mut img = image::RgbImage::from_pixel(4, 4, image::Rgb([100, 120, 160]));
+        img.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+        let gains = gray_world_gains(&img);
+        assert_eq!(gains[1], 1.0);
+        assert!(gains[0] > 1.0 && gains[2] < 1.0);
+
+        apply_gains(&mut img, gains);
+        let [r, g, b] = img.get_pixel(1, 1).0;
+        assert!(r.abs_diff(g) <= 1 && b.abs_diff(g) <= 1);
+        // Doubling linear light is one stop brighter
+        let mut gray = image::RgbImage::from_pixel(1, 1, image::Rgb([118; 3]));
+        apply_gains(&mut gray, [2.0; 3]);
+        assert_eq!(gray.get_pixel(0, 0).0, [162; 3]);
+        assert_eq!(gray_world_gains(&image::RgbImage::new(2, 2)), [1.0; 3]);
+    }
+}
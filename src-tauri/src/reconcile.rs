@@ -0,0 +1,146 @@
+// Recognition of renamed files, so their labels aren't lost
+// A file renamed outside Glimpse (by an importer or a batch-rename tool) shows up as an
+// unlabeled new file while its label stays behind on a name that no longer exists
+
+use crate::database::{FileIdentity, Label};
+use crate::image_processor::ImageInfo;
+use std::collections::{HashMap, HashSet};
+
+/// Label of a vanished file proposed for a new file with the same size and modification time
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LabelTransfer {
+    /// Name the label is stored under, no longer in the folder
+    pub from: String,
+    /// Unlabeled file that looks like the same file renamed
+    pub to: String,
+    pub label: String,
+}
+
+/// Transfers for labels whose file vanished while an unlabeled file with the same size and
+/// modification time appeared. Only one-to-one matches are proposed; when several vanished or
+/// new files share an identity (bursts written in the same minute) there's no telling them apart
+pub fn propose_transfers(
+    images: &[ImageInfo],
+    labels: &[Label],
+    identities: &HashMap<String, FileIdentity>,
+) -> Vec<LabelTransfer> {
+    let present: HashSet<&str> = images.iter().map(|image| image.filename.as_str()).collect();
+    let labeled: HashSet<&str> = labels.iter().map(|label| label.filename.as_str()).collect();
+
+    let mut gaps: HashMap<FileIdentity, Vec<&str>> = HashMap::new();
+    for image in images {
+        if !labeled.contains(image.filename.as_str()) {
+            let identity = FileIdentity {
+                size: image.size,
+                modified_at: image.modified_at.clone(),
+            };
+            gaps.entry(identity).or_default().push(&image.filename);
+        }
+    }
+
+    let mut vanished: HashMap<&FileIdentity, Vec<(&str, &str)>> = HashMap::new();
+    for label in labels {
+        if present.contains(label.filename.as_str()) {
+            continue;
+        }
+        let (Some(value), Some(identity)) = (&label.label, identities.get(&label.filename)) else {
+            continue;
+        };
+        vanished
+            .entry(identity)
+            .or_default()
+            .push((&label.filename, value));
+    }
+
+    let mut transfers: Vec<LabelTransfer> = vanished
+        .into_iter()
+        .filter_map(
+            |(identity, old)| match (old.as_slice(), gaps.get(identity)?.as_slice()) {
+                ([(from, label)], [to]) => Some(LabelTransfer {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    label: label.to_string(),
+                }),
+                _ => None,
+            },
+        )
+        .collect();
+    transfers.sort_by(|a, b| a.to.cmp(&b.to));
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(filename: &str, value: &str) -> Label {
+        Label {
+            filename: filename.to_string(),
+            label: Some(value.to_string()),
+        }
+    }
+
+    fn identity(size: u64, modified_at: &str) -> FileIdentity {
+        FileIdentity {
+            size,
+            modified_at: modified_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_propose_transfers() {
+        let images = vec![
+            ImageInfo::fixture("2024-12-15_0001.NEF")
+                .with_size(100)
+                .with_modified_at("2024/12/15 10:00"),
+            ImageInfo::fixture("2024-12-15_0002.NEF")
+                .with_size(200)
+                .with_modified_at("2024/12/15 10:01"),
+            // Same identity twice: ambiguous
+            ImageInfo::fixture("2024-12-15_0003.NEF")
+                .with_size(300)
+                .with_modified_at("2024/12/15 10:02"),
+            ImageInfo::fixture("2024-12-15_0004.NEF")
+                .with_size(300)
+                .with_modified_at("2024/12/15 10:02"),
+            ImageInfo::fixture("DSC_0005.NEF")
+                .with_size(500)
+                .with_modified_at("2024/12/15 10:03"),
+        ];
+        let labels = vec![
+            label("DSC_0001.NEF", "adopted"),
+            label("DSC_0002.NEF", "rejected"),
+            label("DSC_0003.NEF", "adopted"),
+            // Still there, stays as it is
+            label("DSC_0005.NEF", "adopted"),
+        ];
+        let identities = HashMap::from([
+            (
+                "DSC_0001.NEF".to_string(),
+                identity(100, "2024/12/15 10:00"),
+            ),
+            // Edited since: the size no longer matches
+            (
+                "DSC_0002.NEF".to_string(),
+                identity(199, "2024/12/15 10:01"),
+            ),
+            (
+                "DSC_0003.NEF".to_string(),
+                identity(300, "2024/12/15 10:02"),
+            ),
+            (
+                "DSC_0005.NEF".to_string(),
+                identity(500, "2024/12/15 10:03"),
+            ),
+        ]);
+
+        assert_eq!(
+            propose_transfers(&images, &labels, &identities),
+            vec![LabelTransfer {
+                from: "DSC_0001.NEF".to_string(),
+                to: "2024-12-15_0001.NEF".to_string(),
+                label: "adopted".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Copy-on-write clones of files (APFS clonefile, Btrfs/XFS FICLONE, ReFS block cloning)
+// A clone shares the blocks of its source until either file changes, so exports to the
+// same volume finish at once and take no extra space
+
+use crate::disk;
+use crate::error::{GlimpseError, Result};
+use std::path::Path;
+
+/// Copy `src` to `dst`, as a clone when both are on a volume that supports it
+/// Falls back to a regular copy whenever cloning isn't possible; returns whether it cloned
+pub fn clone_or_copy(src: &Path, dst: &Path) -> Result<bool> {
+    if disk::same_volume(src, dst.parent().unwrap_or(dst)) {
+        match platform::clone_file(src, dst) {
+            Ok(()) => return Ok(true),
+            Err(e) => tracing::debug!("Cannot clone {}: {}", src.display(), e),
+        }
+    }
+    std::fs::copy(src, dst).map_err(|e| GlimpseError::io_at(src, e))?;
+    Ok(false)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn clone_file(src: &Path, dst: &Path) -> std::io::Result<()> {
+        let src = CString::new(src.as_os_str().as_bytes())?;
+        let dst_c = CString::new(dst.as_os_str().as_bytes())?;
+        // clonefile never overwrites
+        let _ = std::fs::remove_file(dst);
+        // SAFETY: both paths are NUL-terminated strings that outlive the call
+        if unsafe { libc::clonefile(src.as_ptr(), dst_c.as_ptr(), 0) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    /// _IOW(0x94, 9, int) from linux/fs.h
+    const FICLONE: u64 = 0x4004_9409;
+
+    pub fn clone_file(src: &Path, dst: &Path) -> std::io::Result<()> {
+        let source = File::open(src)?;
+        let target = File::create(dst)?;
+        // SAFETY: both descriptors stay open for the duration of the call
+        if unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        target.set_permissions(source.metadata()?.permissions())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::fs::File;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceW;
+    use windows_sys::Win32::System::Ioctl::{
+        DUPLICATE_EXTENTS_DATA, FSCTL_DUPLICATE_EXTENTS_TO_FILE,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    pub fn clone_file(src: &Path, dst: &Path) -> std::io::Result<()> {
+        let cluster = cluster_size(dst)?;
+        let source = File::open(src)?;
+        let len = source.metadata()?.len();
+        let target = File::create(dst)?;
+        target.set_len(len)?;
+
+        // Regions must be whole clusters (the last may reach past the end of the file),
+        // and at most 4 GB per call
+        let max_chunk = u64::from(u32::MAX) / cluster * cluster;
+        let mut offset = 0;
+        while offset < len {
+            let count = (len - offset).min(max_chunk).div_ceil(cluster) * cluster;
+            let extents = DUPLICATE_EXTENTS_DATA {
+                FileHandle: source.as_raw_handle() as _,
+                SourceFileOffset: offset as i64,
+                TargetFileOffset: offset as i64,
+                ByteCount: count as i64,
+            };
+            let mut returned = 0u32;
+            // SAFETY: the input buffer is a live DUPLICATE_EXTENTS_DATA of the size passed
+            // and both handles are open for the duration of the call
+            let ok = unsafe {
+                DeviceIoControl(
+                    target.as_raw_handle() as _,
+                    FSCTL_DUPLICATE_EXTENTS_TO_FILE,
+                    &extents as *const DUPLICATE_EXTENTS_DATA as *const _,
+                    std::mem::size_of::<DUPLICATE_EXTENTS_DATA>() as u32,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            offset += count;
+        }
+        Ok(())
+    }
+
+    /// Cluster size of the volume holding `path`
+    fn cluster_size(path: &Path) -> std::io::Result<u64> {
+        let root = crate::volume::volume_root(path)
+            .ok_or_else(|| std::io::Error::other("No volume root"))?;
+        let root: Vec<u16> = root.as_os_str().encode_wide().chain(Some(0)).collect();
+        let (mut sectors, mut bytes, mut free, mut total) = (0u32, 0u32, 0u32, 0u32);
+        // SAFETY: root is NUL-terminated and the out pointers are live locals
+        let ok = unsafe {
+            GetDiskFreeSpaceW(
+                root.as_ptr(),
+                &mut sectors,
+                &mut bytes,
+                &mut free,
+                &mut total,
+            )
+        };
+        if ok == 0 || sectors == 0 || bytes == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(u64::from(sectors) * u64::from(bytes))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+mod platform {
+    use std::path::Path;
+
+    pub fn clone_file(_src: &Path, _dst: &Path) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_clone_or_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("DSC_0001.NEF");
+        let dst = dir.path().join("export").join("DSC_0001.NEF");
+        std::fs::create_dir(dir.path().join("export")).unwrap();
+        std::fs::write(&src, b"raw data").unwrap();
+        std::fs::write(&dst, b"older export that is longer").unwrap();
+
+        // Cloned or copied depending on the file system; the content is the same either way
+        clone_or_copy(&src, &dst).unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), b"raw data");
+        assert!(clone_or_copy(&dir.path().join("missing"), &dst).is_err());
+    }
+}
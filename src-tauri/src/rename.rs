@@ -0,0 +1,442 @@
+// Batch renaming of a session's files from a template such as "{date}_{camera}_{seq}"
+// Renaming in Glimpse keeps labels and caches with the files; a separate tool would leave
+// the labels behind on names that no longer exist
+
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::{capture_time_millis, ExifInfo, ImageInfo};
+use crate::stats::{capture_time, EXIF_DATE_FORMAT, MODIFIED_FORMAT};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Digits of `{seq}` when the template doesn't say (`{seq:3}`)
+const DEFAULT_SEQ_DIGITS: usize = 4;
+
+/// Order the files of an export are numbered in by `{seq}`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceOrder {
+    /// Capture time, so frames of several cameras interleave
+    #[default]
+    CaptureTime,
+    /// File name (session key) as on the card
+    Name,
+}
+
+/// One file renamed; names are session keys, so they keep the subfolder of merged sessions
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+impl Rename {
+    /// The rename that takes this one back
+    pub fn inverse(&self) -> Self {
+        Self {
+            from: self.to.clone(),
+            to: self.from.clone(),
+        }
+    }
+}
+
+/// Characters that aren't allowed in file names on some platform
+fn sanitize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_whitespace() => '-',
+            c => c,
+        })
+        .collect()
+}
+
+/// New file name (without folder) of a file, keeping its extension
+/// Tokens: `{date}` (YYYYMMDD) and `{time}` (HHMMSS) of capture, `{exif_date:FORMAT}`
+/// (capture time in strftime FORMAT, see `exif_date`), `{seq}`, `{seq:N}` or `{seq:N:START}`
+/// (`seq`-th file, N digits, counting from START or 1), `{camera}` (model) and `{name}`
+/// (current name)
+pub fn render(template: &str, seq: usize, image: &ImageInfo, exif: &ExifInfo) -> Result<String> {
+    let path = Path::new(&image.filename);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let time = capture_time(image, exif);
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| {
+                GlimpseError::InvalidConfig(format!("Unclosed token in {}", template))
+            })?;
+        let token = &rest[start + 1..end];
+        let value = match token.split_once(':') {
+            Some(("seq", spec)) => {
+                let (digits, start) = spec.split_once(':').unwrap_or((spec, "1"));
+                let digits: usize = match digits {
+                    "" => DEFAULT_SEQ_DIGITS,
+                    digits => digits.parse().map_err(|_| {
+                        GlimpseError::InvalidConfig(format!("Invalid sequence width: {}", token))
+                    })?,
+                };
+                let start: usize = start.parse().map_err(|_| {
+                    GlimpseError::InvalidConfig(format!("Invalid sequence start: {}", token))
+                })?;
+                format!("{:0width$}", start + seq.saturating_sub(1), width = digits)
+            }
+            Some(("exif_date", spec)) => exif_date(spec, image, exif)?,
+            _ => match token {
+                "seq" => format!("{:0width$}", seq, width = DEFAULT_SEQ_DIGITS),
+                "date" => time
+                    .map(|t| t.format("%Y%m%d").to_string())
+                    .unwrap_or_default(),
+                "time" => time
+                    .map(|t| t.format("%H%M%S").to_string())
+                    .unwrap_or_default(),
+                "camera" => exif
+                    .camera_model
+                    .as_deref()
+                    .map(sanitize)
+                    .unwrap_or_else(|| "unknown".to_string()),
+                "name" => stem.clone(),
+                _ => {
+                    return Err(GlimpseError::InvalidConfig(format!(
+                        "Unknown rename token: {{{}}}",
+                        token
+                    )))
+                }
+            },
+        };
+        name.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+
+    let name = sanitize(&name);
+    if name.is_empty() {
+        return Err(GlimpseError::InvalidConfig(format!(
+            "Template gives an empty name: {}",
+            template
+        )));
+    }
+    Ok(match path.extension() {
+        Some(extension) => format!("{}.{}", name, extension.to_string_lossy()),
+        None => name,
+    })
+}
+
+/// Capture time for `{exif_date:FORMAT}` or `{exif_date:FORMAT@OFFSET}`
+/// The time is DateTimeOriginal, or the file's modification time (local time) without one.
+/// With an OFFSET ("+09:00", "-0500", "Z") times are converted to it from the offset the
+/// camera recorded, so cameras set to different time zones sort together; EXIF times
+/// without a recorded offset are taken to be in OFFSET already
+fn exif_date(spec: &str, image: &ImageInfo, exif: &ExifInfo) -> Result<String> {
+    let invalid = || GlimpseError::InvalidConfig(format!("Invalid date format: {}", spec));
+    let (format, target) = match spec.rsplit_once('@') {
+        Some((format, offset)) => (format, Some(parse_offset(offset).ok_or_else(invalid)?)),
+        None => (spec, None),
+    };
+    if format.is_empty() || StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(invalid());
+    }
+
+    let local = |time: &NaiveDateTime| {
+        Local
+            .from_local_datetime(time)
+            .earliest()
+            .map(|time| time.fixed_offset())
+    };
+    let taken = exif
+        .date_taken
+        .as_deref()
+        .and_then(|date| NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).ok());
+    let time: Option<DateTime<FixedOffset>> = match taken {
+        Some(taken) => {
+            let recorded = exif.offset_time.as_deref().and_then(parse_offset);
+            match (recorded.or(target), target) {
+                (Some(recorded), Some(target)) => recorded
+                    .from_local_datetime(&taken)
+                    .single()
+                    .map(|time| time.with_timezone(&target)),
+                (Some(recorded), None) => recorded.from_local_datetime(&taken).single(),
+                (None, _) => local(&taken),
+            }
+        }
+        None => {
+            let modified = capture_time_millis(Path::new(&image.path))
+                .and_then(DateTime::from_timestamp_millis)
+                .map(|time| time.with_timezone(&Local).fixed_offset())
+                .or_else(|| {
+                    NaiveDateTime::parse_from_str(&image.modified_at, MODIFIED_FORMAT)
+                        .ok()
+                        .and_then(|time| local(&time))
+                });
+            match target {
+                Some(target) => modified.map(|time| time.with_timezone(&target)),
+                None => modified,
+            }
+        }
+    };
+
+    let mut value = String::new();
+    if let Some(time) = time {
+        write!(value, "{}", time.format(format)).map_err(|_| invalid())?;
+    }
+    Ok(value)
+}
+
+/// UTC offset written as "+09:00", "+0900", "-05" or "Z"
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("z") || value.eq_ignore_ascii_case("utc") {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, rest) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest
+        .split_once(':')
+        .unwrap_or((rest.get(..2)?, rest.get(2..)?));
+    if hours.len() != 2 || !(minutes.is_empty() || minutes.len() == 2) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = if minutes.is_empty() {
+        0
+    } else {
+        minutes.parse().ok()?
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Check a template for unknown tokens without a file to render it for
+pub fn validate_template(template: &str) -> Result<()> {
+    let image = ImageInfo {
+        filename: "IMG_0001.JPG".to_string(),
+        path: "IMG_0001.JPG".to_string(),
+        size: 0,
+        modified_at: String::new(),
+        is_video: false,
+    };
+    render(template, 1, &image, &ExifInfo::default()).map(|_| ())
+}
+
+/// File names (without folder) for exporting `images` under `template`, in their order
+/// Files are numbered in `order`, counting afresh for every export; clashing names are left
+/// to the export
+pub fn export_names(
+    images: &[(ImageInfo, ExifInfo)],
+    template: &str,
+    order: SequenceOrder,
+) -> Result<Vec<String>> {
+    let mut positions: Vec<usize> = (0..images.len()).collect();
+    positions.sort_by(|&a, &b| {
+        let ((a, a_exif), (b, b_exif)) = (&images[a], &images[b]);
+        match order {
+            SequenceOrder::CaptureTime => capture_time(a, a_exif).cmp(&capture_time(b, b_exif)),
+            SequenceOrder::Name => std::cmp::Ordering::Equal,
+        }
+        .then_with(|| a.filename.cmp(&b.filename))
+    });
+
+    let mut names = vec![String::new(); images.len()];
+    for (seq, index) in positions.into_iter().enumerate() {
+        let (image, exif) = &images[index];
+        names[index] = render(template, seq + 1, image, exif)?;
+    }
+    Ok(names)
+}
+
+/// Renames for every file of a session whose name changes under `template`
+/// Files are numbered in capture order; two files ending up with the same name is an error
+pub fn plan(images: &[(ImageInfo, ExifInfo)], template: &str) -> Result<Vec<Rename>> {
+    let mut ordered: Vec<&(ImageInfo, ExifInfo)> = images.iter().collect();
+    ordered.sort_by(|(a, a_exif), (b, b_exif)| {
+        capture_time(a, a_exif)
+            .cmp(&capture_time(b, b_exif))
+            .then_with(|| a.filename.cmp(&b.filename))
+    });
+
+    let mut targets = HashSet::new();
+    let mut renames = Vec::new();
+    for (index, (image, exif)) in ordered.into_iter().enumerate() {
+        let name = render(template, index + 1, image, exif)?;
+        let to = match image.filename.rsplit_once('/') {
+            Some((folder, _)) => format!("{}/{}", folder, name),
+            None => name,
+        };
+        if !targets.insert(to.clone()) {
+            return Err(GlimpseError::RenameConflict(to));
+        }
+        if to != image.filename {
+            renames.push(Rename {
+                from: image.filename.clone(),
+                to,
+            });
+        }
+    }
+    Ok(renames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exif(date_taken: Option<&str>) -> ExifInfo {
+        ExifInfo {
+            date_taken: date_taken.map(str::to_string),
+            camera_model: Some("NIKON Z 6_2".to_string()),
+            ..ExifInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let image = ImageInfo::fixture("DSC_0001.NEF").with_modified_at("2024/12/15 11:00");
+        let exif = exif(Some("2024-12-15 10:30:05"));
+
+        assert_eq!(
+            render("{date}_{time}_{camera}_{seq}", 7, &image, &exif).unwrap(),
+            "20241215_103005_NIKON-Z-6_2_0007.NEF"
+        );
+        assert_eq!(
+            render("{name}-{seq:2}", 3, &image, &exif).unwrap(),
+            "DSC_0001-03.NEF"
+        );
+        assert_eq!(
+            render("Smith_Wedding_{seq:4:101}", 3, &image, &exif).unwrap(),
+            "Smith_Wedding_0103.NEF"
+        );
+        assert_eq!(render("{seq::0}", 1, &image, &exif).unwrap(), "0000.NEF");
+        assert!(render("{seq:4:-1}", 1, &image, &exif).is_err());
+        assert!(render("{lens}", 1, &image, &exif).is_err());
+        assert!(render("{date", 1, &image, &exif).is_err());
+        assert!(render("{seq:x}", 1, &image, &exif).is_err());
+    }
+
+    #[test]
+    fn test_exif_date() {
+        let image = ImageInfo::fixture("DSC_0001.NEF").with_modified_at("2024/12/15 11:00");
+        let mut exif = exif(Some("2024-12-15 10:30:05"));
+        assert_eq!(
+            render("{exif_date:%Y%m%d_%H%M%S}", 1, &image, &exif).unwrap(),
+            "20241215_103005.NEF"
+        );
+
+        // A second body still on home time (+01:00) lines up with the local (+09:00) one
+        exif.offset_time = Some("+01:00".to_string());
+        assert_eq!(
+            render("{exif_date:%Y%m%d_%H%M%S@+09:00}", 1, &image, &exif).unwrap(),
+            "20241215_183005.NEF"
+        );
+        assert_eq!(
+            render("{exif_date:%H%M%z@Z}", 1, &image, &exif).unwrap(),
+            "0930+0000.NEF"
+        );
+        // Without a recorded offset the time is taken to be in the wanted one
+        exif.offset_time = None;
+        assert_eq!(
+            render("{exif_date:%H%M@-0500}", 1, &image, &exif).unwrap(),
+            "1030.NEF"
+        );
+
+        // The file time stands in for a missing EXIF date
+        let no_exif = ExifInfo::default();
+        assert_eq!(
+            render("{exif_date:%Y-%m-%d %H.%M}", 1, &image, &no_exif).unwrap(),
+            "2024-12-15-11.00.NEF"
+        );
+
+        assert!(render("{exif_date:%Q}", 1, &image, &exif).is_err());
+        assert!(render("{exif_date:%H@+9}", 1, &image, &exif).is_err());
+        assert!(validate_template("{exif_date:%Y%m%d}_{seq}").is_ok());
+        assert!(validate_template("{exif_date:}").is_err());
+    }
+
+    #[test]
+    fn test_plan() {
+        let images = vec![
+            (
+                ImageInfo::fixture("card1/DSC_0002.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(Some("2024-12-15 10:31:00")),
+            ),
+            (
+                ImageInfo::fixture("card2/DSC_0001.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(Some("2024-12-15 10:30:00")),
+            ),
+            // Already has its new name
+            (
+                ImageInfo::fixture("shoot_0003.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(None),
+            ),
+        ];
+
+        assert_eq!(
+            plan(&images, "shoot_{seq}").unwrap(),
+            vec![
+                Rename {
+                    from: "card2/DSC_0001.NEF".to_string(),
+                    to: "card2/shoot_0001.NEF".to_string(),
+                },
+                Rename {
+                    from: "card1/DSC_0002.NEF".to_string(),
+                    to: "card1/shoot_0002.NEF".to_string(),
+                },
+            ]
+        );
+        // Same camera, no sequence
+        let same = vec![
+            (
+                ImageInfo::fixture("DSC_0001.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(None),
+            ),
+            (
+                ImageInfo::fixture("DSC_0002.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(None),
+            ),
+        ];
+        assert!(matches!(
+            plan(&same, "{camera}"),
+            Err(GlimpseError::RenameConflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_names() {
+        let images = vec![
+            (
+                ImageInfo::fixture("card1/DSC_0002.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(Some("2024-12-15 10:31:00")),
+            ),
+            (
+                ImageInfo::fixture("card2/DSC_0001.NEF").with_modified_at("2024/12/15 11:00"),
+                exif(Some("2024-12-15 10:30:00")),
+            ),
+        ];
+
+        assert_eq!(
+            export_names(&images, "proof_{seq:3}", SequenceOrder::CaptureTime).unwrap(),
+            vec!["proof_002.NEF", "proof_001.NEF"]
+        );
+        assert_eq!(
+            export_names(&images, "proof_{seq:3:10}", SequenceOrder::Name).unwrap(),
+            vec!["proof_010.NEF", "proof_011.NEF"]
+        );
+        assert!(validate_template("{date}_{name}").is_ok());
+        assert!(validate_template("{date}_{lens}").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// Uploads to SFTP servers through the system OpenSSH client
+// Uploads resume where an interrupted run stopped, and the size of each file is checked on
+// the server once it is written
+
+use crate::config::SftpTarget;
+use crate::error::{GlimpseError, Result};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Environment variable the askpass helper reads the password from
+const PASSWORD_VAR: &str = "GLIMPSE_SFTP_PASSWORD";
+
+/// Seconds connecting to the server may take
+const CONNECT_TIMEOUT_SECS: u32 = 20;
+
+/// Client for one SFTP target; every call runs one `sftp` batch
+pub struct SftpClient {
+    program: PathBuf,
+    target: SftpTarget,
+    password: Option<String>,
+    /// Helper handing `password` to ssh, removed on drop
+    askpass: Option<PathBuf>,
+}
+
+impl SftpClient {
+    pub fn new(target: SftpTarget, password: Option<String>) -> Result<Self> {
+        let program = crate::video::find_tool("sftp")
+            .ok_or_else(|| GlimpseError::Sftp("The OpenSSH sftp client was not found".into()))?;
+        let askpass = match password {
+            Some(_) => Some(write_askpass()?),
+            None => None,
+        };
+        Ok(Self {
+            program,
+            target,
+            password,
+            askpass,
+        })
+    }
+
+    /// Sizes of the files in the target folder, which is created if missing
+    pub fn remote_sizes(&self) -> Result<HashMap<String, u64>> {
+        let dir = quote(&self.target.remote_dir)?;
+        let output = self.run(&format!("-mkdir {}\nls -ln {}\n", dir, dir))?;
+        Ok(parse_listing(&output))
+    }
+
+    /// Upload `src` as `name` into the target folder and check its size there
+    /// With `resume`, a shorter copy already on the server is completed instead of replaced
+    pub fn upload(&self, src: &Path, name: &str, resume: bool) -> Result<()> {
+        let size = std::fs::metadata(src)
+            .map_err(|e| GlimpseError::io_at(src, e))?
+            .len();
+        let remote = format!("{}/{}", self.target.remote_dir.trim_end_matches('/'), name);
+        let output = self.run(&upload_script(src, &remote, resume)?)?;
+        match parse_listing(&output).get(name) {
+            Some(&uploaded) if uploaded == size => Ok(()),
+            uploaded => Err(GlimpseError::Sftp(format!(
+                "{} has {} of {} bytes on the server",
+                remote,
+                uploaded.copied().unwrap_or(0),
+                size
+            ))),
+        }
+    }
+
+    /// Run a batch of sftp commands; fails if any command without a `-` prefix fails
+    fn run(&self, script: &str) -> Result<String> {
+        let mut command = Command::new(&self.program);
+        if let Some(askpass) = &self.askpass {
+            // `-b` turns BatchMode on, which rules out password logins; ssh keeps the first
+            // value given for an option, so this has to come before it
+            command
+                .args(["-o", "BatchMode=no", "-o", "NumberOfPasswordPrompts=1"])
+                .env("SSH_ASKPASS", askpass)
+                .env("SSH_ASKPASS_REQUIRE", "force")
+                .env(PASSWORD_VAR, self.password.as_deref().unwrap_or_default());
+        }
+        command
+            .args(["-b", "-", "-P", &self.target.port.to_string()])
+            .arg("-o")
+            .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS))
+            .args(["-o", "StrictHostKeyChecking=accept-new"]);
+        if let Some(identity) = &self.target.identity_file {
+            command.arg("-i").arg(identity);
+        }
+        command
+            .arg(format!("{}@{}", self.target.user, self.target.host))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| GlimpseError::Sftp(e.to_string()))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(script.as_bytes())
+            .map_err(|e| GlimpseError::Sftp(e.to_string()))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| GlimpseError::Sftp(e.to_string()))?;
+        if !output.status.success() {
+            return Err(GlimpseError::Sftp(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+impl Drop for SftpClient {
+    fn drop(&mut self) {
+        if let Some(askpass) = &self.askpass {
+            let _ = std::fs::remove_file(askpass);
+        }
+    }
+}
+
+/// Write a helper that prints the password from the environment when ssh asks for it
+/// The password itself never touches the disk
+fn write_askpass() -> Result<PathBuf> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let (extension, script) = if cfg!(windows) {
+        (
+            ".cmd",
+            format!(
+                "@echo off\r\nsetlocal EnableDelayedExpansion\r\necho(!{}!\r\n",
+                PASSWORD_VAR
+            ),
+        )
+    } else {
+        (
+            "",
+            format!("#!/bin/sh\nprintf '%s\\n' \"${}\"\n", PASSWORD_VAR),
+        )
+    };
+    let path = std::env::temp_dir().join(format!(
+        "glimpse-askpass-{}-{}{}",
+        std::process::id(),
+        id,
+        extension
+    ));
+    std::fs::write(&path, script).map_err(|e| GlimpseError::io_at(&path, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| GlimpseError::io_at(&path, e))?;
+    }
+    Ok(path)
+}
+
+/// Batch uploading `src` to `remote` and listing the result
+fn upload_script(src: &Path, remote: &str, resume: bool) -> Result<String> {
+    let command = if resume { "reput" } else { "put" };
+    let remote = quote(remote)?;
+    Ok(format!(
+        "{} {} {}\nls -ln {}\n",
+        command,
+        quote(&src.to_string_lossy())?,
+        remote,
+        remote
+    ))
+}
+
+/// Quote a path for an sftp batch; glob characters are escaped to match only themselves
+/// Paths with control characters are refused: sftp can't escape them, and a newline would
+/// end the command and start another
+fn quote(path: &str) -> Result<String> {
+    if path.chars().any(char::is_control) {
+        return Err(GlimpseError::Sftp(format!(
+            "Path has control characters: {:?}",
+            path
+        )));
+    }
+    let mut quoted = String::with_capacity(path.len() + 2);
+    quoted.push('"');
+    for c in path.chars() {
+        if matches!(c, '"' | '\\' | '*' | '?' | '[' | ']') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+/// Sizes by file name in `ls -ln` output; the echoed commands, folders and links are skipped
+fn parse_listing(output: &str) -> HashMap<String, u64> {
+    output
+        .lines()
+        .filter(|line| line.starts_with('-'))
+        .filter_map(|line| {
+            // Mode, links, owner, group, size, then month, day and time or year
+            let mut rest = line;
+            let mut fields = Vec::with_capacity(8);
+            for _ in 0..8 {
+                let field = rest.trim_start();
+                let end = field.find(char::is_whitespace)?;
+                fields.push(&field[..end]);
+                rest = &field[end..];
+            }
+            let path = rest.strip_prefix(' ')?;
+            let name = path.rsplit('/').next()?;
+            Some((name.to_string(), fields[4].parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("/srv/proofs").unwrap(), "\"/srv/proofs\"");
+        assert_eq!(
+            quote(r#"C:\Photos\"best" [1]*.jpg"#).unwrap(),
+            r#""C:\\Photos\\\"best\" \[1\]\*.jpg""#
+        );
+        assert!(quote("a.jpg\"\nrm \"/srv/proofs/b.jpg").is_err());
+        assert!(quote("a\rb.jpg").is_err());
+    }
+
+    #[test]
+    fn test_upload_script() {
+        assert_eq!(
+            upload_script(Path::new("/shoot/DSC 0001.NEF"), "/srv/DSC 0001.NEF", true).unwrap(),
+            "reput \"/shoot/DSC 0001.NEF\" \"/srv/DSC 0001.NEF\"\nls -ln \"/srv/DSC 0001.NEF\"\n"
+        );
+        assert!(upload_script(Path::new("/a.jpg"), "/srv/a.jpg", false)
+            .unwrap()
+            .starts_with("put "));
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let output = "sftp> -mkdir \"/srv/proofs\"\n\
+            sftp> ls -ln \"/srv/proofs\"\n\
+            drwxr-xr-x    2 1000     1000         4096 Dec 15 05:00 /srv/proofs/old\n\
+            -rw-r--r--    1 1000     1000     25165824 Dec 15 05:01 /srv/proofs/DSC_0001.NEF\n\
+            -rw-r--r--    1 1000     1000        12345 Jan  1  2024 /srv/proofs/client notes.txt\n\
+            lrwxrwxrwx    1 1000     1000            9 Dec 15 05:02 /srv/proofs/latest\n";
+
+        let sizes = parse_listing(output);
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes["DSC_0001.NEF"], 25165824);
+        assert_eq!(sizes["client notes.txt"], 12345);
+        assert!(parse_listing("").is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+// Identification of image files from their first bytes
+// Recovery tools and careless renames leave files whose extension is wrong or missing
+
+use image::ImageFormat;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes read to identify a file
+const HEADER_LEN: usize = 16;
+
+/// Container format of a file according to its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Jpeg,
+    Png,
+    /// Plain TIFF, or one of the many RAW formats built on it (NEF, ARW, CR2, DNG, ...)
+    Tiff,
+    /// RAW formats with their own container (ORF, RW2, RAF, MRW, CRW, CR3)
+    Raw,
+    /// Other formats the image crate knows
+    Image(ImageFormat),
+}
+
+/// Identify a file from its first bytes; None when it isn't an image or can't be read
+pub fn sniff_file(path: &Path) -> Option<FileFormat> {
+    let mut header = [0u8; HEADER_LEN];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .ok()?;
+    sniff(&header[..read])
+}
+
+/// Identify a file from its first bytes
+pub fn sniff(header: &[u8]) -> Option<FileFormat> {
+    const RAW_MAGIC: &[&[u8]] = &[b"IIRO", b"IIRS", b"MMOR", b"IIU\0", b"FUJIFILM", b"\0MRM"];
+    if RAW_MAGIC.iter().any(|magic| header.starts_with(magic))
+        || header.get(6..14) == Some(b"HEAPCCDR".as_slice())
+        || header.get(4..12) == Some(b"ftypcrx ".as_slice())
+    {
+        return Some(FileFormat::Raw);
+    }
+
+    match image::guess_format(header).ok()? {
+        ImageFormat::Jpeg => Some(FileFormat::Jpeg),
+        ImageFormat::Png => Some(FileFormat::Png),
+        ImageFormat::Tiff => Some(FileFormat::Tiff),
+        format => Some(FileFormat::Image(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\xFF\xD8\xFF\xE1\0\0Exif"), Some(FileFormat::Jpeg));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some(FileFormat::Png));
+        assert_eq!(sniff(b"II*\0\x08\0\0\0"), Some(FileFormat::Tiff));
+        assert_eq!(sniff(b"MM\0*\0\0\0\x08"), Some(FileFormat::Tiff));
+        assert_eq!(sniff(b"IIRO\x08\0\0\0"), Some(FileFormat::Raw));
+        assert_eq!(sniff(b"FUJIFILMCCD-RAW "), Some(FileFormat::Raw));
+        assert_eq!(
+            sniff(b"\0\0\0\x18ftypcrx \0\0\0\x01"),
+            Some(FileFormat::Raw)
+        );
+        // Ordinary MP4 isn't an image
+        assert_eq!(sniff(b"\0\0\0\x18ftypmp42\0\0\0\0"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_sniff_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("IMG_0001");
+        image::DynamicImage::new_rgb8(2, 2)
+            .save_with_format(&path, ImageFormat::Png)
+            .unwrap();
+
+        assert_eq!(sniff_file(&path), Some(FileFormat::Png));
+        assert_eq!(sniff_file(&dir.path().join("missing")), None);
+    }
+}
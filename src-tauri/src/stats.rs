@@ -0,0 +1,161 @@
+// Size, format and date overview of a session's files
+// Shown in the header and used to pick an export strategy (e.g. copy vs move, destination space)
+
+use crate::image_processor::{is_raw_format, ExifInfo, ImageInfo};
+use chrono::NaiveDateTime;
+use std::path::Path;
+
+/// How EXIF dates and the scan's modification times are written
+pub(crate) const EXIF_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+pub(crate) const MODIFIED_FORMAT: &str = "%Y/%m/%d %H:%M";
+
+/// Totals of a session's files
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct FolderStats {
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub average_bytes: u64,
+    pub raw_count: usize,
+    pub jpeg_count: usize,
+    pub video_count: usize,
+    /// PNG and user-added image formats
+    pub other_count: usize,
+    /// Earliest and latest capture time ("YYYY-MM-DD HH:MM:SS"), from EXIF where present and
+    /// the modification time otherwise
+    pub first_capture: Option<String>,
+    pub last_capture: Option<String>,
+}
+
+/// When a file was taken: the EXIF date, or its modification time without one
+pub fn capture_time(image: &ImageInfo, exif: &ExifInfo) -> Option<NaiveDateTime> {
+    exif.date_taken
+        .as_deref()
+        .and_then(|date| NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).ok())
+        .or_else(|| NaiveDateTime::parse_from_str(&image.modified_at, MODIFIED_FORMAT).ok())
+}
+
+/// Move the EXIF date of a file by `seconds`, correcting a camera whose clock was off
+/// Files without a readable EXIF date are left alone
+pub fn shift_date_taken(exif: &mut ExifInfo, seconds: i64) {
+    let shifted = exif
+        .date_taken
+        .as_deref()
+        .and_then(|date| NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).ok())
+        .and_then(|date| date.checked_add_signed(chrono::Duration::seconds(seconds)));
+    if let Some(date) = shifted {
+        exif.date_taken = Some(date.format(EXIF_DATE_FORMAT).to_string());
+    }
+}
+
+pub fn folder_stats(images: &[(ImageInfo, ExifInfo)]) -> FolderStats {
+    let mut stats = FolderStats {
+        file_count: images.len(),
+        ..FolderStats::default()
+    };
+    let mut range: Option<(NaiveDateTime, NaiveDateTime)> = None;
+
+    for (image, exif) in images {
+        stats.total_bytes += image.size;
+
+        let extension = Path::new(&image.filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        if image.is_video {
+            stats.video_count += 1;
+        } else if is_raw_format(extension) {
+            stats.raw_count += 1;
+        } else if extension.eq_ignore_ascii_case("jpg") || extension.eq_ignore_ascii_case("jpeg") {
+            stats.jpeg_count += 1;
+        } else {
+            stats.other_count += 1;
+        }
+
+        if let Some(time) = capture_time(image, exif) {
+            range = Some(match range {
+                Some((first, last)) => (first.min(time), last.max(time)),
+                None => (time, time),
+            });
+        }
+    }
+
+    if !images.is_empty() {
+        stats.average_bytes = stats.total_bytes / images.len() as u64;
+    }
+    if let Some((first, last)) = range {
+        stats.first_capture = Some(first.format(EXIF_DATE_FORMAT).to_string());
+        stats.last_capture = Some(last.format(EXIF_DATE_FORMAT).to_string());
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taken(date: &str) -> ExifInfo {
+        ExifInfo {
+            date_taken: Some(date.to_string()),
+            ..ExifInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_shift_date_taken() {
+        let mut exif = taken("2024-12-15 23:30:00");
+        shift_date_taken(&mut exif, 3600);
+        assert_eq!(exif.date_taken.as_deref(), Some("2024-12-16 00:30:00"));
+        shift_date_taken(&mut exif, -5400);
+        assert_eq!(exif.date_taken.as_deref(), Some("2024-12-15 23:00:00"));
+
+        let mut unreadable = taken("0000:00:00 00:00:00");
+        shift_date_taken(&mut unreadable, 60);
+        assert_eq!(
+            unreadable.date_taken.as_deref(),
+            Some("0000:00:00 00:00:00")
+        );
+    }
+
+    #[test]
+    fn test_folder_stats() {
+        let images = vec![
+            (
+                ImageInfo::fixture("DSC_0001.NEF").with_size(30_000_000),
+                taken("2024-12-15 10:30:00"),
+            ),
+            (
+                ImageInfo::fixture("DSC_0001.JPG").with_size(8_000_000),
+                taken("2024-12-15 08:00:00"),
+            ),
+            // No EXIF; the modification time counts
+            (
+                ImageInfo::fixture("MVI_0002.MOV")
+                    .with_size(100_000_000)
+                    .with_modified_at("2024/12/16 09:00")
+                    .video(),
+                ExifInfo::default(),
+            ),
+            (
+                ImageInfo::fixture("scan.png").with_size(2_000_000),
+                taken("not a date"),
+            ),
+        ];
+
+        let stats = folder_stats(&images);
+        assert_eq!(stats.file_count, 4);
+        assert_eq!(stats.total_bytes, 140_000_000);
+        assert_eq!(stats.average_bytes, 35_000_000);
+        assert_eq!(
+            (
+                stats.raw_count,
+                stats.jpeg_count,
+                stats.video_count,
+                stats.other_count
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(stats.first_capture.as_deref(), Some("2024-12-15 08:00:00"));
+        assert_eq!(stats.last_capture.as_deref(), Some("2024-12-16 09:00:00"));
+        assert_eq!(folder_stats(&[]), FolderStats::default());
+    }
+}
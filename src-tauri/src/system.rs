@@ -0,0 +1,135 @@
+// OS and GPU description for the settings screen and diagnostics reports
+// Both are looked up once per run; the external tools involved can take a moment
+
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Human-readable OS name and version, e.g. "macOS 14.5" or "Ubuntu 24.04 LTS"
+pub fn os_version() -> String {
+    static OS_VERSION: OnceLock<String> = OnceLock::new();
+    OS_VERSION
+        .get_or_init(|| platform::os_version().unwrap_or_else(|| std::env::consts::OS.to_string()))
+        .clone()
+}
+
+/// Name of the (first) graphics adapter, when it can be determined
+pub fn gpu_name() -> Option<String> {
+    static GPU_NAME: OnceLock<Option<String>> = OnceLock::new();
+    GPU_NAME.get_or_init(platform::gpu_name).clone()
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn os_version() -> Option<String> {
+        let release = std::fs::read_to_string("/etc/os-release").ok()?;
+        super::parse_os_release(&release)
+    }
+
+    pub fn gpu_name() -> Option<String> {
+        super::parse_lspci(&super::command_output("lspci", &[])?)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    pub fn os_version() -> Option<String> {
+        let version = super::command_output("sw_vers", &["-productVersion"])?;
+        Some(format!("macOS {}", version.trim()))
+    }
+
+    pub fn gpu_name() -> Option<String> {
+        let displays = super::command_output("system_profiler", &["SPDisplaysDataType"])?;
+        displays
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Chipset Model:"))
+            .map(|name| name.trim().to_string())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    fn powershell(script: &str) -> Option<String> {
+        let output = super::command_output("powershell", &["-NoProfile", "-Command", script])?;
+        let output = output.lines().next()?.trim().to_string();
+        (!output.is_empty()).then_some(output)
+    }
+
+    pub fn os_version() -> Option<String> {
+        powershell("(Get-CimInstance Win32_OperatingSystem | ForEach-Object { $_.Caption + ' ' + $_.Version })")
+    }
+
+    pub fn gpu_name() -> Option<String> {
+        powershell("(Get-CimInstance Win32_VideoController | Select-Object -First 1).Name")
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub fn os_version() -> Option<String> {
+        None
+    }
+
+    pub fn gpu_name() -> Option<String> {
+        None
+    }
+}
+
+/// PRETTY_NAME of an /etc/os-release file
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_os_release(release: &str) -> Option<String> {
+    release
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim_matches('"').to_string())
+}
+
+/// Device name of the first display controller in `lspci` output
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_lspci(lspci: &str) -> Option<String> {
+    const CLASSES: &[&str] = &[
+        "VGA compatible controller: ",
+        "3D controller: ",
+        "Display controller: ",
+    ];
+    lspci.lines().find_map(|line| {
+        CLASSES
+            .iter()
+            .find_map(|class| line.split_once(class))
+            .map(|(_, name)| name.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_os_release() {
+        let release = "NAME=\"Ubuntu\"\nVERSION_ID=\"24.04\"\nPRETTY_NAME=\"Ubuntu 24.04 LTS\"\n";
+        assert_eq!(
+            parse_os_release(release).as_deref(),
+            Some("Ubuntu 24.04 LTS")
+        );
+        assert_eq!(parse_os_release(""), None);
+    }
+
+    #[test]
+    fn test_parse_lspci() {
+        let lspci = "00:00.0 Host bridge: Intel Corporation Device 4641\n\
+            00:02.0 VGA compatible controller: Intel Corporation Alder Lake-P GT2 [Iris Xe Graphics] (rev 0c)\n\
+            01:00.0 3D controller: NVIDIA Corporation GA107M [GeForce RTX 3050 Mobile] (rev a1)\n";
+        assert_eq!(
+            parse_lspci(lspci).as_deref(),
+            Some("Intel Corporation Alder Lake-P GT2 [Iris Xe Graphics] (rev 0c)")
+        );
+        assert_eq!(parse_lspci("00:00.0 Host bridge: Intel\n"), None);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::error::{GlimpseError, Result};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Kind of long-running job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    Thumbnails,
+    Prefetch,
+    Export,
+    Tether,
+    Analysis,
+    Exif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+/// Snapshot of a task sent to the frontend
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub kind: TaskKind,
+    pub status: TaskStatus,
+    pub completed: usize,
+    pub total: usize,
+}
+
+struct TaskControl {
+    info: Mutex<TaskInfo>,
+    resumed: Condvar,
+}
+
+type TaskMap = Arc<Mutex<BTreeMap<u64, Arc<TaskControl>>>>;
+
+/// Registry of running tasks
+/// Jobs get a `TaskHandle` and call `checkpoint()` between work items,
+/// which is where pausing and cancelling take effect
+#[derive(Default)]
+pub struct TaskManager {
+    tasks: TaskMap,
+    next_id: AtomicU64,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new running task
+    pub fn start(&self, kind: TaskKind, total: usize) -> TaskHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let control = Arc::new(TaskControl {
+            info: Mutex::new(TaskInfo {
+                id,
+                kind,
+                status: TaskStatus::Running,
+                completed: 0,
+                total,
+            }),
+            resumed: Condvar::new(),
+        });
+        self.tasks.lock().unwrap().insert(id, Arc::clone(&control));
+
+        TaskHandle {
+            control,
+            tasks: Arc::clone(&self.tasks),
+        }
+    }
+
+    /// Snapshots of all unfinished tasks, oldest first
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .values()
+            .map(|control| control.info.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Pause a running task (workers stop at their next checkpoint)
+    pub fn pause(&self, id: u64) -> Result<TaskInfo> {
+        self.transition(id, TaskStatus::Running, TaskStatus::Paused)
+    }
+
+    /// Resume a paused task
+    pub fn resume(&self, id: u64) -> Result<TaskInfo> {
+        self.transition(id, TaskStatus::Paused, TaskStatus::Running)
+    }
+
+    /// Cancel a running or paused task
+    pub fn cancel(&self, id: u64) -> Result<TaskInfo> {
+        let control = self.control(id)?;
+        let mut info = control.info.lock().unwrap();
+        if matches!(info.status, TaskStatus::Running | TaskStatus::Paused) {
+            info.status = TaskStatus::Cancelled;
+            control.resumed.notify_all();
+        }
+        Ok(info.clone())
+    }
+
+    /// Cancel every unfinished task of `kind`
+    pub fn cancel_kind(&self, kind: TaskKind) -> Vec<TaskInfo> {
+        let ids: Vec<u64> = self
+            .list()
+            .into_iter()
+            .filter(|info| info.kind == kind)
+            .map(|info| info.id)
+            .collect();
+        ids.into_iter()
+            .filter_map(|id| self.cancel(id).ok())
+            .collect()
+    }
+
+    fn control(&self, id: u64) -> Result<Arc<TaskControl>> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(GlimpseError::TaskNotFound(id))
+    }
+
+    /// Move a task from `from` to `to`; tasks in any other state are left as they are
+    fn transition(&self, id: u64, from: TaskStatus, to: TaskStatus) -> Result<TaskInfo> {
+        let control = self.control(id)?;
+        let mut info = control.info.lock().unwrap();
+        if info.status == from {
+            info.status = to;
+            control.resumed.notify_all();
+        }
+        Ok(info.clone())
+    }
+}
+
+/// Handle held by the job doing the work
+/// The task is unregistered when the handle is finished or dropped
+pub struct TaskHandle {
+    control: Arc<TaskControl>,
+    tasks: TaskMap,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> u64 {
+        self.control.info.lock().unwrap().id
+    }
+
+    pub fn info(&self) -> TaskInfo {
+        self.control.info.lock().unwrap().clone()
+    }
+
+    pub fn set_progress(&self, completed: usize) {
+        self.control.info.lock().unwrap().completed = completed;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.info.lock().unwrap().status == TaskStatus::Cancelled
+    }
+
+    /// Block while the task is paused
+    /// Returns false once the task has been cancelled and the job should stop
+    pub fn checkpoint(&self) -> bool {
+        let mut info = self.control.info.lock().unwrap();
+        while info.status == TaskStatus::Paused {
+            info = self.control.resumed.wait(info).unwrap();
+        }
+        info.status != TaskStatus::Cancelled
+    }
+
+    /// Mark the task completed (unless it was cancelled) and return its final snapshot
+    pub fn finish(self) -> TaskInfo {
+        self.unregister()
+    }
+
+    fn unregister(&self) -> TaskInfo {
+        let info = {
+            let mut info = self.control.info.lock().unwrap();
+            if info.status != TaskStatus::Cancelled {
+                info.status = TaskStatus::Completed;
+            }
+            info.clone()
+        };
+        self.tasks.lock().unwrap().remove(&info.id);
+        info
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_task_lifecycle() {
+        let manager = TaskManager::new();
+        let task = manager.start(TaskKind::Export, 10);
+        let id = task.id();
+
+        task.set_progress(3);
+        assert_eq!(manager.list(), vec![task.info()]);
+        assert_eq!(manager.list()[0].completed, 3);
+
+        assert_eq!(manager.pause(id).unwrap().status, TaskStatus::Paused);
+        assert_eq!(manager.resume(id).unwrap().status, TaskStatus::Running);
+        assert!(task.checkpoint());
+
+        let info = task.finish();
+        assert_eq!(info.status, TaskStatus::Completed);
+        assert!(manager.list().is_empty());
+        assert_eq!(manager.pause(id).unwrap_err().code(), "task_not_found");
+    }
+
+    #[test]
+    fn test_cancel_wakes_paused_worker() {
+        let manager = TaskManager::new();
+        let task = manager.start(TaskKind::Thumbnails, 1);
+        let id = task.id();
+        manager.pause(id).unwrap();
+
+        let worker = std::thread::spawn(move || {
+            let keep_going = task.checkpoint();
+            (keep_going, task.finish().status)
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        manager.cancel(id).unwrap();
+
+        assert_eq!(worker.join().unwrap(), (false, TaskStatus::Cancelled));
+        assert!(manager.list().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_kind() {
+        let manager = TaskManager::new();
+        let thumbnails = manager.start(TaskKind::Thumbnails, 1);
+        let export = manager.start(TaskKind::Export, 1);
+
+        let cancelled = manager.cancel_kind(TaskKind::Thumbnails);
+        assert_eq!(cancelled.len(), 1);
+        assert!(thumbnails.is_cancelled());
+        assert!(!export.is_cancelled());
+
+        // Resuming a cancelled task doesn't revive it
+        assert_eq!(
+            manager.resume(thumbnails.id()).unwrap().status,
+            TaskStatus::Cancelled
+        );
+    }
+}
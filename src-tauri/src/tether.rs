@@ -0,0 +1,119 @@
+use crate::image_processor::ImageInfo;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// How often the tethered folder is rescanned
+pub const POLL_INTERVAL: Duration = Duration::from_millis(750);
+
+/// Detects files newly written into a watched folder
+/// Cameras and tethering software write files incrementally, so a new file
+/// is only reported once its size is unchanged between two scans
+#[derive(Debug, Default)]
+pub struct ArrivalTracker {
+    known: HashSet<String>,
+    pending: HashMap<String, u64>,
+}
+
+impl ArrivalTracker {
+    /// Start tracking with the files already in the folder, which are not reported
+    pub fn new(existing: &[ImageInfo]) -> Self {
+        Self {
+            known: existing.iter().map(|i| i.filename.clone()).collect(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Feed the result of a rescan; returns the files that finished arriving, oldest first
+    pub fn poll(&mut self, images: Vec<ImageInfo>) -> Vec<ImageInfo> {
+        let mut arrived = Vec::new();
+        let mut still_pending = HashMap::new();
+
+        for image in images {
+            if self.known.contains(&image.filename) {
+                continue;
+            }
+            match self.pending.get(&image.filename) {
+                Some(&size) if size == image.size && image.size > 0 => {
+                    self.known.insert(image.filename.clone());
+                    arrived.push(image);
+                }
+                _ => {
+                    still_pending.insert(image.filename, image.size);
+                }
+            }
+        }
+
+        // Files deleted before they settled are simply forgotten
+        self.pending = still_pending;
+
+        arrived.sort_by(|a, b| {
+            a.modified_at
+                .cmp(&b.modified_at)
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        arrived
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_files_are_ignored() {
+        let mut tracker = ArrivalTracker::new(&[ImageInfo::fixture("DSC_0001.NEF").with_size(100)]);
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0001.NEF").with_size(100)])
+            .is_empty());
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0001.NEF").with_size(100)])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_new_file_reported_once_settled() {
+        let mut tracker = ArrivalTracker::new(&[]);
+
+        // Still being written
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0002.NEF").with_size(10)])
+            .is_empty());
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0002.NEF").with_size(50)])
+            .is_empty());
+
+        // Size unchanged: arrived
+        let arrived = tracker.poll(vec![ImageInfo::fixture("DSC_0002.NEF").with_size(50)]);
+        assert_eq!(arrived.len(), 1);
+        assert_eq!(arrived[0].filename, "DSC_0002.NEF");
+
+        // Only reported once
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0002.NEF").with_size(50)])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_empty_and_vanished_files() {
+        let mut tracker = ArrivalTracker::new(&[]);
+
+        // Zero-byte placeholders never count as arrived
+        tracker.poll(vec![ImageInfo::fixture("DSC_0003.NEF").with_size(0)]);
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0003.NEF").with_size(0)])
+            .is_empty());
+
+        // A file that disappears starts over when it comes back
+        tracker.poll(vec![ImageInfo::fixture("DSC_0004.NEF").with_size(20)]);
+        tracker.poll(vec![]);
+        assert!(tracker
+            .poll(vec![ImageInfo::fixture("DSC_0004.NEF").with_size(20)])
+            .is_empty());
+        assert_eq!(
+            tracker
+                .poll(vec![ImageInfo::fixture("DSC_0004.NEF").with_size(20)])
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// Minimal reader for TIFF-structured data (EXIF chunks and maker notes)
+
+/// TIFF field types used by callers
+pub const SHORT: u16 = 3;
+pub const LONG: u16 = 4;
+
+/// Byte order aware reads from a TIFF-structured buffer
+#[derive(Clone, Copy)]
+pub struct Tiff<'a> {
+    buf: &'a [u8],
+    little_endian: bool,
+}
+
+/// IFD entry; `value_pos` is the absolute position of the value in the buffer
+pub struct Entry {
+    pub tag: u16,
+    pub typ: u16,
+    pub count: usize,
+    pub value_pos: usize,
+}
+
+impl Entry {
+    /// Size of the value in bytes
+    pub fn size(&self) -> usize {
+        self.count.saturating_mul(type_size(self.typ))
+    }
+}
+
+impl<'a> Tiff<'a> {
+    pub fn new(buf: &'a [u8], little_endian: bool) -> Self {
+        Self { buf, little_endian }
+    }
+
+    /// Read a chunk starting with its own "II*\0" / "MM\0*" header
+    pub fn from_header(buf: &'a [u8]) -> Option<Self> {
+        match buf.get(0..4)? {
+            b"II*\0" => Some(Self::new(buf, true)),
+            b"MM\0*" => Some(Self::new(buf, false)),
+            _ => None,
+        }
+    }
+
+    pub fn buf(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub fn little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    pub fn u16(&self, pos: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.buf.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    pub fn u32(&self, pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.buf.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    pub fn shorts(&self, pos: usize, count: usize) -> Option<Vec<u16>> {
+        (0..count).map(|i| self.u16(pos + i * 2)).collect()
+    }
+
+    /// Entries of the IFD at `ifd_pos`; out-of-line values are at `base` + offset
+    pub fn entries(&self, ifd_pos: usize, base: usize) -> Option<Vec<Entry>> {
+        let count = self.u16(ifd_pos)? as usize;
+        (0..count)
+            .map(|i| {
+                let pos = ifd_pos + 2 + i * 12;
+                let typ = self.u16(pos + 2)?;
+                let count = self.u32(pos + 4)? as usize;
+                let size = count.checked_mul(type_size(typ))?;
+                let value_pos = if size <= 4 {
+                    pos + 8
+                } else {
+                    base + self.u32(pos + 8)? as usize
+                };
+                Some(Entry {
+                    tag: self.u16(pos)?,
+                    typ,
+                    count,
+                    value_pos,
+                })
+            })
+            .collect()
+    }
+
+    pub fn find(&self, ifd_pos: usize, base: usize, tag: u16) -> Option<Entry> {
+        self.entries(ifd_pos, base)?
+            .into_iter()
+            .find(|entry| entry.tag == tag)
+    }
+
+    pub fn bytes(&self, entry: &Entry) -> Option<&'a [u8]> {
+        self.buf
+            .get(entry.value_pos..entry.value_pos.checked_add(entry.size())?)
+    }
+}
+
+fn type_size(typ: u16) -> usize {
+    match typ {
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => 1,
+    }
+}
@@ -0,0 +1,284 @@
+// Moving files to the system trash (Trash on macOS and Linux, Recycle Bin on Windows)
+// Deleting from the grid should be as recoverable as deleting in the file manager
+
+use crate::error::{GlimpseError, Result};
+use std::path::Path;
+
+/// Move a file to the trash of the current user
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Err(GlimpseError::io_at(
+            path,
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        ));
+    }
+    platform::move_to_trash(path)
+}
+
+/// Path as `.trashinfo` records and `file://` URIs write it (URI-escaped, slashes kept)
+#[cfg_attr(any(target_os = "macos", windows), allow(dead_code))]
+pub(crate) fn percent_encode(path: &Path) -> String {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Put `path` into a freedesktop.org trash folder (`files/` plus an `info/` record that
+/// lets the file manager restore it)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn trash_into(trash_dir: &Path, path: &Path) -> Result<()> {
+    let files = trash_dir.join("files");
+    let info = trash_dir.join("info");
+    for dir in [&files, &info] {
+        std::fs::create_dir_all(dir).map_err(|e| GlimpseError::io_at(dir, e))?;
+    }
+
+    let original = std::fs::canonicalize(path).map_err(|e| GlimpseError::io_at(path, e))?;
+    let name = original
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // Earlier files of the same name stay in the trash
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem.to_string(), format!(".{}", extension)),
+        None => (name.clone(), String::new()),
+    };
+    let mut trashed = name;
+    let mut counter = 1;
+    while files.join(&trashed).exists() || info.join(format!("{}.trashinfo", trashed)).exists() {
+        counter += 1;
+        trashed = format!("{}.{}{}", stem, counter, extension);
+    }
+
+    let info_path = info.join(format!("{}.trashinfo", trashed));
+    let record = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&original),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    std::fs::write(&info_path, record).map_err(|e| GlimpseError::io_at(&info_path, e))?;
+
+    let target = files.join(&trashed);
+    let moved = std::fs::rename(&original, &target).or_else(|e| {
+        // The trash is on another volume than the file
+        if e.kind() != std::io::ErrorKind::CrossesDevices {
+            return Err(e);
+        }
+        let copied =
+            std::fs::copy(&original, &target).and_then(|_| std::fs::remove_file(&original));
+        if copied.is_err() {
+            let _ = std::fs::remove_file(&target);
+        }
+        copied
+    });
+    if let Err(e) = moved {
+        let _ = std::fs::remove_file(&info_path);
+        return Err(GlimpseError::io_at(path, e));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::error::{GlimpseError, Result};
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    use std::path::{Path, PathBuf};
+
+    pub fn move_to_trash(path: &Path) -> Result<()> {
+        let home_trash = dirs::data_dir()
+            .ok_or_else(|| GlimpseError::InvalidPath("No data directory for the trash".into()))?
+            .join("Trash");
+        // Files on other volumes (cards, external drives) go to that volume's own trash, so
+        // nothing is copied; the home trash only takes them when that can't be used
+        if let Some(trash_dir) = volume_trash(path, &home_trash) {
+            let created = std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(&trash_dir)
+                .map_err(|e| GlimpseError::io_at(&trash_dir, e));
+            match created.and_then(|()| super::trash_into(&trash_dir, path)) {
+                Ok(()) => return Ok(()),
+                Err(e) => tracing::debug!("Cannot use {}: {}", trash_dir.display(), e),
+            }
+        }
+        super::trash_into(&home_trash, path)
+    }
+
+    /// Trash folder on the volume holding `path`; None when that volume holds the home trash
+    fn volume_trash(path: &Path, home_trash: &Path) -> Option<PathBuf> {
+        let device = std::fs::metadata(path).ok()?.dev();
+        let home_device = home_trash
+            .ancestors()
+            .find_map(|p| std::fs::metadata(p).ok())?
+            .dev();
+        if device == home_device {
+            return None;
+        }
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        Some(topdir_trash(&mount_point(path, device), uid))
+    }
+
+    /// Top directory of the volume `device` that holds `path`
+    fn mount_point(path: &Path, device: u64) -> PathBuf {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        path.ancestors()
+            .take_while(|p| std::fs::metadata(p).is_ok_and(|m| m.dev() == device))
+            .last()
+            .unwrap_or(&path)
+            .to_path_buf()
+    }
+
+    /// `$topdir/.Trash/$uid` where an administrator set up `.Trash` (a real folder with the
+    /// sticky bit), otherwise `$topdir/.Trash-$uid`
+    pub(super) fn topdir_trash(topdir: &Path, uid: u32) -> PathBuf {
+        let shared = topdir.join(".Trash");
+        let usable = std::fs::symlink_metadata(&shared)
+            .is_ok_and(|m| m.is_dir() && m.permissions().mode() & 0o1000 != 0);
+        if usable {
+            shared.join(uid.to_string())
+        } else {
+            topdir.join(format!(".Trash-{}", uid))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use crate::error::{GlimpseError, Result};
+    use std::path::Path;
+
+    pub fn move_to_trash(path: &Path) -> Result<()> {
+        // Finder records where the file came from, so "Put Back" works
+        let path = std::fs::canonicalize(path).map_err(|e| GlimpseError::io_at(path, e))?;
+        let script = format!(
+            "tell application \"Finder\" to delete POSIX file \"{}\"",
+            path.to_string_lossy()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        );
+        let output = std::process::Command::new("osascript")
+            .args(["-e", &script])
+            .output()
+            .map_err(|e| GlimpseError::io_at(&path, e))?;
+        if !output.status.success() {
+            return Err(GlimpseError::io_at(
+                &path,
+                std::io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use crate::error::{GlimpseError, Result};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::UI::Shell::{
+        SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT, FO_DELETE,
+        SHFILEOPSTRUCTW,
+    };
+
+    pub fn move_to_trash(path: &Path) -> Result<()> {
+        // The path list is double-null terminated
+        let mut from: Vec<u16> = path.as_os_str().encode_wide().collect();
+        from.extend([0, 0]);
+        let mut operation = SHFILEOPSTRUCTW {
+            hwnd: std::ptr::null_mut(),
+            wFunc: FO_DELETE,
+            pFrom: from.as_ptr(),
+            pTo: std::ptr::null(),
+            fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT) as u16,
+            fAnyOperationsAborted: 0,
+            hNameMappings: std::ptr::null_mut(),
+            lpszProgressTitle: std::ptr::null(),
+        };
+        // SAFETY: `from` outlives the call and is terminated as the API requires
+        let result = unsafe { SHFileOperationW(&mut operation) };
+        if result != 0 || operation.fAnyOperationsAborted != 0 {
+            return Err(GlimpseError::io_at(
+                path,
+                std::io::Error::other(format!("Recycle Bin refused the file ({})", result)),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use crate::error::{GlimpseError, Result};
+    use std::path::Path;
+
+    pub fn move_to_trash(path: &Path) -> Result<()> {
+        Err(GlimpseError::io_at(
+            path,
+            std::io::Error::from(std::io::ErrorKind::Unsupported),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trash_into() {
+        let dir = tempdir().unwrap();
+        let trash = dir.path().join("Trash");
+        for _ in 0..2 {
+            let photo = dir.path().join("DSC 0001.NEF");
+            std::fs::write(&photo, b"raw").unwrap();
+            trash_into(&trash, &photo).unwrap();
+            assert!(!photo.exists());
+        }
+
+        assert!(trash.join("files/DSC 0001.NEF").exists());
+        assert!(trash.join("files/DSC 0001.2.NEF").exists());
+        let record = std::fs::read_to_string(trash.join("info/DSC 0001.NEF.trashinfo")).unwrap();
+        assert!(record.starts_with("[Trash Info]\nPath=/"));
+        assert!(record.contains("DSC%200001.NEF\nDeletionDate="));
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(
+            percent_encode(Path::new("/shoot/a b#1?,100%/写真.NEF")),
+            "/shoot/a%20b%231%3F%2C100%25/%E5%86%99%E7%9C%9F.NEF"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_topdir_trash() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        assert_eq!(
+            platform::topdir_trash(dir.path(), 1000),
+            dir.path().join(".Trash-1000")
+        );
+        // A shared .Trash without the sticky bit isn't safe to use
+        let shared = dir.path().join(".Trash");
+        std::fs::create_dir(&shared).unwrap();
+        assert_eq!(
+            platform::topdir_trash(dir.path(), 1000),
+            dir.path().join(".Trash-1000")
+        );
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        assert_eq!(
+            platform::topdir_trash(dir.path(), 1000),
+            shared.join("1000")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::{GlimpseError, Result};
+use crate::image_processor::external_path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Places command-line tools are commonly installed when they aren't on PATH
+/// (apps launched from Finder don't inherit the shell PATH)
+const TOOL_SEARCH_DIRS: &[&str] = &["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"];
+
+/// Video metadata
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct VideoInfo {
+    pub duration_seconds: Option<f64>,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+}
+
+/// Find a command-line tool (`ffmpeg`, `ffprobe`, `sftp`) on PATH or in the usual places
+pub(crate) fn find_tool(name: &str) -> Option<PathBuf> {
+    let exe = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+
+    let path_dirs = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    path_dirs
+        .into_iter()
+        .chain(TOOL_SEARCH_DIRS.iter().map(PathBuf::from))
+        .map(|dir| dir.join(&exe))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Check whether ffmpeg is available for poster frame extraction
+pub fn is_ffmpeg_available() -> bool {
+    find_tool("ffmpeg").is_some()
+}
+
+/// Extract a representative frame of a video as a JPEG no larger than `size` x `size`
+pub fn extract_poster_frame(video_path: &Path, output_path: &Path, size: u32) -> Result<()> {
+    let ffmpeg =
+        find_tool("ffmpeg").ok_or_else(|| GlimpseError::Video("ffmpeg was not found".into()))?;
+
+    // `thumbnail` picks the most representative of the first frames,
+    // which avoids black fade-in frames
+    let filter = format!(
+        "thumbnail,scale={0}:{0}:force_original_aspect_ratio=decrease",
+        size
+    );
+    let output = Command::new(ffmpeg)
+        .args(["-v", "error", "-y", "-i"])
+        .arg(external_path(video_path))
+        .args(["-vf", &filter, "-frames:v", "1", "-q:v", "3"])
+        .arg(external_path(output_path))
+        .output()
+        .map_err(|e| GlimpseError::io_at(video_path, e))?;
+
+    if !output.status.success() || !output_path.exists() {
+        return Err(GlimpseError::Video(format!(
+            "ffmpeg failed for {}: {}",
+            video_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Read duration/codec metadata of a video with ffprobe
+pub fn probe_video(video_path: &Path) -> Result<VideoInfo> {
+    let ffprobe =
+        find_tool("ffprobe").ok_or_else(|| GlimpseError::Video("ffprobe was not found".into()))?;
+
+    let output = Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(external_path(video_path))
+        .output()
+        .map_err(|e| GlimpseError::io_at(video_path, e))?;
+
+    if !output.status.success() {
+        return Err(GlimpseError::Video(format!(
+            "ffprobe failed for {}: {}",
+            video_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_ffprobe_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse ffprobe's JSON output
+fn parse_ffprobe_output(json: &str) -> Result<VideoInfo> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| GlimpseError::Video(e.to_string()))?;
+
+    let video_stream = value["streams"]
+        .as_array()
+        .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"));
+
+    let mut info = VideoInfo {
+        duration_seconds: value["format"]["duration"]
+            .as_str()
+            .and_then(|d| d.parse().ok()),
+        ..Default::default()
+    };
+
+    if let Some(stream) = video_stream {
+        info.codec = stream["codec_name"].as_str().map(String::from);
+        info.width = stream["width"].as_u64().map(|w| w as u32);
+        info.height = stream["height"].as_u64().map(|h| h as u32);
+        info.frame_rate = stream["avg_frame_rate"]
+            .as_str()
+            .or_else(|| stream["r_frame_rate"].as_str())
+            .and_then(parse_frame_rate);
+    }
+
+    Ok(info)
+}
+
+/// Parse an ffprobe rational frame rate such as "30000/1001"
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    (den != 0.0).then(|| num / den)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate("30/1"), Some(30.0));
+        assert!((parse_frame_rate("30000/1001").unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("abc"), None);
+    }
+
+    #[test]
+    fn test_parse_ffprobe_output() {
+        let json = r#"{
+            "streams": [
+                { "codec_type": "audio", "codec_name": "aac" },
+                {
+                    "codec_type": "video",
+                    "codec_name": "hevc",
+                    "width": 3840,
+                    "height": 2160,
+                    "avg_frame_rate": "60000/1001"
+                }
+            ],
+            "format": { "duration": "12.345000" }
+        }"#;
+
+        let info = parse_ffprobe_output(json).unwrap();
+        assert_eq!(info.codec.as_deref(), Some("hevc"));
+        assert_eq!(info.width, Some(3840));
+        assert_eq!(info.height, Some(2160));
+        assert_eq!(info.duration_seconds, Some(12.345));
+        assert!((info.frame_rate.unwrap() - 59.94).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_ffprobe_output_without_video_stream() {
+        let info = parse_ffprobe_output(r#"{"streams": [], "format": {}}"#).unwrap();
+        assert_eq!(info, VideoInfo::default());
+    }
+}
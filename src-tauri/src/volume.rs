@@ -0,0 +1,137 @@
+// Detection of source folders whose drive (card reader, external SSD) was disconnected
+
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// How often the folders of the open session are checked
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Change in the availability of a session's folders
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceChange {
+    /// These folders disappeared
+    Lost(Vec<PathBuf>),
+    /// All folders are back
+    Restored,
+}
+
+/// Turns periodic folder checks into lost/restored transitions
+#[derive(Debug, Default)]
+pub struct SourceMonitor {
+    unavailable: bool,
+}
+
+impl SourceMonitor {
+    /// Feed the currently missing folders; returns a change when availability flipped
+    pub fn poll(&mut self, missing: Vec<PathBuf>) -> Option<SourceChange> {
+        match (self.unavailable, missing.is_empty()) {
+            (false, false) => {
+                self.unavailable = true;
+                Some(SourceChange::Lost(missing))
+            }
+            (true, true) => {
+                self.unavailable = false;
+                Some(SourceChange::Restored)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Folders that can't be reached right now
+pub fn missing_folders(folders: &[PathBuf]) -> Vec<PathBuf> {
+    folders
+        .iter()
+        .filter(|folder| !folder.is_dir())
+        .cloned()
+        .collect()
+}
+
+/// Mount point of the removable or external volume holding `path`
+/// Drive roots on Windows; `/Volumes/<name>` on macOS; `/media`, `/run/media` and `/mnt`
+/// mounts on Linux. None for paths on the system volume
+pub fn volume_root(path: &Path) -> Option<PathBuf> {
+    let mut components = path.components();
+    match components.next()? {
+        Component::Prefix(prefix) => {
+            let mut root = prefix.as_os_str().to_os_string();
+            root.push("\\");
+            Some(PathBuf::from(root))
+        }
+        Component::RootDir => {
+            let names: Vec<&str> = components
+                .take(4)
+                .filter_map(|c| c.as_os_str().to_str())
+                .collect();
+            let depth = match names.as_slice() {
+                ["Volumes", _, ..] | ["mnt", _, ..] => 2,
+                ["media", _, _, ..] => 3,
+                ["run", "media", _, _, ..] => 4,
+                _ => return None,
+            };
+            Some(
+                names[..depth]
+                    .iter()
+                    .fold(PathBuf::from("/"), |root, name| root.join(name)),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Whether `path` is on an external volume that is currently not mounted
+pub fn is_volume_missing(path: &Path) -> bool {
+    volume_root(path).is_some_and(|root| !root.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_source_monitor_transitions() {
+        let mut monitor = SourceMonitor::default();
+        let card = PathBuf::from("/Volumes/EOS_DIGITAL/DCIM");
+
+        assert_eq!(monitor.poll(vec![]), None);
+        assert_eq!(
+            monitor.poll(vec![card.clone()]),
+            Some(SourceChange::Lost(vec![card.clone()]))
+        );
+        // Reported once until it comes back
+        assert_eq!(monitor.poll(vec![card]), None);
+        assert_eq!(monitor.poll(vec![]), Some(SourceChange::Restored));
+        assert_eq!(monitor.poll(vec![]), None);
+    }
+
+    #[test]
+    fn test_missing_folders() {
+        let dir = tempdir().unwrap();
+        let gone = dir.path().join("unplugged");
+        let folders = vec![dir.path().to_path_buf(), gone.clone()];
+
+        assert_eq!(missing_folders(&folders), vec![gone]);
+    }
+
+    #[test]
+    fn test_volume_root() {
+        assert_eq!(
+            volume_root(Path::new("/Volumes/EOS_DIGITAL/DCIM/100CANON")),
+            Some(PathBuf::from("/Volumes/EOS_DIGITAL"))
+        );
+        assert_eq!(
+            volume_root(Path::new("/media/daigo/SSD/shoot")),
+            Some(PathBuf::from("/media/daigo/SSD"))
+        );
+        assert_eq!(
+            volume_root(Path::new("/run/media/daigo/SSD/shoot")),
+            Some(PathBuf::from("/run/media/daigo/SSD"))
+        );
+        assert_eq!(volume_root(Path::new("/Users/daigo/Pictures")), None);
+        assert_eq!(volume_root(Path::new("relative/path")), None);
+        assert!(is_volume_missing(Path::new(
+            "/Volumes/glimpse-test-not-mounted/a.jpg"
+        )));
+    }
+}
@@ -0,0 +1,282 @@
+// Watermarks stamped on exported JPEGs (proofs delivered to clients)
+// The mark is a PNG overlay or a line of text, scaled to the photo it's placed on
+
+use crate::error::{GlimpseError, Result};
+use crate::font::Font;
+use image::imageops::FilterType;
+use image::{Rgb, RgbImage, RgbaImage};
+use std::path::Path;
+
+/// Gap between the mark and the photo's edges, as a fraction of its short side
+const MARGIN: f32 = 0.03;
+
+/// What is stamped
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatermarkMark {
+    /// PNG (or any image) whose transparency is kept, e.g. a studio logo
+    Image { path: String },
+    /// A line of text (e.g. "© Studio North — proof") in `color`
+    Text {
+        text: String,
+        /// TrueType font file; None uses a common system sans-serif
+        #[serde(default)]
+        font: Option<String>,
+        #[serde(default = "default_color")]
+        color: [u8; 3],
+    },
+}
+
+fn default_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+/// Where on the photo the mark goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Watermark {
+    pub mark: WatermarkMark,
+    #[serde(default)]
+    pub position: WatermarkPosition,
+    /// 0.0 (invisible) to 1.0 (opaque)
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// Width of the mark as a fraction of the photo's width
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_opacity() -> f32 {
+    0.5
+}
+
+fn default_scale() -> f32 {
+    0.25
+}
+
+impl Watermark {
+    pub fn validate(&self) -> Result<()> {
+        let empty = match &self.mark {
+            WatermarkMark::Image { path } => path.trim().is_empty(),
+            WatermarkMark::Text { text, .. } => text.trim().is_empty(),
+        };
+        if empty {
+            return Err(GlimpseError::InvalidConfig(
+                "The watermark needs an image or text".to_string(),
+            ));
+        }
+        let in_range = |value: f32| value > 0.0 && value <= 1.0;
+        if !in_range(self.opacity) || !in_range(self.scale) {
+            return Err(GlimpseError::InvalidConfig(format!(
+                "Watermark opacity and scale must be above 0 and at most 1: {}, {}",
+                self.opacity, self.scale
+            )));
+        }
+        Ok(())
+    }
+
+    /// The mark, `width` pixels wide
+    pub fn render(&self, width: u32) -> Result<RgbaImage> {
+        let width = width.max(1);
+        match &self.mark {
+            WatermarkMark::Image { path } => {
+                let path = Path::new(path);
+                let overlay = image::open(path)
+                    .map_err(|e| match e {
+                        image::ImageError::IoError(e) => GlimpseError::io_at(path, e),
+                        e => e.into(),
+                    })?
+                    .to_rgba8();
+                let height = (u64::from(overlay.height()) * u64::from(width)
+                    / u64::from(overlay.width().max(1)))
+                .max(1) as u32;
+                Ok(image::imageops::resize(
+                    &overlay,
+                    width,
+                    height,
+                    FilterType::Lanczos3,
+                ))
+            }
+            WatermarkMark::Text { text, font, color } => {
+                let font = Font::load(font.as_deref().map(Path::new))?;
+                let px_per_em = width as f32 / font.text_width(text).max(f32::EPSILON);
+                let coverage = font.render(text, px_per_em);
+                Ok(RgbaImage::from_fn(
+                    coverage.width(),
+                    coverage.height(),
+                    |x, y| {
+                        let [r, g, b] = *color;
+                        image::Rgba([r, g, b, coverage.get_pixel(x, y).0[0]])
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Stamp the mark on `img`
+    pub fn apply(&self, img: &mut RgbImage) -> Result<()> {
+        let (width, height) = img.dimensions();
+        let margin = (width.min(height) as f32 * MARGIN).round() as u32;
+        let mut mark = self.render((width as f32 * self.scale).round() as u32)?;
+        // A tall mark on a wide photo is shrunk to fit its height
+        let room = height.saturating_sub(2 * margin).max(1);
+        if mark.height() > room {
+            let fitted = (u64::from(mark.width()) * u64::from(room) / u64::from(mark.height()))
+                .max(1) as u32;
+            mark = image::imageops::resize(&mark, fitted, room, FilterType::Lanczos3);
+        }
+
+        let (x, y) = self.origin((width, height), mark.dimensions(), margin);
+        blend(img, &mark, (x, y), self.opacity);
+        Ok(())
+    }
+
+    /// Top-left corner of a `mark`-sized mark on a `photo`-sized photo
+    fn origin(&self, photo: (u32, u32), mark: (u32, u32), margin: u32) -> (i64, i64) {
+        use WatermarkPosition::*;
+        let along = |photo: u32, mark: u32, start: bool, end: bool| {
+            let (photo, mark, margin) = (i64::from(photo), i64::from(mark), i64::from(margin));
+            if start {
+                margin
+            } else if end {
+                photo - mark - margin
+            } else {
+                (photo - mark) / 2
+            }
+        };
+        let left = matches!(self.position, TopLeft | Left | BottomLeft);
+        let right = matches!(self.position, TopRight | Right | BottomRight);
+        let top = matches!(self.position, TopLeft | Top | TopRight);
+        let bottom = matches!(self.position, BottomLeft | Bottom | BottomRight);
+        (
+            along(photo.0, mark.0, left, right),
+            along(photo.1, mark.1, top, bottom),
+        )
+    }
+}
+
+/// Paint `mark` over `img` at `origin`, its alpha scaled by `opacity`; parts outside are cut
+fn blend(img: &mut RgbImage, mark: &RgbaImage, origin: (i64, i64), opacity: f32) {
+    for (mx, my, pixel) in mark.enumerate_pixels() {
+        let (x, y) = (origin.0 + i64::from(mx), origin.1 + i64::from(my));
+        if x < 0 || y < 0 || x >= i64::from(img.width()) || y >= i64::from(img.height()) {
+            continue;
+        }
+        let alpha = f32::from(pixel.0[3]) / 255.0 * opacity;
+        if alpha <= 0.0 {
+            continue;
+        }
+        let target = img.get_pixel_mut(x as u32, y as u32);
+        let Rgb(channels) = *target;
+        *target = Rgb(std::array::from_fn(|i| {
+            (f32::from(channels[i]) * (1.0 - alpha) + f32::from(pixel.0[i]) * alpha).round() as u8
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn logo_watermark(path: &Path, position: WatermarkPosition) -> Watermark {
+        Watermark {
+            mark: WatermarkMark::Image {
+                path: path.to_string_lossy().to_string(),
+            },
+            position,
+            opacity: 1.0,
+            scale: 0.25,
+        }
+    }
+
+    #[test]
+    fn test_image_watermark() {
+        let dir = tempdir().unwrap();
+        let logo = dir.path().join("logo.png");
+        RgbaImage::from_pixel(10, 5, image::Rgba([255, 0, 0, 255]))
+            .save(&logo)
+            .unwrap();
+
+        // 200x100 photo: a 50x25 mark, 3 px from the bottom right corner
+        let mut img = RgbImage::new(200, 100);
+        logo_watermark(&logo, WatermarkPosition::BottomRight)
+            .apply(&mut img)
+            .unwrap();
+        assert_eq!(img.get_pixel(170, 80).0, [255, 0, 0]);
+        assert_eq!(img.get_pixel(140, 80).0, [0, 0, 0]);
+        assert_eq!(img.get_pixel(198, 98).0, [0, 0, 0]);
+
+        let mut img = RgbImage::new(200, 100);
+        let mut centered = logo_watermark(&logo, WatermarkPosition::Center);
+        centered.opacity = 0.5;
+        centered.apply(&mut img).unwrap();
+        assert_eq!(img.get_pixel(100, 50).0, [128, 0, 0]);
+        assert_eq!(img.get_pixel(70, 50).0, [0, 0, 0]);
+
+        let missing = logo_watermark(&dir.path().join("missing.png"), WatermarkPosition::Top);
+        assert!(missing.apply(&mut img).is_err());
+    }
+
+    #[test]
+    fn test_text_watermark() {
+        let dir = tempdir().unwrap();
+        let font = dir.path().join("square.ttf");
+        std::fs::write(&font, crate::font::tests::square_font()).unwrap();
+        let watermark = Watermark {
+            mark: WatermarkMark::Text {
+                text: "AB".to_string(),
+                font: Some(font.to_string_lossy().to_string()),
+                color: [0, 0, 255],
+            },
+            position: WatermarkPosition::TopLeft,
+            opacity: 1.0,
+            scale: 0.5,
+        };
+
+        // 100 px of text on a 200x200 photo at 6 px from the corner: 40 px squares at x 12
+        // and 62, y 7
+        let mut img = RgbImage::from_pixel(200, 200, Rgb([255, 255, 255]));
+        watermark.apply(&mut img).unwrap();
+        assert_eq!(img.get_pixel(30, 25).0, [0, 0, 255]);
+        assert_eq!(img.get_pixel(80, 25).0, [0, 0, 255]);
+        assert_eq!(img.get_pixel(55, 25).0, [255, 255, 255]);
+        assert_eq!(img.get_pixel(30, 100).0, [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let watermark = logo_watermark(Path::new("/logo.png"), WatermarkPosition::default());
+        assert!(watermark.validate().is_ok());
+        assert!(Watermark {
+            opacity: 0.0,
+            ..watermark.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Watermark {
+            mark: WatermarkMark::Text {
+                text: " ".to_string(),
+                font: None,
+                color: default_color(),
+            },
+            ..watermark
+        }
+        .validate()
+        .is_err());
+    }
+}
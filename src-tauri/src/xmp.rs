@@ -0,0 +1,178 @@
+// XMP sidecars written next to exported RAW files
+// Lightroom, Capture One and darktable read the rating, color label and keywords from them,
+// so keepers open in the editor already rated
+
+use crate::error::{GlimpseError, Result};
+use crate::metadata::MetadataEdits;
+use crate::stats::EXIF_DATE_FORMAT;
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+
+/// What the sidecars of an export say about each file
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct XmpSidecar {
+    /// Star rating (1-5) of adopted files; rejected files get -1, the editors' reject flag
+    pub rating: u8,
+    /// Color label of adopted files as the editor names it (e.g. "Green")
+    pub color_label: Option<String>,
+    /// Keywords of every exported file (e.g. the client or event)
+    pub keywords: Vec<String>,
+}
+
+impl Default for XmpSidecar {
+    fn default() -> Self {
+        Self {
+            rating: 1,
+            color_label: None,
+            keywords: Vec::new(),
+        }
+    }
+}
+
+impl XmpSidecar {
+    /// Sidecar content for a file with the Glimpse `label` and metadata corrections `edits`
+    pub fn render(&self, label: Option<&str>, edits: Option<&MetadataEdits>) -> String {
+        let (rating, color_label) = match label {
+            Some("adopted") => (i32::from(self.rating), self.color_label.as_deref()),
+            Some("rejected") => (-1, None),
+            _ => (0, None),
+        };
+
+        let mut attributes = format!("\n    xmp:Rating=\"{}\"", rating);
+        if let Some(color_label) = color_label.filter(|l| !l.trim().is_empty()) {
+            attributes.push_str(&format!(
+                "\n    xmp:Label=\"{}\"",
+                escape(color_label.trim())
+            ));
+        }
+        let edits = edits.cloned().unwrap_or_default();
+        let date = edits
+            .date_taken
+            .as_deref()
+            .and_then(|date| NaiveDateTime::parse_from_str(date, EXIF_DATE_FORMAT).ok());
+        if let Some(date) = date {
+            attributes.push_str(&format!(
+                "\n    exif:DateTimeOriginal=\"{}\"",
+                date.format("%Y-%m-%dT%H:%M:%S")
+            ));
+        }
+
+        let mut properties = String::new();
+        if let Some(artist) = &edits.artist {
+            properties.push_str(&format!(
+                "   <dc:creator>\n    <rdf:Seq>\n     <rdf:li>{}</rdf:li>\n    </rdf:Seq>\n   </dc:creator>\n",
+                escape(artist)
+            ));
+        }
+        if let Some(copyright) = &edits.copyright {
+            properties.push_str(&format!(
+                "   <dc:rights>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n    </rdf:Alt>\n   </dc:rights>\n",
+                escape(copyright)
+            ));
+        }
+        let keywords: Vec<&str> = self
+            .keywords
+            .iter()
+            .map(|keyword| keyword.trim())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+        if !keywords.is_empty() {
+            let items: String = keywords
+                .iter()
+                .map(|keyword| format!("     <rdf:li>{}</rdf:li>\n", escape(keyword)))
+                .collect();
+            properties.push_str(&format!(
+                "   <dc:subject>\n    <rdf:Bag>\n{}    </rdf:Bag>\n   </dc:subject>\n",
+                items
+            ));
+        }
+
+        format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\" x:xmptk=\"Glimpse\">\n\
+             \x20<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+             \x20 <rdf:Description rdf:about=\"\"\n\
+             \x20   xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n\
+             \x20   xmlns:exif=\"http://ns.adobe.com/exif/1.0/\"\n\
+             \x20   xmlns:dc=\"http://purl.org/dc/elements/1.1/\"{}>\n\
+             {}\
+             \x20 </rdf:Description>\n\
+             \x20</rdf:RDF>\n\
+             </x:xmpmeta>\n",
+            attributes, properties
+        )
+    }
+}
+
+/// Sidecar of `file`: the same name with an `.xmp` extension, as Lightroom and Capture One
+/// expect (DSC_0001.NEF -> DSC_0001.xmp)
+pub fn sidecar_path(file: &Path) -> PathBuf {
+    file.with_extension("xmp")
+}
+
+/// Write the sidecar of `file`, replacing an older one
+pub fn write_sidecar(file: &Path, content: &str) -> Result<()> {
+    let path = sidecar_path(file);
+    std::fs::write(&path, content).map_err(|e| GlimpseError::io_at(&path, e))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_render() {
+        let sidecar = XmpSidecar {
+            rating: 3,
+            color_label: Some("Green".to_string()),
+            keywords: vec!["Smith & Jones".to_string(), " ".to_string()],
+        };
+
+        let adopted = sidecar.render(Some("adopted"), None);
+        assert!(adopted.contains("xmp:Rating=\"3\""));
+        assert!(adopted.contains("xmp:Label=\"Green\""));
+        assert!(adopted.contains("<rdf:li>Smith &amp; Jones</rdf:li>"));
+        assert_eq!(adopted.matches("<rdf:li>").count(), 1);
+
+        let rejected = sidecar.render(Some("rejected"), None);
+        assert!(rejected.contains("xmp:Rating=\"-1\""));
+        assert!(!rejected.contains("xmp:Label"));
+        assert!(sidecar.render(None, None).contains("xmp:Rating=\"0\""));
+
+        let bare = XmpSidecar::default().render(Some("adopted"), None);
+        assert!(bare.contains("xmp:Rating=\"1\""));
+        assert!(!bare.contains("dc:subject"));
+        assert!(!bare.contains("dc:creator"));
+
+        let edits = MetadataEdits {
+            artist: Some("Jane Doe".to_string()),
+            copyright: Some("(c) 2024 Studio North".to_string()),
+            date_taken: Some("2024-12-15 19:05:30".to_string()),
+        };
+        let edited = XmpSidecar::default().render(None, Some(&edits));
+        assert!(edited.contains("exif:DateTimeOriginal=\"2024-12-15T19:05:30\""));
+        assert!(edited.contains("<rdf:li>Jane Doe</rdf:li>"));
+        assert!(edited.contains("<rdf:li xml:lang=\"x-default\">(c) 2024 Studio North</rdf:li>"));
+    }
+
+    #[test]
+    fn test_write_sidecar() {
+        let dir = tempdir().unwrap();
+        let raw = dir.path().join("DSC_0001.NEF");
+        assert_eq!(sidecar_path(&raw), dir.path().join("DSC_0001.xmp"));
+
+        write_sidecar(&raw, &XmpSidecar::default().render(Some("adopted"), None)).unwrap();
+        let content = std::fs::read_to_string(dir.path().join("DSC_0001.xmp")).unwrap();
+        assert!(content.starts_with("<x:xmpmeta"));
+        assert!(content.trim_end().ends_with("</x:xmpmeta>"));
+    }
+}
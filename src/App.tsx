@@ -18,10 +18,12 @@ import {
 import { useKeyboardNavigation, useGridConfig, useDragAndDrop, useImageLabels } from '@/hooks';
 import { useTranslation } from '@/i18n';
 import type { ImageItem, LabelStatus, FilterMode, ThemeMode, ViewMode } from '@/types';
+import type { ExportOptions } from '@/components/ExportDialog';
 import {
   selectFolder,
   openFolder,
   saveSelection,
+  saveViewState,
   exportAdopted,
   selectExportFolder,
   onThumbnailProgress,
@@ -30,6 +32,7 @@ import {
   clearCache,
   type ThumbnailResult,
   type SubfolderInfo,
+  type ViewState,
 } from '@/utils/tauri';
 import { playCompletionSound } from '@/utils/notification';
 import { getVersion } from '@tauri-apps/api/app';
@@ -37,6 +40,9 @@ import { getVersion } from '@tauri-apps/api/app';
 const GITHUB_OWNER = 'daigotanaka0714';
 const GITHUB_REPO = 'glimpse';
 
+const isFilterMode = (value: string | null): value is FilterMode =>
+  value === 'all' || value === 'adopted' || value === 'rejected';
+
 export default function App() {
   const t = useTranslation();
 
@@ -74,6 +80,8 @@ export default function App() {
 
   // Store session info
   const sessionRef = useRef<{ id: string; cacheDir: string } | null>(null);
+  // View state of the open session (keeps values this UI doesn't edit, like sort order)
+  const viewStateRef = useRef<ViewState | null>(null);
 
   // Apply theme
   useEffect(() => {
@@ -116,7 +124,7 @@ export default function App() {
 
       // Convert image info to ImageItem
       const imageItems = result.images.map((info, index) =>
-        toImageItem(info, index, labelsMap, result.cache_dir)
+        toImageItem(info, index, labelsMap, result.cache_dir, result.cache_extension)
       );
 
       setImages(imageItems);
@@ -124,12 +132,21 @@ export default function App() {
       setSelectedIndices(new Set());
       setAnchorIndex(result.last_selected_index);
       setThumbnailProgress({ completed: 0, total: result.images.length });
+
+      // Restore where the user left off
+      const view = result.view_state;
+      viewStateRef.current = view;
+      setFilterMode(isFilterMode(view.filter) ? view.filter : 'all');
+      if (view.grid_zoom) {
+        setBaseThumbnailSize(view.grid_zoom);
+        updateGridSize(view.grid_zoom);
+      }
     } catch (error) {
       console.error('Failed to open folder:', error);
     } finally {
       setIsLoading(false);
     }
-  }, []);
+  }, [updateGridSize]);
 
   // Drag & drop
   const { isDragging } = useDragAndDrop({
@@ -183,6 +200,18 @@ export default function App() {
     }
   }, [selectedIndex, images.length]);
 
+  // Save filter and zoom so reopening the session restores them
+  useEffect(() => {
+    if (sessionRef.current && viewStateRef.current) {
+      viewStateRef.current = {
+        ...viewStateRef.current,
+        filter: filterMode,
+        grid_zoom: baseThumbnailSize,
+      };
+      saveViewState(viewStateRef.current).catch(console.error);
+    }
+  }, [filterMode, baseThumbnailSize]);
+
   // Filtered image list
   const filteredImages = useMemo(() => {
     switch (filterMode) {
@@ -410,10 +439,13 @@ export default function App() {
     }
   }, [folderPath, handleOpenFolderByPath]);
 
-  const handleExport = useCallback(async (options: { destinationPath: string; mode: 'copy' | 'move' }) => {
+  const handleExport = useCallback(async (options: ExportOptions) => {
     if (!folderPath) return;
 
-    await exportAdopted(folderPath, options.destinationPath, options.mode);
+    await exportAdopted(folderPath, options.destinationPath, options.mode, {
+      auto_rotate: options.autoRotate,
+      strip_private_metadata: options.stripPrivateMetadata,
+    });
   }, [folderPath]);
 
   const handleSelectExportFolder = useCallback(async (): Promise<string | null> => {
@@ -6,6 +6,8 @@ export type ExportMode = 'copy' | 'move';
 export interface ExportOptions {
   destinationPath: string;
   mode: ExportMode;
+  autoRotate: boolean;
+  stripPrivateMetadata: boolean;
 }
 
 interface ExportDialogProps {
@@ -25,6 +27,8 @@ export function ExportDialog({
 }: ExportDialogProps) {
   const [destinationPath, setDestinationPath] = useState<string>('');
   const [exportMode, setExportMode] = useState<ExportMode>('copy');
+  const [autoRotate, setAutoRotate] = useState(false);
+  const [stripPrivateMetadata, setStripPrivateMetadata] = useState(false);
   const [isExporting, setIsExporting] = useState(false);
   const [isComplete, setIsComplete] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -47,7 +51,13 @@ export function ExportDialog({
     setError(null);
 
     try {
-      await onExport({ destinationPath, mode: exportMode });
+      await onExport({
+        destinationPath,
+        mode: exportMode,
+        // Rotating re-encodes, and a move must not leave only a re-encoded copy
+        autoRotate: autoRotate && exportMode === 'copy',
+        stripPrivateMetadata,
+      });
       setIsComplete(true);
     } catch (e) {
       setError(e instanceof Error ? e.message : 'Export failed');
@@ -150,6 +160,28 @@ export function ExportDialog({
             </p>
           </div>
 
+          {/* JPEG metadata */}
+          <div className="space-y-2">
+            <label className="flex items-center gap-2 text-sm text-white/70">
+              <input
+                type="checkbox"
+                checked={autoRotate && exportMode === 'copy'}
+                onChange={(e) => setAutoRotate(e.target.checked)}
+                disabled={isExporting || exportMode === 'move'}
+              />
+              Rotate JPEGs upright (re-encodes them; copies only)
+            </label>
+            <label className="flex items-center gap-2 text-sm text-white/70">
+              <input
+                type="checkbox"
+                checked={stripPrivateMetadata}
+                onChange={(e) => setStripPrivateMetadata(e.target.checked)}
+                disabled={isExporting}
+              />
+              Remove GPS location, serial numbers and owner name from JPEGs
+            </label>
+          </div>
+
           {/* Error message */}
           {error && (
             <div className="p-3 bg-rejected/20 border border-rejected/30 rounded-lg text-sm text-rejected">
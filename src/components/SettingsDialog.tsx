@@ -1,5 +1,5 @@
 import { useState, useEffect, useCallback } from 'react';
-import { X, Cpu, Zap, Info, HardDrive, Trash2, Tag, AlertTriangle, RefreshCw, ExternalLink, Heart, Globe, MessageCircle } from 'lucide-react';
+import { X, Cpu, Zap, Info, HardDrive, Trash2, Tag, AlertTriangle, RefreshCw, ExternalLink, Link2, Heart, Globe, MessageCircle } from 'lucide-react';
 import { invoke } from '@tauri-apps/api/core';
 import { getVersion } from '@tauri-apps/api/app';
 import { open } from '@tauri-apps/plugin-shell';
@@ -20,6 +20,12 @@ interface SystemInfo {
   cpu_count: number;
   current_threads: number;
   recommended_threads: number;
+  current_raw_threads: number;
+  ffmpeg_available: boolean;
+  total_memory_bytes: number | null;
+  available_memory_bytes: number | null;
+  os_version: string;
+  gpu_name: string | null;
 }
 
 interface StorageInfo {
@@ -27,6 +33,27 @@ interface StorageInfo {
   cache_size_display: string;
   label_count: number;
   session_count: number;
+  sessions: SessionStorage[];
+}
+
+interface SessionStorage {
+  session_id: string;
+  folder_path: string;
+  thumbnail_bytes: number;
+  preview_bytes: number;
+  label_count: number;
+  last_opened: string | null;
+}
+
+interface DedupStats {
+  files_linked: number;
+  bytes_reclaimed: number;
+}
+
+interface OptimizeStats {
+  before_bytes: number;
+  after_bytes: number;
+  bytes_reclaimed: number;
 }
 
 interface SettingsDialogProps {
@@ -45,9 +72,10 @@ export function SettingsDialog({ onClose }: SettingsDialogProps) {
   const [useAuto, setUseAuto] = useState(true);
   const [isSaving, setIsSaving] = useState(false);
   const [saved, setSaved] = useState(false);
-  const [isClearing, setIsClearing] = useState<'cache' | 'labels' | null>(null);
+  const [isClearing, setIsClearing] = useState<'cache' | 'labels' | 'dedup' | 'optimize' | null>(null);
   const [confirmDialog, setConfirmDialog] = useState<'cache' | 'labels' | null>(null);
   const [clearResult, setClearResult] = useState<string | null>(null);
+  const [clearingSession, setClearingSession] = useState<string | null>(null);
   const [updateInfo, setUpdateInfo] = useState<UpdateInfo | null>(null);
   const [isCheckingUpdate, setIsCheckingUpdate] = useState(false);
   const [updateError, setUpdateError] = useState<string | null>(null);
@@ -129,6 +157,54 @@ export function SettingsDialog({ onClose }: SettingsDialogProps) {
     }
   };
 
+  const handleDedupCache = async () => {
+    setIsClearing('dedup');
+    try {
+      const stats = await invoke<DedupStats>('dedup_cache');
+      setClearResult(`${t.settings.storage.dedupDone}: ${formatBytes(stats.bytes_reclaimed)}`);
+      await fetchStorageInfo();
+      setTimeout(() => setClearResult(null), 3000);
+    } catch (error) {
+      console.error('Failed to deduplicate cache:', error);
+      setClearResult(t.settings.storage.dedupFailed);
+      setTimeout(() => setClearResult(null), 3000);
+    } finally {
+      setIsClearing(null);
+    }
+  };
+
+  const handleOptimizeDatabase = async () => {
+    setIsClearing('optimize');
+    try {
+      const stats = await invoke<OptimizeStats>('optimize_database');
+      setClearResult(`${t.settings.storage.optimizeDone}: ${formatBytes(stats.bytes_reclaimed)}`);
+      await fetchStorageInfo();
+      setTimeout(() => setClearResult(null), 3000);
+    } catch (error) {
+      console.error('Failed to optimize database:', error);
+      setClearResult(t.settings.storage.optimizeFailed);
+      setTimeout(() => setClearResult(null), 3000);
+    } finally {
+      setIsClearing(null);
+    }
+  };
+
+  const handleClearSessionCache = async (sessionId: string) => {
+    setClearingSession(sessionId);
+    try {
+      const clearedBytes = await invoke<number>('clear_session_cache', { sessionId });
+      setClearResult(`${t.settings.storage.cacheCleared}: ${formatBytes(clearedBytes)}`);
+      await fetchStorageInfo();
+      setTimeout(() => setClearResult(null), 3000);
+    } catch (error) {
+      console.error('Failed to clear session cache:', error);
+      setClearResult(t.settings.storage.clearFailed);
+      setTimeout(() => setClearResult(null), 3000);
+    } finally {
+      setClearingSession(null);
+    }
+  };
+
   const handleClearLabels = async () => {
     setConfirmDialog(null);
     setIsClearing('labels');
@@ -586,14 +662,24 @@ export function SettingsDialog({ onClose }: SettingsDialogProps) {
                           <p className="text-xs text-text-secondary">{storageInfo.cache_size_display}</p>
                         </div>
                       </div>
-                      <button
-                        onClick={() => setConfirmDialog('cache')}
-                        disabled={isClearing === 'cache' || storageInfo.cache_size_bytes === 0}
-                        className="flex items-center gap-1.5 px-3 py-1.5 bg-red-500/20 text-red-400 hover:bg-red-500/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
-                      >
-                        <Trash2 size={12} />
-                        {isClearing === 'cache' ? t.settings.storage.clearing : t.settings.storage.clear}
-                      </button>
+                      <div className="flex items-center gap-2">
+                        <button
+                          onClick={handleDedupCache}
+                          disabled={isClearing !== null || storageInfo.cache_size_bytes === 0}
+                          className="flex items-center gap-1.5 px-3 py-1.5 bg-accent/20 text-accent hover:bg-accent/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
+                        >
+                          <Link2 size={12} />
+                          {isClearing === 'dedup' ? t.settings.storage.deduplicating : t.settings.storage.dedup}
+                        </button>
+                        <button
+                          onClick={() => setConfirmDialog('cache')}
+                          disabled={isClearing === 'cache' || storageInfo.cache_size_bytes === 0}
+                          className="flex items-center gap-1.5 px-3 py-1.5 bg-red-500/20 text-red-400 hover:bg-red-500/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
+                        >
+                          <Trash2 size={12} />
+                          {isClearing === 'cache' ? t.settings.storage.clearing : t.settings.storage.clear}
+                        </button>
+                      </div>
                     </div>
                     <div className="flex items-center justify-between">
                       <div className="flex items-center gap-3">
@@ -605,14 +691,24 @@ export function SettingsDialog({ onClose }: SettingsDialogProps) {
                           <p className="text-xs text-text-secondary">{storageInfo.label_count} {t.settings.storage.labels}</p>
                         </div>
                       </div>
-                      <button
-                        onClick={() => setConfirmDialog('labels')}
-                        disabled={isClearing === 'labels' || storageInfo.label_count === 0}
-                        className="flex items-center gap-1.5 px-3 py-1.5 bg-red-500/20 text-red-400 hover:bg-red-500/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
-                      >
-                        <Trash2 size={12} />
-                        {isClearing === 'labels' ? t.settings.storage.clearing : t.settings.storage.clear}
-                      </button>
+                      <div className="flex items-center gap-2">
+                        <button
+                          onClick={handleOptimizeDatabase}
+                          disabled={isClearing !== null}
+                          className="flex items-center gap-1.5 px-3 py-1.5 bg-accent/20 text-accent hover:bg-accent/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
+                        >
+                          <Zap size={12} />
+                          {isClearing === 'optimize' ? t.settings.storage.optimizing : t.settings.storage.optimize}
+                        </button>
+                        <button
+                          onClick={() => setConfirmDialog('labels')}
+                          disabled={isClearing === 'labels' || storageInfo.label_count === 0}
+                          className="flex items-center gap-1.5 px-3 py-1.5 bg-red-500/20 text-red-400 hover:bg-red-500/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
+                        >
+                          <Trash2 size={12} />
+                          {isClearing === 'labels' ? t.settings.storage.clearing : t.settings.storage.clear}
+                        </button>
+                      </div>
                     </div>
                   </div>
                 ) : (
@@ -622,6 +718,34 @@ export function SettingsDialog({ onClose }: SettingsDialogProps) {
                 )}
               </div>
 
+              {/* Per-session breakdown */}
+              {storageInfo && storageInfo.sessions.length > 0 && (
+                <div className="p-4 bg-bg-tertiary rounded-xl border border-white/5">
+                  <span className="text-sm text-text-secondary">{t.settings.storage.bySession}</span>
+                  <div className="mt-3 max-h-60 overflow-y-auto divide-y divide-white/5">
+                    {storageInfo.sessions.map((session) => (
+                      <div key={session.session_id} className="flex items-center justify-between gap-3 py-2">
+                        <div className="min-w-0">
+                          <p className="text-sm truncate" title={session.folder_path}>{session.folder_path}</p>
+                          <p className="text-xs text-text-secondary">
+                            {formatBytes(session.thumbnail_bytes)} {t.settings.storage.thumbnails} · {formatBytes(session.preview_bytes)} {t.settings.storage.previews} · {session.label_count} {t.settings.storage.labels}
+                            {session.last_opened && ` · ${t.settings.storage.lastOpened} ${new Date(session.last_opened.replace(' ', 'T')).toLocaleDateString()}`}
+                          </p>
+                        </div>
+                        <button
+                          onClick={() => handleClearSessionCache(session.session_id)}
+                          disabled={clearingSession !== null || session.thumbnail_bytes + session.preview_bytes === 0}
+                          className="shrink-0 flex items-center gap-1.5 px-2 py-1 bg-red-500/20 text-red-400 hover:bg-red-500/30 disabled:opacity-50 disabled:cursor-not-allowed rounded-lg text-xs font-medium transition-colors"
+                        >
+                          <Trash2 size={12} />
+                          {clearingSession === session.session_id ? t.settings.storage.clearing : t.settings.storage.clear}
+                        </button>
+                      </div>
+                    ))}
+                  </div>
+                </div>
+              )}
+
               {/* Result message */}
               {clearResult && (
                 <div className={`flex items-center gap-2 p-3 rounded-xl ${
@@ -132,6 +132,18 @@ export const en: Translations = {
       cacheCleared: 'Cache cleared',
       labelsCleared: 'Cleared',
       clearFailed: 'Failed to clear',
+      dedup: 'Deduplicate',
+      deduplicating: 'Deduplicating...',
+      dedupDone: 'Space reclaimed',
+      dedupFailed: 'Failed to deduplicate',
+      optimize: 'Optimize',
+      optimizing: 'Optimizing...',
+      optimizeDone: 'Database compacted',
+      optimizeFailed: 'Failed to optimize database',
+      bySession: 'By Session',
+      thumbnails: 'thumbnails',
+      previews: 'previews',
+      lastOpened: 'opened',
     },
     about: {
       title: 'About',
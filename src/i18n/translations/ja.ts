@@ -132,6 +132,18 @@ export const ja: Translations = {
       cacheCleared: 'キャッシュをクリアしました',
       labelsCleared: 'クリアしました',
       clearFailed: 'クリアに失敗しました',
+      dedup: '重複を統合',
+      deduplicating: '統合中...',
+      dedupDone: '空き容量を確保しました',
+      dedupFailed: '重複の統合に失敗しました',
+      optimize: '最適化',
+      optimizing: '最適化中...',
+      optimizeDone: 'データベースを最適化しました',
+      optimizeFailed: 'データベースの最適化に失敗しました',
+      bySession: 'セッション別',
+      thumbnails: 'サムネイル',
+      previews: 'プレビュー',
+      lastOpened: '最終使用',
     },
     about: {
       title: '概要',
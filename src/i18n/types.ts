@@ -146,6 +146,18 @@ export interface Translations {
       cacheCleared: string;
       labelsCleared: string;
       clearFailed: string;
+      dedup: string;
+      deduplicating: string;
+      dedupDone: string;
+      dedupFailed: string;
+      optimize: string;
+      optimizing: string;
+      optimizeDone: string;
+      optimizeFailed: string;
+      bySession: string;
+      thumbnails: string;
+      previews: string;
+      lastOpened: string;
     };
     about: {
       title: string;
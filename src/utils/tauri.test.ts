@@ -8,6 +8,7 @@ describe('toImageItem', () => {
     path: '/photos/DSC_0001.NEF',
     size: 20 * 1024 * 1024,
     modified_at: '2024/12/15 14:32',
+    is_video: false,
   };
 
   const cacheDir = '/cache/session123/thumbnails';
@@ -42,6 +43,28 @@ describe('toImageItem', () => {
     expect(result.index).toBe(5);
   });
 
+  it('should encode folders of merged session keys into the thumbnail name', () => {
+    const mergedInfo = {
+      ...mockImageInfo,
+      filename: 'CARD2/DCIM/DSC_0001.NEF',
+      path: '/Volumes/CARD2/DCIM/DSC_0001.NEF',
+    };
+    const labels = new Map<string, LabelStatus>();
+    const result = toImageItem(mergedInfo, 0, labels, cacheDir);
+
+    expect(result.thumbnailPath).toBe(
+      '/cache/session123/thumbnails/CARD2%2FDCIM%2FDSC_0001.jpg'
+    );
+
+    const other = toImageItem(
+      { ...mergedInfo, filename: 'CARD2_DCIM/DSC_0001.NEF' },
+      1,
+      labels,
+      cacheDir
+    );
+    expect(other.thumbnailPath).not.toBe(result.thumbnailPath);
+  });
+
   it('should handle JPEG files correctly', () => {
     const jpegInfo = {
       ...mockImageInfo,
@@ -67,4 +90,12 @@ describe('toImageItem', () => {
 
     expect(result.thumbnailPath).toBe('/cache/session123/thumbnails/photo.backup.jpg');
   });
+
+  it('should use the cache extension of the configured format', () => {
+    const labels = new Map<string, LabelStatus>();
+
+    const result = toImageItem(mockImageInfo, 0, labels, cacheDir, 'avif');
+
+    expect(result.thumbnailPath).toMatch(/\.avif$/);
+  });
 });
@@ -8,11 +8,19 @@ export function toAssetUrl(filePath: string): string {
   return convertFileSrc(filePath);
 }
 
+// Error payload of a failed command
+export interface AppError {
+  code: string;
+  message: string;
+  path: string | null;
+}
+
 export interface ImageInfo {
   filename: string;
   path: string;
   size: number;
   modified_at: string;
+  is_video: boolean;
 }
 
 export interface Label {
@@ -26,13 +34,24 @@ export interface SubfolderInfo {
   image_count: number;
 }
 
+// Where the user left off in a session's grid
+export interface ViewState {
+  filter: string | null;
+  sort_order: string | null;
+  grid_zoom: number | null; // Grid thumbnail size in px
+}
+
 export interface OpenFolderResult {
   session_id: string;
   images: ImageInfo[];
   labels: Label[];
   last_selected_index: number;
   cache_dir: string;
+  cache_extension: string; // Extension of cached thumbnails ('jpg' or 'avif')
   subfolders: SubfolderInfo[];
+  view_state: ViewState;
+  task_id: number; // Thumbnail generation task
+  folders: string[]; // Source folders of the session
 }
 
 export interface ThumbnailProgress {
@@ -46,18 +65,41 @@ export interface ThumbnailResult {
   preview_path: string | null; // For RAW files, path to larger preview image
   success: boolean;
   error: string | null;
+  placeholder: boolean; // Online-only cloud file skipped instead of downloaded
+  corrupt: boolean; // File data is truncated or damaged
+  embedded_preview: boolean; // RAW data unreadable; showing the camera's embedded preview
+}
+
+// File that couldn't be decoded because its data is damaged
+export interface ProblemFile {
+  filename: string;
+  original_modified: string;
+  error: string;
 }
 
 export interface ExportResult {
   total: number;
   copied: number;
   skipped: number;
+  // Left out by exportNewOnly: exported to the destination before and unchanged since
+  unchanged: number;
   failed: number;
+  cancelled: boolean;
+}
+
+// Long-running background job (see list_tasks / pause_task / resume_task / cancel_task)
+export interface TaskInfo {
+  id: number;
+  kind: 'thumbnails' | 'prefetch' | 'export' | 'tether' | 'analysis' | 'exif';
+  status: 'running' | 'paused' | 'cancelled' | 'completed';
+  completed: number;
+  total: number;
 }
 
 export interface ExifInfo {
   camera_make: string | null;
   camera_model: string | null;
+  body_serial: string | null;
   lens_model: string | null;
   focal_length: string | null;
   aperture: string | null;
@@ -65,9 +107,24 @@ export interface ExifInfo {
   iso: string | null;
   exposure_compensation: string | null;
   date_taken: string | null;
+  offset_time: string | null;
+  // Fraction of a second of date_taken as recorded, e.g. "05"
+  sub_sec_time: string | null;
   width: number | null;
   height: number | null;
   orientation: number | null;
+  // GPS position in degrees; negative south and west
+  latitude?: number | null;
+  longitude?: number | null;
+  focus_areas: FocusArea[];
+}
+
+// Where the camera focused, normalized to 0-1 of the (unrotated) image
+export interface FocusArea {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
 }
 
 // Open folder selection dialog
@@ -85,6 +142,16 @@ export async function openFolder(folderPath: string): Promise<OpenFolderResult>
   return await invoke('open_folder', { folderPath });
 }
 
+// Open several folders (e.g. one per memory card) as one session
+export async function openFolders(folderPaths: string[]): Promise<OpenFolderResult> {
+  return await invoke('open_folders', { folderPaths });
+}
+
+// Save filter, sort order and grid zoom of the current session
+export async function saveViewState(viewState: ViewState): Promise<void> {
+  await invoke('save_view_state', { viewState });
+}
+
 // Set label
 export async function setLabel(
   filename: string,
@@ -96,18 +163,744 @@ export async function setLabel(
   });
 }
 
+// Compare set: pinned candidates kept across restarts for side-by-side view
+export async function getCompareSet(): Promise<string[]> {
+  return await invoke('get_compare_set');
+}
+
+// Returns the updated set
+export async function addToCompareSet(filenames: string[]): Promise<string[]> {
+  return await invoke('add_to_compare_set', { filenames });
+}
+
+// Returns the updated set
+export async function removeFromCompareSet(
+  filenames: string[]
+): Promise<string[]> {
+  return await invoke('remove_from_compare_set', { filenames });
+}
+
+export async function clearCompareSet(): Promise<void> {
+  await invoke('clear_compare_set');
+}
+
+// Database maintenance: VACUUM and ANALYZE, sizes in bytes
+export interface OptimizeStats {
+  before_bytes: number;
+  after_bytes: number;
+  bytes_reclaimed: number;
+}
+
+export async function optimizeDatabase(): Promise<OptimizeStats> {
+  return await invoke('optimize_database');
+}
+
+// Copies of one file in different folders, found across every session
+export interface DuplicateFile {
+  session_id: string;
+  path: string;
+}
+
+export interface DuplicateGroup {
+  fingerprint: string;
+  // Bytes per copy
+  size: number;
+  files: DuplicateFile[];
+}
+
+// Only files fingerprinted when their session was opened are known
+export async function findGlobalDuplicates(): Promise<DuplicateGroup[]> {
+  return await invoke('find_global_duplicates');
+}
+
+// Per-channel histogram; 'raw' reads a RAW file's linear sensor data before the tone curve
+export type HistogramSource = 'rendered' | 'raw';
+
+export interface Histogram {
+  source: HistogramSource;
+  // 256 bins per channel, darkest first
+  red: number[];
+  green: number[];
+  blue: number[];
+  // Shares (0.0 - 1.0) of clipped values per channel: red, green, blue
+  highlight_clipping: [number, number, number];
+  shadow_clipping: [number, number, number];
+}
+
+export async function getHistogram(
+  path: string,
+  source: HistogramSource = 'rendered'
+): Promise<Histogram> {
+  return await invoke('get_histogram', { path, source });
+}
+
+// Focus peaking overlay for the detail view: the path of a PNG the size of the preview,
+// transparent except for in-focus edges
+export async function getFocusPeaking(path: string): Promise<string> {
+  return await invoke('get_focus_peaking', { path });
+}
+
+// Exposure warning overlay: the path of a PNG the size of the preview, transparent except
+// for blown-out highlights (red) and crushed shadows (blue)
+export async function getClippingMask(path: string): Promise<string> {
+  return await invoke('get_clipping_mask', { path });
+}
+
+// Metadata corrections, written into exported JPEGs (EXIF) and RAW sidecars (XMP)
+export interface MetadataEdits {
+  artist?: string | null;
+  copyright?: string | null;
+  // "YYYY-MM-DD HH:MM:SS"
+  date_taken?: string | null;
+}
+
+// Fields left out are kept; empty strings drop their correction
+export async function editMetadata(
+  sessionId: string,
+  filenames: string[],
+  edits: MetadataEdits
+): Promise<void> {
+  return await invoke('edit_metadata', { sessionId, filenames, edits });
+}
+
+export async function getMetadataEdits(
+  sessionId: string
+): Promise<Record<string, MetadataEdits>> {
+  return await invoke('get_metadata_edits', { sessionId });
+}
+
+// Capture time corrections (a camera whose clock was off), applied wherever images are
+// ordered by capture time
+export async function shiftCaptureTimes(
+  sessionId: string,
+  filenames: string[],
+  seconds: number
+): Promise<void> {
+  return await invoke('shift_capture_times', { sessionId, filenames, seconds });
+}
+
+export async function resetCaptureTimes(sessionId: string, filenames: string[]): Promise<void> {
+  return await invoke('reset_capture_times', { sessionId, filenames });
+}
+
+export async function getCaptureTimeOffsets(sessionId: string): Promise<Record<string, number>> {
+  return await invoke('get_capture_time_offsets', { sessionId });
+}
+
+// GPS positions of a session's geotagged images, for the map view
+export interface ImageLocation {
+  filename: string;
+  latitude: number;
+  longitude: number;
+}
+
+export async function getSessionLocations(sessionId: string): Promise<ImageLocation[]> {
+  return await invoke('get_session_locations', { sessionId });
+}
+
+// Offline reverse geocoding of GPS positions; `region` needs a GeoNames file in the config
+export interface Place {
+  city: string;
+  region: string | null;
+  country: string;
+  country_code: string;
+  distance_km: number;
+  label: string;
+}
+
+// null when no known city is within 100 km
+export async function reverseGeocode(latitude: number, longitude: number): Promise<Place | null> {
+  return await invoke('reverse_geocode', { latitude, longitude });
+}
+
+// SFTP servers exports are uploaded to; passwords live in the OS keychain, not the config
+export interface SftpTarget {
+  name: string;
+  host: string;
+  port: number;
+  user: string;
+  remote_dir: string;
+  identity_file: string | null;
+}
+
+export async function listSftpTargets(): Promise<SftpTarget[]> {
+  return await invoke('list_sftp_targets');
+}
+
+// An empty password removes the stored one; leaving it out keeps it
+export async function saveSftpTarget(
+  target: SftpTarget,
+  password?: string
+): Promise<SftpTarget[]> {
+  return await invoke('save_sftp_target', { target, password });
+}
+
+export async function deleteSftpTarget(name: string): Promise<SftpTarget[]> {
+  return await invoke('delete_sftp_target', { name });
+}
+
+// Files already on the server count as unchanged; interrupted uploads are resumed
+export async function exportSftp(
+  target: string,
+  filter?: ExportFilter
+): Promise<ExportResult> {
+  return await invoke('export_sftp', { target, filter });
+}
+
+// Adopted files to one folder and rejected ones to a rejects folder in one export;
+// unlabeled files stay where they are
+export interface SplitExportResult {
+  adopted: ExportResult;
+  rejected: ExportResult;
+}
+
+export async function exportSplit(
+  adoptedDestination: string,
+  rejectedDestination: string,
+  mode: 'copy' | 'move' = 'copy',
+  options?: ExportFileOptions
+): Promise<SplitExportResult> {
+  return await invoke('export_split', {
+    adoptedDestination,
+    rejectedDestination,
+    mode,
+    options,
+  });
+}
+
+// Past exports of a session; rerunning one exports with the same settings again
+export interface ExportRecord {
+  id: number;
+  session_id: string;
+  preset: string | null;
+  settings: ExportSettings;
+  result: ExportResult;
+  exported_at: string;
+}
+
+export async function getExportHistory(sessionId: string): Promise<ExportRecord[]> {
+  return await invoke('get_export_history', { sessionId });
+}
+
+export async function rerunExport(
+  historyId: number,
+  exportNewOnly?: boolean
+): Promise<ExportResult> {
+  return await invoke('rerun_export', { historyId, exportNewOnly: exportNewOnly ?? null });
+}
+
+// Named export settings, run with one click on the open session
+export type ExportFilter = 'not_rejected' | 'adopted' | 'rejected' | 'all';
+
+export interface ExportSettings {
+  destination: string;
+  mode: 'copy' | 'move';
+  filter: ExportFilter;
+  // Template the exported files are named with (same tokens as renameFiles)
+  rename_template: string | null;
+  // Order {seq} numbers the files in; {seq:4:101} counts from 101 with 4 digits
+  seq_order?: 'capture_time' | 'name';
+  // Only frames taken with this camera or lens
+  gear?: GearFilter;
+  // Skip files an earlier export wrote to the destination unless they changed
+  new_only: boolean;
+  options: ExportFileOptions;
+}
+
+export interface ExportPreset extends ExportSettings {
+  name: string;
+}
+
+export async function listExportPresets(): Promise<ExportPreset[]> {
+  return await invoke('list_export_presets');
+}
+
+export async function saveExportPreset(preset: ExportPreset): Promise<ExportPreset[]> {
+  return await invoke('save_export_preset', { preset });
+}
+
+export async function deleteExportPreset(name: string): Promise<ExportPreset[]> {
+  return await invoke('delete_export_preset', { name });
+}
+
+export async function runExportPreset(name: string): Promise<ExportResult> {
+  return await invoke('run_export_preset', { name });
+}
+
+// Sessions grouped for one shoot (e.g. the three cards of a wedding)
+export interface Project {
+  id: number;
+  name: string;
+  created_at: string;
+  session_ids: string[];
+}
+
+export interface SessionLabelStats {
+  session_id: string;
+  folder_path: string;
+  total_files: number;
+  adopted: number;
+  rejected: number;
+  unlabeled: number;
+}
+
+export interface ProjectStats {
+  sessions: SessionLabelStats[];
+  total_files: number;
+  adopted: number;
+  rejected: number;
+  unlabeled: number;
+}
+
+export async function listProjects(): Promise<Project[]> {
+  return await invoke('list_projects');
+}
+
+export async function createProject(name: string, sessionIds: string[]): Promise<Project> {
+  return await invoke('create_project', { name, sessionIds });
+}
+
+export async function renameProject(projectId: number, name: string): Promise<Project> {
+  return await invoke('rename_project', { projectId, name });
+}
+
+export async function setProjectSessions(
+  projectId: number,
+  sessionIds: string[]
+): Promise<Project> {
+  return await invoke('set_project_sessions', { projectId, sessionIds });
+}
+
+export async function deleteProject(projectId: number): Promise<void> {
+  await invoke('delete_project', { projectId });
+}
+
+export async function getProjectStats(projectId: number): Promise<ProjectStats> {
+  return await invoke('get_project_stats', { projectId });
+}
+
+// Files not rejected in any session of the project, into one folder
+export async function exportProject(
+  projectId: number,
+  destinationFolder: string,
+  mode: 'copy' | 'move' = 'copy',
+  options?: ExportFileOptions,
+  exportNewOnly = false
+): Promise<ExportResult> {
+  return await invoke('export_project', {
+    projectId,
+    destinationFolder,
+    mode,
+    options,
+    exportNewOnly,
+  });
+}
+
+// Pinned folders for one-click opening; available is false while the drive is unplugged
+export interface Bookmark {
+  path: string;
+  name: string;
+  available: boolean;
+}
+
+export async function listBookmarks(): Promise<Bookmark[]> {
+  return await invoke('list_bookmarks');
+}
+
+export async function addBookmark(path: string, name?: string): Promise<Bookmark[]> {
+  return await invoke('add_bookmark', { path, name: name ?? null });
+}
+
+export async function renameBookmark(path: string, name: string): Promise<Bookmark[]> {
+  return await invoke('rename_bookmark', { path, name });
+}
+
+export async function removeBookmark(path: string): Promise<Bookmark[]> {
+  return await invoke('remove_bookmark', { path });
+}
+
+export async function reorderBookmarks(paths: string[]): Promise<Bookmark[]> {
+  return await invoke('reorder_bookmarks', { paths });
+}
+
+// Images found so far while open_folder(s) scans; reset starts the list over
+// The final, sorted list comes with the open_folder(s) result
+export interface ScanProgress {
+  images: ImageInfo[];
+  reset: boolean;
+}
+
+export async function onScanProgress(
+  callback: (progress: ScanProgress) => void
+): Promise<() => void> {
+  const unlisten = await listen<ScanProgress>('scan-progress', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Stage of an open_folder(s) call still in progress
+export type OpenStage = 'scanning' | 'saving' | 'labels' | 'cache';
+
+export async function onOpenProgress(
+  callback: (stage: OpenStage) => void
+): Promise<() => void> {
+  const unlisten = await listen<{ stage: OpenStage }>('open-progress', (event) => {
+    callback(event.payload.stage);
+  });
+  return unlisten;
+}
+
+// Detail view preview; for a RAW file not developed yet it is the camera's embedded JPEG
+// (pending), swapped for the developed preview when onPreviewReady reports it
+export interface PreviewResponse {
+  preview_path: string | null;
+  pending: boolean;
+}
+
+export interface PreviewReady {
+  filename: string;
+  preview_path: string;
+}
+
+export async function getPreview(filename: string): Promise<PreviewResponse> {
+  return await invoke('get_preview', { filename });
+}
+
+export async function onPreviewReady(
+  callback: (preview: PreviewReady) => void
+): Promise<() => void> {
+  const unlisten = await listen<PreviewReady>('preview-ready', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// White balance RAW previews are developed with
+export type WhiteBalance = 'as_shot' | 'auto' | 'daylight';
+
+// Null follows the global setting; returns how many cached previews are being redeveloped
+// (each reported through onPreviewReady)
+export async function setSessionWhiteBalance(mode: WhiteBalance | null): Promise<number> {
+  return await invoke('set_session_white_balance', { mode });
+}
+
+// Preview adjustments for RAW files; stored per image, the original is never changed
+export interface RawAdjustments {
+  // Stops
+  exposure: number;
+  // Kelvin and tint (1.0 neutral); null keeps the white balance as shot
+  wb_temp: number | null;
+  wb_tint: number | null;
+  // Null follows the session's white balance
+  white_balance?: WhiteBalance | null;
+}
+
+// Returns the path of the re-rendered preview
+export async function getAdjustedPreview(
+  path: string,
+  adjustments: RawAdjustments
+): Promise<string> {
+  return await invoke('get_adjusted_preview', {
+    path,
+    exposure: adjustments.exposure,
+    wbTemp: adjustments.wb_temp,
+    wbTint: adjustments.wb_tint,
+    whiteBalance: adjustments.white_balance ?? null,
+  });
+}
+
+export async function getImageAdjustments(filename: string): Promise<RawAdjustments> {
+  return await invoke('get_image_adjustments', { filename });
+}
+
+// Deleting a file of the open session; its caches and label go with it
+export interface FileDeleted {
+  session_id: string;
+  filename: string;
+  to_trash: boolean;
+}
+
+export async function deleteFile(filename: string, toTrash = true): Promise<void> {
+  await invoke('delete_file', { filename, toTrash });
+}
+
+export async function onFileDeleted(
+  callback: (deleted: FileDeleted) => void
+): Promise<() => void> {
+  const unlisten = await listen<FileDeleted>('file-deleted', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Moving files to another folder; labels and caches go to that folder's session
+export interface FilesMoved {
+  source_session_id: string;
+  destination_session_id: string;
+  // Keys in the source session
+  moved: string[];
+  failed: number;
+}
+
+export async function moveFiles(filenames: string[], destination: string): Promise<FilesMoved> {
+  return await invoke('move_files', { filenames, destination });
+}
+
+export async function onFilesMoved(
+  callback: (result: FilesMoved) => void
+): Promise<() => void> {
+  const unlisten = await listen<FilesMoved>('files-moved', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Batch rename on disk; labels and caches follow the files
+export interface Rename {
+  from: string;
+  to: string;
+}
+
+// Tokens: {date} {time} {seq} {seq:N} {camera} {name}; the extension is kept
+export async function renameFiles(sessionId: string, template: string): Promise<Rename[]> {
+  return await invoke('rename_files', { sessionId, template });
+}
+
+// Takes the last renameFiles batch back
+export async function undoRename(sessionId: string): Promise<Rename[]> {
+  return await invoke('undo_rename', { sessionId });
+}
+
+// Labels of files that look renamed (same size and modification time), to confirm
+export interface LabelTransfer {
+  from: string;
+  to: string;
+  label: LabelStatus;
+}
+
+export async function reconcileSession(sessionId: string): Promise<LabelTransfer[]> {
+  return await invoke('reconcile_session', { sessionId });
+}
+
+// Returns the session's labels after the transfers
+export async function transferLabels(
+  sessionId: string,
+  transfers: LabelTransfer[]
+): Promise<Label[]> {
+  return await invoke('transfer_labels', { sessionId, transfers });
+}
+
+// Database check: damage to the file, rows of deleted sessions, cache entries without a file
+export interface OrphanedRows {
+  table: string;
+  count: number;
+}
+
+export interface IntegrityReport {
+  integrity_errors: string[];
+  orphaned_rows: OrphanedRows[];
+  missing_cache_files: number;
+  repaired: boolean;
+}
+
+// With repair, orphaned rows and stale cache entries are deleted
+export async function checkDatabase(repair = false): Promise<IntegrityReport> {
+  return await invoke('check_database', { repair });
+}
+
+// Operations (export-move, delete) a crash interrupted, to report on startup
+export interface InterruptedOperation {
+  id: number;
+  session_id: string;
+  kind: 'export_move' | 'delete';
+  destination: string;
+  started_at: string;
+  // Files not started, written but original kept, and finished
+  pending: number;
+  copied: number;
+  done: number;
+}
+
+export interface ResolveOutcome {
+  completed: number;
+  failed: number;
+}
+
+export async function getInterruptedOperations(): Promise<InterruptedOperation[]> {
+  return await invoke('get_interrupted_operations');
+}
+
+// 'rollback' puts files back where they were; 'dismiss' only forgets the operation
+export async function resolveOperation(
+  operationId: number,
+  resolution: 'resume' | 'rollback' | 'dismiss'
+): Promise<ResolveOutcome> {
+  return await invoke('resolve_operation', { operationId, resolution });
+}
+
+// Benchmark: thumbnail throughput at several thread counts on sample files
+export interface ThreadBenchmark {
+  threads: number;
+  jpeg_per_second: number | null;
+  raw_per_second: number | null;
+}
+
+export interface BenchmarkReport {
+  results: ThreadBenchmark[];
+  recommended_threads: number;
+  // null without RAW samples
+  recommended_raw_threads: number | null;
+}
+
+export async function runBenchmark(
+  samplePaths: string[]
+): Promise<BenchmarkReport> {
+  return await invoke('run_benchmark', { samplePaths });
+}
+
+// Power mode: low power uses fewer thumbnail threads and pauses analysis
+// 'auto' switches to low power while on battery
+export type PowerMode = 'auto' | 'saver' | 'performance';
+
+export interface PowerStatus {
+  mode: PowerMode;
+  // null when the machine doesn't report a power source
+  on_battery: boolean | null;
+  low_power: boolean;
+}
+
+export async function getPowerStatus(): Promise<PowerStatus> {
+  return await invoke('get_power_status');
+}
+
+export async function setPowerMode(mode: PowerMode): Promise<PowerStatus> {
+  return await invoke('set_power_mode', { mode });
+}
+
+export async function onPowerModeChanged(
+  callback: (status: PowerStatus) => void
+): Promise<() => void> {
+  const unlisten = await listen<PowerStatus>('power-mode-changed', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Culling passes: named label sets of a session (first pass, client review, ...)
+// The active pass's labels are the ones getLabels/setLabel work on
+export interface CullingPass {
+  name: string;
+  created_at: string;
+  active: boolean;
+  label_count: number;
+}
+
+export interface PassDifference {
+  filename: string;
+  a: string | null;
+  b: string | null;
+}
+
+export async function listPasses(): Promise<CullingPass[]> {
+  return await invoke('list_passes');
+}
+
+export async function createPass(
+  name: string,
+  copyLabels = false
+): Promise<void> {
+  await invoke('create_pass', { name, copyLabels });
+}
+
+// Returns the labels of the pass that became active
+export async function switchPass(name: string): Promise<Label[]> {
+  return await invoke('switch_pass', { name });
+}
+
+export async function comparePasses(
+  a: string,
+  b: string
+): Promise<PassDifference[]> {
+  return await invoke('compare_passes', { a, b });
+}
+
 // Save selection position
 export async function saveSelection(index: number): Promise<void> {
   await invoke('save_selection', { index });
 }
 
 // Export
+// How exported files are written (omitted fields use the backend defaults)
+export interface ExportFileOptions {
+  // Rotate JPEGs upright per EXIF orientation; false copies files byte for byte
+  auto_rotate?: boolean;
+  // Remove GPS coordinates, serial numbers and owner name from exported JPEGs
+  strip_private_metadata?: boolean;
+  // Downscale JPEGs with a longer edge than this; other formats are copied at full size
+  max_size?: number | null;
+  // Recreate the session's subfolders under the destination instead of flattening them
+  keep_folders?: boolean;
+  // Write an .xmp next to each exported RAW file so the editor opens it rated
+  xmp_sidecar?: XmpSidecar | null;
+  // Write stills (RAW included) as small sRGB JPEGs without metadata; copy only
+  small_jpeg?: SmallJpeg | null;
+  // Stamped on exported JPEGs, which are turned upright for it
+  watermark?: Watermark | null;
+}
+
+// Adopted files get `rating` (1-5) and `color_label`, rejected ones the reject flag (-1)
+export interface XmpSidecar {
+  rating: number;
+  color_label?: string | null;
+  keywords?: string[];
+}
+
+export type WatermarkMark =
+  | { kind: "image"; path: string }
+  // `font` is a TrueType file; a system sans-serif when omitted. `color` is [r, g, b]
+  | { kind: "text"; text: string; font?: string | null; color?: [number, number, number] };
+
+export type WatermarkPosition =
+  | "top_left"
+  | "top"
+  | "top_right"
+  | "left"
+  | "center"
+  | "right"
+  | "bottom_left"
+  | "bottom"
+  | "bottom_right";
+
+// `opacity` 0-1 (default 0.5); `scale` is the mark's width relative to the photo (default 0.25)
+export interface Watermark {
+  mark: WatermarkMark;
+  position?: WatermarkPosition;
+  opacity?: number;
+  scale?: number;
+}
+
+export interface SmallJpeg {
+  long_edge: number;
+  quality: number;
+}
+
 export async function exportAdopted(
   sourceFolder: string,
   destinationFolder: string,
-  mode: 'copy' | 'move' = 'copy'
+  mode: 'copy' | 'move' = 'copy',
+  options?: ExportFileOptions,
+  exportNewOnly = false,
+  gear?: GearFilter
 ): Promise<ExportResult> {
-  return await invoke('export_adopted', { sourceFolder, destinationFolder, mode });
+  return await invoke('export_adopted', {
+    sourceFolder,
+    destinationFolder,
+    mode,
+    options,
+    exportNewOnly,
+    gear,
+  });
 }
 
 // Select export destination folder
@@ -130,6 +923,55 @@ export async function clearCache(): Promise<void> {
   await invoke('clear_cache');
 }
 
+// Remove the cached thumbnails/previews of some files (e.g. replaced outside the app)
+export async function clearCacheForFiles(sessionId: string, filenames: string[]): Promise<number> {
+  return await invoke('clear_cache_for_files', { sessionId, filenames });
+}
+
+// Files of a session that failed to decode (e.g. frames from a damaged card)
+export async function getProblemFiles(sessionId: string): Promise<ProblemFile[]> {
+  return await invoke('get_problem_files', { sessionId });
+}
+
+// Image that got no thumbnail, and why
+export interface ThumbnailFailure {
+  filename: string;
+  kind: 'corrupt' | 'placeholder' | 'error';
+  error: string;
+  failed_at: string;
+}
+
+export interface GenerationReport {
+  failures: ThumbnailFailure[];
+  corrupt: number;
+  placeholders: number;
+}
+
+// Thumbnail failures of a session (kept across restarts until the file succeeds)
+export async function getGenerationReport(sessionId: string): Promise<GenerationReport> {
+  return await invoke('get_generation_report', { sessionId });
+}
+
+// Session created or updated from a .glimpse bundle
+export interface ImportedSession {
+  session_id: string;
+  folders: string[];
+  labels: number;
+}
+
+// Save a session's labels and view state as a portable .glimpse file
+export async function exportSessionBundle(sessionId: string, path: string): Promise<void> {
+  return await invoke('export_session_bundle', { sessionId, path });
+}
+
+// Import a .glimpse file; folderPaths points it at the folders on this machine
+export async function importSessionBundle(
+  path: string,
+  folderPaths?: string[]
+): Promise<ImportedSession> {
+  return await invoke('import_session_bundle', { path, folderPaths });
+}
+
 // Listen for thumbnail progress events
 export async function onThumbnailProgress(
   callback: (progress: ThumbnailProgress) => void
@@ -140,6 +982,23 @@ export async function onThumbnailProgress(
   return unlisten;
 }
 
+// Low-resolution stand-in (the EXIF thumbnail) of an image whose thumbnail isn't generated yet
+export interface InstantThumbnail {
+  filename: string;
+  thumbnail_path: string;
+}
+
+// Listen for instant thumbnails extracted right after a folder opens
+// A batch can arrive after an image's real thumbnail; keep the real one then
+export async function onInstantThumbnails(
+  callback: (thumbnails: InstantThumbnail[]) => void
+): Promise<() => void> {
+  const unlisten = await listen<InstantThumbnail[]>('instant-thumbnails', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
 // Listen for thumbnail generation complete events
 export async function onThumbnailsComplete(
   callback: (results: ThumbnailResult[]) => void
@@ -150,14 +1009,276 @@ export async function onThumbnailsComplete(
   return unlisten;
 }
 
+// Listen for progress of the background EXIF read after opening a folder
+export async function onExifProgress(
+  callback: (progress: ThumbnailProgress) => void
+): Promise<() => void> {
+  const unlisten = await listen<ThumbnailProgress>('exif-progress', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// EXIF of the session is cached; capture-time sorting no longer waits on file reads
+export interface ExifComplete {
+  session_id: string;
+  read: number;
+}
+
+// Listen for the background EXIF read finishing
+export async function onExifComplete(
+  callback: (complete: ExifComplete) => void
+): Promise<() => void> {
+  const unlisten = await listen<ExifComplete>('exif-complete', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Startup cleanup of caches of sessions not opened for a while
+export interface CacheExpired {
+  sessions: number;
+  reclaimed_bytes: number;
+  max_age_days: number;
+}
+
+// Listen for the startup cache expiry
+export async function onCacheExpired(
+  callback: (expired: CacheExpired) => void
+): Promise<() => void> {
+  const unlisten = await listen<CacheExpired>('cache-expired', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Folders of the open session whose drive was disconnected
+export interface SourceUnavailable {
+  session_id: string;
+  missing: string[];
+}
+
+// Listen for the session's drive being disconnected (running jobs are paused)
+export async function onSourceUnavailable(
+  callback: (event: SourceUnavailable) => void
+): Promise<() => void> {
+  const unlisten = await listen<SourceUnavailable>('source-unavailable', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Listen for the session's drive coming back (paused jobs are resumed)
+export async function onSourceAvailable(
+  callback: (sessionId: string) => void
+): Promise<() => void> {
+  const unlisten = await listen<{ session_id: string }>('source-available', (event) => {
+    callback(event.payload.session_id);
+  });
+  return unlisten;
+}
+
+// New file picked up in tether mode
+export interface TetherArrival {
+  image: ImageInfo;
+  thumbnail: ThumbnailResult;
+  select: boolean; // Newest frame of the batch (auto-select)
+}
+
+// Start watching the current session's folders for new files; returns the task ID
+export async function startTether(autoSelect = true): Promise<number> {
+  return await invoke('start_tether', { autoSelect });
+}
+
+// Stop tether mode
+export async function stopTether(): Promise<void> {
+  await invoke('stop_tether');
+}
+
+// Listen for files arriving in tether mode
+export async function onTetherArrival(
+  callback: (arrival: TetherArrival) => void
+): Promise<() => void> {
+  const unlisten = await listen<TetherArrival>('tether-arrival', (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+}
+
+// Why a frame is proposed as a reject (score 0.0 - 1.0)
+export type RejectReason =
+  | { kind: 'blurry' | 'subject_soft' | 'overexposed' | 'underexposed'; score: number }
+  // `of` is the near duplicate the frame was compared with
+  | { kind: 'duplicate' | 'face_lost' | 'eyes_closed'; score: number; of: string };
+
+export interface RejectionSuggestion {
+  filename: string;
+  score: number;
+  reasons: RejectReason[];
+}
+
+// Propose rejects for a session; nothing is labeled until the user confirms
+export async function suggestRejections(
+  sessionId: string,
+  threshold?: number
+): Promise<RejectionSuggestion[]> {
+  return await invoke('suggest_rejections', { sessionId, threshold });
+}
+
+// Face bounding box, normalized to 0.0 - 1.0 of the image size
+export interface FaceBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  score: number;
+}
+
+export interface ImageFaces {
+  filename: string;
+  face_count: number;
+  faces: FaceBox[];
+}
+
+export async function getFaces(sessionId: string): Promise<ImageFaces[]> {
+  return await invoke('get_faces', { sessionId });
+}
+
+// Frames shot in one continuous burst (id = first frame's filename)
+export interface BurstGroup {
+  id: string;
+  filenames: string[];
+}
+
+export interface FrameScore {
+  filename: string;
+  sharpness_score: number;
+  exposure_score: number;
+  score: number;
+}
+
+export interface BurstRanking {
+  group_id: string;
+  recommended: string;
+  frames: FrameScore[]; // Best frame first
+}
+
+export async function detectBursts(maxGapMs?: number): Promise<BurstGroup[]> {
+  return await invoke('detect_bursts', { maxGapMs });
+}
+
+// Score the frames of a burst and recommend the keeper
+export async function rankBurst(groupId: string, maxGapMs?: number): Promise<BurstRanking> {
+  return await invoke('rank_burst', { groupId, maxGapMs });
+}
+
+// Exposure bracket (e.g. for HDR) in the current session (id = first frame's filename)
+export interface BracketSet {
+  id: string;
+  filenames: string[];
+  exposure_bias: number[]; // EV of each frame, in the order of filenames
+}
+
+export async function detectBrackets(maxGapMs?: number): Promise<BracketSet[]> {
+  return await invoke('detect_brackets', { maxGapMs });
+}
+
+// Label every frame of a bracket set at once (null adopts); returns the labeled filenames
+export async function labelBracketSet(
+  setId: string,
+  label: LabelStatus,
+  maxGapMs?: number,
+): Promise<string[]> {
+  return await invoke('label_bracket_set', { setId, label, maxGapMs });
+}
+
+// Visually similar frames from anywhere in the session (id = first frame's filename)
+export interface SimilarCluster {
+  id: string;
+  filenames: string[];
+}
+
+export async function clusterSimilar(
+  sessionId: string,
+  maxDistance?: number,
+): Promise<SimilarCluster[]> {
+  return await invoke('cluster_similar', { sessionId, maxDistance });
+}
+
+// A frame similar to the one searched from; distance is in hash bits (0 = identical)
+export interface SimilarFrame {
+  filename: string;
+  distance: number;
+}
+
+// Frames of the current session most similar to filename, closest first
+export async function findSimilar(
+  filename: string,
+  maxDistance?: number,
+): Promise<SimilarFrame[]> {
+  return await invoke('find_similar', { filename, maxDistance });
+}
+
+// How many frames used one body / lens / focal length / ISO
+export interface UsageCount {
+  value: string;
+  count: number;
+}
+
+export interface GearStats {
+  image_count: number;
+  bodies: UsageCount[];
+  body_serials: UsageCount[];
+  lenses: UsageCount[];
+  focal_lengths: UsageCount[];
+  isos: UsageCount[];
+}
+
+// Frames of one body (a value of GearStats.bodies), serial or lens; empty fields match all
+export interface GearFilter {
+  camera?: string | null;
+  serial?: string | null;
+  lens?: string | null;
+}
+
+export async function getGearStats(sessionId: string): Promise<GearStats> {
+  return await invoke('get_gear_stats', { sessionId });
+}
+
+// Size, format and capture-date overview of a session
+export interface FolderStats {
+  file_count: number;
+  total_bytes: number;
+  average_bytes: number;
+  raw_count: number;
+  jpeg_count: number;
+  video_count: number;
+  other_count: number;
+  // "YYYY-MM-DD HH:MM:SS", from EXIF or the modification time
+  first_capture: string | null;
+  last_capture: string | null;
+}
+
+export async function getFolderStats(sessionId: string): Promise<FolderStats> {
+  return await invoke('get_folder_stats', { sessionId });
+}
+
 // Convert image info to ImageItem
 export function toImageItem(
   info: ImageInfo,
   index: number,
   labels: Map<string, LabelStatus>,
-  cacheDir: string
+  cacheDir: string,
+  cacheExtension = 'jpg'
 ): ImageItem {
-  const thumbnailFilename = info.filename.replace(/\.[^.]+$/, '.jpg');
+  // Keys of merged sessions contain folders, which the cache percent-encodes into the name
+  // (see cache_stem in image_processor.rs)
+  const thumbnailStem = info.filename
+    .replace(/\.[^./]+$/, '')
+    .replace(/%/g, '%25')
+    .replace(/\//g, '%2F')
+    .replace(/:/g, '%3A');
+  const thumbnailFilename = thumbnailStem + '.' + cacheExtension;
   const thumbnailPath = `${cacheDir}/${thumbnailFilename}`;
 
   return {